@@ -3,6 +3,7 @@
 //! Tests Rust's capacity to handle complex object creation at scale,
 //! simulating thousands of HTTP requests per second.
 
+use bumpalo::Bump;
 use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
 
 // ============================================================================
@@ -451,6 +452,212 @@ fn bench_routing(n: i64) -> i64 {
     matches
 }
 
+// ============================================================================
+// Arena-Allocated Variant
+// ============================================================================
+//
+// The same request pipeline with every per-request object and string placed
+// in a bump arena that is reset after each request, so nothing is freed
+// individually - the allocation pattern of TML's region-based memory
+// management. The arena is reused across requests, as a server would keep
+// one per worker.
+
+pub struct ArenaHttpRequest<'a> {
+    pub method: HttpMethod,
+    pub path: &'a str,
+    pub host: &'a str,
+    pub content_type: &'a str,
+    pub content_length: i64,
+    pub user_agent: &'a str,
+    pub accept: &'a str,
+    pub connection: &'a str,
+    pub body: &'a str,
+    pub request_id: i64,
+}
+
+impl<'a> ArenaHttpRequest<'a> {
+    pub fn with_body(
+        arena: &'a Bump,
+        method: HttpMethod,
+        path: &str,
+        body: &str,
+        request_id: i64,
+    ) -> &'a Self {
+        arena.alloc(Self {
+            method,
+            path: arena.alloc_str(path),
+            host: arena.alloc_str("localhost:8080"),
+            content_type: arena.alloc_str("application/json"),
+            content_length: body.len() as i64,
+            user_agent: arena.alloc_str("Rust-Benchmark/1.0"),
+            accept: arena.alloc_str("application/json"),
+            connection: arena.alloc_str("keep-alive"),
+            body: arena.alloc_str(body),
+            request_id,
+        })
+    }
+
+    pub fn new(arena: &'a Bump, method: HttpMethod, path: &str, request_id: i64) -> &'a Self {
+        Self::with_body(arena, method, path, "", request_id)
+    }
+}
+
+pub struct ArenaHttpResponse<'a> {
+    pub status_code: i32,
+    pub status_text: &'a str,
+    pub content_type: &'a str,
+    pub content_length: i64,
+    pub server: &'a str,
+    pub connection: &'a str,
+    pub body: &'a str,
+    pub request_id: i64,
+}
+
+impl<'a> ArenaHttpResponse<'a> {
+    fn build(
+        arena: &'a Bump,
+        status_code: i32,
+        status_text: &str,
+        connection: &str,
+        body: &str,
+        request_id: i64,
+    ) -> &'a Self {
+        arena.alloc(Self {
+            status_code,
+            status_text: arena.alloc_str(status_text),
+            content_type: arena.alloc_str("application/json"),
+            content_length: body.len() as i64,
+            server: arena.alloc_str("Rust-Server/1.0"),
+            connection: arena.alloc_str(connection),
+            body: arena.alloc_str(body),
+            request_id,
+        })
+    }
+
+    pub fn ok(arena: &'a Bump, body: &str, request_id: i64) -> &'a Self {
+        Self::build(arena, 200, "OK", "keep-alive", body, request_id)
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status_code >= 200 && self.status_code < 300
+    }
+}
+
+pub struct ArenaRequestContext<'a> {
+    pub request: &'a ArenaHttpRequest<'a>,
+    pub response_sent: bool,
+    pub processing_time: i64,
+}
+
+pub struct ArenaHttpHeader<'a> {
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+impl<'a> ArenaHttpHeader<'a> {
+    pub fn new(arena: &'a Bump, name: &str, value: &str) -> &'a Self {
+        arena.alloc(Self { name: arena.alloc_str(name), value: arena.alloc_str(value) })
+    }
+}
+
+fn handle_request_in<'a>(
+    arena: &'a Bump,
+    req: &ArenaHttpRequest,
+    stats: &mut ServerStats,
+) -> &'a ArenaHttpResponse<'a> {
+    stats.total_requests += 1;
+    stats.total_bytes_in += req.content_length;
+    if req.method == HttpMethod::Get {
+        stats.get_requests += 1;
+    }
+    if req.method == HttpMethod::Post {
+        stats.post_requests += 1;
+    }
+
+    let req_id = req.request_id;
+    let response = match req_id % 10 {
+        0 => ArenaHttpResponse::ok(arena, r#"{"status": "healthy"}"#, req_id),
+        1 => {
+            if req.method == HttpMethod::Get {
+                ArenaHttpResponse::ok(arena, r#"{"users": [{"id": 1, "name": "Alice"}]}"#, req_id)
+            } else if req.method == HttpMethod::Post {
+                ArenaHttpResponse::build(arena, 201, "Created", "keep-alive", r#"{"id": 2, "name": "Bob"}"#, req_id)
+            } else {
+                let body = bumpalo::format!(in arena, r#"{{"error": "{}"}}"#, "Method not allowed");
+                ArenaHttpResponse::build(arena, 400, "Bad Request", "close", &body, req_id)
+            }
+        }
+        2 => ArenaHttpResponse::ok(arena, r#"{"products": [{"id": 1, "price": 99.99}]}"#, req_id),
+        3 => ArenaHttpResponse::ok(arena, r#"{"orders": []}"#, req_id),
+        4 => ArenaHttpResponse::build(arena, 404, "Not Found", "close", r#"{"error": "Not Found"}"#, req_id),
+        5 => ArenaHttpResponse::build(
+            arena,
+            500,
+            "Internal Server Error",
+            "close",
+            r#"{"error": "Internal Server Error"}"#,
+            req_id,
+        ),
+        _ => ArenaHttpResponse::ok(arena, r#"{"message": "OK"}"#, req_id),
+    };
+
+    stats.total_bytes_out += response.content_length;
+    if response.is_success() {
+        stats.successful_responses += 1;
+    } else {
+        stats.error_responses += 1;
+    }
+    response
+}
+
+fn bench_with_context_arena(n: i64) -> i64 {
+    let mut arena = Bump::new();
+    let mut stats = ServerStats::new();
+    let mut completed = 0i64;
+
+    for i in 0..n {
+        {
+            let req = ArenaHttpRequest::new(&arena, HttpMethod::Get, "/api/benchmark", i);
+            let ctx = arena.alloc(ArenaRequestContext { request: req, response_sent: false, processing_time: 0 });
+
+            let resp = handle_request_in(&arena, ctx.request, &mut stats);
+            ctx.response_sent = true;
+            ctx.processing_time = 100;
+
+            if ctx.response_sent && resp.is_success() {
+                completed += 1;
+            }
+        }
+        arena.reset();
+    }
+
+    completed
+}
+
+fn bench_object_creation_arena(n: i64) -> i64 {
+    let mut arena = Bump::new();
+    let mut count = 0i64;
+
+    for i in 0..n {
+        {
+            let req = ArenaHttpRequest::with_body(&arena, HttpMethod::Post, "/api/test", r#"{"key": "value"}"#, i);
+            let resp = ArenaHttpResponse::ok(&arena, r#"{"result": "success", "id": 12345}"#, i);
+            let ctx = arena.alloc(ArenaRequestContext { request: req, response_sent: false, processing_time: 0 });
+
+            let _h1 = ArenaHttpHeader::new(&arena, "Content-Type", "application/json");
+            let _h2 = ArenaHttpHeader::new(&arena, "Authorization", "Bearer token123");
+            let _h3 = ArenaHttpHeader::new(&arena, "X-Request-ID", "req-12345");
+
+            if resp.is_success() && !ctx.response_sent {
+                count += 1;
+            }
+        }
+        arena.reset();
+    }
+
+    count
+}
+
 // ============================================================================
 // Criterion Benchmarks
 // ============================================================================
@@ -486,12 +693,24 @@ fn criterion_benchmark(c: &mut Criterion) {
             |b, &n| b.iter(|| bench_with_context(black_box(n))),
         );
 
+        group.bench_with_input(
+            BenchmarkId::new("with_context_arena", size),
+            size,
+            |b, &n| b.iter(|| bench_with_context_arena(black_box(n))),
+        );
+
         group.bench_with_input(
             BenchmarkId::new("object_creation", size),
             size,
             |b, &n| b.iter(|| bench_object_creation(black_box(n))),
         );
 
+        group.bench_with_input(
+            BenchmarkId::new("object_creation_arena", size),
+            size,
+            |b, &n| b.iter(|| bench_object_creation_arena(black_box(n))),
+        );
+
         group.bench_with_input(
             BenchmarkId::new("routing", size),
             size,
@@ -1,7 +1,17 @@
 use std::time::Instant;
 
-#[tokio::main]
-async fn main() {
+use tml_benchmarks::env::{apply_run_settings, Environment, RunSettings};
+use tml_benchmarks::histogram::Histogram;
+use tml_benchmarks::report::{BenchResult, Report};
+
+// Run settings go first so the runtime's worker threads inherit them
+fn main() {
+    let settings = apply_run_settings();
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    runtime.block_on(bench(settings));
+}
+
+async fn bench(settings: RunSettings) {
     println!("\n================================================================");
     println!("  Rust TCP Benchmarks: Async (tokio::TcpListener)");
     println!("================================================================\n");
@@ -14,9 +24,13 @@ async fn main() {
 
     let start = Instant::now();
     let mut success = 0;
+    let mut latencies = Histogram::new();
 
     for _ in 0..n {
-        if let Ok(_listener) = tokio::net::TcpListener::bind(addr).await {
+        let op = Instant::now();
+        let bound = tokio::net::TcpListener::bind(addr).await.is_ok();
+        latencies.record(op.elapsed());
+        if bound {
             success += 1;
         }
     }
@@ -28,7 +42,15 @@ async fn main() {
     println!("    Total time: {} ms", ns_elapsed / 1_000_000);
     println!("    Per op:     {} ns", if ns_elapsed > 0 { ns_elapsed / n } else { 0 });
     println!("    Ops/sec:    {}", if ns_elapsed > 0 { (n as i128 * 1_000_000_000) / ns_elapsed as i128 } else { 0 });
+    print!("{}", latencies.summary());
     println!("    Successful: {}/{}\n", success, n);
 
     println!("================================================================\n");
+
+    let mut result = BenchResult::new("TCP bind (tokio)", n as u64, ns_elapsed as u64);
+    result.notes = Some(format!("{}/{} successful", success, n));
+    result.latency = Some(latencies);
+    let mut report = Report::new("TCP", Environment::capture(&settings));
+    report.results.push(result);
+    report.save_if_requested();
 }
@@ -0,0 +1,185 @@
+// Big Integer and Decimal Benchmarks - Rust
+//
+// Targets for TML's planned arbitrary-precision types and an end-to-end
+// workload for the bridge's i128 lowering:
+//
+//   i128        multiply-accumulate, division by a runtime divisor, u128
+//               modular exponentiation and overflow-checked arithmetic
+//   BigUint     parse, add, mul and to-string of 100 to 2000 digit numbers
+//   Decimal     parse, add, mul and to-string of fixed-point decimals
+//
+// BigUint is num-bigint's `BigUint` and Decimal is rust_decimal's `Decimal`
+// (96-bit mantissa, scale of at most 28).
+//
+// Run with: cargo run --release --bin bignum_bench [iterations]
+
+use std::fmt::Write;
+use std::hint::black_box;
+use std::str::FromStr;
+use std::time::Instant;
+
+use num_bigint::BigUint;
+use rust_decimal::Decimal;
+use tml_benchmarks::env::{apply_run_settings, Environment};
+use tml_benchmarks::report::{BenchResult, Report};
+use tml_benchmarks::rng::{self, RngCore, Sample};
+
+// ============================================================================
+// Benchmarks
+// ============================================================================
+
+fn run(report: &mut Report, name: &str, iterations: u64, f: impl FnOnce() -> u64) {
+    let start = Instant::now();
+    black_box(f());
+    let ns = start.elapsed().as_nanos() as u64;
+    let r = BenchResult::new(name, iterations, ns);
+    println!("  {:<40} {:>10.2} ns/op {:>14} ops/sec", name, ns as f64 / iterations as f64, r.ops_per_sec());
+    report.results.push(r);
+}
+
+fn mod_pow(mut base: u128, mut exp: u128, modulus: u128) -> u128 {
+    let mut result = 1u128;
+    base %= modulus;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % modulus;
+        }
+        base = base * base % modulus;
+        exp >>= 1;
+    }
+    result
+}
+
+fn check() {
+    let factorial = (2..=30u32).fold(BigUint::from(1u32), |acc, i| acc * i);
+    assert_eq!(factorial.to_string(), "265252859812191058636308480000000");
+    assert_eq!(BigUint::from_str("000123").unwrap().to_string(), "123");
+
+    let d = |s: &str| Decimal::from_str(s).unwrap();
+    assert_eq!(d("1.10").checked_add(d("-0.005")).unwrap().to_string(), "1.095");
+    assert_eq!(d("-0.5").checked_mul(d("0.25")).unwrap().to_string(), "-0.125");
+    assert_eq!(d("2.50"), d("2.5"));
+
+    // 2^61 - 1 is prime: a^(p-1) = 1 (mod p)
+    let p = (1u128 << 61) - 1;
+    assert_eq!(mod_pow(3, p - 1, p), 1);
+}
+
+fn main() {
+    check();
+    let settings = apply_run_settings();
+    let n: u64 = std::env::args().nth(1).and_then(|a| a.parse().ok()).unwrap_or(10_000_000);
+    let mut report = Report::new("BigNum", Environment::capture(&settings));
+    let mut rng = rng::seeded(2754);
+
+    println!("\n================================================================");
+    println!("  Rust Big Integer and Decimal Benchmarks (num-bigint, rust_decimal)");
+    println!("================================================================\n");
+
+    println!("=== i128 ===");
+    let values: Vec<i128> = (0..4096).map(|_| (rng.next_u64() as i128) << 20 | rng.below(1 << 20) as i128).collect();
+    let divisors: Vec<i128> = (0..4096).map(|_| rng.range(1, 1 << 40) as i128).collect();
+    run(&mut report, "i128 multiply-accumulate", n, || {
+        let mut acc: i128 = 0;
+        for i in 0..n as usize {
+            acc = acc.wrapping_add(values[i & 4095].wrapping_mul(values[(i + 1) & 4095]));
+        }
+        acc as u64
+    });
+    run(&mut report, "i128 div/rem by runtime divisor", n, || {
+        let mut acc: i128 = 0;
+        for i in 0..n as usize {
+            let (v, d) = (values[i & 4095], divisors[i & 4095]);
+            acc ^= v / d + v % d;
+        }
+        acc as u64
+    });
+    run(&mut report, "u128 mod_pow (mod 2^61-1)", n / 100, || {
+        let p = (1u128 << 61) - 1;
+        (0..n / 100).fold(0, |acc, i| acc ^ mod_pow(values[i as usize & 4095] as u128, p - 2, p) as u64)
+    });
+    run(&mut report, "i128 checked_mul (overflow detect)", n, || {
+        let mut overflows = 0u64;
+        for i in 0..n as usize {
+            if values[i & 4095].checked_mul(values[(i * 7) & 4095] >> 40).is_none() {
+                overflows += 1;
+            }
+        }
+        overflows
+    });
+
+    for digits in [100usize, 500, 2000] {
+        println!("\n=== BigUint ({} digits) ===", digits);
+        let strings: Vec<String> = (0..64)
+            .map(|_| {
+                let mut s = String::with_capacity(digits);
+                s.push((b'1' + rng.below(9) as u8) as char);
+                for _ in 1..digits {
+                    s.push((b'0' + rng.below(10) as u8) as char);
+                }
+                s
+            })
+            .collect();
+        let nums: Vec<BigUint> = strings.iter().map(|s| BigUint::from_str(s).unwrap()).collect();
+        let iters = (n / digits as u64 / 10).max(64);
+        run(&mut report, &format!("BigUint parse {}d", digits), iters, || {
+            (0..iters as usize).map(|i| BigUint::from_str(&strings[i & 63]).unwrap().bits()).sum()
+        });
+        run(&mut report, &format!("BigUint add {}d", digits), iters, || {
+            (0..iters as usize).map(|i| (&nums[i & 63] + &nums[(i + 1) & 63]).bits()).sum()
+        });
+        let mul_iters = (iters * 100 / digits as u64).max(16);
+        run(&mut report, &format!("BigUint mul {}d", digits), mul_iters, || {
+            (0..mul_iters as usize).map(|i| (&nums[i & 63] * &nums[(i + 1) & 63]).bits()).sum()
+        });
+        run(&mut report, &format!("BigUint to_string {}d", digits), mul_iters, || {
+            let mut out = String::new();
+            let mut total = 0;
+            for i in 0..mul_iters as usize {
+                out.clear();
+                let _ = write!(out, "{}", nums[i & 63]);
+                total += out.len() as u64;
+            }
+            total
+        });
+    }
+
+    println!("\n=== Decimal ===");
+    let strings: Vec<String> = (0..4096)
+        .map(|_| format!("{}.{:04}", rng.range(-1_000_000, 1_000_000), rng.below(10_000)))
+        .collect();
+    let decimals: Vec<Decimal> = strings.iter().map(|s| Decimal::from_str(s).unwrap()).collect();
+    run(&mut report, "Decimal parse", n / 10, || {
+        (0..n as usize / 10).map(|i| Decimal::from_str(&strings[i & 4095]).unwrap().scale() as u64).sum()
+    });
+    run(&mut report, "Decimal add (sum of 4096)", n / 10, || {
+        let mut total = 0u64;
+        let mut acc = Decimal::ZERO;
+        for i in 0..n as usize / 10 {
+            acc = acc.checked_add(decimals[i & 4095]).unwrap();
+            if i & 4095 == 4095 {
+                total ^= acc.mantissa() as u64;
+                acc = Decimal::ZERO;
+            }
+        }
+        total
+    });
+    run(&mut report, "Decimal mul", n / 10, || {
+        (0..n as usize / 10).fold(0, |acc, i| {
+            acc ^ decimals[i & 4095].checked_mul(decimals[(i + 1) & 4095]).unwrap().mantissa() as u64
+        })
+    });
+    run(&mut report, "Decimal to_string", n / 10, || {
+        let mut out = String::new();
+        let mut total = 0;
+        for i in 0..n as usize / 10 {
+            out.clear();
+            let _ = write!(out, "{}", decimals[i & 4095]);
+            total += out.len() as u64;
+        }
+        total
+    });
+
+    println!("\n================================================================\n");
+    report.save_if_requested();
+}
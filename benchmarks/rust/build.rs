@@ -0,0 +1,17 @@
+// Records the build settings for `harness::env::Environment`.
+
+use std::process::Command;
+
+fn main() {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    if let Ok(out) = Command::new(rustc).arg("-V").output() {
+        let version = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        println!("cargo:rustc-env=BENCH_RUSTC_VERSION={}", version);
+    }
+    let profile = std::env::var("PROFILE").unwrap_or_default();
+    println!("cargo:rustc-env=BENCH_PROFILE={}", profile);
+    // CARGO_ENCODED_RUSTFLAGS separates flags with 0x1f
+    let flags = std::env::var("CARGO_ENCODED_RUSTFLAGS").unwrap_or_default().replace('\x1f', " ");
+    println!("cargo:rustc-env=BENCH_RUSTFLAGS={}", flags);
+    println!("cargo:rerun-if-env-changed=CARGO_ENCODED_RUSTFLAGS");
+}
@@ -0,0 +1,199 @@
+// Closure and Indirect Call Benchmarks - Rust
+//
+// Baselines for the bridge's ClosureInit and indirect-call lowering. Each
+// group runs the same arithmetic through different call shapes:
+//
+//   Capture     closures in iterator chains capturing scalars by reference
+//               or by value, a 256-byte struct by reference, by copy and by
+//               move, and mutable state through FnMut
+//   Dispatch    one closure called through a generic (inlined), &dyn FnMut,
+//               Box<dyn FnMut> and a plain fn pointer
+//   Pipelines   a chain of stages as Vec<Box<dyn Fn>> vs Vec<fn>, and
+//               closures returned from functions (make_adder style)
+//
+// The generic rows are what monomorphization gives; the dyn and fn-pointer
+// rows are the indirect calls TML closures compile to today, so the gap is
+// the overhead the bridge's lowering has to close or explain.
+//
+// Run with: cargo run --release --bin closure_bench [iterations]
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use tml_benchmarks::env::{apply_run_settings, Environment};
+use tml_benchmarks::report::{BenchResult, Report};
+use tml_benchmarks::rng::{self, RngCore};
+
+/// Captured by the struct-capture rows: large enough that copying it into
+/// a closure is not free.
+#[derive(Clone, Copy)]
+struct Weights {
+    w: [u64; 32],
+}
+
+fn step(x: u64, k: u64) -> u64 {
+    x.wrapping_mul(k) ^ (x >> 7)
+}
+
+fn step_fn(x: u64) -> u64 {
+    step(x, 0x9e37_79b9)
+}
+
+fn add_one(x: u64) -> u64 {
+    x.wrapping_add(1)
+}
+
+fn rotate(x: u64) -> u64 {
+    x.rotate_left(13)
+}
+
+fn xor_mask(x: u64) -> u64 {
+    x ^ 0x5555_5555_5555_5555
+}
+
+const STAGES: [fn(u64) -> u64; 4] = [step_fn, add_one, rotate, xor_mask];
+
+// ============================================================================
+// Call shapes
+// ============================================================================
+
+#[inline(never)]
+fn drive_generic<F: FnMut(u64) -> u64>(inputs: &[u64], mut f: F) -> u64 {
+    inputs.iter().fold(0, |acc, &x| acc ^ f(x))
+}
+
+#[inline(never)]
+fn drive_dyn(inputs: &[u64], f: &mut dyn FnMut(u64) -> u64) -> u64 {
+    inputs.iter().fold(0, |acc, &x| acc ^ f(x))
+}
+
+#[inline(never)]
+fn drive_boxed(inputs: &[u64], mut f: Box<dyn FnMut(u64) -> u64>) -> u64 {
+    inputs.iter().fold(0, |acc, &x| acc ^ f(x))
+}
+
+#[inline(never)]
+fn drive_fn_ptr(inputs: &[u64], f: fn(u64) -> u64) -> u64 {
+    inputs.iter().fold(0, |acc, &x| acc ^ f(x))
+}
+
+fn weigh_with(weights: Weights) -> impl Fn(u64) -> u64 {
+    move |x| x.wrapping_mul(weights.w[(x & 31) as usize])
+}
+
+fn make_adder(n: u64) -> impl Fn(u64) -> u64 {
+    move |x| x.wrapping_add(n)
+}
+
+fn make_boxed_adder(n: u64) -> Box<dyn Fn(u64) -> u64> {
+    Box::new(move |x| x.wrapping_add(n))
+}
+
+// ============================================================================
+// Benchmarks
+// ============================================================================
+
+fn run(report: &mut Report, name: &str, iterations: u64, f: impl FnOnce() -> u64) -> u64 {
+    let start = Instant::now();
+    let checksum = black_box(f());
+    let ns = start.elapsed().as_nanos() as u64;
+    let r = BenchResult::new(name, iterations, ns);
+    println!("  {:<44} {:>8.2} ns/op {:>14} ops/sec", name, ns as f64 / iterations as f64, r.ops_per_sec());
+    report.results.push(r);
+    checksum
+}
+
+fn main() {
+    let settings = apply_run_settings();
+    let n: usize = std::env::args().nth(1).and_then(|a| a.parse().ok()).unwrap_or(10_000_000);
+    let mut report = Report::new("Closures", Environment::capture(&settings));
+    let mut rng = rng::seeded(2756);
+    let inputs: Vec<u64> = (0..n).map(|_| rng.next_u64()).collect();
+    let iters = n as u64;
+
+    println!("\n================================================================");
+    println!("  Rust Closure and Indirect Call Benchmarks");
+    println!("================================================================\n");
+
+    println!("=== Capture ===");
+    let k = black_box(0x9e37_79b9u64);
+    let by_ref = run(&mut report, "map/sum, scalar captured by ref", iters, || {
+        let k = &k;
+        inputs.iter().map(|&x| step(x, *k)).fold(0, |a, b| a ^ b)
+    });
+    let by_value = run(&mut report, "map/sum, scalar captured by move", iters, || {
+        inputs.iter().map(move |&x| step(x, k)).fold(0, |a, b| a ^ b)
+    });
+    assert_eq!(by_ref, by_value);
+    run(&mut report, "filter/map/sum, two captures", iters, || {
+        let (lo, hi) = (black_box(u64::MAX / 4), black_box(u64::MAX / 4 * 3));
+        inputs.iter().filter(|&&x| x > lo && x < hi).map(|&x| step(x, k)).fold(0, |a, b| a ^ b)
+    });
+
+    let weights = Weights { w: std::array::from_fn(|i| (i as u64 + 1) * 0x0101) };
+    let struct_ref = run(&mut report, "256 B struct captured by ref", iters, || {
+        let w = &weights;
+        inputs.iter().map(|&x| x.wrapping_mul(w.w[(x & 31) as usize])).fold(0, |a, b| a ^ b)
+    });
+    // A fresh closure per element copies the struct each time
+    let struct_copy = run(&mut report, "256 B struct copied per element", iters, || {
+        inputs.iter().map(|&x| weigh_with(black_box(weights))(x)).fold(0, |a, b| a ^ b)
+    });
+    let struct_move = run(&mut report, "256 B struct moved into closure once", iters, || {
+        let f = weigh_with(weights);
+        inputs.iter().map(|&x| f(x)).fold(0, |a, b| a ^ b)
+    });
+    assert!(struct_ref == struct_copy && struct_copy == struct_move);
+    run(&mut report, "FnMut with captured counter", iters, || {
+        let mut seen = 0u64;
+        let mut count = |x: u64| {
+            seen = seen.wrapping_add(x & 1);
+            seen
+        };
+        inputs.iter().fold(0, |acc, &x| acc ^ count(x))
+    });
+
+    println!("\n=== Dispatch ===");
+    // Trait objects and pointers go through black_box, or LLVM sees the
+    // single target and devirtualizes the calls
+    let expected = drive_fn_ptr(&inputs, step_fn);
+    let checks = [
+        run(&mut report, "generic impl FnMut (monomorphized)", iters, || drive_generic(&inputs, |x| step(x, k))),
+        run(&mut report, "&mut dyn FnMut", iters, || drive_dyn(&inputs, black_box(&mut |x| step(x, k)))),
+        run(&mut report, "Box<dyn FnMut>", iters, || drive_boxed(&inputs, black_box(Box::new(move |x| step(x, k))))),
+        run(&mut report, "fn pointer", iters, || drive_fn_ptr(&inputs, black_box(step_fn as fn(u64) -> u64))),
+    ];
+    assert!(checks.iter().all(|&c| c == expected));
+
+    println!("\n=== Pipelines ({} stages) ===", STAGES.len());
+    let boxed: Vec<Box<dyn Fn(u64) -> u64>> =
+        black_box(STAGES.iter().map(|&f| Box::new(f) as Box<dyn Fn(u64) -> u64>).collect());
+    let pointers: Vec<fn(u64) -> u64> = black_box(STAGES.to_vec());
+    let pipelines = [
+        run(&mut report, "Vec<Box<dyn Fn>> pipeline", iters, || {
+            inputs.iter().fold(0, |acc, &x| acc ^ boxed.iter().fold(x, |v, f| f(v)))
+        }),
+        run(&mut report, "Vec<fn> pipeline", iters, || {
+            inputs.iter().fold(0, |acc, &x| acc ^ pointers.iter().fold(x, |v, f| f(v)))
+        }),
+        run(&mut report, "inlined stage calls", iters, || {
+            inputs.iter().fold(0, |acc, &x| acc ^ xor_mask(rotate(add_one(step_fn(x)))))
+        }),
+    ];
+    assert!(pipelines.iter().all(|&p| p == pipelines[0]));
+
+    let adder = make_adder(black_box(7));
+    let boxed_adder = black_box(make_boxed_adder(black_box(7)));
+    let adders = [
+        run(&mut report, "returned impl Fn (make_adder)", iters, || inputs.iter().fold(0, |acc, &x| acc ^ adder(x))),
+        run(&mut report, "returned Box<dyn Fn>", iters, || inputs.iter().fold(0, |acc, &x| acc ^ boxed_adder(x))),
+    ];
+    assert_eq!(adders[0], adders[1]);
+    let creations = (n / 10).max(1);
+    run(&mut report, "create + call Box<dyn Fn> per element", creations as u64, || {
+        inputs[..creations].iter().fold(0, |acc, &x| acc ^ black_box(make_boxed_adder(x))(acc))
+    });
+
+    println!("\n================================================================\n");
+    report.save_if_requested();
+}
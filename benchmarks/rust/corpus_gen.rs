@@ -0,0 +1,51 @@
+// Corpus Generator
+//
+// Writes the shared JSON corpus (see `harness/corpus.rs`) to the cache
+// directory so suites in other languages can load it, and prints each
+// file with its size.
+//
+// Run with: cargo run --release --bin corpus_gen [seed] [kind[=size]...]
+//
+// Without kinds, every kind is written at its default size. The seed
+// defaults to `BENCH_CORPUS_SEED`, then 0.
+
+use tml_benchmarks::corpus::{self, Kind};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let seed = match args.next() {
+        Some(s) => s.parse().unwrap_or_else(|_| {
+            eprintln!("seed must be a number: {}", s);
+            std::process::exit(2);
+        }),
+        None => corpus::seed(),
+    };
+    let mut docs: Vec<(Kind, usize)> = Vec::new();
+    for arg in args {
+        let (name, size) = arg.split_once('=').unwrap_or((&arg, ""));
+        let Some(kind) = Kind::from_name(name) else {
+            let names: Vec<&str> = Kind::ALL.iter().map(|k| k.name()).collect();
+            eprintln!("unknown corpus kind '{}' (expected one of {})", name, names.join(", "));
+            std::process::exit(2);
+        };
+        let size = if size.is_empty() {
+            kind.default_size()
+        } else {
+            size.parse().unwrap_or_else(|_| {
+                eprintln!("size must be a number: {}", arg);
+                std::process::exit(2);
+            })
+        };
+        docs.push((kind, size));
+    }
+    if docs.is_empty() {
+        docs = Kind::ALL.iter().map(|&k| (k, k.default_size())).collect();
+    }
+
+    let dir = corpus::cache_dir();
+    println!("Corpus seed {} in {}", seed, dir.display());
+    for (kind, size) in docs {
+        let doc = corpus::load(kind, size, seed);
+        println!("  {:<40} {:>10} bytes", corpus::file_name(kind, size, seed), doc.len());
+    }
+}
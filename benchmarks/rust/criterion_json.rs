@@ -0,0 +1,63 @@
+// Criterion to Unified JSON
+//
+// Converts the results of the criterion benches (`cargo bench`) into the
+// JSON schema the standalone benchmarks and the C++/TML harnesses write, so
+// the comparison reports can read both.
+//
+// Usage: criterion_json [--category NAME] [--out FILE] [CRITERION_DIR]
+//
+// CRITERION_DIR defaults to `target/criterion`, the category to
+// "criterion". Without --out the JSON goes to stdout. The environment
+// recorded is this machine and this crate's build settings, which match the
+// benches when both are built by the same `cargo` invocation.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use tml_benchmarks::criterion;
+use tml_benchmarks::env::{Environment, RunSettings};
+use tml_benchmarks::report::Report;
+
+fn main() -> ExitCode {
+    let mut category = "criterion".to_string();
+    let mut out = None;
+    let mut dir = PathBuf::from("target/criterion");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--category" => category = args.next().unwrap_or_default(),
+            "--out" | "-o" => out = args.next(),
+            "--help" | "-h" => {
+                println!("usage: criterion_json [--category NAME] [--out FILE] [CRITERION_DIR]");
+                return ExitCode::SUCCESS;
+            }
+            _ => dir = PathBuf::from(arg),
+        }
+    }
+
+    let mut report = Report::new(&category, Environment::capture(&RunSettings::default()));
+    report.results = match criterion::load(&dir) {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("criterion_json: {}: {}", dir.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if report.results.is_empty() {
+        eprintln!("criterion_json: no criterion results under {}", dir.display());
+        return ExitCode::FAILURE;
+    }
+
+    let json = report.to_json();
+    match out {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, json) {
+                eprintln!("criterion_json: could not write {}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+            eprintln!("  {} results saved to {}", report.results.len(), path);
+        }
+        None => print!("{}", json),
+    }
+    ExitCode::SUCCESS
+}
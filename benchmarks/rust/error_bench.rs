@@ -0,0 +1,213 @@
+// Error-Handling Overhead Benchmarks - Rust
+//
+// Input for TML's default error ABI: the same recursive computation, with
+// the failure raised at the deepest frame and handled at the top, written
+// four ways:
+//
+//   Result<u64, SmallError>     `?` through every frame, error in registers
+//   Result<u64, Box<dyn Error>> `?` with an allocated, formatted error
+//                               (anyhow-style)
+//   panic + catch_unwind        table-based unwinding to a single handler
+//   status code                 i32 return, value through an out-pointer
+//
+// Each is swept over chain depth and failure rate. The 0% rows are the
+// happy-path tax of each style (panics cost nothing until thrown, but the
+// landing pads constrain codegen); the 100% rows are the cost of a failure,
+// which for panics is dominated by the unwinder walking every frame. That
+// unwinder cost is what the Cranelift backend has to match once it emits
+// unwind tables.
+//
+// Run with: cargo run --release --bin error_bench [iterations]
+
+use std::error::Error;
+use std::hint::black_box;
+use std::panic;
+use std::time::Instant;
+
+use tml_benchmarks::env::{apply_run_settings, Environment};
+use tml_benchmarks::report::{BenchResult, Report};
+use tml_benchmarks::rng::{self, RngCore};
+
+const DEPTHS: [u32; 3] = [1, 8, 32];
+/// Failure rates in parts per thousand
+const FAIL_PERMILLE: [u64; 4] = [0, 10, 500, 1000];
+const STATUS_OK: i32 = 0;
+const STATUS_REJECTED: i32 = 1;
+
+#[derive(Debug)]
+struct SmallError {
+    value: u64,
+}
+
+fn rejected(x: u64, permille: u64) -> bool {
+    x % 1000 < permille
+}
+
+/// The shared per-frame work on the way back up.
+fn combine(v: u64, depth: u32) -> u64 {
+    v.wrapping_mul(31).wrapping_add(depth as u64)
+}
+
+// ============================================================================
+// The four styles
+// ============================================================================
+
+#[inline(never)]
+fn result_chain(x: u64, depth: u32, permille: u64) -> Result<u64, SmallError> {
+    if depth == 0 {
+        return if rejected(x, permille) { Err(SmallError { value: x }) } else { Ok(x) };
+    }
+    let v = result_chain(x, depth - 1, permille)?;
+    Ok(combine(v, depth))
+}
+
+#[inline(never)]
+fn boxed_chain(x: u64, depth: u32, permille: u64) -> Result<u64, Box<dyn Error>> {
+    if depth == 0 {
+        if rejected(x, permille) {
+            return Err(format!("value {} rejected", x).into());
+        }
+        return Ok(x);
+    }
+    let v = boxed_chain(x, depth - 1, permille)?;
+    Ok(combine(v, depth))
+}
+
+#[inline(never)]
+fn panic_chain(x: u64, depth: u32, permille: u64) -> u64 {
+    if depth == 0 {
+        if rejected(x, permille) {
+            // A typed payload, so no message is formatted
+            panic::panic_any(SmallError { value: x });
+        }
+        return x;
+    }
+    combine(panic_chain(x, depth - 1, permille), depth)
+}
+
+#[inline(never)]
+fn status_chain(x: u64, depth: u32, permille: u64, out: &mut u64) -> i32 {
+    if depth == 0 {
+        if rejected(x, permille) {
+            return STATUS_REJECTED;
+        }
+        *out = x;
+        return STATUS_OK;
+    }
+    let status = status_chain(x, depth - 1, permille, out);
+    if status != STATUS_OK {
+        return status;
+    }
+    *out = combine(*out, depth);
+    STATUS_OK
+}
+
+/// Checksum of a run: the sum of successful values plus the failure count,
+/// identical for every style on the same inputs.
+#[derive(Debug, Default, PartialEq)]
+struct Tally {
+    sum: u64,
+    failures: u64,
+}
+
+impl Tally {
+    fn add(&mut self, outcome: Option<u64>) {
+        match outcome {
+            Some(v) => self.sum = self.sum.wrapping_add(v),
+            None => self.failures += 1,
+        }
+    }
+}
+
+fn run_result(inputs: &[u64], depth: u32, permille: u64) -> Tally {
+    let mut tally = Tally::default();
+    for &x in inputs {
+        tally.add(result_chain(black_box(x), depth, permille).map_err(|e| black_box(e.value)).ok());
+    }
+    tally
+}
+
+fn run_boxed(inputs: &[u64], depth: u32, permille: u64) -> Tally {
+    let mut tally = Tally::default();
+    for &x in inputs {
+        tally.add(boxed_chain(black_box(x), depth, permille).map_err(black_box).ok());
+    }
+    tally
+}
+
+fn run_panic(inputs: &[u64], depth: u32, permille: u64) -> Tally {
+    let mut tally = Tally::default();
+    for &x in inputs {
+        let outcome = panic::catch_unwind(|| panic_chain(black_box(x), depth, permille));
+        tally.add(outcome.map_err(|payload| black_box(payload.downcast::<SmallError>().is_ok())).ok());
+    }
+    tally
+}
+
+fn run_status(inputs: &[u64], depth: u32, permille: u64) -> Tally {
+    let mut tally = Tally::default();
+    for &x in inputs {
+        let mut out = 0;
+        let status = status_chain(black_box(x), depth, permille, &mut out);
+        tally.add((status == STATUS_OK).then_some(out));
+    }
+    tally
+}
+
+// ============================================================================
+// Benchmarks
+// ============================================================================
+
+fn run(report: &mut Report, name: &str, inputs: &[u64], expected: &Tally, f: impl FnOnce(&[u64]) -> Tally) {
+    let start = Instant::now();
+    let tally = black_box(f(inputs));
+    let ns = start.elapsed().as_nanos() as u64;
+    assert_eq!(&tally, expected, "{} disagrees with the status-code run", name);
+    let r = BenchResult::new(name, inputs.len() as u64, ns);
+    println!("  {:<48} {:>9.2} ns/op {:>14} ops/sec", name, ns as f64 / inputs.len() as f64, r.ops_per_sec());
+    report.results.push(r);
+}
+
+fn main() {
+    let settings = apply_run_settings();
+    let n: usize = std::env::args().nth(1).and_then(|a| a.parse().ok()).unwrap_or(1_000_000);
+    let mut report = Report::new("Errors", Environment::capture(&settings));
+    let mut rng = rng::seeded(2755);
+    let inputs: Vec<u64> = (0..n).map(|_| rng.next_u64()).collect();
+    // Unwinding costs microseconds per panic; fewer calls keep the
+    // high-failure rows short
+    let panic_inputs = &inputs[..(n / 10).max(1)];
+
+    // Silence the default "thread panicked" message for the expected panics
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    println!("\n================================================================");
+    println!("  Rust Error-Handling Overhead Benchmarks");
+    println!("================================================================");
+
+    for depth in DEPTHS {
+        println!("\n=== Depth {} ===", depth);
+        for permille in FAIL_PERMILLE {
+            let rate = format!("{}% errors", permille as f64 / 10.0);
+            let expected = run_status(&inputs, depth, permille);
+            let expected_panic = run_status(panic_inputs, depth, permille);
+            run(&mut report, &format!("status code, depth {}, {}", depth, rate), &inputs, &expected, |i| {
+                run_status(i, depth, permille)
+            });
+            run(&mut report, &format!("Result<_, SmallError>, depth {}, {}", depth, rate), &inputs, &expected, |i| {
+                run_result(i, depth, permille)
+            });
+            run(&mut report, &format!("Result<_, Box<dyn Error>>, depth {}, {}", depth, rate), &inputs, &expected, |i| {
+                run_boxed(i, depth, permille)
+            });
+            run(&mut report, &format!("panic + catch_unwind, depth {}, {}", depth, rate), panic_inputs, &expected_panic, |i| {
+                run_panic(i, depth, permille)
+            });
+        }
+    }
+
+    panic::set_hook(default_hook);
+    println!("\n================================================================\n");
+    report.save_if_requested();
+}
@@ -0,0 +1,223 @@
+// String Formatting Benchmarks - Rust
+//
+// Targets for TML's formatter and the runtime's string building:
+//
+//   integers   format!, write! into a reused String, itoa, and a two-digit
+//              table loop (what a runtime i64-to-string usually looks like)
+//   floats     format!, write!, fixed precision, ryu's shortest round-trip
+//              formatter, and zmij (which serde_json uses since it replaced
+//              ryu) as a second shortest round-trip comparison
+//   reports    100k-line reports built with format! per line, write! into
+//              one String, bytes into a Vec<u8>, one exact allocation from
+//              collected parts (the runtime's str_concat_n), and pairwise
+//              concatenation into a new string each time (str_concat)
+//
+// Run with: cargo run --release --bin format_bench [iterations]
+
+use std::fmt::Write;
+use std::hint::black_box;
+use std::time::Instant;
+
+use tml_benchmarks::env::{apply_run_settings, Environment};
+use tml_benchmarks::report::{BenchResult, Report};
+use tml_benchmarks::rng::{self, RngCore, Sample};
+
+const DIGIT_PAIRS: &[u8; 200] = b"\
+    0001020304050607080910111213141516171819\
+    2021222324252627282930313233343536373839\
+    4041424344454647484950515253545556575859\
+    6061626364656667686970717273747576777879\
+    8081828384858687888990919293949596979899";
+
+/// Append the decimal form of `v`, two digits per step.
+fn push_i64(out: &mut Vec<u8>, v: i64) {
+    let mut buf = [0u8; 20];
+    let mut pos = buf.len();
+    let mut n = v.unsigned_abs();
+    while n >= 100 {
+        let pair = (n % 100) as usize * 2;
+        n /= 100;
+        pos -= 2;
+        buf[pos..pos + 2].copy_from_slice(&DIGIT_PAIRS[pair..pair + 2]);
+    }
+    if n >= 10 {
+        pos -= 2;
+        buf[pos..pos + 2].copy_from_slice(&DIGIT_PAIRS[n as usize * 2..n as usize * 2 + 2]);
+    } else {
+        pos -= 1;
+        buf[pos] = b'0' + n as u8;
+    }
+    if v < 0 {
+        out.push(b'-');
+    }
+    out.extend_from_slice(&buf[pos..]);
+}
+
+/// One line of the report workload.
+struct Row {
+    name: String,
+    count: i64,
+    ratio: f64,
+}
+
+fn run(report: &mut Report, name: &str, iterations: u64, f: impl FnOnce() -> usize) -> usize {
+    let start = Instant::now();
+    let bytes = black_box(f());
+    let ns = start.elapsed().as_nanos() as u64;
+    let r = BenchResult::new(name, iterations, ns);
+    println!("  {:<40} {:>8.2} ns/op {:>14} ops/sec", name, ns as f64 / iterations as f64, r.ops_per_sec());
+    report.results.push(r);
+    bytes
+}
+
+fn main() {
+    let settings = apply_run_settings();
+    let n: usize = std::env::args().nth(1).and_then(|a| a.parse().ok()).unwrap_or(2_000_000);
+    let mut report = Report::new("Formatting", Environment::capture(&settings));
+    let mut rng = rng::seeded(2758);
+    let iters = n as u64;
+
+    // Up to 18 digits, both signs
+    let ints: Vec<i64> = (0..n)
+        .map(|_| {
+            let digits = rng.range(1, 19) as u32;
+            let v = rng.range(0, 10i64.pow(digits));
+            if rng.next_u32() & 1 == 0 { v } else { -v }
+        })
+        .collect();
+    // Short decimals (prices, ratios) and full-precision doubles
+    let floats: Vec<f64> = (0..n)
+        .map(|i| if i % 2 == 0 { rng.range(0, 1_000_000) as f64 / 100.0 } else { rng.next_f64() * 1e6 })
+        .collect();
+
+    println!("\n================================================================");
+    println!("  Rust String Formatting Benchmarks");
+    println!("================================================================\n");
+
+    println!("=== i64 to string ===");
+    let ints_ref = &ints;
+    let lens = [
+        run(&mut report, "format!(\"{}\")", iters, || ints_ref.iter().map(|v| format!("{}", v).len()).sum()),
+        run(&mut report, "write! into reused String", iters, || {
+            let mut s = String::with_capacity(24);
+            ints_ref.iter().map(|v| {
+                s.clear();
+                let _ = write!(s, "{}", v);
+                s.len()
+            }).sum()
+        }),
+        run(&mut report, "itoa::Buffer", iters, || {
+            let mut buf = itoa::Buffer::new();
+            ints_ref.iter().map(|&v| buf.format(v).len()).sum()
+        }),
+        run(&mut report, "two-digit table", iters, || {
+            let mut out = Vec::with_capacity(24);
+            ints_ref.iter().map(|&v| {
+                out.clear();
+                push_i64(&mut out, v);
+                out.len()
+            }).sum()
+        }),
+    ];
+    assert!(lens.iter().all(|&l| l == lens[0]), "integer lengths differ: {:?}", lens);
+
+    println!("\n=== f64 to string ===");
+    let floats_ref = &floats;
+    let shortest = [
+        run(&mut report, "format!(\"{}\")", iters, || floats_ref.iter().map(|v| format!("{}", v).len()).sum()),
+        run(&mut report, "write! into reused String", iters, || {
+            let mut s = String::with_capacity(32);
+            floats_ref.iter().map(|v| {
+                s.clear();
+                let _ = write!(s, "{}", v);
+                s.len()
+            }).sum()
+        }),
+    ];
+    assert_eq!(shortest[0], shortest[1]);
+    run(&mut report, "write! \"{:.3}\"", iters, || {
+        let mut s = String::with_capacity(32);
+        floats_ref.iter().map(|v| {
+            s.clear();
+            let _ = write!(s, "{:.3}", v);
+            s.len()
+        }).sum()
+    });
+    run(&mut report, "ryu::Buffer (shortest round-trip)", iters, || {
+        let mut buf = ryu::Buffer::new();
+        floats_ref.iter().map(|&v| buf.format(v).len()).sum()
+    });
+    run(&mut report, "zmij::Buffer (shortest round-trip)", iters, || {
+        let mut buf = zmij::Buffer::new();
+        floats_ref.iter().map(|&v| buf.format(v).len()).sum()
+    });
+    for (i, &v) in floats.iter().take(1000).enumerate() {
+        let text = ryu::Buffer::new().format(v).to_string();
+        assert_eq!(text.parse::<f64>().unwrap(), v, "ryu output {} does not round-trip", i);
+        let text = zmij::Buffer::new().format(v).to_string();
+        assert_eq!(text.parse::<f64>().unwrap(), v, "zmij output {} does not round-trip", i);
+    }
+
+    let lines = (n / 20).max(1);
+    let rows: Vec<Row> = (0..lines)
+        .map(|i| Row { name: format!("item-{:05}", i % 100_000), count: ints[i], ratio: floats[i] })
+        .collect();
+    println!("\n=== Report of {} lines ===", lines);
+    let rows_ref = &rows;
+    let reports = [
+        run(&mut report, "format! per line + push_str", lines as u64, || {
+            let mut out = String::new();
+            for r in rows_ref {
+                out.push_str(&format!("{} count={} ratio={:.3}\n", r.name, r.count, r.ratio));
+            }
+            out.len()
+        }),
+        run(&mut report, "write! into one String", lines as u64, || {
+            let mut out = String::new();
+            for r in rows_ref {
+                let _ = writeln!(out, "{} count={} ratio={:.3}", r.name, r.count, r.ratio);
+            }
+            out.len()
+        }),
+        run(&mut report, "Vec<u8> + itoa + write! floats", lines as u64, || {
+            let mut out: Vec<u8> = Vec::new();
+            let mut ints = itoa::Buffer::new();
+            for r in rows_ref {
+                out.extend_from_slice(r.name.as_bytes());
+                out.extend_from_slice(b" count=");
+                out.extend_from_slice(ints.format(r.count).as_bytes());
+                out.extend_from_slice(b" ratio=");
+                let _ = std::io::Write::write_fmt(&mut out, format_args!("{:.3}\n", r.ratio));
+            }
+            String::from_utf8(out).unwrap().len()
+        }),
+        run(&mut report, "collected parts, one concat (concat_n)", lines as u64, || {
+            let mut ints = itoa::Buffer::new();
+            let mut parts: Vec<String> = Vec::with_capacity(rows_ref.len() * 5);
+            for r in rows_ref {
+                parts.push(r.name.clone());
+                parts.push(" count=".to_string());
+                parts.push(ints.format(r.count).to_string());
+                parts.push(" ratio=".to_string());
+                parts.push(format!("{:.3}\n", r.ratio));
+            }
+            parts.concat().len()
+        }),
+    ];
+    assert!(reports.iter().all(|&l| l == reports[0]), "report lengths differ: {:?}", reports);
+
+    // Pairwise concatenation copies the report so far each time, so it runs
+    // on a prefix small enough to finish
+    let pairwise = lines.min(5_000);
+    run(&mut report, &format!("pairwise concat, {} lines", pairwise), pairwise as u64, || {
+        let mut out = String::new();
+        for r in &rows_ref[..pairwise] {
+            let line = format!("{} count={} ratio={:.3}\n", r.name, r.count, r.ratio);
+            out = [out.as_str(), line.as_str()].concat();
+        }
+        out.len()
+    });
+
+    println!("\n================================================================\n");
+    report.save_if_requested();
+}
@@ -0,0 +1,388 @@
+// Shared JSON Corpus
+//
+// The documents json_bench parses, available to any benchmark that wants
+// realistic text input (compression, hashing, file I/O) so the suites run
+// on identical bytes:
+//
+//   small         one user record with a nested address
+//   medium        {"items": [...]}, flat records with a short tag list
+//   large         {"data": [...]}, user records with uuid, email, nested
+//                 metadata and tags (about 1.5 MB at the default size)
+//   deep          objects nested `size` levels through "child"
+//   wide          one array of `size` integers
+//   string-heavy  {"strings": [...]} of sentence-length strings
+//
+// Seed 0 gives json_bench's original fixed documents, byte for byte what
+// serde_json's `json!` produced (keys sorted), so earlier results stay
+// comparable. Other seeds draw names, numbers, dates and text from
+// `rng::seeded(seed)`; the shape of each document stays the same and
+// `deep` does not vary. Benchmarks read the seed from
+// `BENCH_CORPUS_SEED` (default 0).
+//
+// `load` caches every document it generates as
+// `<kind>-<size>-s<seed>-v<CORPUS_VERSION>.json` in `BENCH_CORPUS_DIR`
+// (default `<temp>/tml-bench-corpus`), and later runs read the file back.
+// Other languages' suites, TML's included, load these files rather than
+// porting the generators; `cargo run --release --bin corpus_gen` writes the
+// standard set. CORPUS_VERSION changes whenever a generator's output does,
+// so a stale cache is never read.
+//
+// Besides std, only `rng` and rand are used, so crates without this
+// library can include this module next to `rng` (json_bench does).
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use rand::seq::SliceRandom;
+
+use crate::rng::{self, RngCore, Sample, SmallRng};
+
+pub const CORPUS_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Small,
+    Medium,
+    Large,
+    Deep,
+    Wide,
+    StringHeavy,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 6] = [Kind::Small, Kind::Medium, Kind::Large, Kind::Deep, Kind::Wide, Kind::StringHeavy];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Small => "small",
+            Kind::Medium => "medium",
+            Kind::Large => "large",
+            Kind::Deep => "deep",
+            Kind::Wide => "wide",
+            Kind::StringHeavy => "string-heavy",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Kind> {
+        Kind::ALL.into_iter().find(|k| k.name() == name)
+    }
+
+    /// Size used by json_bench: records, levels or array elements (ignored
+    /// for `Small`).
+    pub fn default_size(self) -> usize {
+        match self {
+            Kind::Small => 1,
+            Kind::Medium | Kind::StringHeavy => 1000,
+            Kind::Large | Kind::Wide => 10_000,
+            Kind::Deep => 100,
+        }
+    }
+}
+
+/// `BENCH_CORPUS_SEED`, or 0.
+pub fn seed() -> u64 {
+    match std::env::var("BENCH_CORPUS_SEED") {
+        Ok(s) => s.trim().parse().unwrap_or_else(|_| {
+            eprintln!("  warning: BENCH_CORPUS_SEED: not a number: {}", s);
+            0
+        }),
+        Err(_) => 0,
+    }
+}
+
+/// `BENCH_CORPUS_DIR`, or `tml-bench-corpus` in the temp directory.
+pub fn cache_dir() -> PathBuf {
+    std::env::var_os("BENCH_CORPUS_DIR").map(PathBuf::from).unwrap_or_else(|| std::env::temp_dir().join("tml-bench-corpus"))
+}
+
+pub fn file_name(kind: Kind, size: usize, seed: u64) -> String {
+    format!("{}-{}-s{}-v{}.json", kind.name(), size, seed, CORPUS_VERSION)
+}
+
+/// The cached document, generating and caching it on first use. A cache
+/// that cannot be written is reported and the document returned anyway.
+pub fn load(kind: Kind, size: usize, seed: u64) -> String {
+    let path = cache_dir().join(file_name(kind, size, seed));
+    if let Ok(doc) = std::fs::read_to_string(&path) {
+        return doc;
+    }
+    let doc = generate(kind, size, seed);
+    // Written under a temporary name and renamed, so concurrent runs never
+    // read a partial file
+    let tmp = path.with_extension(format!("json.tmp{}", std::process::id()));
+    let written = std::fs::create_dir_all(cache_dir())
+        .and_then(|_| std::fs::write(&tmp, &doc))
+        .and_then(|_| std::fs::rename(&tmp, &path));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&tmp);
+        eprintln!("  warning: could not cache {}: {}", path.display(), e);
+    }
+    doc
+}
+
+/// `kind` at its default size.
+pub fn load_default(kind: Kind, seed: u64) -> String {
+    load(kind, kind.default_size(), seed)
+}
+
+pub fn generate(kind: Kind, size: usize, seed: u64) -> String {
+    // Seed 0 keeps the fixed documents
+    let mut rng = (seed != 0).then(|| rng::seeded(seed));
+    let mut out = String::new();
+    match kind {
+        Kind::Small => small(&mut out, rng.as_mut()),
+        Kind::Medium => medium(&mut out, size, rng.as_mut()),
+        Kind::Large => large(&mut out, size, rng.as_mut()),
+        Kind::Deep => deep(&mut out, size),
+        Kind::Wide => wide(&mut out, size, rng.as_mut()),
+        Kind::StringHeavy => string_heavy(&mut out, size, rng.as_mut()),
+    }
+    out
+}
+
+// ============================================================================
+// Generators
+// ============================================================================
+
+// Keys are written in sorted order, as serde_json's default map emits them.
+// No generated string needs escaping.
+
+const FIRST_NAMES: [&str; 8] = ["John", "Maria", "Wei", "Aisha", "Lars", "Priya", "Diego", "Yuki"];
+const LAST_NAMES: [&str; 8] = ["Doe", "Silva", "Chen", "Okafor", "Berg", "Patel", "Garcia", "Sato"];
+const CITIES: [(&str, &str); 4] = [("New York", "10001"), ("Chicago", "60601"), ("Austin", "73301"), ("Seattle", "98101")];
+const TAGS: [&str; 8] = ["alpha", "beta", "gamma", "delta", "tag1", "tag2", "tag3", "tag4"];
+const LOREM: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. Sed do eiusmod tempor incididunt ut labore et dolore magna aliqua.";
+
+fn pick<'a>(rng: &mut SmallRng, items: &[&'a str]) -> &'a str {
+    items[rng.below(items.len() as u64) as usize]
+}
+
+/// `["a","b",...]`
+fn push_strings<'a>(out: &mut String, items: impl IntoIterator<Item = &'a str>) {
+    out.push('[');
+    for (i, s) in items.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let _ = write!(out, "\"{}\"", s);
+    }
+    out.push(']');
+}
+
+/// 1 to `max` distinct tags, in pool order.
+fn random_tags(rng: &mut SmallRng, pool: &[&'static str], max: u64) -> Vec<&'static str> {
+    let mut tags = pool.to_vec();
+    tags.shuffle(rng);
+    tags.truncate(rng.range(1, max as i64 + 1) as usize);
+    tags.sort_by_key(|t| pool.iter().position(|p| p == t));
+    tags
+}
+
+/// Floats as serde_json writes them: shortest round-trip, always with a
+/// fraction or exponent.
+fn push_f64(out: &mut String, v: f64) {
+    let _ = write!(out, "{:?}", v);
+}
+
+fn small(out: &mut String, rng: Option<&mut SmallRng>) {
+    let Some(rng) = rng else {
+        out.push_str(concat!(
+            r#"{"active":true,"address":{"city":"New York","street":"123 Main St","zip":"10001"},"age":30,"#,
+            r#""email":"john@example.com","name":"John Doe","scores":[95,87,92,88,91]}"#
+        ));
+        return;
+    };
+    let (first, last) = (pick(rng, &FIRST_NAMES), pick(rng, &LAST_NAMES));
+    let (city, zip) = CITIES[rng.below(CITIES.len() as u64) as usize];
+    let _ = write!(
+        out,
+        r#"{{"active":{},"address":{{"city":"{}","street":"{} Main St","zip":"{}"}},"age":{},"email":"{}@example.com","name":"{} {}","scores":["#,
+        rng.next_u32() & 1 == 0,
+        city,
+        rng.range(1, 1000),
+        zip,
+        rng.range(18, 90),
+        first.to_lowercase(),
+        first,
+        last
+    );
+    for i in 0..5 {
+        let _ = write!(out, "{}{}", if i > 0 { "," } else { "" }, rng.range(50, 101));
+    }
+    out.push_str("]}");
+}
+
+fn medium(out: &mut String, n: usize, mut rng: Option<&mut SmallRng>) {
+    out.push_str(r#"{"items":["#);
+    for i in 0..n {
+        if i > 0 {
+            out.push(',');
+        }
+        let (active, price, tags) = match rng.as_deref_mut() {
+            None => (i % 2 == 0, i as f64 * 1.5, vec!["tag1", "tag2", "tag3"]),
+            Some(r) => (r.next_u32() & 1 == 0, r.range(0, 100_000) as f64 / 100.0, random_tags(r, &TAGS[4..], 3)),
+        };
+        let _ = write!(out, r#"{{"active":{},"id":{},"name":"Item {}","price":"#, active, i, i);
+        push_f64(out, price);
+        out.push_str(r#","tags":"#);
+        push_strings(out, tags);
+        out.push('}');
+    }
+    out.push_str("]}");
+}
+
+fn large(out: &mut String, n: usize, mut rng: Option<&mut SmallRng>) {
+    out.push_str(r#"{"data":["#);
+    for i in 0..n {
+        if i > 0 {
+            out.push(',');
+        }
+        let _ = write!(out, r#"{{"email":"user{}@example.com","id":{},"metadata":{{"created":"#, i, i);
+        let score = match rng.as_deref_mut() {
+            None => {
+                let _ = write!(out, r#""2024-01-01","updated":"2024-01-02","version":{}}},"name":"User {}","score":"#, i % 10, i);
+                i as f64 * 0.1
+            }
+            Some(r) => {
+                let (month, day) = (r.range(1, 13), r.range(1, 29));
+                let _ = write!(
+                    out,
+                    r#""2024-{:02}-{:02}","updated":"2024-{:02}-{:02}","version":{}}},"name":"{} {}","score":"#,
+                    month,
+                    day,
+                    month,
+                    day + r.range(0, 28 - day + 1),
+                    r.range(0, 10),
+                    pick(r, &FIRST_NAMES),
+                    pick(r, &LAST_NAMES)
+                );
+                r.next_f64() * 1000.0
+            }
+        };
+        push_f64(out, score);
+        out.push_str(r#","tags":"#);
+        match rng.as_deref_mut() {
+            None => push_strings(out, TAGS[..4].iter().copied()),
+            Some(r) => push_strings(out, random_tags(r, &TAGS[..4], 4)),
+        }
+        match rng.as_deref_mut() {
+            None => {
+                let _ = write!(out, r#","uuid":"550e8400-e29b-41d4-a716-446655440{:03}"}}"#, i % 1000);
+            }
+            Some(r) => {
+                let (a, b) = (r.next_u64(), r.next_u64());
+                let _ = write!(
+                    out,
+                    r#","uuid":"{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}"}}"#,
+                    a >> 32,
+                    (a >> 16) & 0xffff,
+                    a & 0xfff,
+                    0x8000 | (b >> 48) & 0x3fff,
+                    b & 0xffff_ffff_ffff
+                );
+            }
+        }
+    }
+    out.push_str("]}");
+}
+
+fn deep(out: &mut String, depth: usize) {
+    for _ in 0..depth {
+        out.push_str(r#"{"child":"#);
+    }
+    out.push_str("null");
+    for level in (0..depth).rev() {
+        let _ = write!(out, r#","level":{}}}"#, level);
+    }
+}
+
+fn wide(out: &mut String, n: usize, mut rng: Option<&mut SmallRng>) {
+    out.push('[');
+    for i in 0..n {
+        if i > 0 {
+            out.push(',');
+        }
+        let v = match rng.as_deref_mut() {
+            None => i as i64,
+            Some(r) => r.range(-1_000_000_000, 1_000_000_000),
+        };
+        let _ = write!(out, "{}", v);
+    }
+    out.push(']');
+}
+
+fn string_heavy(out: &mut String, n: usize, mut rng: Option<&mut SmallRng>) {
+    let words: Vec<&str> = LOREM.split(' ').collect();
+    out.push_str(r#"{"strings":["#);
+    for i in 0..n {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push('"');
+        match rng.as_deref_mut() {
+            None => out.push_str(LOREM),
+            Some(r) => {
+                for w in 0..r.range(8, 40) {
+                    if w > 0 {
+                        out.push(' ');
+                    }
+                    out.push_str(pick(r, &words));
+                }
+            }
+        }
+        let _ = write!(out, " Item {}\"", i);
+    }
+    out.push_str("]}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_are_valid_json_and_seeded() {
+        for kind in Kind::ALL {
+            for seed in [0, 1, 2] {
+                let doc = generate(kind, 50, seed);
+                serde_json::from_str::<serde_json::Value>(&doc).unwrap_or_else(|e| panic!("{} seed {}: {}", kind.name(), seed, e));
+                assert_eq!(doc, generate(kind, 50, seed));
+            }
+            if kind != Kind::Deep {
+                assert_ne!(generate(kind, 50, 1), generate(kind, 50, 2), "{} ignores the seed", kind.name());
+            }
+            assert_eq!(Kind::from_name(kind.name()), Some(kind));
+        }
+    }
+
+    #[test]
+    fn seed_zero_matches_serde_json() {
+        use serde_json::{json, Value};
+
+        let medium: Vec<Value> = (0..20)
+            .map(|i| json!({"id": i, "name": format!("Item {}", i), "price": i as f64 * 1.5, "active": i % 2 == 0, "tags": ["tag1", "tag2", "tag3"]}))
+            .collect();
+        assert_eq!(generate(Kind::Medium, 20, 0), json!({ "items": medium }).to_string());
+
+        let large: Vec<Value> = (0..20)
+            .map(|i| {
+                json!({
+                    "id": i,
+                    "uuid": format!("550e8400-e29b-41d4-a716-446655440{:03}", i % 1000),
+                    "name": format!("User {}", i),
+                    "email": format!("user{}@example.com", i),
+                    "score": i as f64 * 0.1,
+                    "metadata": {"created": "2024-01-01", "updated": "2024-01-02", "version": i % 10},
+                    "tags": ["alpha", "beta", "gamma", "delta"]
+                })
+            })
+            .collect();
+        assert_eq!(generate(Kind::Large, 20, 0), json!({ "data": large }).to_string());
+
+        let mut nested = json!(null);
+        for i in (0..5).rev() {
+            nested = json!({"level": i, "child": nested});
+        }
+        assert_eq!(generate(Kind::Deep, 5, 0), nested.to_string());
+        assert_eq!(generate(Kind::Wide, 5, 0), "[0,1,2,3,4]");
+    }
+}
@@ -0,0 +1,147 @@
+// Criterion Results
+//
+// The cargo benches (`benches/`) use criterion, which writes one directory
+// per benchmark under `target/criterion`, each with a `new/` subdirectory
+// holding the latest run:
+//
+//   benchmark.json  ids (`full_id` is `group/function/parameter`)
+//   estimates.json  mean, median, slope, ... with confidence intervals
+//   sample.json     iteration counts and total times of each sample
+//
+// `load` turns such a tree into `BenchResult`s of the unified JSON schema
+// (see `report`): iterations and total time are the sums over the samples,
+// and the time per operation is criterion's headline estimate - the slope
+// for linear sampling, the mean for flat sampling - with its confidence
+// interval.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::report::{BenchResult, Estimate};
+
+/// Load every benchmark below `dir`, sorted by id.
+pub fn load(dir: &Path) -> io::Result<Vec<BenchResult>> {
+    let mut runs = Vec::new();
+    find_runs(dir, &mut runs)?;
+    let mut results = runs.iter().map(|run| load_run(run)).collect::<io::Result<Vec<_>>>()?;
+    results.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(results)
+}
+
+/// Collect the `new/` directories of `dir`'s benchmarks. Criterion's own
+/// `report/` and `base/` directories never contain benchmarks.
+fn find_runs(dir: &Path, runs: &mut Vec<PathBuf>) -> io::Result<()> {
+    let latest = dir.join("new");
+    if latest.join("estimates.json").is_file() {
+        runs.push(latest);
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if path.is_dir() && !matches!(name, "report" | "base" | "change") {
+            find_runs(&path, runs)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_json(path: &Path) -> io::Result<Value> {
+    let text = fs::read_to_string(path)?;
+    serde_json::from_str(&text)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+}
+
+fn invalid(path: &Path, what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), what))
+}
+
+fn load_run(run: &Path) -> io::Result<BenchResult> {
+    let id_path = run.join("benchmark.json");
+    let name = read_json(&id_path)?["full_id"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| invalid(&id_path, "missing full_id"))?;
+
+    let sample_path = run.join("sample.json");
+    let sample = read_json(&sample_path)?;
+    let sum = |key: &str| -> io::Result<f64> {
+        let values = sample[key].as_array().ok_or_else(|| invalid(&sample_path, &format!("missing {}", key)))?;
+        Ok(values.iter().filter_map(Value::as_f64).sum())
+    };
+    let mut result = BenchResult::new(&name, sum("iters")? as u64, sum("times")? as u64);
+
+    let estimates_path = run.join("estimates.json");
+    let estimates = read_json(&estimates_path)?;
+    let (statistic, estimate) = ["slope", "mean"]
+        .into_iter()
+        .find_map(|s| parse_estimate(&estimates[s]).map(|e| (s, e)))
+        .ok_or_else(|| invalid(&estimates_path, "no slope or mean estimate"))?;
+    result.estimate = Some(estimate);
+    result.notes = Some(format!(
+        "criterion {}, {} samples",
+        statistic,
+        sample["iters"].as_array().map_or(0, Vec::len)
+    ));
+    Ok(result)
+}
+
+fn parse_estimate(value: &Value) -> Option<Estimate> {
+    let ci = &value["confidence_interval"];
+    Some(Estimate {
+        point_ns: value["point_estimate"].as_f64()?,
+        lower_ns: ci["lower_bound"].as_f64()?,
+        upper_ns: ci["upper_bound"].as_f64()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, file: &str, contents: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(file), contents).unwrap();
+    }
+
+    #[test]
+    fn loads_slope_and_flat_mean_estimates() {
+        let root = std::env::temp_dir().join(format!("tml-criterion-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let estimate = |point: f64| {
+            format!(
+                "{{\"point_estimate\":{},\"confidence_interval\":{{\"lower_bound\":{},\"upper_bound\":{}}}}}",
+                point,
+                point - 1.0,
+                point + 1.0
+            )
+        };
+
+        let linear = root.join("fib").join("new");
+        write(&linear, "benchmark.json", "{\"full_id\":\"fib\"}");
+        write(&linear, "sample.json", "{\"sampling_mode\":\"Linear\",\"iters\":[10.0,20.0],\"times\":[1000.0,2200.0]}");
+        write(&linear, "estimates.json", &format!("{{\"mean\":{},\"slope\":{}}}", estimate(106.0), estimate(110.0)));
+        write(&root.join("fib").join("report"), "index.html", "");
+
+        let flat = root.join("sort").join("1000").join("new");
+        write(&flat, "benchmark.json", "{\"full_id\":\"sort/1000\"}");
+        write(&flat, "sample.json", "{\"sampling_mode\":\"Flat\",\"iters\":[4.0,4.0],\"times\":[8000.0,8400.0]}");
+        write(&flat, "estimates.json", &format!("{{\"mean\":{},\"slope\":null}}", estimate(2050.0)));
+
+        let results = load(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let names: Vec<_> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["fib", "sort/1000"]);
+        let (fib, sort) = (&results[0], &results[1]);
+        assert_eq!((fib.iterations, fib.total_ns), (30, 3200));
+        assert_eq!((fib.per_op_ns(), fib.ops_per_sec()), (110, 9_090_909));
+        assert_eq!(fib.notes.as_deref(), Some("criterion slope, 2 samples"));
+        assert_eq!(sort.per_op_ns(), 2050);
+        assert_eq!(sort.estimate.map(|e| (e.lower_ns, e.upper_ns)), Some((2049.0, 2051.0)));
+        assert_eq!(sort.notes.as_deref(), Some("criterion mean, 2 samples"));
+    }
+}
@@ -0,0 +1,249 @@
+// Run Settings and Environment Capture
+//
+// Opt-in knobs that make noisy benchmarks (networking, concurrency) more
+// repeatable, read from environment variables so scripts can set them
+// without touching each benchmark's arguments:
+//
+//   BENCH_PIN_CORE=<n>     pin the calling thread to logical core n
+//                          (Linux, Windows; threads started afterwards
+//                          inherit the mask on Linux)
+//   BENCH_HIGH_PRIORITY=1  raise the process priority (nice -10 on Unix,
+//                          which needs privileges; HIGH_PRIORITY_CLASS on
+//                          Windows)
+//
+// `Environment::capture` records what the run looked like: CPU model,
+// core count, frequency governor, OS, the Rust build settings and whether
+// the knobs above took effect, so results from different machines can be
+// compared with the TML ones.
+
+use std::fmt::Write;
+
+/// Settings applied by `apply_run_settings`.
+#[derive(Debug, Clone, Default)]
+pub struct RunSettings {
+    /// Core the calling thread is pinned to
+    pub pinned_core: Option<usize>,
+    /// Whether the process priority was raised
+    pub high_priority: bool,
+    /// Settings that were requested but could not be applied
+    pub errors: Vec<String>,
+}
+
+/// Apply `BENCH_PIN_CORE` and `BENCH_HIGH_PRIORITY`. Failures are printed
+/// and recorded in `errors` rather than aborting the benchmark. Call it
+/// before starting threads or an async runtime.
+pub fn apply_run_settings() -> RunSettings {
+    let mut settings = RunSettings::default();
+    if let Ok(core) = std::env::var("BENCH_PIN_CORE") {
+        match core.trim().parse::<usize>() {
+            Ok(core) => match sys::pin_current_thread(core) {
+                Ok(()) => settings.pinned_core = Some(core),
+                Err(e) => settings.errors.push(format!("pin to core {}: {}", core, e)),
+            },
+            Err(_) => settings.errors.push(format!("BENCH_PIN_CORE: not a core index: {}", core)),
+        }
+    }
+    if std::env::var("BENCH_HIGH_PRIORITY").is_ok_and(|v| v == "1") {
+        match sys::raise_priority() {
+            Ok(()) => settings.high_priority = true,
+            Err(e) => settings.errors.push(format!("raise priority: {}", e)),
+        }
+    }
+    for e in &settings.errors {
+        eprintln!("  warning: {}", e);
+    }
+    settings
+}
+
+/// Machine and build description stored with each JSON result.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    pub cpu_model: String,
+    pub logical_cores: usize,
+    /// cpufreq governor of core 0 (Linux only)
+    pub governor: Option<String>,
+    pub os: String,
+    pub arch: String,
+    /// `rustc -V` of the build (cargo builds only)
+    pub rustc: Option<String>,
+    /// Cargo profile and RUSTFLAGS of the build (cargo builds only)
+    pub profile: Option<String>,
+    pub rustflags: Option<String>,
+    pub debug_assertions: bool,
+    pub settings: RunSettings,
+}
+
+impl Environment {
+    pub fn capture(settings: &RunSettings) -> Self {
+        Self {
+            cpu_model: sys::cpu_model().unwrap_or_else(|| "unknown".to_string()),
+            logical_cores: std::thread::available_parallelism().map_or(0, |n| n.get()),
+            governor: sys::governor(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            rustc: option_env!("BENCH_RUSTC_VERSION").map(String::from),
+            profile: option_env!("BENCH_PROFILE").map(String::from),
+            rustflags: option_env!("BENCH_RUSTFLAGS").map(String::from),
+            debug_assertions: cfg!(debug_assertions),
+            settings: settings.clone(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let opt = |v: &Option<String>| v.as_deref().map_or("null".to_string(), json_string);
+        let errors: Vec<String> = self.settings.errors.iter().map(|e| json_string(e)).collect();
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "{{\"cpu_model\":{},\"logical_cores\":{},\"governor\":{},\"os\":{},\"arch\":{},\
+             \"rustc\":{},\"profile\":{},\"rustflags\":{},\"debug_assertions\":{},\
+             \"pinned_core\":{},\"high_priority\":{},\"setting_errors\":[{}]}}",
+            json_string(&self.cpu_model),
+            self.logical_cores,
+            opt(&self.governor),
+            json_string(&self.os),
+            json_string(&self.arch),
+            opt(&self.rustc),
+            opt(&self.profile),
+            opt(&self.rustflags),
+            self.debug_assertions,
+            self.settings.pinned_core.map_or("null".to_string(), |c| c.to_string()),
+            self.settings.high_priority,
+            errors.join(",")
+        );
+        out
+    }
+}
+
+/// `s` as a JSON string literal.
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    extern "C" {
+        fn sched_setaffinity(pid: i32, cpusetsize: usize, mask: *const u64) -> i32;
+        fn setpriority(which: i32, who: u32, prio: i32) -> i32;
+    }
+
+    pub fn pin_current_thread(core: usize) -> Result<(), String> {
+        let mut mask = [0u64; 16]; // cpu_set_t: 1024 cores
+        let word = mask.get_mut(core / 64).ok_or("core index out of range")?;
+        *word = 1 << (core % 64);
+        if unsafe { sched_setaffinity(0, std::mem::size_of_val(&mask), mask.as_ptr()) } != 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
+    pub fn raise_priority() -> Result<(), String> {
+        // PRIO_PROCESS of the calling process
+        if unsafe { setpriority(0, 0, -10) } != 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
+    pub fn cpu_model() -> Option<String> {
+        let info = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+        info.lines()
+            .find(|l| l.starts_with("model name"))
+            .and_then(|l| l.split_once(':'))
+            .map(|(_, v)| v.trim().to_string())
+    }
+
+    pub fn governor() -> Option<String> {
+        std::fs::read_to_string("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor")
+            .ok()
+            .map(|g| g.trim().to_string())
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCurrentThread() -> isize;
+        fn GetCurrentProcess() -> isize;
+        fn SetThreadAffinityMask(thread: isize, mask: usize) -> usize;
+        fn SetPriorityClass(process: isize, class: u32) -> i32;
+    }
+
+    const HIGH_PRIORITY_CLASS: u32 = 0x80;
+
+    pub fn pin_current_thread(core: usize) -> Result<(), String> {
+        if core >= usize::BITS as usize {
+            return Err("core index out of range".to_string());
+        }
+        if unsafe { SetThreadAffinityMask(GetCurrentThread(), 1 << core) } == 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
+    pub fn raise_priority() -> Result<(), String> {
+        if unsafe { SetPriorityClass(GetCurrentProcess(), HIGH_PRIORITY_CLASS) } == 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
+    pub fn cpu_model() -> Option<String> {
+        std::env::var("PROCESSOR_IDENTIFIER").ok()
+    }
+
+    pub fn governor() -> Option<String> {
+        None
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod sys {
+    pub fn pin_current_thread(_core: usize) -> Result<(), String> {
+        Err("not supported on this OS".to_string())
+    }
+
+    #[cfg(unix)]
+    pub fn raise_priority() -> Result<(), String> {
+        extern "C" {
+            fn setpriority(which: i32, who: u32, prio: i32) -> i32;
+        }
+        if unsafe { setpriority(0, 0, -10) } != 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn raise_priority() -> Result<(), String> {
+        Err("not supported on this OS".to_string())
+    }
+
+    pub fn cpu_model() -> Option<String> {
+        let out = std::process::Command::new("sysctl")
+            .args(["-n", "machdep.cpu.brand_string"])
+            .output()
+            .ok()?;
+        let model = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        (!model.is_empty()).then_some(model)
+    }
+
+    pub fn governor() -> Option<String> {
+        None
+    }
+}
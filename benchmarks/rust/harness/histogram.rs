@@ -0,0 +1,191 @@
+// Latency Histogram
+//
+// HDR-style histogram of nanosecond latencies: values below 128 ns are
+// counted exactly, larger ones in 128 linear sub-buckets per power of two,
+// so every reported percentile is within 1/128 (< 0.8%) of the recorded
+// value. Memory grows with the largest value (about 1 KiB per power of
+// two), never with the number of samples.
+//
+// Record one sample per operation, then report p50/p99/p999 next to the
+// mean; histograms from several threads or runs combine with `merge`.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+const SUB_BUCKET_BITS: u32 = 7;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    total: u64,
+    sum_ns: u128,
+    min_ns: u64,
+    max_ns: u64,
+}
+
+/// Bucket holding `ns`.
+fn bucket_of(ns: u64) -> usize {
+    if ns < SUB_BUCKETS as u64 {
+        return ns as usize;
+    }
+    let shift = 63 - ns.leading_zeros() - SUB_BUCKET_BITS;
+    let sub = (ns >> shift) as usize - SUB_BUCKETS;
+    (shift as usize + 1) * SUB_BUCKETS + sub
+}
+
+/// Largest value that falls into `bucket`.
+fn bucket_high(bucket: usize) -> u64 {
+    if bucket < SUB_BUCKETS {
+        return bucket as u64;
+    }
+    let shift = (bucket / SUB_BUCKETS - 1) as u32;
+    let sub = (bucket % SUB_BUCKETS) as u64;
+    ((SUB_BUCKETS as u64 + sub + 1) << shift) - 1
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one latency in nanoseconds.
+    pub fn record_ns(&mut self, ns: u64) {
+        let bucket = bucket_of(ns);
+        if bucket >= self.counts.len() {
+            self.counts.resize(bucket + 1, 0);
+        }
+        self.counts[bucket] += 1;
+        if self.total == 0 || ns < self.min_ns {
+            self.min_ns = ns;
+        }
+        self.max_ns = self.max_ns.max(ns);
+        self.total += 1;
+        self.sum_ns += ns as u128;
+    }
+
+    /// Record one latency.
+    pub fn record(&mut self, latency: Duration) {
+        self.record_ns(latency.as_nanos().min(u64::MAX as u128) as u64);
+    }
+
+    /// Add every sample of `other`.
+    pub fn merge(&mut self, other: &Histogram) {
+        if other.total == 0 {
+            return;
+        }
+        if other.counts.len() > self.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (count, added) in self.counts.iter_mut().zip(&other.counts) {
+            *count += added;
+        }
+        if self.total == 0 || other.min_ns < self.min_ns {
+            self.min_ns = other.min_ns;
+        }
+        self.max_ns = self.max_ns.max(other.max_ns);
+        self.total += other.total;
+        self.sum_ns += other.sum_ns;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.total
+    }
+
+    pub fn min_ns(&self) -> u64 {
+        self.min_ns
+    }
+
+    pub fn max_ns(&self) -> u64 {
+        self.max_ns
+    }
+
+    pub fn mean_ns(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.sum_ns as f64 / self.total as f64
+    }
+
+    /// Latency at or below which `p` percent of the samples fall (0-100),
+    /// reported as the top of its bucket and capped at the maximum. 0 when
+    /// empty.
+    pub fn percentile(&self, p: f64) -> u64 {
+        if self.total == 0 {
+            return 0;
+        }
+        let rank = ((p.clamp(0.0, 100.0) / 100.0) * self.total as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bucket, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return bucket_high(bucket).clamp(self.min_ns, self.max_ns);
+            }
+        }
+        self.max_ns
+    }
+
+    /// Indented `p50/p90/p99/p999/max` lines in the benchmarks' text format.
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        for (label, p) in [("p50", 50.0), ("p90", 90.0), ("p99", 99.0), ("p999", 99.9)] {
+            let _ = writeln!(out, "    {:<12}{} ns", format!("{}:", label), self.percentile(p));
+        }
+        let _ = writeln!(out, "    {:<12}{} ns", "max:", self.max_ns);
+        out
+    }
+
+    /// The summary as a JSON object for the unified result files.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"count\":{},\"min_ns\":{},\"mean_ns\":{:.1},\"p50_ns\":{},\"p90_ns\":{},\
+             \"p99_ns\":{},\"p999_ns\":{},\"max_ns\":{}}}",
+            self.total,
+            self.min_ns,
+            self.mean_ns(),
+            self.percentile(50.0),
+            self.percentile(90.0),
+            self.percentile(99.0),
+            self.percentile(99.9),
+            self.max_ns
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_stay_within_bucket_precision() {
+        let mut h = Histogram::new();
+        for ns in 1..=100_000 {
+            h.record_ns(ns);
+        }
+        assert_eq!(h.count(), 100_000);
+        assert_eq!((h.min_ns(), h.max_ns()), (1, 100_000));
+        for (p, exact) in [(50.0, 50_000.0), (99.0, 99_000.0), (99.9, 99_900.0)] {
+            let got = h.percentile(p) as f64;
+            assert!((got - exact).abs() / exact < 1.0 / 128.0, "p{}: {}", p, got);
+        }
+        assert_eq!(h.percentile(100.0), 100_000);
+        assert_eq!(h.percentile(0.0), 1);
+    }
+
+    #[test]
+    fn merge_matches_recording_everything_in_one() {
+        let (mut a, mut b, mut all) = (Histogram::new(), Histogram::new(), Histogram::new());
+        for ns in [5, 90, 1_000, 70_000] {
+            a.record_ns(ns);
+            all.record_ns(ns);
+        }
+        for ns in [2, 300, 9_999_999] {
+            b.record_ns(ns);
+            all.record_ns(ns);
+        }
+        a.merge(&b);
+        a.merge(&Histogram::new());
+        assert_eq!(a.to_json(), all.to_json());
+        assert_eq!((a.min_ns(), a.max_ns()), (2, 9_999_999));
+    }
+}
@@ -0,0 +1,18 @@
+// Shared Benchmark Harness - Rust
+//
+// Utilities shared by the Rust benchmarks (cargo targets link this crate's
+// library). Standalone files built with plain `rustc` include a module
+// directly instead, under the same name (`report` refers to `crate::env`
+// and `crate::histogram`, `corpus` to `crate::rng`), e.g.:
+//
+//     #[path = "harness/histogram.rs"]
+//     #[allow(dead_code)]
+//     mod histogram;
+
+// `criterion` needs serde_json and is only available to cargo targets.
+pub mod corpus;
+pub mod criterion;
+pub mod env;
+pub mod histogram;
+pub mod report;
+pub mod rng;
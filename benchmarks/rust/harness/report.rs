@@ -0,0 +1,117 @@
+// JSON Results
+//
+// Writes results in the schema of the C++ harness (`common/bench.hpp`):
+// `{"category", "language", "results": [{"name", "iterations", "total_ns",
+// "per_op_ns", "ops_per_sec", "notes"?}]}`, plus an `environment` object
+// (see `env`), a `latency` object per result when a histogram was kept and
+// `ci_ns` (`[lower, upper]`) when the time per operation is a statistical
+// estimate rather than `total_ns / iterations`.
+//
+// A benchmark saves its report when `BENCH_JSON=<path>` is set.
+
+use std::fmt::Write;
+
+use crate::env::{json_string, Environment};
+use crate::histogram::Histogram;
+
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub name: String,
+    pub iterations: u64,
+    pub total_ns: u64,
+    pub notes: Option<String>,
+    pub latency: Option<Histogram>,
+    /// Estimated time per operation, overriding `total_ns / iterations`
+    pub estimate: Option<Estimate>,
+}
+
+/// A time-per-operation estimate with its confidence interval, in ns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub point_ns: f64,
+    pub lower_ns: f64,
+    pub upper_ns: f64,
+}
+
+impl BenchResult {
+    pub fn new(name: &str, iterations: u64, total_ns: u64) -> Self {
+        Self { name: name.to_string(), iterations, total_ns, notes: None, latency: None, estimate: None }
+    }
+
+    pub fn per_op_ns(&self) -> u64 {
+        if let Some(estimate) = &self.estimate {
+            return estimate.point_ns.round() as u64;
+        }
+        if self.iterations == 0 {
+            return 0;
+        }
+        self.total_ns / self.iterations
+    }
+
+    pub fn ops_per_sec(&self) -> u64 {
+        if let Some(estimate) = &self.estimate {
+            return if estimate.point_ns > 0.0 { (1e9 / estimate.point_ns) as u64 } else { 0 };
+        }
+        if self.total_ns == 0 {
+            return 0;
+        }
+        (self.iterations as u128 * 1_000_000_000 / self.total_ns as u128) as u64
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub category: String,
+    pub environment: Environment,
+    pub results: Vec<BenchResult>,
+}
+
+impl Report {
+    pub fn new(category: &str, environment: Environment) -> Self {
+        Self { category: category.to_string(), environment, results: Vec::new() }
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{{");
+        let _ = writeln!(out, "  \"category\": {},", json_string(&self.category));
+        let _ = writeln!(out, "  \"language\": \"rust\",");
+        let _ = writeln!(out, "  \"environment\": {},", self.environment.to_json());
+        let _ = writeln!(out, "  \"results\": [");
+        for (i, r) in self.results.iter().enumerate() {
+            let _ = write!(
+                out,
+                "    {{\"name\": {}, \"iterations\": {}, \"total_ns\": {}, \"per_op_ns\": {}, \"ops_per_sec\": {}",
+                json_string(&r.name),
+                r.iterations,
+                r.total_ns,
+                r.per_op_ns(),
+                r.ops_per_sec()
+            );
+            if let Some(notes) = &r.notes {
+                let _ = write!(out, ", \"notes\": {}", json_string(notes));
+            }
+            if let Some(e) = &r.estimate {
+                let _ = write!(out, ", \"ci_ns\": [{:.1}, {:.1}]", e.lower_ns, e.upper_ns);
+            }
+            if let Some(latency) = &r.latency {
+                let _ = write!(out, ", \"latency\": {}", latency.to_json());
+            }
+            let _ = writeln!(out, "}}{}", if i + 1 < self.results.len() { "," } else { "" });
+        }
+        let _ = writeln!(out, "  ]");
+        let _ = writeln!(out, "}}");
+        out
+    }
+
+    /// Write the report to `BENCH_JSON`, if set.
+    pub fn save_if_requested(&self) {
+        let Ok(path) = std::env::var("BENCH_JSON") else {
+            return;
+        };
+        match std::fs::write(&path, self.to_json()) {
+            Ok(()) => println!("  JSON saved to {}", path),
+            Err(e) => eprintln!("  could not write {}: {}", path, e),
+        }
+    }
+}
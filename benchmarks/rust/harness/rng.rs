@@ -0,0 +1,57 @@
+// Seeded Random Numbers
+//
+// Reproducible benchmark inputs, drawn from the `rand` crate. `seeded(seed)`
+// gives rand's SmallRng through `seed_from_u64`, so a seed names the same
+// inputs on every run and machine of one rand version. The `Sample` helpers
+// are the draws the input generators share; rng_bench measures rand's
+// generators themselves.
+
+pub use rand::rngs::SmallRng;
+pub use rand::RngCore;
+use rand::{Rng, SeedableRng};
+
+/// Generator used for benchmark inputs.
+pub fn seeded(seed: u64) -> SmallRng {
+    SmallRng::seed_from_u64(seed)
+}
+
+/// Draws shared by the input generators, on any rand generator.
+pub trait Sample: RngCore {
+    /// Uniform in `[0, bound)`; `bound` must be non-zero.
+    fn below(&mut self, bound: u64) -> u64 {
+        self.gen_range(0..bound)
+    }
+
+    /// Uniform in `[low, high)`.
+    fn range(&mut self, low: i64, high: i64) -> i64 {
+        self.gen_range(low..high)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        self.gen()
+    }
+}
+
+impl<R: RngCore + ?Sized> Sample for R {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampling_is_in_range_and_seeded() {
+        let mut a = seeded(7);
+        let mut b = seeded(7);
+        for _ in 0..1000 {
+            let x = a.range(-5, 5);
+            assert!((-5..5).contains(&x));
+            assert_eq!(x, b.range(-5, 5));
+            let f = a.next_f64();
+            assert!((0.0..1.0).contains(&f));
+            assert_eq!(f, b.next_f64());
+            assert!(a.below(3) < 3);
+            b.below(3);
+        }
+    }
+}
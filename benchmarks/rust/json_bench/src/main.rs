@@ -4,9 +4,21 @@
 //!
 //! Run with: cargo run --release
 
+use serde::Deserialize;
+use serde_json::value::RawValue;
 use serde_json::{json, Value};
+use std::borrow::Cow;
 use std::time::Instant;
 
+#[path = "../../harness/corpus.rs"]
+#[allow(dead_code)]
+mod corpus;
+#[path = "../../harness/rng.rs"]
+#[allow(dead_code)]
+mod rng;
+
+use corpus::Kind;
+
 // ============================================================================
 // Benchmark Infrastructure
 // ============================================================================
@@ -63,84 +75,161 @@ fn print_separator() {
 }
 
 // ============================================================================
-// Test Data Generation
+// Test Data
 // ============================================================================
 
-fn generate_small_json() -> String {
-    json!({
-        "name": "John Doe",
-        "age": 30,
-        "active": true,
-        "email": "john@example.com",
-        "scores": [95, 87, 92, 88, 91],
-        "address": {
-            "street": "123 Main St",
-            "city": "New York",
-            "zip": "10001"
-        }
-    })
-    .to_string()
+/// A shared corpus document (`harness/corpus.rs`) at json_bench's size, for
+/// the seed in `BENCH_CORPUS_SEED`.
+fn document(kind: Kind) -> String {
+    corpus::load_default(kind, corpus::seed())
 }
 
-fn generate_medium_json(num_items: usize) -> String {
-    let items: Vec<Value> = (0..num_items)
-        .map(|i| {
-            json!({
-                "id": i,
-                "name": format!("Item {}", i),
-                "price": i as f64 * 1.5,
-                "active": i % 2 == 0,
-                "tags": ["tag1", "tag2", "tag3"]
-            })
-        })
-        .collect();
-    json!({ "items": items }).to_string()
+// ============================================================================
+// Typed Records: Owned vs Borrowed
+// ============================================================================
+
+// Owned and borrowed mirrors of the string-heavy and large documents. With
+// `#[serde(borrow)]`, a `Cow<str>` points into the input unless the string
+// has escapes; the RawValue records keep `metadata` as unparsed JSON text.
+// Record fields are parsed for the cost of it and otherwise unused.
+
+/// Borrowing string for sequences: serde only borrows a `Cow` directly
+/// under a `#[serde(borrow)]` field, so `Vec<Cow<str>>` would always copy.
+#[derive(Deserialize)]
+struct Str<'a>(#[serde(borrow)] Cow<'a, str>);
+
+#[derive(Deserialize)]
+struct OwnedStrings {
+    strings: Vec<String>,
 }
 
-fn generate_large_json(num_items: usize) -> String {
-    let data: Vec<Value> = (0..num_items)
-        .map(|i| {
-            json!({
-                "id": i,
-                "uuid": format!("550e8400-e29b-41d4-a716-446655440{:03}", i % 1000),
-                "name": format!("User {}", i),
-                "email": format!("user{}@example.com", i),
-                "score": i as f64 * 0.1,
-                "metadata": {
-                    "created": "2024-01-01",
-                    "updated": "2024-01-02",
-                    "version": i % 10
-                },
-                "tags": ["alpha", "beta", "gamma", "delta"]
-            })
-        })
-        .collect();
-    json!({ "data": data }).to_string()
+#[derive(Deserialize)]
+struct BorrowedStrings<'a> {
+    #[serde(borrow)]
+    strings: Vec<Str<'a>>,
 }
 
-fn generate_deep_json(depth: usize) -> String {
-    let mut result = json!(null);
-    for i in (0..depth).rev() {
-        result = json!({
-            "level": i,
-            "child": result
-        });
-    }
-    result.to_string()
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct OwnedMetadata {
+    created: String,
+    updated: String,
+    version: u32,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct OwnedRecord {
+    id: u64,
+    uuid: String,
+    name: String,
+    email: String,
+    score: f64,
+    metadata: OwnedMetadata,
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct OwnedDocument {
+    data: Vec<OwnedRecord>,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct BorrowedMetadata<'a> {
+    #[serde(borrow)]
+    created: Cow<'a, str>,
+    #[serde(borrow)]
+    updated: Cow<'a, str>,
+    version: u32,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct BorrowedRecord<'a> {
+    id: u64,
+    #[serde(borrow)]
+    uuid: Cow<'a, str>,
+    #[serde(borrow)]
+    name: Cow<'a, str>,
+    #[serde(borrow)]
+    email: Cow<'a, str>,
+    score: f64,
+    #[serde(borrow)]
+    metadata: BorrowedMetadata<'a>,
+    #[serde(borrow)]
+    tags: Vec<Str<'a>>,
+}
+
+#[derive(Deserialize)]
+struct BorrowedDocument<'a> {
+    #[serde(borrow)]
+    data: Vec<BorrowedRecord<'a>>,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct RawRecord<'a> {
+    id: u64,
+    name: &'a str,
+    #[serde(borrow)]
+    metadata: &'a RawValue,
 }
 
-fn generate_wide_array(size: usize) -> String {
-    let arr: Vec<usize> = (0..size).collect();
-    serde_json::to_string(&arr).unwrap()
+#[derive(Deserialize)]
+struct RawDocument<'a> {
+    #[serde(borrow)]
+    data: Vec<RawRecord<'a>>,
 }
 
-fn generate_string_heavy_json(num_items: usize) -> String {
-    let strings: Vec<String> = (0..num_items)
-        .map(|i| {
-            format!("Lorem ipsum dolor sit amet, consectetur adipiscing elit. Sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Item {}", i)
-        })
-        .collect();
-    json!({ "strings": strings }).to_string()
+#[derive(Deserialize)]
+struct RawItems<'a> {
+    #[serde(borrow)]
+    data: Vec<&'a RawValue>,
+}
+
+// ============================================================================
+// Document Mutation
+// ============================================================================
+
+/// Edits `items` records of a large corpus document, three operations
+/// each: rename `email` to `contact_email`, insert an `audit` object into
+/// `metadata` and move `metadata.updated` into it. Odd rounds undo the even
+/// ones, so the document alternates between two shapes instead of growing.
+fn mutate_large_document(doc: &mut Value, items: usize, round: usize) {
+    let data = doc["data"].as_array_mut().unwrap();
+    let len = data.len();
+    for k in 0..items {
+        let item = data[k * 7919 % len].as_object_mut().unwrap();
+        if round.is_multiple_of(2) {
+            let email = item.remove("email").unwrap();
+            item.insert("contact_email".to_string(), email);
+            let metadata = item["metadata"].as_object_mut().unwrap();
+            let updated = metadata.remove("updated").unwrap();
+            metadata.insert("audit".to_string(), json!({ "by": "bench", "round": round, "updated": updated }));
+        } else {
+            let email = item.remove("contact_email").unwrap();
+            item.insert("email".to_string(), email);
+            let metadata = item["metadata"].as_object_mut().unwrap();
+            let mut audit = metadata.remove("audit").unwrap();
+            let updated = audit["updated"].take();
+            metadata.insert("updated".to_string(), updated);
+        }
+    }
+}
+
+/// Walks a deep corpus document and, at every level, inserts a `mark`
+/// field on even rounds and removes it on odd ones.
+fn mutate_deep_document(doc: &mut Value, round: usize) {
+    let mut node = doc;
+    while let Some(obj) = node.as_object_mut() {
+        if round.is_multiple_of(2) {
+            obj.insert("mark".to_string(), json!(round));
+        } else {
+            obj.remove("mark");
+        }
+        node = obj.get_mut("child").unwrap();
+    }
 }
 
 // ============================================================================
@@ -154,7 +243,7 @@ fn run_benchmarks() {
     let mut results = Vec::new();
 
     // Small JSON parsing
-    let json_str = generate_small_json();
+    let json_str = document(Kind::Small);
     let r = benchmark("Rust: Parse small JSON", 100000, json_str.len(), || {
         let _: Value = serde_json::from_str(&json_str).unwrap();
     });
@@ -162,7 +251,7 @@ fn run_benchmarks() {
     print_result(results.last().unwrap());
 
     // Medium JSON parsing
-    let json_str = generate_medium_json(1000);
+    let json_str = document(Kind::Medium);
     let r = benchmark("Rust: Parse medium JSON (100KB)", 1000, json_str.len(), || {
         let _: Value = serde_json::from_str(&json_str).unwrap();
     });
@@ -170,7 +259,7 @@ fn run_benchmarks() {
     print_result(results.last().unwrap());
 
     // Large JSON parsing
-    let json_str = generate_large_json(10000);
+    let json_str = document(Kind::Large);
     let r = benchmark("Rust: Parse large JSON (1MB)", 100, json_str.len(), || {
         let _: Value = serde_json::from_str(&json_str).unwrap();
     });
@@ -178,7 +267,7 @@ fn run_benchmarks() {
     print_result(results.last().unwrap());
 
     // Deep nesting
-    let json_str = generate_deep_json(100);
+    let json_str = document(Kind::Deep);
     let r = benchmark(
         "Rust: Parse deep nesting (100 levels)",
         10000,
@@ -191,7 +280,7 @@ fn run_benchmarks() {
     print_result(results.last().unwrap());
 
     // Wide array
-    let json_str = generate_wide_array(10000);
+    let json_str = document(Kind::Wide);
     let r = benchmark(
         "Rust: Parse wide array (10K ints)",
         1000,
@@ -204,7 +293,7 @@ fn run_benchmarks() {
     print_result(results.last().unwrap());
 
     // String-heavy JSON
-    let json_str = generate_string_heavy_json(1000);
+    let json_str = document(Kind::StringHeavy);
     let r = benchmark("Rust: Parse string-heavy JSON", 500, json_str.len(), || {
         let _: Value = serde_json::from_str(&json_str).unwrap();
     });
@@ -214,7 +303,7 @@ fn run_benchmarks() {
     print_separator();
 
     // Serialization benchmarks
-    let json_str = generate_medium_json(1000);
+    let json_str = document(Kind::Medium);
     let obj: Value = serde_json::from_str(&json_str).unwrap();
     let r = benchmark("Rust: Serialize medium JSON", 1000, json_str.len(), || {
         let _ = serde_json::to_string(&obj).unwrap();
@@ -222,7 +311,7 @@ fn run_benchmarks() {
     results.push(r);
     print_result(results.last().unwrap());
 
-    let json_str = generate_large_json(10000);
+    let json_str = document(Kind::Large);
     let obj: Value = serde_json::from_str(&json_str).unwrap();
     let r = benchmark("Rust: Serialize large JSON", 100, json_str.len(), || {
         let _ = serde_json::to_string(&obj).unwrap();
@@ -230,7 +319,7 @@ fn run_benchmarks() {
     results.push(r);
     print_result(results.last().unwrap());
 
-    let json_str = generate_medium_json(1000);
+    let json_str = document(Kind::Medium);
     let obj: Value = serde_json::from_str(&json_str).unwrap();
     let r = benchmark("Rust: Pretty print medium JSON", 500, json_str.len(), || {
         let _ = serde_json::to_string_pretty(&obj).unwrap();
@@ -261,7 +350,7 @@ fn run_benchmarks() {
     print_separator();
 
     // Access patterns
-    let json_str = generate_medium_json(1000);
+    let json_str = document(Kind::Medium);
     let obj: Value = serde_json::from_str(&json_str).unwrap();
     let items = obj.get("items").unwrap().as_array().unwrap();
 
@@ -279,6 +368,117 @@ fn run_benchmarks() {
 
     print_separator();
 
+    // Zero-copy parsing: the same documents as typed owned and borrowed
+    // records, and with parts kept as raw JSON text
+    let json_str = document(Kind::StringHeavy);
+    let bytes = json_str.as_bytes();
+    let borrowed: BorrowedStrings = serde_json::from_slice(bytes).unwrap();
+    assert!(borrowed.strings.iter().all(|s| matches!(s.0, Cow::Borrowed(_))));
+
+    let r = benchmark("Rust: Parse string-heavy (Value)", 500, bytes.len(), || {
+        let _: Value = serde_json::from_slice(bytes).unwrap();
+    });
+    results.push(r);
+    print_result(results.last().unwrap());
+
+    let r = benchmark("Rust: Parse string-heavy (String)", 500, bytes.len(), || {
+        let doc: OwnedStrings = serde_json::from_slice(bytes).unwrap();
+        std::hint::black_box(doc.strings.len());
+    });
+    results.push(r);
+    print_result(results.last().unwrap());
+
+    let r = benchmark("Rust: Parse string-heavy (Cow borrow)", 500, bytes.len(), || {
+        let doc: BorrowedStrings = serde_json::from_slice(bytes).unwrap();
+        std::hint::black_box(doc.strings.len());
+    });
+    results.push(r);
+    print_result(results.last().unwrap());
+
+    let json_str = document(Kind::Large);
+    let bytes = json_str.as_bytes();
+
+    let r = benchmark("Rust: Parse large (owned structs)", 100, bytes.len(), || {
+        let doc: OwnedDocument = serde_json::from_slice(bytes).unwrap();
+        std::hint::black_box(doc.data.len());
+    });
+    results.push(r);
+    print_result(results.last().unwrap());
+
+    let r = benchmark("Rust: Parse large (borrowed structs)", 100, bytes.len(), || {
+        let doc: BorrowedDocument = serde_json::from_slice(bytes).unwrap();
+        std::hint::black_box(doc.data.len());
+    });
+    results.push(r);
+    print_result(results.last().unwrap());
+
+    let r = benchmark("Rust: Parse large (RawValue metadata)", 100, bytes.len(), || {
+        let doc: RawDocument = serde_json::from_slice(bytes).unwrap();
+        std::hint::black_box(doc.data.len());
+    });
+    results.push(r);
+    print_result(results.last().unwrap());
+
+    // Passthrough: split the array into raw items and write them back out
+    // without building a tree
+    let r = benchmark("Rust: RawValue passthrough large", 100, bytes.len(), || {
+        let doc: RawItems = serde_json::from_slice(bytes).unwrap();
+        let _ = serde_json::to_string(&doc.data).unwrap();
+    });
+    results.push(r);
+    print_result(results.last().unwrap());
+
+    let r = benchmark("Rust: Value roundtrip large", 100, bytes.len(), || {
+        let doc: Value = serde_json::from_slice(bytes).unwrap();
+        let _ = serde_json::to_string(&doc["data"]).unwrap();
+    });
+    results.push(r);
+    print_result(results.last().unwrap());
+
+    print_separator();
+
+    // Mutation: parse once, then edit the same document in place
+    let json_str = document(Kind::Large);
+    let mut doc: Value = serde_json::from_str(&json_str).unwrap();
+    let original = doc.clone();
+    mutate_large_document(&mut doc, 1000, 0);
+    assert_ne!(doc, original);
+    mutate_large_document(&mut doc, 1000, 1);
+    assert_eq!(doc, original, "odd rounds must undo even ones");
+
+    let mut round = 0;
+    let r = benchmark("Rust: Mutate large JSON (3000 ops)", 1000, 0, || {
+        mutate_large_document(&mut doc, 1000, round);
+        round += 1;
+    });
+    results.push(r);
+    print_result(results.last().unwrap());
+
+    let r = benchmark(
+        "Rust: Mutate + serialize large JSON",
+        100,
+        json_str.len(),
+        || {
+            mutate_large_document(&mut doc, 1000, round);
+            round += 1;
+            let _ = serde_json::to_string(&doc).unwrap();
+        },
+    );
+    results.push(r);
+    print_result(results.last().unwrap());
+
+    let json_str = document(Kind::Deep);
+    let mut doc: Value = serde_json::from_str(&json_str).unwrap();
+    let mut round = 0;
+    let r = benchmark("Rust: Mutate deep nesting (100 levels)", 10000, 0, || {
+        mutate_deep_document(&mut doc, round);
+        round += 1;
+    });
+    results.push(r);
+    print_result(results.last().unwrap());
+
+    print_separator();
+
     // Summary
     println!("\n=== Summary ===\n");
     let total_time: f64 = results.iter().map(|r| r.time_us).sum();
@@ -291,14 +491,14 @@ fn main() {
 
     // Show test data sizes
     println!("\nTest data sizes:");
-    println!("  Small JSON:  {} bytes", generate_small_json().len());
-    println!("  Medium JSON: {} bytes", generate_medium_json(1000).len());
-    println!("  Large JSON:  {} bytes", generate_large_json(10000).len());
-    println!("  Deep JSON:   {} bytes", generate_deep_json(100).len());
-    println!("  Wide Array:  {} bytes", generate_wide_array(10000).len());
+    println!("  Small JSON:  {} bytes", document(Kind::Small).len());
+    println!("  Medium JSON: {} bytes", document(Kind::Medium).len());
+    println!("  Large JSON:  {} bytes", document(Kind::Large).len());
+    println!("  Deep JSON:   {} bytes", document(Kind::Deep).len());
+    println!("  Wide Array:  {} bytes", document(Kind::Wide).len());
     println!(
         "  String-heavy:{} bytes",
-        generate_string_heavy_json(1000).len()
+        document(Kind::StringHeavy).len()
     );
 
     run_benchmarks();
@@ -0,0 +1,266 @@
+// Data Layout Benchmarks - Rust
+//
+// Input for TML's default struct layout, which the bridge's
+// compute_struct_layout has to mirror. One million particles are stored
+// three ways:
+//
+//   AoS          Vec<Particle>, Rust layout (fields reordered, no padding
+//                beyond the 8-byte alignment of the record)
+//   AoS repr(C)  the same struct in declaration order, as C lays it out;
+//                the fields are declared in an order that pads badly
+//   SoA          one Vec per field
+//
+// and run through kernels touching one field (sum of masses), six fields
+// (position update), every field (energy), and random records (gather by
+// index). The first two stream only what they use in SoA form; the gather
+// touches one cache line per record in AoS form but one per field in SoA.
+//
+// Run with: cargo run --release --bin layout_bench [particles] [rounds]
+
+use std::hint::black_box;
+use std::mem::size_of;
+use std::time::Instant;
+
+use tml_benchmarks::env::{apply_run_settings, Environment};
+use tml_benchmarks::report::{BenchResult, Report};
+use tml_benchmarks::rng::{self, Sample};
+
+const DT: f64 = 0.01;
+
+/// Operations every layout provides, written so that each computes
+/// bit-identical results.
+trait Particles {
+    fn len(&self) -> usize;
+    /// One field
+    fn total_mass(&self) -> f64;
+    /// Position and velocity: six fields read, three written
+    fn integrate(&mut self, dt: f64);
+    /// Every field
+    fn energy(&self) -> f64;
+    /// Whole records at random indices
+    fn gather(&self, indices: &[u32]) -> f64;
+}
+
+macro_rules! aos_particle {
+    ($name:ident $(, #[$attr:meta])?) => {
+        // Declaration order alternates small and 8-byte fields, which
+        // repr(C) pads and the Rust layout packs
+        $(#[$attr])?
+        #[derive(Clone, Copy)]
+        struct $name {
+            alive: bool,
+            x: f64,
+            id: u32,
+            y: f64,
+            kind: u8,
+            z: f64,
+            mass: f32,
+            vx: f64,
+            vy: f64,
+            vz: f64,
+            charge: f32,
+        }
+
+        impl Particles for Vec<$name> {
+            fn len(&self) -> usize {
+                self.as_slice().len()
+            }
+
+            fn total_mass(&self) -> f64 {
+                self.iter().map(|p| p.mass as f64).sum()
+            }
+
+            fn integrate(&mut self, dt: f64) {
+                for p in self.iter_mut() {
+                    p.x += p.vx * dt;
+                    p.y += p.vy * dt;
+                    p.z += p.vz * dt;
+                }
+            }
+
+            fn energy(&self) -> f64 {
+                self.iter()
+                    .map(|p| {
+                        let v2 = p.vx * p.vx + p.vy * p.vy + p.vz * p.vz;
+                        let kinetic = 0.5 * p.mass as f64 * v2;
+                        let potential = p.charge as f64 * (p.x + p.y + p.z) * (p.kind as f64 + 1.0);
+                        if p.alive { kinetic + potential + (p.id & 1) as f64 } else { 0.0 }
+                    })
+                    .sum()
+            }
+
+            fn gather(&self, indices: &[u32]) -> f64 {
+                indices
+                    .iter()
+                    .map(|&i| {
+                        let p = &self[i as usize];
+                        p.x * p.mass as f64 + p.vx * p.charge as f64
+                    })
+                    .sum()
+            }
+        }
+    };
+}
+
+aos_particle!(Particle);
+aos_particle!(ParticleC, #[repr(C)]);
+
+#[derive(Default)]
+struct SoaParticles {
+    alive: Vec<bool>,
+    x: Vec<f64>,
+    id: Vec<u32>,
+    y: Vec<f64>,
+    kind: Vec<u8>,
+    z: Vec<f64>,
+    mass: Vec<f32>,
+    vx: Vec<f64>,
+    vy: Vec<f64>,
+    vz: Vec<f64>,
+    charge: Vec<f32>,
+}
+
+impl Particles for SoaParticles {
+    fn len(&self) -> usize {
+        self.x.len()
+    }
+
+    fn total_mass(&self) -> f64 {
+        self.mass.iter().map(|&m| m as f64).sum()
+    }
+
+    fn integrate(&mut self, dt: f64) {
+        for (x, vx) in self.x.iter_mut().zip(&self.vx) {
+            *x += vx * dt;
+        }
+        for (y, vy) in self.y.iter_mut().zip(&self.vy) {
+            *y += vy * dt;
+        }
+        for (z, vz) in self.z.iter_mut().zip(&self.vz) {
+            *z += vz * dt;
+        }
+    }
+
+    fn energy(&self) -> f64 {
+        (0..self.len())
+            .map(|i| {
+                let (vx, vy, vz) = (self.vx[i], self.vy[i], self.vz[i]);
+                let v2 = vx * vx + vy * vy + vz * vz;
+                let kinetic = 0.5 * self.mass[i] as f64 * v2;
+                let potential = self.charge[i] as f64 * (self.x[i] + self.y[i] + self.z[i]) * (self.kind[i] as f64 + 1.0);
+                if self.alive[i] { kinetic + potential + (self.id[i] & 1) as f64 } else { 0.0 }
+            })
+            .sum()
+    }
+
+    fn gather(&self, indices: &[u32]) -> f64 {
+        indices
+            .iter()
+            .map(|&i| {
+                let i = i as usize;
+                self.x[i] * self.mass[i] as f64 + self.vx[i] * self.charge[i] as f64
+            })
+            .sum()
+    }
+}
+
+/// The same particles in all three layouts.
+fn generate(n: usize, seed: u64) -> (Vec<Particle>, Vec<ParticleC>, SoaParticles) {
+    let mut rng = rng::seeded(seed);
+    let mut aos = Vec::with_capacity(n);
+    let mut aos_c = Vec::with_capacity(n);
+    let mut soa = SoaParticles::default();
+    for i in 0..n {
+        let mut f = || rng.next_f64() * 2.0 - 1.0;
+        let (x, y, z, vx, vy, vz) = (f(), f(), f(), f(), f(), f());
+        let (mass, charge) = (f().abs() as f32 + 0.1, f() as f32);
+        let (alive, id, kind) = (i % 16 != 0, i as u32, (i % 4) as u8);
+        aos.push(Particle { alive, x, id, y, kind, z, mass, vx, vy, vz, charge });
+        aos_c.push(ParticleC { alive, x, id, y, kind, z, mass, vx, vy, vz, charge });
+        soa.alive.push(alive);
+        soa.x.push(x);
+        soa.id.push(id);
+        soa.y.push(y);
+        soa.kind.push(kind);
+        soa.z.push(z);
+        soa.mass.push(mass);
+        soa.vx.push(vx);
+        soa.vy.push(vy);
+        soa.vz.push(vz);
+        soa.charge.push(charge);
+    }
+    (aos, aos_c, soa)
+}
+
+// ============================================================================
+// Benchmarks
+// ============================================================================
+
+fn run(report: &mut Report, name: &str, iterations: u64, f: impl FnOnce() -> f64) -> f64 {
+    let start = Instant::now();
+    let checksum = black_box(f());
+    let ns = start.elapsed().as_nanos() as u64;
+    let r = BenchResult::new(name, iterations, ns);
+    println!("  {:<36} {:>8.3} ns/particle {:>14} particles/sec", name, ns as f64 / iterations as f64, r.ops_per_sec());
+    report.results.push(r);
+    checksum
+}
+
+/// Run `kernel` `rounds` times on each layout and check they agree.
+fn compare(
+    report: &mut Report,
+    kernel: &str,
+    rounds: u64,
+    layouts: (&mut Vec<Particle>, &mut Vec<ParticleC>, &mut SoaParticles),
+    f: impl Fn(&mut dyn Particles) -> f64,
+) {
+    let (aos, aos_c, soa) = layouts;
+    let per_round = aos.len() as u64;
+    let mut each = |name: &str, p: &mut dyn Particles| {
+        run(report, &format!("{} ({})", kernel, name), rounds * per_round, || (0..rounds).map(|_| f(&mut *p)).sum())
+    };
+    let sums = [each("AoS", aos), each("AoS repr(C)", aos_c), each("SoA", soa)];
+    assert!(sums.iter().all(|s| s.to_bits() == sums[0].to_bits()), "{} differs between layouts: {:?}", kernel, sums);
+}
+
+fn main() {
+    let settings = apply_run_settings();
+    let n: usize = std::env::args().nth(1).and_then(|a| a.parse().ok()).unwrap_or(1_000_000);
+    let rounds: u64 = std::env::args().nth(2).and_then(|a| a.parse().ok()).unwrap_or(20);
+    let mut report = Report::new("Layout", Environment::capture(&settings));
+
+    println!("\n================================================================");
+    println!("  Rust Data Layout Benchmarks ({} particles, {} rounds)", n, rounds);
+    println!("================================================================\n");
+    println!(
+        "  Record size: AoS {} B, AoS repr(C) {} B, SoA {} B across 11 arrays",
+        size_of::<Particle>(),
+        size_of::<ParticleC>(),
+        6 * size_of::<f64>() + 2 * size_of::<f32>() + size_of::<u32>() + size_of::<u8>() + size_of::<bool>()
+    );
+
+    let (mut aos, mut aos_c, mut soa) = generate(n, 2757);
+    let mut rng = rng::seeded(27572);
+    let indices: Vec<u32> = (0..n).map(|_| rng.below(n as u64) as u32).collect();
+
+    println!("\n=== One field: sum of masses ===");
+    compare(&mut report, "total_mass", rounds, (&mut aos, &mut aos_c, &mut soa), |p| p.total_mass());
+
+    println!("\n=== Six fields: position update ===");
+    compare(&mut report, "integrate", rounds, (&mut aos, &mut aos_c, &mut soa), |p| {
+        p.integrate(DT);
+        0.0
+    });
+    // The updates must also have produced identical positions
+    let energies = [aos.energy(), aos_c.energy(), soa.energy()];
+    assert!(energies.iter().all(|e| e.to_bits() == energies[0].to_bits()), "positions differ: {:?}", energies);
+
+    println!("\n=== Every field: energy ===");
+    compare(&mut report, "energy", rounds, (&mut aos, &mut aos_c, &mut soa), |p| p.energy());
+
+    println!("\n=== Random records: gather ===");
+    compare(&mut report, "gather", rounds, (&mut aos, &mut aos_c, &mut soa), |p| p.gather(&indices));
+
+    println!("\n================================================================\n");
+    report.save_if_requested();
+}
@@ -0,0 +1,369 @@
+// Process and IPC Benchmarks - Rust
+//
+// Baselines for TML's process/IPC stdlib:
+//
+//   spawn       std::process::Command spawn + wait of a child that exits
+//               immediately (this binary re-run with `--child noop`)
+//   pipe        parent -> child throughput over the child's stdin, and
+//               64-byte ping-pong latency over stdin/stdout
+//   shared mem  ping-pong latency and bulk transfer through a file mapped
+//               MAP_SHARED in both processes (Unix only), synchronized by
+//               atomics in the mapping rather than by the kernel
+//
+// Children are this executable with `--child <mode>`, so nothing else has
+// to be installed.
+//
+// Run with: cargo run --release --bin process_bench [iterations]
+
+use std::io::{BufReader, Read, Write};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+use tml_benchmarks::env::{apply_run_settings, Environment};
+use tml_benchmarks::histogram::Histogram;
+use tml_benchmarks::report::{BenchResult, Report};
+
+const CHUNK: usize = 64 * 1024;
+const PIPE_BYTES: usize = 256 * 1024 * 1024;
+const MESSAGE: usize = 64;
+
+fn child(args: &[String]) {
+    match args.first().map(String::as_str) {
+        Some("noop") => {}
+        Some("sink") => {
+            // Count stdin and report the total, so the parent can check it
+            let mut total = 0usize;
+            let mut buf = vec![0u8; CHUNK];
+            let mut stdin = std::io::stdin().lock();
+            while let Ok(n) = stdin.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                total += n;
+            }
+            println!("{}", total);
+        }
+        Some("echo") => {
+            let mut stdin = std::io::stdin().lock();
+            let mut stdout = std::io::stdout().lock();
+            let mut buf = [0u8; MESSAGE];
+            while stdin.read_exact(&mut buf).is_ok() {
+                if stdout.write_all(&buf).and_then(|_| stdout.flush()).is_err() {
+                    break;
+                }
+            }
+        }
+        #[cfg(unix)]
+        Some("shm") => shm::child(&args[1]),
+        other => {
+            eprintln!("process_bench: unknown child mode {:?}", other);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn self_command(mode: &str) -> Command {
+    let exe = std::env::current_exe().expect("current executable");
+    let mut cmd = Command::new(exe);
+    cmd.args(["--child", mode]);
+    cmd
+}
+
+fn print_result(r: &BenchResult) {
+    println!("=== {} ===", r.name);
+    println!("    Iterations: {}", r.iterations);
+    println!("    Total time: {} ms", r.total_ns / 1_000_000);
+    println!("    Per op:     {} ns", r.per_op_ns());
+    if let Some(latency) = &r.latency {
+        print!("{}", latency.summary());
+    }
+    if let Some(notes) = &r.notes {
+        println!("    Notes:      {}", notes);
+    }
+    println!();
+}
+
+// ============================================================================
+// Spawn
+// ============================================================================
+
+fn bench_spawn(iterations: u64) -> BenchResult {
+    let mut latencies = Histogram::new();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        let op = Instant::now();
+        let status = self_command("noop").status().expect("spawn child");
+        latencies.record(op.elapsed());
+        assert!(status.success());
+    }
+    let mut r = BenchResult::new("Spawn + wait (Command)", iterations, start.elapsed().as_nanos() as u64);
+    r.latency = Some(latencies);
+    r
+}
+
+// ============================================================================
+// Pipes
+// ============================================================================
+
+fn bench_pipe_throughput() -> BenchResult {
+    let mut child = self_command("sink")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("spawn sink");
+    let chunk = vec![0x5au8; CHUNK];
+    let start = Instant::now();
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        for _ in 0..PIPE_BYTES / CHUNK {
+            stdin.write_all(&chunk).expect("write to child");
+        }
+    }
+    let mut reply = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut reply).expect("read sink total");
+    let ns = start.elapsed().as_nanos() as u64;
+    child.wait().expect("wait for sink");
+    assert_eq!(reply.trim().parse::<usize>().ok(), Some(PIPE_BYTES));
+
+    let chunks = (PIPE_BYTES / CHUNK) as u64;
+    let mut r = BenchResult::new("Pipe write 64 KiB chunks", chunks, ns);
+    let mb_s = PIPE_BYTES as f64 / (1024.0 * 1024.0) / (ns as f64 / 1e9);
+    r.notes = Some(format!("{} MiB, {:.0} MiB/s", PIPE_BYTES >> 20, mb_s));
+    r
+}
+
+fn bench_pipe_ping_pong(iterations: u64) -> BenchResult {
+    let mut child = self_command("echo")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("spawn echo");
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut message = [0u8; MESSAGE];
+    let mut reply = [0u8; MESSAGE];
+    let mut latencies = Histogram::new();
+
+    let start = Instant::now();
+    for seq in 0..iterations {
+        message[..8].copy_from_slice(&seq.to_le_bytes());
+        let op = Instant::now();
+        stdin.write_all(&message).expect("write to echo");
+        stdout.read_exact(&mut reply).expect("read from echo");
+        latencies.record(op.elapsed());
+        assert_eq!(reply, message);
+    }
+    let ns = start.elapsed().as_nanos() as u64;
+    drop(stdin);
+    child.wait().expect("wait for echo");
+
+    let mut r = BenchResult::new("Pipe ping-pong 64 B", iterations, ns);
+    r.latency = Some(latencies);
+    r
+}
+
+// ============================================================================
+// Shared Memory
+// ============================================================================
+
+#[cfg(unix)]
+mod shm {
+    use std::fs::{File, OpenOptions};
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Instant;
+
+    use super::*;
+
+    /// Mapping layout: a header of two sequence counters, then the data area.
+    /// The parent publishes by bumping `request`, the child answers by
+    /// copying it to `reply`; a request of `u64::MAX` ends the child.
+    pub const DATA: usize = 1024 * 1024;
+    const HEADER: usize = 128;
+    const SIZE: usize = HEADER + DATA;
+    const STOP: u64 = u64::MAX;
+
+    pub struct Mapping {
+        ptr: *mut u8,
+        _file: File,
+    }
+
+    impl Mapping {
+        pub fn open(path: &Path, create: bool) -> Mapping {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(create)
+                .truncate(create)
+                .open(path)
+                .expect("open shared file");
+            if create {
+                file.set_len(SIZE as u64).expect("size shared file");
+            }
+            let ptr = unsafe {
+                libc::mmap(
+                    std::ptr::null_mut(),
+                    SIZE,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            assert!(ptr != libc::MAP_FAILED, "mmap: {}", std::io::Error::last_os_error());
+            Mapping { ptr: ptr as *mut u8, _file: file }
+        }
+
+        fn counter(&self, index: usize) -> &AtomicU64 {
+            // Separate cache lines for the two counters
+            unsafe { &*(self.ptr.add(index * 64) as *const AtomicU64) }
+        }
+
+        pub fn request(&self) -> &AtomicU64 {
+            self.counter(0)
+        }
+
+        pub fn reply(&self) -> &AtomicU64 {
+            self.counter(1)
+        }
+
+        pub fn data(&self) -> *mut u8 {
+            unsafe { self.ptr.add(HEADER) }
+        }
+    }
+
+    impl Drop for Mapping {
+        fn drop(&mut self) {
+            unsafe { libc::munmap(self.ptr as *mut libc::c_void, SIZE) };
+        }
+    }
+
+    /// Spin briefly, then yield, until `counter` reaches `value`.
+    pub fn wait_for(counter: &AtomicU64, value: u64) {
+        let mut spins = 0u32;
+        while counter.load(Ordering::Acquire) != value {
+            if spins < 1000 {
+                std::hint::spin_loop();
+                spins += 1;
+            } else {
+                std::thread::yield_now();
+            }
+        }
+    }
+
+    /// Answer requests until told to stop. A request with the top bit set
+    /// asks for a checksum of the data area in the first 8 bytes.
+    pub fn child(path: &str) {
+        let map = Mapping::open(Path::new(path), false);
+        let mut last = 0;
+        loop {
+            let mut spins = 0u32;
+            let request = loop {
+                let r = map.request().load(Ordering::Acquire);
+                if r != last {
+                    break r;
+                }
+                if spins < 1000 {
+                    std::hint::spin_loop();
+                    spins += 1;
+                } else {
+                    std::thread::yield_now();
+                }
+            };
+            if request == STOP {
+                return;
+            }
+            if request & (1 << 63) != 0 {
+                let data = unsafe { std::slice::from_raw_parts_mut(map.data(), DATA) };
+                let sum = data.iter().fold(0u64, |acc, b| acc.wrapping_add(*b as u64));
+                data[..8].copy_from_slice(&sum.to_le_bytes());
+            }
+            last = request;
+            map.reply().store(request, Ordering::Release);
+        }
+    }
+
+    fn with_child<T>(f: impl FnOnce(&Mapping) -> T) -> T {
+        let path = std::env::temp_dir().join(format!("tml-process-bench-{}.shm", std::process::id()));
+        let map = Mapping::open(&path, true);
+        let mut child = self_command("shm").arg(&path).spawn().expect("spawn shm child");
+        let result = f(&map);
+        map.request().store(STOP, Ordering::Release);
+        child.wait().expect("wait for shm child");
+        drop(map);
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    pub fn bench_ping_pong(iterations: u64) -> BenchResult {
+        with_child(|map| {
+            let mut latencies = Histogram::new();
+            let start = Instant::now();
+            for seq in 1..=iterations {
+                let op = Instant::now();
+                map.request().store(seq, Ordering::Release);
+                wait_for(map.reply(), seq);
+                latencies.record(op.elapsed());
+            }
+            let ns = start.elapsed().as_nanos() as u64;
+            let mut r = BenchResult::new("Shared memory ping-pong", iterations, ns);
+            r.notes = Some("atomic counters in a MAP_SHARED file mapping".to_string());
+            r.latency = Some(latencies);
+            r
+        })
+    }
+
+    pub fn bench_bulk(blocks: u64) -> BenchResult {
+        with_child(|map| {
+            let block: Vec<u8> = (0..DATA).map(|i| i as u8).collect();
+            let expected = block.iter().fold(0u64, |acc, b| acc.wrapping_add(*b as u64));
+            let start = Instant::now();
+            for seq in 1..=blocks {
+                let request = seq | 1 << 63;
+                unsafe { std::ptr::copy_nonoverlapping(block.as_ptr(), map.data(), DATA) };
+                map.request().store(request, Ordering::Release);
+                wait_for(map.reply(), request);
+                let sum = unsafe { std::ptr::read_unaligned(map.data() as *const u64) };
+                assert_eq!(u64::from_le(sum), expected, "child checksum");
+            }
+            let ns = start.elapsed().as_nanos() as u64;
+            let mut r = BenchResult::new("Shared memory 1 MiB blocks", blocks, ns);
+            let mb_s = blocks as f64 / (ns as f64 / 1e9);
+            r.notes = Some(format!("copy in, child checksums, {:.0} MiB/s", mb_s));
+            r
+        })
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--child") {
+        child(&args[2..]);
+        return;
+    }
+    let settings = apply_run_settings();
+    let iterations: u64 = args.get(1).and_then(|a| a.parse().ok()).unwrap_or(1000);
+
+    println!("\n================================================================");
+    println!("  Rust Process and IPC Benchmarks");
+    println!("================================================================\n");
+
+    let mut report = Report::new("Process", Environment::capture(&settings));
+    report.results.push(bench_spawn(iterations / 10));
+    report.results.push(bench_pipe_throughput());
+    report.results.push(bench_pipe_ping_pong(iterations * 10));
+    #[cfg(unix)]
+    {
+        report.results.push(shm::bench_ping_pong(iterations * 10));
+        report.results.push(shm::bench_bulk(256));
+    }
+    #[cfg(not(unix))]
+    println!("  (shared-memory benchmarks need Unix mmap; skipped)\n");
+
+    for r in &report.results {
+        print_result(r);
+    }
+    println!("================================================================\n");
+    report.save_if_requested();
+}
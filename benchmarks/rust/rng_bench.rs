@@ -0,0 +1,117 @@
+// Random Number Benchmarks - Rust
+//
+// Baselines for TML's random module, measured on the `rand` crate: raw
+// generation throughput of StdRng (ChaCha12) and SmallRng (xoshiro256++),
+// byte fills, `gen_range` sampling, floats, and `SliceRandom::shuffle` of
+// large vectors. Every generator is seeded with `seed_from_u64`, so the
+// shuffled data and sampled values are identical between runs.
+//
+// Run with: cargo run --release --bin rng_bench [iterations]
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use rand::rngs::{SmallRng, StdRng};
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore, SeedableRng};
+use tml_benchmarks::env::{apply_run_settings, Environment};
+use tml_benchmarks::report::{BenchResult, Report};
+
+const SEED: u64 = 0x7431_2026;
+
+fn run(report: &mut Report, name: &str, iterations: u64, bytes_per_op: usize, f: impl FnOnce() -> u64) {
+    let start = Instant::now();
+    black_box(f());
+    let ns = start.elapsed().as_nanos() as u64;
+    let mut r = BenchResult::new(name, iterations, ns);
+    if bytes_per_op > 0 && ns > 0 {
+        let mb_s = (iterations as f64 * bytes_per_op as f64) / (1024.0 * 1024.0) / (ns as f64 / 1e9);
+        r.notes = Some(format!("{:.0} MiB/s", mb_s));
+    }
+    println!(
+        "  {:<40} {:>8.2} ns/op {:>14} ops/sec{}",
+        name,
+        ns as f64 / iterations as f64,
+        r.ops_per_sec(),
+        r.notes.as_ref().map(|n| format!("  {}", n)).unwrap_or_default()
+    );
+    report.results.push(r);
+}
+
+fn generate<R: RngCore>(mut rng: R, n: u64) -> u64 {
+    let mut acc = 0u64;
+    for _ in 0..n {
+        acc ^= rng.next_u64();
+    }
+    acc
+}
+
+fn fill<R: RngCore>(mut rng: R, buf: &mut [u8], blocks: u64) -> u64 {
+    for _ in 0..blocks {
+        rng.fill_bytes(buf);
+    }
+    buf[0] as u64
+}
+
+fn shuffle<R: RngCore>(mut rng: R, items: &mut [u32], rounds: u64) -> u64 {
+    for _ in 0..rounds {
+        items.shuffle(&mut rng);
+    }
+    items[0] as u64
+}
+
+fn main() {
+    let settings = apply_run_settings();
+    let n: u64 = std::env::args().nth(1).and_then(|a| a.parse().ok()).unwrap_or(10_000_000);
+    let mut report = Report::new("Random", Environment::capture(&settings));
+
+    println!("\n================================================================");
+    println!("  Rust Random Number Benchmarks (rand)");
+    println!("================================================================\n");
+
+    println!("=== Generation (u64) ===");
+    run(&mut report, "SmallRng next_u64", n, 8, || generate(SmallRng::seed_from_u64(SEED), n));
+    run(&mut report, "StdRng next_u64", n, 8, || generate(StdRng::seed_from_u64(SEED), n));
+
+    println!("\n=== Fill bytes (64 KiB buffer) ===");
+    let blocks = (n / 8192).max(1);
+    let mut buf = vec![0u8; 64 * 1024];
+    run(&mut report, "SmallRng fill_bytes", blocks, buf.len(), || {
+        fill(SmallRng::seed_from_u64(SEED), &mut buf, blocks)
+    });
+    run(&mut report, "StdRng fill_bytes", blocks, buf.len(), || fill(StdRng::seed_from_u64(SEED), &mut buf, blocks));
+
+    println!("\n=== Sampling ===");
+    run(&mut report, "SmallRng gen_range(0..1000)", n, 0, || {
+        let mut rng = SmallRng::seed_from_u64(SEED);
+        (0..n).map(|_| rng.gen_range(0..1000u64)).sum()
+    });
+    // A bound just above 2^63 rejects almost half the draws
+    run(&mut report, "SmallRng gen_range(0..2^63 + 1)", n, 0, || {
+        let mut rng = SmallRng::seed_from_u64(SEED);
+        (0..n).fold(0, |acc, _| acc ^ rng.gen_range(0..(1u64 << 63) + 1))
+    });
+    run(&mut report, "StdRng gen_range(-1e6..1e6)", n, 0, || {
+        let mut rng = StdRng::seed_from_u64(SEED);
+        (0..n).fold(0, |acc, _| acc ^ rng.gen_range(-1_000_000..1_000_000i64) as u64)
+    });
+    run(&mut report, "SmallRng gen::<f64>", n, 0, || {
+        let mut rng = SmallRng::seed_from_u64(SEED);
+        (0..n).map(|_| rng.gen::<f64>()).sum::<f64>() as u64
+    });
+
+    println!("\n=== Shuffle (SliceRandom::shuffle) ===");
+    for len in [1_000usize, 1_000_000, 10_000_000] {
+        let rounds = (n as usize / len).clamp(1, 1000) as u64;
+        let mut items: Vec<u32> = (0..len as u32).collect();
+        run(&mut report, &format!("SmallRng shuffle {} u32", len), rounds, 0, || {
+            shuffle(SmallRng::seed_from_u64(SEED), &mut items, rounds)
+        });
+        run(&mut report, &format!("StdRng shuffle {} u32", len), rounds, 0, || {
+            shuffle(StdRng::seed_from_u64(SEED), &mut items, rounds)
+        });
+    }
+
+    println!("\n================================================================\n");
+    report.save_if_requested();
+}
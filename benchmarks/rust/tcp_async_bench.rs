@@ -2,8 +2,30 @@
 // Matches TML async benchmark for fair comparison
 // Requires: cargo add tokio --features full
 
-#[tokio::main]
-async fn main() {
+use std::time::Instant;
+
+#[path = "harness/env.rs"]
+#[allow(dead_code)]
+mod env;
+#[path = "harness/histogram.rs"]
+#[allow(dead_code)]
+mod histogram;
+#[path = "harness/report.rs"]
+#[allow(dead_code)]
+mod report;
+
+use env::{apply_run_settings, Environment, RunSettings};
+use histogram::Histogram;
+use report::{BenchResult, Report};
+
+// Run settings go first so the runtime's worker threads inherit them
+fn main() {
+    let settings = apply_run_settings();
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    runtime.block_on(bench(settings));
+}
+
+async fn bench(settings: RunSettings) {
     println!("\n================================================================");
     println!("  Rust TCP Benchmarks: Async (tokio::TcpListener)");
     println!("================================================================\n");
@@ -14,11 +36,15 @@ async fn main() {
     println!("=== ASYNC TCP (tokio::TcpListener) ===");
     println!("  Binding to 127.0.0.1:0 ({} iterations)\n", n);
 
-    let start = std::time::Instant::now();
+    let start = Instant::now();
     let mut success = 0;
+    let mut latencies = Histogram::new();
 
     for _ in 0..n {
-        if let Ok(_listener) = tokio::net::TcpListener::bind(addr).await {
+        let op = Instant::now();
+        let bound = tokio::net::TcpListener::bind(addr).await.is_ok();
+        latencies.record(op.elapsed());
+        if bound {
             success += 1;
         }
     }
@@ -30,7 +56,15 @@ async fn main() {
     println!("    Total time: {} ms", ns_elapsed / 1_000_000);
     println!("    Per op:     {} ns", if ns_elapsed > 0 { ns_elapsed / n } else { 0 });
     println!("    Ops/sec:    {}", if ns_elapsed > 0 { (n as i128 * 1_000_000_000) / ns_elapsed as i128 } else { 0 });
+    print!("{}", latencies.summary());
     println!("    Successful: {}/{}\n", success, n);
 
     println!("================================================================\n");
+
+    let mut result = BenchResult::new("TCP bind (tokio)", n as u64, ns_elapsed as u64);
+    result.notes = Some(format!("{}/{} successful", success, n));
+    result.latency = Some(latencies);
+    let mut report = Report::new("TCP", Environment::capture(&settings));
+    report.results.push(result);
+    report.save_if_requested();
 }
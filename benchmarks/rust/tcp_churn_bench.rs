@@ -0,0 +1,351 @@
+// TCP Benchmark: Connection per Request vs Reused Connection (std::net)
+//
+// A local echo server answers 64-byte requests with one thread per client,
+// all accepting on the same listener, so a kept-alive connection never
+// waits for another to close. Each of `clients` threads sends
+// `requests_per_client` requests either over one kept-alive connection
+// ("reused") or over a fresh connection per request ("churn": connect,
+// write, read, close), the connection-per-request mode of the TML TCP
+// benchmark.
+//
+// Churn runs sweep the listen backlog, since a short accept queue under
+// concurrent connects shows up as SYN retries in the latency tail, and try
+// two close modes:
+//
+//   graceful  normal close; the client side keeps TIME_WAIT entries
+//   abort     SO_LINGER with a zero timeout; close sends RST, no TIME_WAIT
+//
+// The listener always has SO_REUSEADDR on Unix (std sets it), so repeated
+// runs do not fail on a port in TIME_WAIT. Setting the backlog re-issues
+// listen() on the bound socket, which Linux and the BSDs honor; on Windows
+// the backlog stays at std's default and only the linger mode applies.
+//
+// Build: rustc --edition 2021 -O tcp_churn_bench.rs
+// Usage: tcp_churn_bench [clients] [requests_per_client] [backlogs, e.g. 16,128,1024]
+
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Instant;
+
+#[path = "harness/env.rs"]
+#[allow(dead_code)]
+mod env;
+#[path = "harness/histogram.rs"]
+#[allow(dead_code)]
+mod histogram;
+#[path = "harness/report.rs"]
+#[allow(dead_code)]
+mod report;
+
+use env::{apply_run_settings, Environment};
+use histogram::Histogram;
+use report::{BenchResult, Report};
+
+const PAYLOAD: usize = 64;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Reused,
+    Churn { abort: bool },
+}
+
+impl Mode {
+    fn name(self) -> &'static str {
+        match self {
+            Mode::Reused => "reused",
+            Mode::Churn { abort: false } => "churn graceful",
+            Mode::Churn { abort: true } => "churn abort",
+        }
+    }
+}
+
+// ============================================================================
+// Socket Options
+// ============================================================================
+
+#[cfg(unix)]
+mod sys {
+    use std::os::unix::io::AsRawFd;
+
+    #[cfg(target_os = "linux")]
+    const SOL_SOCKET: i32 = 1;
+    #[cfg(target_os = "linux")]
+    const SO_LINGER: i32 = 13;
+    #[cfg(not(target_os = "linux"))]
+    const SOL_SOCKET: i32 = 0xffff;
+    #[cfg(not(target_os = "linux"))]
+    const SO_LINGER: i32 = 0x80;
+
+    #[repr(C)]
+    struct Linger {
+        l_onoff: i32,
+        l_linger: i32,
+    }
+
+    extern "C" {
+        fn listen(fd: i32, backlog: i32) -> i32;
+        fn setsockopt(fd: i32, level: i32, name: i32, value: *const u8, len: u32) -> i32;
+    }
+
+    pub fn set_backlog(listener: &std::net::TcpListener, backlog: i32) -> Result<(), String> {
+        if unsafe { listen(listener.as_raw_fd(), backlog) } != 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
+    pub fn set_abortive_close(stream: &std::net::TcpStream) -> Result<(), String> {
+        let linger = Linger { l_onoff: 1, l_linger: 0 };
+        let len = std::mem::size_of::<Linger>() as u32;
+        let value = &linger as *const Linger as *const u8;
+        if unsafe { setsockopt(stream.as_raw_fd(), SOL_SOCKET, SO_LINGER, value, len) } != 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::os::windows::io::AsRawSocket;
+
+    const SOL_SOCKET: i32 = 0xffff;
+    const SO_LINGER: i32 = 0x80;
+
+    #[repr(C)]
+    struct Linger {
+        l_onoff: u16,
+        l_linger: u16,
+    }
+
+    #[link(name = "ws2_32")]
+    extern "system" {
+        fn setsockopt(s: usize, level: i32, name: i32, value: *const u8, len: i32) -> i32;
+    }
+
+    pub fn set_backlog(_listener: &std::net::TcpListener, _backlog: i32) -> Result<(), String> {
+        Err("backlog cannot be changed after listen() on Windows".to_string())
+    }
+
+    pub fn set_abortive_close(stream: &std::net::TcpStream) -> Result<(), String> {
+        let linger = Linger { l_onoff: 1, l_linger: 0 };
+        let len = std::mem::size_of::<Linger>() as i32;
+        let value = &linger as *const Linger as *const u8;
+        if unsafe { setsockopt(stream.as_raw_socket() as usize, SOL_SOCKET, SO_LINGER, value, len) } != 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Server and Clients
+// ============================================================================
+
+/// Answer each request on a connection until the client closes it.
+fn serve(mut stream: TcpStream) {
+    let _ = stream.set_nodelay(true);
+    let mut buf = [0u8; PAYLOAD];
+    while stream.read_exact(&mut buf).is_ok() {
+        if stream.write_all(&buf).is_err() {
+            break;
+        }
+    }
+}
+
+struct Server {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl Server {
+    fn start(threads: usize, backlog: Option<i32>, notes: &mut Vec<String>) -> Server {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind server");
+        if let Some(backlog) = backlog {
+            if let Err(e) = sys::set_backlog(&listener, backlog) {
+                notes.push(format!("backlog {} not applied: {}", backlog, e));
+            }
+        }
+        let addr = listener.local_addr().expect("server address");
+        let stop = Arc::new(AtomicBool::new(false));
+        let threads = (0..threads)
+            .map(|_| {
+                let listener = listener.try_clone().expect("clone listener");
+                let stop = Arc::clone(&stop);
+                thread::spawn(move || {
+                    while let Ok((stream, _)) = listener.accept() {
+                        if stop.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        serve(stream);
+                    }
+                })
+            })
+            .collect();
+        Server { addr, stop, threads }
+    }
+
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Wake every acceptor blocked in accept()
+        for _ in &self.threads {
+            let _ = TcpStream::connect(self.addr);
+        }
+        for t in self.threads {
+            let _ = t.join();
+        }
+    }
+}
+
+struct ClientStats {
+    latencies: Histogram,
+    errors: u64,
+    first_error: Option<String>,
+}
+
+impl ClientStats {
+    fn fail(&mut self, e: impl ToString) {
+        self.errors += 1;
+        self.first_error.get_or_insert_with(|| e.to_string());
+    }
+}
+
+fn round_trip(stream: &mut TcpStream, request: &[u8; PAYLOAD]) -> std::io::Result<()> {
+    let mut reply = [0u8; PAYLOAD];
+    stream.write_all(request)?;
+    stream.read_exact(&mut reply)?;
+    if reply != *request {
+        return Err(std::io::Error::other("echo mismatch"));
+    }
+    Ok(())
+}
+
+fn connect(addr: SocketAddr, abort: bool) -> std::io::Result<TcpStream> {
+    let stream = TcpStream::connect(addr)?;
+    stream.set_nodelay(true)?;
+    if abort {
+        sys::set_abortive_close(&stream).map_err(std::io::Error::other)?;
+    }
+    Ok(stream)
+}
+
+fn run_client(addr: SocketAddr, mode: Mode, requests: u64, start: &Barrier) -> ClientStats {
+    let mut stats = ClientStats { latencies: Histogram::new(), errors: 0, first_error: None };
+    let mut request = [0u8; PAYLOAD];
+    start.wait();
+
+    if mode == Mode::Reused {
+        let mut stream = match connect(addr, false) {
+            Ok(s) => s,
+            Err(e) => {
+                stats.fail(e);
+                return stats;
+            }
+        };
+        for seq in 0..requests {
+            request[..8].copy_from_slice(&seq.to_le_bytes());
+            let op = Instant::now();
+            match round_trip(&mut stream, &request) {
+                Ok(()) => stats.latencies.record(op.elapsed()),
+                Err(e) => stats.fail(e),
+            }
+        }
+        let _ = stream.shutdown(Shutdown::Both);
+        return stats;
+    }
+
+    let abort = mode == Mode::Churn { abort: true };
+    for seq in 0..requests {
+        request[..8].copy_from_slice(&seq.to_le_bytes());
+        let op = Instant::now();
+        let result = connect(addr, abort).and_then(|mut stream| round_trip(&mut stream, &request));
+        // The stream is closed (dropped) before the clock stops
+        match result {
+            Ok(()) => stats.latencies.record(op.elapsed()),
+            Err(e) => stats.fail(e),
+        }
+    }
+    stats
+}
+
+fn bench(mode: Mode, backlog: Option<i32>, clients: usize, requests: u64) -> BenchResult {
+    let mut notes = vec![format!("{} clients", clients)];
+    let server = Server::start(clients, backlog, &mut notes);
+    let start = Arc::new(Barrier::new(clients + 1));
+    let addr = server.addr;
+    let handles: Vec<_> = (0..clients)
+        .map(|_| {
+            let start = Arc::clone(&start);
+            thread::spawn(move || run_client(addr, mode, requests, &start))
+        })
+        .collect();
+    start.wait();
+    let t = Instant::now();
+    let per_client: Vec<ClientStats> = handles.into_iter().map(|h| h.join().expect("client thread")).collect();
+    let ns_elapsed = t.elapsed().as_nanos() as u64;
+    server.stop();
+
+    let mut latencies = Histogram::new();
+    let mut errors = 0;
+    for c in &per_client {
+        latencies.merge(&c.latencies);
+        errors += c.errors;
+    }
+    if let Some(e) = per_client.iter().find_map(|c| c.first_error.as_ref()) {
+        notes.push(format!("{} errors, first: {}", errors, e));
+    }
+    let completed = latencies.count();
+
+    let name = match backlog {
+        Some(b) => format!("TCP {} (backlog {})", mode.name(), b),
+        None => format!("TCP {}", mode.name()),
+    };
+    println!("=== {} ===", name);
+    println!("    Requests:   {} ({} errors)", completed, errors);
+    println!("    Total time: {} ms", ns_elapsed / 1_000_000);
+    println!(
+        "    Req/sec:    {}",
+        if ns_elapsed > 0 { completed as u128 * 1_000_000_000 / ns_elapsed as u128 } else { 0 }
+    );
+    print!("{}", latencies.summary());
+    for note in &notes[1..] {
+        println!("    Note:       {}", note);
+    }
+    println!();
+
+    let mut result = BenchResult::new(&name, completed, ns_elapsed);
+    result.notes = Some(notes.join("; "));
+    result.latency = Some(latencies);
+    result
+}
+
+fn main() {
+    let settings = apply_run_settings();
+    let args: Vec<String> = std::env::args().collect();
+    let clients: usize = args.get(1).and_then(|a| a.parse().ok()).unwrap_or(8).max(1);
+    let requests: u64 = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(500);
+    let backlogs: Vec<i32> = args
+        .get(3)
+        .map(|a| a.split(',').filter_map(|b| b.trim().parse().ok()).collect())
+        .unwrap_or_else(|| vec![16, 128, 1024]);
+
+    println!("\n================================================================");
+    println!("  Rust TCP Benchmarks: Connection Churn (std::net)");
+    println!("================================================================\n");
+    println!("  {} clients x {} requests, {}-byte echo\n", clients, requests, PAYLOAD);
+
+    let mut report = Report::new("TCP", Environment::capture(&settings));
+    report.results.push(bench(Mode::Reused, None, clients, requests));
+    for abort in [false, true] {
+        for &backlog in &backlogs {
+            report.results.push(bench(Mode::Churn { abort }, Some(backlog), clients, requests));
+        }
+    }
+
+    println!("================================================================\n");
+    report.save_if_requested();
+}
@@ -1,7 +1,22 @@
 use std::net::TcpListener;
 use std::time::Instant;
 
+#[path = "harness/env.rs"]
+#[allow(dead_code)]
+mod env;
+#[path = "harness/histogram.rs"]
+#[allow(dead_code)]
+mod histogram;
+#[path = "harness/report.rs"]
+#[allow(dead_code)]
+mod report;
+
+use env::{apply_run_settings, Environment};
+use histogram::Histogram;
+use report::{BenchResult, Report};
+
 fn main() {
+    let settings = apply_run_settings();
     println!("\n================================================================");
     println!("  Rust TCP Benchmarks: Socket Bind (std::net)");
     println!("================================================================\n");
@@ -14,8 +29,12 @@ fn main() {
     let n = 50;
     let start = Instant::now();
     let mut success = 0;
+    let mut latencies = Histogram::new();
     for _ in 0..n {
-        if let Ok(_listener) = TcpListener::bind(addr) {
+        let op = Instant::now();
+        let bound = TcpListener::bind(addr).is_ok();
+        latencies.record(op.elapsed());
+        if bound {
             success += 1;
         }
     }
@@ -26,7 +45,15 @@ fn main() {
     println!("    Total time: {} ms", ns_elapsed / 1_000_000);
     println!("    Per op:     {} ns", if ns_elapsed > 0 { ns_elapsed / n } else { 0 });
     println!("    Ops/sec:    {}", if ns_elapsed > 0 { (n as i128 * 1_000_000_000) / ns_elapsed as i128 } else { 0 });
+    print!("{}", latencies.summary());
     println!("    Successful: {}/{}\n", success, n);
 
     println!("================================================================\n");
+
+    let mut result = BenchResult::new("TCP bind (std::net)", n as u64, ns_elapsed as u64);
+    result.notes = Some(format!("{}/{} successful", success, n));
+    result.latency = Some(latencies);
+    let mut report = Report::new("TCP", Environment::capture(&settings));
+    report.results.push(result);
+    report.save_if_requested();
 }
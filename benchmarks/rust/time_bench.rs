@@ -0,0 +1,160 @@
+// Date/Time Benchmarks - Rust
+//
+// Baselines for the `time_ms`/`time_ns` runtime primitives and TML's
+// datetime library: clock reads, SystemTime arithmetic, RFC 3339
+// formatting and parsing with chrono (`DateTime::parse_from_rfc3339`,
+// `to_rfc3339`) and time (`OffsetDateTime::parse`, `format(&Rfc3339)`),
+// and Duration formatting.
+//
+// Run with: cargo run --release --bin time_bench [iterations]
+
+use std::fmt::Write;
+use std::hint::black_box;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tml_benchmarks::env::{apply_run_settings, Environment};
+use tml_benchmarks::report::{BenchResult, Report};
+
+// ============================================================================
+// Formatting helpers
+// ============================================================================
+
+/// `1h 02m 03.456s` style, as a log line or progress display would show it.
+fn format_duration(out: &mut String, d: Duration) {
+    let secs = d.as_secs();
+    let millis = d.subsec_millis();
+    if secs >= 3600 {
+        let _ = write!(out, "{}h {:02}m {:02}.{:03}s", secs / 3600, secs / 60 % 60, secs % 60, millis);
+    } else if secs >= 60 {
+        let _ = write!(out, "{}m {:02}.{:03}s", secs / 60, secs % 60, millis);
+    } else {
+        let _ = write!(out, "{}.{:03}s", secs, millis);
+    }
+}
+
+// ============================================================================
+// Benchmarks
+// ============================================================================
+
+fn run(report: &mut Report, name: &str, iterations: u64, f: impl FnOnce() -> u64) {
+    let start = Instant::now();
+    black_box(f());
+    let ns = start.elapsed().as_nanos() as u64;
+    let r = BenchResult::new(name, iterations, ns);
+    println!("  {:<40} {:>8.2} ns/op {:>14} ops/sec", name, ns as f64 / iterations as f64, r.ops_per_sec());
+    report.results.push(r);
+}
+
+fn main() {
+    let settings = apply_run_settings();
+    let n: u64 = std::env::args().nth(1).and_then(|a| a.parse().ok()).unwrap_or(1_000_000);
+    let mut report = Report::new("Time", Environment::capture(&settings));
+
+    println!("\n================================================================");
+    println!("  Rust Date/Time Benchmarks");
+    println!("================================================================\n");
+
+    println!("=== Clocks ===");
+    run(&mut report, "Instant::now + elapsed", n, || (0..n).map(|_| Instant::now().elapsed().as_nanos() as u64).sum());
+    run(&mut report, "SystemTime::now (unix ns)", n, || {
+        (0..n).fold(0, |acc, _| acc ^ SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64)
+    });
+
+    println!("\n=== SystemTime arithmetic ===");
+    let base = SystemTime::now();
+    run(&mut report, "SystemTime + Duration", n, || {
+        let mut t = base;
+        for i in 0..n {
+            t += Duration::from_nanos(black_box(i) & 0xffff);
+        }
+        t.duration_since(base).unwrap().as_nanos() as u64
+    });
+    run(&mut report, "SystemTime duration_since", n, || {
+        let later = base + Duration::from_secs(86_400);
+        (0..n).map(|_| black_box(later).duration_since(black_box(base)).unwrap().as_secs()).sum()
+    });
+
+    // Timestamps spread over 1970-2100 with varying precision
+    let timestamps: Vec<DateTime<Utc>> = (0..1024u64)
+        .map(|i| {
+            let secs = (i.wrapping_mul(0x9e37_79b9) % 4_102_444_800) as i64;
+            let nanos = match i % 3 {
+                0 => 0,
+                1 => (i % 1000) as u32 * 1_000_000,
+                _ => (i * 7919 % 1_000_000_000) as u32,
+            };
+            DateTime::from_timestamp(secs, nanos).unwrap()
+        })
+        .collect();
+    let offset_times: Vec<OffsetDateTime> = timestamps
+        .iter()
+        .map(|t| OffsetDateTime::from_unix_timestamp_nanos(t.timestamp_nanos_opt().unwrap() as i128).unwrap())
+        .collect();
+    // Every fourth one as local time with an offset
+    let ist = chrono::FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap();
+    let strings: Vec<String> = timestamps
+        .iter()
+        .enumerate()
+        .map(|(i, t)| if i % 4 == 0 { t.with_timezone(&ist).to_rfc3339() } else { t.to_rfc3339() })
+        .collect();
+    for (s, t) in strings.iter().zip(&timestamps) {
+        assert_eq!(DateTime::parse_from_rfc3339(s).unwrap(), *t, "{}", s);
+        let parsed = OffsetDateTime::parse(s, &Rfc3339).unwrap();
+        assert_eq!(parsed.unix_timestamp_nanos(), t.timestamp_nanos_opt().unwrap() as i128, "{}", s);
+    }
+
+    println!("\n=== RFC 3339 (chrono) ===");
+    run(&mut report, "chrono to_rfc3339", n, || {
+        (0..n as usize).map(|i| timestamps[i % timestamps.len()].to_rfc3339().len() as u64).sum()
+    });
+    run(&mut report, "chrono parse_from_rfc3339", n, || {
+        (0..n as usize).fold(0, |acc, i| {
+            acc ^ DateTime::parse_from_rfc3339(&strings[i % strings.len()]).unwrap().timestamp() as u64
+        })
+    });
+    run(&mut report, "chrono Utc::now -> RFC 3339", n / 4, || {
+        (0..n / 4).map(|_| Utc::now().to_rfc3339().len() as u64).sum()
+    });
+
+    println!("\n=== RFC 3339 (time) ===");
+    run(&mut report, "time format(&Rfc3339)", n, || {
+        (0..n as usize).map(|i| offset_times[i % offset_times.len()].format(&Rfc3339).unwrap().len() as u64).sum()
+    });
+    run(&mut report, "time OffsetDateTime::parse", n, || {
+        (0..n as usize).fold(0, |acc, i| {
+            acc ^ OffsetDateTime::parse(&strings[i % strings.len()], &Rfc3339).unwrap().unix_timestamp() as u64
+        })
+    });
+    run(&mut report, "time now_utc -> RFC 3339", n / 4, || {
+        (0..n / 4).map(|_| OffsetDateTime::now_utc().format(&Rfc3339).unwrap().len() as u64).sum()
+    });
+
+    println!("\n=== Duration formatting ===");
+    let durations: Vec<Duration> = (0..1024u64).map(|i| Duration::from_micros(i * i * 3_331)).collect();
+    run(&mut report, "Duration {:?}", n, || {
+        let mut out = String::with_capacity(32);
+        let mut total = 0;
+        for i in 0..n as usize {
+            out.clear();
+            let _ = write!(out, "{:?}", durations[i % durations.len()]);
+            total += out.len() as u64;
+        }
+        total
+    });
+    run(&mut report, "Duration h/m/s", n, || {
+        let mut out = String::with_capacity(32);
+        let mut total = 0;
+        for i in 0..n as usize {
+            out.clear();
+            format_duration(&mut out, durations[i % durations.len()]);
+            total += out.len() as u64;
+        }
+        total
+    });
+
+    println!("\n================================================================\n");
+    report.save_if_requested();
+}
@@ -0,0 +1,189 @@
+// UDP Echo Benchmark: Concurrent Clients (std::net)
+//
+// An echo server of `server_threads` threads shares one socket (each thread
+// blocks in recv_from on its own handle), and `clients` threads each own a
+// socket and run a ping-pong loop: send a 64-byte datagram carrying a
+// sequence number, wait for the echo, repeat. The run reports aggregate
+// round trips per second, the latency distribution over all clients and
+// each client's p50/p99, so unfair scheduling between clients shows up.
+//
+// The sweep starts with one client, the single-client baseline the TML UDP
+// benchmark measures, and doubles up to `max_clients`.
+//
+// Build: rustc --edition 2021 -O udp_bench.rs
+// Usage: udp_bench [max_clients] [round_trips_per_client] [server_threads]
+
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[path = "harness/env.rs"]
+#[allow(dead_code)]
+mod env;
+#[path = "harness/histogram.rs"]
+#[allow(dead_code)]
+mod histogram;
+#[path = "harness/report.rs"]
+#[allow(dead_code)]
+mod report;
+
+use env::{apply_run_settings, Environment};
+use histogram::Histogram;
+use report::{BenchResult, Report};
+
+const PAYLOAD: usize = 64;
+/// A datagram not echoed within this time counts as lost
+const REPLY_TIMEOUT: Duration = Duration::from_millis(200);
+
+struct ClientStats {
+    latencies: Histogram,
+    lost: u64,
+}
+
+fn arg(index: usize, default: usize) -> usize {
+    std::env::args().nth(index).and_then(|a| a.parse().ok()).unwrap_or(default)
+}
+
+/// Echo every datagram back to its sender until `stop` is set.
+fn spawn_server(threads: usize, stop: &Arc<AtomicBool>) -> (std::net::SocketAddr, Vec<thread::JoinHandle<()>>) {
+    let socket = UdpSocket::bind("127.0.0.1:0").expect("bind server");
+    socket.set_read_timeout(Some(Duration::from_millis(20))).expect("server timeout");
+    let addr = socket.local_addr().expect("server address");
+    let handles = (0..threads)
+        .map(|_| {
+            let socket = socket.try_clone().expect("clone server socket");
+            let stop = Arc::clone(stop);
+            thread::spawn(move || {
+                let mut buf = [0u8; 1500];
+                while !stop.load(Ordering::Relaxed) {
+                    if let Ok((len, from)) = socket.recv_from(&mut buf) {
+                        let _ = socket.send_to(&buf[..len], from);
+                    }
+                }
+            })
+        })
+        .collect();
+    (addr, handles)
+}
+
+fn run_client(server: std::net::SocketAddr, round_trips: u64, start: &Barrier) -> ClientStats {
+    let socket = UdpSocket::bind("127.0.0.1:0").expect("bind client");
+    socket.connect(server).expect("connect client");
+    socket.set_read_timeout(Some(REPLY_TIMEOUT)).expect("client timeout");
+    let mut stats = ClientStats { latencies: Histogram::new(), lost: 0 };
+    let mut out = [0u8; PAYLOAD];
+    let mut buf = [0u8; PAYLOAD];
+    start.wait();
+
+    for seq in 0..round_trips {
+        out[..8].copy_from_slice(&seq.to_le_bytes());
+        let op = Instant::now();
+        if socket.send(&out).is_err() {
+            stats.lost += 1;
+            continue;
+        }
+        // Skip late echoes of datagrams already counted as lost
+        loop {
+            match socket.recv(&mut buf) {
+                Ok(len) if len >= 8 && buf[..8] == out[..8] => {
+                    stats.latencies.record(op.elapsed());
+                    break;
+                }
+                Ok(_) => continue,
+                Err(_) => {
+                    stats.lost += 1;
+                    break;
+                }
+            }
+        }
+    }
+    stats
+}
+
+fn bench(clients: usize, round_trips: u64, server_threads: usize) -> BenchResult {
+    let stop = Arc::new(AtomicBool::new(false));
+    let (server, servers) = spawn_server(server_threads, &stop);
+    let start = Arc::new(Barrier::new(clients + 1));
+
+    let handles: Vec<_> = (0..clients)
+        .map(|_| {
+            let start = Arc::clone(&start);
+            thread::spawn(move || run_client(server, round_trips, &start))
+        })
+        .collect();
+    start.wait();
+    let t = Instant::now();
+    let per_client: Vec<ClientStats> = handles.into_iter().map(|h| h.join().expect("client thread")).collect();
+    let ns_elapsed = t.elapsed().as_nanos() as u64;
+
+    stop.store(true, Ordering::Relaxed);
+    for h in servers {
+        let _ = h.join();
+    }
+
+    let mut latencies = Histogram::new();
+    let mut lost = 0;
+    for c in &per_client {
+        latencies.merge(&c.latencies);
+        lost += c.lost;
+    }
+    let completed = latencies.count();
+
+    println!("=== {} client(s), {} server thread(s) ===", clients, server_threads);
+    println!("    Round trips: {} ({} lost)", completed, lost);
+    println!("    Total time:  {} ms", ns_elapsed / 1_000_000);
+    println!(
+        "    Ops/sec:     {}",
+        if ns_elapsed > 0 { completed as u128 * 1_000_000_000 / ns_elapsed as u128 } else { 0 }
+    );
+    print!("{}", latencies.summary());
+    if clients > 1 {
+        let p99: Vec<u64> = per_client.iter().map(|c| c.latencies.percentile(99.0)).collect();
+        println!(
+            "    Per-client p99: min {} ns, max {} ns",
+            p99.iter().min().unwrap_or(&0),
+            p99.iter().max().unwrap_or(&0)
+        );
+        if clients <= 16 {
+            for (i, c) in per_client.iter().enumerate() {
+                println!(
+                    "      client {:>2}: p50 {:>8} ns  p99 {:>8} ns  lost {}",
+                    i,
+                    c.latencies.percentile(50.0),
+                    c.latencies.percentile(99.0),
+                    c.lost
+                );
+            }
+        }
+    }
+    println!();
+
+    let mut result = BenchResult::new(&format!("UDP echo ({} clients)", clients), completed, ns_elapsed);
+    result.notes = Some(format!("{} server threads, {}-byte payload, {} lost", server_threads, PAYLOAD, lost));
+    result.latency = Some(latencies);
+    result
+}
+
+fn main() {
+    let settings = apply_run_settings();
+    let max_clients = arg(1, 16).max(1);
+    let round_trips = arg(2, 10_000) as u64;
+    let server_threads = arg(3, 4).max(1);
+
+    println!("\n================================================================");
+    println!("  Rust UDP Benchmarks: Concurrent Echo (std::net)");
+    println!("================================================================\n");
+    println!("  {} round trips per client, {}-byte payload\n", round_trips, PAYLOAD);
+
+    let mut report = Report::new("UDP", Environment::capture(&settings));
+    let mut clients = 1;
+    while clients <= max_clients {
+        report.results.push(bench(clients, round_trips, server_threads));
+        clients *= 2;
+    }
+
+    println!("================================================================\n");
+    report.save_if_requested();
+}
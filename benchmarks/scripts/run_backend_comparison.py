@@ -0,0 +1,402 @@
+#!/usr/bin/env python3
+"""
+TML Backend Comparison (Cranelift vs LLVM)
+
+Builds every program in a corpus with both codegen backends at O0 and O2 and
+records, per program and configuration:
+- Compile time (wall clock of `tml build`, median of --compile-runs)
+- Runtime of the produced executable (median of --runs)
+- Executable size
+
+Both backends consume the same MIR for a given program, so the differences
+are purely code generation. The report ends with a suggested default backend
+per build profile (debug = O0, release = O2).
+
+Programs with a Rust counterpart of the same name in --rust-dir are also
+built with `rustc` at the matching opt level. Those builds are only sized
+(not run), giving the code size table a third column so regressions in the
+bridge's lowering show up against both LLVM and rustc.
+
+Usage:
+    python run_backend_comparison.py
+    python run_backend_comparison.py --corpus ../tml --runs 5
+    python run_backend_comparison.py --filter "algorithms*" --output report.md
+    python run_backend_comparison.py --no-rustc
+"""
+
+import argparse
+import fnmatch
+import json
+import os
+import shutil
+import statistics
+import subprocess
+import sys
+import tempfile
+import time
+from datetime import datetime
+from pathlib import Path
+
+# Paths
+SCRIPT_DIR = Path(__file__).parent
+BENCHMARKS_DIR = SCRIPT_DIR.parent
+PROJECT_ROOT = BENCHMARKS_DIR.parent
+BUILD_DIR = PROJECT_ROOT / "build" / "debug"
+TML_COMPILER = BUILD_DIR / ("tml.exe" if os.name == "nt" else "tml")
+RESULTS_DIR = BENCHMARKS_DIR / "results"
+DEFAULT_CORPUS = BENCHMARKS_DIR / "tml"
+DEFAULT_RUST_DIR = BENCHMARKS_DIR / "rust"
+
+BACKENDS = ["llvm", "cranelift"]
+OPT_LEVELS = ["O0", "O2"]
+PROFILES = {"debug": "O0", "release": "O2"}
+# Size baseline, built from <rust-dir>/<program>.rs
+RUSTC = "rustc"
+RUSTC_OPT_LEVELS = {"O0": "0", "O2": "2"}
+
+
+def find_executable(out_dir: Path, stem: str) -> Path | None:
+    """Locate the executable `tml build` produced for `stem` in `out_dir`."""
+    for name in (f"{stem}.exe", stem):
+        candidate = out_dir / name
+        if candidate.is_file():
+            return candidate
+    return None
+
+
+def build_program(source: Path, backend: str, opt_level: str, out_dir: Path,
+                  compile_runs: int, verbose: bool) -> dict:
+    """Build `source` and time the compiler. The last build's output is kept."""
+    result = {
+        "success": False,
+        "compile_ms": None,
+        "binary_size": None,
+        "executable": None,
+        "error": None,
+    }
+    cmd = [
+        str(TML_COMPILER),
+        "build",
+        str(source),
+        f"-{opt_level}",
+        f"--backend={backend}",
+        "--no-cache",
+        f"--out-dir={out_dir}",
+    ]
+    if verbose:
+        print(f"    Running: {' '.join(cmd)}")
+
+    timings = []
+    for _ in range(compile_runs):
+        start = time.perf_counter()
+        try:
+            proc = subprocess.run(cmd, capture_output=True, text=True, timeout=300,
+                                  cwd=str(PROJECT_ROOT))
+        except subprocess.TimeoutExpired:
+            result["error"] = "compilation timed out"
+            return result
+        timings.append((time.perf_counter() - start) * 1000.0)
+        if proc.returncode != 0:
+            result["error"] = (proc.stderr or proc.stdout).strip()[-2000:]
+            return result
+
+    exe = find_executable(out_dir, source.stem)
+    if exe is None:
+        result["error"] = f"executable for {source.stem} not found in {out_dir}"
+        return result
+
+    result.update(
+        success=True,
+        compile_ms=statistics.median(timings),
+        binary_size=exe.stat().st_size,
+        executable=str(exe),
+    )
+    return result
+
+
+def build_rust(source: Path, opt_level: str, out_dir: Path, verbose: bool) -> dict:
+    """Build the Rust counterpart `source` with rustc for its size."""
+    exe = out_dir / (f"{source.stem}.exe" if os.name == "nt" else source.stem)
+    cmd = [RUSTC, "-C", f"opt-level={RUSTC_OPT_LEVELS[opt_level]}", str(source), "-o", str(exe)]
+    if verbose:
+        print(f"    Running: {' '.join(cmd)}")
+    result = {"success": False, "compile_ms": None, "binary_size": None, "error": None}
+    start = time.perf_counter()
+    try:
+        proc = subprocess.run(cmd, capture_output=True, text=True, timeout=300)
+    except subprocess.TimeoutExpired:
+        result["error"] = "compilation timed out"
+        return result
+    if proc.returncode != 0:
+        result["error"] = proc.stderr.strip()[-2000:]
+        return result
+    result.update(
+        success=True,
+        compile_ms=(time.perf_counter() - start) * 1000.0,
+        binary_size=exe.stat().st_size,
+    )
+    return result
+
+
+def run_program(exe: Path, runs: int, timeout: int) -> dict:
+    """Run `exe` `runs` times and return the median wall-clock time."""
+    timings = []
+    for _ in range(runs):
+        start = time.perf_counter()
+        try:
+            proc = subprocess.run([str(exe)], capture_output=True, timeout=timeout)
+        except subprocess.TimeoutExpired:
+            return {"success": False, "runtime_ms": None, "error": "run timed out"}
+        timings.append((time.perf_counter() - start) * 1000.0)
+        if proc.returncode != 0:
+            return {
+                "success": False,
+                "runtime_ms": None,
+                "error": f"exit code {proc.returncode}",
+            }
+    return {"success": True, "runtime_ms": statistics.median(timings), "error": None}
+
+
+def collect_corpus(corpus: Path, pattern: str | None) -> list[Path]:
+    files = sorted(corpus.glob("*.tml")) if corpus.is_dir() else [corpus]
+    if pattern:
+        files = [f for f in files if fnmatch.fnmatch(f.name, pattern)]
+    return files
+
+
+def run_comparison(files: list[Path], args) -> list[dict]:
+    results = []
+    with tempfile.TemporaryDirectory(prefix="tml_backend_cmp_") as tmp:
+        for source in files:
+            print(f"\n{source.name}")
+            print("-" * 50)
+            entry = {"file": source.name, "configs": {}}
+            for backend in BACKENDS:
+                for opt_level in OPT_LEVELS:
+                    key = f"{backend}-{opt_level}"
+                    print(f"  {key:<14}", end=" ", flush=True)
+                    out_dir = Path(tmp) / source.stem / key
+                    out_dir.mkdir(parents=True, exist_ok=True)
+
+                    build = build_program(source, backend, opt_level, out_dir,
+                                          args.compile_runs, args.verbose)
+                    config = dict(build)
+                    if build["success"] and not args.no_run:
+                        run = run_program(Path(build["executable"]), args.runs, args.timeout)
+                        config["runtime_ms"] = run["runtime_ms"]
+                        config["success"] = run["success"]
+                        config["error"] = run["error"]
+                    config.pop("executable", None)
+                    entry["configs"][key] = config
+
+                    if config["success"]:
+                        runtime = config.get("runtime_ms")
+                        run_text = f", run {runtime:.1f} ms" if runtime is not None else ""
+                        print(f"compile {config['compile_ms']:.0f} ms{run_text}")
+                    else:
+                        print(f"FAILED ({(config['error'] or '').splitlines()[-1:]})")
+
+            rust_source = args.rust_dir / f"{source.stem}.rs"
+            if args.rustc and rust_source.is_file():
+                for opt_level in OPT_LEVELS:
+                    key = f"rustc-{opt_level}"
+                    print(f"  {key:<14}", end=" ", flush=True)
+                    out_dir = Path(tmp) / source.stem / key
+                    out_dir.mkdir(parents=True, exist_ok=True)
+                    config = build_rust(rust_source, opt_level, out_dir, args.verbose)
+                    entry["configs"][key] = config
+                    if config["success"]:
+                        print(f"size {config['binary_size']} bytes")
+                    else:
+                        print(f"FAILED ({(config['error'] or '').splitlines()[-1:]})")
+            results.append(entry)
+    return results
+
+
+def geomean_ratio(results: list[dict], metric: str, opt_level: str,
+                  baseline: str = "llvm") -> float | None:
+    """Geometric mean of cranelift/`baseline` for `metric` over programs where both succeeded."""
+    ratios = []
+    for entry in results:
+        base = entry["configs"].get(f"{baseline}-{opt_level}", {})
+        clif = entry["configs"].get(f"cranelift-{opt_level}", {})
+        a, b = base.get(metric), clif.get(metric)
+        if base.get("success") and clif.get("success") and a and b:
+            ratios.append(b / a)
+    if not ratios:
+        return None
+    return statistics.geometric_mean(ratios)
+
+
+def recommend(results: list[dict]) -> dict:
+    """Pick a default backend per profile.
+
+    Debug builds are dominated by the edit-compile-run loop, so compile time
+    plus one run decides. Release builds are judged on runtime alone.
+    """
+    recommendations = {}
+    for profile, opt_level in PROFILES.items():
+        totals = {}
+        for backend in BACKENDS:
+            total = 0.0
+            for entry in results:
+                config = entry["configs"].get(f"{backend}-{opt_level}", {})
+                if not config.get("success"):
+                    total = float("inf")
+                    break
+                runtime = config.get("runtime_ms") or 0.0
+                total += runtime if profile == "release" else config["compile_ms"] + runtime
+            totals[backend] = total
+        best = min(totals, key=totals.get)
+        recommendations[profile] = {
+            "backend": best if totals[best] != float("inf") else None,
+            "totals_ms": {k: (None if v == float("inf") else v) for k, v in totals.items()},
+        }
+    return recommendations
+
+
+def fmt(value, suffix="") -> str:
+    if value is None:
+        return "N/A"
+    if isinstance(value, float):
+        return f"{value:.1f}{suffix}"
+    return f"{value}{suffix}"
+
+
+def size_ratio(entry: dict, key: str, baseline: str) -> float | None:
+    a = entry["configs"].get(baseline, {}).get("binary_size")
+    b = entry["configs"].get(key, {}).get("binary_size")
+    return b / a if a and b else None
+
+
+def generate_report(results: list[dict], recommendations: dict) -> str:
+    lines = [
+        "# TML Backend Comparison: Cranelift vs LLVM",
+        "",
+        f"**Date:** {datetime.now().strftime('%Y-%m-%d %H:%M:%S')}",
+        f"**Compiler:** `{TML_COMPILER}`",
+        "",
+        "## Summary",
+        "",
+        "Geometric mean of Cranelift / LLVM (below 1.0 means Cranelift is better):",
+        "",
+        "| Metric | " + " | ".join(OPT_LEVELS) + " |",
+        "|--------|" + "|".join("-----" for _ in OPT_LEVELS) + "|",
+    ]
+    for metric, label in (("compile_ms", "Compile time"), ("runtime_ms", "Runtime"),
+                          ("binary_size", "Binary size")):
+        cells = [fmt(geomean_ratio(results, metric, o), "x") for o in OPT_LEVELS]
+        lines.append(f"| {label} | " + " | ".join(cells) + " |")
+    cells = [fmt(geomean_ratio(results, "binary_size", o, "rustc"), "x") for o in OPT_LEVELS]
+    lines.append("| Binary size vs rustc | " + " | ".join(cells) + " |")
+
+    lines.extend(["", "## Suggested Defaults", "",
+                  "| Profile | Opt level | Backend | Total (ms) per backend |",
+                  "|---------|-----------|---------|------------------------|"])
+    for profile, rec in recommendations.items():
+        totals = ", ".join(f"{b}: {fmt(t)}" for b, t in rec["totals_ms"].items())
+        lines.append(f"| {profile} | {PROFILES[profile]} | {rec['backend'] or 'N/A'} | {totals} |")
+
+    lines.extend(["", "## Code Size", "",
+                  "Executable bytes per program (rustc: the Rust counterpart, if any).", "",
+                  "| Program | Opt | LLVM | Cranelift | rustc | Cranelift / LLVM | Cranelift / rustc |",
+                  "|---------|-----|------|-----------|-------|------------------|-------------------|"])
+    for entry in results:
+        for o in OPT_LEVELS:
+            sizes = [entry["configs"].get(f"{b}-{o}", {}).get("binary_size")
+                     for b in ("llvm", "cranelift", "rustc")]
+            clif = f"cranelift-{o}"
+            lines.append(
+                f"| {entry['file']} | {o} | " + " | ".join(fmt(s) for s in sizes) + " | "
+                f"{fmt(size_ratio(entry, clif, f'llvm-{o}'), 'x')} | "
+                f"{fmt(size_ratio(entry, clif, f'rustc-{o}'), 'x')} |"
+            )
+
+    lines.extend(["", "## Per Program", ""])
+    header = "| Program | Config | Compile (ms) | Runtime (ms) | Size (bytes) | Status |"
+    lines.extend([header, "|---------|--------|--------------|--------------|--------------|--------|"])
+    for entry in results:
+        for key, config in entry["configs"].items():
+            status = "ok" if config["success"] else "failed"
+            lines.append(
+                f"| {entry['file']} | {key} | {fmt(config.get('compile_ms'))} | "
+                f"{fmt(config.get('runtime_ms'))} | {fmt(config.get('binary_size'))} | {status} |"
+            )
+
+    failures = [(e["file"], k, c["error"]) for e in results for k, c in e["configs"].items()
+                if not c["success"]]
+    if failures:
+        lines.extend(["", "## Failures", ""])
+        for name, key, error in failures:
+            first_line = (error or "unknown error").strip().splitlines()[-1]
+            lines.append(f"- **{name}** ({key}): `{first_line}`")
+
+    lines.append("")
+    return "\n".join(lines)
+
+
+def main():
+    parser = argparse.ArgumentParser(description="TML Backend Comparison (Cranelift vs LLVM)")
+    parser.add_argument("--corpus", type=Path, default=DEFAULT_CORPUS,
+                        help="Directory of .tml programs (or a single file)")
+    parser.add_argument("--filter", type=str, help="Glob over file names, e.g. 'algo*'")
+    parser.add_argument("--runs", type=int, default=3, help="Executions per program (median)")
+    parser.add_argument("--compile-runs", type=int, default=1,
+                        help="Builds per configuration (median)")
+    parser.add_argument("--timeout", type=int, default=120, help="Per-run timeout in seconds")
+    parser.add_argument("--no-run", action="store_true", help="Only measure compilation")
+    parser.add_argument("--rust-dir", type=Path, default=DEFAULT_RUST_DIR,
+                        help="Directory of Rust counterparts (<program>.rs) sized with rustc")
+    parser.add_argument("--no-rustc", dest="rustc", action="store_false",
+                        help="Skip the rustc size baseline")
+    parser.add_argument("-v", "--verbose", action="store_true", help="Verbose output")
+    parser.add_argument("-o", "--output", type=str, help="Output file for report")
+    args = parser.parse_args()
+
+    if not TML_COMPILER.exists():
+        print(f"Error: TML compiler not found at {TML_COMPILER}")
+        print("Run 'scripts\\build.bat' first.")
+        sys.exit(1)
+
+    if args.rustc and shutil.which(RUSTC) is None:
+        print("Warning: rustc not found, skipping the rustc size baseline")
+        args.rustc = False
+
+    files = collect_corpus(args.corpus, args.filter)
+    if not files:
+        print(f"Error: no .tml programs found in {args.corpus}")
+        sys.exit(1)
+
+    print("=" * 60)
+    print("TML Backend Comparison (Cranelift vs LLVM)")
+    print("=" * 60)
+    print(f"Compiler: {TML_COMPILER}")
+    print(f"Programs: {len(files)}")
+    print(f"Configurations: {', '.join(f'{b}-{o}' for b in BACKENDS for o in OPT_LEVELS)}")
+
+    results = run_comparison(files, args)
+    recommendations = recommend(results)
+    report = generate_report(results, recommendations)
+
+    RESULTS_DIR.mkdir(exist_ok=True)
+    if args.output:
+        output_path = Path(args.output)
+    else:
+        timestamp = datetime.now().strftime("%Y%m%d_%H%M%S")
+        output_path = RESULTS_DIR / f"backend_comparison_{timestamp}.md"
+
+    output_path.write_text(report, encoding="utf-8")
+    print(f"\nReport saved to: {output_path}")
+
+    json_path = output_path.with_suffix(".json")
+    with open(json_path, "w") as f:
+        json.dump({"results": results, "recommendations": recommendations}, f, indent=2)
+    print(f"JSON data saved to: {json_path}")
+
+    print("\n" + "=" * 60)
+    for profile, rec in recommendations.items():
+        print(f"Suggested {profile} backend: {rec['backend'] or 'N/A'}")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,111 @@
+#!/usr/bin/env python3
+"""
+Cranelift CPU class timings
+
+Compiles the `algorithms` and `math` end-to-end fixtures at O2 once per CPU
+class, links each against the runtime stub and runs them in interleaved
+rounds. Prints the median time of every benchmark loop per class as the
+Markdown table in docs/specs/16-COMPILER-ARCHITECTURE.md ("CPU tuning"),
+and whether the classes produced the same .text.
+
+Needs cargo, a C compiler (`CC`, default `cc`) and, for the .text check,
+objcopy. Run from anywhere:
+
+    python3 compiler/cranelift/scripts/cpu_class_timings.py [--rounds 15]
+"""
+
+import argparse
+import json
+import os
+import re
+import statistics
+import subprocess
+import sys
+import tempfile
+from pathlib import Path
+
+BRIDGE_DIR = Path(__file__).resolve().parent.parent
+FIXTURES = BRIDGE_DIR / "tests" / "fixtures"
+PROGRAMS = ["algorithms", "math"]
+CLASSES = ["baseline", "nehalem", "haswell", "skylake-avx512", "native"]
+
+# Timing lines of the fixtures: "<benchmark>: <n> ns"
+TIMING = re.compile(r"^(.+): (\d+) ns$")
+
+
+def run(cmd, **kwargs):
+    return subprocess.run(cmd, check=True, **kwargs)
+
+
+def build_replay():
+    """Build cranelift-replay in release mode and return its path."""
+    run(["cargo", "build", "--release", "--features", "dev-tools", "--bin", "cranelift-replay"], cwd=BRIDGE_DIR)
+    meta = run(["cargo", "metadata", "--format-version", "1", "--no-deps"],
+               cwd=BRIDGE_DIR, capture_output=True, text=True)
+    target_dir = Path(json.loads(meta.stdout)["target_directory"])
+    exe = "cranelift-replay.exe" if os.name == "nt" else "cranelift-replay"
+    return target_dir / "release" / exe
+
+
+def text_section(obj, work):
+    """The .text bytes of `obj`, or None without objcopy."""
+    out = work / (obj.name + ".text")
+    try:
+        run(["objcopy", "-O", "binary", "--only-section=.text", str(obj), str(out)], capture_output=True)
+    except (OSError, subprocess.CalledProcessError):
+        return None
+    return out.read_bytes()
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.strip().splitlines()[0])
+    parser.add_argument("--rounds", type=int, default=15, help="runs of every program per class")
+    args = parser.parse_args()
+
+    replay = build_replay()
+    cc = os.environ.get("CC", "cc")
+    with tempfile.TemporaryDirectory() as tmp:
+        work = Path(tmp)
+        stub = work / "runtime_stub.o"
+        run([cc, "-std=c11", "-O1", "-c", "-o", str(stub), str(FIXTURES / "runtime_stub.c")])
+
+        exes = {}
+        texts = {}
+        for program in PROGRAMS:
+            for cpu in CLASSES:
+                obj = work / f"{program}.{cpu}.o"
+                exe = work / f"{program}.{cpu}"
+                mir = FIXTURES / "programs" / f"{program}.mir"
+                run([str(replay), "compile", str(mir), "--set", f"opt_level=2,cpu={cpu}", "-o", str(obj)],
+                    capture_output=True)
+                run([cc, "-o", str(exe), str(obj), str(stub), "-lm"], capture_output=True)
+                exes[(program, cpu)] = exe
+                texts[(program, cpu)] = text_section(obj, work)
+
+        # Interleave the classes so machine noise spreads over all of them
+        samples = {}
+        for _ in range(args.rounds):
+            for (program, cpu), exe in exes.items():
+                out = run([str(exe)], capture_output=True, text=True).stdout
+                for line in out.splitlines():
+                    m = TIMING.match(line.strip())
+                    if m:
+                        samples.setdefault(m.group(1), {}).setdefault(cpu, []).append(int(m.group(2)))
+
+    print("| Benchmark | " + " | ".join(CLASSES) + " |")
+    print("|-----------|" + "|".join("-" * (len(c) + 1) + ":" for c in CLASSES) + "|")
+    for name, per_class in samples.items():
+        cells = [f"{statistics.median(per_class[c]) / 1e6:.2f}" for c in CLASSES]
+        print(f"| {name} | " + " | ".join(cells) + " |")
+    print()
+    for program in PROGRAMS:
+        found = {texts[(program, c)] for c in CLASSES}
+        if None in found:
+            print(f"{program}: .text not compared (no objcopy)")
+        else:
+            print(f"{program}: {'identical' if len(found) == 1 else 'different'} .text across classes")
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
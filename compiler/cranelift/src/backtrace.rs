@@ -0,0 +1,60 @@
+//! Panic Backtraces
+//!
+//! A panic caught inside the bridge becomes a `PANIC:` error carrying only
+//! the panic message, which often does not say where it happened ("unknown
+//! panic in Cranelift bridge", an index out of bounds). With the
+//! `panic_backtrace` option the Rust backtrace of the panic is appended to
+//! that message, so a user can report something a maintainer can act on.
+//!
+//! The stack is unwound by the time `catch_unwind` returns, so the backtrace
+//! is captured by a panic hook. The hook is installed the first time a call
+//! asks for backtraces and chains to the hook it replaces. It only captures
+//! on threads whose current call set the option (parallel workers inherit
+//! their caller's setting), into a per-thread slot that `append` empties
+//! when the panic is turned into a message. Function names need the symbol
+//! table; a stripped library shows addresses only.
+
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::sync::Once;
+
+thread_local! {
+    /// Whether the call on this thread asked for backtraces
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    /// Backtrace of the last panic on this thread, until taken
+    static LAST: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static INSTALL_HOOK: Once = Once::new();
+
+/// Capture the backtraces of panics on this thread when `on`, until changed.
+pub fn enable(on: bool) {
+    ENABLED.with(|enabled| enabled.set(on));
+    LAST.with(|last| last.borrow_mut().take());
+    if on {
+        INSTALL_HOOK.call_once(|| {
+            let previous = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                if ENABLED.try_with(Cell::get).unwrap_or(false) {
+                    let trace = Backtrace::force_capture().to_string();
+                    let _ = LAST.try_with(|last| *last.borrow_mut() = Some(trace));
+                }
+                previous(info);
+            }));
+        });
+    }
+}
+
+/// Whether panics on this thread have their backtraces captured.
+pub fn enabled() -> bool {
+    ENABLED.with(Cell::get)
+}
+
+/// `message` of a panic caught on this thread, followed by the panic's
+/// backtrace when one was captured.
+pub fn append(message: String) -> String {
+    match LAST.with(|last| last.borrow_mut().take()) {
+        Some(trace) if enabled() => format!("{}\nbacktrace:\n{}", message, trace.trim_end()),
+        _ => message,
+    }
+}
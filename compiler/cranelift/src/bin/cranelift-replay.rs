@@ -0,0 +1,145 @@
+//! cranelift-replay — run the Cranelift bridge without the C++ compiler
+//!
+//! Loads a MIR module in the binary format the driver hands to the bridge, or a
+//! replay bundle written through the `replay_dir` option, and runs one bridge
+//! operation on it. Bundles replay with the options they were recorded with; `--target`,
+//! `--profile` and `--set` adjust them.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use tml_cranelift_bridge::dev::{self, Input, Severity};
+
+const USAGE: &str = "\
+usage: cranelift-replay <command> <file> [options]
+
+commands:
+  compile    compile to an object file (written next to the input, or to -o)
+  ir-dump    print the Cranelift IR
+  validate   check every function's CFG and value references
+  stats      print instruction, block and register pressure statistics
+
+options:
+  -o <path>            object file to write (compile)
+  --target <triple>    compile for another target
+  --profile <name>     apply an option profile (debug, release, small, jit)
+  --set <k=v,...>      override option fields, as in profile_overrides
+  --show-options       print the options before running";
+
+const COMMANDS: &[&str] = &["compile", "ir-dump", "validate", "stats"];
+
+struct Args {
+    command: String,
+    input: PathBuf,
+    output: Option<PathBuf>,
+    target: Option<String>,
+    profile: Option<String>,
+    overrides: Vec<String>,
+    show_options: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = std::env::args().skip(1);
+    let command = args.next().ok_or("missing command")?;
+    if command == "-h" || command == "--help" {
+        return Err(String::new());
+    }
+    if !COMMANDS.contains(&command.as_str()) {
+        return Err(format!("unknown command '{}'", command));
+    }
+    let mut input = None;
+    let mut parsed = Args {
+        command,
+        input: PathBuf::new(),
+        output: None,
+        target: None,
+        profile: None,
+        overrides: Vec::new(),
+        show_options: false,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "-o" => parsed.output = Some(PathBuf::from(value()?)),
+            "--target" => parsed.target = Some(value()?),
+            "--profile" => parsed.profile = Some(value()?),
+            "--set" => parsed.overrides.push(value()?),
+            "--show-options" => parsed.show_options = true,
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+    parsed.input = input.ok_or("missing input file")?;
+    Ok(parsed)
+}
+
+fn run(args: &Args) -> Result<bool, String> {
+    let mut input = Input::load(&args.input)?;
+    if let Some(version) = &input.bridge_version {
+        eprintln!("replay bundle: {} call, bridge {}", input.entry, version);
+        if let Some(error) = &input.recorded_error {
+            eprintln!("recorded error: {}", error);
+        }
+    }
+    if let Some(target) = &args.target {
+        input.set_target(target)?;
+    }
+    if let Some(profile) = &args.profile {
+        input.set_profile(profile)?;
+    }
+    for overrides in &args.overrides {
+        input.apply_overrides(overrides)?;
+    }
+    if args.show_options {
+        eprint!("{}", input.options());
+    }
+
+    match args.command.as_str() {
+        "compile" => {
+            let object = dev::compile(&input)?;
+            let output = args.output.clone().unwrap_or_else(|| object_path(&args.input));
+            std::fs::write(&output, &object).map_err(|e| format!("cannot write {}: {}", output.display(), e))?;
+            println!("wrote {} ({} bytes)", output.display(), object.len());
+        }
+        "ir-dump" => print!("{}", dev::ir_dump(&input)?),
+        "validate" => {
+            let findings = dev::validate(&input)?;
+            for finding in &findings {
+                println!("{}", finding);
+            }
+            let errors = findings.iter().filter(|d| d.severity == Severity::Error).count();
+            println!("{} errors, {} warnings", errors, findings.len() - errors);
+            return Ok(errors == 0);
+        }
+        "stats" => print!("{}", dev::stats(&input)?),
+        _ => unreachable!("command checked by parse_args"),
+    }
+    Ok(true)
+}
+
+fn object_path(input: &Path) -> PathBuf {
+    let ext = if cfg!(windows) { "obj" } else { "o" };
+    input.with_extension(ext)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("error: {}\n", e);
+            }
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
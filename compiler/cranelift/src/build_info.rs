@@ -0,0 +1,60 @@
+//! Build Information Section
+//!
+//! With `emit_build_info`, each object carries a `.tml.build` section naming
+//! what produced it, so a shipped binary can be traced back to the backend
+//! and settings of its objects. The payload is text, one `key=value` per
+//! line, readable with `readelf -p .tml.build` or `strings`:
+//!
+//! ```text
+//! producer=tml-cranelift-bridge 0.1.0
+//! cranelift=0.128.3
+//! mir=1.2
+//! target=x86_64-unknown-linux-gnu
+//! profile=release
+//! opt_level=2
+//! cpu=native
+//! features=has_sse3,has_ssse3,has_sse41,...
+//! ```
+//!
+//! `mir` is the newest MIR format the bridge reads, `features` the ISA
+//! extensions the code was compiled with. A linked binary holds one copy
+//! per object that had the option set.
+
+use cranelift_codegen::isa::TargetIsa;
+
+use crate::cpu;
+use crate::mir_reader::{MIR_VERSION_MAJOR, MIR_VERSION_MINOR};
+use crate::options::BridgeOptions;
+
+/// Section name for the build information (ELF/COFF).
+pub const BUILD_SECTION: &str = ".tml.build";
+/// Mach-O segment/section pair for the same data.
+pub const BUILD_SECTION_MACHO: (&str, &str) = ("__TML", "__tml_build");
+
+/// The section payload for code compiled with `options` for `isa`.
+pub fn encode(options: &BridgeOptions, isa: &dyn TargetIsa) -> Vec<u8> {
+    let features: Vec<_> = isa
+        .isa_flags()
+        .into_iter()
+        .filter(|flag| flag.as_bool() == Some(true))
+        .map(|flag| flag.name)
+        .collect();
+    let fields = [
+        ("producer", format!("tml-cranelift-bridge {}", env!("CARGO_PKG_VERSION"))),
+        ("cranelift", cranelift_object::VERSION.to_string()),
+        ("mir", format!("{}.{}", MIR_VERSION_MAJOR, MIR_VERSION_MINOR)),
+        ("target", isa.triple().to_string()),
+        ("profile", options.profile.clone()),
+        ("opt_level", options.opt_level.to_string()),
+        ("cpu", cpu::class(options).to_string()),
+        ("features", features.join(",")),
+    ];
+    let mut out = String::new();
+    for (key, value) in fields {
+        out.push_str(key);
+        out.push('=');
+        out.push_str(&value);
+        out.push('\n');
+    }
+    out.into_bytes()
+}
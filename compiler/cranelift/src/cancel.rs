@@ -0,0 +1,83 @@
+//! Compile Cancellation
+//!
+//! A build driver that sees the user edit a file again wants the compiles
+//! already running for the old sources to stop. It creates a token with
+//! `cranelift_cancel_token_new`, passes it in the options of every compile
+//! of the build, and calls `cranelift_cancel` to stop them all. Translation
+//! checks the token before each function, so a cancelled compile returns
+//! within one function's worth of work, with `BridgeError::Cancelled`.
+//!
+//! Tokens handed to C++ are registered by address, like result buffers: an
+//! unknown or freed handle is an error in the options and a no-op in
+//! `cranelift_cancel`, never a dangling read. A compile holds its own
+//! reference, so freeing a token while it runs is safe (it just can no
+//! longer be cancelled).
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::error::{BridgeError, BridgeResult};
+
+/// Opaque C handle of a cancel token.
+#[repr(C)]
+pub struct CraneliftCancelToken {
+    _private: [u8; 0],
+}
+
+/// The flag a compile polls.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Fail with `Cancelled` once `token` is set.
+pub fn check(token: Option<&CancelToken>) -> BridgeResult<()> {
+    match token {
+        Some(t) if t.is_cancelled() => Err(BridgeError::Cancelled),
+        _ => Ok(()),
+    }
+}
+
+/// Tokens handed out and not freed: handle address → token.
+static LIVE_TOKENS: Mutex<BTreeMap<usize, CancelToken>> = Mutex::new(BTreeMap::new());
+
+fn live_tokens() -> MutexGuard<'static, BTreeMap<usize, CancelToken>> {
+    LIVE_TOKENS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Register a new token and return its handle.
+pub fn new_handle() -> *mut CraneliftCancelToken {
+    let token = CancelToken::default();
+    let handle = Arc::as_ptr(&token.0) as *mut CraneliftCancelToken;
+    live_tokens().insert(handle as usize, token);
+    handle
+}
+
+/// The token behind a live handle.
+pub fn lookup(handle: *const CraneliftCancelToken) -> Option<CancelToken> {
+    live_tokens().get(&(handle as usize)).cloned()
+}
+
+/// Resolve an options handle; null means none.
+pub fn from_handle(name: &str, handle: *const CraneliftCancelToken) -> BridgeResult<Option<CancelToken>> {
+    if handle.is_null() {
+        return Ok(None);
+    }
+    lookup(handle)
+        .map(Some)
+        .ok_or_else(|| BridgeError::FfiMisuse(format!("{}: {:#x} is not a live cancel token", name, handle as usize)))
+}
+
+/// Unregister a handle; unknown handles are ignored.
+pub fn free_handle(handle: *const CraneliftCancelToken) {
+    live_tokens().remove(&(handle as usize));
+}
@@ -0,0 +1,130 @@
+//! CLIF Text Output
+//!
+//! Prints Cranelift functions for `--emit-clif`, decorated with the names
+//! carried over from MIR: each block header is preceded by a comment with the
+//! MIR block name, and stack slots created for allocas are annotated with the
+//! local's name. Comments keep the text parseable by `clif-util`.
+//!
+//! With annotation enabled every function is framed by machine-readable
+//! separator lines:
+//!
+//! ```text
+//! ;; @function index=3 name=add symbol=tml_add
+//! ;; @signature (i32, i32) -> i32 system_v
+//! function u0:3(i32, i32) -> i32 system_v { ... }
+//! ;; @end
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use cranelift_codegen::entity::SecondaryMap;
+use cranelift_codegen::ir::entities::AnyEntity;
+use cranelift_codegen::ir::pcc::Fact;
+use cranelift_codegen::ir::{Block, Function as ClifFunc, Inst, Signature, StackSlot, Value};
+use cranelift_codegen::write::{self, FuncWriter};
+
+/// MIR names for the entities of one translated function.
+#[derive(Debug, Default)]
+pub struct DebugNames {
+    /// Cranelift block → MIR block (id, name)
+    pub blocks: HashMap<Block, (u32, String)>,
+    /// Stack slot → MIR alloca name
+    pub slots: HashMap<StackSlot, String>,
+}
+
+struct AnnotatedWriter<'a> {
+    names: &'a DebugNames,
+}
+
+impl FuncWriter for AnnotatedWriter<'_> {
+    fn write_block_header(
+        &mut self,
+        w: &mut dyn Write,
+        func: &ClifFunc,
+        block: Block,
+        indent: usize,
+    ) -> fmt::Result {
+        if let Some((id, name)) = self.names.blocks.get(&block) {
+            if name.is_empty() || *name == format!("bb{}", id) {
+                writeln!(w, "{:1$}; bb{2}", "", indent - 4, id)?;
+            } else {
+                writeln!(w, "{:1$}; {2} (bb{3})", "", indent - 4, name, id)?;
+            }
+        }
+        write::write_block_header(w, func, block, indent)
+    }
+
+    fn write_instruction(
+        &mut self,
+        w: &mut dyn Write,
+        func: &ClifFunc,
+        aliases: &SecondaryMap<Value, Vec<Value>>,
+        inst: Inst,
+        indent: usize,
+    ) -> fmt::Result {
+        write::PlainWriter.write_instruction(w, func, aliases, inst, indent)
+    }
+
+    fn write_entity_definition(
+        &mut self,
+        w: &mut dyn Write,
+        func: &ClifFunc,
+        entity: AnyEntity,
+        value: &dyn fmt::Display,
+        maybe_fact: Option<&Fact>,
+    ) -> fmt::Result {
+        if let AnyEntity::StackSlot(slot) = entity
+            && let Some(name) = self.names.slots.get(&slot).filter(|n| !n.is_empty())
+        {
+            return writeln!(w, "    {} = {}  ; {}", entity, value, name);
+        }
+        self.super_entity_definition(w, func, entity, value, maybe_fact)
+    }
+}
+
+/// Render `func` as CLIF text annotated with `names`.
+pub fn write_function(func: &ClifFunc, names: &DebugNames) -> String {
+    let mut text = String::new();
+    // Writing into a String cannot fail
+    let _ = write::decorate_function(&mut AnnotatedWriter { names }, &mut text, func);
+    text
+}
+
+/// Separator closing an annotated function.
+pub const FUNCTION_END: &str = ";; @end\n";
+
+/// Separator lines opening an annotated function.
+pub fn function_header(index: usize, name: &str, symbol: &str, sig: &Signature) -> String {
+    format!(
+        ";; @function index={} name={} symbol={}\n;; @signature {}\n",
+        index, name, symbol, sig
+    )
+}
+
+/// Match `name` against a glob supporting `*` (any run) and `?` (one char).
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    // Position of the last `*` and the name index it is currently matching up to
+    let mut star: Option<(usize, usize)> = None;
+
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((star_pi, star_ni)) = star {
+            // Let the last `*` absorb one more character and retry
+            pi = star_pi + 1;
+            ni = star_ni + 1;
+            star = Some((star_pi, star_ni + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
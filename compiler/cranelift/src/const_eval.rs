@@ -0,0 +1,474 @@
+//! Compile-Time Evaluation of Constant Functions
+//!
+//! A function without parameters whose body only computes on constants
+//! (arithmetic, casts, selects, phis and branches; no memory, calls or
+//! strings) always returns the same value. `fold_module` runs such bodies
+//! through a small MIR interpreter so call sites can use the result as an
+//! immediate instead of calling the function.
+//!
+//! The interpreter follows the translator's lowering exactly: operands of
+//! different widths are extended by the same signedness rules, `Div`/`Mod`
+//! are signed, `Shr` is arithmetic and shift amounts wrap at the operand
+//! width. Anything that would trap or produce a NaN at run time is left to
+//! the real code.
+
+use std::collections::HashMap;
+
+use crate::mir_types::*;
+use crate::translate::{int_signedness, switch_case_pattern};
+
+/// Instructions executed before evaluation of one function gives up.
+const MAX_STEPS: usize = 10_000;
+
+/// Evaluate every constant function of `mir` whose name is unique in the
+/// module. Returns MIR name → result, typed as the function's return type.
+pub fn fold_module(mir: &Module) -> HashMap<String, Constant> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for func in &mir.functions {
+        *counts.entry(&func.name).or_default() += 1;
+    }
+    mir.functions
+        .iter()
+        .filter(|f| counts[f.name.as_str()] == 1)
+        .filter_map(|f| Some((f.name.clone(), evaluate(f)?)))
+        .collect()
+}
+
+/// Result of calling `func`, if it is a constant function that finishes
+/// within `MAX_STEPS` instructions.
+pub fn evaluate(func: &Function) -> Option<Constant> {
+    if !func.params.is_empty() || func.blocks.is_empty() {
+        return None;
+    }
+    let ret = scalar_type(&func.return_type)?;
+    let mut interp = Interpreter::new(func)?;
+    let value = interp.run()?;
+    value.coerce(ret)?.into_constant(&func.return_type)
+}
+
+/// Interpreter view of the Cranelift types constant functions compute with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScalarType {
+    Int(u32),
+    F32,
+    F64,
+}
+
+fn scalar_type(ty: &MirType) -> Option<ScalarType> {
+    match ty {
+        MirType::Primitive(p) if p.is_integer() || *p == PrimitiveType::Bool => {
+            match p.bit_width() {
+                bits @ (8 | 16 | 32 | 64) => Some(ScalarType::Int(bits)),
+                _ => None,
+            }
+        }
+        MirType::Primitive(PrimitiveType::F32) => Some(ScalarType::F32),
+        MirType::Primitive(PrimitiveType::F64) => Some(ScalarType::F64),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Scalar {
+    /// Bit pattern in the low `bits` bits, upper bits zero
+    Int { bits: u32, raw: u64 },
+    F32(f32),
+    F64(f64),
+}
+
+fn mask(bits: u32) -> u64 {
+    if bits >= 64 { u64::MAX } else { (1u64 << bits) - 1 }
+}
+
+impl Scalar {
+    fn int(bits: u32, value: i64) -> Self {
+        Scalar::Int { bits, raw: value as u64 & mask(bits) }
+    }
+
+    fn bool(b: bool) -> Self {
+        Scalar::Int { bits: 8, raw: b as u64 }
+    }
+
+    fn ty(self) -> ScalarType {
+        match self {
+            Scalar::Int { bits, .. } => ScalarType::Int(bits),
+            Scalar::F32(_) => ScalarType::F32,
+            Scalar::F64(_) => ScalarType::F64,
+        }
+    }
+
+    fn signed(self) -> Option<i64> {
+        match self {
+            Scalar::Int { bits, raw } => Some(((raw << (64 - bits)) as i64) >> (64 - bits)),
+            _ => None,
+        }
+    }
+
+    fn unsigned(self) -> Option<u64> {
+        match self {
+            Scalar::Int { raw, .. } => Some(raw),
+            _ => None,
+        }
+    }
+
+    fn float(self) -> Option<f64> {
+        match self {
+            Scalar::F32(f) => Some(f as f64),
+            Scalar::F64(f) => Some(f),
+            Scalar::Int { .. } => None,
+        }
+    }
+
+    fn from_float(ty: ScalarType, f: f64) -> Option<Self> {
+        match ty {
+            ScalarType::F32 => Some(Scalar::F32(f as f32)),
+            ScalarType::F64 => Some(Scalar::F64(f)),
+            ScalarType::Int(_) => None,
+        }
+    }
+
+    /// Integer → float conversion, rounding once to the target precision.
+    fn int_to_float(self, ty: ScalarType, signed: bool) -> Option<Self> {
+        match (ty, signed) {
+            (ScalarType::F32, true) => Some(Scalar::F32(self.signed()? as f32)),
+            (ScalarType::F32, false) => Some(Scalar::F32(self.unsigned()? as f32)),
+            (ScalarType::F64, true) => Some(Scalar::F64(self.signed()? as f64)),
+            (ScalarType::F64, false) => Some(Scalar::F64(self.unsigned()? as f64)),
+            (ScalarType::Int(_), _) => None,
+        }
+    }
+
+    /// Extend or truncate an integer to `bits`.
+    fn resize(self, bits: u32, signed: bool) -> Option<Self> {
+        let value = if signed { self.signed()? } else { self.unsigned()? as i64 };
+        Some(Scalar::int(bits, value))
+    }
+
+    /// `coerce_value`: integers sign-extend or truncate, floats convert.
+    fn coerce(self, target: ScalarType) -> Option<Self> {
+        match (self.ty(), target) {
+            (a, b) if a == b => Some(self),
+            (ScalarType::Int(_), ScalarType::Int(bits)) => self.resize(bits, true),
+            (ScalarType::F32 | ScalarType::F64, ScalarType::F32 | ScalarType::F64) => {
+                Scalar::from_float(target, self.float()?)
+            }
+            _ => None,
+        }
+    }
+
+    fn into_constant(self, ty: &MirType) -> Option<Constant> {
+        Some(match self {
+            Scalar::Int { raw, .. } if matches!(ty, MirType::Primitive(PrimitiveType::Bool)) => {
+                Constant::Bool(raw & 1 != 0)
+            }
+            Scalar::Int { bits, .. } => Constant::Int {
+                value: self.signed()?,
+                bit_width: bits as u8,
+                is_signed: int_signedness(ty).unwrap_or(true),
+            },
+            Scalar::F32(f) => Constant::Float { value: f as f64, is_f64: false },
+            Scalar::F64(f) => Constant::Float { value: f, is_f64: true },
+        })
+    }
+}
+
+struct Interpreter<'f> {
+    func: &'f Function,
+    blocks: HashMap<u32, &'f BasicBlock>,
+    /// Integer signedness, inferred like the translator's `collect_signedness`
+    signedness: HashMap<ValueId, bool>,
+    /// Static phi types, inferred like the translator's `infer_phi_type`
+    phi_types: HashMap<ValueId, ScalarType>,
+    values: HashMap<ValueId, Scalar>,
+}
+
+impl<'f> Interpreter<'f> {
+    /// None if `func` uses anything besides pure scalar instructions.
+    fn new(func: &'f Function) -> Option<Self> {
+        let mut types: HashMap<ValueId, ScalarType> = HashMap::new();
+        let mut signedness: HashMap<ValueId, bool> = HashMap::new();
+        let mut phi_types = HashMap::new();
+        for inst in func.blocks.iter().flat_map(|b| &b.instructions) {
+            let ty_of = |v: &Value| types.get(&v.id).copied();
+            let known = |v: &Value| signedness.get(&v.id).copied();
+            let wider = |a: Option<ScalarType>, b: Option<ScalarType>| match (a, b) {
+                (Some(ScalarType::Int(x)), Some(ScalarType::Int(y))) => {
+                    Some(ScalarType::Int(x.max(y)))
+                }
+                (a, b) => a.or(b),
+            };
+            let (ty, signed) = match &inst.inst {
+                Instruction::Constant(Constant::Int { bit_width, is_signed, .. }) => {
+                    if !matches!(bit_width, 8 | 16 | 32 | 64) {
+                        return None;
+                    }
+                    (Some(ScalarType::Int(*bit_width as u32)), Some(*is_signed))
+                }
+                Instruction::Constant(Constant::Float { is_f64, .. }) => {
+                    (Some(if *is_f64 { ScalarType::F64 } else { ScalarType::F32 }), None)
+                }
+                Instruction::Constant(Constant::Bool(_)) => (Some(ScalarType::Int(8)), None),
+                Instruction::Binary { op, .. } if op.is_comparison() => {
+                    (Some(ScalarType::Int(8)), None)
+                }
+                Instruction::Binary { left, right, .. } => {
+                    (wider(ty_of(left), ty_of(right)), known(left).or(known(right)))
+                }
+                Instruction::Unary { operand, .. } => (ty_of(operand), known(operand)),
+                Instruction::Cast { target_type, .. } => {
+                    (Some(scalar_type(target_type)?), int_signedness(target_type))
+                }
+                Instruction::Select { true_val, false_val, .. } => (
+                    wider(ty_of(true_val), ty_of(false_val)),
+                    known(true_val).or(known(false_val)),
+                ),
+                Instruction::Phi { incoming } => {
+                    let ty = incoming.iter().find_map(|(v, _)| ty_of(v));
+                    phi_types.insert(inst.result, ty.unwrap_or(ScalarType::Int(64)));
+                    (ty, incoming.iter().find_map(|(v, _)| known(v)))
+                }
+                _ => return None,
+            };
+            if let Some(ty) = ty {
+                types.insert(inst.result, ty);
+            }
+            if let Some(signed) = signed {
+                signedness.insert(inst.result, signed);
+            }
+        }
+
+        Some(Self {
+            func,
+            blocks: func.blocks.iter().map(|b| (b.id, b)).collect(),
+            signedness,
+            phi_types,
+            values: HashMap::new(),
+        })
+    }
+
+    fn run(&mut self) -> Option<Scalar> {
+        let mut steps = 0;
+        let mut prev: Option<u32> = None;
+        let mut block = &self.func.blocks[0];
+        loop {
+            // Phis read their incoming values simultaneously
+            let mut phis = Vec::new();
+            for inst in &block.instructions {
+                if let Instruction::Phi { incoming } = &inst.inst {
+                    let from = prev?;
+                    let (v, _) = incoming.iter().find(|(_, b)| *b == from)?;
+                    let value = self.get(v)?.coerce(self.phi_types[&inst.result])?;
+                    phis.push((inst.result, value));
+                }
+            }
+            self.values.extend(phis);
+
+            // Terminators count as a step, so empty loops also run out
+            steps += block.instructions.len() + 1;
+            if steps > MAX_STEPS {
+                return None;
+            }
+            for inst in &block.instructions {
+                if matches!(inst.inst, Instruction::Phi { .. }) {
+                    continue;
+                }
+                let value = self.eval(&inst.inst)?;
+                self.values.insert(inst.result, value);
+            }
+
+            let next = match block.terminator.as_ref()? {
+                Terminator::Return { value } => return self.get(value.as_ref()?),
+                Terminator::Branch { target } => *target,
+                Terminator::CondBranch { condition, true_block, false_block } => {
+                    if self.get(condition)?.unsigned()? != 0 {
+                        *true_block
+                    } else {
+                        *false_block
+                    }
+                }
+                Terminator::Switch { discriminant, cases, default_block } => {
+                    let Scalar::Int { bits, raw } = self.get(discriminant)? else {
+                        return None;
+                    };
+                    let signed = self.signedness.get(&discriminant.id).copied();
+                    cases
+                        .iter()
+                        .find(|(c, _)| switch_case_pattern(*c, bits, signed) == Some(raw as u128))
+                        .map_or(*default_block, |(_, b)| *b)
+                }
+                Terminator::Unreachable => return None,
+            };
+            prev = Some(block.id);
+            block = self.blocks.get(&next)?;
+        }
+    }
+
+    fn get(&self, v: &Value) -> Option<Scalar> {
+        self.values.get(&v.id).copied()
+    }
+
+    /// True when integer operands should be treated as unsigned: one side is
+    /// known unsigned and the other is not known signed.
+    fn is_unsigned_pair(&self, a: &Value, b: &Value) -> bool {
+        let (a, b) = (self.signedness.get(&a.id), self.signedness.get(&b.id));
+        matches!((a, b), (Some(false), Some(false) | None) | (None, Some(false)))
+    }
+
+    fn eval(&self, inst: &Instruction) -> Option<Scalar> {
+        match inst {
+            Instruction::Constant(Constant::Int { value, bit_width, .. }) => {
+                Some(Scalar::int(*bit_width as u32, *value))
+            }
+            Instruction::Constant(Constant::Float { value, is_f64 }) => Some(if *is_f64 {
+                Scalar::F64(*value)
+            } else {
+                Scalar::F32(*value as f32)
+            }),
+            Instruction::Constant(Constant::Bool(b)) => Some(Scalar::bool(*b)),
+            Instruction::Binary { op, left, right } => {
+                let unsigned = self.is_unsigned_pair(left, right);
+                binary(*op, self.get(left)?, self.get(right)?, unsigned)
+            }
+            Instruction::Unary { op, operand } => unary(*op, self.get(operand)?),
+            Instruction::Cast { kind, operand, target_type } => {
+                cast(*kind, self.get(operand)?, target_type)
+            }
+            Instruction::Select { condition, true_val, false_val } => {
+                let (tv, fv) = (self.get(true_val)?, self.get(false_val)?);
+                let picked = if self.get(condition)?.unsigned()? != 0 { tv } else { fv };
+                match (tv.ty(), fv.ty()) {
+                    (ScalarType::Int(a), ScalarType::Int(b)) if a != b => {
+                        picked.resize(a.max(b), !self.is_unsigned_pair(true_val, false_val))
+                    }
+                    (a, b) if a == b => Some(picked),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+fn binary(op: BinOp, lhs: Scalar, rhs: Scalar, unsigned: bool) -> Option<Scalar> {
+    // Same operand coercions as `translate_binary`
+    let (lhs, rhs) = match (lhs.ty(), rhs.ty()) {
+        (a, b) if a == b => (lhs, rhs),
+        (ScalarType::Int(a), ScalarType::Int(b)) => {
+            let bits = a.max(b);
+            (lhs.resize(bits, !unsigned)?, rhs.resize(bits, !unsigned)?)
+        }
+        (ScalarType::Int(_), ty) => (lhs.int_to_float(ty, true)?, rhs),
+        (ty, ScalarType::Int(_)) => (lhs, rhs.int_to_float(ty, true)?),
+        _ => (lhs.coerce(ScalarType::F64)?, rhs.coerce(ScalarType::F64)?),
+    };
+    match lhs.ty() {
+        ScalarType::Int(bits) => int_binary(op, bits, lhs, rhs, unsigned),
+        ty => float_binary(op, ty, lhs.float()?, rhs.float()?),
+    }
+}
+
+fn int_binary(op: BinOp, bits: u32, lhs: Scalar, rhs: Scalar, unsigned: bool) -> Option<Scalar> {
+    let (a, b) = (lhs.signed()?, rhs.signed()?);
+    let (ua, ub) = (lhs.unsigned()?, rhs.unsigned()?);
+    let cmp = if unsigned { ua.cmp(&ub) } else { a.cmp(&b) };
+    let shift = (ub & (bits as u64 - 1)) as u32;
+    // Division by zero and MIN / -1 trap at run time
+    let traps = b == 0 || (a == i64::MIN >> (64 - bits) && b == -1);
+    let value = match op {
+        BinOp::Add => a.wrapping_add(b),
+        BinOp::Sub => a.wrapping_sub(b),
+        BinOp::Mul => a.wrapping_mul(b),
+        BinOp::Div | BinOp::Mod if traps => return None,
+        BinOp::Div => a / b,
+        BinOp::Mod => a % b,
+        BinOp::And | BinOp::BitAnd => a & b,
+        BinOp::Or | BinOp::BitOr => a | b,
+        BinOp::BitXor => a ^ b,
+        BinOp::Shl => a.wrapping_shl(shift),
+        BinOp::Shr => a >> shift,
+        BinOp::Eq | BinOp::Ueq => return Some(Scalar::bool(a == b)),
+        BinOp::Ne | BinOp::One => return Some(Scalar::bool(a != b)),
+        BinOp::Lt | BinOp::Ult => return Some(Scalar::bool(cmp.is_lt())),
+        BinOp::Le | BinOp::Ule => return Some(Scalar::bool(cmp.is_le())),
+        BinOp::Gt | BinOp::Ugt => return Some(Scalar::bool(cmp.is_gt())),
+        BinOp::Ge | BinOp::Uge => return Some(Scalar::bool(cmp.is_ge())),
+        BinOp::Ord => return Some(Scalar::bool(true)),
+        BinOp::Uno => return Some(Scalar::bool(false)),
+    };
+    Some(Scalar::int(bits, value))
+}
+
+fn float_binary(op: BinOp, ty: ScalarType, a: f64, b: f64) -> Option<Scalar> {
+    let uno = a.is_nan() || b.is_nan();
+    let flag = |b: bool| Some(Scalar::bool(b));
+    let value = match op {
+        BinOp::Add => a + b,
+        BinOp::Sub => a - b,
+        BinOp::Mul => a * b,
+        BinOp::Div => a / b,
+        BinOp::Eq => return flag(a == b),
+        BinOp::Ne => return flag(a != b),
+        BinOp::Lt => return flag(a < b),
+        BinOp::Le => return flag(a <= b),
+        BinOp::Gt => return flag(a > b),
+        BinOp::Ge => return flag(a >= b),
+        BinOp::Ord => return flag(!uno),
+        BinOp::Uno => return flag(uno),
+        BinOp::One => return flag(!uno && a != b),
+        BinOp::Ueq => return flag(uno || a == b),
+        BinOp::Ult => return flag(uno || a < b),
+        BinOp::Ule => return flag(uno || a <= b),
+        BinOp::Ugt => return flag(uno || a > b),
+        BinOp::Uge => return flag(uno || a >= b),
+        _ => return None,
+    };
+    // F32 operands are computed in f64 and narrowed. f64 has more than twice
+    // the precision of f32, so this rounds exactly like f32 arithmetic.
+    Scalar::from_float(ty, value).filter(|r| !r.float().is_some_and(f64::is_nan))
+}
+
+fn unary(op: UnaryOp, operand: Scalar) -> Option<Scalar> {
+    match (op, operand) {
+        (UnaryOp::Neg, Scalar::F32(f)) => Some(Scalar::F32(-f)),
+        (UnaryOp::Neg, Scalar::F64(f)) => Some(Scalar::F64(-f)),
+        (UnaryOp::Neg, Scalar::Int { bits, .. }) => {
+            Some(Scalar::int(bits, operand.signed()?.wrapping_neg()))
+        }
+        (UnaryOp::Not, Scalar::Int { bits, raw }) => Some(Scalar::Int { bits, raw: raw ^ 1 }),
+        (UnaryOp::BitNot, Scalar::Int { bits, raw }) => {
+            Some(Scalar::Int { bits, raw: !raw & mask(bits) })
+        }
+        _ => None,
+    }
+}
+
+fn cast(kind: CastKind, operand: Scalar, target_type: &MirType) -> Option<Scalar> {
+    let target = scalar_type(target_type)?;
+    let to_bool = matches!(target_type, MirType::Primitive(PrimitiveType::Bool));
+    match (kind, operand.ty(), target) {
+        (CastKind::Trunc | CastKind::ZExt | CastKind::SExt, ScalarType::Int(src), ScalarType::Int(bits)) => {
+            if to_bool && (kind == CastKind::Trunc || src > 8) {
+                Some(Scalar::bool(operand.unsigned()? & 1 != 0))
+            } else {
+                operand.resize(bits, kind == CastKind::SExt)
+            }
+        }
+        (CastKind::FPTrunc | CastKind::FPExt, ScalarType::F32 | ScalarType::F64, _) => {
+            Scalar::from_float(target, operand.float()?)
+        }
+        (CastKind::SIToFP | CastKind::UIToFP, ScalarType::Int(_), _) => {
+            operand.int_to_float(target, kind == CastKind::SIToFP)
+        }
+        (CastKind::BoolCast, _, ScalarType::Int(bits)) => {
+            let flag = match operand {
+                // NaN counts as true, as in C
+                Scalar::F32(_) | Scalar::F64(_) => operand.float()? != 0.0,
+                Scalar::Int { raw, .. } => raw != 0,
+            };
+            Some(Scalar::int(bits, flag as i64))
+        }
+        // Float → int conversions trap when out of range; bitcasts are rare
+        // enough in constant code to leave to the translator
+        _ => None,
+    }
+}
@@ -0,0 +1,147 @@
+//! Constant Pool
+//!
+//! Generated code often repeats the same numeric literal many times in one
+//! function (coefficient tables, unrolled loops), and each MIR `Constant`
+//! would otherwise become its own `iconst`/`f64const`. This pass counts the
+//! scalar constants of a function's live blocks; a constant seen more than
+//! once is materialized a single time, at the top of the nearest block that
+//! dominates every block defining it. Hoisting all of them to the entry
+//! block instead would keep each one live, and in a register, across the
+//! whole function; the nearest dominator shares it just as well.
+//!
+//! With `const_pool_threshold` set, a function with more distinct float
+//! constants than the threshold gets them in a read-only `.const.<func>`
+//! data object instead, and each float is a load from it.
+
+use std::collections::{HashMap, HashSet};
+
+use cranelift_codegen::ir::{types, Type};
+
+use crate::mir_types::*;
+
+/// A scalar constant by type and bit pattern. Floats compare by bits, so
+/// `0.0` and `-0.0` stay apart and NaNs are shared only with identical NaNs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConstKey {
+    pub ty: Type,
+    pub bits: u64,
+}
+
+impl ConstKey {
+    /// Key of an integer, bool or float constant; None for the others.
+    pub fn of(constant: &Constant) -> Option<Self> {
+        let (ty, bits) = match constant {
+            Constant::Int { value, bit_width, .. } => (int_type(*bit_width), *value as u64),
+            Constant::Bool(b) => (types::I8, *b as u64),
+            Constant::Float { value, is_f64: true } => (types::F64, value.to_bits()),
+            Constant::Float { value, is_f64: false } => {
+                (types::F32, (*value as f32).to_bits() as u64)
+            }
+            _ => return None,
+        };
+        Some(Self { ty, bits })
+    }
+
+    pub fn is_float(&self) -> bool {
+        self.ty.is_float()
+    }
+}
+
+/// Cranelift type of an integer constant of `bit_width` bits.
+pub fn int_type(bit_width: u8) -> Type {
+    match bit_width {
+        8 => types::I8,
+        16 => types::I16,
+        32 => types::I32,
+        64 => types::I64,
+        128 => types::I128,
+        _ => types::I64,
+    }
+}
+
+/// How the scalar constants of one function are emitted.
+#[derive(Debug, Default)]
+pub struct ConstPlan {
+    /// Constants used more than once, in order of first use, with the
+    /// block they are materialized in
+    pub shared: Vec<(ConstKey, u32)>,
+    /// Byte offset of each pooled float in the function's rodata object
+    pub pooled: HashMap<ConstKey, u32>,
+    /// Contents of the rodata object (empty when nothing is pooled)
+    pub pool_bytes: Vec<u8>,
+}
+
+/// Plan the constants of `func`'s `live` blocks, whose immediate
+/// dominators are `idom` (see `validate::immediate_dominators`). Floats are
+/// pooled when there are more than `pool_threshold` distinct ones (0 =
+/// never); f64s come first so every entry is naturally aligned.
+/// `big_endian` is the target's byte order.
+pub fn plan(
+    func: &Function,
+    live: &HashSet<u32>,
+    idom: &HashMap<u32, u32>,
+    pool_threshold: usize,
+    big_endian: bool,
+) -> ConstPlan {
+    let mut counts: HashMap<ConstKey, usize> = HashMap::new();
+    let mut homes: HashMap<ConstKey, u32> = HashMap::new();
+    let mut order = Vec::new();
+    for block in func.blocks.iter().filter(|b| live.contains(&b.id)) {
+        for inst in &block.instructions {
+            let Instruction::Constant(constant) = &inst.inst else {
+                continue;
+            };
+            if let Some(key) = ConstKey::of(constant) {
+                let count = counts.entry(key).or_insert(0);
+                if *count == 0 {
+                    order.push(key);
+                }
+                *count += 1;
+                homes
+                    .entry(key)
+                    .and_modify(|home| *home = common_dominator(idom, *home, block.id))
+                    .or_insert(block.id);
+            }
+        }
+    }
+
+    let mut plan = ConstPlan {
+        shared: order.iter().filter(|k| counts[k] > 1).map(|k| (*k, homes[k])).collect(),
+        ..ConstPlan::default()
+    };
+
+    let floats = order.iter().filter(|k| k.is_float()).count();
+    if pool_threshold == 0 || floats <= pool_threshold {
+        return plan;
+    }
+    for width in [types::F64, types::F32] {
+        for key in order.iter().filter(|k| k.ty == width) {
+            plan.pooled.insert(*key, plan.pool_bytes.len() as u32);
+            let bytes = key.bits.to_le_bytes();
+            let mut entry = bytes[..width.bytes() as usize].to_vec();
+            if big_endian {
+                entry.reverse();
+            }
+            plan.pool_bytes.extend(entry);
+        }
+    }
+    plan
+}
+
+/// The nearest block dominating both `a` and `b`.
+fn common_dominator(idom: &HashMap<u32, u32>, a: u32, b: u32) -> u32 {
+    let mut above_a = HashSet::from([a]);
+    let mut up = a;
+    while let Some(&next) = idom.get(&up).filter(|&&next| next != up) {
+        above_a.insert(next);
+        up = next;
+    }
+    let mut up = b;
+    while !above_a.contains(&up) {
+        match idom.get(&up) {
+            Some(&next) if next != up => up = next,
+            _ => break,
+        }
+    }
+    up
+}
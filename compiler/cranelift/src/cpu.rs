@@ -0,0 +1,200 @@
+//! CPU Classes and Cranelift Flags
+//!
+//! Builds the ISA a module is compiled for: the architecture and object
+//! format of `target_triple` (the host when empty), with the instruction set
+//! extensions the `cpu` option picks:
+//!
+//! - `native`: whatever the compiling host supports, detected at run time.
+//!   Objects may not run on older CPUs. The default when compiling for the
+//!   host; other targets default to `baseline`.
+//! - `baseline`: the architecture's base ISA (SSE2 on x86-64), for objects
+//!   that must run anywhere.
+//! - an ISA preset of the target architecture, e.g. `nehalem`, `haswell`,
+//!   `skylake-avx512`, `znver3` on x86-64.
+//!
+//! `target_features` then adds or removes single extensions, as a
+//! comma-separated string in the LLVM/rustc form: `+avx2,+bmi2,-fma`, or
+//! `+lse` on AArch64. Without a `cpu`, features start from `baseline` rather
+//! than the host, so a build farm gets the same objects on every machine.
+//!
+//! Cranelift has no per-CPU scheduling or cost model: a class only decides
+//! which extensions instruction selection may use (`popcnt`, `lzcnt`, BMI,
+//! FMA, AVX). Everything else stays tunable through `flag.<name>=<value>`
+//! options, which set a shared Cranelift setting (`enable_probestack`,
+//! `probestack_strategy`, `regalloc_algorithm`, ...) or, failing that, an ISA
+//! setting (`has_avx2`, ...) after the bridge's own choices. Stack probes
+//! are off by default; frames above a page on Windows need
+//! `flag.enable_probestack=true,flag.probestack_strategy=inline`.
+
+use cranelift_codegen::isa::{self, OwnedTargetIsa};
+use cranelift_codegen::settings::{self, Configurable, SetError, SettingKind};
+use target_lexicon::{Architecture, Triple};
+
+use crate::error::{BridgeError, BridgeResult};
+use crate::options::BridgeOptions;
+use crate::profiles;
+use crate::types::POINTER_TYPE;
+
+/// Class using the host's extensions.
+pub const NATIVE: &str = "native";
+/// Class using only the base ISA.
+pub const BASELINE: &str = "baseline";
+
+/// Option key prefix of Cranelift flags.
+pub const FLAG_PREFIX: &str = "flag.";
+
+/// Whether `triple` (empty = the host) runs on the compiling host: same
+/// architecture, operating system and object format. Vendor and environment
+/// spellings (`x86_64-pc-linux-gnu`) do not matter.
+pub fn is_host(triple: &str) -> bool {
+    let host = Triple::host();
+    triple.is_empty()
+        || profiles::target(triple).is_ok_and(|t| {
+            t.architecture == host.architecture
+                && t.operating_system == host.operating_system
+                && t.binary_format == host.binary_format
+        })
+}
+
+/// The CPU class `options` compile for: `cpu`, or when it is unset,
+/// `native` for the host and `baseline` for other targets or when there are
+/// target features to add to it.
+pub fn class(options: &BridgeOptions) -> &str {
+    match options.cpu.as_str() {
+        "" if options.target_features.is_empty() && is_host(&options.target_triple) => NATIVE,
+        "" => BASELINE,
+        cpu => cpu,
+    }
+}
+
+/// The ISA `options` compile for: the CPU class and target features, the
+/// shared flags derived from the other options, then the `flag.` settings.
+pub fn isa(options: &BridgeOptions) -> BridgeResult<OwnedTargetIsa> {
+    let target = profiles::target(&options.target_triple)?;
+    let pic = options.position_independent || pic_required(&target);
+    let mut isa_builder = isa_builder(class(options), &options.target_triple, target)?;
+    apply_features(&mut isa_builder, &options.target_features)?;
+
+    let mut shared_flags = settings::builder();
+    match options.opt_level {
+        0 => {
+            let _ = shared_flags.set("opt_level", "none");
+        }
+        _ => {
+            let _ = shared_flags.set("opt_level", "speed_and_size");
+        }
+    }
+    let _ = shared_flags.set("is_pic", if pic { "true" } else { "false" });
+    // I128 parameters/returns follow the LLVM ABI, as the LLVM backend does
+    let _ = shared_flags.set("enable_llvm_abi_extensions", "true");
+    if options.frame_pointers {
+        let _ = shared_flags.set("preserve_frame_pointers", "true");
+    }
+    if options.nan_canonicalization {
+        let _ = shared_flags.set("enable_nan_canonicalization", "true");
+    }
+
+    for (name, value) in &options.cranelift_flags {
+        let invalid = |e: SetError| {
+            BridgeError::InvalidOptions(format!("{}{}={}: {}", FLAG_PREFIX, name, value, e))
+        };
+        match shared_flags.set(name, value) {
+            Ok(()) => {}
+            Err(SetError::BadName(_)) => isa_builder.set(name, value).map_err(invalid)?,
+            Err(e) => return Err(invalid(e)),
+        }
+    }
+
+    let isa = isa_builder
+        .finish(settings::Flags::new(shared_flags))
+        .map_err(|e| BridgeError::Codegen(format!("failed to build ISA: {}", e)))?;
+    // Layouts and signatures assume `POINTER_TYPE`; fail rather than
+    // miscompile for a target with other pointers
+    if isa.pointer_type() != POINTER_TYPE {
+        return Err(BridgeError::InvalidTarget(format!(
+            "'{}' has {}-bit pointers; only 64-bit targets are supported",
+            isa.triple(),
+            isa.pointer_bits()
+        )));
+    }
+    Ok(isa)
+}
+
+/// Whether code for `target` is position independent whatever the options
+/// say. Non-PIC aarch64 code addresses nearby symbols with an ADRP/ADD pair
+/// whose relocations the object writer cannot emit, so arm64 objects reach
+/// every symbol through the GOT, as Apple's arm64 ABI requires anyway.
+fn pic_required(target: &Triple) -> bool {
+    matches!(target.architecture, Architecture::Aarch64(_))
+}
+
+/// ISA builder for `target` (parsed from `triple`) with the extensions of
+/// `cpu`.
+fn isa_builder(cpu: &str, triple: &str, target: Triple) -> BridgeResult<isa::Builder> {
+    if cpu == NATIVE {
+        if !is_host(triple) {
+            return Err(BridgeError::InvalidOptions(format!(
+                "cpu '{}' needs the host as target, not '{}'",
+                NATIVE, target
+            )));
+        }
+        return cranelift_native::builder().map_err(|e| {
+            BridgeError::InvalidTarget(format!("failed to create native ISA builder: {}", e))
+        });
+    }
+    let mut builder = isa::lookup(target.clone())
+        .map_err(|e| BridgeError::InvalidTarget(format!("no ISA for '{}': {}", target, e)))?;
+    if cpu == BASELINE {
+        return Ok(builder);
+    }
+    let presets: Vec<_> = builder
+        .iter()
+        .filter(|s| s.kind == SettingKind::Preset)
+        .map(|s| s.name)
+        .collect();
+    if !presets.contains(&cpu) {
+        let mut expected = vec![NATIVE, BASELINE];
+        expected.extend(&presets);
+        return Err(BridgeError::InvalidOptions(format!(
+            "unknown cpu '{}' (expected one of: {})",
+            cpu,
+            expected.join(", ")
+        )));
+    }
+    builder
+        .enable(cpu)
+        .map_err(|e| BridgeError::InvalidOptions(format!("cpu '{}': {}", cpu, e)))?;
+    Ok(builder)
+}
+
+/// Turn the extensions of a feature string on (`+name`) or off (`-name`).
+/// A feature is the ISA setting `has_<name>`, with dots dropped and dashes
+/// as underscores (`+sse4.2` is `has_sse42`, `+avx512f` is `has_avx512f`).
+fn apply_features(builder: &mut isa::Builder, features: &str) -> BridgeResult<()> {
+    for entry in features.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let invalid = |what: String| BridgeError::InvalidOptions(format!("target feature '{}': {}", entry, what));
+        let (value, name) = match (entry.strip_prefix('+'), entry.strip_prefix('-')) {
+            (Some(name), _) => ("true", name),
+            (_, Some(name)) => ("false", name),
+            _ => return Err(invalid("expected +name or -name".to_string())),
+        };
+        let setting = format!("has_{}", name.replace('.', "").replace('-', "_"));
+        match builder.set(&setting, value) {
+            Ok(()) => {}
+            Err(SetError::BadName(_)) => {
+                let known: Vec<_> = builder
+                    .iter()
+                    .filter(|s| s.kind == SettingKind::Bool)
+                    .filter_map(|s| s.name.strip_prefix("has_"))
+                    .collect();
+                return Err(invalid(format!(
+                    "unknown on {} (expected one of: {})",
+                    builder.triple().architecture,
+                    known.join(", ")
+                )));
+            }
+            Err(e) => return Err(invalid(e.to_string())),
+        }
+    }
+    Ok(())
+}
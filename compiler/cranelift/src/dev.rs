@@ -0,0 +1,137 @@
+//! Developer API
+//!
+//! Rust entry points for the `cranelift-replay` tool, which runs the bridge on
+//! a MIR file or a replay bundle without the C++ compiler. Not part of the C
+//! API and not used by the driver; errors are plain strings for printing.
+
+use std::path::Path;
+
+use crate::mir_reader::MirBinaryReader;
+use crate::mir_types::Module;
+use crate::options::BridgeOptions;
+use crate::stats::MirStats;
+use crate::translate::ModuleTranslator;
+use crate::{profiles, replay, validate};
+
+pub use crate::validate::{Diagnostic, Severity};
+
+/// MIR to run the bridge on, with the options to run it under.
+pub struct Input {
+    /// C API call that failed (`compile` for plain MIR files)
+    pub entry: String,
+    pub modules: Vec<Vec<u8>>,
+    /// CGU function indices (None = whole module)
+    pub func_indices: Option<Vec<usize>>,
+    /// Bridge version that wrote the bundle
+    pub bridge_version: Option<String>,
+    /// Error recorded in the bundle
+    pub recorded_error: Option<String>,
+    options: BridgeOptions,
+}
+
+impl Input {
+    /// Load a replay bundle, or a MIR module with default options.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        if !data.starts_with(replay::MAGIC) {
+            return Ok(Self {
+                entry: "compile".to_string(),
+                modules: vec![data],
+                func_indices: None,
+                bridge_version: None,
+                recorded_error: None,
+                options: BridgeOptions::default(),
+            });
+        }
+        let bundle = replay::decode(&data)
+            .ok_or_else(|| format!("{}: malformed or unsupported replay bundle", path.display()))?;
+        Ok(Self {
+            entry: bundle.entry,
+            modules: bundle.modules,
+            func_indices: bundle.func_indices,
+            bridge_version: Some(bundle.bridge_version),
+            recorded_error: Some(bundle.error),
+            options: bundle.options,
+        })
+    }
+
+    /// Compile for `triple` (empty = the host).
+    pub fn set_target(&mut self, triple: &str) -> Result<(), String> {
+        profiles::target(triple).map_err(|e| e.to_string())?;
+        self.options.target_triple = triple.to_string();
+        Ok(())
+    }
+
+    /// Replace the codegen fields with profile `name` for the current target.
+    pub fn set_profile(&mut self, name: &str) -> Result<(), String> {
+        let target = profiles::target(&self.options.target_triple).map_err(|e| e.to_string())?;
+        profiles::apply(&mut self.options, name, &target).map_err(|e| e.to_string())?;
+        self.options.profile = name.to_string();
+        Ok(())
+    }
+
+    /// Apply comma-separated `field=value` overrides, as in `profile_overrides`.
+    pub fn apply_overrides(&mut self, overrides: &str) -> Result<(), String> {
+        profiles::apply_overrides(&mut self.options, overrides).map_err(|e| e.to_string())
+    }
+
+    /// The options as `key=value` lines.
+    pub fn options(&self) -> String {
+        self.options.snapshot()
+    }
+
+    fn read_modules(&self) -> Result<Vec<Module>, String> {
+        self.modules
+            .iter()
+            .map(|data| MirBinaryReader::new(data).read_module().map_err(|e| e.to_string()))
+            .collect()
+    }
+}
+
+/// Compile to an object file: LTO for multi-module input (unless it came
+/// from `compile_multi`), CGU mode when function indices are given.
+pub fn compile(input: &Input) -> Result<Vec<u8>, String> {
+    if input.entry == "compile_multi" {
+        let blobs: Vec<&[u8]> = input.modules.iter().map(Vec::as_slice).collect();
+        return crate::compile_mir_multi_impl(&blobs, &input.options).map(|c| c.object).map_err(|e| e.to_string());
+    }
+    if input.modules.len() > 1 || input.entry == "compile_lto" {
+        let blobs: Vec<&[u8]> = input.modules.iter().map(Vec::as_slice).collect();
+        return crate::compile_mir_lto_impl(&blobs, &input.options).map(|c| c.object).map_err(|e| e.to_string());
+    }
+    crate::compile_mir_impl(&input.modules[0], input.func_indices.as_deref(), &input.options)
+        .map(|c| c.object)
+        .map_err(|e| e.to_string())
+}
+
+/// Cranelift IR text of every module, each introduced by a `; module` line
+/// when there are several.
+pub fn ir_dump(input: &Input) -> Result<String, String> {
+    let mut out = String::new();
+    for (i, module) in input.read_modules()?.iter().enumerate() {
+        if input.modules.len() > 1 {
+            out.push_str(&format!("; module {}: {}\n", i, module.name));
+        }
+        let mut translator = ModuleTranslator::new(&input.options).map_err(|e| e.to_string())?;
+        let text = translator
+            .generate_ir_text(module, input.func_indices.as_deref())
+            .map_err(|e| e.to_string())?;
+        out.push_str(&text);
+    }
+    Ok(out)
+}
+
+/// CFG and value reference findings of every function.
+pub fn validate(input: &Input) -> Result<Vec<Diagnostic>, String> {
+    Ok(input.read_modules()?.iter().flat_map(validate::check_module).collect())
+}
+
+/// Statistics report of every module.
+pub fn stats(input: &Input) -> Result<String, String> {
+    Ok(input
+        .read_modules()?
+        .iter()
+        .map(|m| MirStats::collect(m).to_text())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
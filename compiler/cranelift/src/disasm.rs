@@ -0,0 +1,88 @@
+//! Disassembly Listings
+//!
+//! `cranelift_disassemble` returns the machine code of every compiled
+//! function as assembly text. Capstone is not vendored, so the listing is
+//! Cranelift's own print of the final VCode (after register allocation, in
+//! the target's syntax) rather than a decode of the emitted bytes. Calls and
+//! data references show up there as `User(userextnameN)`; they are replaced
+//! with the symbols they stand for:
+//!
+//! ```text
+//! ; Function: main (tml_main), 32 bytes
+//!   pushq %rbp
+//!   ...
+//!   call    tml_zeta
+//! ```
+
+use cranelift_codegen::Context;
+use cranelift_codegen::ir::{UserExternalName, UserExternalNameRef};
+use cranelift_module::{DataId, FuncId, Module};
+use cranelift_object::ObjectModule;
+
+/// Marker Cranelift prints for external names.
+const EXTNAME: &str = "userextname";
+
+/// Listing of the function compiled in `ctx` as `symbol`, headed by its MIR
+/// name and code size. Empty when `ctx` was not compiled with disassembly.
+pub fn listing(mir_name: &str, symbol: &str, ctx: &Context, module: &ObjectModule) -> String {
+    let Some(code) = ctx.compiled_code() else {
+        return String::new();
+    };
+    let Some(vcode) = code.vcode.as_deref() else {
+        return String::new();
+    };
+    let mut text = format!("; Function: {} ({}), {} bytes\n", mir_name, symbol, code.buffer.data().len());
+    for line in vcode.lines() {
+        text.push_str(&symbolize(line, |index| external_symbol(ctx, module, index)));
+        text.push('\n');
+    }
+    text.push('\n');
+    text
+}
+
+/// The symbol of the function's external name `index`, if it names a
+/// declared function or data object.
+fn external_symbol(ctx: &Context, module: &ObjectModule, index: u32) -> Option<String> {
+    let names = ctx.func.params.user_named_funcs();
+    let UserExternalName { namespace, index } = names.get(UserExternalNameRef::from_u32(index))?.clone();
+    let declarations = module.declarations();
+    match namespace {
+        0 => {
+            let id = FuncId::from_u32(index);
+            Some(declarations.get_function_decl(id).linkage_name(id).into_owned())
+        }
+        1 => {
+            let id = DataId::from_u32(index);
+            Some(declarations.get_data_decl(id).linkage_name(id).into_owned())
+        }
+        _ => None,
+    }
+}
+
+/// `line` with every `User(userextnameN)` (or bare `userextnameN`) replaced
+/// by `resolve(N)`; names it cannot resolve are left as printed.
+fn symbolize(line: &str, resolve: impl Fn(u32) -> Option<String>) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    while let Some(at) = rest.find(EXTNAME) {
+        let digits = rest[at + EXTNAME.len()..].bytes().take_while(u8::is_ascii_digit).count();
+        let end = at + EXTNAME.len() + digits;
+        let symbol = rest[at + EXTNAME.len()..end].parse().ok().and_then(&resolve);
+        let Some(symbol) = symbol else {
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        };
+        let wrapped = rest[..at].ends_with("User(") && rest[end..].starts_with(')');
+        if wrapped {
+            out.push_str(&rest[..at - "User(".len()]);
+            rest = &rest[end + 1..];
+        } else {
+            out.push_str(&rest[..at]);
+            rest = &rest[end..];
+        }
+        out.push_str(&symbol);
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,96 @@
+//! Debug Dumps
+//!
+//! The bridge runs inside the compiler process, whose stdout is the user's
+//! terminal, so it never prints. Text meant for whoever is debugging a
+//! compile goes to the dump sink named in the options instead: the `dump`
+//! callback, or else the file at `dump_path`, which is appended to. A call
+//! dumps three kinds of records, on the calling thread and in translation
+//! order:
+//!
+//! - `DUMP_IR`: the Cranelift IR of each function a compile translates that
+//!   matches `ir_filter`, as IR text prints it, before it is compiled
+//! - `DUMP_DIAGNOSTIC`: each CFG validation finding and frame size warning
+//! - `DUMP_VERIFIER`: the function the Cranelift verifier rejected, annotated
+//!   with its errors (the call's error message only has the short form)
+//!
+//! Every record ends with a newline. Dumps are best effort: a failing write
+//! is ignored rather than failing the compile, but a `dump_path` that cannot
+//! be opened fails the call before anything is translated. Without a sink
+//! nothing is dumped; IR text and its diagnostics still come back through
+//! `emit_ir` and the IR entry points as before.
+
+use std::ffi::c_void;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use cranelift_codegen::CodegenError;
+use cranelift_codegen::ir;
+use cranelift_codegen::print_errors::pretty_verifier_error;
+
+use crate::error::{BridgeError, BridgeResult};
+
+/// `void dump(void* context, int kind, const char* text, size_t len)`:
+/// receive one record of `kind` (`DUMP_*`), `len` bytes of UTF-8 followed by
+/// a NUL the length does not count.
+pub type CraneliftDumpFn = unsafe extern "C" fn(*mut c_void, i32, *const i8, usize);
+
+/// Record kinds passed to the callback
+pub const DUMP_IR: i32 = 1;
+pub const DUMP_DIAGNOSTIC: i32 = 2;
+pub const DUMP_VERIFIER: i32 = 3;
+
+/// Where a call's dumps go.
+#[derive(Debug, Clone)]
+pub enum DumpSink {
+    Callback { dump: CraneliftDumpFn, context: *mut c_void },
+    File(PathBuf),
+}
+
+/// An open dump sink.
+pub enum Dumper {
+    Callback { dump: CraneliftDumpFn, context: *mut c_void },
+    File(File),
+}
+
+impl Dumper {
+    /// Open `sink`, creating its file if needed.
+    pub fn open(sink: &DumpSink) -> BridgeResult<Self> {
+        match sink {
+            DumpSink::Callback { dump, context } => Ok(Dumper::Callback { dump: *dump, context: *context }),
+            DumpSink::File(path) => OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map(Dumper::File)
+                .map_err(|e| BridgeError::InvalidOptions(format!("dump_path: cannot open {}: {}", path.display(), e))),
+        }
+    }
+
+    /// Dump `text` as a record of `kind`.
+    pub fn record(&mut self, kind: i32, text: &str) {
+        let mut record = text.to_string();
+        if !record.ends_with('\n') {
+            record.push('\n');
+        }
+        match self {
+            Dumper::Callback { dump, context } => {
+                let len = record.len();
+                record.push('\0');
+                unsafe { dump(*context, kind, record.as_ptr() as *const i8, len) };
+            }
+            Dumper::File(file) => {
+                let _ = file.write_all(record.as_bytes());
+            }
+        }
+    }
+}
+
+/// The verifier's listing of `func` annotated with the errors it found, if
+/// `error` is a verifier failure.
+pub fn verifier_report(error: &CodegenError, func: &ir::Function) -> Option<String> {
+    match error {
+        CodegenError::Verifier(errors) => Some(pretty_verifier_error(func, None, errors.clone())),
+        _ => None,
+    }
+}
@@ -0,0 +1,222 @@
+//! DWARF Line Info
+//!
+//! With `debug_info` and `source_file` set, an ELF object gets a small
+//! DWARF 4 compile unit for the source file: a `DW_TAG_subprogram` with the
+//! code range of every compiled function, and a line table mapping code
+//! offsets to the source lines the MIR carries. That is what debuggers need
+//! to break on `foo.tml:123` and what symbolizers need to print it in a
+//! backtrace. The translator marks each Cranelift instruction with its MIR
+//! line as the source location; the compiled function reports which code
+//! ranges came from which location.
+//!
+//! Other object formats get no debug sections yet, and neither does MIR
+//! without locations, so objects from older front ends are unchanged.
+//! Builds without the `debug-info` feature leave this module out and never
+//! emit debug sections.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use cranelift_codegen::Context;
+use cranelift_codegen::gimli::write::{
+    Address, AttributeValue, DwarfUnit, EndianVec, LineProgram, LineString, Range, RangeList,
+    RelocateWriter, Relocation, RelocationTarget, Sections,
+};
+use cranelift_codegen::gimli::{self, Encoding, Format, LineEncoding, RunTimeEndian};
+use cranelift_module::FuncId;
+use cranelift_object::ObjectProduct;
+use cranelift_object::object::write::{self as obj, Object};
+use cranelift_object::object::{RelocationEncoding, RelocationFlags, RelocationKind, SectionKind};
+use target_lexicon::{BinaryFormat, Endianness, Triple};
+
+use crate::error::{BridgeError, BridgeResult};
+
+/// One compiled function and the source lines of its code.
+struct FunctionLines {
+    name: String,
+    symbol: String,
+    func_id: FuncId,
+    size: u64,
+    /// `(code offset, line)`, by offset
+    rows: Vec<(u32, u32)>,
+}
+
+/// Line information collected from the compiled functions of a module.
+#[derive(Default)]
+pub struct LineTable {
+    functions: Vec<FunctionLines>,
+}
+
+impl LineTable {
+    /// A table for `triple` when it is an ELF target; other formats get no
+    /// debug sections.
+    pub fn for_target(triple: &Triple) -> Option<Self> {
+        (triple.binary_format == BinaryFormat::Elf).then(Self::default)
+    }
+
+    /// Record the function compiled into `ctx` and defined as `func_id`.
+    pub fn record(&mut self, name: &str, symbol: &str, func_id: FuncId, ctx: &Context) {
+        let Some(code) = ctx.compiled_code() else {
+            return;
+        };
+        let mut rows: Vec<(u32, u32)> = code
+            .buffer
+            .get_srclocs_sorted()
+            .iter()
+            .filter(|loc| !loc.loc.is_default())
+            .map(|loc| (loc.start, loc.loc.bits()))
+            .collect();
+        // A row per line change is enough; the line holds until the next row
+        rows.dedup_by_key(|&mut (_, line)| line);
+        self.functions.push(FunctionLines {
+            name: name.to_string(),
+            symbol: symbol.to_string(),
+            func_id,
+            size: code.buffer.data().len() as u64,
+            rows,
+        });
+    }
+
+    /// Add the debug sections for `source_file` to `product`, unless no
+    /// function has a located instruction.
+    pub fn emit(&self, source_file: &str, triple: &Triple, product: &mut ObjectProduct) -> BridgeResult<()> {
+        if self.functions.iter().all(|f| f.rows.is_empty()) {
+            return Ok(());
+        }
+        let failed = |e: gimli::write::Error| BridgeError::Codegen(format!("failed to write debug info: {}", e));
+        let address_size = triple.pointer_width().map_or(8, |w| w.bytes());
+        let encoding = Encoding { format: Format::Dwarf32, version: 4, address_size };
+        let mut dwarf = DwarfUnit::new(encoding);
+
+        let path = Path::new(source_file);
+        let dir = path.parent().map(|p| p.to_string_lossy().into_owned()).unwrap_or_default();
+        let file = path.file_name().map_or_else(|| source_file.to_string(), |f| f.to_string_lossy().into_owned());
+        dwarf.unit.line_program = LineProgram::new(
+            encoding,
+            LineEncoding::default(),
+            LineString::String(dir.clone().into_bytes()),
+            None,
+            LineString::String(file.clone().into_bytes()),
+            None,
+        );
+        let program = &mut dwarf.unit.line_program;
+        let file_id = program.add_file(LineString::String(file.into_bytes()), program.default_directory(), None);
+
+        // Address::Symbol indices are positions in `self.functions`
+        let mut ranges = Vec::new();
+        for (i, func) in self.functions.iter().enumerate() {
+            let start = Address::Symbol { symbol: i, addend: 0 };
+            ranges.push(Range::StartLength { begin: start, length: func.size });
+            if func.rows.is_empty() {
+                continue;
+            }
+            let program = &mut dwarf.unit.line_program;
+            program.begin_sequence(Some(start));
+            for &(offset, line) in &func.rows {
+                let row = program.row();
+                row.address_offset = offset as u64;
+                row.file = file_id;
+                row.line = line as u64;
+                program.generate_row();
+            }
+            program.end_sequence(func.size);
+        }
+        let ranges = dwarf.unit.ranges.add(RangeList(ranges));
+
+        let root = dwarf.unit.root();
+        let unit = dwarf.unit.get_mut(root);
+        let producer = format!("TML Cranelift bridge {}", env!("CARGO_PKG_VERSION"));
+        unit.set(gimli::DW_AT_producer, AttributeValue::String(producer.into_bytes()));
+        unit.set(gimli::DW_AT_name, AttributeValue::String(source_file.as_bytes().to_vec()));
+        unit.set(gimli::DW_AT_comp_dir, AttributeValue::String(dir.into_bytes()));
+        unit.set(gimli::DW_AT_low_pc, AttributeValue::Address(Address::Constant(0)));
+        unit.set(gimli::DW_AT_ranges, AttributeValue::RangeListRef(ranges));
+        for (i, func) in self.functions.iter().enumerate() {
+            let id = dwarf.unit.add(root, gimli::DW_TAG_subprogram);
+            let entry = dwarf.unit.get_mut(id);
+            entry.set(gimli::DW_AT_name, AttributeValue::String(func.name.clone().into_bytes()));
+            entry.set(gimli::DW_AT_linkage_name, AttributeValue::String(func.symbol.clone().into_bytes()));
+            entry.set(gimli::DW_AT_low_pc, AttributeValue::Address(Address::Symbol { symbol: i, addend: 0 }));
+            entry.set(gimli::DW_AT_high_pc, AttributeValue::Udata(func.size));
+            if let Some(&(_, line)) = func.rows.first() {
+                entry.set(gimli::DW_AT_decl_file, AttributeValue::FileIndex(Some(file_id)));
+                entry.set(gimli::DW_AT_decl_line, AttributeValue::Udata(line as u64));
+            }
+        }
+
+        let endian = match triple.endianness() {
+            Ok(Endianness::Big) => RunTimeEndian::Big,
+            _ => RunTimeEndian::Little,
+        };
+        let mut sections = Sections::new(SectionWriter::new(endian));
+        dwarf.write(&mut sections).map_err(failed)?;
+        let symbols: Vec<_> = self.functions.iter().map(|f| product.function_symbol(f.func_id)).collect();
+        add_sections(&mut product.object, &sections, &symbols)
+    }
+}
+
+/// A debug section's bytes and the relocations they need.
+#[derive(Clone)]
+struct SectionWriter {
+    data: EndianVec<RunTimeEndian>,
+    relocs: Vec<Relocation>,
+}
+
+impl SectionWriter {
+    fn new(endian: RunTimeEndian) -> Self {
+        Self { data: EndianVec::new(endian), relocs: Vec::new() }
+    }
+}
+
+impl RelocateWriter for SectionWriter {
+    type Writer = EndianVec<RunTimeEndian>;
+
+    fn writer(&self) -> &Self::Writer {
+        &self.data
+    }
+
+    fn writer_mut(&mut self) -> &mut Self::Writer {
+        &mut self.data
+    }
+
+    fn relocate(&mut self, relocation: Relocation) {
+        self.relocs.push(relocation);
+    }
+}
+
+/// Add the non-empty `sections` to `object`, relocated against the function
+/// `symbols` and each other.
+fn add_sections(
+    object: &mut Object<'static>,
+    sections: &Sections<SectionWriter>,
+    symbols: &[obj::SymbolId],
+) -> BridgeResult<()> {
+    let mut ids = HashMap::new();
+    sections.for_each(|id, section| -> BridgeResult<()> {
+        let data = section.data.slice();
+        if !data.is_empty() {
+            let section_id = object.add_section(Vec::new(), id.name().as_bytes().to_vec(), SectionKind::Debug);
+            object.append_section_data(section_id, data, 1);
+            ids.insert(id, section_id);
+        }
+        Ok(())
+    })?;
+    sections.for_each(|id, section| -> BridgeResult<()> {
+        for reloc in &section.relocs {
+            let symbol = match reloc.target {
+                RelocationTarget::Symbol(i) => symbols[i],
+                RelocationTarget::Section(target) => object.section_symbol(ids[&target]),
+            };
+            let flags = RelocationFlags::Generic {
+                kind: RelocationKind::Absolute,
+                encoding: RelocationEncoding::Generic,
+                size: reloc.size * 8,
+            };
+            let relocation = obj::Relocation { offset: reloc.offset as u64, symbol, addend: reloc.addend, flags };
+            object
+                .add_relocation(ids[&id], relocation)
+                .map_err(|e| BridgeError::Codegen(format!("failed to relocate {}: {}", id.name(), e)))?;
+        }
+        Ok(())
+    })
+}
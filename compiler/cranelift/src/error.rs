@@ -7,6 +7,48 @@ pub enum BridgeError {
     Codegen(String),
     UnsupportedInstruction(String),
     InvalidTarget(String),
+    InvalidOptions(String),
+    /// Bad pointer, length or field value from the C caller (see `ffi_check`)
+    FfiMisuse(String),
+    /// The compile's cancel token was set (see `cancel`)
+    Cancelled,
+    /// The call went over `max_memory_bytes` (see `memory`)
+    ResourceLimit(String),
+    /// A panic caught inside the bridge or Cranelift
+    Panic(String),
+}
+
+/// `CraneliftResult::error_code` values, for drivers deciding whether to
+/// fall back to the LLVM backend without parsing `error_msg`.
+pub const ERROR_NONE: i32 = 0;
+pub const ERROR_DESERIALIZE: i32 = 1;
+pub const ERROR_TRANSLATION: i32 = 2;
+pub const ERROR_CODEGEN: i32 = 3;
+pub const ERROR_UNSUPPORTED_INSTRUCTION: i32 = 4;
+pub const ERROR_INVALID_TARGET: i32 = 5;
+pub const ERROR_PANIC: i32 = 6;
+pub const ERROR_INVALID_OPTIONS: i32 = 7;
+/// Null or empty inputs, names the module lacks, and FFI misuse
+pub const ERROR_INVALID_ARGUMENT: i32 = 8;
+pub const ERROR_CANCELLED: i32 = 9;
+pub const ERROR_RESOURCE_LIMIT: i32 = 10;
+
+impl BridgeError {
+    /// The `ERROR_*` code reported for this error.
+    pub fn code(&self) -> i32 {
+        match self {
+            BridgeError::MirDeserialize(_) => ERROR_DESERIALIZE,
+            BridgeError::Translation(_) => ERROR_TRANSLATION,
+            BridgeError::Codegen(_) => ERROR_CODEGEN,
+            BridgeError::UnsupportedInstruction(_) => ERROR_UNSUPPORTED_INSTRUCTION,
+            BridgeError::InvalidTarget(_) => ERROR_INVALID_TARGET,
+            BridgeError::InvalidOptions(_) => ERROR_INVALID_OPTIONS,
+            BridgeError::FfiMisuse(_) => ERROR_INVALID_ARGUMENT,
+            BridgeError::Cancelled => ERROR_CANCELLED,
+            BridgeError::ResourceLimit(_) => ERROR_RESOURCE_LIMIT,
+            BridgeError::Panic(_) => ERROR_PANIC,
+        }
+    }
 }
 
 impl fmt::Display for BridgeError {
@@ -19,6 +61,11 @@ impl fmt::Display for BridgeError {
                 write!(f, "unsupported instruction: {}", msg)
             }
             BridgeError::InvalidTarget(msg) => write!(f, "invalid target: {}", msg),
+            BridgeError::InvalidOptions(msg) => write!(f, "invalid options: {}", msg),
+            BridgeError::FfiMisuse(msg) => write!(f, "FFI misuse: {}", msg),
+            BridgeError::Cancelled => write!(f, "cancelled"),
+            BridgeError::ResourceLimit(msg) => write!(f, "resource limit exceeded: {}", msg),
+            BridgeError::Panic(msg) => write!(f, "PANIC: {}", msg),
         }
     }
 }
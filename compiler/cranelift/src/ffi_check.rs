@@ -0,0 +1,189 @@
+//! FFI Boundary Checks
+//!
+//! Entry points trust the pointers and lengths C++ hands them: a misaligned
+//! pointer, a length that overflows, or garbage in an options field is
+//! undefined behavior inside `slice::from_raw_parts` or silently coerced.
+//! When enabled, these checks run on the raw arguments before anything is
+//! dereferenced and turn misuse into an `FfiMisuse` error naming the entry
+//! point's parameter.
+//!
+//! Checks are compiled into debug builds and builds with the `ffi-checks`
+//! feature. Debug builds run them unless `TML_CRANELIFT_FFI_CHECKS=0`;
+//! `ffi-checks` release builds run them only with
+//! `TML_CRANELIFT_FFI_CHECKS=1`, so nightly CI can turn them on without a
+//! separate bridge build per configuration.
+//!
+//! The checks cannot prove a pointer valid, only catch the common ways of
+//! passing a wrong one: integers or lengths passed as pointers (addresses in
+//! the first page), misaligned arrays, lengths that wrap the address space,
+//! a null array with a count, and out-of-range option values.
+
+use std::sync::OnceLock;
+
+use crate::error::{BridgeError, BridgeResult};
+use crate::options::{FAST_MATH_CONTRACT, FAST_MATH_REASSOCIATE, RUNTIME_PREFIX_OFF};
+use crate::{CraneliftOptions, CraneliftOptionsV2, CraneliftTestEntry};
+
+/// Environment variable turning the checks on (`1`) or off (`0`).
+pub const ENV_VAR: &str = "TML_CRANELIFT_FFI_CHECKS";
+
+/// Addresses below this are never valid pointers.
+const FIRST_PAGE: usize = 4096;
+
+/// Whether this build contains the checks.
+pub const COMPILED_IN: bool = cfg!(any(debug_assertions, feature = "ffi-checks"));
+
+/// Whether the checks run, decided once per process.
+pub fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| match std::env::var(ENV_VAR).as_deref() {
+        Ok("0") => false,
+        Ok(v) if !v.is_empty() => COMPILED_IN,
+        _ => cfg!(debug_assertions),
+    })
+}
+
+/// Run `checks` when enabled.
+pub fn run(checks: impl FnOnce() -> BridgeResult<()>) -> BridgeResult<()> {
+    if enabled() { checks() } else { Ok(()) }
+}
+
+fn misuse(name: &str, problem: String) -> BridgeError {
+    BridgeError::FfiMisuse(format!("{}: {}", name, problem))
+}
+
+/// A pointer the bridge reads `T`s from: not in the first page and aligned
+/// for `T`. Null is left to the entry point.
+pub fn pointer<T>(name: &str, ptr: *const T) -> BridgeResult<()> {
+    let addr = ptr as usize;
+    if addr == 0 {
+        return Ok(());
+    }
+    if addr < FIRST_PAGE {
+        return Err(misuse(name, format!("pointer {:#x} is in the first page", addr)));
+    }
+    let align = std::mem::align_of::<T>();
+    if !addr.is_multiple_of(align) {
+        return Err(misuse(name, format!("pointer {:#x} is not {}-byte aligned", addr, align)));
+    }
+    Ok(())
+}
+
+/// An array of `len` `T`s at `ptr`. Null is accepted only with `len == 0`;
+/// an empty array is never read, so its pointer may be anything.
+pub fn array<T>(name: &str, ptr: *const T, len: usize) -> BridgeResult<()> {
+    if len == 0 {
+        return Ok(());
+    }
+    if ptr.is_null() {
+        return Err(misuse(name, format!("null pointer with length {}", len)));
+    }
+    pointer(name, ptr)?;
+    let bytes = len
+        .checked_mul(std::mem::size_of::<T>())
+        .filter(|b| *b <= isize::MAX as usize)
+        .ok_or_else(|| misuse(name, format!("length {} overflows", len)))?;
+    if (ptr as usize).checked_add(bytes).is_none() {
+        return Err(misuse(name, format!("{} bytes at {:#x} wrap the address space", bytes, ptr as usize)));
+    }
+    Ok(())
+}
+
+/// A `CraneliftOptions` pointer and the values of its fields.
+pub fn options(ptr: *const CraneliftOptions) -> BridgeResult<()> {
+    pointer("options", ptr)?;
+    if ptr.is_null() {
+        return Ok(());
+    }
+    // A mismatched struct is reported by `BridgeOptions::from_raw` without
+    // reading further
+    if crate::options::check_struct_size(unsafe { (*ptr).struct_size }).is_err() {
+        return Ok(());
+    }
+    let opts = unsafe { &*ptr };
+    let field = |name: &str, value: i32, ok: bool| {
+        if ok { Ok(()) } else { Err(misuse(&format!("options.{}", name), format!("invalid value {}", value))) }
+    };
+    field("optimization_level", opts.optimization_level, (0..=3).contains(&opts.optimization_level))?;
+    for (name, value) in [
+        ("debug_info", opts.debug_info),
+        ("dll_export", opts.dll_export),
+        ("emit_inline_metadata", opts.emit_inline_metadata),
+        ("trace_hooks", opts.trace_hooks),
+        ("ir_annotate", opts.ir_annotate),
+        ("ir_sort", opts.ir_sort),
+        ("nan_canonicalization", opts.nan_canonicalization),
+        ("frame_pointers", opts.frame_pointers),
+        ("trap_abort", opts.trap_abort),
+        ("strict_runtime_calls", opts.strict_runtime_calls),
+        ("json_output", opts.json_output),
+        ("emit_ir", opts.emit_ir),
+        ("function_stats", opts.function_stats),
+        ("position_independent", opts.position_independent),
+        ("emit_build_info", opts.emit_build_info),
+        ("panic_backtrace", opts.panic_backtrace),
+    ] {
+        field(name, value, value == 0 || value == 1)?;
+    }
+    let fast_math = FAST_MATH_CONTRACT | FAST_MATH_REASSOCIATE;
+    field("fast_math", opts.fast_math, opts.fast_math & !fast_math == 0)?;
+    field("runtime_prefix", opts.runtime_prefix, (0..=RUNTIME_PREFIX_OFF).contains(&opts.runtime_prefix))?;
+
+    for (name, s) in [
+        ("target_triple", opts.target_triple),
+        ("ir_filter", opts.ir_filter),
+        ("multiversion", opts.multiversion),
+        ("profile", opts.profile),
+        ("profile_overrides", opts.profile_overrides),
+        ("replay_dir", opts.replay_dir),
+        ("symbol_prefix", opts.symbol_prefix),
+        ("source_file", opts.source_file),
+        ("dump_path", opts.dump_path),
+    ] {
+        pointer(&format!("options.{}", name), s)?;
+    }
+    pointer("options.cancel_token", opts.cancel_token)?;
+    pointer("options.result_context", opts.result_context)?;
+
+    array("options.symbol_renames", opts.symbol_renames, opts.num_symbol_renames)?;
+    if opts.num_symbol_renames > 0 {
+        let renames = unsafe { std::slice::from_raw_parts(opts.symbol_renames, opts.num_symbol_renames) };
+        for (i, r) in renames.iter().enumerate() {
+            pointer(&format!("options.symbol_renames[{}].from", i), r.from)?;
+            pointer(&format!("options.symbol_renames[{}].to", i), r.to)?;
+        }
+    }
+
+    array("options.tests", opts.tests, opts.num_tests)?;
+    if opts.num_tests == 0 {
+        return Ok(());
+    }
+    let tests: &[CraneliftTestEntry] = unsafe { std::slice::from_raw_parts(opts.tests, opts.num_tests) };
+    for (i, t) in tests.iter().enumerate() {
+        for (name, s) in [("function", t.function), ("name", t.name), ("expected_panic", t.expected_panic)] {
+            pointer(&format!("options.tests[{}].{}", i, name), s)?;
+        }
+    }
+    Ok(())
+}
+
+/// A `CraneliftOptionsV2` pointer, its `base` fields and its flags. A struct
+/// too small for its version is left to `BridgeOptions::from_versioned`,
+/// which reports it without reading past the header.
+pub fn options_v2(ptr: *const CraneliftOptionsV2) -> BridgeResult<()> {
+    pointer("options", ptr)?;
+    if ptr.is_null() || (unsafe { (*ptr).size } as usize) < std::mem::size_of::<CraneliftOptionsV2>() {
+        return Ok(());
+    }
+    let opts = unsafe { &*ptr };
+    options(&opts.base)?;
+    array("options.flags", opts.flags, opts.num_flags)?;
+    if opts.num_flags == 0 {
+        return Ok(());
+    }
+    let flags: &[*const i8] = unsafe { std::slice::from_raw_parts(opts.flags, opts.num_flags) };
+    for (i, &flag) in flags.iter().enumerate() {
+        pointer(&format!("options.flags[{}]", i), flag)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,189 @@
+//! Cross-module inlining metadata
+//!
+//! Selects small public functions and packages their MIR (plus the struct/enum
+//! definitions they reference) as a self-contained MIR module. The translator
+//! serializes it into a `.tml.inline` custom section so later compiles of
+//! dependent modules can inline across module boundaries — a lightweight
+//! ThinLTO analogue for the Cranelift backend.
+
+use std::collections::HashSet;
+
+use crate::mir_types::*;
+
+/// Section name for the serialized inline candidates (ELF/COFF).
+pub const INLINE_SECTION: &str = ".tml.inline";
+/// Mach-O segment/section pair for the same data.
+pub const INLINE_SECTION_MACHO: (&str, &str) = ("__TML", "__tml_inline");
+
+/// Functions with more instructions than this are never exported for inlining.
+pub const MAX_INLINE_INSTRUCTIONS: usize = 32;
+
+/// Whether a function is small and simple enough to be inlined elsewhere.
+/// `private` names the module's non-public functions: a body calling one
+/// can't be inlined into a module that has no symbol for it.
+pub fn is_inline_candidate(func: &Function, private: &HashSet<&str>) -> bool {
+    if !func.is_public || func.blocks.is_empty() {
+        return false;
+    }
+    if func.name == "main" || func.name == "tml_main" {
+        return false;
+    }
+
+    let mut inst_count = 0;
+    for block in &func.blocks {
+        for inst in &block.instructions {
+            // Suspension points and closures depend on state that doesn't
+            // survive being copied into another module.
+            if matches!(
+                inst.inst,
+                Instruction::Await { .. } | Instruction::ClosureInit { .. }
+            ) {
+                return false;
+            }
+            if calls_private(&inst.inst, private) {
+                return false;
+            }
+            inst_count += 1;
+        }
+    }
+    inst_count <= MAX_INLINE_INSTRUCTIONS
+}
+
+fn calls_private(inst: &Instruction, private: &HashSet<&str>) -> bool {
+    match inst {
+        Instruction::Call { func_name, .. } => private.contains(func_name.as_str()),
+        Instruction::MethodCall { method_name, .. } => {
+            private.contains(method_name.as_str())
+                || inst.method_targets().iter().any(|t| private.contains(t.as_str()))
+        }
+        _ => false,
+    }
+}
+
+/// Build the inline metadata module for the given functions.
+/// Returns None when no function qualifies.
+pub fn build_inline_module<'a>(
+    mir: &Module,
+    funcs: impl IntoIterator<Item = &'a Function>,
+) -> Option<Module> {
+    let private: HashSet<&str> = mir
+        .functions
+        .iter()
+        .filter(|f| !f.is_public)
+        .map(|f| f.name.as_str())
+        .collect();
+    let functions: Vec<Function> = funcs
+        .into_iter()
+        .filter(|f| is_inline_candidate(f, &private))
+        .cloned()
+        .collect();
+    if functions.is_empty() {
+        return None;
+    }
+
+    // Collect every aggregate name the candidates mention, then close over
+    // the field/payload types of those definitions.
+    let mut names = HashSet::new();
+    for func in &functions {
+        collect_function_type_names(func, &mut names);
+    }
+    loop {
+        let mut found = HashSet::new();
+        for s in mir.structs.iter().filter(|s| names.contains(&s.name)) {
+            for field in &s.fields {
+                collect_type_names(&field.ty, &mut found);
+            }
+        }
+        for e in mir.enums.iter().filter(|e| names.contains(&e.name)) {
+            for variant in &e.variants {
+                for pt in &variant.payload_types {
+                    collect_type_names(pt, &mut found);
+                }
+            }
+        }
+        let before = names.len();
+        names.extend(found);
+        if names.len() == before {
+            break;
+        }
+    }
+
+    Some(Module {
+        name: mir.name.clone(),
+        structs: mir
+            .structs
+            .iter()
+            .filter(|s| names.contains(&s.name))
+            .cloned()
+            .collect(),
+        enums: mir
+            .enums
+            .iter()
+            .filter(|e| names.contains(&e.name))
+            .cloned()
+            .collect(),
+        functions,
+        constants: Vec::new(),
+    })
+}
+
+fn collect_function_type_names(func: &Function, names: &mut HashSet<String>) {
+    for param in &func.params {
+        collect_type_names(&param.ty, names);
+    }
+    collect_type_names(&func.return_type, names);
+
+    for block in &func.blocks {
+        for inst in &block.instructions {
+            match &inst.inst {
+                Instruction::Alloca { alloc_type, .. } => collect_type_names(alloc_type, names),
+                Instruction::Call { return_type, .. }
+                | Instruction::MethodCall { return_type, .. } => {
+                    collect_type_names(return_type, names)
+                }
+                Instruction::Cast { target_type, .. } => collect_type_names(target_type, names),
+                Instruction::ArrayInit { element_type, .. }
+                | Instruction::ArrayFill { element_type, .. } => {
+                    collect_type_names(element_type, names)
+                }
+                Instruction::StructInit { struct_name, .. } => {
+                    names.insert(struct_name.clone());
+                }
+                Instruction::EnumInit { enum_name, .. } => {
+                    names.insert(enum_name.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn collect_type_names(ty: &MirType, names: &mut HashSet<String>) {
+    match ty {
+        MirType::Primitive(_) => {}
+        MirType::Pointer { pointee, .. } => collect_type_names(pointee, names),
+        MirType::Array { element, .. } | MirType::Slice { element } => {
+            collect_type_names(element, names)
+        }
+        MirType::Tuple { elements } => {
+            for elem in elements {
+                collect_type_names(elem, names);
+            }
+        }
+        MirType::Struct { name, type_args } | MirType::Enum { name, type_args } => {
+            names.insert(name.clone());
+            for arg in type_args {
+                collect_type_names(arg, names);
+            }
+        }
+        MirType::Function {
+            params,
+            return_type,
+        } => {
+            for p in params {
+                collect_type_names(p, names);
+            }
+            collect_type_names(return_type, names);
+        }
+    }
+}
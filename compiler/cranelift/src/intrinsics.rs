@@ -0,0 +1,130 @@
+//! Integer Arithmetic Intrinsics
+//!
+//! `saturating_*` and `wrapping_*` reach MIR as ordinary calls (the stdlib
+//! `@intrinsic` functions) or as method calls on primitive integers. Emitting
+//! them as calls would need a runtime implementation per width; instead they
+//! are lowered inline here. Scalar `*_sat` instructions exist in CLIF only for
+//! vectors, so saturation is built from the overflow condition and a select.
+
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::{InstBuilder, Type, Value, types};
+use cranelift_frontend::FunctionBuilder;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntIntrinsic {
+    SaturatingAdd,
+    SaturatingSub,
+    SaturatingMul,
+    WrappingAdd,
+    WrappingSub,
+    WrappingMul,
+    WrappingNeg,
+}
+
+impl IntIntrinsic {
+    /// Recognize an intrinsic by its (possibly path-qualified) name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let base = name.rsplit("::").next().unwrap_or(name);
+        Some(match base {
+            "saturating_add" => Self::SaturatingAdd,
+            "saturating_sub" => Self::SaturatingSub,
+            "saturating_mul" => Self::SaturatingMul,
+            "wrapping_add" => Self::WrappingAdd,
+            "wrapping_sub" => Self::WrappingSub,
+            "wrapping_mul" => Self::WrappingMul,
+            "wrapping_neg" => Self::WrappingNeg,
+            _ => return None,
+        })
+    }
+
+    pub fn arity(self) -> usize {
+        match self {
+            Self::WrappingNeg => 1,
+            _ => 2,
+        }
+    }
+
+    /// Whether the intrinsic can be lowered inline for `ty`. 128-bit
+    /// saturating multiply has no high-half multiply to build on.
+    pub fn supports(self, ty: Type) -> bool {
+        ty.is_int() && !(self == Self::SaturatingMul && ty == types::I128)
+    }
+
+    /// Emit the intrinsic. `args` must already have type `ty` and match
+    /// `arity()`.
+    pub fn emit(self, b: &mut FunctionBuilder, ty: Type, signed: bool, args: &[Value]) -> Value {
+        match self {
+            Self::WrappingAdd => b.ins().iadd(args[0], args[1]),
+            Self::WrappingSub => b.ins().isub(args[0], args[1]),
+            Self::WrappingMul => b.ins().imul(args[0], args[1]),
+            Self::WrappingNeg => b.ins().ineg(args[0]),
+            Self::SaturatingAdd => saturating_add(b, ty, signed, args[0], args[1]),
+            Self::SaturatingSub => saturating_sub(b, ty, signed, args[0], args[1]),
+            Self::SaturatingMul => saturating_mul(b, ty, signed, args[0], args[1]),
+        }
+    }
+}
+
+/// `MIN` if `sign_of` is negative, else `MAX`, for signed `ty`.
+fn signed_limit(b: &mut FunctionBuilder, ty: Type, sign_of: Value) -> Value {
+    let sign = b.ins().sshr_imm(sign_of, i64::from(ty.bits() - 1));
+    let max = signed_max(b, ty);
+    b.ins().bxor(sign, max)
+}
+
+fn signed_max(b: &mut FunctionBuilder, ty: Type) -> Value {
+    let all_ones = b.ins().iconst(ty, -1);
+    b.ins().ushr_imm(all_ones, 1)
+}
+
+fn saturating_add(b: &mut FunctionBuilder, ty: Type, signed: bool, x: Value, y: Value) -> Value {
+    let sum = b.ins().iadd(x, y);
+    if signed {
+        // Overflow iff both operands have the sign opposite to the sum
+        let xs = b.ins().bxor(x, sum);
+        let ys = b.ins().bxor(y, sum);
+        let both = b.ins().band(xs, ys);
+        let overflow = b.ins().icmp_imm(IntCC::SignedLessThan, both, 0);
+        let limit = signed_limit(b, ty, x);
+        b.ins().select(overflow, limit, sum)
+    } else {
+        let overflow = b.ins().icmp(IntCC::UnsignedLessThan, sum, x);
+        let max = b.ins().iconst(ty, -1);
+        b.ins().select(overflow, max, sum)
+    }
+}
+
+fn saturating_sub(b: &mut FunctionBuilder, ty: Type, signed: bool, x: Value, y: Value) -> Value {
+    let diff = b.ins().isub(x, y);
+    if signed {
+        // Overflow iff the operands differ in sign and the result took y's sign
+        let xy = b.ins().bxor(x, y);
+        let xd = b.ins().bxor(x, diff);
+        let both = b.ins().band(xy, xd);
+        let overflow = b.ins().icmp_imm(IntCC::SignedLessThan, both, 0);
+        let limit = signed_limit(b, ty, x);
+        b.ins().select(overflow, limit, diff)
+    } else {
+        let overflow = b.ins().icmp(IntCC::UnsignedLessThan, x, y);
+        let zero = b.ins().iconst(ty, 0);
+        b.ins().select(overflow, zero, diff)
+    }
+}
+
+fn saturating_mul(b: &mut FunctionBuilder, ty: Type, signed: bool, x: Value, y: Value) -> Value {
+    let lo = b.ins().imul(x, y);
+    if signed {
+        // The product fits iff the high half is the sign extension of the low half
+        let hi = b.ins().smulhi(x, y);
+        let lo_sign = b.ins().sshr_imm(lo, i64::from(ty.bits() - 1));
+        let overflow = b.ins().icmp(IntCC::NotEqual, hi, lo_sign);
+        let xy = b.ins().bxor(x, y);
+        let limit = signed_limit(b, ty, xy);
+        b.ins().select(overflow, limit, lo)
+    } else {
+        let hi = b.ins().umulhi(x, y);
+        let overflow = b.ins().icmp_imm(IntCC::NotEqual, hi, 0);
+        let max = b.ins().iconst(ty, -1);
+        b.ins().select(overflow, max, lo)
+    }
+}
@@ -0,0 +1,36 @@
+//! Minimal JSON output helpers
+//!
+//! The bridge only ever writes small JSON documents, so it builds them by hand
+//! instead of pulling in serde.
+
+use std::fmt::Write;
+
+/// Quote and escape `s` as a JSON string literal.
+pub fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Format a float as JSON (non-finite values become `null`).
+pub fn number(v: f64) -> String {
+    if v.is_finite() {
+        format!("{:.3}", v)
+    } else {
+        "null".to_string()
+    }
+}
@@ -1,25 +1,100 @@
-/// TML Cranelift Bridge — C API Entry Points
-///
-/// This crate provides a C-compatible FFI layer for the Cranelift code generator.
-/// The C++ compiler serializes MIR to binary, calls these functions, and receives
-/// object file bytes or IR text back.
+//! TML Cranelift Bridge — C API Entry Points
+//!
+//! This crate provides a C-compatible FFI layer for the Cranelift code generator.
+//! The C++ compiler serializes MIR to binary, calls these functions, and receives
+//! object file bytes or IR text back.
+
+// Object emission is the only backend; a build without it has nothing to
+// compile to
+#[cfg(not(feature = "object"))]
+compile_error!("the bridge needs the `object` feature (build with `--features object`)");
 
+mod backtrace;
+mod build_info;
+mod cancel;
+mod clif_text;
+#[cfg(feature = "interpreter")]
+mod const_eval;
+mod const_pool;
+mod cpu;
+#[cfg(feature = "dev-tools")]
+pub mod dev;
+mod disasm;
+mod dump;
+#[cfg(feature = "debug-info")]
+mod dwarf;
 mod error;
+mod ffi_check;
+mod inline_meta;
+mod intrinsics;
+mod json;
+mod lto;
+mod memory;
 mod mir_reader;
 mod mir_types;
+mod mir_writer;
+mod multiversion;
+mod options;
+mod parallel;
+mod pointer_ops;
+mod profiles;
+mod replay;
+mod result_context;
+mod runtime_sigs;
+mod self_test;
+mod slot_lifetimes;
+mod stats;
+mod stream;
+mod test_registry;
+#[cfg(test)]
+mod test_util;
 mod translate;
+mod traps;
 mod types;
+mod validate;
 
-use std::ffi::{CStr, CString};
+use std::alloc::Layout;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{CStr, CString, c_void};
+use std::mem::MaybeUninit;
 use std::panic;
 use std::ptr;
 use std::slice;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
 
-use error::BridgeResult;
+use cancel::CraneliftCancelToken;
+use dump::CraneliftDumpFn;
+use error::{BridgeError, BridgeResult, ERROR_INVALID_ARGUMENT, ERROR_INVALID_TARGET, ERROR_NONE, ERROR_TRANSLATION};
 use mir_reader::MirBinaryReader;
-use translate::ModuleTranslator;
+use options::BridgeOptions;
+use result_context::{CraneliftContext, ResultContext};
+use stream::CraneliftWriteFn;
+use translate::{Compiled, FunctionStats, ModuleTranslator};
 
 /// Result struct returned to C++.
+///
+/// The buffers are allocations registered in `LIVE_BUFFERS` with their
+/// layout and the `generation` of the result owning them, a number no other
+/// result gets. `cranelift_free_result` frees only buffers registered under
+/// the result's own generation, with the registered layout, so length fields
+/// edited by the caller or a stale copy of the struct cannot free memory
+/// twice or with the wrong layout, even once the allocator hands a freed
+/// address to a newer result. `magic` tells results from this bridge
+/// (`RESULT_LIVE`) from freed ones (`RESULT_FREED`) and from zeroed or
+/// foreign structs.
+///
+/// String fields (`ir_text`, `error_msg`) are valid UTF-8 of `*_len` bytes,
+/// followed by a NUL that the length does not count. IR text may contain
+/// interior NULs (quoted string data), so read it by length; error messages
+/// never do.
+///
+/// `cancelled` is 1 when a compile failed because its cancel token was set,
+/// so drivers can drop the result quietly instead of reporting an error.
+/// `error_code` classifies a failure (`error::ERROR_*`, 0 on success).
+///
+/// `function_stats` holds `num_function_stats` records when a compile ran
+/// with the `function_stats` option, and is null otherwise.
 #[repr(C)]
 pub struct CraneliftResult {
     pub success: i32,
@@ -28,152 +103,945 @@ pub struct CraneliftResult {
     pub ir_text: *const i8,
     pub ir_text_len: usize,
     pub error_msg: *const i8,
+    pub error_msg_len: usize,
+    pub magic: u32,
+    pub cancelled: i32,
+    pub error_code: i32,
+    pub function_stats: *const CraneliftFunctionStats,
+    pub num_function_stats: usize,
+    pub generation: u64,
+}
+
+/// Compile time and code size of one function, see `translate::FunctionStats`.
+/// `name` points into the same buffer as the records.
+#[repr(C)]
+pub struct CraneliftFunctionStats {
+    pub name: *const i8,
+    pub translate_us: u64,
+    pub codegen_us: u64,
+    pub code_bytes: u64,
+}
+
+/// `CraneliftResult::magic` of a result that still owns its buffers ("TMLR").
+pub const RESULT_LIVE: u32 = 0x544D_4C52;
+/// `CraneliftResult::magic` after `cranelift_free_result` ("FREE").
+pub const RESULT_FREED: u32 = 0x4652_4545;
+
+/// A buffer handed out in a result.
+struct LiveBuffer {
+    layout: Layout,
+    /// `CraneliftResult::generation` of the result owning it
+    generation: u64,
+    /// Context that frees it with its other results, if any
+    context: Option<ResultContext>,
+}
+
+/// Buffers handed out in results that have not been freed, by address.
+static LIVE_BUFFERS: Mutex<BTreeMap<usize, LiveBuffer>> = Mutex::new(BTreeMap::new());
+/// Next `CraneliftResult::generation`; 0 is never handed out, so zeroed
+/// structs own nothing.
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+fn next_generation() -> u64 {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+fn live_buffers() -> std::sync::MutexGuard<'static, BTreeMap<usize, LiveBuffer>> {
+    // A panic while the lock is held cannot leave the map half-updated
+    LIVE_BUFFERS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Hand `bytes` over to C++ until `cranelift_free_result` of the result
+/// with `generation`. Empty buffers become null, since they would share a
+/// dangling address.
+fn export_buffer(bytes: Vec<u8>, generation: u64) -> *const u8 {
+    if bytes.is_empty() {
+        return ptr::null();
+    }
+    let boxed = bytes.into_boxed_slice();
+    let layout = Layout::for_value(&*boxed);
+    let ptr = Box::into_raw(boxed) as *mut u8;
+    live_buffers().insert(ptr as usize, LiveBuffer { layout, generation, context: None });
+    ptr
+}
+
+/// Hand `stats` over to C++ as `CraneliftFunctionStats` records followed
+/// by their NUL-terminated names, in one buffer; null when empty.
+fn export_function_stats(stats: &[FunctionStats], generation: u64) -> *const CraneliftFunctionStats {
+    if stats.is_empty() {
+        return ptr::null();
+    }
+    let records = std::mem::size_of::<CraneliftFunctionStats>() * stats.len();
+    let names: usize = stats.iter().map(|s| s.name.len() + 1).sum();
+    let layout = Layout::from_size_align(records + names, std::mem::align_of::<CraneliftFunctionStats>())
+        .expect("function stats fit in memory");
+    let base = unsafe { std::alloc::alloc(layout) };
+    if base.is_null() {
+        std::alloc::handle_alloc_error(layout);
+    }
+    let mut name = unsafe { base.add(records) };
+    for (i, s) in stats.iter().enumerate() {
+        unsafe {
+            ptr::copy_nonoverlapping(s.name.as_ptr(), name, s.name.len());
+            *name.add(s.name.len()) = 0;
+            (base as *mut CraneliftFunctionStats).add(i).write(CraneliftFunctionStats {
+                name: name as *const i8,
+                translate_us: s.translate_us,
+                codegen_us: s.codegen_us,
+                code_bytes: s.code_bytes,
+            });
+            name = name.add(s.name.len() + 1);
+        }
+    }
+    live_buffers().insert(base as usize, LiveBuffer { layout, generation, context: None });
+    base as *const CraneliftFunctionStats
+}
+
+/// Free a buffer from `export_buffer`; unknown or already freed addresses,
+/// and addresses now owned by another generation, are ignored.
+fn release_buffer(ptr: *const u8, generation: u64) {
+    if ptr.is_null() {
+        return;
+    }
+    let mut buffers = live_buffers();
+    if buffers.get(&(ptr as usize)).is_some_and(|b| b.generation == generation)
+        && let Some(buffer) = buffers.remove(&(ptr as usize))
+    {
+        unsafe { std::alloc::dealloc(ptr as *mut u8, buffer.layout) };
+    }
+}
+
+/// Hand the buffers of `result` to `context`.
+fn adopt_buffers(result: &CraneliftResult, context: ResultContext) {
+    let mut buffers = live_buffers();
+    let owned = [result.data, result.ir_text as *const u8, result.error_msg as *const u8];
+    for ptr in owned.into_iter().chain([result.function_stats as *const u8]) {
+        if let Some(buffer) = buffers.get_mut(&(ptr as usize))
+            && buffer.generation == result.generation
+        {
+            buffer.context = Some(context);
+        }
+    }
+}
+
+/// Free every buffer `context` owns.
+fn release_context_buffers(context: ResultContext) {
+    let mut buffers = live_buffers();
+    let owned: Vec<usize> = buffers
+        .iter()
+        .filter(|(_, buffer)| buffer.context == Some(context))
+        .map(|(&ptr, _)| ptr)
+        .collect();
+    for ptr in owned {
+        if let Some(buffer) = buffers.remove(&ptr) {
+            unsafe { std::alloc::dealloc(ptr as *mut u8, buffer.layout) };
+        }
+    }
 }
 
 /// Options struct received from C++.
 #[repr(C)]
 pub struct CraneliftOptions {
+    /// `sizeof(CraneliftOptions)` as the caller compiled it; anything else
+    /// fails the call before another field is read
+    pub struct_size: usize,
     pub optimization_level: i32,
     pub target_triple: *const i8,
     pub debug_info: i32,
     pub dll_export: i32,
+    pub emit_inline_metadata: i32,
+    pub trace_hooks: i32,
+    pub ir_filter: *const i8,
+    pub ir_annotate: i32,
+    pub ir_sort: i32,
+    pub fast_math: i32,
+    pub nan_canonicalization: i32,
+    pub frame_warn_bytes: usize,
+    pub frame_pointers: i32,
+    pub trap_abort: i32,
+    pub tests: *const CraneliftTestEntry,
+    pub num_tests: usize,
+    pub strict_runtime_calls: i32,
+    pub multiversion: *const i8,
+    pub runtime_prefix: i32,
+    pub profile: *const i8,
+    pub profile_overrides: *const i8,
+    pub replay_dir: *const i8,
+    pub json_output: i32,
+    pub cancel_token: *const CraneliftCancelToken,
+    pub emit_ir: i32,
+    pub function_stats: i32,
+    pub write: Option<CraneliftWriteFn>,
+    pub write_context: *mut c_void,
+    pub symbol_renames: *const CraneliftSymbolRename,
+    pub num_symbol_renames: usize,
+    pub symbol_prefix: *const i8,
+    pub position_independent: i32,
+    pub source_file: *const i8,
+    pub emit_build_info: i32,
+    pub result_context: *const CraneliftContext,
+    pub max_memory_bytes: usize,
+    pub dump: Option<CraneliftDumpFn>,
+    pub dump_context: *mut c_void,
+    pub dump_path: *const i8,
+    pub panic_backtrace: i32,
+}
+
+/// Versioned options received from C++: `CraneliftOptions` behind a
+/// `size`/`version` header, followed by fields that do not fit the plain
+/// struct. `size` is the caller's `sizeof`, so a header mismatch fails the
+/// call instead of reading past the struct.
+#[repr(C)]
+pub struct CraneliftOptionsV2 {
+    pub size: u32,
+    pub version: u32,
+    pub base: CraneliftOptions,
+    /// `key=value` Cranelift settings, applied after the profile and
+    /// `profile_overrides` as `flag.<key>=<value>` overrides
+    pub flags: *const *const i8,
+    pub num_flags: usize,
+}
+
+/// One `@test` function for the `.tml.tests` registry section.
+#[repr(C)]
+pub struct CraneliftTestEntry {
+    /// MIR function name
+    pub function: *const i8,
+    /// Display name (null = function name)
+    pub name: *const i8,
+    pub should_panic: i32,
+    /// Expected panic message substring (null = any)
+    pub expected_panic: *const i8,
+}
+
+/// A symbol the bridge declares under another name.
+#[repr(C)]
+pub struct CraneliftSymbolRename {
+    /// Symbol the bridge would use, e.g. `tml_foo`
+    pub from: *const i8,
+    /// Symbol declared instead
+    pub to: *const i8,
+}
+
+/// Hand `text` over to C++ as a NUL-terminated string; returns the pointer
+/// and the length without the NUL.
+fn export_text(text: String, generation: u64) -> (*const i8, usize) {
+    let len = text.len();
+    let mut bytes = text.into_bytes();
+    bytes.push(0);
+    (export_buffer(bytes, generation) as *const i8, len)
 }
 
 impl CraneliftResult {
     fn success_with_data(data: Vec<u8>) -> Self {
-        let len = data.len();
-        let ptr = data.as_ptr();
-        std::mem::forget(data); // C++ will call cranelift_free_result
+        let generation = next_generation();
         Self {
             success: 1,
-            data: ptr,
-            data_len: len,
+            data_len: data.len(),
+            data: export_buffer(data, generation),
             ir_text: ptr::null(),
             ir_text_len: 0,
             error_msg: ptr::null(),
+            error_msg_len: 0,
+            magic: RESULT_LIVE,
+            cancelled: 0,
+            error_code: ERROR_NONE,
+            function_stats: ptr::null(),
+            num_function_stats: 0,
+            generation,
+        }
+    }
+
+    /// The object of a compile, plus its IR text under `emit_ir`.
+    fn success_with_compiled(compiled: Compiled) -> Self {
+        let mut result = Self::success_with_stats(compiled.object, &compiled.function_stats);
+        if let Some(ir) = compiled.ir_text {
+            (result.ir_text, result.ir_text_len) = export_text(ir, result.generation);
         }
+        result
+    }
+
+    fn success_with_stats(data: Vec<u8>, stats: &[FunctionStats]) -> Self {
+        let mut result = Self::success_with_data(data);
+        result.function_stats = export_function_stats(stats, result.generation);
+        result.num_function_stats = stats.len();
+        result
     }
 
     fn success_with_ir(ir: String) -> Self {
-        let cstr = CString::new(ir).unwrap_or_default();
-        let len = cstr.as_bytes().len();
-        let ptr = cstr.as_ptr();
-        std::mem::forget(cstr);
+        // Interior NULs are kept: IR may quote string data
+        let generation = next_generation();
+        let (ir_text, ir_text_len) = export_text(ir, generation);
         Self {
             success: 1,
             data: ptr::null(),
             data_len: 0,
-            ir_text: ptr,
-            ir_text_len: len,
+            ir_text,
+            ir_text_len,
             error_msg: ptr::null(),
+            error_msg_len: 0,
+            magic: RESULT_LIVE,
+            cancelled: 0,
+            error_code: ERROR_NONE,
+            function_stats: ptr::null(),
+            num_function_stats: 0,
+            generation,
         }
     }
 
-    fn error(msg: String) -> Self {
-        let cstr = CString::new(msg).unwrap_or_default();
-        let ptr = cstr.as_ptr();
-        std::mem::forget(cstr);
+    fn error(code: i32, msg: String) -> Self {
+        // Escape interior NULs so the message also reads whole as a C string
+        let generation = next_generation();
+        let (error_msg, error_msg_len) = export_text(msg.replace('\0', "\\0"), generation);
         Self {
             success: 0,
             data: ptr::null(),
             data_len: 0,
             ir_text: ptr::null(),
             ir_text_len: 0,
-            error_msg: ptr,
+            error_msg,
+            error_msg_len,
+            magic: RESULT_LIVE,
+            cancelled: 0,
+            error_code: code,
+            function_stats: ptr::null(),
+            num_function_stats: 0,
+            generation,
         }
     }
-}
 
-fn get_target_triple(opts: &CraneliftOptions) -> String {
-    if opts.target_triple.is_null() {
-        return String::new();
+    /// Turn a result carrying a report in `ir_text` into a failure with
+    /// `msg`, keeping the report.
+    fn fail_with_report(&mut self, code: i32, msg: String) {
+        let (error_msg, error_msg_len) = export_text(msg.replace('\0', "\\0"), self.generation);
+        self.success = 0;
+        self.error_code = code;
+        self.error_msg = error_msg;
+        self.error_msg_len = error_msg_len;
+    }
+
+    fn from_error(e: BridgeError) -> Self {
+        let mut result = Self::error(e.code(), e.to_string());
+        result.cancelled = matches!(e, BridgeError::Cancelled) as i32;
+        result
     }
-    unsafe { CStr::from_ptr(opts.target_triple) }
-        .to_str()
-        .unwrap_or("")
-        .to_string()
 }
 
 fn compile_mir_impl(
     mir_data: &[u8],
     func_indices: Option<&[usize]>,
-    opts: &CraneliftOptions,
-) -> BridgeResult<Vec<u8>> {
+    opts: &BridgeOptions,
+) -> BridgeResult<Compiled> {
     let mut reader = MirBinaryReader::new(mir_data);
     let module = reader.read_module()?;
+    compile_module(&module, func_indices, opts)
+}
 
-    let target = get_target_triple(opts);
-    let opt_level = opts.optimization_level.max(0).min(3) as u8;
+/// Compile a module with function bodies compiled on `threads` worker
+/// threads (see `parallel`).
+fn compile_mir_parallel_impl(mir_data: &[u8], threads: usize, opts: &BridgeOptions) -> BridgeResult<Compiled> {
+    let module = MirBinaryReader::new(mir_data).read_module()?;
+    let mut translator = ModuleTranslator::new(opts)?;
+    translator.compile_in_parallel(threads);
+    translator.translate_module(&module, None)?;
+    translator.finish()
+}
 
-    let mut translator = ModuleTranslator::new(&target, opt_level)?;
-    translator.translate_module(&module, func_indices)?;
+fn compile_module(
+    module: &mir_types::Module,
+    func_indices: Option<&[usize]>,
+    opts: &BridgeOptions,
+) -> BridgeResult<Compiled> {
+    let mut translator = ModuleTranslator::new(opts)?;
+    translator.translate_module(module, func_indices)?;
     translator.finish()
 }
 
-fn generate_ir_impl(mir_data: &[u8], opts: &CraneliftOptions) -> BridgeResult<String> {
+/// Indices of the functions named `names` (the first one of each name);
+/// fails naming every name the module does not define or declare.
+fn function_indices(module: &mir_types::Module, names: &[String]) -> Result<Vec<usize>, String> {
+    let mut by_name = HashMap::new();
+    for (i, func) in module.functions.iter().enumerate() {
+        by_name.entry(func.name.as_str()).or_insert(i);
+    }
+    let mut indices = Vec::with_capacity(names.len());
+    let mut missing = Vec::new();
+    for name in names {
+        match by_name.get(name.as_str()) {
+            Some(&i) => indices.push(i),
+            None => missing.push(format!("'{}'", name)),
+        }
+    }
+    if missing.is_empty() {
+        Ok(indices)
+    } else {
+        Err(format!("no function named {} in module '{}'", missing.join(", "), module.name))
+    }
+}
+
+fn read_modules(blobs: &[&[u8]]) -> BridgeResult<Vec<mir_types::Module>> {
+    blobs.iter().map(|blob| MirBinaryReader::new(blob).read_module()).collect()
+}
+
+fn compile_mir_lto_impl(blobs: &[&[u8]], opts: &BridgeOptions) -> BridgeResult<Compiled> {
+    let mut module = lto::merge_modules(read_modules(blobs)?)?;
+    lto::prune_unreachable(&mut module);
+    if opts.opt_level > 0 {
+        lto::inline_small_functions(&mut module)?;
+    }
+    compile_module(&module, None, opts)
+}
+
+/// Every module in one object: the modules are merged as for LTO, so calls
+/// between them are direct and a function several modules define (say, the
+/// same generic instance) is emitted once, but nothing is dropped,
+/// internalized or inlined.
+fn compile_mir_multi_impl(blobs: &[&[u8]], opts: &BridgeOptions) -> BridgeResult<Compiled> {
+    compile_module(&lto::merge_modules(read_modules(blobs)?)?, None, opts)
+}
+
+fn generate_ir_impl(
+    mir_data: &[u8],
+    func_indices: Option<&[usize]>,
+    opts: &BridgeOptions,
+) -> BridgeResult<String> {
     let mut reader = MirBinaryReader::new(mir_data);
     let module = reader.read_module()?;
 
-    let target = get_target_triple(opts);
-    let opt_level = opts.optimization_level.max(0).min(3) as u8;
+    let mut translator = ModuleTranslator::new(opts)?;
+    translator.generate_ir_text(&module, func_indices)
+}
+
+/// Assembly listings of the compiled functions (see `disasm`).
+fn disassemble_impl(
+    mir_data: &[u8],
+    func_indices: Option<&[usize]>,
+    opts: &BridgeOptions,
+) -> BridgeResult<String> {
+    let module = MirBinaryReader::new(mir_data).read_module()?;
+    let mut translator = ModuleTranslator::new(opts)?;
+    translator.disassemble();
+    translator.translate_module(&module, func_indices)?;
+    Ok(translator.take_disassembly())
+}
 
-    let mut translator = ModuleTranslator::new(&target, opt_level)?;
-    translator.generate_ir_text(&module)
+/// Code size estimates of the functions of a module (see
+/// `ModuleTranslator::estimate_sizes`).
+fn estimate_impl(mir_data: &[u8], opts: &BridgeOptions) -> BridgeResult<Vec<FunctionStats>> {
+    let module = MirBinaryReader::new(mir_data).read_module()?;
+    let mut translator = ModuleTranslator::new(opts)?;
+    translator.estimate_sizes();
+    translator.translate_module(&module, None)?;
+    Ok(translator.take_estimates())
 }
 
 /// Catch panics and convert to CraneliftResult.
 fn catch_and_convert<F: FnOnce() -> CraneliftResult + panic::UnwindSafe>(f: F) -> CraneliftResult {
     match panic::catch_unwind(f) {
         Ok(result) => result,
-        Err(e) => {
-            let msg = if let Some(s) = e.downcast_ref::<&str>() {
-                s.to_string()
-            } else if let Some(s) = e.downcast_ref::<String>() {
-                s.clone()
-            } else {
-                "unknown panic in Cranelift bridge".to_string()
-            };
-            CraneliftResult::error(format!("PANIC: {}", msg))
+        Err(e) => CraneliftResult::from_error(BridgeError::Panic(panic_message(e))),
+    }
+}
+
+/// The message of a caught panic, with its backtrace under
+/// `panic_backtrace`.
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    let message = if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic in Cranelift bridge".to_string()
+    };
+    backtrace::append(message)
+}
+
+/// Run a translation of `input`, catching panics, and return the error
+/// result when it fails. When `replay_dir` is set, save a replay bundle and
+/// name it in the message; cancelled compiles have nothing to replay.
+fn translate_with_replay<T>(
+    input: replay::Input,
+    opts: &BridgeOptions,
+    f: impl FnOnce() -> BridgeResult<T>,
+) -> Result<T, CraneliftResult> {
+    if opts.max_memory_bytes > 0 && !memory::COUNTING {
+        return Err(CraneliftResult::from_error(BridgeError::InvalidOptions(
+            "max_memory_bytes needs a bridge built with the memory-limit feature".to_string(),
+        )));
+    }
+    let budget = memory::Budget::new(opts.max_memory_bytes);
+    let scope = memory::enter(budget.as_ref());
+    let outcome = panic::catch_unwind(panic::AssertUnwindSafe(f));
+    drop(scope);
+    let error = match outcome {
+        Ok(Ok(value)) => return Ok(value),
+        Ok(Err(BridgeError::Cancelled)) => return Err(CraneliftResult::from_error(BridgeError::Cancelled)),
+        Ok(Err(e)) => e,
+        Err(e) => BridgeError::Panic(panic_message(e)),
+    };
+    let (code, msg) = (error.code(), error.to_string());
+    let Some(dir) = &opts.replay_dir else {
+        return Err(CraneliftResult::error(code, msg));
+    };
+    match replay::write(dir, &input, opts, &msg) {
+        Ok(path) => Err(CraneliftResult::error(code, format!("{} (replay bundle: {})", msg, path.display()))),
+        Err(e) => Err(CraneliftResult::error(code, format!("{} (replay bundle not written: {})", msg, e))),
+    }
+}
+
+/// Run `f` with panics caught and store its result through the out-parameter
+/// of a `*_v2` entry point.
+fn write_result(result: *mut CraneliftResult, f: impl FnOnce() -> CraneliftResult + panic::UnwindSafe) -> i32 {
+    if result.is_null() {
+        return 0;
+    }
+    if let Err(e) = ffi_check::run(|| ffi_check::pointer("result", result)) {
+        // Still reported, as the caller's misaligned struct is writable
+        unsafe { result.write_unaligned(CraneliftResult::from_error(e)) };
+        return 0;
+    }
+    result_context::set_current(None);
+    backtrace::enable(false);
+    let value = catch_and_convert(f);
+    if let Some(context) = result_context::take_current() {
+        result_context::with_live(context, || adopt_buffers(&value, context));
+    }
+    let success = value.success;
+    unsafe { result.write(value) };
+    success
+}
+
+/// Call a `*_v2` entry point and return its result by value.
+fn by_value(f: impl FnOnce(*mut CraneliftResult) -> i32) -> CraneliftResult {
+    let mut result = MaybeUninit::<CraneliftResult>::uninit();
+    f(result.as_mut_ptr());
+    // Written by `write_result`, as the pointer is not null
+    unsafe { result.assume_init() }
+}
+
+/// Options as received over the C API.
+#[derive(Clone, Copy)]
+enum RawOptions {
+    Struct(*const CraneliftOptions),
+    /// Key-value blob, see `options::BLOB_MAGIC`
+    Blob(*const u8, usize),
+    Versioned(*const CraneliftOptionsV2),
+}
+
+impl RawOptions {
+    fn resolve(self) -> BridgeResult<BridgeOptions> {
+        ffi_check::run(|| match self {
+            RawOptions::Struct(options) => ffi_check::options(options),
+            RawOptions::Blob(data, len) => ffi_check::array("options", data, len),
+            RawOptions::Versioned(options) => ffi_check::options_v2(options),
+        })?;
+        let opts = match self {
+            RawOptions::Struct(options) => BridgeOptions::from_raw(options),
+            RawOptions::Blob(data, len) if data.is_null() || len == 0 => Ok(BridgeOptions::default()),
+            RawOptions::Blob(data, len) => {
+                BridgeOptions::from_blob(unsafe { slice::from_raw_parts(data, len) })
+            }
+            RawOptions::Versioned(options) => BridgeOptions::from_versioned(options),
+        }?;
+        // Picked up by `write_result` once the call's result is built
+        result_context::set_current(opts.result_context);
+        backtrace::enable(opts.panic_backtrace);
+        Ok(opts)
+    }
+}
+
+/// Compile a module, or the functions at `func_indices` (CGU mode).
+fn compile_entry(
+    entry: &str,
+    mir_data: *const u8,
+    mir_len: usize,
+    func_indices: *const usize,
+    num_indices: usize,
+    options: RawOptions,
+) -> CraneliftResult {
+    if mir_data.is_null() || mir_len == 0 {
+        return CraneliftResult::error(ERROR_INVALID_ARGUMENT, "null or empty MIR data".into());
+    }
+    let checked = ffi_check::run(|| {
+        ffi_check::array("mir_data", mir_data, mir_len)?;
+        ffi_check::array("func_indices", func_indices, num_indices)
+    });
+    if let Err(e) = checked {
+        return CraneliftResult::from_error(e);
+    }
+    let data = unsafe { slice::from_raw_parts(mir_data, mir_len) };
+    let indices = if func_indices.is_null() || num_indices == 0 {
+        None
+    } else {
+        Some(unsafe { slice::from_raw_parts(func_indices, num_indices) })
+    };
+    let opts = match options.resolve() {
+        Ok(opts) => opts,
+        Err(e) => return CraneliftResult::from_error(e),
+    };
+
+    let input = replay::Input { entry, modules: &[data], func_indices: indices };
+    match translate_with_replay(input, &opts, || compile_mir_impl(data, indices, &opts)) {
+        Ok(compiled) => CraneliftResult::success_with_compiled(compiled),
+        Err(result) => result,
+    }
+}
+
+fn compile_parallel_entry(mir_data: *const u8, mir_len: usize, threads: usize, options: RawOptions) -> CraneliftResult {
+    if mir_data.is_null() || mir_len == 0 {
+        return CraneliftResult::error(ERROR_INVALID_ARGUMENT, "null or empty MIR data".into());
+    }
+    if let Err(e) = ffi_check::run(|| ffi_check::array("mir_data", mir_data, mir_len)) {
+        return CraneliftResult::from_error(e);
+    }
+    let data = unsafe { slice::from_raw_parts(mir_data, mir_len) };
+    let opts = match options.resolve() {
+        Ok(opts) => opts,
+        Err(e) => return CraneliftResult::from_error(e),
+    };
+
+    let input = replay::Input { entry: "compile_parallel", modules: &[data], func_indices: None };
+    match translate_with_replay(input, &opts, || compile_mir_parallel_impl(data, threads, &opts)) {
+        Ok(compiled) => CraneliftResult::success_with_compiled(compiled),
+        Err(result) => result,
+    }
+}
+
+/// Compile the functions named `func_names` (MIR names, as
+/// `cranelift_list_functions` reports them). Replay bundles record the
+/// resolved indices.
+fn compile_named_entry(
+    mir_data: *const u8,
+    mir_len: usize,
+    func_names: *const *const i8,
+    num_names: usize,
+    options: RawOptions,
+) -> CraneliftResult {
+    if mir_data.is_null() || mir_len == 0 {
+        return CraneliftResult::error(ERROR_INVALID_ARGUMENT, "null or empty MIR data".into());
+    }
+    if func_names.is_null() || num_names == 0 {
+        return CraneliftResult::error(ERROR_INVALID_ARGUMENT, "null or empty function name list".into());
+    }
+    let checked = ffi_check::run(|| {
+        ffi_check::array("mir_data", mir_data, mir_len)?;
+        ffi_check::array("func_names", func_names, num_names)
+    });
+    if let Err(e) = checked {
+        return CraneliftResult::from_error(e);
+    }
+    let data = unsafe { slice::from_raw_parts(mir_data, mir_len) };
+    let mut names = Vec::with_capacity(num_names);
+    for (i, &name) in unsafe { slice::from_raw_parts(func_names, num_names) }.iter().enumerate() {
+        if name.is_null() {
+            return CraneliftResult::error(ERROR_INVALID_ARGUMENT, format!("null function name at index {}", i));
+        }
+        if let Err(e) = ffi_check::run(|| ffi_check::pointer(&format!("func_names[{}]", i), name)) {
+            return CraneliftResult::from_error(e);
+        }
+        names.push(unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned());
+    }
+    let opts = match options.resolve() {
+        Ok(opts) => opts,
+        Err(e) => return CraneliftResult::from_error(e),
+    };
+
+    let whole = replay::Input { entry: "compile_named", modules: &[data], func_indices: None };
+    let module = match translate_with_replay(whole, &opts, || MirBinaryReader::new(data).read_module()) {
+        Ok(module) => module,
+        Err(result) => return result,
+    };
+    let indices = match function_indices(&module, &names) {
+        Ok(indices) => indices,
+        Err(msg) => return CraneliftResult::error(ERROR_INVALID_ARGUMENT, msg),
+    };
+    let input = replay::Input { entry: "compile_named", modules: &[data], func_indices: Some(&indices) };
+    match translate_with_replay(input, &opts, || compile_module(&module, Some(&indices), &opts)) {
+        Ok(compiled) => CraneliftResult::success_with_compiled(compiled),
+        Err(result) => result,
+    }
+}
+
+fn compile_lto_entry(
+    mir_blobs: *const *const u8,
+    mir_lens: *const usize,
+    count: usize,
+    options: RawOptions,
+) -> CraneliftResult {
+    compile_modules_entry("compile_lto", compile_mir_lto_impl, mir_blobs, mir_lens, count, options)
+}
+
+fn compile_multi_entry(
+    mir_blobs: *const *const u8,
+    mir_lens: *const usize,
+    count: usize,
+    options: RawOptions,
+) -> CraneliftResult {
+    compile_modules_entry("compile_multi", compile_mir_multi_impl, mir_blobs, mir_lens, count, options)
+}
+
+/// Compiles several MIR modules into one object.
+type ModulesCompiler = fn(&[&[u8]], &BridgeOptions) -> BridgeResult<Compiled>;
+
+fn compile_modules_entry(
+    entry: &str,
+    compile: ModulesCompiler,
+    mir_blobs: *const *const u8,
+    mir_lens: *const usize,
+    count: usize,
+    options: RawOptions,
+) -> CraneliftResult {
+    if mir_blobs.is_null() || mir_lens.is_null() || count == 0 {
+        return CraneliftResult::error(ERROR_INVALID_ARGUMENT, "null or empty MIR module list".into());
+    }
+    let checked = ffi_check::run(|| {
+        ffi_check::array("mir_blobs", mir_blobs, count)?;
+        ffi_check::array("mir_lens", mir_lens, count)
+    });
+    if let Err(e) = checked {
+        return CraneliftResult::from_error(e);
+    }
+    let ptrs = unsafe { slice::from_raw_parts(mir_blobs, count) };
+    let lens = unsafe { slice::from_raw_parts(mir_lens, count) };
+    let mut blobs = Vec::with_capacity(count);
+    for (i, (&p, &len)) in ptrs.iter().zip(lens).enumerate() {
+        if p.is_null() || len == 0 {
+            return CraneliftResult::error(ERROR_INVALID_ARGUMENT, format!("null or empty MIR data for module {}", i));
         }
+        if let Err(e) = ffi_check::run(|| ffi_check::array(&format!("mir_blobs[{}]", i), p, len)) {
+            return CraneliftResult::from_error(e);
+        }
+        blobs.push(unsafe { slice::from_raw_parts(p, len) });
+    }
+    let opts = match options.resolve() {
+        Ok(opts) => opts,
+        Err(e) => return CraneliftResult::from_error(e),
+    };
+
+    let input = replay::Input { entry, modules: &blobs, func_indices: None };
+    match translate_with_replay(input, &opts, || compile(&blobs, &opts)) {
+        Ok(compiled) => CraneliftResult::success_with_compiled(compiled),
+        Err(result) => result,
+    }
+}
+
+/// IR text of a module, or of the functions at `func_indices` (CGU mode).
+fn generate_ir_entry(
+    entry: &str,
+    mir_data: *const u8,
+    mir_len: usize,
+    func_indices: *const usize,
+    num_indices: usize,
+    options: RawOptions,
+) -> CraneliftResult {
+    text_entry(entry, generate_ir_impl, mir_data, mir_len, func_indices, num_indices, options)
+}
+
+/// Renders a module, or the functions at the given indices, as text.
+type TextGenerator = fn(&[u8], Option<&[usize]>, &BridgeOptions) -> BridgeResult<String>;
+
+/// Text produced by `generate` (IR or disassembly) for a module, returned
+/// in `ir_text`.
+fn text_entry(
+    entry: &str,
+    generate: TextGenerator,
+    mir_data: *const u8,
+    mir_len: usize,
+    func_indices: *const usize,
+    num_indices: usize,
+    options: RawOptions,
+) -> CraneliftResult {
+    if mir_data.is_null() || mir_len == 0 {
+        return CraneliftResult::error(ERROR_INVALID_ARGUMENT, "null or empty MIR data".into());
+    }
+    let checked = ffi_check::run(|| {
+        ffi_check::array("mir_data", mir_data, mir_len)?;
+        ffi_check::array("func_indices", func_indices, num_indices)
+    });
+    if let Err(e) = checked {
+        return CraneliftResult::from_error(e);
+    }
+    let data = unsafe { slice::from_raw_parts(mir_data, mir_len) };
+    let indices = if func_indices.is_null() || num_indices == 0 {
+        None
+    } else {
+        Some(unsafe { slice::from_raw_parts(func_indices, num_indices) })
+    };
+    let opts = match options.resolve() {
+        Ok(opts) => opts,
+        Err(e) => return CraneliftResult::from_error(e),
+    };
+
+    let input = replay::Input { entry, modules: &[data], func_indices: indices };
+    match translate_with_replay(input, &opts, || generate(data, indices, &opts)) {
+        Ok(ir_text) => CraneliftResult::success_with_ir(ir_text),
+        Err(result) => result,
+    }
+}
+
+/// Estimate the code size of each function of a module, returned as
+/// `function_stats` records without an object.
+fn estimate_entry(mir_data: *const u8, mir_len: usize, options: RawOptions) -> CraneliftResult {
+    if mir_data.is_null() || mir_len == 0 {
+        return CraneliftResult::error(ERROR_INVALID_ARGUMENT, "null or empty MIR data".into());
+    }
+    if let Err(e) = ffi_check::run(|| ffi_check::array("mir_data", mir_data, mir_len)) {
+        return CraneliftResult::from_error(e);
+    }
+    let data = unsafe { slice::from_raw_parts(mir_data, mir_len) };
+    let opts = match options.resolve() {
+        Ok(opts) => opts,
+        Err(e) => return CraneliftResult::from_error(e),
+    };
+
+    let input = replay::Input { entry: "estimate", modules: &[data], func_indices: None };
+    match translate_with_replay(input, &opts, || estimate_impl(data, &opts)) {
+        Ok(stats) => CraneliftResult::success_with_stats(Vec::new(), &stats),
+        Err(result) => result,
+    }
+}
+
+/// Read a module and return `describe(module)` in `ir_text`, without
+/// translating anything.
+fn describe_entry(
+    mir_data: *const u8,
+    mir_len: usize,
+    describe: impl FnOnce(&mir_types::Module) -> String,
+) -> CraneliftResult {
+    if mir_data.is_null() || mir_len == 0 {
+        return CraneliftResult::error(ERROR_INVALID_ARGUMENT, "null or empty MIR data".into());
+    }
+    if let Err(e) = ffi_check::run(|| ffi_check::array("mir_data", mir_data, mir_len)) {
+        return CraneliftResult::from_error(e);
+    }
+    let data = unsafe { slice::from_raw_parts(mir_data, mir_len) };
+
+    match MirBinaryReader::new(data).read_module() {
+        Ok(module) => CraneliftResult::success_with_ir(describe(&module)),
+        Err(e) => CraneliftResult::from_error(e),
+    }
+}
+
+fn resolve_options_entry(options: RawOptions) -> CraneliftResult {
+    let resolved = options
+        .resolve()
+        .and_then(|opts| {
+            cpu::isa(&opts)?;
+            Ok((profiles::target(&opts.target_triple)?, opts))
+        });
+    match resolved {
+        Ok((target, opts)) => CraneliftResult::success_with_ir(profiles::to_json(&opts, &target)),
+        Err(e) => CraneliftResult::from_error(e),
+    }
+}
+
+fn self_test_entry(options: RawOptions) -> CraneliftResult {
+    let opts = match options.resolve() {
+        Ok(opts) => opts,
+        Err(e) => return CraneliftResult::from_error(e),
+    };
+    let report = self_test::run(&opts);
+    let mut result = CraneliftResult::success_with_ir(report.to_json());
+    if let Some(failure) = report.first_failure() {
+        result.fail_with_report(ERROR_INVALID_TARGET, format!("self test failed: {}", failure));
+    }
+    result
+}
+
+/// Validate a module without translating it; see `cranelift_validate_mir_v2`.
+fn validate_entry(mir_data: *const u8, mir_len: usize) -> CraneliftResult {
+    let mut first_error = None;
+    let mut result = describe_entry(mir_data, mir_len, |module| {
+        let diags = validate::check_module(module);
+        first_error = diags.iter().find(|d| d.severity == validate::Severity::Error).map(|d| d.to_string());
+        validate::report_json(module, &diags)
+    });
+    if let Some(error) = first_error {
+        result.fail_with_report(ERROR_TRANSLATION, format!("MIR validation failed: {}", error));
     }
+    result
 }
 
 // ============================================================================
 // C API
+//
+// Every entry point has a `*_v2` form taking a caller-provided result as an
+// out-parameter. Returning the struct by value depends on rustc and the C++
+// toolchain agreeing on the struct-return ABI (MSVC and MinGW differ); the
+// by-value forms remain as wrappers for existing callers. A `*_v2` call
+// returns the result's `success`, or does nothing and returns 0 when the
+// result pointer is null.
 // ============================================================================
 
 /// Compile a full MIR module to an object file.
 #[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_v2(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const CraneliftOptions,
+    result: *mut CraneliftResult,
+) -> i32 {
+    write_result(result, move || {
+        compile_entry("compile", mir_data, mir_len, ptr::null(), 0, RawOptions::Struct(options))
+    })
+}
+
+/// By-value variant of `cranelift_compile_mir_v2`.
+#[unsafe(no_mangle)]
 pub extern "C" fn cranelift_compile_mir(
     mir_data: *const u8,
     mir_len: usize,
     options: *const CraneliftOptions,
 ) -> CraneliftResult {
-    catch_and_convert(move || {
-        if mir_data.is_null() || mir_len == 0 {
-            return CraneliftResult::error("null or empty MIR data".into());
-        }
-        let data = unsafe { slice::from_raw_parts(mir_data, mir_len) };
-        let opts = if options.is_null() {
-            CraneliftOptions {
-                optimization_level: 0,
-                target_triple: ptr::null(),
-                debug_info: 0,
-                dll_export: 0,
-            }
-        } else {
-            unsafe { ptr::read(options) }
-        };
+    by_value(|result| cranelift_compile_mir_v2(mir_data, mir_len, options, result))
+}
 
-        match compile_mir_impl(data, None, &opts) {
-            Ok(obj_bytes) => CraneliftResult::success_with_data(obj_bytes),
-            Err(e) => CraneliftResult::error(e.to_string()),
-        }
+/// Compile a full MIR module to one object, with the function bodies
+/// compiled on `num_threads` threads inside the bridge (0 = one per CPU).
+/// Translation to Cranelift IR stays on the calling thread; the object is
+/// the same for every thread count.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_parallel_v2(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const CraneliftOptions,
+    num_threads: usize,
+    result: *mut CraneliftResult,
+) -> i32 {
+    write_result(result, move || {
+        compile_parallel_entry(mir_data, mir_len, num_threads, RawOptions::Struct(options))
     })
 }
 
+/// By-value variant of `cranelift_compile_mir_parallel_v2`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_parallel(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const CraneliftOptions,
+    num_threads: usize,
+) -> CraneliftResult {
+    by_value(|result| cranelift_compile_mir_parallel_v2(mir_data, mir_len, options, num_threads, result))
+}
+
 /// Compile a subset of functions from a MIR module (CGU mode).
 #[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_cgu_v2(
+    mir_data: *const u8,
+    mir_len: usize,
+    func_indices: *const usize,
+    num_indices: usize,
+    options: *const CraneliftOptions,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Struct(options);
+    write_result(result, move || {
+        compile_entry("compile_cgu", mir_data, mir_len, func_indices, num_indices, options)
+    })
+}
+
+/// By-value variant of `cranelift_compile_mir_cgu_v2`.
+#[unsafe(no_mangle)]
 pub extern "C" fn cranelift_compile_mir_cgu(
     mir_data: *const u8,
     mir_len: usize,
@@ -181,98 +1049,989 @@ pub extern "C" fn cranelift_compile_mir_cgu(
     num_indices: usize,
     options: *const CraneliftOptions,
 ) -> CraneliftResult {
-    catch_and_convert(move || {
-        if mir_data.is_null() || mir_len == 0 {
-            return CraneliftResult::error("null or empty MIR data".into());
-        }
-        let data = unsafe { slice::from_raw_parts(mir_data, mir_len) };
-        let indices = if func_indices.is_null() || num_indices == 0 {
-            None
-        } else {
-            Some(unsafe { slice::from_raw_parts(func_indices, num_indices) })
-        };
-        let opts = if options.is_null() {
-            CraneliftOptions {
-                optimization_level: 0,
-                target_triple: ptr::null(),
-                debug_info: 0,
-                dll_export: 0,
-            }
-        } else {
-            unsafe { ptr::read(options) }
-        };
+    by_value(|result| cranelift_compile_mir_cgu_v2(mir_data, mir_len, func_indices, num_indices, options, result))
+}
 
-        match compile_mir_impl(data, indices, &opts) {
-            Ok(obj_bytes) => CraneliftResult::success_with_data(obj_bytes),
-            Err(e) => CraneliftResult::error(e.to_string()),
-        }
-    })
+/// Compile the functions named in `func_names` (CGU mode by name). Unlike
+/// indices, names survive the MIR writer reordering functions; names the
+/// module does not contain fail the call, listing each of them.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_named_v2(
+    mir_data: *const u8,
+    mir_len: usize,
+    func_names: *const *const i8,
+    num_names: usize,
+    options: *const CraneliftOptions,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Struct(options);
+    write_result(result, move || compile_named_entry(mir_data, mir_len, func_names, num_names, options))
 }
 
-/// Generate Cranelift IR text from a MIR module (no compilation).
+/// By-value variant of `cranelift_compile_mir_named_v2`.
 #[unsafe(no_mangle)]
-pub extern "C" fn cranelift_generate_ir(
+pub extern "C" fn cranelift_compile_mir_named(
     mir_data: *const u8,
     mir_len: usize,
+    func_names: *const *const i8,
+    num_names: usize,
     options: *const CraneliftOptions,
 ) -> CraneliftResult {
-    catch_and_convert(move || {
-        if mir_data.is_null() || mir_len == 0 {
-            return CraneliftResult::error("null or empty MIR data".into());
-        }
-        let data = unsafe { slice::from_raw_parts(mir_data, mir_len) };
-        let opts = if options.is_null() {
-            CraneliftOptions {
-                optimization_level: 0,
-                target_triple: ptr::null(),
-                debug_info: 0,
-                dll_export: 0,
-            }
-        } else {
-            unsafe { ptr::read(options) }
-        };
+    by_value(|result| cranelift_compile_mir_named_v2(mir_data, mir_len, func_names, num_names, options, result))
+}
 
-        match generate_ir_impl(data, &opts) {
-            Ok(ir_text) => CraneliftResult::success_with_ir(ir_text),
-            Err(e) => CraneliftResult::error(e.to_string()),
-        }
+/// Whole-module LTO: merge several MIR modules into one namespace, drop
+/// unreachable functions, inline small leaves and emit a single object.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_lto_v2(
+    mir_blobs: *const *const u8,
+    mir_lens: *const usize,
+    count: usize,
+    options: *const CraneliftOptions,
+    result: *mut CraneliftResult,
+) -> i32 {
+    write_result(result, move || {
+        compile_lto_entry(mir_blobs, mir_lens, count, RawOptions::Struct(options))
     })
 }
 
-/// Free a CraneliftResult. Must be called for every result returned.
+/// By-value variant of `cranelift_compile_mir_lto_v2`.
 #[unsafe(no_mangle)]
-pub extern "C" fn cranelift_free_result(result: *mut CraneliftResult) {
-    if result.is_null() {
-        return;
-    }
-    let r = unsafe { &*result };
+pub extern "C" fn cranelift_compile_mir_lto(
+    mir_blobs: *const *const u8,
+    mir_lens: *const usize,
+    count: usize,
+    options: *const CraneliftOptions,
+) -> CraneliftResult {
+    by_value(|result| cranelift_compile_mir_lto_v2(mir_blobs, mir_lens, count, options, result))
+}
 
-    if !r.data.is_null() && r.data_len > 0 {
-        unsafe {
-            let _ = Vec::from_raw_parts(r.data as *mut u8, r.data_len, r.data_len);
-        }
+/// Compile several MIR modules into a single object. Every module's
+/// functions are declared before any is translated, so calls between them
+/// are resolved within the object, and a function defined by more than one
+/// module is emitted once (the first definition wins). Unlike LTO, every
+/// function is kept with its linkage.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_multi_v2(
+    mir_blobs: *const *const u8,
+    mir_lens: *const usize,
+    count: usize,
+    options: *const CraneliftOptions,
+    result: *mut CraneliftResult,
+) -> i32 {
+    write_result(result, move || {
+        compile_multi_entry(mir_blobs, mir_lens, count, RawOptions::Struct(options))
+    })
+}
+
+/// By-value variant of `cranelift_compile_mir_multi_v2`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_multi(
+    mir_blobs: *const *const u8,
+    mir_lens: *const usize,
+    count: usize,
+    options: *const CraneliftOptions,
+) -> CraneliftResult {
+    by_value(|result| cranelift_compile_mir_multi_v2(mir_blobs, mir_lens, count, options, result))
+}
+
+/// Generate Cranelift IR text from a MIR module (no compilation).
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_generate_ir_v2(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const CraneliftOptions,
+    result: *mut CraneliftResult,
+) -> i32 {
+    write_result(result, move || {
+        generate_ir_entry("generate_ir", mir_data, mir_len, ptr::null(), 0, RawOptions::Struct(options))
+    })
+}
+
+/// By-value variant of `cranelift_generate_ir_v2`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_generate_ir(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const CraneliftOptions,
+) -> CraneliftResult {
+    by_value(|result| cranelift_generate_ir_v2(mir_data, mir_len, options, result))
+}
+
+/// Generate Cranelift IR text for a subset of functions (CGU mode).
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_generate_ir_cgu_v2(
+    mir_data: *const u8,
+    mir_len: usize,
+    func_indices: *const usize,
+    num_indices: usize,
+    options: *const CraneliftOptions,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Struct(options);
+    write_result(result, move || {
+        generate_ir_entry("generate_ir_cgu", mir_data, mir_len, func_indices, num_indices, options)
+    })
+}
+
+/// By-value variant of `cranelift_generate_ir_cgu_v2`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_generate_ir_cgu(
+    mir_data: *const u8,
+    mir_len: usize,
+    func_indices: *const usize,
+    num_indices: usize,
+    options: *const CraneliftOptions,
+) -> CraneliftResult {
+    by_value(|result| cranelift_generate_ir_cgu_v2(mir_data, mir_len, func_indices, num_indices, options, result))
+}
+
+/// Compile a MIR module and return an assembly listing of each function in
+/// `ir_text` instead of the object. Functions are filtered and ordered by
+/// `ir_filter` and `ir_sort`, as for IR text.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_disassemble_v2(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const CraneliftOptions,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Struct(options);
+    write_result(result, move || {
+        text_entry("disassemble", disassemble_impl, mir_data, mir_len, ptr::null(), 0, options)
+    })
+}
+
+/// By-value variant of `cranelift_disassemble_v2`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_disassemble(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const CraneliftOptions,
+) -> CraneliftResult {
+    by_value(|result| cranelift_disassemble_v2(mir_data, mir_len, options, result))
+}
+
+/// Translate and compile a MIR module without building the object, and
+/// return the estimated machine code size of each function as
+/// `function_stats` records, for balancing CGUs before compiling them.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_estimate_v2(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const CraneliftOptions,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Struct(options);
+    write_result(result, move || estimate_entry(mir_data, mir_len, options))
+}
+
+/// By-value variant of `cranelift_estimate_v2`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_estimate(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const CraneliftOptions,
+) -> CraneliftResult {
+    by_value(|result| cranelift_estimate_v2(mir_data, mir_len, options, result))
+}
+
+/// Summarize a MIR module as JSON (instruction kinds, block/phi density,
+/// largest functions). The document is returned in `ir_text`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_mir_stats_v2(mir_data: *const u8, mir_len: usize, result: *mut CraneliftResult) -> i32 {
+    write_result(result, move || {
+        describe_entry(mir_data, mir_len, |module| stats::MirStats::collect(module).to_json())
+    })
+}
+
+/// By-value variant of `cranelift_mir_stats_v2`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_mir_stats(mir_data: *const u8, mir_len: usize) -> CraneliftResult {
+    by_value(|result| cranelift_mir_stats_v2(mir_data, mir_len, result))
+}
+
+/// List the functions of a MIR module without compiling anything: index,
+/// name, publicness, parameter and return types and size, as JSON in
+/// `ir_text` (see `stats::function_list`).
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_list_functions_v2(mir_data: *const u8, mir_len: usize, result: *mut CraneliftResult) -> i32 {
+    write_result(result, move || describe_entry(mir_data, mir_len, stats::function_list))
+}
+
+/// By-value variant of `cranelift_list_functions_v2`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_list_functions(mir_data: *const u8, mir_len: usize) -> CraneliftResult {
+    by_value(|result| cranelift_list_functions_v2(mir_data, mir_len, result))
+}
+
+/// Check a MIR module without translating it: the header (by reading it),
+/// the CFG of every function and its value references (see `validate`).
+/// The findings are returned as JSON in `ir_text`; with any error the call
+/// fails with the first one as the message, still returning the findings.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_validate_mir_v2(mir_data: *const u8, mir_len: usize, result: *mut CraneliftResult) -> i32 {
+    write_result(result, move || validate_entry(mir_data, mir_len))
+}
+
+/// By-value variant of `cranelift_validate_mir_v2`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_validate_mir(mir_data: *const u8, mir_len: usize) -> CraneliftResult {
+    by_value(|result| cranelift_validate_mir_v2(mir_data, mir_len, result))
+}
+
+/// Resolve the profile, target defaults and overrides of `options` and
+/// return the resulting codegen fields as JSON in `ir_text`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_resolve_options_v2(options: *const CraneliftOptions, result: *mut CraneliftResult) -> i32 {
+    write_result(result, move || resolve_options_entry(RawOptions::Struct(options)))
+}
+
+/// By-value variant of `cranelift_resolve_options_v2`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_resolve_options(options: *const CraneliftOptions) -> CraneliftResult {
+    by_value(|result| cranelift_resolve_options_v2(options, result))
+}
+
+// Key-value options variants: the `*_v2` calls with the options given as a
+// blob (see `options::BLOB_MAGIC`) instead of a `CraneliftOptions` struct,
+// so new options need no struct field. A null or empty blob means defaults.
+
+/// `cranelift_compile_mir_v2` with an options blob.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_kv(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const u8,
+    options_len: usize,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Blob(options, options_len);
+    write_result(result, move || compile_entry("compile", mir_data, mir_len, ptr::null(), 0, options))
+}
+
+/// `cranelift_compile_mir_cgu_v2` with an options blob.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_cgu_kv(
+    mir_data: *const u8,
+    mir_len: usize,
+    func_indices: *const usize,
+    num_indices: usize,
+    options: *const u8,
+    options_len: usize,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Blob(options, options_len);
+    write_result(result, move || {
+        compile_entry("compile_cgu", mir_data, mir_len, func_indices, num_indices, options)
+    })
+}
+
+/// `cranelift_compile_mir_named_v2` with an options blob.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_named_kv(
+    mir_data: *const u8,
+    mir_len: usize,
+    func_names: *const *const i8,
+    num_names: usize,
+    options: *const u8,
+    options_len: usize,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Blob(options, options_len);
+    write_result(result, move || compile_named_entry(mir_data, mir_len, func_names, num_names, options))
+}
+
+/// `cranelift_compile_mir_lto_v2` with an options blob.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_lto_kv(
+    mir_blobs: *const *const u8,
+    mir_lens: *const usize,
+    count: usize,
+    options: *const u8,
+    options_len: usize,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Blob(options, options_len);
+    write_result(result, move || compile_lto_entry(mir_blobs, mir_lens, count, options))
+}
+
+/// `cranelift_compile_mir_multi_v2` with an options blob.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_multi_kv(
+    mir_blobs: *const *const u8,
+    mir_lens: *const usize,
+    count: usize,
+    options: *const u8,
+    options_len: usize,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Blob(options, options_len);
+    write_result(result, move || compile_multi_entry(mir_blobs, mir_lens, count, options))
+}
+
+/// `cranelift_generate_ir_v2` with an options blob.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_generate_ir_kv(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const u8,
+    options_len: usize,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Blob(options, options_len);
+    write_result(result, move || generate_ir_entry("generate_ir", mir_data, mir_len, ptr::null(), 0, options))
+}
+
+/// `cranelift_generate_ir_cgu_v2` with an options blob.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_generate_ir_cgu_kv(
+    mir_data: *const u8,
+    mir_len: usize,
+    func_indices: *const usize,
+    num_indices: usize,
+    options: *const u8,
+    options_len: usize,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Blob(options, options_len);
+    write_result(result, move || {
+        generate_ir_entry("generate_ir_cgu", mir_data, mir_len, func_indices, num_indices, options)
+    })
+}
+
+/// `cranelift_disassemble_v2` with an options blob.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_disassemble_kv(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const u8,
+    options_len: usize,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Blob(options, options_len);
+    write_result(result, move || {
+        text_entry("disassemble", disassemble_impl, mir_data, mir_len, ptr::null(), 0, options)
+    })
+}
+
+/// `cranelift_estimate_v2` with an options blob.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_estimate_kv(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const u8,
+    options_len: usize,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Blob(options, options_len);
+    write_result(result, move || estimate_entry(mir_data, mir_len, options))
+}
+
+/// `cranelift_resolve_options_v2` with an options blob.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_resolve_options_kv(
+    options: *const u8,
+    options_len: usize,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Blob(options, options_len);
+    write_result(result, move || resolve_options_entry(options))
+}
+
+// Versioned options variants: the `*_v2` calls with a `CraneliftOptionsV2`,
+// which adds raw Cranelift settings to the struct fields. A null pointer
+// means defaults.
+
+/// `cranelift_compile_mir_v2` with versioned options.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_ex(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const CraneliftOptionsV2,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Versioned(options);
+    write_result(result, move || compile_entry("compile", mir_data, mir_len, ptr::null(), 0, options))
+}
+
+/// `cranelift_compile_mir_cgu_v2` with versioned options.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_cgu_ex(
+    mir_data: *const u8,
+    mir_len: usize,
+    func_indices: *const usize,
+    num_indices: usize,
+    options: *const CraneliftOptionsV2,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Versioned(options);
+    write_result(result, move || {
+        compile_entry("compile_cgu", mir_data, mir_len, func_indices, num_indices, options)
+    })
+}
+
+/// `cranelift_compile_mir_named_v2` with versioned options.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_named_ex(
+    mir_data: *const u8,
+    mir_len: usize,
+    func_names: *const *const i8,
+    num_names: usize,
+    options: *const CraneliftOptionsV2,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Versioned(options);
+    write_result(result, move || compile_named_entry(mir_data, mir_len, func_names, num_names, options))
+}
+
+/// `cranelift_compile_mir_lto_v2` with versioned options.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_lto_ex(
+    mir_blobs: *const *const u8,
+    mir_lens: *const usize,
+    count: usize,
+    options: *const CraneliftOptionsV2,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Versioned(options);
+    write_result(result, move || compile_lto_entry(mir_blobs, mir_lens, count, options))
+}
+
+/// `cranelift_compile_mir_multi_v2` with versioned options.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_compile_mir_multi_ex(
+    mir_blobs: *const *const u8,
+    mir_lens: *const usize,
+    count: usize,
+    options: *const CraneliftOptionsV2,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Versioned(options);
+    write_result(result, move || compile_multi_entry(mir_blobs, mir_lens, count, options))
+}
+
+/// `cranelift_generate_ir_v2` with versioned options.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_generate_ir_ex(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const CraneliftOptionsV2,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Versioned(options);
+    write_result(result, move || generate_ir_entry("generate_ir", mir_data, mir_len, ptr::null(), 0, options))
+}
+
+/// `cranelift_generate_ir_cgu_v2` with versioned options.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_generate_ir_cgu_ex(
+    mir_data: *const u8,
+    mir_len: usize,
+    func_indices: *const usize,
+    num_indices: usize,
+    options: *const CraneliftOptionsV2,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Versioned(options);
+    write_result(result, move || {
+        generate_ir_entry("generate_ir_cgu", mir_data, mir_len, func_indices, num_indices, options)
+    })
+}
+
+/// `cranelift_disassemble_v2` with versioned options.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_disassemble_ex(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const CraneliftOptionsV2,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Versioned(options);
+    write_result(result, move || {
+        text_entry("disassemble", disassemble_impl, mir_data, mir_len, ptr::null(), 0, options)
+    })
+}
+
+/// `cranelift_estimate_v2` with versioned options.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_estimate_ex(
+    mir_data: *const u8,
+    mir_len: usize,
+    options: *const CraneliftOptionsV2,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Versioned(options);
+    write_result(result, move || estimate_entry(mir_data, mir_len, options))
+}
+
+/// `cranelift_resolve_options_v2` with versioned options.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_resolve_options_ex(
+    options: *const CraneliftOptionsV2,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Versioned(options);
+    write_result(result, move || resolve_options_entry(options))
+}
+
+/// Run the built-in self test under `options` (see `self_test`). The JSON
+/// report is in `ir_text` whether or not it passed; on failure `error_msg`
+/// names the first failed check.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_self_test_v2(options: *const CraneliftOptions, result: *mut CraneliftResult) -> i32 {
+    write_result(result, move || self_test_entry(RawOptions::Struct(options)))
+}
+
+/// By-value variant of `cranelift_self_test_v2`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_self_test(options: *const CraneliftOptions) -> CraneliftResult {
+    by_value(|result| cranelift_self_test_v2(options, result))
+}
+
+/// `cranelift_self_test_v2` with an options blob.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_self_test_kv(
+    options: *const u8,
+    options_len: usize,
+    result: *mut CraneliftResult,
+) -> i32 {
+    let options = RawOptions::Blob(options, options_len);
+    write_result(result, move || self_test_entry(options))
+}
+
+/// `cranelift_self_test_v2` with versioned options.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_self_test_ex(options: *const CraneliftOptionsV2, result: *mut CraneliftResult) -> i32 {
+    write_result(result, move || self_test_entry(RawOptions::Versioned(options)))
+}
+
+/// Free a CraneliftResult. Must be called for every result returned.
+/// Freeing the same result twice, a copy of it, or a zeroed struct is a
+/// no-op; afterwards the pointers are null and `magic` is `RESULT_FREED`.
+#[unsafe(no_mangle)]
+#[allow(clippy::not_unsafe_ptr_arg_deref)] // null is checked before the dereference
+pub extern "C" fn cranelift_free_result(result: *mut CraneliftResult) {
+    if result.is_null() {
+        return;
     }
-    if !r.ir_text.is_null() && r.ir_text_len > 0 {
-        unsafe {
-            let _ = CString::from_raw(r.ir_text as *mut i8);
-        }
+    let r = unsafe { &mut *result };
+    if r.magic != RESULT_LIVE {
+        return;
     }
-    if !r.error_msg.is_null() {
-        unsafe {
-            let _ = CString::from_raw(r.error_msg as *mut i8);
-        }
+
+    release_buffer(r.data, r.generation);
+    release_buffer(r.ir_text as *const u8, r.generation);
+    release_buffer(r.error_msg as *const u8, r.generation);
+    release_buffer(r.function_stats as *const u8, r.generation);
+
+    r.data = ptr::null();
+    r.data_len = 0;
+    r.ir_text = ptr::null();
+    r.ir_text_len = 0;
+    r.error_msg = ptr::null();
+    r.error_msg_len = 0;
+    r.function_stats = ptr::null();
+    r.num_function_stats = 0;
+    r.magic = RESULT_FREED;
+}
+
+/// Create a cancel token for `CraneliftOptions::cancel_token` (or the
+/// `cancel_token` option key). Free it with `cranelift_cancel_token_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_cancel_token_new() -> *mut CraneliftCancelToken {
+    cancel::new_handle()
+}
+
+/// Cancel every compile using `token`: each stops before its next function
+/// and returns a result with `cancelled` set. Compiles started with the
+/// token afterwards fail at once. Unknown or freed tokens are ignored.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_cancel(token: *const CraneliftCancelToken) {
+    if let Some(token) = cancel::lookup(token) {
+        token.cancel();
     }
+}
+
+/// Free a cancel token. Compiles already running with it are unaffected;
+/// freeing it twice is a no-op.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_cancel_token_free(token: *mut CraneliftCancelToken) {
+    cancel::free_handle(token);
+}
+
+/// Create a result context for `CraneliftOptions::result_context` (or the
+/// `result_context` option key). Free it with `cranelift_context_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_context_new() -> *mut CraneliftContext {
+    result_context::new_handle()
+}
+
+/// Free the buffers of every result `context` owns; the context stays
+/// usable. Unknown or freed contexts are ignored.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_context_free_results(context: *const CraneliftContext) {
+    if let Some(context) = result_context::lookup(context) {
+        result_context::with_live(context, || release_context_buffers(context));
+    }
+}
+
+/// Free a context together with the results it owns; freeing it twice is a
+/// no-op.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_context_free(context: *mut CraneliftContext) {
+    result_context::free_handle(context, release_context_buffers);
+}
+
+/// `cranelift_features` bits.
+pub const FEATURE_OBJECT: u32 = 1;
+pub const FEATURE_JIT: u32 = 2;
+pub const FEATURE_DEBUG_INFO: u32 = 4;
+pub const FEATURE_INTERPRETER: u32 = 8;
+pub const FEATURE_FFI_CHECKS: u32 = 16;
+pub const FEATURE_MEMORY_LIMIT: u32 = 32;
+
+/// Layout version of the structs shared with C++ (`CRANELIFT_ABI_VERSION`).
+/// Bumped whenever a field of one of them is added, removed or moved.
+pub const ABI_VERSION: u32 = 10;
+
+/// `ABI_VERSION` of this build, for the caller to compare with the header
+/// it was compiled against.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_abi_version() -> u32 {
+    ABI_VERSION
+}
 
-    // Zero out the struct so C++ doesn't double-free
-    unsafe {
-        ptr::write_bytes(result, 0, 1);
+/// Capabilities compiled into this build, as `FEATURE_*` bits following
+/// the cargo features. The bridge has no JIT, so that bit is never set.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_features() -> u32 {
+    let mut features = 0;
+    if cfg!(feature = "object") {
+        features |= FEATURE_OBJECT;
+    }
+    if cfg!(feature = "debug-info") {
+        features |= FEATURE_DEBUG_INFO;
+    }
+    if cfg!(feature = "interpreter") {
+        features |= FEATURE_INTERPRETER;
+    }
+    if ffi_check::COMPILED_IN {
+        features |= FEATURE_FFI_CHECKS;
+    }
+    if memory::COUNTING {
+        features |= FEATURE_MEMORY_LIMIT;
     }
+    features
 }
 
-/// Get the Cranelift version string.
+/// Versions of the bridge and of what it reads, from
+/// `cranelift_version_info`.
+#[repr(C)]
+pub struct CraneliftVersionInfo {
+    /// Version of the Cranelift crates built in, e.g. "0.128.3"
+    pub cranelift_version: *const i8,
+    /// The bridge's own semantic version
+    pub bridge_major: u32,
+    pub bridge_minor: u32,
+    pub bridge_patch: u32,
+    /// MIR binary format read: this major version, minor versions
+    /// `mir_minor_min..=mir_minor_max`
+    pub mir_major: u16,
+    pub mir_minor_min: u16,
+    pub mir_minor_max: u16,
+}
+
+// Points only at a string that lives as long as the process
+unsafe impl Send for CraneliftVersionInfo {}
+unsafe impl Sync for CraneliftVersionInfo {}
+
+/// Versions of the bridge, Cranelift and the MIR format (statically
+/// allocated), so the driver can refuse to serialize MIR the bridge cannot
+/// read before it tries.
 #[unsafe(no_mangle)]
-pub extern "C" fn cranelift_version() -> *const i8 {
-    // Return a static string
-    static VERSION: &[u8] = b"cranelift-0.128\0";
-    VERSION.as_ptr() as *const i8
+pub extern "C" fn cranelift_version_info() -> *const CraneliftVersionInfo {
+    static CRANELIFT_VERSION: OnceLock<CString> = OnceLock::new();
+    static INFO: OnceLock<CraneliftVersionInfo> = OnceLock::new();
+    let part = |s: &str| s.parse().unwrap_or(0);
+    INFO.get_or_init(|| CraneliftVersionInfo {
+        cranelift_version: CRANELIFT_VERSION
+            .get_or_init(|| CString::new(cranelift_object::VERSION).unwrap_or_default())
+            .as_ptr(),
+        bridge_major: part(env!("CARGO_PKG_VERSION_MAJOR")),
+        bridge_minor: part(env!("CARGO_PKG_VERSION_MINOR")),
+        bridge_patch: part(env!("CARGO_PKG_VERSION_PATCH")),
+        mir_major: mir_reader::MIR_VERSION_MAJOR,
+        mir_minor_min: mir_reader::MIR_VERSION_MINOR_MIN,
+        mir_minor_max: mir_reader::MIR_VERSION_MINOR,
+    })
+}
+
+/// Target triples this build compiles for, comma-separated with the host
+/// first (statically allocated). A triple is listed when the self test's
+/// ISA, MIR and object checks pass for it; the first call runs them.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_get_supported_targets() -> *const i8 {
+    static TARGETS: OnceLock<CString> = OnceLock::new();
+    TARGETS
+        .get_or_init(|| CString::new(self_test::supported_targets().join(",")).unwrap_or_default())
+        .as_ptr()
+}
+
+/// Triple the bridge compiles for when `target_triple` is empty (statically
+/// allocated): the target it was itself built for, environment included, so
+/// a driver built with another toolchain can pick a matching linker.
+#[unsafe(no_mangle)]
+pub extern "C" fn cranelift_native_triple() -> *const i8 {
+    static TRIPLE: OnceLock<CString> = OnceLock::new();
+    TRIPLE
+        .get_or_init(|| CString::new(target_lexicon::Triple::host().to_string()).unwrap_or_default())
+        .as_ptr()
+}
+
+/// FFI ownership protocol of `CraneliftResult`. These tests avoid Cranelift
+/// itself so they also run under Miri: `cargo +nightly miri test --lib ownership`.
+#[cfg(test)]
+mod ownership_tests {
+    use super::*;
+
+    fn owned(result: &CraneliftResult) -> usize {
+        let map = live_buffers();
+        let stats = result.function_stats as *const u8;
+        [result.data, result.ir_text as *const u8, result.error_msg as *const u8, stats]
+            .iter()
+            .filter(|p| map.get(&(**p as usize)).is_some_and(|b| b.generation == result.generation))
+            .count()
+    }
+
+    #[test]
+    fn results_own_their_buffers_until_freed() {
+        let mut data = CraneliftResult::success_with_data(vec![1, 2, 3]);
+        let mut ir = CraneliftResult::success_with_ir("function %f()".to_string());
+        let mut error = CraneliftResult::error(ERROR_INVALID_ARGUMENT, "bad\0input".to_string());
+        assert_eq!((owned(&data), owned(&ir), owned(&error)), (1, 1, 1));
+        assert_eq!(unsafe { slice::from_raw_parts(data.data, data.data_len) }, [1, 2, 3]);
+        assert_eq!(unsafe { *ir.ir_text.add(ir.ir_text_len) }, 0);
+        let msg = unsafe { std::ffi::CStr::from_ptr(error.error_msg) };
+        assert_eq!(msg.to_str().unwrap(), "bad\\0input");
+        assert_eq!(error.error_msg_len, msg.count_bytes());
+
+        for result in [&mut data, &mut ir, &mut error] {
+            cranelift_free_result(result);
+            assert_eq!(result.magic, RESULT_FREED);
+            assert!(result.data.is_null() && result.ir_text.is_null() && result.error_msg.is_null());
+            cranelift_free_result(result);
+        }
+        // Empty object data is null rather than a dangling pointer
+        let mut empty = CraneliftResult::success_with_data(Vec::new());
+        assert!(empty.data.is_null());
+        cranelift_free_result(&mut empty);
+    }
+
+    #[test]
+    fn copies_and_edited_lengths_cannot_double_free() {
+        let mut result = CraneliftResult::success_with_data(vec![7; 64]);
+        // C++ copies the struct by value and shrinks the length it reports
+        let mut copy = unsafe { ptr::read(&result) };
+        result.data_len = 3;
+        cranelift_free_result(&mut result);
+        assert_eq!(copy.magic, RESULT_LIVE);
+        assert_eq!(owned(&copy), 0);
+        cranelift_free_result(&mut copy);
+        assert_eq!(copy.magic, RESULT_FREED);
+    }
+
+    #[test]
+    fn stale_copies_cannot_free_a_reused_address() {
+        let mut result = CraneliftResult::success_with_data(vec![7; 64]);
+        let mut stale = unsafe { ptr::read(&result) };
+        cranelift_free_result(&mut result);
+        let mut next = CraneliftResult::success_with_data(vec![8; 64]);
+        // Whether or not the allocator reused the address, make it so
+        stale.data = next.data;
+        assert_eq!(owned(&stale), 0);
+        cranelift_free_result(&mut stale);
+        assert_eq!(stale.magic, RESULT_FREED);
+        assert_eq!(owned(&next), 1);
+        assert_eq!(unsafe { slice::from_raw_parts(next.data, next.data_len) }, [8; 64]);
+        cranelift_free_result(&mut next);
+        assert_eq!(owned(&next), 0);
+    }
+
+    #[test]
+    fn zeroed_and_foreign_results_are_ignored() {
+        cranelift_free_result(ptr::null_mut());
+        let mut zeroed: CraneliftResult = unsafe { std::mem::zeroed() };
+        cranelift_free_result(&mut zeroed);
+        assert_eq!(zeroed.magic, 0);
+
+        let foreign = [0u8; 4];
+        let mut forged = CraneliftResult::success_with_data(Vec::new());
+        forged.data = foreign.as_ptr();
+        forged.data_len = foreign.len();
+        cranelift_free_result(&mut forged);
+        assert_eq!(forged.magic, RESULT_FREED);
+        assert_eq!(foreign, [0; 4]);
+    }
+
+    #[test]
+    fn out_parameter_entry_points_fill_the_result() {
+        assert_eq!(cranelift_mir_stats_v2(ptr::null(), 0, ptr::null_mut()), 0);
+
+        let mut result: CraneliftResult = unsafe { std::mem::zeroed() };
+        assert_eq!(cranelift_mir_stats_v2(ptr::null(), 0, &mut result), 0);
+        assert_eq!(result.magic, RESULT_LIVE);
+        let msg = unsafe { std::ffi::CStr::from_ptr(result.error_msg) };
+        assert_eq!(msg.to_str().unwrap(), "null or empty MIR data");
+        cranelift_free_result(&mut result);
+
+        assert_eq!(cranelift_resolve_options_v2(ptr::null(), &mut result), 1);
+        assert!(result.success == 1 && !result.ir_text.is_null());
+        cranelift_free_result(&mut result);
+        // The by-value wrappers return the same results
+        let mut by_value = cranelift_mir_stats(ptr::null(), 0);
+        assert_eq!((by_value.success, by_value.magic), (0, RESULT_LIVE));
+        cranelift_free_result(&mut by_value);
+    }
+
+    #[test]
+    fn strings_are_utf8_with_explicit_lengths() {
+        let mut ir = CraneliftResult::success_with_ir("a\0b".to_string());
+        let bytes = unsafe { slice::from_raw_parts(ir.ir_text as *const u8, ir.ir_text_len + 1) };
+        assert_eq!(bytes, b"a\0b\0");
+        cranelift_free_result(&mut ir);
+
+        // Invalid UTF-8 in options is replaced, not dropped
+        let mut options = test_util::default_options();
+        options.profile = c"fa\xffst".as_ptr();
+        let mut result = cranelift_resolve_options(&options);
+        let msg = unsafe { slice::from_raw_parts(result.error_msg as *const u8, result.error_msg_len + 1) };
+        let (text, nul) = msg.split_at(result.error_msg_len);
+        assert_eq!(nul, [0]);
+        assert!(std::str::from_utf8(text).unwrap().contains("unknown profile 'fa\u{fffd}st'"));
+        cranelift_free_result(&mut result);
+    }
+
+    #[test]
+    fn contexts_free_the_results_they_own() {
+        let context = cranelift_context_new();
+        let mut options = test_util::default_options();
+        options.result_context = context;
+        let mut results: Vec<_> = (0..3).map(|_| cranelift_resolve_options(&options)).collect();
+        assert!(results.iter().all(|r| r.success == 1 && owned(r) == 1));
+        // Freeing one early still works, and the context skips it
+        cranelift_free_result(&mut results[0]);
+        let unowned = cranelift_resolve_options(&test_util::default_options());
+        cranelift_context_free_results(context);
+        assert!(results.iter().all(|r| owned(r) == 0));
+        assert_eq!(owned(&unowned), 1);
+
+        // Still usable until freed, then an error in the options
+        let mut later = cranelift_resolve_options(&options);
+        assert_eq!(owned(&later), 1);
+        cranelift_context_free(context);
+        assert_eq!(owned(&later), 0);
+        cranelift_context_free(context);
+        let mut stale = cranelift_resolve_options(&options);
+        assert_eq!((stale.success, stale.error_code), (0, ERROR_INVALID_ARGUMENT));
+        assert_eq!(owned(&stale), 1);
+        for result in [&mut later, &mut stale] {
+            cranelift_free_result(result);
+        }
+        let mut unowned = unowned;
+        cranelift_free_result(&mut unowned);
+    }
+}
+
+/// C API entry points that report on the bridge and check their callers.
+#[cfg(test)]
+mod tests {
+    use crate::mir_types::*;
+    use crate::test_util::*;
+
+    #[test]
+    fn ffi_misuse_is_reported() {
+        if !crate::ffi_check::enabled() {
+            return;
+        }
+        let m = module(vec![function(
+            "f",
+            Vec::new(),
+            prim(PrimitiveType::I32),
+            vec![block(0, vec![const_int(0, 1, 32)], Terminator::Return { value: Some(v(0)) })],
+        )]);
+        let bytes = crate::mir_writer::MirBinaryWriter::new().write_module(&m);
+        let cgu = |indices: *const usize, count: usize, options: &crate::CraneliftOptions| {
+            let mut result = unsafe { std::mem::zeroed() };
+            crate::cranelift_compile_mir_cgu_v2(bytes.as_ptr(), bytes.len(), indices, count, options, &mut result);
+            take(&mut result)
+        };
+
+        let options = default_options();
+        let out = cgu(std::ptr::null(), 2, &options);
+        assert_eq!(out.text, "FFI misuse: func_indices: null pointer with length 2");
+        let indices = [0usize; 2];
+        let misaligned = (indices.as_ptr() as usize + 1) as *const usize;
+        assert!(cgu(misaligned, 1, &options).text.contains("func_indices: pointer 0x"));
+        assert!(cgu(indices.as_ptr(), 1, &options).success);
+
+        let mut options = default_options();
+        options.optimization_level = 7;
+        assert_eq!(cgu(indices.as_ptr(), 1, &options).text, "FFI misuse: options.optimization_level: invalid value 7");
+        let mut options = default_options();
+        options.runtime_prefix = -1;
+        assert!(cgu(indices.as_ptr(), 1, &options).text.contains("options.runtime_prefix: invalid value -1"));
+        let mut options = default_options();
+        options.profile = 8 as *const i8;
+        assert!(cgu(indices.as_ptr(), 1, &options).text.contains("options.profile: pointer 0x8 is in the first page"));
+
+        // A misaligned result struct still receives the error
+        let mut storage = [0u8; std::mem::size_of::<crate::CraneliftResult>() + 16];
+        let offset = 8 - storage.as_ptr() as usize % 8 + 1;
+        let result = storage[offset..].as_mut_ptr() as *mut crate::CraneliftResult;
+        let options = default_options();
+        assert_eq!(crate::cranelift_compile_mir_v2(bytes.as_ptr(), bytes.len(), &options, result), 0);
+        let mut result = unsafe { result.read_unaligned() };
+        assert!(take(&mut result).text.starts_with("FFI misuse: result: pointer 0x"));
+    }
+
+    #[test]
+    fn version_info_matches_the_mir_the_reader_accepts() {
+        let info = unsafe { &*crate::cranelift_version_info() };
+        let cranelift = unsafe { std::ffi::CStr::from_ptr(info.cranelift_version) };
+        assert!(cranelift.to_str().unwrap().starts_with("0.128."), "{:?}", cranelift);
+        let bridge = format!("{}.{}.{}", info.bridge_major, info.bridge_minor, info.bridge_patch);
+        assert_eq!(bridge, env!("CARGO_PKG_VERSION"));
+
+        // No instructions, whose layout differs between minor versions
+        let m = module(vec![function(
+            "f",
+            Vec::new(),
+            prim(PrimitiveType::Unit),
+            vec![block(0, Vec::new(), Terminator::Return { value: None })],
+        )]);
+        let mut mir = crate::mir_writer::MirBinaryWriter::new().write_module(&m);
+        // Header: magic u32 | major u16 | minor u16
+        assert_eq!(u16::from_le_bytes([mir[4], mir[5]]), info.mir_major);
+        assert_eq!(u16::from_le_bytes([mir[6], mir[7]]), info.mir_minor_max);
+        let mut with_minor = |minor: u16| {
+            mir[6..8].copy_from_slice(&minor.to_le_bytes());
+            crate::mir_reader::MirBinaryReader::new(&mir).read_module().map(|_| ())
+        };
+        assert!(with_minor(info.mir_minor_min).is_ok());
+        let newer = with_minor(info.mir_minor_max + 1).unwrap_err();
+        assert!(newer.to_string().contains("outside the supported"), "{}", newer);
+    }
+
+    #[test]
+    fn native_triple_is_the_default_target() {
+        let native = unsafe { std::ffi::CStr::from_ptr(crate::cranelift_native_triple()) };
+        let native = native.to_str().unwrap();
+        assert!(!native.is_empty());
+        let resolved = resolve_options(&default_options());
+        assert!(resolved.success, "{}", resolved.text);
+        assert!(resolved.text.contains(&format!("\"target\":\"{}\"", native)), "{}", resolved.text);
+        let targets = unsafe { std::ffi::CStr::from_ptr(crate::cranelift_get_supported_targets()) };
+        assert!(targets.to_str().unwrap().starts_with(native));
+    }
 }
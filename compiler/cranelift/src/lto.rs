@@ -0,0 +1,449 @@
+//! Whole-module LTO over concatenated MIR
+//!
+//! The driver hands the bridge every module's MIR at link time. The modules are
+//! merged into a single `mir_types::Module` namespace (imports resolve to the
+//! definitions from other modules), unreachable functions are dropped, the
+//! survivors are internalized, and small leaf functions are inlined into their
+//! callers before the merged module is translated into one object.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::error::{BridgeError, BridgeResult};
+use crate::mir_types::*;
+
+/// Callees with more instructions than this are never inlined.
+const MAX_INLINE_CALLEE_INSTRUCTIONS: usize = 16;
+
+/// Merge several MIR modules into one namespace.
+///
+/// Struct/enum definitions are deduplicated by name. For functions, a
+/// definition (non-empty body) always wins over a declaration, and identical
+/// definitions (the same generic instance, say) are kept once. A non-public
+/// function defined differently elsewhere is first renamed within its own
+/// module (see `rename_private_duplicates`); two different public
+/// definitions of one name are an error.
+pub fn merge_modules(mut modules: Vec<Module>) -> BridgeResult<Module> {
+    rename_private_duplicates(&mut modules);
+    let mut iter = modules.into_iter();
+    let mut merged = iter
+        .next()
+        .ok_or_else(|| BridgeError::Translation("LTO requires at least one module".into()))?;
+
+    let mut struct_names: HashSet<String> = merged.structs.iter().map(|s| s.name.clone()).collect();
+    let mut enum_names: HashSet<String> = merged.enums.iter().map(|e| e.name.clone()).collect();
+    let mut const_names: HashSet<String> =
+        merged.constants.iter().map(|(n, _)| n.clone()).collect();
+    let mut func_index: HashMap<String, usize> = HashMap::new();
+    for (i, f) in merged.functions.iter().enumerate() {
+        func_index.entry(f.name.clone()).or_insert(i);
+    }
+
+    for module in iter {
+        for s in module.structs {
+            if struct_names.insert(s.name.clone()) {
+                merged.structs.push(s);
+            }
+        }
+        for e in module.enums {
+            if enum_names.insert(e.name.clone()) {
+                merged.enums.push(e);
+            }
+        }
+        for (name, value) in module.constants {
+            if const_names.insert(name.clone()) {
+                merged.constants.push((name, value));
+            }
+        }
+        for func in module.functions {
+            match func_index.get(&func.name) {
+                Some(&i) => {
+                    let existing = &merged.functions[i];
+                    if existing.blocks.is_empty() {
+                        // Replace a bare declaration with the real definition
+                        if !func.blocks.is_empty() {
+                            merged.functions[i] = func;
+                        }
+                    } else if !func.blocks.is_empty() && *existing != func {
+                        return Err(BridgeError::Translation(format!(
+                            "LTO: conflicting definitions of public function '{}' in module '{}'",
+                            func.name, module.name
+                        )));
+                    }
+                }
+                None => {
+                    func_index.insert(func.name.clone(), merged.functions.len());
+                    merged.functions.push(func);
+                }
+            }
+        }
+    }
+
+    Ok(merged)
+}
+
+/// Rename non-public functions that another module defines differently to
+/// `<name>.lto<module index>`, along with the calls to them in their module,
+/// so every module keeps its own private helpers after the merge.
+fn rename_private_duplicates(modules: &mut [Module]) {
+    let mut definitions: HashMap<&str, Vec<&Function>> = HashMap::new();
+    for func in modules.iter().flat_map(|m| &m.functions).filter(|f| !f.blocks.is_empty()) {
+        definitions.entry(&func.name).or_default().push(func);
+    }
+    let duplicated: HashSet<String> = definitions
+        .into_iter()
+        .filter(|(_, defs)| defs.iter().any(|d| *d != defs[0]))
+        .map(|(name, _)| name.to_string())
+        .collect();
+    if duplicated.is_empty() {
+        return;
+    }
+
+    for (index, module) in modules.iter_mut().enumerate() {
+        let renames: HashMap<String, String> = module
+            .functions
+            .iter()
+            .filter(|f| !f.is_public && !f.blocks.is_empty() && duplicated.contains(&f.name))
+            .map(|f| (f.name.clone(), format!("{}.lto{}", f.name, index)))
+            .collect();
+        if renames.is_empty() {
+            continue;
+        }
+        for func in &mut module.functions {
+            if let Some(new_name) = renames.get(&func.name) {
+                func.name = new_name.clone();
+            }
+            for inst in func.blocks.iter_mut().flat_map(|b| &mut b.instructions) {
+                // A method bound through its receiver type calls the renamed
+                // function by its new name instead
+                let bound = inst.inst.method_targets().iter().find_map(|t| renames.get(t)).cloned();
+                match &mut inst.inst {
+                    Instruction::Call { func_name, .. } | Instruction::ClosureInit { func_name, .. } => {
+                        if let Some(new_name) = renames.get(func_name) {
+                            *func_name = new_name.clone();
+                        }
+                    }
+                    Instruction::MethodCall { method_name, receiver_type, .. } => {
+                        if let Some(new_name) = bound.or_else(|| renames.get(method_name).cloned()) {
+                            *method_name = new_name;
+                            receiver_type.clear();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Drop functions not reachable from the roots and internalize the rest.
+///
+/// Roots are `main`/`tml_main` when present; a module set without an entry
+/// point (e.g. a library) keeps every public function as a root.
+pub fn prune_unreachable(module: &mut Module) {
+    let by_name: HashMap<&str, usize> = module
+        .functions
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (f.name.as_str(), i))
+        .collect();
+    let lookup = |name: &str| -> Option<usize> {
+        by_name
+            .get(name)
+            .or_else(|| name.strip_prefix("tml_").and_then(|n| by_name.get(n)))
+            .copied()
+    };
+
+    let is_entry = |f: &Function| f.name == "main" || f.name == "tml_main";
+    let has_entry = module.functions.iter().any(is_entry);
+    let mut worklist: Vec<usize> = module
+        .functions
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| if has_entry { is_entry(f) } else { f.is_public })
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut reachable = HashSet::new();
+    while let Some(i) = worklist.pop() {
+        if !reachable.insert(i) {
+            continue;
+        }
+        for block in &module.functions[i].blocks {
+            for inst in &block.instructions {
+                if let Some(callee) = inst.inst.referenced_function().and_then(lookup) {
+                    worklist.push(callee);
+                }
+                // The translator binds these directly (see `Instruction::method_targets`)
+                worklist.extend(inst.inst.method_targets().iter().find_map(|t| lookup(t)));
+            }
+        }
+    }
+
+    let mut index = 0;
+    module.functions.retain(|_| {
+        let keep = reachable.contains(&index);
+        index += 1;
+        keep
+    });
+
+    // Only the entry point needs to stay visible to the linker
+    if has_entry {
+        for func in &mut module.functions {
+            func.is_public = is_entry(func);
+        }
+    }
+}
+
+/// Whether `func` is a single-block leaf simple enough to splice into callers.
+fn is_inlinable(func: &Function) -> bool {
+    let [block] = func.blocks.as_slice() else {
+        return false;
+    };
+    if !matches!(block.terminator, Some(Terminator::Return { .. })) {
+        return false;
+    }
+    block.instructions.len() <= MAX_INLINE_CALLEE_INSTRUCTIONS
+        && block.instructions.iter().all(|inst| {
+            !matches!(
+                inst.inst,
+                Instruction::Phi { .. }
+                    | Instruction::Await { .. }
+                    | Instruction::ClosureInit { .. }
+            ) && inst.inst.referenced_function() != Some(func.name.as_str())
+        })
+}
+
+/// Inline calls to small single-block leaf functions (one round). Fails on
+/// a callee that uses a value its body does not define.
+pub fn inline_small_functions(module: &mut Module) -> BridgeResult<()> {
+    let callees: HashMap<String, Function> = module
+        .functions
+        .iter()
+        .filter(|f| is_inlinable(f))
+        .map(|f| (f.name.clone(), f.clone()))
+        .collect();
+    if callees.is_empty() {
+        return Ok(());
+    }
+    let lookup = |name: &str| {
+        callees
+            .get(name)
+            .or_else(|| name.strip_prefix("tml_").and_then(|n| callees.get(n)))
+    };
+
+    for caller in &mut module.functions {
+        // Maps a call result to the inlined return value
+        let mut substitutions: HashMap<ValueId, ValueId> = HashMap::new();
+        // Don't trust next_value_id blindly; fresh ids must not collide
+        let max_used = caller
+            .params
+            .iter()
+            .map(|p| p.value_id)
+            .chain(caller.blocks.iter().flat_map(|b| b.instructions.iter().map(|i| i.result)))
+            .filter(|&id| id != u32::MAX)
+            .max()
+            .map_or(0, |id| id + 1);
+        let mut next_value_id = caller.next_value_id.max(max_used);
+
+        for block in &mut caller.blocks {
+            let mut rewritten = Vec::with_capacity(block.instructions.len());
+            for inst in block.instructions.drain(..) {
+                let callee = match &inst.inst {
+                    Instruction::Call {
+                        func_name, args, ..
+                    } => lookup(func_name).filter(|c| {
+                        c.name != caller.name && c.params.len() == args.len()
+                    }),
+                    _ => None,
+                };
+                let (Some(callee), Instruction::Call { args, .. }) = (callee, &inst.inst) else {
+                    rewritten.push(inst);
+                    continue;
+                };
+
+                let mut remap: HashMap<ValueId, ValueId> = callee
+                    .params
+                    .iter()
+                    .zip(args)
+                    .map(|(p, a)| (p.value_id, a.id))
+                    .collect();
+                let undefined = |id: ValueId| {
+                    BridgeError::Translation(format!(
+                        "LTO: cannot inline '{}' into '{}': %{} is not defined in its body",
+                        callee.name, caller.name, id
+                    ))
+                };
+                let body = &callee.blocks[0];
+                for callee_inst in &body.instructions {
+                    let mut copy = callee_inst.clone();
+                    let mut unknown = None;
+                    copy.inst.for_each_operand_mut(|v| match remap.get(&v.id) {
+                        Some(&id) => v.id = id,
+                        None => unknown = Some(v.id),
+                    });
+                    if let Some(id) = unknown {
+                        return Err(undefined(id));
+                    }
+                    copy.result = next_value_id;
+                    remap.insert(callee_inst.result, next_value_id);
+                    next_value_id += 1;
+                    rewritten.push(copy);
+                }
+                if let Some(Terminator::Return { value: Some(ret) }) = &body.terminator {
+                    let &mapped = remap.get(&ret.id).ok_or_else(|| undefined(ret.id))?;
+                    // The return value may itself be an earlier inlined call result
+                    let mapped = substitutions.get(&mapped).copied().unwrap_or(mapped);
+                    substitutions.insert(inst.result, mapped);
+                }
+            }
+            block.instructions = rewritten;
+        }
+
+        caller.next_value_id = next_value_id;
+        if substitutions.is_empty() {
+            continue;
+        }
+        let mut substitute = |v: &mut Value| {
+            if let Some(&new_id) = substitutions.get(&v.id) {
+                v.id = new_id;
+            }
+        };
+        for block in &mut caller.blocks {
+            for inst in &mut block.instructions {
+                inst.inst.for_each_operand_mut(&mut substitute);
+            }
+            if let Some(term) = &mut block.terminator {
+                term.for_each_operand_mut(&mut substitute);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use PrimitiveType::I32;
+
+    fn call(result: u32, name: &str, args: Vec<Value>) -> InstructionData {
+        inst(result, Instruction::Call { func_name: name.into(), args, return_type: prim(I32) })
+    }
+
+    /// `name` returning `value`, or calling `callee` when given.
+    fn returning(name: &str, value: i64, callee: Option<&str>, is_public: bool) -> Function {
+        let body = match callee {
+            Some(callee) => vec![call(0, callee, Vec::new())],
+            None => vec![const_int(0, value, 32)],
+        };
+        let ret = block(0, body, Terminator::Return { value: Some(v(0)) });
+        Function { is_public, ..function(name, Vec::new(), prim(I32), vec![ret]) }
+    }
+
+    fn declaration(name: &str) -> Function {
+        function(name, Vec::new(), prim(I32), Vec::new())
+    }
+
+    fn names(module: &Module) -> Vec<&str> {
+        module.functions.iter().map(|f| f.name.as_str()).collect()
+    }
+
+    fn called(func: &Function) -> Vec<&str> {
+        let instructions = func.blocks.iter().flat_map(|b| &b.instructions);
+        instructions.filter_map(|i| i.inst.referenced_function()).collect()
+    }
+
+    #[test]
+    fn merge_resolves_declarations_and_keeps_identical_definitions_once() {
+        let a = module(vec![
+            returning("main", 0, Some("lib"), true),
+            declaration("lib"),
+            returning("inst", 7, None, true),
+        ]);
+        let b = module(vec![returning("lib", 1, None, true), returning("inst", 7, None, true)]);
+        let merged = merge_modules(vec![a, b]).unwrap();
+        assert_eq!(names(&merged), ["main", "lib", "inst"]);
+        assert!(!merged.functions[1].blocks.is_empty());
+    }
+
+    #[test]
+    fn merge_rejects_conflicting_public_definitions() {
+        let a = module(vec![returning("f", 1, None, true)]);
+        let b = module(vec![returning("f", 2, None, true)]);
+        let err = merge_modules(vec![a, b]).unwrap_err().to_string();
+        assert!(err.contains("conflicting definitions of public function 'f'"), "{}", err);
+    }
+
+    #[test]
+    fn merge_renames_private_duplicates_per_module() {
+        let a = module(vec![returning("a", 0, Some("helper"), true), returning("helper", 1, None, false)]);
+        let b = module(vec![returning("b", 0, Some("helper"), true), returning("helper", 2, None, false)]);
+        // A public definition keeps the name; the private one is renamed
+        let c = module(vec![returning("c", 0, Some("helper"), true), returning("helper", 3, None, true)]);
+        let merged = merge_modules(vec![a, b, c]).unwrap();
+        assert_eq!(names(&merged), ["a", "helper.lto0", "b", "helper.lto1", "c", "helper"]);
+        assert_eq!(called(&merged.functions[0]), ["helper.lto0"]);
+        assert_eq!(called(&merged.functions[2]), ["helper.lto1"]);
+        assert_eq!(called(&merged.functions[4]), ["helper"]);
+    }
+
+    #[test]
+    fn prune_keeps_what_the_entry_point_reaches_and_internalizes_it() {
+        let mut m = module(vec![
+            returning("main", 0, Some("used"), true),
+            returning("used", 1, None, true),
+            returning("unused", 2, None, true),
+        ]);
+        prune_unreachable(&mut m);
+        assert_eq!(names(&m), ["main", "used"]);
+        assert!(m.functions[0].is_public && !m.functions[1].is_public);
+
+        // Without an entry point, public functions are the roots
+        let mut lib = module(vec![
+            returning("api", 0, Some("tml_inner"), true),
+            returning("inner", 1, None, false),
+            returning("dead", 2, None, false),
+        ]);
+        prune_unreachable(&mut lib);
+        assert_eq!(names(&lib), ["api", "inner"]);
+        assert!(lib.functions[0].is_public);
+    }
+
+    #[test]
+    fn inlining_splices_the_callee_and_forwards_its_result() {
+        let add = block(
+            0,
+            vec![inst(2, Instruction::Binary { op: BinOp::Add, left: v(0), right: v(1) })],
+            Terminator::Return { value: Some(v(2)) },
+        );
+        let add = function("add", vec![(0, prim(I32)), (1, prim(I32))], prim(I32), vec![add]);
+        let main = block(
+            0,
+            vec![const_int(0, 2, 32), const_int(1, 3, 32), call(2, "add", vec![v(0), v(1)])],
+            Terminator::Return { value: Some(v(2)) },
+        );
+        let mut m = module(vec![function("main", Vec::new(), prim(I32), vec![main]), add]);
+        inline_small_functions(&mut m).unwrap();
+
+        let main = &m.functions[0].blocks[0];
+        assert!(called(&m.functions[0]).is_empty());
+        let spliced = main.instructions.last().unwrap();
+        assert_eq!(spliced.inst, Instruction::Binary { op: BinOp::Add, left: v(0), right: v(1) });
+        assert_eq!(main.terminator, Some(Terminator::Return { value: Some(v(spliced.result)) }));
+    }
+
+    #[test]
+    fn inlining_fails_on_a_value_the_callee_does_not_define() {
+        let bad = block(
+            0,
+            vec![inst(1, Instruction::Unary { op: UnaryOp::Neg, operand: v(9) })],
+            Terminator::Return { value: Some(v(1)) },
+        );
+        let mut m = module(vec![
+            returning("main", 0, Some("bad"), true),
+            function("bad", Vec::new(), prim(I32), vec![bad]),
+        ]);
+        let err = inline_small_functions(&mut m).unwrap_err().to_string();
+        assert!(err.contains("cannot inline 'bad' into 'main': %9"), "{}", err);
+    }
+}
@@ -0,0 +1,179 @@
+//! Memory Limits
+//!
+//! Pathological generated MIR can make a compile allocate until the OOM
+//! killer ends the whole compiler process, with no diagnostics. With the
+//! `max_memory_bytes` option, `CountingAlloc` charges every allocation made
+//! while the call reads and translates MIR to a per-call budget (worker
+//! threads of a parallel compile charge their caller's), and checkpoints in
+//! the MIR reader and before each function fail the call with
+//! `BridgeError::ResourceLimit` once the budget is exceeded.
+//!
+//! Counting needs `CountingAlloc` as the process's global allocator, which a
+//! library must not take over unasked: it is installed only with the
+//! `memory-limit` feature, and other builds reject a non-zero limit.
+//!
+//! The budget is checked between allocations rather than enforced inside
+//! them, since a refused allocation aborts the process: a single request
+//! larger than the memory left still ends it. The MIR reader never reserves
+//! more elements than the remaining input could encode, which keeps a bad
+//! element count from making such a request.
+
+#[cfg(feature = "memory-limit")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+
+use crate::error::{BridgeError, BridgeResult};
+
+/// Heap bytes a call may hold, and the bytes it holds.
+#[derive(Debug)]
+pub struct Budget {
+    limit: usize,
+    /// Bytes allocated minus bytes freed while the budget was current; frees
+    /// of older memory can make it negative
+    #[cfg_attr(not(feature = "memory-limit"), allow(dead_code))]
+    used: AtomicIsize,
+    peak: AtomicUsize,
+}
+
+impl Budget {
+    /// A budget of `limit` bytes; None for 0 (no limit).
+    pub fn new(limit: usize) -> Option<Self> {
+        (limit > 0).then(|| Self { limit, used: AtomicIsize::new(0), peak: AtomicUsize::new(0) })
+    }
+
+    #[cfg(feature = "memory-limit")]
+    fn charge(&self, bytes: isize) {
+        let used = self.used.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        if used > 0 {
+            self.peak.fetch_max(used as usize, Ordering::Relaxed);
+        }
+    }
+
+    fn exceeded(&self) -> bool {
+        self.peak.load(Ordering::Relaxed) > self.limit
+    }
+}
+
+thread_local! {
+    /// Budget of the call running on this thread. A raw pointer in a
+    /// const-initialized cell: the allocator reads it, so it must neither
+    /// allocate nor register a destructor.
+    static CURRENT: Cell<*const Budget> = const { Cell::new(std::ptr::null()) };
+}
+
+/// The budget current on a thread, to install on the workers it starts.
+#[derive(Debug, Clone, Copy)]
+pub struct Current(*const Budget);
+
+// Only dereferenced by threads joined while the budget is alive
+unsafe impl Send for Current {}
+unsafe impl Sync for Current {}
+
+/// The budget of the call running on this thread.
+pub fn current() -> Current {
+    Current(CURRENT.with(Cell::get))
+}
+
+/// Makes a budget current on this thread until dropped.
+pub struct Scope {
+    previous: *const Budget,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.previous));
+    }
+}
+
+/// Charge this thread's allocations to `budget` until the returned scope
+/// is dropped; the budget must outlive the scope.
+pub fn enter(budget: Option<&Budget>) -> Scope {
+    enter_current(Current(budget.map_or(std::ptr::null(), |b| b as *const Budget)))
+}
+
+/// `enter` for a budget taken from another thread with `current`.
+pub fn enter_current(budget: Current) -> Scope {
+    Scope { previous: CURRENT.with(|current| current.replace(budget.0)) }
+}
+
+/// Limit and peak use of the budget current on this thread, if it has
+/// been exceeded.
+fn over_budget() -> Option<(usize, usize)> {
+    let budget = CURRENT.with(Cell::get);
+    // Set by `enter`, whose caller keeps the budget alive while it is current
+    let budget = unsafe { budget.as_ref() }?;
+    budget.exceeded().then(|| (budget.limit, budget.peak.load(Ordering::Relaxed)))
+}
+
+/// Whether the budget current on this thread has been exceeded.
+pub fn exceeded() -> bool {
+    over_budget().is_some()
+}
+
+/// Fail once the budget current on this thread has been exceeded.
+pub fn check() -> BridgeResult<()> {
+    match over_budget() {
+        None => Ok(()),
+        Some((limit, peak)) => Err(BridgeError::ResourceLimit(format!(
+            "memory limit of {} bytes exceeded ({} bytes allocated at peak)",
+            limit, peak
+        ))),
+    }
+}
+
+/// Charge `bytes` to the budget current on this thread, if any. Threads
+/// being torn down have none.
+#[cfg(feature = "memory-limit")]
+fn charge(bytes: isize) {
+    let _ = CURRENT.try_with(|current| {
+        let budget = current.get();
+        if !budget.is_null() {
+            unsafe { (*budget).charge(bytes) };
+        }
+    });
+}
+
+/// The system allocator, counting towards the current budget.
+#[cfg(feature = "memory-limit")]
+pub struct CountingAlloc;
+
+#[cfg(feature = "memory-limit")]
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            charge(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            charge(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        charge(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new.is_null() {
+            charge(new_size as isize - layout.size() as isize);
+        }
+        new
+    }
+}
+
+/// Whether this build counts allocations, so `max_memory_bytes` can be
+/// honored.
+pub const COUNTING: bool = cfg!(feature = "memory-limit");
+
+#[cfg(feature = "memory-limit")]
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
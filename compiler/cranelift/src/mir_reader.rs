@@ -1,22 +1,30 @@
-/// MIR Binary Format Reader
-///
-/// Deserializes the TML MIR binary format produced by the C++ `MirBinaryWriter`.
-/// Format: little-endian, length-prefixed strings, tagged types/instructions.
+//! MIR Binary Format Reader
+//!
+//! Deserializes the TML MIR binary format produced by the C++ `MirBinaryWriter`.
+//! Format: little-endian, length-prefixed strings, tagged types/instructions.
 
 use crate::error::{BridgeError, BridgeResult};
+use crate::memory;
 use crate::mir_types::*;
 
-const MIR_MAGIC: u32 = 0x544D4952; // "TMIR"
-const MIR_VERSION_MAJOR: u16 = 1;
+pub(crate) const MIR_MAGIC: u32 = 0x544D4952; // "TMIR"
+pub(crate) const MIR_VERSION_MAJOR: u16 = 1;
+/// 1: MethodCall carries the receiver type name after the receiver
+/// 2: every instruction ends with its source line and column
+pub(crate) const MIR_VERSION_MINOR: u16 = 2;
+/// Oldest minor version read
+pub(crate) const MIR_VERSION_MINOR_MIN: u16 = 0;
 
 pub struct MirBinaryReader<'a> {
     data: &'a [u8],
     pos: usize,
+    /// Minor version of the data; fields added since are defaulted
+    minor: u16,
 }
 
 impl<'a> MirBinaryReader<'a> {
     pub fn new(data: &'a [u8]) -> Self {
-        Self { data, pos: 0 }
+        Self { data, pos: 0, minor: 0 }
     }
 
     pub fn read_module(&mut self) -> BridgeResult<Module> {
@@ -26,28 +34,29 @@ impl<'a> MirBinaryReader<'a> {
 
         // Structs
         let struct_count = self.read_u32()? as usize;
-        let mut structs = Vec::with_capacity(struct_count);
+        let mut structs = Vec::with_capacity(self.capacity(struct_count));
         for _ in 0..struct_count {
             structs.push(self.read_struct_def()?);
         }
 
         // Enums
         let enum_count = self.read_u32()? as usize;
-        let mut enums = Vec::with_capacity(enum_count);
+        let mut enums = Vec::with_capacity(self.capacity(enum_count));
         for _ in 0..enum_count {
             enums.push(self.read_enum_def()?);
         }
 
         // Functions
         let func_count = self.read_u32()? as usize;
-        let mut functions = Vec::with_capacity(func_count);
+        let mut functions = Vec::with_capacity(self.capacity(func_count));
         for _ in 0..func_count {
             functions.push(self.read_function()?);
+            memory::check()?;
         }
 
         // Constants
         let const_count = self.read_u32()? as usize;
-        let mut constants = Vec::with_capacity(const_count);
+        let mut constants = Vec::with_capacity(self.capacity(const_count));
         for _ in 0..const_count {
             let cname = self.read_string()?;
             let cval = self.read_constant_value()?;
@@ -72,16 +81,29 @@ impl<'a> MirBinaryReader<'a> {
             )));
         }
         let major = self.read_u16()?;
-        let _minor = self.read_u16()?;
+        self.minor = self.read_u16()?;
         if major != MIR_VERSION_MAJOR {
             return Err(BridgeError::MirDeserialize(format!(
                 "version mismatch: expected major {}, got {}",
                 MIR_VERSION_MAJOR, major
             )));
         }
+        if !(MIR_VERSION_MINOR_MIN..=MIR_VERSION_MINOR).contains(&self.minor) {
+            return Err(BridgeError::MirDeserialize(format!(
+                "version mismatch: minor {} is outside the supported {}..={}",
+                self.minor, MIR_VERSION_MINOR_MIN, MIR_VERSION_MINOR
+            )));
+        }
         Ok(())
     }
 
+    /// Capacity to reserve for `count` elements: never more than the bytes
+    /// left, as every element takes at least one, so a corrupt count fails
+    /// at EOF instead of reserving gigabytes.
+    fn capacity(&self, count: usize) -> usize {
+        count.min(self.data.len() - self.pos)
+    }
+
     // Primitive readers
     fn read_u8(&mut self) -> BridgeResult<u8> {
         if self.pos >= self.data.len() {
@@ -197,7 +219,7 @@ impl<'a> MirBinaryReader<'a> {
             4 => {
                 // Tuple
                 let count = self.read_u32()? as usize;
-                let mut elements = Vec::with_capacity(count);
+                let mut elements = Vec::with_capacity(self.capacity(count));
                 for _ in 0..count {
                     elements.push(self.read_type()?);
                 }
@@ -207,7 +229,7 @@ impl<'a> MirBinaryReader<'a> {
                 // Struct
                 let name = self.read_string()?;
                 let count = self.read_u32()? as usize;
-                let mut type_args = Vec::with_capacity(count);
+                let mut type_args = Vec::with_capacity(self.capacity(count));
                 for _ in 0..count {
                     type_args.push(self.read_type()?);
                 }
@@ -217,7 +239,7 @@ impl<'a> MirBinaryReader<'a> {
                 // Enum
                 let name = self.read_string()?;
                 let count = self.read_u32()? as usize;
-                let mut type_args = Vec::with_capacity(count);
+                let mut type_args = Vec::with_capacity(self.capacity(count));
                 for _ in 0..count {
                     type_args.push(self.read_type()?);
                 }
@@ -226,7 +248,7 @@ impl<'a> MirBinaryReader<'a> {
             7 => {
                 // Function
                 let param_count = self.read_u32()? as usize;
-                let mut params = Vec::with_capacity(param_count);
+                let mut params = Vec::with_capacity(self.capacity(param_count));
                 for _ in 0..param_count {
                     params.push(self.read_type()?);
                 }
@@ -278,6 +300,11 @@ impl<'a> MirBinaryReader<'a> {
                 // Unit
                 Ok(Constant::Unit)
             }
+            5 => {
+                // WideString
+                let value = self.read_string()?;
+                Ok(Constant::WideString(value))
+            }
             _ => Err(BridgeError::MirDeserialize(format!(
                 "unknown constant tag: {}",
                 tag
@@ -329,7 +356,7 @@ impl<'a> MirBinaryReader<'a> {
                 // Gep
                 let base = self.read_value()?;
                 let count = self.read_u32()? as usize;
-                let mut indices = Vec::with_capacity(count);
+                let mut indices = Vec::with_capacity(self.capacity(count));
                 for _ in 0..count {
                     indices.push(self.read_value()?);
                 }
@@ -339,7 +366,7 @@ impl<'a> MirBinaryReader<'a> {
                 // ExtractValue
                 let aggregate = self.read_value()?;
                 let count = self.read_u32()? as usize;
-                let mut indices = Vec::with_capacity(count);
+                let mut indices = Vec::with_capacity(self.capacity(count));
                 for _ in 0..count {
                     indices.push(self.read_u32()?);
                 }
@@ -350,7 +377,7 @@ impl<'a> MirBinaryReader<'a> {
                 let aggregate = self.read_value()?;
                 let value = self.read_value()?;
                 let count = self.read_u32()? as usize;
-                let mut indices = Vec::with_capacity(count);
+                let mut indices = Vec::with_capacity(self.capacity(count));
                 for _ in 0..count {
                     indices.push(self.read_u32()?);
                 }
@@ -364,7 +391,7 @@ impl<'a> MirBinaryReader<'a> {
                 // Call
                 let func_name = self.read_string()?;
                 let count = self.read_u32()? as usize;
-                let mut args = Vec::with_capacity(count);
+                let mut args = Vec::with_capacity(self.capacity(count));
                 for _ in 0..count {
                     args.push(self.read_value()?);
                 }
@@ -378,15 +405,17 @@ impl<'a> MirBinaryReader<'a> {
             9 => {
                 // MethodCall
                 let receiver = self.read_value()?;
+                let receiver_type = if self.minor >= 1 { self.read_string()? } else { String::new() };
                 let method_name = self.read_string()?;
                 let count = self.read_u32()? as usize;
-                let mut args = Vec::with_capacity(count);
+                let mut args = Vec::with_capacity(self.capacity(count));
                 for _ in 0..count {
                     args.push(self.read_value()?);
                 }
                 let return_type = self.read_type()?;
                 Instruction::MethodCall {
                     receiver,
+                    receiver_type,
                     method_name,
                     args,
                     return_type,
@@ -408,7 +437,7 @@ impl<'a> MirBinaryReader<'a> {
             11 => {
                 // Phi
                 let count = self.read_u32()? as usize;
-                let mut incoming = Vec::with_capacity(count);
+                let mut incoming = Vec::with_capacity(self.capacity(count));
                 for _ in 0..count {
                     let val = self.read_value()?;
                     let block = self.read_u32()?;
@@ -436,7 +465,7 @@ impl<'a> MirBinaryReader<'a> {
                 // StructInit
                 let struct_name = self.read_string()?;
                 let count = self.read_u32()? as usize;
-                let mut fields = Vec::with_capacity(count);
+                let mut fields = Vec::with_capacity(self.capacity(count));
                 for _ in 0..count {
                     fields.push(self.read_value()?);
                 }
@@ -450,7 +479,7 @@ impl<'a> MirBinaryReader<'a> {
                 let enum_name = self.read_string()?;
                 let variant_name = self.read_string()?;
                 let count = self.read_u32()? as usize;
-                let mut payload = Vec::with_capacity(count);
+                let mut payload = Vec::with_capacity(self.capacity(count));
                 for _ in 0..count {
                     payload.push(self.read_value()?);
                 }
@@ -463,7 +492,7 @@ impl<'a> MirBinaryReader<'a> {
             16 => {
                 // TupleInit
                 let count = self.read_u32()? as usize;
-                let mut elements = Vec::with_capacity(count);
+                let mut elements = Vec::with_capacity(self.capacity(count));
                 for _ in 0..count {
                     elements.push(self.read_value()?);
                 }
@@ -473,7 +502,7 @@ impl<'a> MirBinaryReader<'a> {
                 // ArrayInit
                 let element_type = self.read_type()?;
                 let count = self.read_u32()? as usize;
-                let mut elements = Vec::with_capacity(count);
+                let mut elements = Vec::with_capacity(self.capacity(count));
                 for _ in 0..count {
                     elements.push(self.read_value()?);
                 }
@@ -482,6 +511,17 @@ impl<'a> MirBinaryReader<'a> {
                     elements,
                 }
             }
+            20 => {
+                // ArrayFill
+                let element_type = self.read_type()?;
+                let value = self.read_value()?;
+                let count = self.read_u64()?;
+                Instruction::ArrayFill {
+                    element_type,
+                    value,
+                    count,
+                }
+            }
             18 => {
                 // Await
                 let poll_value = self.read_value()?;
@@ -499,13 +539,13 @@ impl<'a> MirBinaryReader<'a> {
                 // ClosureInit
                 let func_name = self.read_string()?;
                 let cap_count = self.read_u32()? as usize;
-                let mut captures = Vec::with_capacity(cap_count);
+                let mut captures = Vec::with_capacity(self.capacity(cap_count));
                 for _ in 0..cap_count {
                     let cname = self.read_string()?;
                     let cval = self.read_value()?;
                     captures.push((cname, cval));
                 }
-                let mut cap_types = Vec::with_capacity(cap_count);
+                let mut cap_types = Vec::with_capacity(self.capacity(cap_count));
                 for _ in 0..cap_count {
                     let tname = self.read_string()?;
                     let ttype = self.read_type()?;
@@ -529,7 +569,12 @@ impl<'a> MirBinaryReader<'a> {
             }
         };
 
-        Ok(InstructionData { result, inst })
+        let loc = if self.minor >= 2 {
+            SourceLoc { line: self.read_u32()?, column: self.read_u32()? }
+        } else {
+            SourceLoc::default()
+        };
+        Ok(InstructionData { result, inst, loc })
     }
 
     // Terminator reader
@@ -566,7 +611,7 @@ impl<'a> MirBinaryReader<'a> {
                 // Switch
                 let discriminant = self.read_value()?;
                 let count = self.read_u32()? as usize;
-                let mut cases = Vec::with_capacity(count);
+                let mut cases = Vec::with_capacity(self.capacity(count));
                 for _ in 0..count {
                     let val = self.read_i64()?;
                     let block = self.read_u32()?;
@@ -596,13 +641,13 @@ impl<'a> MirBinaryReader<'a> {
         let name = self.read_string()?;
 
         let pred_count = self.read_u32()? as usize;
-        let mut predecessors = Vec::with_capacity(pred_count);
+        let mut predecessors = Vec::with_capacity(self.capacity(pred_count));
         for _ in 0..pred_count {
             predecessors.push(self.read_u32()?);
         }
 
         let inst_count = self.read_u32()? as usize;
-        let mut instructions = Vec::with_capacity(inst_count);
+        let mut instructions = Vec::with_capacity(self.capacity(inst_count));
         for _ in 0..inst_count {
             instructions.push(self.read_instruction()?);
         }
@@ -629,7 +674,7 @@ impl<'a> MirBinaryReader<'a> {
         let is_public = self.read_u8()? != 0;
 
         let param_count = self.read_u32()? as usize;
-        let mut params = Vec::with_capacity(param_count);
+        let mut params = Vec::with_capacity(self.capacity(param_count));
         for _ in 0..param_count {
             let pname = self.read_string()?;
             let pty = self.read_type()?;
@@ -644,7 +689,7 @@ impl<'a> MirBinaryReader<'a> {
         let return_type = self.read_type()?;
 
         let block_count = self.read_u32()? as usize;
-        let mut blocks = Vec::with_capacity(block_count);
+        let mut blocks = Vec::with_capacity(self.capacity(block_count));
         for _ in 0..block_count {
             blocks.push(self.read_block()?);
         }
@@ -666,12 +711,12 @@ impl<'a> MirBinaryReader<'a> {
     fn read_struct_def(&mut self) -> BridgeResult<StructDef> {
         let name = self.read_string()?;
         let tp_count = self.read_u32()? as usize;
-        let mut type_params = Vec::with_capacity(tp_count);
+        let mut type_params = Vec::with_capacity(self.capacity(tp_count));
         for _ in 0..tp_count {
             type_params.push(self.read_string()?);
         }
         let field_count = self.read_u32()? as usize;
-        let mut fields = Vec::with_capacity(field_count);
+        let mut fields = Vec::with_capacity(self.capacity(field_count));
         for _ in 0..field_count {
             let fname = self.read_string()?;
             let ftype = self.read_type()?;
@@ -690,16 +735,16 @@ impl<'a> MirBinaryReader<'a> {
     fn read_enum_def(&mut self) -> BridgeResult<EnumDef> {
         let name = self.read_string()?;
         let tp_count = self.read_u32()? as usize;
-        let mut type_params = Vec::with_capacity(tp_count);
+        let mut type_params = Vec::with_capacity(self.capacity(tp_count));
         for _ in 0..tp_count {
             type_params.push(self.read_string()?);
         }
         let var_count = self.read_u32()? as usize;
-        let mut variants = Vec::with_capacity(var_count);
+        let mut variants = Vec::with_capacity(self.capacity(var_count));
         for _ in 0..var_count {
             let vname = self.read_string()?;
             let pt_count = self.read_u32()? as usize;
-            let mut payload_types = Vec::with_capacity(pt_count);
+            let mut payload_types = Vec::with_capacity(self.capacity(pt_count));
             for _ in 0..pt_count {
                 payload_types.push(self.read_type()?);
             }
@@ -1,5 +1,7 @@
-/// MIR data model — mirrors the C++ `mir::Module` hierarchy.
-/// Used as the deserialization target for the binary MIR format.
+//! MIR data model — mirrors the C++ `mir::Module` hierarchy.
+//! Used as the deserialization target for the binary MIR format.
+
+use std::fmt;
 
 // Primitive types (matches C++ PrimitiveType enum values exactly)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -23,6 +25,7 @@ pub enum PrimitiveType {
     Str = 15,
 }
 
+#[allow(dead_code)] // mirrors the C++ helpers; not all are used by the translator yet
 impl PrimitiveType {
     pub fn from_u8(v: u8) -> Option<Self> {
         match v {
@@ -57,6 +60,14 @@ impl PrimitiveType {
         matches!(self, Self::F32 | Self::F64)
     }
 
+    pub fn is_integer(self) -> bool {
+        self.is_signed()
+            || matches!(
+                self,
+                Self::U8 | Self::U16 | Self::U32 | Self::U64 | Self::U128
+            )
+    }
+
     pub fn bit_width(self) -> u32 {
         match self {
             Self::Unit => 0,
@@ -74,7 +85,7 @@ impl PrimitiveType {
 }
 
 // Type system
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum MirType {
     Primitive(PrimitiveType),
     Pointer {
@@ -105,6 +116,7 @@ pub enum MirType {
     },
 }
 
+#[allow(dead_code)] // mirrors the C++ helpers; not all are used by the translator yet
 impl MirType {
     pub fn is_unit(&self) -> bool {
         matches!(self, MirType::Primitive(PrimitiveType::Unit))
@@ -127,9 +139,52 @@ impl MirType {
     }
 }
 
+/// TML type notation (`I32`, `*mut Point`, `List[I64]`, `func(I32) -> Bool`),
+/// as reported by `cranelift_list_functions`.
+impl fmt::Display for MirType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list(f: &mut fmt::Formatter<'_>, types: &[MirType]) -> fmt::Result {
+            for (i, t) in types.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{}", t)?;
+            }
+            Ok(())
+        }
+        match self {
+            MirType::Primitive(p) => write!(f, "{:?}", p),
+            MirType::Pointer { is_mut, pointee } => {
+                write!(f, "*{}{}", if *is_mut { "mut " } else { "" }, pointee)
+            }
+            MirType::Array { size, element } => write!(f, "[{}; {}]", element, size),
+            MirType::Slice { element } => write!(f, "[{}]", element),
+            MirType::Tuple { elements } => {
+                f.write_str("(")?;
+                list(f, elements)?;
+                f.write_str(")")
+            }
+            MirType::Struct { name, type_args } | MirType::Enum { name, type_args } => {
+                f.write_str(name)?;
+                if !type_args.is_empty() {
+                    f.write_str("[")?;
+                    list(f, type_args)?;
+                    f.write_str("]")?;
+                }
+                Ok(())
+            }
+            MirType::Function { params, return_type } => {
+                f.write_str("func(")?;
+                list(f, params)?;
+                write!(f, ") -> {}", return_type)
+            }
+        }
+    }
+}
+
 pub type ValueId = u32;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Value {
     pub id: ValueId,
 }
@@ -156,6 +211,25 @@ pub enum BinOp {
     BitXor = 15,
     Shl = 16,
     Shr = 17,
+    // Float predicates beyond the C++ BinOp set. Eq/Lt/Le/Gt/Ge are ordered
+    // (false on NaN) and Ne is unordered (true on NaN); these select the
+    // other half. On integers they reduce to the plain comparison.
+    /// Neither operand is NaN
+    Ord = 18,
+    /// Either operand is NaN
+    Uno = 19,
+    /// Ordered and not equal
+    One = 20,
+    /// Unordered or equal
+    Ueq = 21,
+    /// Unordered or less than
+    Ult = 22,
+    /// Unordered or less than or equal
+    Ule = 23,
+    /// Unordered or greater than
+    Ugt = 24,
+    /// Unordered or greater than or equal
+    Uge = 25,
 }
 
 impl BinOp {
@@ -179,6 +253,14 @@ impl BinOp {
             15 => Some(Self::BitXor),
             16 => Some(Self::Shl),
             17 => Some(Self::Shr),
+            18 => Some(Self::Ord),
+            19 => Some(Self::Uno),
+            20 => Some(Self::One),
+            21 => Some(Self::Ueq),
+            22 => Some(Self::Ult),
+            23 => Some(Self::Ule),
+            24 => Some(Self::Ugt),
+            25 => Some(Self::Uge),
             _ => None,
         }
     }
@@ -186,7 +268,20 @@ impl BinOp {
     pub fn is_comparison(self) -> bool {
         matches!(
             self,
-            Self::Eq | Self::Ne | Self::Lt | Self::Le | Self::Gt | Self::Ge
+            Self::Eq
+                | Self::Ne
+                | Self::Lt
+                | Self::Le
+                | Self::Gt
+                | Self::Ge
+                | Self::Ord
+                | Self::Uno
+                | Self::One
+                | Self::Ueq
+                | Self::Ult
+                | Self::Ule
+                | Self::Ugt
+                | Self::Uge
         )
     }
 }
@@ -225,6 +320,9 @@ pub enum CastKind {
     UIToFP = 9,
     PtrToInt = 10,
     IntToPtr = 11,
+    /// `x != 0` (or `x != 0.0`), as opposed to Trunc's low bit. Not in the
+    /// C++ CastKind set yet; emitted for explicit `as Bool` once it is.
+    BoolCast = 12,
 }
 
 impl CastKind {
@@ -242,13 +340,14 @@ impl CastKind {
             9 => Some(Self::UIToFP),
             10 => Some(Self::PtrToInt),
             11 => Some(Self::IntToPtr),
+            12 => Some(Self::BoolCast),
             _ => None,
         }
     }
 }
 
 // Constants
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Constant {
     Int {
         value: i64,
@@ -262,10 +361,12 @@ pub enum Constant {
     Bool(bool),
     String(String),
     Unit,
+    /// Emitted as NUL-terminated UTF-16LE for Windows W-API FFI
+    WideString(String),
 }
 
 // Instructions
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
     Binary {
         op: BinOp,
@@ -307,6 +408,9 @@ pub enum Instruction {
     },
     MethodCall {
         receiver: Value,
+        /// Concrete type name of the receiver (empty = unknown, as in MIR
+        /// written before minor version 1)
+        receiver_type: String,
         method_name: String,
         args: Vec<Value>,
         return_type: MirType,
@@ -341,6 +445,12 @@ pub enum Instruction {
         element_type: MirType,
         elements: Vec<Value>,
     },
+    /// `[value; count]` without materializing one operand per element
+    ArrayFill {
+        element_type: MirType,
+        value: Value,
+        count: u64,
+    },
     Await {
         poll_value: Value,
         poll_type: MirType,
@@ -356,14 +466,193 @@ pub enum Instruction {
     },
 }
 
-#[derive(Debug, Clone)]
+impl Instruction {
+    /// Visit every value operand of this instruction.
+    pub fn for_each_operand(&self, mut f: impl FnMut(&Value)) {
+        match self {
+            Instruction::Binary { left, right, .. } => {
+                f(left);
+                f(right);
+            }
+            Instruction::Unary { operand, .. } | Instruction::Cast { operand, .. } => f(operand),
+            Instruction::Load { ptr } => f(ptr),
+            Instruction::Store { ptr, value } => {
+                f(ptr);
+                f(value);
+            }
+            Instruction::Alloca { .. } | Instruction::Constant(_) => {}
+            Instruction::Gep { base, indices } => {
+                f(base);
+                indices.iter().for_each(f);
+            }
+            Instruction::ExtractValue { aggregate, .. } => f(aggregate),
+            Instruction::InsertValue {
+                aggregate, value, ..
+            } => {
+                f(aggregate);
+                f(value);
+            }
+            Instruction::Call { args, .. } => args.iter().for_each(f),
+            Instruction::MethodCall { receiver, args, .. } => {
+                f(receiver);
+                args.iter().for_each(f);
+            }
+            Instruction::Phi { incoming } => incoming.iter().for_each(|(v, _)| f(v)),
+            Instruction::Select {
+                condition,
+                true_val,
+                false_val,
+            } => {
+                f(condition);
+                f(true_val);
+                f(false_val);
+            }
+            Instruction::StructInit { fields, .. } => fields.iter().for_each(f),
+            Instruction::EnumInit { payload, .. } => payload.iter().for_each(f),
+            Instruction::TupleInit { elements } | Instruction::ArrayInit { elements, .. } => {
+                elements.iter().for_each(f)
+            }
+            Instruction::ArrayFill { value, .. } => f(value),
+            Instruction::Await { poll_value, .. } => f(poll_value),
+            Instruction::ClosureInit { captures, .. } => captures.iter().for_each(|(_, v)| f(v)),
+        }
+    }
+
+    /// Visit every value operand of this instruction mutably.
+    pub fn for_each_operand_mut(&mut self, mut f: impl FnMut(&mut Value)) {
+        match self {
+            Instruction::Binary { left, right, .. } => {
+                f(left);
+                f(right);
+            }
+            Instruction::Unary { operand, .. } | Instruction::Cast { operand, .. } => f(operand),
+            Instruction::Load { ptr } => f(ptr),
+            Instruction::Store { ptr, value } => {
+                f(ptr);
+                f(value);
+            }
+            Instruction::Alloca { .. } | Instruction::Constant(_) => {}
+            Instruction::Gep { base, indices } => {
+                f(base);
+                indices.iter_mut().for_each(f);
+            }
+            Instruction::ExtractValue { aggregate, .. } => f(aggregate),
+            Instruction::InsertValue {
+                aggregate, value, ..
+            } => {
+                f(aggregate);
+                f(value);
+            }
+            Instruction::Call { args, .. } => args.iter_mut().for_each(f),
+            Instruction::MethodCall { receiver, args, .. } => {
+                f(receiver);
+                args.iter_mut().for_each(f);
+            }
+            Instruction::Phi { incoming } => incoming.iter_mut().for_each(|(v, _)| f(v)),
+            Instruction::Select {
+                condition,
+                true_val,
+                false_val,
+            } => {
+                f(condition);
+                f(true_val);
+                f(false_val);
+            }
+            Instruction::StructInit { fields, .. } => fields.iter_mut().for_each(f),
+            Instruction::EnumInit { payload, .. } => payload.iter_mut().for_each(f),
+            Instruction::TupleInit { elements } | Instruction::ArrayInit { elements, .. } => {
+                elements.iter_mut().for_each(f)
+            }
+            Instruction::ArrayFill { value, .. } => f(value),
+            Instruction::Await { poll_value, .. } => f(poll_value),
+            Instruction::ClosureInit { captures, .. } => {
+                captures.iter_mut().for_each(|(_, v)| f(v))
+            }
+        }
+    }
+
+    /// Name of the function this instruction references directly, if any.
+    pub fn referenced_function(&self) -> Option<&str> {
+        match self {
+            Instruction::Call { func_name, .. } | Instruction::ClosureInit { func_name, .. } => {
+                Some(func_name)
+            }
+            Instruction::MethodCall { method_name, .. } => Some(method_name),
+            _ => None,
+        }
+    }
+
+    /// Functions a `MethodCall` with a known receiver type can be bound to
+    /// directly, in lookup order: `Type__method` (class methods),
+    /// `Type_method` (the LLVM backend's symbol scheme) and `Type::method`
+    /// (drop glue). Empty for other instructions, an unknown receiver type
+    /// or an already qualified method name.
+    pub fn method_targets(&self) -> Vec<String> {
+        match self {
+            Instruction::MethodCall { receiver_type, method_name, .. }
+                if !receiver_type.is_empty() && !method_name.contains("::") =>
+            {
+                ["__", "_", "::"]
+                    .iter()
+                    .map(|sep| format!("{}{}{}", receiver_type, sep, method_name))
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Instruction kind name, matching the C++ `InstTag` enumerators.
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Instruction::Binary { .. } => "Binary",
+            Instruction::Unary { .. } => "Unary",
+            Instruction::Load { .. } => "Load",
+            Instruction::Store { .. } => "Store",
+            Instruction::Alloca { .. } => "Alloca",
+            Instruction::Gep { .. } => "Gep",
+            Instruction::ExtractValue { .. } => "ExtractValue",
+            Instruction::InsertValue { .. } => "InsertValue",
+            Instruction::Call { .. } => "Call",
+            Instruction::MethodCall { .. } => "MethodCall",
+            Instruction::Cast { .. } => "Cast",
+            Instruction::Phi { .. } => "Phi",
+            Instruction::Constant(_) => "Constant",
+            Instruction::Select { .. } => "Select",
+            Instruction::StructInit { .. } => "StructInit",
+            Instruction::EnumInit { .. } => "EnumInit",
+            Instruction::TupleInit { .. } => "TupleInit",
+            Instruction::ArrayInit { .. } => "ArrayInit",
+            Instruction::ArrayFill { .. } => "ArrayFill",
+            Instruction::Await { .. } => "Await",
+            Instruction::ClosureInit { .. } => "ClosureInit",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct InstructionData {
     pub result: ValueId,
     pub inst: Instruction,
+    /// Where in the source the instruction comes from
+    pub loc: SourceLoc,
+}
+
+/// A position in the module's source file, 1-based; line 0 means unknown
+/// (MIR written before minor version 2, or compiler-generated code).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceLoc {
+    pub line: u32,
+    pub column: u32,
+}
+
+impl SourceLoc {
+    pub fn is_known(self) -> bool {
+        self.line != 0
+    }
 }
 
 // Terminators
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Terminator {
     Return { value: Option<Value> },
     Branch { target: u32 },
@@ -372,8 +661,34 @@ pub enum Terminator {
     Unreachable,
 }
 
+impl Terminator {
+    /// Visit every value operand of this terminator.
+    pub fn for_each_operand(&self, mut f: impl FnMut(&Value)) {
+        match self {
+            Terminator::Return { value: Some(v) } => f(v),
+            Terminator::CondBranch { condition, .. } => f(condition),
+            Terminator::Switch { discriminant, .. } => f(discriminant),
+            Terminator::Return { value: None }
+            | Terminator::Branch { .. }
+            | Terminator::Unreachable => {}
+        }
+    }
+
+    /// Visit every value operand of this terminator mutably.
+    pub fn for_each_operand_mut(&mut self, mut f: impl FnMut(&mut Value)) {
+        match self {
+            Terminator::Return { value: Some(v) } => f(v),
+            Terminator::CondBranch { condition, .. } => f(condition),
+            Terminator::Switch { discriminant, .. } => f(discriminant),
+            Terminator::Return { value: None }
+            | Terminator::Branch { .. }
+            | Terminator::Unreachable => {}
+        }
+    }
+}
+
 // Basic block
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BasicBlock {
     pub id: u32,
     pub name: String,
@@ -383,14 +698,14 @@ pub struct BasicBlock {
 }
 
 // Function
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FunctionParam {
     pub name: String,
     pub ty: MirType,
     pub value_id: ValueId,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Function {
     pub name: String,
     pub is_public: bool,
@@ -0,0 +1,496 @@
+//! MIR Binary Format Writer
+//!
+//! Serializes a `mir_types::Module` back into the TML MIR binary format.
+//! Mirrors `MirBinaryReader` exactly, so `read(write(m))` round-trips.
+
+use crate::mir_reader::{MIR_MAGIC, MIR_VERSION_MAJOR, MIR_VERSION_MINOR};
+use crate::mir_types::*;
+
+#[derive(Default)]
+pub struct MirBinaryWriter {
+    out: Vec<u8>,
+}
+
+impl MirBinaryWriter {
+    pub fn new() -> Self {
+        Self { out: Vec::new() }
+    }
+
+    /// Serialize a full module (header included) and return the bytes.
+    pub fn write_module(mut self, module: &Module) -> Vec<u8> {
+        self.write_u32(MIR_MAGIC);
+        self.write_u16(MIR_VERSION_MAJOR);
+        self.write_u16(MIR_VERSION_MINOR);
+
+        self.write_string(&module.name);
+
+        self.write_u32(module.structs.len() as u32);
+        for s in &module.structs {
+            self.write_struct_def(s);
+        }
+
+        self.write_u32(module.enums.len() as u32);
+        for e in &module.enums {
+            self.write_enum_def(e);
+        }
+
+        self.write_u32(module.functions.len() as u32);
+        for func in &module.functions {
+            self.write_function(func);
+        }
+
+        self.write_u32(module.constants.len() as u32);
+        for (name, value) in &module.constants {
+            self.write_string(name);
+            self.write_constant_value(value);
+        }
+
+        self.out
+    }
+
+    // Primitive writers
+    fn write_u8(&mut self, v: u8) {
+        self.out.push(v);
+    }
+
+    fn write_u16(&mut self, v: u16) {
+        self.out.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, v: u32) {
+        self.out.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, v: u64) {
+        self.out.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn write_i64(&mut self, v: i64) {
+        self.out.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn write_f64(&mut self, v: f64) {
+        self.out.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn write_bool(&mut self, v: bool) {
+        self.write_u8(if v { 1 } else { 0 });
+    }
+
+    fn write_string(&mut self, s: &str) {
+        self.write_u32(s.len() as u32);
+        self.out.extend_from_slice(s.as_bytes());
+    }
+
+    fn write_value(&mut self, v: &Value) {
+        self.write_u32(v.id);
+    }
+
+    fn write_values(&mut self, values: &[Value]) {
+        self.write_u32(values.len() as u32);
+        for v in values {
+            self.write_value(v);
+        }
+    }
+
+    // Type writer
+    fn write_type(&mut self, ty: &MirType) {
+        match ty {
+            MirType::Primitive(prim) => {
+                self.write_u8(0);
+                self.write_u8(*prim as u8);
+            }
+            MirType::Pointer { is_mut, pointee } => {
+                self.write_u8(1);
+                self.write_bool(*is_mut);
+                self.write_type(pointee);
+            }
+            MirType::Array { size, element } => {
+                self.write_u8(2);
+                self.write_u64(*size);
+                self.write_type(element);
+            }
+            MirType::Slice { element } => {
+                self.write_u8(3);
+                self.write_type(element);
+            }
+            MirType::Tuple { elements } => {
+                self.write_u8(4);
+                self.write_u32(elements.len() as u32);
+                for elem in elements {
+                    self.write_type(elem);
+                }
+            }
+            MirType::Struct { name, type_args } => {
+                self.write_u8(5);
+                self.write_string(name);
+                self.write_u32(type_args.len() as u32);
+                for arg in type_args {
+                    self.write_type(arg);
+                }
+            }
+            MirType::Enum { name, type_args } => {
+                self.write_u8(6);
+                self.write_string(name);
+                self.write_u32(type_args.len() as u32);
+                for arg in type_args {
+                    self.write_type(arg);
+                }
+            }
+            MirType::Function {
+                params,
+                return_type,
+            } => {
+                self.write_u8(7);
+                self.write_u32(params.len() as u32);
+                for p in params {
+                    self.write_type(p);
+                }
+                self.write_type(return_type);
+            }
+        }
+    }
+
+    // Constant value writer
+    fn write_constant_value(&mut self, c: &Constant) {
+        match c {
+            Constant::Int {
+                value,
+                bit_width,
+                is_signed,
+            } => {
+                self.write_u8(0);
+                self.write_i64(*value);
+                self.write_u8(*bit_width);
+                self.write_bool(*is_signed);
+            }
+            Constant::Float { value, is_f64 } => {
+                self.write_u8(1);
+                self.write_f64(*value);
+                self.write_bool(*is_f64);
+            }
+            Constant::Bool(b) => {
+                self.write_u8(2);
+                self.write_bool(*b);
+            }
+            Constant::String(s) => {
+                self.write_u8(3);
+                self.write_string(s);
+            }
+            Constant::Unit => {
+                self.write_u8(4);
+            }
+            Constant::WideString(s) => {
+                self.write_u8(5);
+                self.write_string(s);
+            }
+        }
+    }
+
+    // Instruction writer
+    fn write_instruction(&mut self, inst_data: &InstructionData) {
+        self.write_u32(inst_data.result);
+
+        match &inst_data.inst {
+            Instruction::Binary { op, left, right } => {
+                self.write_u8(0);
+                self.write_u8(*op as u8);
+                self.write_value(left);
+                self.write_value(right);
+            }
+            Instruction::Unary { op, operand } => {
+                self.write_u8(1);
+                self.write_u8(*op as u8);
+                self.write_value(operand);
+            }
+            Instruction::Load { ptr } => {
+                self.write_u8(2);
+                self.write_value(ptr);
+            }
+            Instruction::Store { ptr, value } => {
+                self.write_u8(3);
+                self.write_value(ptr);
+                self.write_value(value);
+            }
+            Instruction::Alloca { name, alloc_type } => {
+                self.write_u8(4);
+                self.write_string(name);
+                self.write_type(alloc_type);
+            }
+            Instruction::Gep { base, indices } => {
+                self.write_u8(5);
+                self.write_value(base);
+                self.write_values(indices);
+            }
+            Instruction::ExtractValue { aggregate, indices } => {
+                self.write_u8(6);
+                self.write_value(aggregate);
+                self.write_u32(indices.len() as u32);
+                for &idx in indices {
+                    self.write_u32(idx);
+                }
+            }
+            Instruction::InsertValue {
+                aggregate,
+                value,
+                indices,
+            } => {
+                self.write_u8(7);
+                self.write_value(aggregate);
+                self.write_value(value);
+                self.write_u32(indices.len() as u32);
+                for &idx in indices {
+                    self.write_u32(idx);
+                }
+            }
+            Instruction::Call {
+                func_name,
+                args,
+                return_type,
+            } => {
+                self.write_u8(8);
+                self.write_string(func_name);
+                self.write_values(args);
+                self.write_type(return_type);
+            }
+            Instruction::MethodCall {
+                receiver,
+                receiver_type,
+                method_name,
+                args,
+                return_type,
+            } => {
+                self.write_u8(9);
+                self.write_value(receiver);
+                self.write_string(receiver_type);
+                self.write_string(method_name);
+                self.write_values(args);
+                self.write_type(return_type);
+            }
+            Instruction::Cast {
+                kind,
+                operand,
+                target_type,
+            } => {
+                self.write_u8(10);
+                self.write_u8(*kind as u8);
+                self.write_value(operand);
+                self.write_type(target_type);
+            }
+            Instruction::Phi { incoming } => {
+                self.write_u8(11);
+                self.write_u32(incoming.len() as u32);
+                for (val, block) in incoming {
+                    self.write_value(val);
+                    self.write_u32(*block);
+                }
+            }
+            Instruction::Constant(c) => {
+                self.write_u8(12);
+                self.write_constant_value(c);
+            }
+            Instruction::Select {
+                condition,
+                true_val,
+                false_val,
+            } => {
+                self.write_u8(13);
+                self.write_value(condition);
+                self.write_value(true_val);
+                self.write_value(false_val);
+            }
+            Instruction::StructInit {
+                struct_name,
+                fields,
+            } => {
+                self.write_u8(14);
+                self.write_string(struct_name);
+                self.write_values(fields);
+            }
+            Instruction::EnumInit {
+                enum_name,
+                variant_name,
+                payload,
+            } => {
+                self.write_u8(15);
+                self.write_string(enum_name);
+                self.write_string(variant_name);
+                self.write_values(payload);
+            }
+            Instruction::TupleInit { elements } => {
+                self.write_u8(16);
+                self.write_values(elements);
+            }
+            Instruction::ArrayInit {
+                element_type,
+                elements,
+            } => {
+                self.write_u8(17);
+                self.write_type(element_type);
+                self.write_values(elements);
+            }
+            Instruction::ArrayFill {
+                element_type,
+                value,
+                count,
+            } => {
+                self.write_u8(20);
+                self.write_type(element_type);
+                self.write_value(value);
+                self.write_u64(*count);
+            }
+            Instruction::Await {
+                poll_value,
+                poll_type,
+                result_type,
+                suspension_id,
+            } => {
+                self.write_u8(18);
+                self.write_value(poll_value);
+                self.write_type(poll_type);
+                self.write_type(result_type);
+                self.write_u32(*suspension_id);
+            }
+            Instruction::ClosureInit {
+                func_name,
+                captures,
+                cap_types,
+                func_type,
+                result_type,
+            } => {
+                self.write_u8(19);
+                self.write_string(func_name);
+                self.write_u32(captures.len() as u32);
+                for (cname, cval) in captures {
+                    self.write_string(cname);
+                    self.write_value(cval);
+                }
+                for (tname, tty) in cap_types {
+                    self.write_string(tname);
+                    self.write_type(tty);
+                }
+                self.write_type(func_type);
+                self.write_type(result_type);
+            }
+        }
+        self.write_u32(inst_data.loc.line);
+        self.write_u32(inst_data.loc.column);
+    }
+
+    // Terminator writer
+    fn write_terminator(&mut self, term: &Terminator) {
+        match term {
+            Terminator::Return { value } => {
+                self.write_u8(0);
+                self.write_bool(value.is_some());
+                if let Some(v) = value {
+                    self.write_value(v);
+                }
+            }
+            Terminator::Branch { target } => {
+                self.write_u8(1);
+                self.write_u32(*target);
+            }
+            Terminator::CondBranch {
+                condition,
+                true_block,
+                false_block,
+            } => {
+                self.write_u8(2);
+                self.write_value(condition);
+                self.write_u32(*true_block);
+                self.write_u32(*false_block);
+            }
+            Terminator::Switch {
+                discriminant,
+                cases,
+                default_block,
+            } => {
+                self.write_u8(3);
+                self.write_value(discriminant);
+                self.write_u32(cases.len() as u32);
+                for (val, block) in cases {
+                    self.write_i64(*val);
+                    self.write_u32(*block);
+                }
+                self.write_u32(*default_block);
+            }
+            Terminator::Unreachable => {
+                self.write_u8(4);
+            }
+        }
+    }
+
+    // Block writer
+    fn write_block(&mut self, block: &BasicBlock) {
+        self.write_u32(block.id);
+        self.write_string(&block.name);
+
+        self.write_u32(block.predecessors.len() as u32);
+        for &pred in &block.predecessors {
+            self.write_u32(pred);
+        }
+
+        self.write_u32(block.instructions.len() as u32);
+        for inst in &block.instructions {
+            self.write_instruction(inst);
+        }
+
+        self.write_bool(block.terminator.is_some());
+        if let Some(term) = &block.terminator {
+            self.write_terminator(term);
+        }
+    }
+
+    // Function writer
+    fn write_function(&mut self, func: &Function) {
+        self.write_string(&func.name);
+        self.write_bool(func.is_public);
+
+        self.write_u32(func.params.len() as u32);
+        for param in &func.params {
+            self.write_string(&param.name);
+            self.write_type(&param.ty);
+            self.write_u32(param.value_id);
+        }
+
+        self.write_type(&func.return_type);
+
+        self.write_u32(func.blocks.len() as u32);
+        for block in &func.blocks {
+            self.write_block(block);
+        }
+
+        self.write_u32(func.next_value_id);
+        self.write_u32(func.next_block_id);
+    }
+
+    fn write_struct_def(&mut self, s: &StructDef) {
+        self.write_string(&s.name);
+        self.write_u32(s.type_params.len() as u32);
+        for tp in &s.type_params {
+            self.write_string(tp);
+        }
+        self.write_u32(s.fields.len() as u32);
+        for field in &s.fields {
+            self.write_string(&field.name);
+            self.write_type(&field.ty);
+        }
+    }
+
+    fn write_enum_def(&mut self, e: &EnumDef) {
+        self.write_string(&e.name);
+        self.write_u32(e.type_params.len() as u32);
+        for tp in &e.type_params {
+            self.write_string(tp);
+        }
+        self.write_u32(e.variants.len() as u32);
+        for variant in &e.variants {
+            self.write_string(&variant.name);
+            self.write_u32(variant.payload_types.len() as u32);
+            for pt in &variant.payload_types {
+                self.write_type(pt);
+            }
+        }
+    }
+}
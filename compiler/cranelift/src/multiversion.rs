@@ -0,0 +1,95 @@
+//! Function Multi-Versioning
+//!
+//! Functions selected by the `multiversion` option are compiled twice into
+//! the same object: `<symbol>.base` with the module's ISA flags and
+//! `<symbol>.avx2` with AVX2-era x86-64 extensions enabled. `<symbol>`
+//! itself becomes a dispatch stub that asks the runtime once whether the
+//! CPU supports those extensions, caches the chosen variant in
+//! `<symbol>.impl`, and forwards every call to it. Callers, including other
+//! objects, keep calling `<symbol>`.
+
+use cranelift_codegen::ir::{BlockArg, FuncRef, GlobalValue, InstBuilder, MemFlags};
+use cranelift_codegen::isa::{self, OwnedTargetIsa, TargetIsa};
+use cranelift_codegen::settings::Configurable;
+use cranelift_frontend::FunctionBuilder;
+
+use crate::error::{BridgeError, BridgeResult};
+use crate::types::POINTER_TYPE;
+
+/// Runtime check for the extensions in `AVX2_FLAGS`: `i32 (void)`.
+pub const CPU_FEATURE_IMPORT: &str = "tml_cpu_has_avx2";
+
+/// ISA flags enabled for the `.avx2` variant (Haswell and later). The
+/// runtime check must test every one of them.
+const AVX2_FLAGS: &[&str] = &[
+    "has_sse3", "has_ssse3", "has_sse41", "has_sse42", "has_popcnt", "has_avx", "has_avx2",
+    "has_fma", "has_bmi1", "has_bmi2", "has_lzcnt",
+];
+
+/// Suffixes of the symbols derived from a multi-versioned function.
+pub const BASE_SUFFIX: &str = ".base";
+pub const AVX2_SUFFIX: &str = ".avx2";
+pub const SLOT_SUFFIX: &str = ".impl";
+
+/// The AVX2 variant ISA: `base`'s target and shared flags plus `AVX2_FLAGS`.
+/// None when `base` is not x86-64.
+pub fn avx2_isa(base: &dyn TargetIsa) -> BridgeResult<Option<OwnedTargetIsa>> {
+    if base.triple().architecture != target_lexicon::Architecture::X86_64 {
+        return Ok(None);
+    }
+    let mut builder = isa::lookup(base.triple().clone())
+        .map_err(|e| BridgeError::InvalidTarget(format!("no ISA for multi-versioning: {}", e)))?;
+    for flag in AVX2_FLAGS {
+        builder
+            .enable(flag)
+            .map_err(|e| BridgeError::Codegen(format!("failed to enable {}: {}", flag, e)))?;
+    }
+    builder
+        .finish(base.flags().clone())
+        .map(Some)
+        .map_err(|e| BridgeError::Codegen(format!("failed to build AVX2 ISA: {}", e)))
+}
+
+/// Variants and state a dispatch stub refers to.
+pub struct Dispatch {
+    pub base: FuncRef,
+    pub avx2: FuncRef,
+    pub has_avx2: FuncRef,
+    /// Pointer-sized cache of the chosen variant (zero until resolved)
+    pub slot: GlobalValue,
+}
+
+/// Emit the body of a dispatch stub into `b`, whose function has the
+/// variants' signature. Racing first calls store the same pointer.
+pub fn build_dispatcher(b: &mut FunctionBuilder, d: &Dispatch) {
+    let entry = b.create_block();
+    let resolve = b.create_block();
+    let forward = b.create_block();
+    b.append_block_params_for_function_params(entry);
+    b.append_block_param(forward, POINTER_TYPE);
+    b.set_cold_block(resolve);
+
+    b.switch_to_block(entry);
+    let args = b.block_params(entry).to_vec();
+    let slot = b.ins().global_value(POINTER_TYPE, d.slot);
+    let cached = b.ins().load(POINTER_TYPE, MemFlags::trusted(), slot, 0);
+    b.ins()
+        .brif(cached, forward, &[BlockArg::Value(cached)], resolve, &[]);
+
+    b.switch_to_block(resolve);
+    let check = b.ins().call(d.has_avx2, &[]);
+    let has_avx2 = b.inst_results(check)[0];
+    let base = b.ins().func_addr(POINTER_TYPE, d.base);
+    let avx2 = b.ins().func_addr(POINTER_TYPE, d.avx2);
+    let chosen = b.ins().select(has_avx2, avx2, base);
+    b.ins().store(MemFlags::trusted(), chosen, slot, 0);
+    b.ins().jump(forward, &[BlockArg::Value(chosen)]);
+
+    b.switch_to_block(forward);
+    let target = b.block_params(forward)[0];
+    let sig = b.import_signature(b.func.signature.clone());
+    let call = b.ins().call_indirect(sig, target, &args);
+    let results = b.inst_results(call).to_vec();
+    b.ins().return_(&results);
+    b.seal_all_blocks();
+}
@@ -0,0 +1,676 @@
+//! Bridge Options
+//!
+//! Resolved, Rust-side view of the C `CraneliftOptions` struct. Every entry
+//! point converts the raw pointer it receives into a `BridgeOptions` once, so
+//! the translator never touches FFI data directly. A zeroed C struct (or a null
+//! pointer) maps to the defaults. A named profile replaces the codegen fields
+//! (see `profiles`), and overrides then adjust single fields.
+//!
+//! The `*_kv` entry points take a versioned key-value blob instead of the
+//! struct, so options can be added without changing the C layout; unknown
+//! keys and bad values are rejected with an error naming the key. The
+//! `*_ex` entry points take `CraneliftOptionsV2`, the struct behind a
+//! size/version header plus a list of raw Cranelift settings.
+
+use std::ffi::CStr;
+
+use crate::{CraneliftOptions, CraneliftOptionsV2};
+use crate::cancel::{self, CancelToken};
+use crate::cpu;
+use crate::dump::DumpSink;
+use crate::error::{BridgeError, BridgeResult};
+use crate::profiles;
+use crate::result_context::{self, ResultContext};
+use crate::stream::ObjectSink;
+use crate::test_registry::TestEntry;
+
+#[derive(Debug, Clone, Default)]
+pub struct BridgeOptions {
+    /// Profile the codegen fields were taken from; empty when none
+    pub profile: String,
+    /// 0 = none, 1-3 = speed_and_size
+    pub opt_level: u8,
+    /// Target triple; empty means the host
+    pub target_triple: String,
+    /// DWARF line info for `source_file` (ELF only; see `dwarf`)
+    pub debug_info: bool,
+    /// Export public functions from the DLL the object is linked into
+    /// (COFF only; see `ModuleTranslator::add_dll_exports`)
+    pub dll_export: bool,
+    /// Emit small public functions' MIR into the `.tml.inline` section
+    pub emit_inline_metadata: bool,
+    /// Call `__tml_trace(func_index, block_id)` at every block head
+    pub trace_hooks: bool,
+    /// IR text: only print functions matching one of these globs (empty = all)
+    pub ir_filter: Vec<String>,
+    /// IR text: frame functions with `;; @function`/`;; @end` separators
+    pub ir_annotate: bool,
+    /// IR text: print functions sorted by name instead of module order
+    pub ir_sort: bool,
+    /// IR text: return a JSON document instead (see `generate_ir_text`)
+    pub json_output: bool,
+    /// Compiles also return the IR text of the functions they define, as
+    /// the IR entry points would print it (`json_output` aside)
+    pub emit_ir: bool,
+    /// Compiles also return per-function timings and code sizes
+    pub function_stats: bool,
+    /// Fast math: fuse float multiply-add chains into `fma`
+    pub fp_contract: bool,
+    /// Fast math: reassociate float add/mul chains with constant operands
+    pub fp_reassociate: bool,
+    /// Replace every NaN produced by float arithmetic with the canonical NaN,
+    /// so results are bit-identical across targets and backends
+    pub nan_canonicalization: bool,
+    /// Warn about functions whose stack slots exceed this many bytes
+    /// (0 = `DEFAULT_FRAME_WARN_BYTES`)
+    pub frame_warn_bytes: usize,
+    /// Keep frame pointers in every function. Off lets Cranelift skip the
+    /// frame setup of leaf functions on targets that allow it (aarch64; x64
+    /// always sets up a frame). Cranelift never uses a red zone, so there
+    /// is no separate toggle for it.
+    pub frame_pointers: bool,
+    /// Call the host's `tml_trap_abort(category)` before every trap the
+    /// translator emits, and check divisions explicitly so they do too
+    pub trap_abort: bool,
+    /// Test functions to record in the `.tml.tests` section (empty = no section)
+    pub tests: Vec<TestEntry>,
+    /// Reject calls to runtime functions whose arity or types differ from
+    /// the runtime signature manifest instead of coercing the arguments
+    pub strict_runtime_calls: bool,
+    /// Functions (globs over MIR names and symbols) compiled as baseline and
+    /// AVX2 variants behind a CPU dispatch stub; x86-64 only
+    pub multiversion: Vec<String>,
+    /// Import runtime functions through their `tml_rt_` aliases so they
+    /// cannot collide with C symbols (None = per-target default, see
+    /// `runtime_sigs::prefix_by_default`)
+    pub runtime_prefix: Option<bool>,
+    /// Directory for replay bundles of failed translations (None = don't
+    /// write any; empty = the system temp directory), see `replay`
+    pub replay_dir: Option<String>,
+    /// CPU class whose instruction set extensions codegen may use (empty =
+    /// the host's), see `cpu`
+    pub cpu: String,
+    /// Comma-separated `+name`/`-name` extensions added to or removed from
+    /// the CPU class (empty = none), see `cpu::apply_features`
+    pub target_features: String,
+    /// Cranelift settings set after the bridge's own, as `(name, value)`
+    pub cranelift_flags: Vec<(String, String)>,
+    /// Move a function's float constants into a read-only pool when it has
+    /// more distinct ones than this (0 = never), see `const_pool`
+    pub const_pool_threshold: usize,
+    /// Stop translating with `BridgeError::Cancelled` once set
+    pub cancel: Option<CancelToken>,
+    /// Stream the object to this callback instead of returning it, see
+    /// `stream`
+    pub write: Option<ObjectSink>,
+    /// Prefix of the symbols of TML functions (None = `DEFAULT_SYMBOL_PREFIX`,
+    /// empty = none); C runtime functions never get one
+    pub symbol_prefix: Option<String>,
+    /// Symbols declared under another name, as `(symbol, new name)`; applies
+    /// to definitions and imports alike
+    pub symbol_renames: Vec<(String, String)>,
+    /// Emit position-independent code, reaching imports through the GOT,
+    /// so objects can be linked into shared libraries (always on for
+    /// aarch64, see `cpu::isa`)
+    pub position_independent: bool,
+    /// Path of the module's source file, named by trap messages and debug
+    /// info together with the MIR's line numbers (empty = unknown)
+    pub source_file: String,
+    /// Record the bridge, MIR format and codegen settings in a `.tml.build`
+    /// section, see `build_info`
+    pub emit_build_info: bool,
+    /// Context that owns the call's result, see `result_context`
+    pub result_context: Option<ResultContext>,
+    /// Heap bytes reading and translating MIR may hold (0 = no limit), see
+    /// `memory`
+    pub max_memory_bytes: usize,
+    /// Where IR, diagnostic and verifier dumps go (None = nowhere), see
+    /// `dump`
+    pub dump: Option<DumpSink>,
+    /// Append the Rust backtrace to the message of a caught panic, see
+    /// `backtrace`
+    pub panic_backtrace: bool,
+}
+
+/// Frame size warning threshold when none is given: a quarter of the
+/// smallest common default thread stack (1 MiB on Windows).
+pub const DEFAULT_FRAME_WARN_BYTES: usize = 256 * 1024;
+
+/// Symbol prefix when none is given, as in the LLVM backend's output.
+pub const DEFAULT_SYMBOL_PREFIX: &str = "tml_";
+
+/// `CraneliftOptions::fast_math` bits
+pub const FAST_MATH_CONTRACT: i32 = 1;
+pub const FAST_MATH_REASSOCIATE: i32 = 2;
+
+/// `CraneliftOptions::runtime_prefix` values (0 = target default)
+pub const RUNTIME_PREFIX_ON: i32 = 1;
+pub const RUNTIME_PREFIX_OFF: i32 = 2;
+
+/// `CraneliftOptionsV2::version` this bridge reads. The plain
+/// `CraneliftOptions` struct counts as version 1.
+pub const OPTIONS_VERSION: u32 = 2;
+
+impl BridgeOptions {
+    /// Build options from a (possibly null) pointer received over the C API.
+    /// Fails on a `struct_size` other than this build's, an unknown profile,
+    /// a bad override or, with a profile, an unparsable target triple.
+    pub fn from_raw(options: *const CraneliftOptions) -> BridgeResult<Self> {
+        if options.is_null() {
+            return Ok(Self::default());
+        }
+        check_struct_size(unsafe { (*options).struct_size })?;
+        let opts = unsafe { &*options };
+
+        let target_triple = c_str_or_empty(opts.target_triple);
+        let ir_filter = glob_list(opts.ir_filter);
+        let tests = if opts.tests.is_null() {
+            Vec::new()
+        } else {
+            unsafe { std::slice::from_raw_parts(opts.tests, opts.num_tests) }
+                .iter()
+                .filter(|t| !t.function.is_null())
+                .map(|t| {
+                    let function = c_str_or_empty(t.function);
+                    let name = if t.name.is_null() { function.clone() } else { c_str_or_empty(t.name) };
+                    TestEntry {
+                        function,
+                        name,
+                        should_panic: t.should_panic != 0,
+                        expected_panic: (!t.expected_panic.is_null())
+                            .then(|| c_str_or_empty(t.expected_panic)),
+                    }
+                })
+                .collect()
+        };
+
+        let mut resolved = Self {
+            profile: c_str_or_empty(opts.profile),
+            opt_level: opts.optimization_level.clamp(0, 3) as u8,
+            target_triple,
+            debug_info: opts.debug_info != 0,
+            dll_export: opts.dll_export != 0,
+            emit_inline_metadata: opts.emit_inline_metadata != 0,
+            trace_hooks: opts.trace_hooks != 0,
+            ir_filter,
+            ir_annotate: opts.ir_annotate != 0,
+            ir_sort: opts.ir_sort != 0,
+            json_output: opts.json_output != 0,
+            emit_ir: opts.emit_ir != 0,
+            function_stats: opts.function_stats != 0,
+            fp_contract: opts.fast_math & FAST_MATH_CONTRACT != 0,
+            fp_reassociate: opts.fast_math & FAST_MATH_REASSOCIATE != 0,
+            nan_canonicalization: opts.nan_canonicalization != 0,
+            frame_warn_bytes: opts.frame_warn_bytes,
+            frame_pointers: opts.frame_pointers != 0,
+            trap_abort: opts.trap_abort != 0,
+            tests,
+            strict_runtime_calls: opts.strict_runtime_calls != 0,
+            multiversion: glob_list(opts.multiversion),
+            runtime_prefix: match opts.runtime_prefix {
+                RUNTIME_PREFIX_ON => Some(true),
+                RUNTIME_PREFIX_OFF => Some(false),
+                _ => None,
+            },
+            replay_dir: (!opts.replay_dir.is_null()).then(|| c_str_or_empty(opts.replay_dir)),
+            cpu: String::new(),
+            target_features: String::new(),
+            cranelift_flags: Vec::new(),
+            const_pool_threshold: 0,
+            cancel: cancel::from_handle("options.cancel_token", opts.cancel_token)?,
+            write: opts.write.map(|write| ObjectSink { write, context: opts.write_context }),
+            symbol_prefix: (!opts.symbol_prefix.is_null()).then(|| c_str_or_empty(opts.symbol_prefix)),
+            symbol_renames: Vec::new(),
+            position_independent: opts.position_independent != 0,
+            source_file: c_str_or_empty(opts.source_file),
+            emit_build_info: opts.emit_build_info != 0,
+            result_context: result_context::from_handle("options.result_context", opts.result_context)?,
+            max_memory_bytes: opts.max_memory_bytes,
+            dump: match (opts.dump, c_str_or_empty(opts.dump_path)) {
+                (Some(dump), _) => Some(DumpSink::Callback { dump, context: opts.dump_context }),
+                (None, path) if !path.is_empty() => Some(DumpSink::File(path.into())),
+                (None, _) => None,
+            },
+            panic_backtrace: opts.panic_backtrace != 0,
+        };
+        if !opts.symbol_renames.is_null() {
+            let renames = unsafe { std::slice::from_raw_parts(opts.symbol_renames, opts.num_symbol_renames) };
+            for (i, rename) in renames.iter().enumerate() {
+                resolved
+                    .add_symbol_rename(c_str_or_empty(rename.from), c_str_or_empty(rename.to))
+                    .map_err(|e| BridgeError::InvalidOptions(format!("symbol_renames[{}]: {}", i, e)))?;
+            }
+        }
+        if !resolved.profile.is_empty() {
+            let target = profiles::target(&resolved.target_triple)?;
+            let profile = resolved.profile.clone();
+            profiles::apply(&mut resolved, &profile, &target)?;
+        }
+        profiles::apply_overrides(&mut resolved, &c_str_or_empty(opts.profile_overrides))?;
+        Ok(resolved)
+    }
+
+    /// Build options from a (possibly null) `CraneliftOptionsV2`: the `base`
+    /// fields as in `from_raw`, then each `flags` entry as a `flag.<key>`
+    /// override. Fails on a version other than `OPTIONS_VERSION` or a `size`
+    /// smaller than that version's struct; flag names are checked when the
+    /// ISA is built.
+    pub fn from_versioned(options: *const CraneliftOptionsV2) -> BridgeResult<Self> {
+        if options.is_null() {
+            return Ok(Self::default());
+        }
+        // Only the header is known to be there until `size` is checked
+        let (size, version) = unsafe { ((*options).size, (*options).version) };
+        if version > OPTIONS_VERSION {
+            return Err(BridgeError::InvalidOptions(format!(
+                "options version {} is newer than this bridge supports ({})",
+                version, OPTIONS_VERSION
+            )));
+        }
+        if version < OPTIONS_VERSION {
+            return Err(BridgeError::InvalidOptions(format!("options version {} is not a versioned struct", version)));
+        }
+        let expected = std::mem::size_of::<CraneliftOptionsV2>();
+        if (size as usize) < expected {
+            return Err(BridgeError::InvalidOptions(format!(
+                "options size {} is smaller than version {} needs ({})",
+                size, version, expected
+            )));
+        }
+        let opts = unsafe { &*options };
+
+        let mut resolved = Self::from_raw(&opts.base)?;
+        let flags = if opts.flags.is_null() {
+            &[][..]
+        } else {
+            unsafe { std::slice::from_raw_parts(opts.flags, opts.num_flags) }
+        };
+        for (i, &flag) in flags.iter().enumerate() {
+            let invalid = |problem: String| BridgeError::InvalidOptions(format!("flags[{}]: {}", i, problem));
+            if flag.is_null() {
+                return Err(invalid("null entry".to_string()));
+            }
+            let text = c_str_or_empty(flag);
+            let (name, value) = text
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected key=value, got '{}'", text)))?;
+            let field = format!("{}{}", cpu::FLAG_PREFIX, name.trim());
+            profiles::set_field(&mut resolved, &field, value.trim()).map_err(invalid)?;
+        }
+        Ok(resolved)
+    }
+
+    /// The resolved options as `key=value` lines, recorded in replay bundles.
+    /// Codegen fields use the override syntax; each test is a `test=` line
+    /// with tab-separated function, name, should_panic and expected message,
+    /// and each symbol rename a `symbol_rename=` line with a tab between the
+    /// two names.
+    /// `replay_dir`, `cancel`, `result_context`, `write` and `dump` are left
+    /// out.
+    pub fn snapshot(&self) -> String {
+        let flag = |b: bool| if b { "1" } else { "0" };
+        let mut out = String::new();
+        let mut line = |key: &str, value: &str| {
+            out.push_str(key);
+            out.push('=');
+            out.push_str(value);
+            out.push('\n');
+        };
+        line("profile", &self.profile);
+        line("target_triple", &self.target_triple);
+        line("opt_level", &self.opt_level.to_string());
+        line("debug_info", flag(self.debug_info));
+        line("dll_export", flag(self.dll_export));
+        line("emit_inline_metadata", flag(self.emit_inline_metadata));
+        line("trace_hooks", flag(self.trace_hooks));
+        line("ir_filter", &self.ir_filter.join(","));
+        line("ir_annotate", flag(self.ir_annotate));
+        line("ir_sort", flag(self.ir_sort));
+        line("json_output", flag(self.json_output));
+        line("emit_ir", flag(self.emit_ir));
+        line("function_stats", flag(self.function_stats));
+        line("position_independent", flag(self.position_independent));
+        line("source_file", &self.source_file);
+        line("emit_build_info", flag(self.emit_build_info));
+        line("max_memory_bytes", &self.max_memory_bytes.to_string());
+        line("panic_backtrace", flag(self.panic_backtrace));
+        line("fp_contract", flag(self.fp_contract));
+        line("fp_reassociate", flag(self.fp_reassociate));
+        line("nan_canonicalization", flag(self.nan_canonicalization));
+        line("frame_warn_bytes", &self.frame_warn_bytes.to_string());
+        line("frame_pointers", flag(self.frame_pointers));
+        line("trap_abort", flag(self.trap_abort));
+        line("strict_runtime_calls", flag(self.strict_runtime_calls));
+        line("multiversion", &self.multiversion.join(","));
+        line("runtime_prefix", self.runtime_prefix.map_or("target", flag));
+        line("cpu", &self.cpu);
+        line("target_features", &self.target_features);
+        line("const_pool_threshold", &self.const_pool_threshold.to_string());
+        for (name, value) in &self.cranelift_flags {
+            line(&format!("{}{}", cpu::FLAG_PREFIX, name), value);
+        }
+        for t in &self.tests {
+            let mut fields = vec![t.function.as_str(), t.name.as_str(), flag(t.should_panic)];
+            fields.extend(t.expected_panic.as_deref());
+            line("test", &fields.join("\t"));
+        }
+        if let Some(prefix) = &self.symbol_prefix {
+            line("symbol_prefix", prefix);
+        }
+        for (from, to) in &self.symbol_renames {
+            line("symbol_rename", &format!("{}\t{}", from, to));
+        }
+        out
+    }
+
+    /// Parse a `snapshot`. The fields are taken as recorded, without
+    /// re-applying the profile. None on an unknown key or a bad value.
+    #[cfg(any(test, feature = "dev-tools"))]
+    pub fn from_snapshot(text: &str) -> Option<Self> {
+        let mut opts = Self::default();
+        for entry in text.lines().filter(|l| !l.is_empty()) {
+            let (key, value) = entry.split_once('=')?;
+            opts.set(key, value).ok()?;
+        }
+        Some(opts)
+    }
+
+    /// Build options from a key-value blob (layout at `BLOB_MAGIC`). Keys are
+    /// the `snapshot` keys plus `replay_dir`, `dump_path`, `cancel_token` and
+    /// `result_context` (handles as `0x` hex); a later pair overrides an
+    /// earlier one, except that `test` and `symbol_rename` pairs accumulate.
+    /// A `profile` is applied first, so the other pairs act as its overrides.
+    pub fn from_blob(data: &[u8]) -> BridgeResult<Self> {
+        let pairs = read_blob(data)?;
+        let last = |key: &str| pairs.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+        let mut opts = Self::default();
+        if let Some(profile) = last("profile").filter(|p| !p.is_empty()) {
+            let target = profiles::target(last("target_triple").unwrap_or(""))?;
+            profiles::apply(&mut opts, profile, &target)?;
+        }
+        for (key, value) in &pairs {
+            opts.set(key, value)
+                .map_err(|e| BridgeError::InvalidOptions(format!("option '{}': {}", key, e)))?;
+        }
+        Ok(opts)
+    }
+
+    /// Set the field named `key` from its `snapshot` form.
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let flag = || match value {
+            "1" => Ok(true),
+            "0" => Ok(false),
+            _ => Err("expected 0 or 1".to_string()),
+        };
+        let list = || value.split(',').map(str::trim).filter(|p| !p.is_empty()).map(String::from).collect();
+        match key {
+            "profile" => self.profile = value.to_string(),
+            "target_triple" => self.target_triple = value.to_string(),
+            "dll_export" => self.dll_export = flag()?,
+            "trace_hooks" => self.trace_hooks = flag()?,
+            "ir_filter" => self.ir_filter = list(),
+            "ir_annotate" => self.ir_annotate = flag()?,
+            "ir_sort" => self.ir_sort = flag()?,
+            "json_output" => self.json_output = flag()?,
+            "emit_ir" => self.emit_ir = flag()?,
+            "function_stats" => self.function_stats = flag()?,
+            "position_independent" => self.position_independent = flag()?,
+            "source_file" => self.source_file = value.to_string(),
+            "emit_build_info" => self.emit_build_info = flag()?,
+            "panic_backtrace" => self.panic_backtrace = flag()?,
+            "max_memory_bytes" => self.max_memory_bytes = value.parse().map_err(|_| "expected a byte count")?,
+            "multiversion" => self.multiversion = list(),
+            "replay_dir" => self.replay_dir = Some(value.to_string()),
+            "dump_path" => self.dump = (!value.is_empty()).then(|| DumpSink::File(value.into())),
+            "cancel_token" => {
+                let digits = value.strip_prefix("0x").ok_or("expected a 0x-prefixed handle")?;
+                let handle = usize::from_str_radix(digits, 16).map_err(|e| e.to_string())?;
+                self.cancel = Some(cancel::lookup(handle as *const _).ok_or("not a live cancel token")?);
+            }
+            "result_context" => {
+                let digits = value.strip_prefix("0x").ok_or("expected a 0x-prefixed handle")?;
+                let handle = usize::from_str_radix(digits, 16).map_err(|e| e.to_string())?;
+                let context = result_context::lookup(handle as *const _).ok_or("not a live result context")?;
+                self.result_context = Some(context);
+            }
+            "test" => {
+                let mut fields = value.split('\t');
+                let mut next = || fields.next().ok_or("expected function, name and should_panic");
+                self.tests.push(TestEntry {
+                    function: next()?.to_string(),
+                    name: next()?.to_string(),
+                    should_panic: next()? == "1",
+                    expected_panic: fields.next().map(String::from),
+                });
+            }
+            "symbol_prefix" => self.symbol_prefix = Some(value.to_string()),
+            "symbol_rename" => {
+                let (from, to) = value.split_once('\t').ok_or("expected symbol and new name")?;
+                self.add_symbol_rename(from.to_string(), to.to_string())?;
+            }
+            _ if profiles::is_field(key) => profiles::set_field(self, key, value)?,
+            _ => return Err("unknown option".to_string()),
+        }
+        Ok(())
+    }
+
+    /// Declare `from` as `to`. Each symbol can be renamed once.
+    fn add_symbol_rename(&mut self, from: String, to: String) -> Result<(), String> {
+        if from.is_empty() || to.is_empty() {
+            return Err("symbol names must not be empty".to_string());
+        }
+        if self.symbol_renames.iter().any(|(f, _)| *f == from) {
+            return Err(format!("'{}' is renamed more than once", from));
+        }
+        self.symbol_renames.push((from, to));
+        Ok(())
+    }
+}
+
+/// Options blob layout (little-endian), accepted by the `*_kv` entry points:
+///
+/// ```text
+/// magic "TMLO" | u32 version | u32 count | (str key | str value)*
+/// str: u32 byte length | UTF-8 bytes
+/// ```
+///
+/// Values use the `snapshot` syntax. Invalid UTF-8 is replaced with U+FFFD.
+pub const BLOB_MAGIC: &[u8; 4] = b"TMLO";
+pub const BLOB_VERSION: u32 = 1;
+
+fn read_blob(data: &[u8]) -> BridgeResult<Vec<(String, String)>> {
+    let malformed = |what: &str| BridgeError::InvalidOptions(format!("malformed options blob: {}", what));
+    let mut pos = 0;
+    let mut take = |n: usize| -> BridgeResult<&[u8]> {
+        let bytes = data.get(pos..pos + n).ok_or_else(|| malformed("truncated"))?;
+        pos += n;
+        Ok(bytes)
+    };
+    if take(4)? != BLOB_MAGIC {
+        return Err(malformed("bad magic"));
+    }
+    let u32_at = |b: &[u8]| u32::from_le_bytes(b.try_into().unwrap());
+    let version = u32_at(take(4)?);
+    if version > BLOB_VERSION {
+        return Err(BridgeError::InvalidOptions(format!(
+            "options blob version {} is newer than this bridge supports ({})",
+            version, BLOB_VERSION
+        )));
+    }
+    let count = u32_at(take(4)?);
+    let mut pairs = Vec::new();
+    for _ in 0..count {
+        let mut read_str = || -> BridgeResult<String> {
+            let len = u32_at(take(4)?) as usize;
+            Ok(String::from_utf8_lossy(take(len)?).into_owned())
+        };
+        let key = read_str()?;
+        let value = read_str()?;
+        pairs.push((key, value));
+    }
+    if pos != data.len() {
+        return Err(malformed("trailing bytes"));
+    }
+    Ok(pairs)
+}
+
+/// Encode `pairs` as an options blob.
+#[cfg(test)]
+pub fn encode_blob(pairs: &[(&str, &str)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(BLOB_MAGIC);
+    out.extend_from_slice(&BLOB_VERSION.to_le_bytes());
+    out.extend_from_slice(&(pairs.len() as u32).to_le_bytes());
+    for s in pairs.iter().flat_map(|(k, v)| [k, v]) {
+        out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        out.extend_from_slice(s.as_bytes());
+    }
+    out
+}
+
+/// Fail unless `struct_size` is this build's `sizeof(CraneliftOptions)`:
+/// a caller built against another header would have every later field at
+/// the wrong offset.
+pub fn check_struct_size(struct_size: usize) -> BridgeResult<()> {
+    let expected = std::mem::size_of::<CraneliftOptions>();
+    match struct_size {
+        n if n == expected => Ok(()),
+        0 => Err(BridgeError::InvalidOptions(format!(
+            "options.struct_size is not set (expected sizeof(CraneliftOptions) = {})",
+            expected
+        ))),
+        n => Err(BridgeError::InvalidOptions(format!(
+            "options.struct_size is {} but this bridge's CraneliftOptions is {} bytes; \
+             the header and the bridge library are from different builds",
+            n, expected
+        ))),
+    }
+}
+
+/// Comma-separated globs; null or empty means none.
+fn glob_list(ptr: *const std::ffi::c_char) -> Vec<String> {
+    c_str_or_empty(ptr)
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// A C string option; invalid UTF-8 is replaced with U+FFFD rather than
+/// dropping the whole value.
+fn c_str_or_empty(ptr: *const std::ffi::c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mir_types::*;
+    use crate::test_util::*;
+
+    #[test]
+    fn versioned_options_forward_cranelift_flags() {
+        use crate::{CraneliftOptions, CraneliftOptionsV2};
+        use crate::options::OPTIONS_VERSION;
+        let resolve = |opts: &CraneliftOptionsV2| {
+            let mut result = unsafe { std::mem::zeroed() };
+            crate::cranelift_resolve_options_ex(opts, &mut result);
+            take(&mut result)
+        };
+        let versioned = |flags: &[*const i8]| CraneliftOptionsV2 {
+            size: std::mem::size_of::<CraneliftOptionsV2>() as u32,
+            version: OPTIONS_VERSION,
+            base: CraneliftOptions { optimization_level: 2, ..default_options() },
+            flags: flags.as_ptr(),
+            num_flags: flags.len(),
+        };
+
+        // Flags land on top of the struct fields, later entries winning
+        let flags = [
+            c"preserve_frame_pointers=true".as_ptr(),
+            c" enable_alias_analysis = false".as_ptr(),
+            c"preserve_frame_pointers=false".as_ptr(),
+        ];
+        let out = resolve(&versioned(&flags));
+        assert!(out.success, "{}", out.text);
+        let fields = ["\"opt_level\":2", "\"enable_alias_analysis\":\"false\"", "\"preserve_frame_pointers\":\"false\""];
+        for field in fields {
+            assert!(out.text.contains(field), "{} not in {}", field, out.text);
+        }
+
+        let unknown = resolve(&versioned(&[c"enable_nothing=true".as_ptr()]));
+        assert!(!unknown.success && unknown.text.contains("flag.enable_nothing=true"), "{}", unknown.text);
+        let malformed = resolve(&versioned(&[c"preserve_frame_pointers".as_ptr()]));
+        assert!(malformed.text.contains("flags[0]: expected key=value"), "{}", malformed.text);
+        let null = resolve(&versioned(&[std::ptr::null()]));
+        assert!(null.text.contains("flags[0]: null entry"), "{}", null.text);
+
+        let newer = CraneliftOptionsV2 { version: OPTIONS_VERSION + 1, ..versioned(&[]) };
+        assert!(resolve(&newer).text.contains("is newer than this bridge supports"));
+        let short = CraneliftOptionsV2 { size: 8, ..versioned(&[]) };
+        assert!(resolve(&short).text.contains("options size 8 is smaller than version 2 needs"));
+    }
+
+    #[test]
+    fn key_value_options_blob() {
+        use crate::options::encode_blob;
+        let resolve = |blob: &[u8]| {
+            let mut result = unsafe { std::mem::zeroed() };
+            crate::cranelift_resolve_options_kv(blob.as_ptr(), blob.len(), &mut result);
+            take(&mut result)
+        };
+
+        // The profile comes first, the other pairs override it in any order
+        let blob = encode_blob(&[("opt_level", "1"), ("profile", "release"), ("target_triple", "x86_64-pc-windows-msvc")]);
+        let out = resolve(&blob);
+        assert!(out.success, "{}", out.text);
+        for field in ["\"profile\":\"release\"", "\"opt_level\":1", "\"emit_inline_metadata\":true", "\"runtime_prefix\":false"] {
+            assert!(out.text.contains(field), "{} not in {}", field, out.text);
+        }
+        // Empty means defaults
+        assert!(resolve(&[]).text.contains("\"opt_level\":0"));
+
+        let unknown = resolve(&encode_blob(&[("opt_levle", "2")]));
+        assert!(!unknown.success && unknown.text.contains("option 'opt_levle': unknown option"), "{}", unknown.text);
+        let bad = resolve(&encode_blob(&[("ir_sort", "yes")]));
+        assert!(bad.text.contains("option 'ir_sort': expected 0 or 1"), "{}", bad.text);
+        let mut newer = encode_blob(&[]);
+        newer[4] = 2;
+        assert!(resolve(&newer).text.contains("options blob version 2 is newer than this bridge supports (1)"));
+        let truncated = encode_blob(&[("opt_level", "2")]);
+        assert!(resolve(&truncated[..truncated.len() - 1]).text.contains("malformed options blob: truncated"));
+
+        // Compilation honors the blob
+        let m = module(vec![function(
+            "f",
+            Vec::new(),
+            prim(PrimitiveType::I32),
+            vec![block(0, vec![const_int(0, 1, 32)], Terminator::Return { value: Some(v(0)) })],
+        )]);
+        let mir = crate::mir_writer::MirBinaryWriter::new().write_module(&m);
+        let blob = encode_blob(&[("json_output", "1"), ("ir_filter", "f")]);
+        let mut result = unsafe { std::mem::zeroed() };
+        let ok = crate::cranelift_generate_ir_kv(mir.as_ptr(), mir.len(), blob.as_ptr(), blob.len(), &mut result);
+        let out = take(&mut result);
+        assert_eq!(ok, 1);
+        assert!(out.text.starts_with("{\"module\":\"test\",\"functions\":{\"tml_f\""), "{}", out.text);
+    }
+
+    #[test]
+    fn options_struct_size_must_match_this_build() {
+        assert_eq!(crate::cranelift_abi_version(), crate::ABI_VERSION);
+        let mut options = default_options();
+        assert!(resolve_options(&options).success);
+        let older = std::mem::size_of::<crate::CraneliftOptions>() - 8;
+        for (size, expected) in [(0, "options.struct_size is not set"), (older, "are from different builds")] {
+            options.struct_size = size;
+            let mut result = crate::cranelift_resolve_options(&options);
+            assert_eq!(result.error_code, crate::error::ERROR_INVALID_OPTIONS);
+            let out = take(&mut result);
+            assert!(out.text.contains(expected), "{}", out.text);
+        }
+    }
+}
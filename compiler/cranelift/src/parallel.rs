@@ -0,0 +1,123 @@
+//! Parallel Function Compilation
+//!
+//! `cranelift_compile_mir_parallel` still translates MIR to CLIF on the
+//! calling thread, because translation declares functions and data in the
+//! one `ObjectModule`. Each finished function is queued here instead of
+//! being compiled on the spot. Lowering, register allocation and emission,
+//! most of a compile's time, then run on worker threads, and the translator
+//! defines the machine code in queue order, so the object does not depend on
+//! the thread count or on scheduling.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use cranelift_codegen::Context;
+use cranelift_codegen::control::ControlPlane;
+use cranelift_codegen::isa::TargetIsa;
+use cranelift_module::FuncId;
+
+use crate::backtrace;
+use crate::cancel::CancelToken;
+use crate::dump;
+use crate::memory;
+
+/// A translated function waiting to be compiled.
+pub struct Pending {
+    /// MIR name, for error messages
+    pub name: String,
+    pub func_id: FuncId,
+    pub ctx: Context,
+    /// Compile with the multi-versioning ISA
+    pub avx2: bool,
+    /// The translator's `function_stats` entry for this function, if kept
+    pub stats_slot: Option<usize>,
+    /// Set by `compile`: the compile result, or the panic it raised
+    pub outcome: Option<std::thread::Result<Result<(), String>>>,
+    /// Set by `compile`: microseconds the worker spent compiling
+    pub codegen_us: u64,
+    /// Set by `compile`: the verifier's annotated listing, if it rejected
+    /// the function
+    pub verifier_report: Option<String>,
+}
+
+impl Pending {
+    pub fn new(name: &str, func_id: FuncId, ctx: Context, avx2: bool, stats_slot: Option<usize>) -> Self {
+        Self {
+            name: name.to_string(),
+            func_id,
+            ctx,
+            avx2,
+            stats_slot,
+            outcome: None,
+            codegen_us: 0,
+            verifier_report: None,
+        }
+    }
+}
+
+/// Worker count for `threads` (0 = one per available CPU), never more than
+/// there are functions.
+pub fn worker_count(threads: usize, functions: usize) -> usize {
+    let threads = match threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    threads.min(functions).max(1)
+}
+
+/// Compile every entry of `pending` on `threads` workers, taking functions
+/// in order as workers free up. Workers charge the calling thread's memory
+/// budget and stop taking functions once `cancel` is set or the budget is
+/// exceeded, leaving the rest without an outcome.
+pub fn compile(
+    pending: &mut [Pending],
+    isa: &dyn TargetIsa,
+    avx2_isa: Option<&dyn TargetIsa>,
+    threads: usize,
+    cancel: Option<&CancelToken>,
+) {
+    let workers = worker_count(threads, pending.len());
+    let queue: Vec<Mutex<&mut Pending>> = pending.iter_mut().map(Mutex::new).collect();
+    let next = AtomicUsize::new(0);
+    let budget = memory::current();
+    let backtraces = backtrace::enabled();
+    let work = || {
+        let _budget = memory::enter_current(budget);
+        backtrace::enable(backtraces);
+        loop {
+            if cancel.is_some_and(CancelToken::is_cancelled) || memory::exceeded() {
+                return;
+            }
+            let Some(slot) = queue.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                return;
+            };
+            // Each index is taken once, so the lock is never contended
+            let mut entry = slot.lock().unwrap_or_else(|e| e.into_inner());
+            let isa = avx2_isa.filter(|_| entry.avx2).unwrap_or(isa);
+            let ctx = &mut entry.ctx;
+            let started = Instant::now();
+            let mut report = None;
+            let compiled = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                ctx.compile(isa, &mut ControlPlane::default()).map(|_| ()).map_err(|e| {
+                    report = dump::verifier_report(&e.inner, e.func);
+                    format!("{:?}", e.inner)
+                })
+            }));
+            entry.codegen_us = started.elapsed().as_micros() as u64;
+            entry.verifier_report = report;
+            // Taken here, as the backtrace was captured on this thread
+            let message = |payload| Box::new(crate::panic_message(payload)) as Box<dyn std::any::Any + Send>;
+            entry.outcome = Some(compiled.map_err(message));
+        }
+    };
+    if workers == 1 {
+        work();
+        return;
+    }
+    std::thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(work);
+        }
+    });
+}
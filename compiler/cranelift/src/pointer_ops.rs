@@ -0,0 +1,64 @@
+//! Pointer Operations
+//!
+//! Every conversion between pointers and integers, and all address
+//! arithmetic, goes through a `PointerModel`. Today the only model is
+//! `FlatPointers`: a pointer is a `POINTER_TYPE` integer. Targets where
+//! pointers carry more than an address (CHERI-style capabilities) would add
+//! a model that keeps provenance through these operations instead of
+//! scattering width checks across the translator.
+
+use cranelift_codegen::ir::{InstBuilder, Type, Value};
+use cranelift_frontend::FunctionBuilder;
+
+use crate::types::POINTER_TYPE;
+
+pub trait PointerModel {
+    /// Address of `ptr` as an integer of type `int_ty`.
+    fn ptr_to_int(&self, b: &mut FunctionBuilder, ptr: Value, int_ty: Type) -> Value;
+
+    /// Pointer from integer address `addr` (zero-extended when narrower).
+    fn int_to_ptr(&self, b: &mut FunctionBuilder, addr: Value) -> Value;
+
+    /// Signed byte offset `index * scale` as a pointer-sized integer.
+    fn scaled_offset(&self, b: &mut FunctionBuilder, index: Value, scale: i64) -> Value;
+
+    /// `ptr + offset` for an offset produced by `scaled_offset`.
+    fn offset(&self, b: &mut FunctionBuilder, ptr: Value, offset: Value) -> Value;
+}
+
+/// Pointers are plain `POINTER_TYPE` integers.
+pub struct FlatPointers;
+
+impl FlatPointers {
+    fn resize(b: &mut FunctionBuilder, v: Value, target: Type, signed: bool) -> Value {
+        let ty = b.func.dfg.value_type(v);
+        if ty == target || !ty.is_int() {
+            v
+        } else if ty.bits() > target.bits() {
+            b.ins().ireduce(target, v)
+        } else if signed {
+            b.ins().sextend(target, v)
+        } else {
+            b.ins().uextend(target, v)
+        }
+    }
+}
+
+impl PointerModel for FlatPointers {
+    fn ptr_to_int(&self, b: &mut FunctionBuilder, ptr: Value, int_ty: Type) -> Value {
+        Self::resize(b, ptr, int_ty, false)
+    }
+
+    fn int_to_ptr(&self, b: &mut FunctionBuilder, addr: Value) -> Value {
+        Self::resize(b, addr, POINTER_TYPE, false)
+    }
+
+    fn scaled_offset(&self, b: &mut FunctionBuilder, index: Value, scale: i64) -> Value {
+        let index = Self::resize(b, index, POINTER_TYPE, true);
+        b.ins().imul_imm(index, scale)
+    }
+
+    fn offset(&self, b: &mut FunctionBuilder, ptr: Value, offset: Value) -> Value {
+        b.ins().iadd(ptr, offset)
+    }
+}
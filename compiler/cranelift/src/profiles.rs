@@ -0,0 +1,225 @@
+//! Option Profiles
+//!
+//! Named sets of codegen options, resolved for a target inside the bridge so
+//! the C++ driver passes a profile name and a triple instead of setting every
+//! flag itself. A profile only decides the codegen fields listed in `FIELDS`;
+//! output and selection options (IR text, tests, multi-versioning, trace
+//! hooks, DLL export) still come from `CraneliftOptions` as given.
+//!
+//! Overrides adjust single fields after the profile is applied, written as
+//! `field=value` pairs separated by commas (`"opt_level=1,frame_pointers=0"`).
+
+use std::str::FromStr;
+
+use target_lexicon::{Architecture, OperatingSystem, Triple};
+
+use crate::cpu;
+use crate::error::{BridgeError, BridgeResult};
+use crate::json;
+use crate::options::{BridgeOptions, DEFAULT_FRAME_WARN_BYTES};
+use crate::runtime_sigs;
+
+/// Profile names accepted by `apply`.
+pub const PROFILES: &[&str] = &["debug", "release", "small", "jit"];
+
+/// Option fields a profile sets, which are also the override keys.
+pub const FIELDS: &[&str] = &[
+    "opt_level",
+    "debug_info",
+    "emit_inline_metadata",
+    "fp_contract",
+    "fp_reassociate",
+    "nan_canonicalization",
+    "frame_warn_bytes",
+    "frame_pointers",
+    "trap_abort",
+    "strict_runtime_calls",
+    "runtime_prefix",
+    "cpu",
+    "target_features",
+    "const_pool_threshold",
+];
+
+/// Whether `key` names a profile field: one of `FIELDS` or a Cranelift flag
+/// (`flag.<name>`, see `cpu`).
+pub fn is_field(key: &str) -> bool {
+    FIELDS.contains(&key) || key.starts_with(cpu::FLAG_PREFIX)
+}
+
+/// The target named by `triple`, or the host when it is empty.
+pub fn target(triple: &str) -> BridgeResult<Triple> {
+    if triple.is_empty() {
+        return Ok(Triple::host());
+    }
+    Triple::from_str(triple)
+        .map_err(|e| BridgeError::InvalidTarget(format!("'{}': {}", triple, e)))
+}
+
+/// Replace the profile fields of `opts` with profile `name` for `triple`.
+///
+/// - `debug`: no optimization, debug info, frame pointers, traps reported as
+///   panics and runtime calls checked against the manifest
+/// - `release`: full optimization and inline metadata for downstream crates
+/// - `small`: optimization without inline metadata
+/// - `jit`: light optimization, frame pointers for profilers, traps as panics
+///   and bare runtime names, which in-process linking resolves to the host
+pub fn apply(opts: &mut BridgeOptions, name: &str, triple: &Triple) -> BridgeResult<()> {
+    let debug = name == "debug";
+    let jit = name == "jit";
+    let (opt_level, inline_metadata) = match name {
+        "debug" => (0, false),
+        "release" => (3, true),
+        "small" => (1, false),
+        "jit" => (1, false),
+        _ => {
+            return Err(BridgeError::InvalidOptions(format!(
+                "unknown profile '{}' (expected one of: {})",
+                name,
+                PROFILES.join(", ")
+            )));
+        }
+    };
+    // The Apple arm64 ABI requires a valid frame pointer in every function
+    let apple_arm64 = matches!(triple.architecture, Architecture::Aarch64(_))
+        && matches!(
+            triple.operating_system,
+            OperatingSystem::Darwin(_) | OperatingSystem::MacOSX(_) | OperatingSystem::IOS(_)
+        );
+
+    opts.opt_level = opt_level;
+    opts.debug_info = debug;
+    opts.emit_inline_metadata = inline_metadata;
+    opts.fp_contract = false;
+    opts.fp_reassociate = false;
+    opts.nan_canonicalization = false;
+    opts.frame_warn_bytes = 0;
+    opts.frame_pointers = debug || jit || apple_arm64;
+    opts.trap_abort = debug || jit;
+    opts.strict_runtime_calls = debug;
+    opts.runtime_prefix = if jit { Some(false) } else { None };
+    opts.cpu = String::new();
+    opts.target_features = String::new();
+    opts.cranelift_flags.clear();
+    opts.const_pool_threshold = 0;
+    Ok(())
+}
+
+/// Apply comma-separated `field=value` overrides to `opts`. Entries starting
+/// with `+` or `-` continue a preceding `target_features` value, so a feature
+/// string keeps its commas: `target_features=+avx2,+bmi2,opt_level=2`.
+pub fn apply_overrides(opts: &mut BridgeOptions, overrides: &str) -> BridgeResult<()> {
+    let mut in_features = false;
+    for entry in overrides.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        if in_features && (entry.starts_with('+') || entry.starts_with('-')) {
+            opts.target_features.push(',');
+            opts.target_features.push_str(entry);
+            continue;
+        }
+        let invalid = |what: &str| {
+            BridgeError::InvalidOptions(format!("override '{}': {}", entry, what))
+        };
+        let (field, value) = entry
+            .split_once('=')
+            .map(|(f, v)| (f.trim(), v.trim()))
+            .ok_or_else(|| invalid("expected field=value"))?;
+        set_field(opts, field, value).map_err(|e| invalid(&e))?;
+        in_features = field == "target_features";
+    }
+    Ok(())
+}
+
+/// Set one profile field of `opts` from its override syntax. A `cpu` class,
+/// target feature or Cranelift flag is only checked when the ISA is built.
+pub fn set_field(opts: &mut BridgeOptions, field: &str, value: &str) -> Result<(), String> {
+    if let Some(name) = field.strip_prefix(cpu::FLAG_PREFIX) {
+        if name.is_empty() {
+            return Err("expected a Cranelift setting name".to_string());
+        }
+        opts.cranelift_flags.retain(|(n, _)| n != name);
+        opts.cranelift_flags.push((name.to_string(), value.to_string()));
+        return Ok(());
+    }
+    let flag = || match value {
+        "1" | "true" | "on" => Ok(true),
+        "0" | "false" | "off" => Ok(false),
+        _ => Err("expected a boolean".to_string()),
+    };
+    match field {
+        "opt_level" => {
+            opts.opt_level = value
+                .parse()
+                .ok()
+                .filter(|l| *l <= 3)
+                .ok_or("expected 0-3")?;
+        }
+        "debug_info" => opts.debug_info = flag()?,
+        "emit_inline_metadata" => opts.emit_inline_metadata = flag()?,
+        "fp_contract" => opts.fp_contract = flag()?,
+        "fp_reassociate" => opts.fp_reassociate = flag()?,
+        "nan_canonicalization" => opts.nan_canonicalization = flag()?,
+        "frame_warn_bytes" => {
+            opts.frame_warn_bytes = value.parse().map_err(|_| "expected a byte count")?;
+        }
+        "frame_pointers" => opts.frame_pointers = flag()?,
+        "trap_abort" => opts.trap_abort = flag()?,
+        "strict_runtime_calls" => opts.strict_runtime_calls = flag()?,
+        "runtime_prefix" => {
+            opts.runtime_prefix = if value == "target" { None } else { Some(flag()?) };
+        }
+        "cpu" => opts.cpu = value.to_string(),
+        "target_features" => opts.target_features = value.to_string(),
+        "const_pool_threshold" => {
+            opts.const_pool_threshold = value.parse().map_err(|_| "expected a constant count")?;
+        }
+        _ => {
+            return Err(format!(
+                "unknown field (expected one of: {}, {}<name>)",
+                FIELDS.join(", "),
+                cpu::FLAG_PREFIX
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The profile fields of `opts` as JSON, with defaults resolved for `triple`.
+pub fn to_json(opts: &BridgeOptions, triple: &Triple) -> String {
+    let runtime_prefix = opts
+        .runtime_prefix
+        .unwrap_or_else(|| runtime_sigs::prefix_by_default(triple));
+    let frame_warn_bytes = match opts.frame_warn_bytes {
+        0 => DEFAULT_FRAME_WARN_BYTES,
+        n => n,
+    };
+    let profile = if opts.profile.is_empty() { "null".to_string() } else { json::string(&opts.profile) };
+    let cpu = cpu::class(opts);
+    let flags: Vec<_> = opts
+        .cranelift_flags
+        .iter()
+        .map(|(name, value)| format!("{}:{}", json::string(name), json::string(value)))
+        .collect();
+    format!(
+        "{{\"profile\":{},\"target\":{},\"opt_level\":{},\"debug_info\":{},\
+         \"emit_inline_metadata\":{},\"fp_contract\":{},\"fp_reassociate\":{},\
+         \"nan_canonicalization\":{},\"frame_warn_bytes\":{},\"frame_pointers\":{},\
+         \"trap_abort\":{},\"strict_runtime_calls\":{},\"runtime_prefix\":{},\
+         \"const_pool_threshold\":{},\"cpu\":{},\"flags\":{{{}}},\"target_features\":{}}}",
+        profile,
+        json::string(&triple.to_string()),
+        opts.opt_level,
+        opts.debug_info,
+        opts.emit_inline_metadata,
+        opts.fp_contract,
+        opts.fp_reassociate,
+        opts.nan_canonicalization,
+        frame_warn_bytes,
+        opts.frame_pointers,
+        opts.trap_abort,
+        opts.strict_runtime_calls,
+        runtime_prefix,
+        opts.const_pool_threshold,
+        json::string(cpu),
+        flags.join(","),
+        json::string(&opts.target_features)
+    )
+}
@@ -0,0 +1,158 @@
+//! Replay bundles
+//!
+//! When the `replay_dir` option is set, a failed translation (an error or a
+//! caught panic) is saved as a single self-contained file: the MIR the entry
+//! point received, a snapshot of the resolved options and the bridge version.
+//! The path is appended to the error message, so users can attach the file to
+//! a bug report and maintainers can re-run exactly the failing compilation.
+//!
+//! Layout (little-endian):
+//!
+//! ```text
+//! magic "TMLR" | u32 version | str bridge_version | str entry | str options
+//!   | u32 index_count | u64 func_index* | u32 module_count | bytes mir* | str error
+//! str, bytes: u32 byte length | data
+//! ```
+//!
+//! `entry` names the C API call (`compile`, `compile_cgu`, `compile_named`,
+//! `compile_lto`, `compile_multi`, `compile_parallel`, `generate_ir`,
+//! `generate_ir_cgu`, `disassemble`, `estimate`; parallel compiles replay on
+//! one thread), `options` is `BridgeOptions::snapshot` and the function
+//! indices are those of CGU calls (none otherwise); named compiles record the
+//! indices the names resolved to.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::options::BridgeOptions;
+
+pub const MAGIC: &[u8; 4] = b"TMLR";
+pub const VERSION: u32 = 1;
+
+/// Version recorded in bundles: crate version and the Cranelift it wraps.
+pub const BRIDGE_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), " (cranelift-0.128)");
+
+/// File extension of replay bundles.
+pub const EXTENSION: &str = "tmlreplay";
+
+/// What an entry point was asked to translate.
+pub struct Input<'a> {
+    pub entry: &'a str,
+    pub modules: &'a [&'a [u8]],
+    pub func_indices: Option<&'a [usize]>,
+}
+
+/// Serialize a failed translation of `input` under `opts`.
+pub fn encode(input: &Input, opts: &BridgeOptions, error: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    write_bytes(&mut out, BRIDGE_VERSION.as_bytes());
+    write_bytes(&mut out, input.entry.as_bytes());
+    write_bytes(&mut out, opts.snapshot().as_bytes());
+    let indices = input.func_indices.unwrap_or(&[]);
+    out.extend_from_slice(&(indices.len() as u32).to_le_bytes());
+    for &i in indices {
+        out.extend_from_slice(&(i as u64).to_le_bytes());
+    }
+    out.extend_from_slice(&(input.modules.len() as u32).to_le_bytes());
+    for module in input.modules {
+        write_bytes(&mut out, module);
+    }
+    write_bytes(&mut out, error.as_bytes());
+    out
+}
+
+fn write_bytes(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+}
+
+/// Write a bundle into `dir` (the system temp directory when empty) and
+/// return its path.
+pub fn write(dir: &str, input: &Input, opts: &BridgeOptions, error: &str) -> std::io::Result<PathBuf> {
+    // Several CGUs can fail at once in one process
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let dir = if dir.is_empty() { std::env::temp_dir() } else { Path::new(dir).to_path_buf() };
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "tml-{}-{}-{}.{}",
+        input.entry,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed),
+        EXTENSION
+    ));
+    std::fs::write(&path, encode(input, opts, error))?;
+    Ok(path)
+}
+
+/// A decoded replay bundle.
+#[cfg(any(test, feature = "dev-tools"))]
+#[derive(Debug)]
+pub struct Bundle {
+    pub bridge_version: String,
+    pub entry: String,
+    pub options: BridgeOptions,
+    pub func_indices: Option<Vec<usize>>,
+    pub modules: Vec<Vec<u8>>,
+    pub error: String,
+}
+
+/// Parse a bundle written by `encode`. None if it is malformed.
+#[cfg(any(test, feature = "dev-tools"))]
+pub fn decode(data: &[u8]) -> Option<Bundle> {
+    let mut r = Reader { data, pos: 0 };
+    if r.take(4)? != MAGIC || r.u32()? != VERSION {
+        return None;
+    }
+    let bridge_version = r.str()?;
+    let entry = r.str()?;
+    let options = BridgeOptions::from_snapshot(&r.str()?)?;
+    let index_count = r.u32()?;
+    let mut indices = Vec::new();
+    for _ in 0..index_count {
+        indices.push(u64::from_le_bytes(r.take(8)?.try_into().unwrap()) as usize);
+    }
+    let module_count = r.u32()?;
+    let mut modules = Vec::new();
+    for _ in 0..module_count {
+        modules.push(r.bytes()?.to_vec());
+    }
+    let error = r.str()?;
+    Some(Bundle {
+        bridge_version,
+        entry,
+        options,
+        func_indices: (index_count > 0).then_some(indices),
+        modules,
+        error,
+    })
+}
+
+#[cfg(any(test, feature = "dev-tools"))]
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+#[cfg(any(test, feature = "dev-tools"))]
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos + n)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn str(&mut self) -> Option<String> {
+        String::from_utf8(self.bytes()?.to_vec()).ok()
+    }
+}
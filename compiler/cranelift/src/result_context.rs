@@ -0,0 +1,99 @@
+//! Result Contexts
+//!
+//! Every `CraneliftResult` owns its buffers until `cranelift_free_result`,
+//! and a driver handling thousands of results per build leaks the ones its
+//! early exits skip. A result context collects them instead: a call whose
+//! options name a context (`result_context`) gives the buffers of its result
+//! to that context, and `cranelift_context_free_results` frees everything
+//! the context owns at once. Results can still be freed one by one before
+//! that; results the context has freed must not be used again.
+//!
+//! Contexts handed to C++ are registered by address, like cancel tokens: an
+//! unknown or freed handle is an error in the options and a no-op elsewhere.
+//! The options are resolved deep inside each entry point, so the context
+//! reaches `write_result`, which hands the finished result over, through a
+//! per-thread slot. Results that fail before their options are read (null
+//! MIR, bad options) stay with the caller.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::error::{BridgeError, BridgeResult};
+
+/// Opaque C handle of a result context.
+#[repr(C)]
+pub struct CraneliftContext {
+    _private: [u8; 0],
+}
+
+/// A live context, identified by its handle address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResultContext(pub usize);
+
+/// Contexts handed out and not freed, by handle address. The boxes only
+/// give each context a distinct address.
+static LIVE_CONTEXTS: Mutex<BTreeMap<usize, Box<u8>>> = Mutex::new(BTreeMap::new());
+
+thread_local! {
+    /// Context of the call in progress on this thread, set once its
+    /// options are resolved.
+    static CURRENT: Cell<Option<ResultContext>> = const { Cell::new(None) };
+}
+
+fn live_contexts() -> MutexGuard<'static, BTreeMap<usize, Box<u8>>> {
+    LIVE_CONTEXTS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Register a new context and return its handle.
+pub fn new_handle() -> *mut CraneliftContext {
+    let context = Box::new(0u8);
+    let handle = &*context as *const u8 as *mut CraneliftContext;
+    live_contexts().insert(handle as usize, context);
+    handle
+}
+
+/// The context behind a live handle.
+pub fn lookup(handle: *const CraneliftContext) -> Option<ResultContext> {
+    live_contexts().contains_key(&(handle as usize)).then_some(ResultContext(handle as usize))
+}
+
+/// Resolve an options handle; null means none.
+pub fn from_handle(name: &str, handle: *const CraneliftContext) -> BridgeResult<Option<ResultContext>> {
+    if handle.is_null() {
+        return Ok(None);
+    }
+    lookup(handle).map(Some).ok_or_else(|| {
+        BridgeError::FfiMisuse(format!("{}: {:#x} is not a live result context", name, handle as usize))
+    })
+}
+
+/// Run `f` with `context` if it is still live, holding the registry lock so
+/// the context cannot be freed meanwhile.
+pub fn with_live(context: ResultContext, f: impl FnOnce()) {
+    let contexts = live_contexts();
+    if contexts.contains_key(&context.0) {
+        f();
+    }
+}
+
+/// Unregister a handle, running `f` with its context first; unknown handles
+/// are ignored.
+pub fn free_handle(handle: *const CraneliftContext, f: impl FnOnce(ResultContext)) {
+    let mut contexts = live_contexts();
+    if contexts.contains_key(&(handle as usize)) {
+        f(ResultContext(handle as usize));
+        contexts.remove(&(handle as usize));
+    }
+}
+
+/// Make `context` the owner of the result of the call in progress on this
+/// thread.
+pub fn set_current(context: Option<ResultContext>) {
+    CURRENT.with(|current| current.set(context));
+}
+
+/// The owner set for the call in progress, clearing it.
+pub fn take_current() -> Option<ResultContext> {
+    CURRENT.with(Cell::take)
+}
@@ -0,0 +1,100 @@
+//! Runtime Signatures
+//!
+//! Signatures of the C runtime functions generated code calls, parsed from
+//! the shared manifest `compiler/runtime/core/runtime_signatures.def`. The
+//! C++ test suite checks the same manifest against `essential.h`, so the
+//! imports declared here cannot silently drift from the runtime.
+
+use std::sync::OnceLock;
+
+use cranelift_codegen::ir::{Type, types};
+
+use crate::types::POINTER_TYPE;
+
+const MANIFEST: &str = include_str!("../../runtime/core/runtime_signatures.def");
+
+/// Prefix of the aliases the runtime exports for `TML_RT_SHIM` entries.
+pub const SHIM_PREFIX: &str = "tml_rt_";
+
+#[derive(Debug, Clone)]
+pub struct RuntimeSig {
+    pub name: &'static str,
+    pub params: Vec<Type>,
+    /// None for `void`
+    pub ret: Option<Type>,
+    /// Also exported as `SHIM_PREFIX` + name (`TML_RT_SHIM`)
+    pub shim: bool,
+}
+
+impl RuntimeSig {
+    /// Symbol to import: the prefixed alias when `prefixed` and one exists.
+    pub fn symbol(&self, prefixed: bool) -> String {
+        if prefixed && self.shim {
+            format!("{}{}", SHIM_PREFIX, self.name)
+        } else {
+            self.name.to_string()
+        }
+    }
+}
+
+/// Whether runtime imports are prefixed when the options leave it to the
+/// target. ELF and Mach-O resolve symbols in one flat namespace, where a
+/// `print` or `panic` from libc, another shared library or user C code can
+/// silently take the runtime's place. COFF imports are bound per DLL and a
+/// clash is a link error, so Windows keeps the bare names.
+pub fn prefix_by_default(triple: &target_lexicon::Triple) -> bool {
+    triple.binary_format != target_lexicon::BinaryFormat::Coff
+}
+
+/// Every manifest entry, in manifest order.
+pub fn all() -> &'static [RuntimeSig] {
+    static SIGS: OnceLock<Vec<RuntimeSig>> = OnceLock::new();
+    SIGS.get_or_init(|| {
+        MANIFEST
+            .lines()
+            .filter_map(|line| {
+                parse_entry(line).unwrap_or_else(|| panic!("malformed runtime manifest line: {}", line))
+            })
+            .collect()
+    })
+}
+
+/// Parse one manifest line: Some(None) for comments and blank lines.
+fn parse_entry(line: &'static str) -> Option<Option<RuntimeSig>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with("//") {
+        return Some(None);
+    }
+    let shim = line.starts_with("TML_RT_SHIM(");
+    let body = line
+        .strip_prefix("TML_RT_FN(")
+        .or_else(|| line.strip_prefix("TML_RT_SHIM("))
+        .or_else(|| line.strip_prefix("TML_RT_LEGACY("))?
+        .strip_suffix("))")?;
+    let (head, params) = body.split_once('(')?;
+    let mut head = head.split(',').map(str::trim);
+    let name = head.next()?;
+    let ret = match head.next()? {
+        "void" => None,
+        other => Some(abi_type(other)?),
+    };
+    let params = params
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(abi_type)
+        .collect::<Option<Vec<_>>>()?;
+    Some(Some(RuntimeSig { name, params, ret, shim }))
+}
+
+fn abi_type(class: &str) -> Option<Type> {
+    Some(match class {
+        "i8" => types::I8,
+        "i32" => types::I32,
+        "i64" => types::I64,
+        "f32" => types::F32,
+        "f64" => types::F64,
+        "ptr" => POINTER_TYPE,
+        _ => return None,
+    })
+}
@@ -0,0 +1,390 @@
+//! Self Test
+//!
+//! `cranelift_self_test` runs a small built-in suite so the driver or the
+//! installer can detect a broken toolchain/ISA combination on a user's
+//! machine at startup, before the first real compilation fails:
+//!
+//! - `isa`: the ISA for the options (target, CPU class, Cranelift flags)
+//!   builds
+//! - `mir`: an embedded MIR module survives a binary round trip
+//! - `object`: the module compiles and the object has the target's magic
+//! - `execute`: a compiled function runs in process and returns the
+//!   expected value (x86-64 hosts compiling for themselves with the host's
+//!   own extensions; skipped elsewhere)
+//!
+//! The report is JSON: `{"passed", "target", "cpu", "checks": [{"name",
+//! "status", "detail"}]}` with status `pass`, `fail` or `skip`.
+//!
+//! `supported_targets` runs the suite once per candidate triple to tell which
+//! targets this build can compile for.
+
+use target_lexicon::{BinaryFormat, Triple};
+
+use crate::cpu;
+use crate::json;
+use crate::mir_reader::MirBinaryReader;
+use crate::mir_types::*;
+use crate::mir_writer::MirBinaryWriter;
+use crate::options::BridgeOptions;
+use crate::translate::ModuleTranslator;
+
+/// MIR name of the function the `execute` check runs.
+const SUM_FUNCTION: &str = "self_test_sum";
+
+/// Arguments of the `execute` run and the value `SUM_FUNCTION` returns
+/// for them: `3 * (0 + 1 + ... + 9)`.
+const SUM_ARGS: (i64, i64) = (3, 10);
+const SUM_EXPECTED: i64 = 135;
+
+/// Triples `supported_targets` tries besides the host's: the targets the
+/// TML driver knows, and one per other architecture Cranelift has a
+/// backend for.
+const CANDIDATE_TARGETS: &[&str] = &[
+    "x86_64-unknown-linux-gnu",
+    "x86_64-unknown-linux-musl",
+    "x86_64-pc-windows-msvc",
+    "x86_64-apple-darwin",
+    "aarch64-unknown-linux-gnu",
+    "aarch64-unknown-linux-musl",
+    "aarch64-apple-darwin",
+    "riscv64gc-unknown-linux-gnu",
+    "s390x-unknown-linux-gnu",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    Fail,
+    Skip,
+}
+
+impl Status {
+    fn as_str(self) -> &'static str {
+        match self {
+            Status::Pass => "pass",
+            Status::Fail => "fail",
+            Status::Skip => "skip",
+        }
+    }
+}
+
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+pub struct Report {
+    pub target: String,
+    pub cpu: String,
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// No check failed (skipped checks do not count).
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.status != Status::Fail)
+    }
+
+    /// The first failed check as `name: detail`.
+    pub fn first_failure(&self) -> Option<String> {
+        self.checks
+            .iter()
+            .find(|c| c.status == Status::Fail)
+            .map(|c| format!("{}: {}", c.name, c.detail))
+    }
+
+    pub fn to_json(&self) -> String {
+        let checks: Vec<String> = self
+            .checks
+            .iter()
+            .map(|c| {
+                format!(
+                    "{{\"name\":{},\"status\":{},\"detail\":{}}}",
+                    json::string(c.name),
+                    json::string(c.status.as_str()),
+                    json::string(&c.detail)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"passed\":{},\"target\":{},\"cpu\":{},\"checks\":[{}]}}",
+            self.passed(),
+            json::string(&self.target),
+            json::string(&self.cpu),
+            checks.join(",")
+        )
+    }
+}
+
+/// Run every check under `opts`. A check whose prerequisite failed is
+/// skipped; a panic fails the check it happened in.
+pub fn run(opts: &BridgeOptions) -> Report {
+    let mut report = Report {
+        target: if opts.target_triple.is_empty() { Triple::host().to_string() } else { opts.target_triple.clone() },
+        cpu: cpu::class(opts).to_string(),
+        checks: Vec::new(),
+    };
+    let mut triple = None;
+    let isa_ok = check(&mut report, "isa", || {
+        let isa = cpu::isa(opts).map_err(|e| e.to_string())?;
+        triple = Some(isa.triple().clone());
+        Ok(format!("{} ({})", isa.triple(), isa.name()))
+    });
+
+    let mut module = None;
+    let mir_ok = check(&mut report, "mir", || {
+        let bytes = MirBinaryWriter::new().write_module(&sum_module());
+        let read = MirBinaryReader::new(&bytes).read_module().map_err(|e| e.to_string())?;
+        if read.functions.len() != 1 || read.functions[0].name != SUM_FUNCTION {
+            return Err("module changed in the round trip".to_string());
+        }
+        module = Some(read);
+        Ok(format!("{} bytes", bytes.len()))
+    });
+
+    let mut code = None;
+    match (isa_ok && mir_ok, module.as_ref(), triple.as_ref()) {
+        (true, Some(module), Some(triple)) => {
+            check(&mut report, "object", || {
+                let mut translator = ModuleTranslator::new(opts).map_err(|e| e.to_string())?;
+                translator.capture_code(SUM_FUNCTION);
+                translator.translate_module(module, None).map_err(|e| e.to_string())?;
+                code = translator.captured_code.take();
+                let object = translator.finish().map_err(|e| e.to_string())?.object;
+                check_magic(&object, triple)?;
+                Ok(format!("{} bytes, {}", object.len(), triple.binary_format))
+            });
+        }
+        _ => skip(&mut report, "object", "needs the isa and mir checks"),
+    }
+
+    match execute_skip_reason(opts, code.as_deref()) {
+        Some(reason) => skip(&mut report, "execute", reason),
+        None => {
+            let code = code.unwrap_or_default();
+            check(&mut report, "execute", || {
+                let (x, n) = SUM_ARGS;
+                let got = exec::call_i64_i64(&code, x, n)?;
+                if got != SUM_EXPECTED {
+                    return Err(format!("{}({}, {}) returned {}, expected {}", SUM_FUNCTION, x, n, got, SUM_EXPECTED));
+                }
+                Ok(format!("{}({}, {}) = {}", SUM_FUNCTION, x, n, got))
+            });
+        }
+    }
+    report
+}
+
+/// Run check `name`, recording its outcome; true if it passed.
+fn check(report: &mut Report, name: &'static str, f: impl FnOnce() -> Result<String, String>) -> bool {
+    let (status, detail) = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(Ok(detail)) => (Status::Pass, detail),
+        Ok(Err(detail)) => (Status::Fail, detail),
+        Err(payload) => (Status::Fail, format!("panic: {}", crate::panic_message(payload))),
+    };
+    report.checks.push(Check { name, status, detail });
+    status == Status::Pass
+}
+
+fn skip(report: &mut Report, name: &'static str, reason: &str) {
+    report.checks.push(Check { name, status: Status::Skip, detail: reason.to_string() });
+}
+
+/// Why the compiled code cannot be run here, if it cannot.
+fn execute_skip_reason(opts: &BridgeOptions, code: Option<&[u8]>) -> Option<&'static str> {
+    if !cfg!(all(target_arch = "x86_64", any(unix, windows))) {
+        // Other architectures need an instruction cache flush first
+        return Some("in-process execution is only checked on x86-64 hosts");
+    }
+    if !cpu::is_host(&opts.target_triple) {
+        return Some("the code is for another target");
+    }
+    let class = cpu::class(opts);
+    if !(class == cpu::NATIVE || class == cpu::BASELINE) {
+        // A preset can enable extensions this host lacks
+        return Some("the cpu class may not match this host");
+    }
+    if opts.target_features.split(',').any(|f| f.trim().starts_with('+')) {
+        return Some("the target features may not match this host");
+    }
+    if code.is_none() {
+        return Some("needs the object check");
+    }
+    None
+}
+
+/// The triples this build compiles for: the host's, then each of
+/// `CANDIDATE_TARGETS` whose checks pass under default options.
+pub fn supported_targets() -> Vec<String> {
+    let host = Triple::host().to_string();
+    std::iter::once(host.as_str())
+        .chain(CANDIDATE_TARGETS.iter().copied().filter(|t| *t != host))
+        .filter(|triple| {
+            let opts = BridgeOptions { target_triple: triple.to_string(), ..BridgeOptions::default() };
+            run(&opts).passed()
+        })
+        .map(String::from)
+        .collect()
+}
+
+/// Check that `object` starts with the magic of `triple`'s object format.
+fn check_magic(object: &[u8], triple: &Triple) -> Result<(), String> {
+    let expected: &[u8] = match triple.binary_format {
+        BinaryFormat::Elf => b"\x7fELF",
+        BinaryFormat::Macho => &[0xCF, 0xFA, 0xED, 0xFE],
+        BinaryFormat::Coff => match triple.architecture {
+            target_lexicon::Architecture::X86_64 => &[0x64, 0x86],
+            target_lexicon::Architecture::Aarch64(_) => &[0x64, 0xAA],
+            _ => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+    if object.starts_with(expected) {
+        Ok(())
+    } else {
+        Err(format!(
+            "object starts with {:02x?}, expected {} magic {:02x?}",
+            &object[..object.len().min(4)],
+            triple.binary_format,
+            expected
+        ))
+    }
+}
+
+/// `fn self_test_sum(x: i64, n: i64) -> i64`: the sum of `x * i` for
+/// `i` in `0..n`, a loop with phis so block parameters are exercised.
+fn sum_module() -> Module {
+    let i64_ty = || MirType::Primitive(PrimitiveType::I64);
+    let v = |id| Value { id };
+    let int = |result, value| InstructionData {
+        result,
+        inst: Instruction::Constant(Constant::Int { value, bit_width: 64, is_signed: true }),
+        loc: SourceLoc::default(),
+    };
+    let binary = |result, op, left, right| InstructionData {
+        result,
+        inst: Instruction::Binary { op, left: v(left), right: v(right) },
+        loc: SourceLoc::default(),
+    };
+    let phi = |result, entry, latch| InstructionData {
+        result,
+        inst: Instruction::Phi { incoming: vec![(v(entry), 0), (v(latch), 2)] },
+        loc: SourceLoc::default(),
+    };
+    let block = |id, instructions, terminator| BasicBlock {
+        id,
+        name: format!("bb{}", id),
+        predecessors: Vec::new(),
+        instructions,
+        terminator: Some(terminator),
+    };
+    let param = |name: &str, value_id| FunctionParam { name: name.to_string(), ty: i64_ty(), value_id };
+
+    let function = Function {
+        name: SUM_FUNCTION.to_string(),
+        is_public: true,
+        params: vec![param("x", 0), param("n", 1)],
+        return_type: i64_ty(),
+        blocks: vec![
+            block(0, vec![int(2, 0), int(3, 1)], Terminator::Branch { target: 1 }),
+            block(
+                1,
+                vec![phi(4, 2, 8), phi(5, 2, 9), binary(6, BinOp::Lt, 5, 1)],
+                Terminator::CondBranch { condition: v(6), true_block: 2, false_block: 3 },
+            ),
+            block(
+                2,
+                vec![binary(7, BinOp::Mul, 0, 5), binary(8, BinOp::Add, 4, 7), binary(9, BinOp::Add, 5, 3)],
+                Terminator::Branch { target: 1 },
+            ),
+            block(3, Vec::new(), Terminator::Return { value: Some(v(4)) }),
+        ],
+        next_value_id: 10,
+        next_block_id: 4,
+    };
+    Module {
+        name: "self_test".to_string(),
+        structs: Vec::new(),
+        enums: Vec::new(),
+        functions: vec![function],
+        constants: Vec::new(),
+    }
+}
+
+/// Running machine code in process: copy it into fresh pages, make them
+/// executable and call them with the platform C calling convention, which
+/// is what the translator emits.
+mod exec {
+    type SumFn = extern "C" fn(i64, i64) -> i64;
+
+    #[cfg(all(target_arch = "x86_64", unix))]
+    pub fn call_i64_i64(code: &[u8], x: i64, n: i64) -> Result<i64, String> {
+        use std::io::Error;
+        let len = code.len().max(1);
+        unsafe {
+            let page = libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANON,
+                -1,
+                0,
+            );
+            if page == libc::MAP_FAILED {
+                return Err(format!("mmap failed: {}", Error::last_os_error()));
+            }
+            std::ptr::copy_nonoverlapping(code.as_ptr(), page as *mut u8, code.len());
+            let result = if libc::mprotect(page, len, libc::PROT_READ | libc::PROT_EXEC) == 0 {
+                let f: SumFn = std::mem::transmute(page);
+                Ok(f(x, n))
+            } else {
+                Err(format!("mprotect failed: {}", Error::last_os_error()))
+            };
+            libc::munmap(page, len);
+            result
+        }
+    }
+
+    #[cfg(all(target_arch = "x86_64", windows))]
+    pub fn call_i64_i64(code: &[u8], x: i64, n: i64) -> Result<i64, String> {
+        use std::ffi::c_void;
+        use std::io::Error;
+
+        const MEM_COMMIT: u32 = 0x1000;
+        const MEM_RESERVE: u32 = 0x2000;
+        const MEM_RELEASE: u32 = 0x8000;
+        const PAGE_READWRITE: u32 = 0x04;
+        const PAGE_EXECUTE_READ: u32 = 0x20;
+
+        #[link(name = "kernel32")]
+        unsafe extern "system" {
+            fn VirtualAlloc(address: *mut c_void, size: usize, kind: u32, protect: u32) -> *mut c_void;
+            fn VirtualProtect(address: *mut c_void, size: usize, protect: u32, old: *mut u32) -> i32;
+            fn VirtualFree(address: *mut c_void, size: usize, kind: u32) -> i32;
+        }
+
+        let len = code.len().max(1);
+        unsafe {
+            let page = VirtualAlloc(std::ptr::null_mut(), len, MEM_COMMIT | MEM_RESERVE, PAGE_READWRITE);
+            if page.is_null() {
+                return Err(format!("VirtualAlloc failed: {}", Error::last_os_error()));
+            }
+            std::ptr::copy_nonoverlapping(code.as_ptr(), page as *mut u8, code.len());
+            let mut old = 0;
+            let result = if VirtualProtect(page, len, PAGE_EXECUTE_READ, &mut old) != 0 {
+                let f: SumFn = std::mem::transmute(page);
+                Ok(f(x, n))
+            } else {
+                Err(format!("VirtualProtect failed: {}", Error::last_os_error()))
+            };
+            VirtualFree(page, 0, MEM_RELEASE);
+            result
+        }
+    }
+
+    #[cfg(not(all(target_arch = "x86_64", any(unix, windows))))]
+    pub fn call_i64_i64(_code: &[u8], _x: i64, _n: i64) -> Result<i64, String> {
+        Err("in-process execution is not supported on this host".to_string())
+    }
+}
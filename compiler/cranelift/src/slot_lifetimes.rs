@@ -0,0 +1,211 @@
+//! Stack Slot Lifetimes
+//!
+//! MIR has no lifetime markers, so every alloca would otherwise get its own
+//! stack slot for the whole function. This pass infers each alloca's live
+//! range from the uses of its address (including addresses derived through
+//! GEPs, casts and selects) and lets allocas with disjoint ranges share a
+//! slot.
+//!
+//! Ranges are intervals over the blocks in translation order. Any interval
+//! overlapping a loop (a back edge `B → H`) is widened to the whole loop,
+//! so values carried around the back edge stay live. Addresses that are
+//! stored to memory, returned, merged by a phi, put into an aggregate or
+//! passed to a call may outlive any use we can see (a load reading them back
+//! is not tracked), so those allocas keep a private slot. Only the runtime
+//! memory functions in `BORROWING_CALLS` are known to drop the address when
+//! they return.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::mir_types::*;
+use crate::types as ty;
+use crate::validate::successors;
+
+/// Runtime functions that use a pointer argument only during the call.
+const BORROWING_CALLS: [&str; 6] = ["mem_copy", "mem_move", "mem_set", "mem_zero", "mem_compare", "mem_eq"];
+
+/// Slot assignment for the allocas of one function.
+#[derive(Debug, Default)]
+pub struct SlotPlan {
+    /// Alloca result → shared slot group
+    pub group_of: HashMap<ValueId, usize>,
+    /// Size in bytes of each group (largest member)
+    pub group_sizes: Vec<u32>,
+    /// Member alloca names of each group, in assignment order
+    pub group_names: Vec<Vec<String>>,
+}
+
+fn touch(intervals: &mut HashMap<ValueId, Interval>, alloca: ValueId, at: usize) {
+    let iv = intervals.entry(alloca).or_insert(Interval { start: at, end: at });
+    iv.start = iv.start.min(at);
+    iv.end = iv.end.max(at);
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Interval {
+    start: usize,
+    end: usize,
+}
+
+impl Interval {
+    fn overlaps(&self, lo: usize, hi: usize) -> bool {
+        self.start <= hi && lo <= self.end
+    }
+}
+
+/// Plan slot sharing for the allocas of `func`, skipping `excluded` ones
+/// (promoted to SSA variables, so they need no slot).
+pub fn plan(func: &Function, excluded: &HashSet<ValueId>) -> SlotPlan {
+    // Linear positions: one per instruction and one per terminator
+    let mut block_range: HashMap<u32, (usize, usize)> = HashMap::new();
+    let mut pos = 0;
+    for block in &func.blocks {
+        let start = pos;
+        pos += block.instructions.len() + 1;
+        block_range.insert(block.id, (start, pos - 1));
+    }
+    let whole = Interval { start: 0, end: pos.saturating_sub(1) };
+
+    let mut allocas: Vec<(ValueId, String, u32)> = Vec::new();
+    // Value → allocas whose address it may carry
+    let mut roots: HashMap<ValueId, Vec<ValueId>> = HashMap::new();
+    let mut intervals: HashMap<ValueId, Interval> = HashMap::new();
+    let mut pinned: HashSet<ValueId> = HashSet::new();
+
+    let mut pos = 0;
+    for block in &func.blocks {
+        for inst in &block.instructions {
+            if let Instruction::Alloca { name, alloc_type } = &inst.inst {
+                if !excluded.contains(&inst.result) {
+                    allocas.push((inst.result, name.clone(), ty::type_size(alloc_type)));
+                    roots.insert(inst.result, vec![inst.result]);
+                }
+                pos += 1;
+                continue;
+            }
+
+            let mut used: Vec<ValueId> = Vec::new();
+            inst.inst.for_each_operand(|v| {
+                if let Some(r) = roots.get(&v.id) {
+                    used.extend(r);
+                }
+            });
+            for &a in &used {
+                touch(&mut intervals, a, pos);
+            }
+            match &inst.inst {
+                Instruction::Store { value, .. } => {
+                    // The address itself is written to memory
+                    if let Some(r) = roots.get(&value.id) {
+                        pinned.extend(r);
+                    }
+                }
+                Instruction::Phi { .. }
+                | Instruction::InsertValue { .. }
+                | Instruction::MethodCall { .. }
+                | Instruction::StructInit { .. }
+                | Instruction::EnumInit { .. }
+                | Instruction::TupleInit { .. }
+                | Instruction::ArrayInit { .. }
+                | Instruction::ArrayFill { .. }
+                | Instruction::ClosureInit { .. }
+                | Instruction::Await { .. } => pinned.extend(&used),
+                Instruction::Call { func_name, .. } if !BORROWING_CALLS.contains(&func_name.as_str()) => {
+                    pinned.extend(&used);
+                }
+                // Loaded data is not an address into the slot
+                Instruction::Load { .. } => {}
+                _ if !used.is_empty() => {
+                    used.sort_unstable();
+                    used.dedup();
+                    roots.insert(inst.result, used);
+                }
+                _ => {}
+            }
+            pos += 1;
+        }
+        if let Some(term) = &block.terminator {
+            term.for_each_operand(|v| {
+                if let Some(r) = roots.get(&v.id) {
+                    for &a in r {
+                        touch(&mut intervals, a, pos);
+                    }
+                    if matches!(term, Terminator::Return { .. }) {
+                        pinned.extend(r);
+                    }
+                }
+            });
+        }
+        pos += 1;
+    }
+
+    // Widen intervals over loops until nothing changes
+    let back_edges: Vec<(usize, usize)> = func
+        .blocks
+        .iter()
+        .filter_map(|b| Some((b, b.terminator.as_ref()?)))
+        .flat_map(|(b, term)| {
+            let (_, b_end) = block_range[&b.id];
+            successors(term)
+                .into_iter()
+                .filter_map(|s| block_range.get(&s).map(|&(h_start, _)| (h_start, b_end)))
+                .filter(|&(h_start, b_end)| h_start <= b_end)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    for iv in intervals.values_mut() {
+        loop {
+            let mut changed = false;
+            for &(lo, hi) in &back_edges {
+                if iv.overlaps(lo, hi) && (iv.start > lo || iv.end < hi) {
+                    iv.start = iv.start.min(lo);
+                    iv.end = iv.end.max(hi);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+    for a in &pinned {
+        intervals.insert(*a, whole);
+    }
+
+    // Greedy assignment in order of first use; unused allocas share freely
+    let mut order: Vec<(ValueId, String, u32, Interval)> = allocas
+        .into_iter()
+        .map(|(id, name, size)| {
+            let iv = intervals.get(&id).copied().unwrap_or(Interval { start: 0, end: 0 });
+            (id, name, size, iv)
+        })
+        .collect();
+    order.sort_by_key(|(id, _, _, iv)| (iv.start, *id));
+
+    let mut plan = SlotPlan::default();
+    // Group → end of its current occupant
+    let mut busy_until: Vec<usize> = Vec::new();
+    for (id, name, size, iv) in order {
+        let free = (0..busy_until.len())
+            .filter(|&g| busy_until[g] < iv.start)
+            // Prefer the smallest group that already fits, else the largest
+            .min_by_key(|&g| {
+                let fits = plan.group_sizes[g] >= size;
+                (!fits, if fits { plan.group_sizes[g] } else { u32::MAX - plan.group_sizes[g] })
+            });
+        let group = match free {
+            Some(g) => g,
+            None => {
+                busy_until.push(0);
+                plan.group_sizes.push(0);
+                plan.group_names.push(Vec::new());
+                busy_until.len() - 1
+            }
+        };
+        busy_until[group] = iv.end;
+        plan.group_sizes[group] = plan.group_sizes[group].max(size);
+        plan.group_names[group].push(name);
+        plan.group_of.insert(id, group);
+    }
+    plan
+}
@@ -0,0 +1,543 @@
+//! MIR Statistics
+//!
+//! Summarizes a MIR module for `--explain-compile-time` diagnostics and for
+//! the CGU partitioner's size heuristics: instruction counts per kind, block
+//! and phi density, and the largest functions. Rendered as JSON.
+//!
+//! The report also counts float arithmetic, which is what the
+//! `nan_canonicalization` option pays for: Cranelift follows each such
+//! operation with a NaN check and a select of the canonical NaN.
+//!
+//! Method calls whose receiver type binds them to a function of the module
+//! are counted as devirtualized: the translator calls that function directly.
+//!
+//! Register pressure is estimated from MIR liveness: the peak number of live
+//! values, and the peak number live across a call (those need a callee-saved
+//! register or a spill slot). Functions beyond the x86-64 SysV register budget
+//! are listed under `register_pressure` so they can be restructured.
+//!
+//! `function_list` is the lighter per-function listing the CGU planner
+//! partitions by, so it needs no reader of its own for the binary format.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::json;
+use crate::mir_types::*;
+use crate::types as ty;
+use crate::validate;
+
+/// Number of functions listed under `largest_functions`.
+const LARGEST_FUNCTIONS: usize = 10;
+
+/// Instructions added per float operation by NaN canonicalization
+/// (`fcmp uno`, canonical NaN constant, `select`).
+const NAN_CANONICALIZATION_COST: usize = 3;
+
+/// Allocatable general-purpose registers on x86-64 (all but `rsp`).
+const ALLOCATABLE_REGS: usize = 15;
+
+/// Callee-saved general-purpose registers on x86-64 SysV
+/// (`rbx`, `rbp`, `r12`–`r15`).
+const CALLEE_SAVED_REGS: usize = 6;
+
+/// Every function of `module` in order, as JSON: index, name, whether it is
+/// public and defined here (declarations have no blocks), parameter and
+/// return types in TML notation, and block and instruction counts.
+pub fn function_list(module: &Module) -> String {
+    let functions: Vec<String> = module
+        .functions
+        .iter()
+        .enumerate()
+        .map(|(i, func)| {
+            let params: Vec<String> = func
+                .params
+                .iter()
+                .map(|p| format!("{{\"name\":{},\"type\":{}}}", json::string(&p.name), json::string(&p.ty.to_string())))
+                .collect();
+            format!(
+                "{{\"index\":{},\"name\":{},\"public\":{},\"defined\":{},\"params\":[{}],\"return\":{},\
+                 \"blocks\":{},\"instructions\":{}}}",
+                i,
+                json::string(&func.name),
+                func.is_public,
+                !func.blocks.is_empty(),
+                params.join(","),
+                json::string(&func.return_type.to_string()),
+                func.blocks.len(),
+                func.blocks.iter().map(|b| b.instructions.len()).sum::<usize>()
+            )
+        })
+        .collect();
+    format!("{{\"module\":{},\"functions\":[{}]}}", json::string(&module.name), functions.join(","))
+}
+
+/// Size summary of one function.
+#[derive(Debug, Clone)]
+pub struct FunctionStats {
+    pub name: String,
+    pub blocks: usize,
+    pub instructions: usize,
+    pub phis: usize,
+    /// Float arithmetic results (add/sub/mul/div, negation)
+    pub float_ops: usize,
+    /// Estimated stack frame: total alloca bytes, before slot sharing
+    pub frame_bytes: u32,
+    /// Instructions in the largest block
+    pub largest_block: usize,
+    /// Peak number of simultaneously live values
+    pub max_live: usize,
+    /// Peak number of values live across a call
+    pub live_across_calls: usize,
+}
+
+impl FunctionStats {
+    pub fn of(func: &Function) -> Self {
+        let instructions = func.blocks.iter().map(|b| b.instructions.len()).sum();
+        let phis = func
+            .blocks
+            .iter()
+            .flat_map(|b| &b.instructions)
+            .filter(|i| matches!(i.inst, Instruction::Phi { .. }))
+            .count();
+        let pressure = register_pressure(func);
+        Self {
+            name: func.name.clone(),
+            blocks: func.blocks.len(),
+            instructions,
+            phis,
+            float_ops: count_float_ops(func),
+            frame_bytes: func
+                .blocks
+                .iter()
+                .flat_map(|b| &b.instructions)
+                .filter_map(|i| match &i.inst {
+                    Instruction::Alloca { alloc_type, .. } => Some(ty::type_size(alloc_type)),
+                    _ => None,
+                })
+                .fold(0u32, u32::saturating_add),
+            largest_block: func
+                .blocks
+                .iter()
+                .map(|b| b.instructions.len())
+                .max()
+                .unwrap_or(0),
+            max_live: pressure.max_live,
+            live_across_calls: pressure.live_across_calls,
+        }
+    }
+
+    /// Callee-saved registers the allocator will likely clobber.
+    pub fn estimated_callee_saved(&self) -> usize {
+        self.live_across_calls.min(CALLEE_SAVED_REGS)
+    }
+
+    /// Values that cannot all stay in registers: the excess over the
+    /// register budget, or over the callee-saved set at a call.
+    pub fn estimated_spills(&self) -> usize {
+        self.max_live
+            .saturating_sub(ALLOCATABLE_REGS)
+            .max(self.live_across_calls.saturating_sub(CALLEE_SAVED_REGS))
+    }
+}
+
+#[derive(Default)]
+struct Pressure {
+    max_live: usize,
+    live_across_calls: usize,
+}
+
+/// Backward liveness over `func`. Constants are left out since Cranelift
+/// rematerializes them; phi operands are live out of their predecessor.
+fn register_pressure(func: &Function) -> Pressure {
+    let constants: HashSet<ValueId> = func
+        .blocks
+        .iter()
+        .flat_map(|b| &b.instructions)
+        .filter(|i| matches!(i.inst, Instruction::Constant(_)))
+        .map(|i| i.result)
+        .collect();
+    let index: HashMap<u32, usize> = func
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(i, b)| (b.id, i))
+        .collect();
+
+    // Phi operands flowing out of each block.
+    let mut phi_out: Vec<HashSet<ValueId>> = vec![HashSet::new(); func.blocks.len()];
+    for inst in func.blocks.iter().flat_map(|b| &b.instructions) {
+        if let Instruction::Phi { incoming } = &inst.inst {
+            for (v, pred) in incoming {
+                if let Some(&p) = index.get(pred)
+                    && !constants.contains(&v.id)
+                {
+                    phi_out[p].insert(v.id);
+                }
+            }
+        }
+    }
+
+    // Walk a block backwards from `live`, calling `at` after each step.
+    let scan = |block: &BasicBlock,
+                live: &mut HashSet<ValueId>,
+                at: &mut dyn FnMut(&HashSet<ValueId>, bool)| {
+        if let Some(term) = &block.terminator {
+            term.for_each_operand(|v| {
+                if !constants.contains(&v.id) {
+                    live.insert(v.id);
+                }
+            });
+        }
+        at(live, false);
+        for inst in block.instructions.iter().rev() {
+            live.remove(&inst.result);
+            let is_call = matches!(
+                inst.inst,
+                Instruction::Call { .. } | Instruction::MethodCall { .. }
+            );
+            // Live here, after the result is dropped: held across the call.
+            if is_call {
+                at(live, true);
+            }
+            if !matches!(inst.inst, Instruction::Phi { .. }) {
+                inst.inst.for_each_operand(|v| {
+                    if !constants.contains(&v.id) {
+                        live.insert(v.id);
+                    }
+                });
+            }
+            at(live, false);
+        }
+    };
+
+    let mut live_in: Vec<HashSet<ValueId>> = vec![HashSet::new(); func.blocks.len()];
+    let live_out = |live_in: &[HashSet<ValueId>], i: usize| {
+        let mut out = phi_out[i].clone();
+        if let Some(term) = &func.blocks[i].terminator {
+            for succ in validate::successors(term) {
+                if let Some(&s) = index.get(&succ) {
+                    out.extend(&live_in[s]);
+                }
+            }
+        }
+        out
+    };
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in (0..func.blocks.len()).rev() {
+            let mut live = live_out(&live_in, i);
+            scan(&func.blocks[i], &mut live, &mut |_, _| {});
+            if live != live_in[i] {
+                live_in[i] = live;
+                changed = true;
+            }
+        }
+    }
+
+    let mut pressure = Pressure::default();
+    for i in 0..func.blocks.len() {
+        let mut live = live_out(&live_in, i);
+        scan(&func.blocks[i], &mut live, &mut |live, across_call| {
+            if across_call {
+                pressure.live_across_calls = pressure.live_across_calls.max(live.len());
+            } else {
+                pressure.max_live = pressure.max_live.max(live.len());
+            }
+        });
+    }
+    pressure
+}
+
+fn float_type(ty: &MirType) -> bool {
+    matches!(ty, MirType::Primitive(p) if p.is_float())
+}
+
+/// Count float arithmetic in `func`. MIR values are untyped, so float-ness
+/// is inferred from typed sources (parameters, literals, casts, calls,
+/// allocas) and propagated in program order.
+fn count_float_ops(func: &Function) -> usize {
+    let mut floats: HashSet<ValueId> = func
+        .params
+        .iter()
+        .filter(|p| float_type(&p.ty))
+        .map(|p| p.value_id)
+        .collect();
+    let float_allocas: HashSet<ValueId> = func
+        .blocks
+        .iter()
+        .flat_map(|b| &b.instructions)
+        .filter(|i| {
+            matches!(&i.inst, Instruction::Alloca { alloc_type, .. } if float_type(alloc_type))
+        })
+        .map(|i| i.result)
+        .collect();
+
+    let mut count = 0;
+    for inst in func.blocks.iter().flat_map(|b| &b.instructions) {
+        let is_float = |v: &Value| floats.contains(&v.id);
+        let (produces_float, arithmetic) = match &inst.inst {
+            Instruction::Constant(Constant::Float { .. }) => (true, false),
+            Instruction::Binary { op, left, right } => {
+                let float = is_float(left) || is_float(right);
+                let arith = matches!(op, BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div);
+                (float && arith, float && arith)
+            }
+            Instruction::Unary {
+                op: UnaryOp::Neg,
+                operand,
+            } => (is_float(operand), is_float(operand)),
+            Instruction::Cast { target_type, .. } => (float_type(target_type), false),
+            Instruction::Call { return_type, .. } | Instruction::MethodCall { return_type, .. } => {
+                (float_type(return_type), false)
+            }
+            Instruction::Load { ptr } => (float_allocas.contains(&ptr.id), false),
+            Instruction::Select { true_val, .. } => (is_float(true_val), false),
+            Instruction::Phi { incoming } => (incoming.iter().any(|(v, _)| is_float(v)), false),
+            _ => (false, false),
+        };
+        if produces_float {
+            floats.insert(inst.result);
+        }
+        if arithmetic {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Aggregate statistics over a module.
+#[derive(Debug, Default)]
+pub struct MirStats {
+    pub module_name: String,
+    /// Functions with a body
+    pub functions: usize,
+    /// Body-less declarations
+    pub declarations: usize,
+    pub structs: usize,
+    pub enums: usize,
+    pub blocks: usize,
+    pub instructions: usize,
+    pub phis: usize,
+    pub float_ops: usize,
+    pub method_calls: usize,
+    /// Method calls bound to a function of the module by their receiver type
+    pub devirtualized_calls: usize,
+    /// Largest estimated frame and the function it belongs to
+    pub max_frame: Option<(String, u32)>,
+    /// Instruction kind → count (sorted for stable output)
+    pub by_kind: BTreeMap<&'static str, usize>,
+    /// Largest functions by instruction count, descending
+    pub largest: Vec<FunctionStats>,
+    /// Functions expected to spill or clobber callee-saved registers,
+    /// by estimated spills then live-across-call count, descending
+    pub register_pressure: Vec<FunctionStats>,
+}
+
+impl MirStats {
+    pub fn collect(module: &Module) -> Self {
+        let mut stats = MirStats {
+            module_name: module.name.clone(),
+            structs: module.structs.len(),
+            enums: module.enums.len(),
+            ..Default::default()
+        };
+
+        let names: HashSet<&str> = module.functions.iter().map(|f| f.name.as_str()).collect();
+        let mut per_function = Vec::new();
+        for func in &module.functions {
+            if func.blocks.is_empty() {
+                stats.declarations += 1;
+                continue;
+            }
+            stats.functions += 1;
+            for inst in func.blocks.iter().flat_map(|b| &b.instructions) {
+                *stats.by_kind.entry(inst.inst.kind_name()).or_insert(0) += 1;
+                if let Instruction::MethodCall { .. } = inst.inst {
+                    stats.method_calls += 1;
+                    if inst.inst.method_targets().iter().any(|t| names.contains(t.as_str())) {
+                        stats.devirtualized_calls += 1;
+                    }
+                }
+            }
+            let fs = FunctionStats::of(func);
+            stats.blocks += fs.blocks;
+            stats.instructions += fs.instructions;
+            stats.phis += fs.phis;
+            stats.float_ops += fs.float_ops;
+            if stats.max_frame.as_ref().is_none_or(|(_, b)| fs.frame_bytes > *b) {
+                stats.max_frame = Some((fs.name.clone(), fs.frame_bytes));
+            }
+            per_function.push(fs);
+        }
+
+        let mut pressured: Vec<FunctionStats> = per_function
+            .iter()
+            .filter(|f| f.estimated_spills() > 0 || f.estimated_callee_saved() == CALLEE_SAVED_REGS)
+            .cloned()
+            .collect();
+        pressured.sort_by(|a, b| {
+            (b.estimated_spills(), b.live_across_calls)
+                .cmp(&(a.estimated_spills(), a.live_across_calls))
+                .then(a.name.cmp(&b.name))
+        });
+        pressured.truncate(LARGEST_FUNCTIONS);
+        stats.register_pressure = pressured;
+
+        per_function.sort_by(|a, b| b.instructions.cmp(&a.instructions).then(a.name.cmp(&b.name)));
+        per_function.truncate(LARGEST_FUNCTIONS);
+        stats.largest = per_function;
+        stats
+    }
+
+    pub fn avg_blocks_per_function(&self) -> f64 {
+        ratio(self.blocks, self.functions)
+    }
+
+    pub fn avg_instructions_per_block(&self) -> f64 {
+        ratio(self.instructions, self.blocks)
+    }
+
+    /// Phis per block
+    pub fn phi_density(&self) -> f64 {
+        ratio(self.phis, self.blocks)
+    }
+
+    pub fn to_json(&self) -> String {
+        let by_kind: Vec<String> = self
+            .by_kind
+            .iter()
+            .map(|(k, v)| format!("{}:{}", json::string(k), v))
+            .collect();
+        let largest: Vec<String> = self
+            .largest
+            .iter()
+            .map(|f| {
+                format!(
+                    "{{\"name\":{},\"blocks\":{},\"instructions\":{},\"phis\":{},\
+                     \"float_ops\":{},\"frame_bytes\":{},\"largest_block\":{},\"max_live\":{}}}",
+                    json::string(&f.name),
+                    f.blocks,
+                    f.instructions,
+                    f.phis,
+                    f.float_ops,
+                    f.frame_bytes,
+                    f.largest_block,
+                    f.max_live
+                )
+            })
+            .collect();
+        let pressure: Vec<String> = self
+            .register_pressure
+            .iter()
+            .map(|f| {
+                format!(
+                    "{{\"name\":{},\"max_live\":{},\"live_across_calls\":{},\
+                     \"largest_block\":{},\"estimated_callee_saved\":{},\"estimated_spills\":{}}}",
+                    json::string(&f.name),
+                    f.max_live,
+                    f.live_across_calls,
+                    f.largest_block,
+                    f.estimated_callee_saved(),
+                    f.estimated_spills()
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"module\":{},\"functions\":{},\"declarations\":{},\"structs\":{},\"enums\":{},\
+             \"blocks\":{},\"instructions\":{},\"phis\":{},\
+             \"avg_blocks_per_function\":{},\"avg_instructions_per_block\":{},\"phi_density\":{},\
+             \"nan_canonicalization\":{{\"float_ops\":{},\"estimated_extra_instructions\":{}}},\
+             \"method_calls\":{{\"total\":{},\"devirtualized\":{}}},\"max_frame\":{},\
+             \"instructions_by_kind\":{{{}}},\"largest_functions\":[{}],\"register_pressure\":[{}]}}",
+            json::string(&self.module_name),
+            self.functions,
+            self.declarations,
+            self.structs,
+            self.enums,
+            self.blocks,
+            self.instructions,
+            self.phis,
+            json::number(self.avg_blocks_per_function()),
+            json::number(self.avg_instructions_per_block()),
+            json::number(self.phi_density()),
+            self.float_ops,
+            self.float_ops * NAN_CANONICALIZATION_COST,
+            self.method_calls,
+            self.devirtualized_calls,
+            match &self.max_frame {
+                Some((name, bytes)) => {
+                    format!("{{\"function\":{},\"bytes\":{}}}", json::string(name), bytes)
+                }
+                None => "null".to_string(),
+            },
+            by_kind.join(","),
+            largest.join(","),
+            pressure.join(",")
+        )
+    }
+
+    /// The same report as plain text, for the `cranelift-replay` tool.
+    #[cfg(feature = "dev-tools")]
+    pub fn to_text(&self) -> String {
+        let mut out = format!(
+            "module {}: {} functions, {} declarations, {} structs, {} enums\n\
+             {} blocks ({:.1} per function), {} instructions ({:.1} per block), \
+             {} phis ({:.2} per block)\n\
+             {} float ops (+{} instructions with NaN canonicalization)\n\
+             {} method calls ({} devirtualized)\n",
+            self.module_name,
+            self.functions,
+            self.declarations,
+            self.structs,
+            self.enums,
+            self.blocks,
+            self.avg_blocks_per_function(),
+            self.instructions,
+            self.avg_instructions_per_block(),
+            self.phis,
+            self.phi_density(),
+            self.float_ops,
+            self.float_ops * NAN_CANONICALIZATION_COST,
+            self.method_calls,
+            self.devirtualized_calls
+        );
+        if let Some((name, bytes)) = &self.max_frame {
+            out.push_str(&format!("largest frame: {} bytes in {}\n", bytes, name));
+        }
+        out.push_str("instructions by kind:\n");
+        for (kind, count) in &self.by_kind {
+            out.push_str(&format!("  {:<16} {}\n", kind, count));
+        }
+        out.push_str("largest functions:\n");
+        for f in &self.largest {
+            out.push_str(&format!(
+                "  {} ({} instructions, {} blocks, {} phis, frame {} bytes, max live {})\n",
+                f.name, f.instructions, f.blocks, f.phis, f.frame_bytes, f.max_live
+            ));
+        }
+        if !self.register_pressure.is_empty() {
+            out.push_str("register pressure:\n");
+            for f in &self.register_pressure {
+                out.push_str(&format!(
+                    "  {} (max live {}, {} across calls, ~{} callee-saved, ~{} spills)\n",
+                    f.name,
+                    f.max_live,
+                    f.live_across_calls,
+                    f.estimated_callee_saved(),
+                    f.estimated_spills()
+                ));
+            }
+        }
+        out
+    }
+}
+
+fn ratio(num: usize, den: usize) -> f64 {
+    if den == 0 {
+        0.0
+    } else {
+        num as f64 / den as f64
+    }
+}
@@ -0,0 +1,49 @@
+//! Streamed Object Output
+//!
+//! A compile normally returns the object in `CraneliftResult::data`, so a
+//! large module exists twice at the end: once as the bridge's in-memory
+//! object and once as the emitted bytes, which the C++ side then copies
+//! again. With `CraneliftOptions::write` set, the object is written to the
+//! callback in buffered chunks as its sections are emitted and the result
+//! carries no data.
+
+use std::ffi::c_void;
+use std::io::{self, BufWriter, Write};
+
+use cranelift_object::object::write::Object;
+
+use crate::error::{BridgeError, BridgeResult};
+
+/// `int write(void* context, const uint8_t* data, size_t len)`: consume
+/// `len` bytes, returning 0, or fail the compile by returning anything else.
+pub type CraneliftWriteFn = unsafe extern "C" fn(*mut c_void, *const u8, usize) -> i32;
+
+/// Bytes handed to the callback at a time, at most.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// A caller's write callback and the context it is called with.
+#[derive(Debug, Clone, Copy)]
+pub struct ObjectSink {
+    pub write: CraneliftWriteFn,
+    pub context: *mut c_void,
+}
+
+impl Write for ObjectSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match unsafe { (self.write)(self.context, buf.as_ptr(), buf.len()) } {
+            0 => Ok(buf.len()),
+            status => Err(io::Error::other(format!("write callback returned {}", status))),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Emit `object` through `sink`.
+pub fn write_object(object: &Object, sink: ObjectSink) -> BridgeResult<()> {
+    object
+        .write_stream(BufWriter::with_capacity(CHUNK_BYTES, sink))
+        .map_err(|e| BridgeError::Codegen(format!("failed to write object file: {}", e)))
+}
@@ -0,0 +1,110 @@
+//! Test registry section
+//!
+//! The C++ driver knows which functions carry `@test` / `@should_panic`
+//! (MIR has no decorators) and passes them in the options. The translator
+//! records the ones it defines in a `.tml.tests` custom section, so a test
+//! runner can enumerate tests straight from an object or executable.
+//!
+//! Layout (little-endian):
+//!
+//! ```text
+//! magic "TMLT" | u32 version | u32 count | entry*
+//! entry: u8 flags | str symbol | str name | str expected   (expected only if HAS_EXPECTED)
+//! str:   u32 byte length | UTF-8 bytes
+//! ```
+
+/// Section name for the registry (ELF/COFF).
+pub const TESTS_SECTION: &str = ".tml.tests";
+/// Mach-O segment/section pair for the same data.
+pub const TESTS_SECTION_MACHO: (&str, &str) = ("__TML", "__tml_tests");
+
+pub const MAGIC: &[u8; 4] = b"TMLT";
+pub const VERSION: u32 = 1;
+
+/// Entry flag: the test passes only if it panics.
+pub const SHOULD_PANIC: u8 = 1;
+/// Entry flag: an expected panic message substring follows.
+pub const HAS_EXPECTED: u8 = 2;
+
+/// A test function as described by the driver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestEntry {
+    /// MIR function name
+    pub function: String,
+    /// Display name
+    pub name: String,
+    pub should_panic: bool,
+    /// Substring the panic message must contain
+    pub expected_panic: Option<String>,
+}
+
+/// Serialize `(symbol, entry)` pairs into the section payload.
+pub fn encode<'a>(entries: impl IntoIterator<Item = (String, &'a TestEntry)>) -> Vec<u8> {
+    let entries: Vec<_> = entries.into_iter().collect();
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    for (symbol, entry) in entries {
+        let mut flags = 0;
+        if entry.should_panic {
+            flags |= SHOULD_PANIC;
+        }
+        if entry.expected_panic.is_some() {
+            flags |= HAS_EXPECTED;
+        }
+        out.push(flags);
+        write_str(&mut out, &symbol);
+        write_str(&mut out, &entry.name);
+        if let Some(expected) = &entry.expected_panic {
+            write_str(&mut out, expected);
+        }
+    }
+    out
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+/// Parse a section payload back into `(symbol, entry)` pairs, the entry's
+/// `function` being the symbol. None if the payload is malformed.
+#[cfg(test)]
+pub fn decode(data: &[u8]) -> Option<Vec<(String, TestEntry)>> {
+    let mut pos = 0;
+    let mut take = |n: usize| -> Option<&[u8]> {
+        let bytes = data.get(pos..pos + n)?;
+        pos += n;
+        Some(bytes)
+    };
+    if take(4)? != MAGIC {
+        return None;
+    }
+    let u32_at = |b: &[u8]| u32::from_le_bytes(b.try_into().unwrap());
+    if u32_at(take(4)?) != VERSION {
+        return None;
+    }
+    let count = u32_at(take(4)?);
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let flags = take(1)?[0];
+        let mut read_str = || -> Option<String> {
+            let len = u32_at(take(4)?) as usize;
+            String::from_utf8(take(len)?.to_vec()).ok()
+        };
+        let symbol = read_str()?;
+        let name = read_str()?;
+        let expected_panic = if flags & HAS_EXPECTED != 0 { Some(read_str()?) } else { None };
+        entries.push((
+            symbol.clone(),
+            TestEntry {
+                function: symbol,
+                name,
+                should_panic: flags & SHOULD_PANIC != 0,
+                expected_panic,
+            },
+        ));
+    }
+    Some(entries)
+}
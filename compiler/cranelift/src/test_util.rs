@@ -0,0 +1,135 @@
+//! Shared MIR builders and C API helpers for unit tests.
+
+use crate::mir_types::*;
+use crate::mir_writer::MirBinaryWriter;
+use crate::{
+    CraneliftOptions, CraneliftResult, cranelift_compile_mir, cranelift_free_result,
+    cranelift_generate_ir, cranelift_resolve_options,
+};
+
+pub fn prim(ty: PrimitiveType) -> MirType {
+    MirType::Primitive(ty)
+}
+
+pub fn v(id: u32) -> Value {
+    Value { id }
+}
+
+pub fn inst(result: u32, inst: Instruction) -> InstructionData {
+    InstructionData { result, inst, loc: SourceLoc::default() }
+}
+
+pub fn const_int(result: u32, value: i64, bit_width: u8) -> InstructionData {
+    inst(
+        result,
+        Instruction::Constant(Constant::Int {
+            value,
+            bit_width,
+            is_signed: true,
+        }),
+    )
+}
+
+pub fn block(id: u32, instructions: Vec<InstructionData>, terminator: Terminator) -> BasicBlock {
+    BasicBlock {
+        id,
+        name: format!("bb{}", id),
+        predecessors: Vec::new(),
+        instructions,
+        terminator: Some(terminator),
+    }
+}
+
+pub fn function(
+    name: &str,
+    params: Vec<(u32, MirType)>,
+    return_type: MirType,
+    blocks: Vec<BasicBlock>,
+) -> Function {
+    Function {
+        name: name.into(),
+        is_public: true,
+        params: params
+            .into_iter()
+            .enumerate()
+            .map(|(i, (value_id, ty))| FunctionParam {
+                name: format!("p{}", i),
+                ty,
+                value_id,
+            })
+            .collect(),
+        return_type,
+        blocks,
+        next_value_id: 1000,
+        next_block_id: 1000,
+    }
+}
+
+pub fn module(functions: Vec<Function>) -> Module {
+    Module {
+        name: "test".into(),
+        structs: Vec::new(),
+        enums: Vec::new(),
+        functions,
+        constants: Vec::new(),
+    }
+}
+
+pub fn default_options() -> CraneliftOptions {
+    // All-zero is the C++ side's default: O0, host target, no extras
+    let mut options: CraneliftOptions = unsafe { std::mem::zeroed() };
+    options.struct_size = std::mem::size_of::<CraneliftOptions>();
+    options
+}
+
+/// Outcome of a C API call, copied out before the result is freed.
+pub struct Outcome {
+    pub success: bool,
+    pub data: Vec<u8>,
+    pub text: String,
+}
+
+/// Copy out a result, checking the string contract on the way: valid UTF-8
+/// of the given length, followed by a NUL.
+pub fn take(result: &mut CraneliftResult) -> Outcome {
+    let read = |p: *const i8, len: usize| {
+        if p.is_null() {
+            return String::new();
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(p as *const u8, len + 1) };
+        assert_eq!(bytes[len], 0, "string is not NUL-terminated at its length");
+        String::from_utf8(bytes[..len].to_vec()).expect("string is not valid UTF-8")
+    };
+    let outcome = Outcome {
+        success: result.success != 0,
+        data: if result.data.is_null() {
+            Vec::new()
+        } else {
+            unsafe { std::slice::from_raw_parts(result.data, result.data_len) }.to_vec()
+        },
+        text: if result.ir_text.is_null() {
+            read(result.error_msg, result.error_msg_len)
+        } else {
+            read(result.ir_text, result.ir_text_len)
+        },
+    };
+    cranelift_free_result(result);
+    outcome
+}
+
+/// Serialize `module` and compile it to an object through the C API.
+pub fn compile(module: &Module, options: &CraneliftOptions) -> Outcome {
+    let bytes = MirBinaryWriter::new().write_module(module);
+    take(&mut cranelift_compile_mir(bytes.as_ptr(), bytes.len(), options))
+}
+
+/// Serialize `module` and render it as CLIF text through the C API.
+pub fn generate_ir(module: &Module, options: &CraneliftOptions) -> Outcome {
+    let bytes = MirBinaryWriter::new().write_module(module);
+    take(&mut cranelift_generate_ir(bytes.as_ptr(), bytes.len(), options))
+}
+
+/// Resolve `options` through the C API (JSON on success).
+pub fn resolve_options(options: &CraneliftOptions) -> Outcome {
+    take(&mut cranelift_resolve_options(options))
+}
@@ -1,25 +1,65 @@
-/// MIR → Cranelift IR Translation
-///
-/// Translates deserialized MIR instructions into Cranelift IR.
-/// Handles phi-to-block-parameter conversion, all Tier 1 instructions
-/// (arithmetic, calls, casts, constants, alloca, load/store, terminators),
-/// and Tier 2 aggregates (struct/enum/tuple/array init, GEP, extract/insert).
+//! MIR → Cranelift IR Translation
+//!
+//! Translates deserialized MIR instructions into Cranelift IR.
+//! Handles phi-to-block-parameter conversion, all Tier 1 instructions
+//! (arithmetic, calls, casts, constants, alloca, load/store, terminators),
+//! and Tier 2 aggregates (struct/enum/tuple/array init, GEP, extract/insert).
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
 
 use cranelift_codegen::ir::{
     condcodes::{FloatCC, IntCC},
-    types, AbiParam, Block, BlockArg, Function as ClifFunc, InstBuilder, MemFlags, StackSlotData,
-    StackSlotKind, TrapCode, Value as ClifValue,
+    types, AbiParam, Block, BlockArg, Endianness, Function as ClifFunc, InstBuilder,
+    InstructionData as ClifInstData, MemFlags, Opcode, StackSlotData, StackSlotKind, TrapCode,
+    Value as ClifValue, ValueDef,
 };
-use cranelift_codegen::settings::{self, Configurable};
-use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
 use cranelift_module::{FuncId, Linkage, Module};
-use cranelift_object::{ObjectBuilder, ObjectModule};
-
+use cranelift_object::object::SectionKind;
+use cranelift_object::object::write::SymbolSection;
+use cranelift_object::{ObjectBuilder, ObjectModule, ObjectProduct};
+
+use crate::build_info;
+use crate::cancel;
+use crate::clif_text::{self, DebugNames};
+#[cfg(feature = "interpreter")]
+use crate::const_eval;
+use crate::const_pool::{self, ConstKey, ConstPlan};
+use crate::cpu;
+use crate::disasm;
+use crate::dump::{self, Dumper};
+#[cfg(feature = "debug-info")]
+use crate::dwarf::LineTable;
 use crate::error::{BridgeError, BridgeResult};
+use crate::inline_meta;
+use crate::intrinsics::IntIntrinsic;
+use crate::json;
+use crate::memory;
 use crate::mir_types::*;
+use crate::mir_writer::MirBinaryWriter;
+use crate::multiversion;
+use crate::options::{BridgeOptions, DEFAULT_FRAME_WARN_BYTES, DEFAULT_SYMBOL_PREFIX};
+use crate::parallel;
+use crate::pointer_ops::{FlatPointers, PointerModel};
+use crate::runtime_sigs;
+use crate::slot_lifetimes::{self, SlotPlan};
+use crate::stats::MirStats;
+use crate::stream;
+use crate::test_registry;
+use crate::traps::{TRAP_ABORT_AT_IMPORT, TRAP_ABORT_IMPORT, TrapCategory, UNREACHABLE_TRAP};
 use crate::types::{self as ty, POINTER_TYPE};
+use crate::validate::{self, Diagnostic, Severity};
+
+/// Float lowering freedoms granted by the fast-math option. Both off means
+/// strict IEEE semantics: every operation rounds exactly as written.
+#[derive(Debug, Clone, Copy, Default)]
+struct FloatMode {
+    /// Fuse `a * b + c` into `fma` (only where the ISA has native FMA)
+    contract: bool,
+    /// Regroup constant operands of add/mul chains
+    reassociate: bool,
+}
 
 /// Translator state for a single module compilation.
 pub struct ModuleTranslator {
@@ -30,33 +70,78 @@ pub struct ModuleTranslator {
     struct_defs: HashMap<String, Vec<StructField>>,
     /// Enum definitions from MIR module
     enum_defs: HashMap<String, Vec<EnumVariant>>,
-    /// Set of C runtime function names (these do NOT get tml_ prefix)
-    runtime_names: std::collections::HashSet<String>,
+    /// Options this module is compiled with
+    options: BridgeOptions,
+    /// CFG validation and frame size warnings collected while translating
+    pub diagnostics: Vec<Diagnostic>,
+    /// Stack frame bytes (sum of stack slots) of each translated function
+    pub frame_sizes: Vec<(String, u32)>,
+    /// ISA for `.avx2` variants when multi-versioning on x86-64
+    avx2_isa: Option<cranelift_codegen::isa::OwnedTargetIsa>,
+    /// Results of constant functions, substituted for calls to them
+    const_fns: HashMap<String, Constant>,
+    /// Import `TML_RT_SHIM` runtime functions under their `tml_rt_` aliases
+    prefix_runtime: bool,
+    /// Function whose machine code to keep in `captured_code` (self test)
+    capture: Option<String>,
+    /// Machine code of `capture`, when it needs no relocations
+    pub captured_code: Option<Vec<u8>>,
+    /// Functions another CGU defines, declared as imports
+    imported: HashSet<String>,
+    /// Worker threads compiling the translated functions (None = compile
+    /// each one as it is translated), see `parallel`
+    threads: Option<usize>,
+    /// Functions translated in parallel mode, waiting to be compiled
+    pending: Vec<parallel::Pending>,
+    /// IR text of each translated function under `emit_ir`, as `(MIR name, text)`
+    ir_functions: Vec<(String, String)>,
+    /// Per-function compile statistics under `function_stats`, in definition order
+    function_stats: Vec<FunctionStats>,
+    /// Keep a listing of each compiled function in `disassembly`
+    disassemble: bool,
+    /// Machine code listings, as `(MIR name, text)`; see `disasm`
+    disassembly: Vec<(String, String)>,
+    /// Compile functions without defining them, see `estimate_sizes`
+    estimate: bool,
+    /// Symbol prefix and renames from the options
+    symbols: SymbolScheme,
+    /// Source lines of the compiled functions, for the DWARF line table
+    /// (None unless `debug_info` and `source_file` are set; see `dwarf`)
+    #[cfg(feature = "debug-info")]
+    lines: Option<LineTable>,
+    /// Sink of the IR, diagnostic and verifier dumps, if the options name one
+    dumper: Option<Dumper>,
+    /// Public functions to put in the DLL export table under `dll_export`,
+    /// as MIR names; see `add_dll_exports`
+    dll_exports: Vec<String>,
 }
 
-impl ModuleTranslator {
-    pub fn new(target_triple: &str, opt_level: u8) -> BridgeResult<Self> {
-        let isa_builder = cranelift_native::builder().map_err(|e| {
-            BridgeError::InvalidTarget(format!("failed to create native ISA builder: {}", e))
-        })?;
-
-        let mut shared_flags = settings::builder();
-        match opt_level {
-            0 => {
-                let _ = shared_flags.set("opt_level", "none");
-            }
-            _ => {
-                let _ = shared_flags.set("opt_level", "speed_and_size");
-            }
-        }
-        let _ = shared_flags.set("is_pic", "false");
+/// Output of a compile: the object file and, under `emit_ir`, the IR text
+/// of its functions.
+#[derive(Debug)]
+pub struct Compiled {
+    pub object: Vec<u8>,
+    pub ir_text: Option<String>,
+    /// Empty unless `function_stats` is set
+    pub function_stats: Vec<FunctionStats>,
+}
 
-        let flags = settings::Flags::new(shared_flags);
-        let isa = isa_builder
-            .finish(flags)
-            .map_err(|e| BridgeError::Codegen(format!("failed to build ISA: {}", e)))?;
+/// Where one function's compile time went, and the machine code it became.
+/// A multi-versioned function counts its variants and dispatch stub.
+#[derive(Debug, Clone)]
+pub struct FunctionStats {
+    /// MIR name
+    pub name: String,
+    /// MIR to Cranelift IR, including validation
+    pub translate_us: u64,
+    /// Cranelift compile and definition in the object
+    pub codegen_us: u64,
+    pub code_bytes: u64,
+}
 
-        let _ = target_triple; // We use native ISA, triple is for future cross-compilation
+impl ModuleTranslator {
+    pub fn new(options: &BridgeOptions) -> BridgeResult<Self> {
+        let isa = cpu::isa(options)?;
 
         let obj_builder =
             ObjectBuilder::new(isa, "tml_module", cranelift_module::default_libcall_names())
@@ -64,37 +149,135 @@ impl ModuleTranslator {
                     BridgeError::Codegen(format!("failed to create object builder: {}", e))
                 })?;
         let module = ObjectModule::new(obj_builder);
+        let prefix_runtime = options
+            .runtime_prefix
+            .unwrap_or_else(|| runtime_sigs::prefix_by_default(module.isa().triple()));
+        #[cfg(feature = "debug-info")]
+        let lines = if options.debug_info && !options.source_file.is_empty() {
+            LineTable::for_target(module.isa().triple())
+        } else {
+            None
+        };
+        let dumper = options.dump.as_ref().map(Dumper::open).transpose()?;
+        let avx2_isa = if options.multiversion.is_empty() {
+            None
+        } else {
+            multiversion::avx2_isa(module.isa())?
+        };
 
         Ok(Self {
             module,
             func_ids: HashMap::new(),
             struct_defs: HashMap::new(),
             enum_defs: HashMap::new(),
-            runtime_names: std::collections::HashSet::new(),
+            options: options.clone(),
+            diagnostics: Vec::new(),
+            frame_sizes: Vec::new(),
+            avx2_isa,
+            const_fns: HashMap::new(),
+            prefix_runtime,
+            capture: None,
+            captured_code: None,
+            imported: HashSet::new(),
+            threads: None,
+            pending: Vec::new(),
+            ir_functions: Vec::new(),
+            function_stats: Vec::new(),
+            disassemble: false,
+            disassembly: Vec::new(),
+            estimate: false,
+            symbols: SymbolScheme::new(options),
+            #[cfg(feature = "debug-info")]
+            lines,
+            dumper,
+            dll_exports: Vec::new(),
         })
     }
 
-    /// Populate the set of C runtime function names (no tml_ prefix).
+    /// Compile the translated functions on `threads` worker threads (0 =
+    /// one per CPU) once the whole module is translated. The object is the
+    /// same for every thread count.
+    pub fn compile_in_parallel(&mut self, threads: usize) {
+        self.threads = Some(threads);
+    }
+
+    /// Keep the machine code of the MIR function `name` in `captured_code`
+    /// when it is defined, so it can be run in process.
+    pub fn capture_code(&mut self, name: &str) {
+        self.capture = Some(name.to_string());
+    }
+
+    /// Keep an assembly listing of every compiled function that matches
+    /// `ir_filter`, for `take_disassembly`. Functions are compiled as they
+    /// are translated, since the listing comes from the compile itself.
+    pub fn disassemble(&mut self) {
+        self.disassemble = true;
+    }
+
+    /// The listings kept under `disassemble`, in definition order or sorted
+    /// by MIR name under `ir_sort`.
+    pub fn take_disassembly(&mut self) -> String {
+        let mut listings = std::mem::take(&mut self.disassembly);
+        if self.options.ir_sort {
+            listings.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+        listings.into_iter().map(|(_, text)| text).collect()
+    }
+
+    /// Compile every function as it is translated but define none in the
+    /// object, keeping only the statistics `take_estimates` returns. The
+    /// code sizes are those of a real compile, without the relocations and
+    /// object layout; the module must not be finished.
+    pub fn estimate_sizes(&mut self) {
+        self.estimate = true;
+        self.options.function_stats = true;
+    }
+
+    /// The statistics kept under `estimate_sizes`, in definition order.
+    pub fn take_estimates(&mut self) -> Vec<FunctionStats> {
+        std::mem::take(&mut self.function_stats)
+    }
+
+    /// Float mode for function translation. Contraction is only enabled
+    /// where `fma` is a single instruction; elsewhere Cranelift would lower
+    /// it to a libm call, which is slower than the separate operations.
+    fn float_mode(&self) -> FloatMode {
+        let isa = self.module.isa();
+        let native_fma = match isa.triple().architecture {
+            target_lexicon::Architecture::Aarch64(_) => true,
+            target_lexicon::Architecture::X86_64 => isa
+                .isa_flags()
+                .iter()
+                .any(|f| f.name == "has_fma" && f.as_bool() == Some(true)),
+            _ => false,
+        };
+        FloatMode {
+            contract: self.options.fp_contract && native_fma,
+            reassociate: self.options.fp_reassociate,
+        }
+    }
+
+    /// Populate the set of C runtime function names (no symbol prefix).
     fn init_runtime_names(&mut self) {
-        let names = [
-            "print", "println", "panic", "assert_tml", "assert_tml_loc",
-            "print_i32", "print_i64", "print_f32", "print_f64", "print_bool", "print_char",
-            "str_len", "str_eq", "str_hash", "str_concat", "str_concat_opt",
-            "str_concat_3", "str_concat_4", "str_concat_n",
-            "str_substring", "str_slice", "str_contains", "str_starts_with", "str_ends_with",
-            "str_to_upper", "str_to_lower", "str_trim", "str_char_at", "char_to_string",
-            "time_ms", "time_us", "time_ns", "sleep_ms", "sleep_us",
-            "elapsed_ms", "elapsed_us", "elapsed_ns",
-            "mem_alloc", "mem_alloc_zeroed", "mem_realloc", "mem_free",
-            "mem_copy", "mem_move", "mem_set", "mem_zero", "mem_compare", "mem_eq",
-            "tml_set_output_suppressed", "tml_get_output_suppressed",
-            "tml_run_should_panic", "tml_get_panic_message", "tml_panic_message_contains",
-        ];
-        for name in &names {
-            self.runtime_names.insert(name.to_string());
+        for sig in runtime_sigs::all() {
+            self.symbols.runtime_names.insert(sig.name.to_string());
+        }
+    }
+
+    /// Evaluate the module's constant functions so their call sites can use
+    /// the result directly. Only when optimizing, and not with trace hooks,
+    /// which must still see the callee's blocks run.
+    /// Builds without the `interpreter` feature leave every call in place.
+    #[cfg(feature = "interpreter")]
+    fn fold_constant_functions(&mut self, mir: &crate::mir_types::Module) {
+        if self.options.opt_level > 0 && !self.options.trace_hooks {
+            self.const_fns = const_eval::fold_module(mir);
         }
     }
 
+    #[cfg(not(feature = "interpreter"))]
+    fn fold_constant_functions(&mut self, _mir: &crate::mir_types::Module) {}
+
     /// Translate a full MIR module. If `func_indices` is Some, only translate those functions (CGU mode).
     pub fn translate_module(
         &mut self,
@@ -112,6 +295,17 @@ impl ModuleTranslator {
             self.enum_defs.insert(e.name.clone(), e.variants.clone());
         }
 
+        // In CGU mode, the functions left out are defined by other objects
+        if let Some(idx) = func_indices {
+            let here: HashSet<&str> = idx.iter().filter_map(|&i| mir.functions.get(i)).map(|f| f.name.as_str()).collect();
+            self.imported = mir
+                .functions
+                .iter()
+                .filter(|f| !here.contains(f.name.as_str()))
+                .map(|f| f.name.clone())
+                .collect();
+        }
+
         // Phase 1: Declare all functions (so calls can reference any function)
         for func in &mir.functions {
             self.declare_function(func)?;
@@ -119,6 +313,7 @@ impl ModuleTranslator {
 
         // Declare runtime functions
         self.declare_runtime_functions()?;
+        self.fold_constant_functions(mir);
 
         // Phase 2: Define function bodies (only the requested subset in CGU mode)
         let indices: Vec<usize> = match func_indices {
@@ -128,6 +323,9 @@ impl ModuleTranslator {
 
         let mut defined_funcs = std::collections::HashSet::new();
         for &i in &indices {
+            // Between functions, so a cancelled compile stops within one
+            cancel::check(self.options.cancel.as_ref())?;
+            memory::check()?;
             if i < mir.functions.len() {
                 let func = &mir.functions[i];
                 // Skip duplicate function definitions
@@ -135,45 +333,200 @@ impl ModuleTranslator {
                     continue;
                 }
                 defined_funcs.insert(func.name.clone());
-                self.translate_function(func)?;
+                self.translate_function(func, i)?;
             }
         }
+        self.compile_pending()?;
+
+        if self.options.emit_inline_metadata {
+            // Only the functions defined in this object are exported, so each
+            // CGU carries metadata for exactly its own definitions.
+            let defined = indices
+                .iter()
+                .filter_map(|&i| mir.functions.get(i))
+                .filter(|f| !f.blocks.is_empty());
+            self.emit_inline_metadata(mir, defined)?;
+        }
+
+        if !self.options.tests.is_empty() {
+            self.emit_test_registry(mir, &defined_funcs)?;
+        }
 
         Ok(())
     }
 
-    /// Finish compilation and return the object file bytes.
-    pub fn finish(self) -> BridgeResult<Vec<u8>> {
-        let product = self.module.finish();
-        let bytes = product.emit().map_err(|e| {
-            BridgeError::Codegen(format!("failed to emit object file: {}", e))
-        })?;
-        Ok(bytes)
+    /// Record the requested tests defined in this object in `.tml.tests`.
+    /// Naming a function the module doesn't have is an error; tests that
+    /// live in another CGU are simply left to that CGU's object.
+    fn emit_test_registry(
+        &mut self,
+        mir: &crate::mir_types::Module,
+        defined: &std::collections::HashSet<String>,
+    ) -> BridgeResult<()> {
+        let mut entries = Vec::new();
+        for test in &self.options.tests {
+            if !mir.functions.iter().any(|f| f.name == test.function) {
+                return Err(BridgeError::Translation(format!(
+                    "test function '{}' not found in module",
+                    test.function
+                )));
+            }
+            if defined.contains(&test.function) {
+                entries.push((self.resolve_symbol_name(&test.function), test));
+            }
+        }
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let bytes = test_registry::encode(entries);
+        self.define_custom_section(
+            bytes,
+            test_registry::TESTS_SECTION,
+            test_registry::TESTS_SECTION_MACHO,
+            "test registry",
+        )
     }
 
-    /// Map a MIR function name to the symbol name used in object files.
-    /// User/library functions get "tml_" prefix; C runtime functions keep bare names.
-    fn resolve_symbol_name(&self, mir_name: &str) -> String {
-        // If it already has tml_ prefix, keep it
-        if mir_name.starts_with("tml_") {
-            return mir_name.to_string();
+    /// Serialize inline candidates into the `.tml.inline` custom section.
+    fn emit_inline_metadata<'f>(
+        &mut self,
+        mir: &crate::mir_types::Module,
+        funcs: impl IntoIterator<Item = &'f Function>,
+    ) -> BridgeResult<()> {
+        let Some(inline_module) = inline_meta::build_inline_module(mir, funcs) else {
+            return Ok(());
+        };
+        let bytes = MirBinaryWriter::new().write_module(&inline_module);
+        self.define_custom_section(
+            bytes,
+            inline_meta::INLINE_SECTION,
+            inline_meta::INLINE_SECTION_MACHO,
+            "inline metadata",
+        )
+    }
+
+    /// Place `bytes` in a retained custom section: `section` on ELF/COFF,
+    /// the `macho` segment/section pair on Mach-O.
+    fn define_custom_section(
+        &mut self,
+        bytes: Vec<u8>,
+        section: &str,
+        macho: (&str, &str),
+        what: &str,
+    ) -> BridgeResult<()> {
+        let data_id = self
+            .module
+            .declare_anonymous_data(false, false)
+            .map_err(|e| BridgeError::Codegen(format!("failed to declare {}: {}", what, e)))?;
+
+        let mut data_desc = cranelift_module::DataDescription::new();
+        data_desc.define(bytes.into_boxed_slice());
+        let (segment, section) = match self.module.isa().triple().binary_format {
+            target_lexicon::BinaryFormat::Macho => macho,
+            _ => ("", section),
+        };
+        data_desc.set_segment_section(segment, section);
+        data_desc.set_used(true);
+
+        self.module
+            .define_data(data_id, &data_desc)
+            .map_err(|e| BridgeError::Codegen(format!("failed to define {}: {}", what, e)))
+    }
+
+    /// Finish compilation and return the object file bytes, or stream them
+    /// to the `write` callback and return none, with the IR text under
+    /// `emit_ir`.
+    pub fn finish(mut self) -> BridgeResult<Compiled> {
+        if self.options.emit_build_info {
+            let bytes = build_info::encode(&self.options, self.module.isa());
+            self.define_custom_section(
+                bytes,
+                build_info::BUILD_SECTION,
+                build_info::BUILD_SECTION_MACHO,
+                "build information",
+            )?;
+        }
+        let ir_text = self.options.emit_ir.then(|| {
+            if self.options.ir_sort {
+                self.ir_functions.sort_by(|a, b| a.0.cmp(&b.0));
+            }
+            self.ir_functions.iter().map(|(_, text)| text.as_str()).collect()
+        });
+        let triple = self.module.isa().triple().clone();
+        let dll_exports: Vec<FuncId> =
+            self.dll_exports.iter().filter_map(|name| self.func_ids.get(name).copied()).collect();
+        let mut product = self.module.finish();
+        #[cfg(feature = "debug-info")]
+        if let Some(lines) = &self.lines {
+            lines.emit(&self.options.source_file, &triple, &mut product)?;
+        }
+        if triple.binary_format == target_lexicon::BinaryFormat::Coff {
+            Self::add_dll_exports(&dll_exports, &triple, &mut product);
+        }
+        let bytes = match self.options.write {
+            Some(sink) => {
+                stream::write_object(&product.object, sink)?;
+                Vec::new()
+            }
+            None => product.emit().map_err(|e| {
+                BridgeError::Codegen(format!("failed to emit object file: {}", e))
+            })?,
+        };
+        Ok(Compiled { object: bytes, ir_text, function_stats: self.function_stats })
+    }
+
+    /// Export the `functions` this object defines from the DLL it is
+    /// linked into, as `__declspec(dllexport)` does: a `.drectve`
+    /// section asks the linker for an export table entry per function, in
+    /// the MSVC (`/EXPORT:`) or MinGW (`-export:`) spelling of the target.
+    /// ELF and Mach-O export every public symbol already.
+    fn add_dll_exports(functions: &[FuncId], triple: &target_lexicon::Triple, product: &mut ObjectProduct) {
+        let gnu = triple.environment == target_lexicon::Environment::Gnu;
+        let mut exported = HashSet::new();
+        let mut directives = Vec::new();
+        for &func_id in functions {
+            let symbol = product.object.symbol(product.function_symbol(func_id));
+            if !matches!(symbol.section, SymbolSection::Section(_)) || !exported.insert(symbol.name.clone()) {
+                continue; // Defined by another object, or already exported
+            }
+            directives.extend_from_slice(if gnu { b" -export:\"" } else { b" /EXPORT:\"" });
+            directives.extend_from_slice(&symbol.name);
+            directives.push(b'"');
         }
-        // C runtime functions don't get the prefix
-        if self.runtime_names.contains(mir_name) {
-            return mir_name.to_string();
+        if !directives.is_empty() {
+            let drectve = product.object.add_section(Vec::new(), b".drectve".to_vec(), SectionKind::Linker);
+            product.object.append_section_data(drectve, &directives, 1);
         }
-        // All other functions get tml_ prefix (matches LLVM codegen behavior)
-        format!("tml_{}", mir_name)
+    }
+
+    /// Map a MIR function name to the symbol name used in object files.
+    fn resolve_symbol_name(&self, mir_name: &str) -> String {
+        self.symbols.symbol(mir_name)
     }
 
     fn declare_function(&mut self, func: &Function) -> BridgeResult<()> {
+        // Body-less functions are `@extern` declarations: runtime functions
+        // keep their manifest signature and symbol, others are C symbols
+        let external = func.blocks.is_empty();
+        if external && self.symbols.runtime_names.contains(&func.name) {
+            return Ok(());
+        }
         let sig = self.build_signature(func);
-        let symbol_name = self.resolve_symbol_name(&func.name);
-        let linkage = if func.is_public || func.name == "main" || func.name == "tml_main" {
+        let symbol_name = if external {
+            self.symbols.renamed(func.name.clone())
+        } else {
+            self.resolve_symbol_name(&func.name)
+        };
+        let linkage = if external || self.imported.contains(&func.name) {
+            Linkage::Import
+        } else if func.is_public || func.name == "main" || func.name == "tml_main" {
             Linkage::Export
         } else {
             Linkage::Local
         };
+        if self.options.dll_export && func.is_public && linkage == Linkage::Export {
+            self.dll_exports.push(func.name.clone());
+        }
 
         // If already declared, try to re-declare with same signature (idempotent).
         // If signatures differ, use a disambiguated symbol name.
@@ -231,81 +584,31 @@ impl ModuleTranslator {
 
     fn build_signature(&self, func: &Function) -> cranelift_codegen::ir::Signature {
         let mut sig = self.module.make_signature();
-        for param in &func.params {
-            if let Some(cl_ty) = ty::mir_type_to_cranelift(&param.ty) {
-                sig.params.push(AbiParam::new(cl_ty));
-            }
-        }
-        if let Some(ret_ty) = ty::mir_type_to_cranelift(&func.return_type) {
-            sig.returns.push(AbiParam::new(ret_ty));
-        }
+        sig.params.extend(func.params.iter().filter_map(|param| ty::abi_param(&param.ty)));
+        sig.returns.extend(ty::abi_param(&func.return_type));
         sig
     }
 
     fn declare_runtime_functions(&mut self) -> BridgeResult<()> {
-        // Declare external runtime functions from essential.h
-        let rt_funcs: Vec<(&str, Vec<cranelift_codegen::ir::Type>, Option<cranelift_codegen::ir::Type>)> = vec![
-            // I/O
-            ("print", vec![POINTER_TYPE], None),
-            ("println", vec![POINTER_TYPE], None),
-            ("panic", vec![POINTER_TYPE], None),
-            ("assert_tml", vec![types::I32, POINTER_TYPE], None),
-            ("assert_tml_loc", vec![types::I32, POINTER_TYPE, POINTER_TYPE, types::I32], None),
-            // Type-specific print
-            ("print_i32", vec![types::I32], None),
-            ("print_i64", vec![types::I64], None),
-            ("print_f32", vec![types::F32], None),
-            ("print_f64", vec![types::F64], None),
-            ("print_bool", vec![types::I32], None),
-            ("print_char", vec![types::I32], None),
-            // String functions
-            ("str_len", vec![POINTER_TYPE], Some(types::I32)),
-            ("str_eq", vec![POINTER_TYPE, POINTER_TYPE], Some(types::I32)),
-            ("str_hash", vec![POINTER_TYPE], Some(types::I32)),
-            ("str_concat", vec![POINTER_TYPE, POINTER_TYPE], Some(POINTER_TYPE)),
-            ("str_concat_opt", vec![POINTER_TYPE, POINTER_TYPE], Some(POINTER_TYPE)),
-            ("str_concat_3", vec![POINTER_TYPE, POINTER_TYPE, POINTER_TYPE], Some(POINTER_TYPE)),
-            ("str_concat_4", vec![POINTER_TYPE, POINTER_TYPE, POINTER_TYPE, POINTER_TYPE], Some(POINTER_TYPE)),
-            ("str_concat_n", vec![POINTER_TYPE, types::I64], Some(POINTER_TYPE)),
-            ("str_substring", vec![POINTER_TYPE, types::I32, types::I32], Some(POINTER_TYPE)),
-            ("str_slice", vec![POINTER_TYPE, types::I64, types::I64], Some(POINTER_TYPE)),
-            ("str_contains", vec![POINTER_TYPE, POINTER_TYPE], Some(types::I32)),
-            ("str_starts_with", vec![POINTER_TYPE, POINTER_TYPE], Some(types::I32)),
-            ("str_ends_with", vec![POINTER_TYPE, POINTER_TYPE], Some(types::I32)),
-            ("str_to_upper", vec![POINTER_TYPE], Some(POINTER_TYPE)),
-            ("str_to_lower", vec![POINTER_TYPE], Some(POINTER_TYPE)),
-            ("str_trim", vec![POINTER_TYPE], Some(POINTER_TYPE)),
-            ("str_char_at", vec![POINTER_TYPE, types::I32], Some(types::I32)),
-            ("char_to_string", vec![types::I8], Some(POINTER_TYPE)),
-            // Time
-            ("time_ms", vec![], Some(types::I32)),
-            ("time_us", vec![], Some(types::I64)),
-            ("time_ns", vec![], Some(types::I64)),
-            ("sleep_ms", vec![types::I32], None),
-            ("sleep_us", vec![types::I64], None),
-            ("elapsed_ms", vec![types::I32], Some(types::I32)),
-            ("elapsed_us", vec![types::I64], Some(types::I64)),
-            ("elapsed_ns", vec![types::I64], Some(types::I64)),
-            // Memory
-            ("mem_alloc", vec![types::I64], Some(POINTER_TYPE)),
-            ("mem_alloc_zeroed", vec![types::I64], Some(POINTER_TYPE)),
-            ("mem_realloc", vec![POINTER_TYPE, types::I64], Some(POINTER_TYPE)),
-            ("mem_free", vec![POINTER_TYPE], None),
-            ("mem_copy", vec![POINTER_TYPE, POINTER_TYPE, types::I64], None),
-            ("mem_move", vec![POINTER_TYPE, POINTER_TYPE, types::I64], None),
-            ("mem_set", vec![POINTER_TYPE, types::I32, types::I64], None),
-            ("mem_zero", vec![POINTER_TYPE, types::I64], None),
-            ("mem_compare", vec![POINTER_TYPE, POINTER_TYPE, types::I64], Some(types::I32)),
-            ("mem_eq", vec![POINTER_TYPE, POINTER_TYPE, types::I64], Some(types::I32)),
-            // Test/panic support
-            ("tml_set_output_suppressed", vec![types::I32], None),
-            ("tml_get_output_suppressed", vec![], Some(types::I32)),
-            ("tml_run_should_panic", vec![POINTER_TYPE], Some(types::I32)),
-            ("tml_get_panic_message", vec![], Some(POINTER_TYPE)),
-            ("tml_panic_message_contains", vec![POINTER_TYPE], Some(types::I32)),
-        ];
+        // Runtime imports come from the shared signature manifest; the
+        // symbol may be a prefixed alias of the name MIR calls
+        let mut rt_funcs: Vec<(&str, String, Vec<cranelift_codegen::ir::Type>, Option<cranelift_codegen::ir::Type>)> =
+            runtime_sigs::all()
+                .iter()
+                .filter(|sig| match sig.name {
+                    TRAP_ABORT_IMPORT => self.options.trap_abort,
+                    TRAP_ABORT_AT_IMPORT => self.options.trap_abort && !self.options.source_file.is_empty(),
+                    multiversion::CPU_FEATURE_IMPORT => !self.options.multiversion.is_empty(),
+                    _ => true,
+                })
+                .map(|sig| (sig.name, sig.symbol(self.prefix_runtime), sig.params.clone(), sig.ret))
+                .collect();
+
+        if self.options.trace_hooks {
+            rt_funcs.push((TRACE_HOOK, TRACE_HOOK.to_string(), vec![types::I32, types::I32], None));
+        }
 
-        for (name, params, ret) in &rt_funcs {
+        for (name, symbol, params, ret) in &rt_funcs {
             if self.func_ids.contains_key(*name) {
                 continue; // Already declared as a user function
             }
@@ -316,11 +619,12 @@ impl ModuleTranslator {
             if let Some(r) = ret {
                 sig.returns.push(AbiParam::new(*r));
             }
+            let symbol = self.symbols.renamed(symbol.clone());
             let id = self
                 .module
-                .declare_function(name, Linkage::Import, &sig)
+                .declare_function(&symbol, Linkage::Import, &sig)
                 .map_err(|e| {
-                    BridgeError::Codegen(format!("failed to declare runtime function '{}': {}", name, e))
+                    BridgeError::Codegen(format!("failed to declare runtime function '{}': {}", symbol, e))
                 })?;
             self.func_ids.insert(name.to_string(), id);
         }
@@ -328,10 +632,73 @@ impl ModuleTranslator {
         Ok(())
     }
 
-    fn translate_function(&mut self, func: &Function) -> BridgeResult<()> {
+    /// Validate the CFG of `func`: keep warnings, fail on the first error.
+    fn validate_function(&mut self, func: &Function) -> BridgeResult<()> {
+        for diag in validate::check_function(func) {
+            if diag.severity == Severity::Error {
+                self.dump(dump::DUMP_DIAGNOSTIC, &diag.to_string());
+                return Err(BridgeError::Translation(diag.to_string()));
+            }
+            self.add_diagnostic(diag);
+        }
+        Ok(())
+    }
+
+    /// Keep `diag` and dump it.
+    fn add_diagnostic(&mut self, diag: Diagnostic) {
+        self.dump(dump::DUMP_DIAGNOSTIC, &diag.to_string());
+        self.diagnostics.push(diag);
+    }
+
+    /// Write `text` to the dump sink, if there is one.
+    fn dump(&mut self, kind: i32, text: &str) {
+        if let Some(dumper) = self.dumper.as_mut() {
+            dumper.record(kind, text);
+        }
+    }
+
+    /// Dump the verifier's listing of the MIR function `name`.
+    fn dump_verifier(&mut self, name: &str, report: Option<String>) {
+        if let Some(report) = report {
+            self.dump(dump::DUMP_VERIFIER, &format!("; verifier errors in function '{}'\n{}", name, report));
+        }
+    }
+
+    /// Record the frame size of a translated function and warn if it is
+    /// over the configured threshold. Slots are counted with their alignment
+    /// padding; spill slots added by register allocation are not included.
+    fn check_frame_size(&mut self, func: &Function, cl_func: &ClifFunc) {
+        let bytes = cl_func.sized_stack_slots.values().fold(0u32, |total, slot| {
+            let align = 1u32 << slot.align_shift;
+            total.next_multiple_of(align).saturating_add(slot.size)
+        });
+        self.frame_sizes.push((func.name.clone(), bytes));
+
+        let limit = match self.options.frame_warn_bytes {
+            0 => DEFAULT_FRAME_WARN_BYTES,
+            n => n,
+        };
+        if bytes as usize > limit {
+            self.add_diagnostic(Diagnostic {
+                severity: Severity::Warning,
+                function: func.name.clone(),
+                block: None,
+                instruction: None,
+                message: format!(
+                    "stack frame of {} bytes exceeds the {} byte limit",
+                    bytes, limit
+                ),
+            });
+        }
+    }
+
+    fn translate_function(&mut self, func: &Function, func_index: usize) -> BridgeResult<()> {
+        let started = Instant::now();
         let func_id = *self.func_ids.get(&func.name).ok_or_else(|| {
             BridgeError::Translation(format!("function '{}' not declared", func.name))
         })?;
+        let first_diag = self.diagnostics.len();
+        self.validate_function(func)?;
 
         // Skip empty functions (no blocks = no body to translate)
         if func.blocks.is_empty() {
@@ -344,9 +711,11 @@ impl ModuleTranslator {
             sig,
         );
 
+        let float_mode = self.float_mode();
         let mut fb_ctx = FunctionBuilderContext::new();
         let mut builder = FunctionBuilder::new(&mut cl_func, &mut fb_ctx);
 
+        let names;
         {
             let mut ftx = FunctionTranslator::new(
                 &mut builder,
@@ -355,46 +724,280 @@ impl ModuleTranslator {
                 &self.enum_defs,
                 &mut self.module,
                 func,
-                &self.runtime_names,
+                &self.symbols,
             );
+            ftx.configure(&self.options, float_mode, &self.const_fns, func_index);
             ftx.translate()?;
+            names = ftx.debug_names();
+        }
+        builder.finalize();
+        self.check_frame_size(func, &cl_func);
+        if self.options.function_stats {
+            self.function_stats.push(FunctionStats {
+                name: func.name.clone(),
+                translate_us: started.elapsed().as_micros() as u64,
+                codegen_us: 0,
+                code_bytes: 0,
+            });
+        }
+        // Printed before compiling, which rewrites the function in place
+        if self.options.emit_ir && self.ir_selected(func) {
+            let text = self.function_ir_text(func_index, func, func_id, &cl_func, &names, first_diag);
+            self.ir_functions.push((func.name.clone(), text));
+        }
+        if self.dumper.is_some() && self.ir_selected(func) {
+            // Its diagnostics were dumped as they were found
+            let text = self.function_ir_text(func_index, func, func_id, &cl_func, &names, self.diagnostics.len());
+            self.dump(dump::DUMP_IR, &text);
+        }
+
+        if self.is_multiversioned(func, func_id) {
+            return self.define_multiversioned(func, func_id, cl_func);
+        }
+        self.define_clif(func, func_id, cl_func, false)
+    }
+
+    /// Whether `func` matches the `ir_filter` globs (by MIR name or symbol);
+    /// true when there are none.
+    fn ir_selected(&self, func: &Function) -> bool {
+        let filter = &self.options.ir_filter;
+        let symbol = self.resolve_symbol_name(&func.name);
+        filter.is_empty()
+            || filter.iter().any(|p| clif_text::glob_match(p, &func.name) || clif_text::glob_match(p, &symbol))
+    }
+
+    /// IR text of one translated function: the diagnostics from
+    /// `first_diag` on as comments, then the function, framed as
+    /// `ir_annotate` asks.
+    fn function_ir_text(
+        &self,
+        func_index: usize,
+        func: &Function,
+        func_id: FuncId,
+        cl_func: &ClifFunc,
+        names: &DebugNames,
+        first_diag: usize,
+    ) -> String {
+        let mut text = String::new();
+        for diag in &self.diagnostics[first_diag..] {
+            text.push_str(&format!("; {}\n", diag));
         }
+        if self.options.ir_annotate {
+            let symbol = self.module.declarations().get_function_decl(func_id).linkage_name(func_id);
+            text.push_str(&clif_text::function_header(func_index, &func.name, &symbol, &cl_func.signature));
+            text.push_str(&clif_text::write_function(cl_func, names));
+            text.push_str(clif_text::FUNCTION_END);
+        } else {
+            text.push_str(&format!("; Function: {}\n", func.name));
+            text.push_str(&clif_text::write_function(cl_func, names));
+        }
+        text.push('\n');
+        text
+    }
+
+    /// Whether `func` matches the `multiversion` globs (by MIR name or symbol).
+    fn is_multiversioned(&mut self, func: &Function, func_id: FuncId) -> bool {
+        let symbol = self.module.declarations().get_function_decl(func_id).linkage_name(func_id);
+        let matched = self
+            .options
+            .multiversion
+            .iter()
+            .any(|p| clif_text::glob_match(p, &func.name) || clif_text::glob_match(p, &symbol));
+        if matched && self.avx2_isa.is_none() {
+            self.add_diagnostic(Diagnostic {
+                severity: Severity::Warning,
+                function: func.name.clone(),
+                block: None,
+                instruction: None,
+                message: "multi-versioning needs an x86-64 target; only the baseline is compiled"
+                    .to_string(),
+            });
+        }
+        matched && self.avx2_isa.is_some()
+    }
+
+    /// Define `cl_func` as `<symbol>.base` and `<symbol>.avx2`, and the
+    /// function itself as a dispatch stub between them (see `multiversion`).
+    fn define_multiversioned(
+        &mut self,
+        func: &Function,
+        func_id: FuncId,
+        cl_func: ClifFunc,
+    ) -> BridgeResult<()> {
+        let symbol = self
+            .module
+            .declarations()
+            .get_function_decl(func_id)
+            .linkage_name(func_id)
+            .into_owned();
+        let sig = cl_func.signature.clone();
+        let declare = |module: &mut ObjectModule, suffix: &str| {
+            module
+                .declare_function(&format!("{}{}", symbol, suffix), Linkage::Local, &sig)
+                .map_err(|e| BridgeError::Codegen(format!("failed to declare {}{}: {}", symbol, suffix, e)))
+        };
+        let base_id = declare(&mut self.module, multiversion::BASE_SUFFIX)?;
+        let avx2_id = declare(&mut self.module, multiversion::AVX2_SUFFIX)?;
+        let slot_name = format!("{}{}", symbol, multiversion::SLOT_SUFFIX);
+        let slot_id = self
+            .module
+            .declare_data(&slot_name, Linkage::Local, true, false)
+            .map_err(|e| BridgeError::Codegen(format!("failed to declare {}: {}", slot_name, e)))?;
+        let mut slot_desc = cranelift_module::DataDescription::new();
+        slot_desc.define_zeroinit(POINTER_TYPE.bytes() as usize);
+        self.module
+            .define_data(slot_id, &slot_desc)
+            .map_err(|e| BridgeError::Codegen(format!("failed to define {}: {}", slot_name, e)))?;
+
+        self.define_clif(func, base_id, cl_func.clone(), false)?;
+        self.define_clif(func, avx2_id, cl_func, true)?;
+
+        let has_avx2_id = *self.func_ids.get(multiversion::CPU_FEATURE_IMPORT).ok_or_else(|| {
+            BridgeError::Translation(format!("{} not declared", multiversion::CPU_FEATURE_IMPORT))
+        })?;
+        let mut stub = ClifFunc::with_name_signature(
+            cranelift_codegen::ir::UserFuncName::user(0, func_id.as_u32()),
+            sig,
+        );
+        let mut fb_ctx = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut stub, &mut fb_ctx);
+        let dispatch = multiversion::Dispatch {
+            base: self.module.declare_func_in_func(base_id, builder.func),
+            avx2: self.module.declare_func_in_func(avx2_id, builder.func),
+            has_avx2: self.module.declare_func_in_func(has_avx2_id, builder.func),
+            slot: self.module.declare_data_in_func(slot_id, builder.func),
+        };
+        multiversion::build_dispatcher(&mut builder, &dispatch);
         builder.finalize();
+        self.define_clif(func, func_id, stub, false)
+    }
 
+    /// Compile `cl_func` as the body of `func_id`, with the module's ISA or,
+    /// for `avx2`, the multi-versioning ISA.
+    fn define_clif(
+        &mut self,
+        func: &Function,
+        func_id: FuncId,
+        cl_func: ClifFunc,
+        avx2: bool,
+    ) -> BridgeResult<()> {
         let mut ctx = cranelift_codegen::Context::for_function(cl_func);
+        // Set by `translate_function` just before defining
+        let stats_slot = self.function_stats.len().checked_sub(1);
+        if self.threads.is_some() && self.capture.is_none() && !self.disassemble && !self.estimate {
+            self.pending.push(parallel::Pending::new(&func.name, func_id, ctx, avx2, stats_slot));
+            return Ok(());
+        }
 
-        // Use catch_unwind to handle Cranelift internal panics gracefully
-        // (e.g., "remove_constant_phis: entry block unknown")
+        let listed = self.disassemble && self.ir_selected(func);
+        ctx.set_disasm(listed);
+        let started = Instant::now();
+        let mut report = None;
+        // Dead blocks are dropped and the CFG validated before translation, but
+        // keep catch_unwind as a last line of defense against Cranelift panics.
         let define_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            self.module.define_function(func_id, &mut ctx)
+            match self.avx2_isa.as_deref().filter(|_| avx2) {
+                None if !self.estimate => self.module.define_function(func_id, &mut ctx).map_err(|e| {
+                    if let cranelift_module::ModuleError::Compilation(inner) = &e {
+                        report = dump::verifier_report(inner, &ctx.func);
+                    }
+                    format!("{:?}", e)
+                }),
+                isa => {
+                    let isa = isa.unwrap_or(self.module.isa());
+                    let mut ctrl_plane = cranelift_codegen::control::ControlPlane::default();
+                    ctx.compile(isa, &mut ctrl_plane).map_err(|e| {
+                        report = dump::verifier_report(&e.inner, e.func);
+                        format!("{:?}", e.inner)
+                    })?;
+                    if self.estimate {
+                        return Ok(());
+                    }
+                    define_compiled(&mut self.module, func_id, &ctx)
+                }
+            }
         }));
 
-        match define_result {
-            Ok(Ok(())) => Ok(()),
-            Ok(Err(e)) => Err(BridgeError::Codegen(format!(
-                "failed to define function '{}': {:?}",
-                func.name, e
-            ))),
-            Err(panic_info) => {
-                let msg = if let Some(s) = panic_info.downcast_ref::<&str>() {
-                    s.to_string()
-                } else if let Some(s) = panic_info.downcast_ref::<String>() {
-                    s.clone()
-                } else {
-                    "unknown panic".to_string()
-                };
-                Err(BridgeError::Codegen(format!(
-                    "PANIC in function '{}': {}",
-                    func.name, msg
-                )))
-            }
+        self.record_codegen(stats_slot, started.elapsed().as_micros() as u64, &ctx);
+        self.dump_verifier(&func.name, report);
+        if matches!(define_result, Ok(Ok(()))) && !self.estimate {
+            self.record_lines(&func.name, func_id, &ctx);
+        }
+
+        if !avx2 && self.capture.as_deref() == Some(func.name.as_str()) {
+            self.captured_code = ctx
+                .compiled_code()
+                .filter(|code| code.buffer.relocs().is_empty())
+                .map(|code| code.buffer.data().to_vec());
+        }
+        if listed {
+            let symbol = self.module.declarations().get_function_decl(func_id).linkage_name(func_id);
+            let text = disasm::listing(&func.name, &symbol, &ctx, &self.module);
+            self.disassembly.push((func.name.clone(), text));
+        }
+
+        definition_result(&func.name, define_result)
+    }
+
+    /// Compile the functions queued in parallel mode and define them in
+    /// queue order, failing with the first error in that order.
+    fn compile_pending(&mut self) -> BridgeResult<()> {
+        let Some(threads) = self.threads else {
+            return Ok(());
+        };
+        let mut pending = std::mem::take(&mut self.pending);
+        let cancel = self.options.cancel.as_ref();
+        parallel::compile(&mut pending, self.module.isa(), self.avx2_isa.as_deref(), threads, cancel);
+        // Workers only leave functions uncompiled once cancelled or over budget
+        cancel::check(cancel)?;
+        memory::check()?;
+        for mut entry in pending {
+            self.dump_verifier(&entry.name, entry.verifier_report.take());
+            let compiled = entry.outcome.expect("every function is compiled unless stopped");
+            let defined =
+                compiled.map(|r| r.and_then(|()| define_compiled(&mut self.module, entry.func_id, &entry.ctx)));
+            definition_result(&entry.name, defined)?;
+            self.record_codegen(entry.stats_slot, entry.codegen_us, &entry.ctx);
+            self.record_lines(&entry.name, entry.func_id, &entry.ctx);
+        }
+        Ok(())
+    }
+
+    /// Add `codegen_us` and the machine code compiled into `ctx` to the
+    /// statistics in `slot`.
+    fn record_codegen(&mut self, slot: Option<usize>, codegen_us: u64, ctx: &cranelift_codegen::Context) {
+        if let Some(stats) = slot.and_then(|i| self.function_stats.get_mut(i)) {
+            stats.codegen_us += codegen_us;
+            stats.code_bytes += ctx.compiled_code().map_or(0, |code| code.buffer.data().len() as u64);
         }
     }
 
+    /// Add the source lines of the function compiled into `ctx` to the line
+    /// table, if there is one.
+    /// Builds without the `debug-info` feature have no line table.
+    #[cfg(feature = "debug-info")]
+    fn record_lines(&mut self, name: &str, func_id: FuncId, ctx: &cranelift_codegen::Context) {
+        if let Some(lines) = self.lines.as_mut() {
+            let symbol = self.module.declarations().get_function_decl(func_id).linkage_name(func_id);
+            lines.record(name, &symbol, func_id, ctx);
+        }
+    }
+
+    #[cfg(not(feature = "debug-info"))]
+    fn record_lines(&mut self, _name: &str, _func_id: FuncId, _ctx: &cranelift_codegen::Context) {}
+
     /// Generate Cranelift IR text for a module (without compiling to object).
+    /// If `func_indices` is Some, only those functions are printed (CGU mode).
+    ///
+    /// With `json_output` the result is a JSON document for editor tooling:
+    /// each printed function keyed by symbol with its IR and validation
+    /// findings, plus the module statistics. Functions that fail validation
+    /// are listed with their findings and `"ir": null` instead of failing
+    /// the whole call.
     pub fn generate_ir_text(
         &mut self,
         mir: &crate::mir_types::Module,
+        func_indices: Option<&[usize]>,
     ) -> BridgeResult<String> {
         // Initialize runtime names before any declarations
         self.init_runtime_names();
@@ -411,20 +1014,54 @@ impl ModuleTranslator {
             self.declare_function(func)?;
         }
         self.declare_runtime_functions()?;
+        self.fold_constant_functions(mir);
+
+        let mut selected: Vec<(usize, &Function)> = match func_indices {
+            Some(idx) => idx
+                .iter()
+                .filter_map(|&i| mir.functions.get(i).map(|f| (i, f)))
+                .collect(),
+            None => mir.functions.iter().enumerate().collect(),
+        };
+        selected.retain(|(_, f)| self.ir_selected(f));
+        if self.options.ir_sort {
+            selected.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+        }
 
+        let json_output = self.options.json_output;
         let mut ir_text = String::new();
-        for func in &mir.functions {
+        let mut json_functions = Vec::new();
+        for (func_index, func) in selected {
+            cancel::check(self.options.cancel.as_ref())?;
+            memory::check()?;
             let func_id = *self.func_ids.get(&func.name).unwrap();
+            let symbol = self.module.declarations().get_function_decl(func_id).linkage_name(func_id).into_owned();
+            let first_diag = self.diagnostics.len();
+            if json_output {
+                let diags = validate::check_function(func);
+                let invalid = diags.iter().any(|d| d.severity == Severity::Error);
+                for diag in diags {
+                    self.add_diagnostic(diag);
+                }
+                if invalid {
+                    let findings = &self.diagnostics[first_diag..];
+                    json_functions.push(function_json(func_index, func, &symbol, None, findings));
+                    continue;
+                }
+            } else {
+                self.validate_function(func)?;
+            }
             let sig = self.build_signature(func);
             let mut cl_func = ClifFunc::with_name_signature(
                 cranelift_codegen::ir::UserFuncName::user(0, func_id.as_u32()),
                 sig,
             );
 
+            let float_mode = self.float_mode();
             let mut fb_ctx = FunctionBuilderContext::new();
             let mut builder = FunctionBuilder::new(&mut cl_func, &mut fb_ctx);
 
-            {
+            let names = {
                 let mut ftx = FunctionTranslator::new(
                     &mut builder,
                     &mut self.func_ids,
@@ -432,25 +1069,89 @@ impl ModuleTranslator {
                     &self.enum_defs,
                     &mut self.module,
                     func,
-                    &self.runtime_names,
+                    &self.symbols,
                 );
+                ftx.configure(&self.options, float_mode, &self.const_fns, func_index);
                 ftx.translate()?;
-            }
+                ftx.debug_names()
+            };
             builder.finalize();
-
-            ir_text.push_str(&format!("; Function: {}\n", func.name));
-            ir_text.push_str(&cl_func.display().to_string());
-            ir_text.push('\n');
+            self.check_frame_size(func, &cl_func);
+            if json_output {
+                let ir = clif_text::write_function(&cl_func, &names);
+                let findings = &self.diagnostics[first_diag..];
+                json_functions.push(function_json(func_index, func, &symbol, Some(&ir), findings));
+                continue;
+            }
+            ir_text.push_str(&self.function_ir_text(func_index, func, func_id, &cl_func, &names, first_diag));
         }
 
+        if json_output {
+            return Ok(format!(
+                "{{\"module\":{},\"functions\":{{{}}},\"stats\":{}}}",
+                json::string(&mir.name),
+                json_functions.join(","),
+                MirStats::collect(mir).to_json()
+            ));
+        }
         Ok(ir_text)
     }
 }
 
+/// Define the machine code compiled into `ctx` as the body of `func_id`.
+fn define_compiled(module: &mut ObjectModule, func_id: FuncId, ctx: &cranelift_codegen::Context) -> Result<(), String> {
+    let buffer = &ctx.compiled_code().expect("compiled before defining").buffer;
+    let relocs: Vec<_> = buffer
+        .relocs()
+        .iter()
+        .map(|r| cranelift_module::ModuleReloc::from_mach_reloc(r, &ctx.func, func_id))
+        .collect();
+    module
+        .define_function_bytes(func_id, buffer.alignment as u64, buffer.data(), &relocs)
+        .map_err(|e| format!("{:?}", e))
+}
+
+/// Turn the outcome of compiling and defining MIR function `name`, or the
+/// panic it raised, into the bridge result.
+fn definition_result(name: &str, result: std::thread::Result<Result<(), String>>) -> BridgeResult<()> {
+    match result {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(BridgeError::Codegen(format!(
+            "failed to define function '{}': {}",
+            name, e
+        ))),
+        Err(panic_info) => {
+            Err(BridgeError::Panic(format!("in function '{}': {}", name, crate::panic_message(panic_info))))
+        }
+    }
+}
+
+/// One `functions` member of the JSON IR document.
+fn function_json(
+    func_index: usize,
+    func: &Function,
+    symbol: &str,
+    ir: Option<&str>,
+    findings: &[Diagnostic],
+) -> String {
+    let findings: Vec<String> = findings.iter().map(Diagnostic::to_json).collect();
+    format!(
+        "{}:{{\"name\":{},\"index\":{},\"ir\":{},\"findings\":[{}]}}",
+        json::string(symbol),
+        json::string(&func.name),
+        func_index,
+        ir.map_or_else(|| "null".to_string(), json::string),
+        findings.join(",")
+    )
+}
+
+/// Incoming (value_id, from_block_id) pairs of a single phi.
+type PhiIncoming = Vec<(ValueId, u32)>;
+
 /// Phi information collected in a pre-pass.
 struct PhiInfo {
-    /// block_id -> list of (result_value_id, incoming_pairs: Vec<(value_id, from_block_id)>)
-    block_params: HashMap<u32, Vec<(ValueId, Vec<(ValueId, u32)>)>>,
+    /// block_id -> list of (result_value_id, incoming_pairs)
+    block_params: HashMap<u32, Vec<(ValueId, PhiIncoming)>>,
 }
 
 /// Per-function translation state.
@@ -461,20 +1162,194 @@ struct FunctionTranslator<'a, 'b> {
     enum_defs: &'a HashMap<String, Vec<EnumVariant>>,
     module: &'a mut ObjectModule,
     mir_func: &'a Function,
-    /// C runtime function names (no tml_ prefix)
-    runtime_names: &'a std::collections::HashSet<String>,
     /// Maps MIR ValueId → Cranelift Value
     values: HashMap<ValueId, ClifValue>,
     /// Maps MIR block id → Cranelift Block
     blocks: HashMap<u32, Block>,
     /// Maps alloca result_id → StackSlot
     alloca_slots: HashMap<ValueId, cranelift_codegen::ir::StackSlot>,
+    /// Type stores to each stack-slot alloca are coerced to, from the local's
+    /// own MIR type: a slot shared with wider locals is larger than it
+    alloca_store_types: HashMap<ValueId, cranelift_codegen::ir::Type>,
     /// Phi info (block parameters)
     phi_info: PhiInfo,
     /// String constants data section
     string_data: HashMap<String, cranelift_module::DataId>,
+    /// UTF-16 string constants data section
+    wide_string_data: HashMap<String, cranelift_module::DataId>,
     /// Maps MIR ValueId → inferred Cranelift type (from instruction analysis)
     value_types: HashMap<ValueId, cranelift_codegen::ir::Type>,
+    /// Maps MIR ValueId → integer signedness (true = signed), where known
+    signedness: HashMap<ValueId, bool>,
+    /// Index of this function in the MIR module when trace hooks are enabled
+    trace_func_index: Option<u32>,
+    /// Fast-math freedoms for float arithmetic
+    float_mode: FloatMode,
+    /// Lower traps to calls to `TRAP_ABORT_IMPORT` (see `traps`)
+    trap_abort: bool,
+    /// `source_file` option: with `trap_abort`, traps after a located
+    /// instruction call `TRAP_ABORT_AT_IMPORT` with the file and line
+    source_file: &'a str,
+    /// Location of the last located instruction of the current block
+    loc: SourceLoc,
+    /// Runtime calls must match the signature manifest exactly
+    strict_runtime_calls: bool,
+    /// Maps alloca stack slots → MIR local names (for annotated CLIF text)
+    slot_names: HashMap<cranelift_codegen::ir::StackSlot, String>,
+    /// Allocas only accessed through direct Load/Store (see `non_escaping_allocas`)
+    private_allocas: HashSet<ValueId>,
+    /// Last value stored to each private alloca in the current block
+    slot_values: HashMap<cranelift_codegen::ir::StackSlot, ClifValue>,
+    /// Scalar allocas promoted to SSA variables (alloca result_id → variable)
+    promoted: HashMap<ValueId, (Variable, cranelift_codegen::ir::Type)>,
+    /// MIR values that are integer/bool constants (for branch folding), with
+    /// the width in bits they are emitted at
+    const_ints: HashMap<ValueId, (i64, u32)>,
+    /// Let allocas with disjoint lifetimes share stack slots
+    share_slots: bool,
+    /// Slot sharing plan (empty unless `share_slots`)
+    slot_plan: SlotPlan,
+    /// Stack slot created for each shared group
+    group_slots: HashMap<usize, cranelift_codegen::ir::StackSlot>,
+    /// Results of constant functions (see `const_eval`), used instead of calls
+    const_calls: Option<&'a HashMap<String, Constant>>,
+    /// Pool floats once a function has more distinct ones (0 = never)
+    const_pool_threshold: usize,
+    /// Shared and pooled scalar constants (see `const_pool`)
+    const_plan: ConstPlan,
+    /// Values of the shared constants, with the MIR block they are in
+    const_values: HashMap<ConstKey, (ClifValue, u32)>,
+    /// Immediate dominator of each MIR block (see `validate`)
+    idom: HashMap<u32, u32>,
+    /// MIR block being translated
+    block_id: u32,
+    /// Address of the function's float pool
+    pool_base: Option<ClifValue>,
+    /// Removed phis → the value they always carried (see `simplify_phis`)
+    phi_aliases: HashMap<ValueId, ValueId>,
+    /// How names become symbols, for the functions a call declares
+    symbols: &'a SymbolScheme,
+}
+
+/// Runtime hook called at every block head in trace mode.
+const TRACE_HOOK: &str = "__tml_trace";
+
+/// Fill-initialized arrays up to this many elements are stored inline.
+const MAX_UNROLLED_FILL: u64 = 16;
+/// Largest `[value; count]` array placed in a stack slot (1 MiB).
+const MAX_FILL_ARRAY_SIZE: u32 = 1 << 20;
+
+/// Allocas whose address is only ever used as the pointer operand of a
+/// `Load`/`Store`. Nothing else can observe or modify their memory.
+fn non_escaping_allocas(func: &Function) -> HashSet<ValueId> {
+    let mut allocas: HashSet<ValueId> = func
+        .blocks
+        .iter()
+        .flat_map(|b| &b.instructions)
+        .filter(|i| matches!(i.inst, Instruction::Alloca { .. }))
+        .map(|i| i.result)
+        .collect();
+
+    for block in &func.blocks {
+        for inst in &block.instructions {
+            match &inst.inst {
+                Instruction::Load { .. } => {}
+                Instruction::Store { value, .. } => {
+                    allocas.remove(&value.id);
+                }
+                other => other.for_each_operand(|v| {
+                    allocas.remove(&v.id);
+                }),
+            }
+        }
+        if let Some(term) = &block.terminator {
+            term.for_each_operand(|v| {
+                allocas.remove(&v.id);
+            });
+        }
+    }
+    allocas
+}
+
+/// Bit pattern of switch case `value` in a `bits`-wide discriminant, or None
+/// if no value of that width can equal it. `signed` is the discriminant's
+/// signedness when known; unknown accepts either reading. 64-bit cases are
+/// stored as i64, so any value is a valid u64 bit pattern.
+pub(crate) fn switch_case_pattern(value: i64, bits: u32, signed: Option<bool>) -> Option<u128> {
+    if bits >= 64 {
+        return Some(value as u64 as u128);
+    }
+    let fits_signed = value >= -(1i64 << (bits - 1)) && value < (1i64 << (bits - 1));
+    let fits_unsigned = value >= 0 && value < (1i64 << bits);
+    let fits = match signed {
+        Some(true) => fits_signed,
+        Some(false) => fits_unsigned,
+        None => fits_signed || fits_unsigned,
+    };
+    if !fits {
+        return None;
+    }
+    Some((value as u64 & ((1u64 << bits) - 1)) as u128)
+}
+
+/// Bit pattern of an integer constant emitted at `bits` bits, in the form
+/// `switch_case_pattern` gives case values.
+fn const_bit_pattern(value: i64, bits: u32) -> u128 {
+    if bits >= 64 {
+        return value as u64 as u128;
+    }
+    (value as u64 & ((1u64 << bits) - 1)) as u128
+}
+
+/// Signedness of an integer MIR type; None for non-integers and bool.
+pub(crate) fn int_signedness(ty: &MirType) -> Option<bool> {
+    match ty {
+        MirType::Primitive(p) if p.is_integer() => Some(p.is_signed()),
+        _ => None,
+    }
+}
+
+/// How MIR function names become linker symbols: the name with the
+/// `symbol_prefix` (`tml_` unless set), except for C runtime functions and
+/// names that already carry the prefix, then the `symbol_renames`.
+struct SymbolScheme {
+    prefix: String,
+    renames: HashMap<String, String>,
+    /// C runtime function names, filled in before any declaration
+    runtime_names: HashSet<String>,
+}
+
+impl SymbolScheme {
+    fn new(options: &BridgeOptions) -> Self {
+        Self {
+            prefix: options.symbol_prefix.clone().unwrap_or_else(|| DEFAULT_SYMBOL_PREFIX.to_string()),
+            renames: options.symbol_renames.iter().cloned().collect(),
+            runtime_names: HashSet::new(),
+        }
+    }
+
+    fn symbol(&self, mir_name: &str) -> String {
+        let bare =
+            self.prefix.is_empty() || mir_name.starts_with(&self.prefix) || self.runtime_names.contains(mir_name);
+        self.renamed(if bare { mir_name.to_string() } else { format!("{}{}", self.prefix, mir_name) })
+    }
+
+    /// `symbol` as `symbol_renames` maps it.
+    fn renamed(&self, symbol: String) -> String {
+        self.renames.get(&symbol).cloned().unwrap_or(symbol)
+    }
+}
+
+/// Fail on a NUL inside a literal passed to the runtime as a NUL-terminated
+/// string, which would silently end it early.
+fn reject_interior_nul(kind: &str, s: &str) -> BridgeResult<()> {
+    match s.find('\0') {
+        Some(at) => Err(BridgeError::UnsupportedInstruction(format!(
+            "{} literal {:?} has a NUL at byte {}; the runtime takes NUL-terminated strings",
+            kind, s, at
+        ))),
+        None => Ok(()),
+    }
 }
 
 fn make_stack_slot(size: u32) -> StackSlotData {
@@ -489,7 +1364,7 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
         enum_defs: &'a HashMap<String, Vec<EnumVariant>>,
         module: &'a mut ObjectModule,
         mir_func: &'a Function,
-        runtime_names: &'a std::collections::HashSet<String>,
+        symbols: &'a SymbolScheme,
     ) -> Self {
         Self {
             builder,
@@ -498,44 +1373,107 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
             enum_defs,
             module,
             mir_func,
-            runtime_names,
+            symbols,
             values: HashMap::new(),
             blocks: HashMap::new(),
             alloca_slots: HashMap::new(),
+            alloca_store_types: HashMap::new(),
             phi_info: PhiInfo {
                 block_params: HashMap::new(),
             },
             string_data: HashMap::new(),
+            wide_string_data: HashMap::new(),
             value_types: HashMap::new(),
+            signedness: HashMap::new(),
+            trace_func_index: None,
+            float_mode: FloatMode::default(),
+            trap_abort: false,
+            source_file: "",
+            loc: SourceLoc::default(),
+            strict_runtime_calls: false,
+            slot_names: HashMap::new(),
+            private_allocas: non_escaping_allocas(mir_func),
+            slot_values: HashMap::new(),
+            promoted: HashMap::new(),
+            const_ints: HashMap::new(),
+            share_slots: false,
+            slot_plan: SlotPlan::default(),
+            group_slots: HashMap::new(),
+            const_calls: None,
+            const_pool_threshold: 0,
+            const_plan: ConstPlan::default(),
+            const_values: HashMap::new(),
+            idom: HashMap::new(),
+            block_id: 0,
+            pool_base: None,
+            phi_aliases: HashMap::new(),
+        }
+    }
+
+    /// Take the per-function settings from `options`, so the object and IR
+    /// text paths translate a function the same way. `func_index` is the
+    /// function's index in the MIR module, for trace hooks.
+    fn configure(
+        &mut self,
+        options: &'a BridgeOptions,
+        float_mode: FloatMode,
+        const_calls: &'a HashMap<String, Constant>,
+        func_index: usize,
+    ) {
+        if options.trace_hooks {
+            self.trace_func_index = Some(func_index as u32);
         }
+        self.float_mode = float_mode;
+        self.trap_abort = options.trap_abort;
+        self.source_file = &options.source_file;
+        self.strict_runtime_calls = options.strict_runtime_calls;
+        self.share_slots = options.opt_level > 0;
+        self.const_calls = Some(const_calls);
+        self.const_pool_threshold = options.const_pool_threshold;
     }
 
     /// Resolve a MIR function name to the linker symbol name.
     fn resolve_symbol_name(&self, mir_name: &str) -> String {
-        if mir_name.starts_with("tml_") {
-            return mir_name.to_string();
-        }
-        if self.runtime_names.contains(mir_name) {
-            return mir_name.to_string();
-        }
-        format!("tml_{}", mir_name)
+        self.symbols.symbol(mir_name)
     }
 
     fn translate(&mut self) -> BridgeResult<()> {
         // Pre-pass: determine types for all values (needed for phi type inference)
         self.collect_value_types();
 
+        // Pre-pass: turn scalar locals that never escape into SSA variables
+        self.promote_allocas();
+
+        // Pre-pass: overlap the slots of allocas with disjoint lifetimes
+        if self.share_slots {
+            let promoted = self.promoted.keys().copied().collect();
+            self.slot_plan = slot_lifetimes::plan(self.mir_func, &promoted);
+        }
+
         // Pre-pass: collect phi instructions to convert to block parameters
         self.collect_phi_info();
 
-        // Create Cranelift blocks
-        for block in &self.mir_func.blocks {
+        // Pre-pass: fold constant branch conditions and find the live blocks
+        self.collect_const_ints();
+        let live = self.reachable_blocks();
+
+        // Pre-pass: drop phis that can only carry one value
+        self.simplify_phis(&live);
+
+        // Pre-pass: share repeated constants and pick the floats to pool
+        let big_endian = self.module.isa().endianness() == Endianness::Big;
+        self.idom = validate::immediate_dominators(self.mir_func);
+        self.const_plan =
+            const_pool::plan(self.mir_func, &live, &self.idom, self.const_pool_threshold, big_endian);
+
+        // Create Cranelift blocks (dead blocks are never created or translated)
+        for block in self.mir_func.blocks.iter().filter(|b| live.contains(&b.id)) {
             let cl_block = self.builder.create_block();
             self.blocks.insert(block.id, cl_block);
         }
 
         // Add block parameters for phi nodes
-        for block in &self.mir_func.blocks {
+        for block in self.mir_func.blocks.iter().filter(|b| live.contains(&b.id)) {
             let cl_block = self.blocks[&block.id];
             if let Some(phis) = self.phi_info.block_params.get(&block.id) {
                 for (result_id, _incoming) in phis {
@@ -570,19 +1508,37 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
         }
 
         self.builder.switch_to_block(entry_block);
+        self.materialize_pool()?;
 
         // Translate each block
         for (block_idx, block) in self.mir_func.blocks.iter().enumerate() {
+            if !live.contains(&block.id) {
+                continue;
+            }
             if block_idx > 0 {
                 let cl_block = self.blocks[&block.id];
                 self.builder.switch_to_block(cl_block);
             }
 
+            // Forwarded values are only known to dominate within one block
+            self.slot_values.clear();
+            self.block_id = block.id;
+            self.materialize_shared_constants();
+
+            if let Some(func_index) = self.trace_func_index {
+                self.emit_trace_hook(func_index, block.id)?;
+            }
+
             // Translate instructions (skip phi nodes — already handled as block params)
+            self.loc = SourceLoc::default();
             for inst_data in &block.instructions {
                 if matches!(&inst_data.inst, Instruction::Phi { .. }) {
                     continue;
                 }
+                if inst_data.loc.is_known() {
+                    self.loc = inst_data.loc;
+                    self.builder.set_srcloc(cranelift_codegen::ir::SourceLoc::new(inst_data.loc.line));
+                }
                 self.translate_instruction(inst_data)?;
             }
 
@@ -600,14 +1556,240 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
         Ok(())
     }
 
-    /// Pre-pass: collect all phi instructions and group by block.
+    /// MIR block and local names for the translated function.
+    fn debug_names(&self) -> DebugNames {
+        DebugNames {
+            blocks: self
+                .mir_func
+                .blocks
+                .iter()
+                .filter_map(|b| Some((*self.blocks.get(&b.id)?, (b.id, b.name.clone()))))
+                .collect(),
+            slots: self.slot_names.clone(),
+        }
+    }
+
+    /// End the current block with a trap. Under `trap_abort` the host's
+    /// abort import is called first with the trap's wasm category, and with
+    /// the source file and line when both are known.
+    fn emit_trap(&mut self, code: TrapCode) -> BridgeResult<()> {
+        if self.trap_abort {
+            let located = !self.source_file.is_empty() && self.loc.is_known();
+            let import = if located { TRAP_ABORT_AT_IMPORT } else { TRAP_ABORT_IMPORT };
+            let func_id = *self.func_ids.get(import).ok_or_else(|| {
+                BridgeError::Translation(format!("{} not declared", import))
+            })?;
+            let func_ref = self
+                .module
+                .declare_func_in_func(func_id, self.builder.func);
+            let category = TrapCategory::of(code) as i64;
+            let mut args = vec![self.builder.ins().iconst(types::I32, category)];
+            if located {
+                let loc = self.loc;
+                args.push(self.translate_string_constant(self.source_file)?);
+                args.push(self.builder.ins().iconst(types::I32, loc.line as i64));
+                args.push(self.builder.ins().iconst(types::I32, loc.column as i64));
+            }
+            self.builder.ins().call(func_ref, &args);
+        }
+        self.builder.ins().trap(code);
+        Ok(())
+    }
+
+    /// Branch to an aborting block when `lhs / rhs` would trap: a zero
+    /// divisor and, with `check_overflow`, `MIN / -1`. Translation continues
+    /// in a fresh block where the division is safe. i128 keeps the native
+    /// overflow trap, since its MIN does not fit an immediate.
+    fn guard_division(
+        &mut self,
+        lhs: ClifValue,
+        rhs: ClifValue,
+        check_overflow: bool,
+    ) -> BridgeResult<()> {
+        let bits = self.builder.func.dfg.value_type(rhs).bits();
+        let is_zero = self.builder.ins().icmp_imm(IntCC::Equal, rhs, 0);
+        let mut checks = vec![(is_zero, TrapCode::INTEGER_DIVISION_BY_ZERO)];
+        if check_overflow && bits <= 64 {
+            let min = i64::MIN >> (64 - bits);
+            let is_min = self.builder.ins().icmp_imm(IntCC::Equal, lhs, min);
+            let is_neg_one = self.builder.ins().icmp_imm(IntCC::Equal, rhs, -1);
+            let overflows = self.builder.ins().band(is_min, is_neg_one);
+            checks.push((overflows, TrapCode::INTEGER_OVERFLOW));
+        }
+        for (cond, code) in checks {
+            let trap_block = self.builder.create_block();
+            let cont = self.builder.create_block();
+            self.builder.set_cold_block(trap_block);
+            self.builder.ins().brif(cond, trap_block, &[], cont, &[]);
+            self.builder.switch_to_block(trap_block);
+            self.emit_trap(code)?;
+            self.builder.switch_to_block(cont);
+        }
+        Ok(())
+    }
+
+    /// Call `__tml_trace(func_index, block_id)` at the current position.
+    fn emit_trace_hook(&mut self, func_index: u32, block_id: u32) -> BridgeResult<()> {
+        let func_id = *self.func_ids.get(TRACE_HOOK).ok_or_else(|| {
+            BridgeError::Translation(format!("{} not declared", TRACE_HOOK))
+        })?;
+        let func_ref = self
+            .module
+            .declare_func_in_func(func_id, self.builder.func);
+        let func_arg = self.builder.ins().iconst(types::I32, func_index as i64);
+        let block_arg = self.builder.ins().iconst(types::I32, block_id as i64);
+        self.builder.ins().call(func_ref, &[func_arg, block_arg]);
+        Ok(())
+    }
+
+    /// Pre-pass: record integer and bool constants by value id.
+    fn collect_const_ints(&mut self) {
+        for inst in self.mir_func.blocks.iter().flat_map(|b| &b.instructions) {
+            let constant = match &inst.inst {
+                Instruction::Constant(Constant::Bool(b)) => (*b as i64, 8),
+                Instruction::Constant(Constant::Int { value, bit_width, .. }) => {
+                    (*value, const_pool::int_type(*bit_width).bits())
+                }
+                _ => continue,
+            };
+            self.const_ints.insert(inst.result, constant);
+        }
+    }
+
+    /// Successors of `term` once constant conditions are folded. Constants
+    /// are compared as bit patterns of their width, as the unfolded branch
+    /// and `translate_switch` compare them.
+    fn live_successors(&self, term: &Terminator) -> Vec<u32> {
+        match term {
+            Terminator::Branch { target } => vec![*target],
+            Terminator::CondBranch {
+                condition,
+                true_block,
+                false_block,
+            } => match self.const_ints.get(&condition.id) {
+                Some(&(c, bits)) => vec![if const_bit_pattern(c, bits) != 0 { *true_block } else { *false_block }],
+                None => vec![*true_block, *false_block],
+            },
+            Terminator::Switch {
+                discriminant,
+                cases,
+                default_block,
+            } => match self.const_ints.get(&discriminant.id) {
+                Some(&(d, bits)) => {
+                    let pattern = const_bit_pattern(d, bits);
+                    let signedness = self.signedness.get(&discriminant.id).copied();
+                    vec![cases
+                        .iter()
+                        .find(|&&(v, _)| switch_case_pattern(v, bits, signedness) == Some(pattern))
+                        .map_or(*default_block, |(_, b)| *b)]
+                }
+                None => cases
+                    .iter()
+                    .map(|(_, b)| *b)
+                    .chain(std::iter::once(*default_block))
+                    .collect(),
+            },
+            Terminator::Return { .. } | Terminator::Unreachable => Vec::new(),
+        }
+    }
+
+    /// Blocks reachable from the entry block over live (folded) edges.
+    fn reachable_blocks(&self) -> HashSet<u32> {
+        let by_id: HashMap<u32, &BasicBlock> =
+            self.mir_func.blocks.iter().map(|b| (b.id, b)).collect();
+        let mut live = HashSet::new();
+        let mut worklist: Vec<u32> = self.mir_func.blocks.first().map(|b| b.id).into_iter().collect();
+        while let Some(id) = worklist.pop() {
+            if !live.insert(id) {
+                continue;
+            }
+            if let Some(term) = by_id.get(&id).and_then(|b| b.terminator.as_ref()) {
+                worklist.extend(
+                    self.live_successors(term)
+                        .into_iter()
+                        .filter(|s| by_id.contains_key(s)),
+                );
+            }
+        }
+        live
+    }
+
+    /// mem2reg: promote private scalar allocas to `cranelift_frontend` variables.
+    ///
+    /// Only allocas whose stores all have a known, convertible value type are
+    /// promoted; the rest keep their stack slot (and store-to-load forwarding).
+    fn promote_allocas(&mut self) {
+        let mut candidates: HashMap<ValueId, cranelift_codegen::ir::Type> = HashMap::new();
+        for inst in self.mir_func.blocks.iter().flat_map(|b| &b.instructions) {
+            if let Instruction::Alloca { alloc_type, .. } = &inst.inst
+                && self.private_allocas.contains(&inst.result)
+                && let Some(cl_ty) = ty::mir_type_to_cranelift(alloc_type)
+                && cl_ty.bytes() <= 8
+                && ty::type_size(alloc_type) == cl_ty.bytes()
+            {
+                candidates.insert(inst.result, cl_ty);
+            }
+        }
+
+        for inst in self.mir_func.blocks.iter().flat_map(|b| &b.instructions) {
+            if let Instruction::Store { ptr, value } = &inst.inst
+                && let Some(&slot_ty) = candidates.get(&ptr.id)
+            {
+                let convertible = self
+                    .value_types
+                    .get(&value.id)
+                    .is_some_and(|&t| t == slot_ty || (t.is_int() && slot_ty.is_int()));
+                if !convertible {
+                    candidates.remove(&ptr.id);
+                }
+            }
+        }
+
+        for (alloca_id, cl_ty) in candidates {
+            let var = self.builder.declare_var(cl_ty);
+            self.promoted.insert(alloca_id, (var, cl_ty));
+        }
+    }
+
+    /// Convert `val` to `target`: integer resize, float promote/demote, or a
+    /// same-size bitcast. Returns None when no sensible conversion exists.
+    fn coerce_value(
+        &mut self,
+        val: ClifValue,
+        target: cranelift_codegen::ir::Type,
+    ) -> Option<ClifValue> {
+        let val_ty = self.builder.func.dfg.value_type(val);
+        if val_ty == target {
+            return Some(val);
+        }
+        let ins = self.builder.ins();
+        Some(if val_ty.is_int() && target.is_int() {
+            if val_ty.bytes() < target.bytes() {
+                ins.sextend(target, val)
+            } else {
+                ins.ireduce(target, val)
+            }
+        } else if val_ty.is_float() && target.is_float() {
+            if val_ty.bytes() < target.bytes() {
+                ins.fpromote(target, val)
+            } else {
+                ins.fdemote(target, val)
+            }
+        } else if val_ty.bytes() == target.bytes() {
+            ins.bitcast(target, MemFlags::new(), val)
+        } else {
+            return None;
+        })
+    }
+
+    /// Pre-pass: collect all phi instructions and group by block.
     fn collect_phi_info(&mut self) {
         for block in &self.mir_func.blocks {
             let mut phis = Vec::new();
             for inst in &block.instructions {
                 if let Instruction::Phi { incoming } = &inst.inst {
                     // Convert Vec<(Value, u32)> to Vec<(ValueId, u32)>
-                    let converted: Vec<(ValueId, u32)> =
+                    let converted: PhiIncoming =
                         incoming.iter().map(|(v, b)| (v.id, *b)).collect();
                     phis.push((inst.result, converted));
                 }
@@ -618,18 +1800,79 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
         }
     }
 
+    /// Remove trivially redundant phis: those whose incoming values from live
+    /// blocks are all one value `w`, ignoring the phi itself (a loop that
+    /// passes it around unchanged). In SSA form `w` dominates the phi's block,
+    /// so its uses read `w` instead, and the block loses a parameter and every
+    /// branch to it an argument. Removing one phi can make another trivial,
+    /// so this runs to a fixpoint. Phis whose type would change are kept.
+    fn simplify_phis(&mut self, live: &HashSet<u32>) {
+        let mut block_ids: Vec<u32> = self.phi_info.block_params.keys().copied().collect();
+        block_ids.retain(|id| live.contains(id));
+        block_ids.sort_unstable();
+        loop {
+            let mut removed = false;
+            for block_id in &block_ids {
+                let Some(phis) = self.phi_info.block_params.get_mut(block_id) else {
+                    continue;
+                };
+                phis.retain(|(result, incoming)| {
+                    let resolve = |mut id: ValueId| {
+                        while let Some(&next) = self.phi_aliases.get(&id) {
+                            id = next;
+                        }
+                        id
+                    };
+                    let mut values = incoming
+                        .iter()
+                        .filter(|(_, pred)| live.contains(pred))
+                        .map(|&(id, _)| resolve(id))
+                        .filter(|id| id != result);
+                    let Some(only) = values.next() else {
+                        return true;
+                    };
+                    let same_type = match (self.value_types.get(&only), self.value_types.get(result)) {
+                        (Some(a), Some(b)) => a == b,
+                        (Some(_), None) => true,
+                        (None, _) => false,
+                    };
+                    if only == u32::MAX || !same_type || values.any(|id| id != only) {
+                        return true;
+                    }
+                    self.phi_aliases.insert(*result, only);
+                    removed = true;
+                    false
+                });
+            }
+            if !removed {
+                break;
+            }
+        }
+        self.phi_info.block_params.retain(|_, phis| !phis.is_empty());
+
+        // Resolve chains so a single lookup finds the surviving value
+        let ids: Vec<ValueId> = self.phi_aliases.keys().copied().collect();
+        for id in ids {
+            let mut target = self.phi_aliases[&id];
+            while let Some(&next) = self.phi_aliases.get(&target) {
+                target = next;
+            }
+            self.phi_aliases.insert(id, target);
+        }
+    }
+
     /// Infer the Cranelift type for a phi node by looking at incoming values.
     fn infer_phi_type(&self, result_id: ValueId) -> cranelift_codegen::ir::Type {
         // Look at phi incoming values to determine the type
         for block in &self.mir_func.blocks {
             for inst in &block.instructions {
-                if inst.result == result_id {
-                    if let Instruction::Phi { incoming } = &inst.inst {
-                        // Use the type of the first incoming value
-                        for (val, _block_id) in incoming {
-                            if let Some(&ty) = self.value_types.get(&val.id) {
-                                return ty;
-                            }
+                if inst.result == result_id
+                    && let Instruction::Phi { incoming } = &inst.inst
+                {
+                    // Use the type of the first incoming value
+                    for (val, _block_id) in incoming {
+                        if let Some(&ty) = self.value_types.get(&val.id) {
+                            return ty;
                         }
                     }
                 }
@@ -648,16 +1891,19 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
             } else {
                 // Unit type or unmappable — skip
             }
+            if let Some(signed) = int_signedness(&param.ty) {
+                self.signedness.insert(param.value_id, signed);
+            }
         }
 
         // First pass: collect alloca types (alloca result_id → the type being allocated)
         let mut alloca_types: HashMap<ValueId, cranelift_codegen::ir::Type> = HashMap::new();
         for block in &self.mir_func.blocks {
             for inst in &block.instructions {
-                if let Instruction::Alloca { alloc_type, .. } = &inst.inst {
-                    if let Some(cl_ty) = ty::mir_type_to_cranelift(alloc_type) {
-                        alloca_types.insert(inst.result, cl_ty);
-                    }
+                if let Instruction::Alloca { alloc_type, .. } = &inst.inst
+                    && let Some(cl_ty) = ty::mir_type_to_cranelift(alloc_type)
+                {
+                    alloca_types.insert(inst.result, cl_ty);
                 }
             }
         }
@@ -680,7 +1926,7 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
                             if *is_f64 { Some(types::F64) } else { Some(types::F32) }
                         },
                         Constant::Bool(_) => Some(types::I8),
-                        Constant::String(_) => Some(POINTER_TYPE),
+                        Constant::String(_) | Constant::WideString(_) => Some(POINTER_TYPE),
                         Constant::Unit => None,
                     },
                     Instruction::Binary { op, left, right } => {
@@ -735,6 +1981,7 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
                     Instruction::EnumInit { .. } => Some(POINTER_TYPE),
                     Instruction::TupleInit { .. } => Some(POINTER_TYPE),
                     Instruction::ArrayInit { .. } => Some(POINTER_TYPE),
+                    Instruction::ArrayFill { .. } => Some(POINTER_TYPE),
                     Instruction::Phi { incoming } => {
                         // Try to get type from incoming values
                         incoming.iter()
@@ -747,6 +1994,75 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
                 }
             }
         }
+
+        self.collect_signedness();
+    }
+
+    /// Infer integer signedness from the typed places MIR has: parameters,
+    /// literals, casts, call returns and allocas, propagated through
+    /// arithmetic, selects and phis.
+    fn collect_signedness(&mut self) {
+        let mut alloca_signedness: HashMap<ValueId, bool> = HashMap::new();
+        for block in &self.mir_func.blocks {
+            for inst in &block.instructions {
+                if let Instruction::Alloca { alloc_type, .. } = &inst.inst
+                    && let Some(signed) = int_signedness(alloc_type)
+                {
+                    alloca_signedness.insert(inst.result, signed);
+                }
+            }
+        }
+
+        for block in &self.mir_func.blocks {
+            for inst in &block.instructions {
+                let known = |v: &Value| self.signedness.get(&v.id).copied();
+                let signed = match &inst.inst {
+                    Instruction::Constant(Constant::Int { is_signed, .. }) => Some(*is_signed),
+                    Instruction::Binary { op, left, right } if !op.is_comparison() => {
+                        known(left).or(known(right))
+                    }
+                    Instruction::Unary { operand, .. } => known(operand),
+                    Instruction::Cast { target_type, .. } => int_signedness(target_type),
+                    Instruction::Call { return_type, .. }
+                    | Instruction::MethodCall { return_type, .. } => int_signedness(return_type),
+                    Instruction::Load { ptr } => alloca_signedness.get(&ptr.id).copied(),
+                    Instruction::Select {
+                        true_val,
+                        false_val,
+                        ..
+                    } => known(true_val).or(known(false_val)),
+                    Instruction::Phi { incoming } => incoming.iter().find_map(|(v, _)| known(v)),
+                    _ => None,
+                };
+                if let Some(signed) = signed {
+                    self.signedness.insert(inst.result, signed);
+                }
+            }
+        }
+    }
+
+    /// True when integer operands should be treated as unsigned: one side is
+    /// known unsigned and the other is not known signed.
+    fn is_unsigned_pair(&self, a: &Value, b: &Value) -> bool {
+        let (a, b) = (self.signedness.get(&a.id), self.signedness.get(&b.id));
+        matches!(
+            (a, b),
+            (Some(false), Some(false) | None) | (None, Some(false))
+        )
+    }
+
+    /// Widen an integer to `target`, zero-extending unsigned values.
+    fn extend_int(
+        &mut self,
+        val: ClifValue,
+        target: cranelift_codegen::ir::Type,
+        unsigned: bool,
+    ) -> ClifValue {
+        if unsigned {
+            self.builder.ins().uextend(target, val)
+        } else {
+            self.builder.ins().sextend(target, val)
+        }
     }
 
     fn get_value(&mut self, val: &Value) -> BridgeResult<ClifValue> {
@@ -754,12 +2070,16 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
         if val.id == u32::MAX {
             return Ok(self.builder.ins().iconst(types::I64, 0));
         }
-        if let Some(&v) = self.values.get(&val.id) {
+        let id = *self.phi_aliases.get(&val.id).unwrap_or(&val.id);
+        if let Some(&v) = self.values.get(&id) {
+            return Ok(v);
+        }
+        if let Some(v) = self.forward_constant(id)? {
             return Ok(v);
         }
-        // Value not found — this can happen for forward references or
-        // values from unreachable blocks. Produce a zero constant with the
-        // inferred type (or I64 default) instead of failing hard.
+        // Value not found — a forward reference, or a value from an
+        // unreachable block. Produce a zero constant with the inferred type
+        // (or I64 default) instead of failing hard.
         let fallback_ty = self.value_types.get(&val.id).copied().unwrap_or(types::I64);
         if fallback_ty.is_int() {
             Ok(self.builder.ins().iconst(fallback_ty, 0))
@@ -772,6 +2092,19 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
         }
     }
 
+    /// A constant used before the block defining it is translated, emitted
+    /// at the use. The builders put the constant a short-circuit phi takes
+    /// from the left operand's edge in the merge block itself, and LICM can
+    /// hoist a use above the constant it reads.
+    fn forward_constant(&mut self, id: ValueId) -> BridgeResult<Option<ClifValue>> {
+        let mir_func = self.mir_func;
+        let constant = mir_func.blocks.iter().flat_map(|b| &b.instructions).find_map(|inst| match &inst.inst {
+            Instruction::Constant(c) if inst.result == id => Some(c),
+            _ => None,
+        });
+        constant.map(|c| self.translate_constant(c)).transpose()
+    }
+
     fn translate_instruction(&mut self, inst_data: &InstructionData) -> BridgeResult<()> {
         let result_id = inst_data.result;
         match &inst_data.inst {
@@ -783,7 +2116,8 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
             Instruction::Binary { op, left, right } => {
                 let lhs = self.get_value(left)?;
                 let rhs = self.get_value(right)?;
-                let val = self.translate_binary(*op, lhs, rhs)?;
+                let unsigned = self.is_unsigned_pair(left, right);
+                let val = self.translate_binary(*op, lhs, rhs, unsigned)?;
                 self.values.insert(result_id, val);
             }
 
@@ -793,21 +2127,75 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
                 self.values.insert(result_id, val);
             }
 
-            Instruction::Alloca { name: _, alloc_type } => {
-                let size = ty::type_size(alloc_type);
-                let slot = self.builder.create_sized_stack_slot(make_stack_slot(size));
+            Instruction::Alloca { .. } if self.promoted.contains_key(&result_id) => {
+                // Lives in an SSA variable; no stack slot needed
+            }
+
+            Instruction::Alloca { name, alloc_type } => {
+                let slot = match self.slot_plan.group_of.get(&result_id) {
+                    Some(&group) => *self.group_slots.entry(group).or_insert_with(|| {
+                        let size = self.slot_plan.group_sizes[group];
+                        let slot = self.builder.create_sized_stack_slot(make_stack_slot(size));
+                        self.slot_names.insert(slot, self.slot_plan.group_names[group].join(", "));
+                        slot
+                    }),
+                    None => {
+                        let size = ty::type_size(alloc_type);
+                        let slot = self.builder.create_sized_stack_slot(make_stack_slot(size));
+                        self.slot_names.insert(slot, name.clone());
+                        slot
+                    }
+                };
                 self.alloca_slots.insert(result_id, slot);
+                let store_ty = match ty::mir_type_to_cranelift(alloc_type) {
+                    Some(cl_ty) if cl_ty.is_int() => cl_ty,
+                    _ => match ty::type_size(alloc_type) {
+                        1 => types::I8,
+                        2 => types::I16,
+                        4 => types::I32,
+                        _ => types::I64,
+                    },
+                };
+                self.alloca_store_types.insert(result_id, store_ty);
                 let addr = self.builder.ins().stack_addr(POINTER_TYPE, slot, 0);
                 self.values.insert(result_id, addr);
             }
 
+            Instruction::Load { ptr } if self.promoted.contains_key(&ptr.id) => {
+                let (var, var_ty) = self.promoted[&ptr.id];
+                let load_ty = self.value_types.get(&result_id).copied().unwrap_or(var_ty);
+                let val = self.builder.use_var(var);
+                let val = self.coerce_value(val, load_ty).ok_or_else(|| {
+                    BridgeError::Translation(format!(
+                        "cannot load {} from promoted local of type {}",
+                        load_ty, var_ty
+                    ))
+                })?;
+                self.values.insert(result_id, val);
+            }
+
             Instruction::Load { ptr } => {
                 let ptr_val = self.get_value(ptr)?;
                 // Use the pre-computed type for this load result if available,
                 // otherwise default to I64
                 let load_ty = self.value_types.get(&result_id).copied().unwrap_or(types::I64);
                 if let Some(&slot) = self.alloca_slots.get(&ptr.id) {
-                    let val = self.builder.ins().stack_load(load_ty, slot, 0);
+                    // Store-to-load forwarding: reuse the value last stored in this block
+                    let forwarded = self
+                        .slot_values
+                        .get(&slot)
+                        .copied()
+                        .filter(|&v| self.builder.func.dfg.value_type(v) == load_ty);
+                    let val = match forwarded {
+                        Some(v) => v,
+                        None => {
+                            let v = self.builder.ins().stack_load(load_ty, slot, 0);
+                            if self.private_allocas.contains(&ptr.id) {
+                                self.slot_values.insert(slot, v);
+                            }
+                            v
+                        }
+                    };
                     self.values.insert(result_id, val);
                 } else {
                     let val = self.builder.ins().load(load_ty, MemFlags::new(), ptr_val, 0);
@@ -815,18 +2203,25 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
                 }
             }
 
+            Instruction::Store { ptr, value } if self.promoted.contains_key(&ptr.id) => {
+                let (var, var_ty) = self.promoted[&ptr.id];
+                let val = self.get_value(value)?;
+                let val = self.coerce_value(val, var_ty).ok_or_else(|| {
+                    BridgeError::Translation(format!(
+                        "cannot store {} into promoted local of type {}",
+                        self.builder.func.dfg.value_type(val),
+                        var_ty
+                    ))
+                })?;
+                self.builder.def_var(var, val);
+            }
+
             Instruction::Store { ptr, value } => {
                 let mut val = self.get_value(value)?;
                 if let Some(&slot) = self.alloca_slots.get(&ptr.id) {
                     // Coerce value to match load type (stored and loaded types must match)
                     let val_ty = self.builder.func.dfg.value_type(val);
-                    let slot_size = self.builder.func.sized_stack_slots[slot].size;
-                    let expected_ty = match slot_size {
-                        1 => types::I8,
-                        2 => types::I16,
-                        4 => types::I32,
-                        _ => types::I64,
-                    };
+                    let expected_ty = self.alloca_store_types[&ptr.id];
                     if val_ty != expected_ty && val_ty.is_int() && expected_ty.is_int() {
                         val = if val_ty.bytes() < expected_ty.bytes() {
                             self.builder.ins().sextend(expected_ty, val)
@@ -835,6 +2230,11 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
                         };
                     }
                     self.builder.ins().stack_store(val, slot, 0);
+                    if self.private_allocas.contains(&ptr.id) {
+                        self.slot_values.insert(slot, val);
+                    } else {
+                        self.slot_values.remove(&slot);
+                    }
                 } else {
                     let ptr_v = self.get_value(ptr)?;
                     self.builder.ins().store(MemFlags::new(), val, ptr_v, 0);
@@ -846,6 +2246,15 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
                 args,
                 return_type,
             } => {
+                if let Some(constant) = self.folded_call(func_name, args, return_type) {
+                    let val = self.translate_constant(constant)?;
+                    self.values.insert(result_id, val);
+                    return Ok(());
+                }
+                if let Some(v) = self.try_int_intrinsic(func_name, args, return_type)? {
+                    self.values.insert(result_id, v);
+                    return Ok(());
+                }
                 let call_val = self.translate_call(func_name, args, return_type)?;
                 if let Some(v) = call_val {
                     self.values.insert(result_id, v);
@@ -857,10 +2266,22 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
                 method_name,
                 args,
                 return_type,
+                ..
             } => {
-                let mut all_args = vec![receiver.clone()];
+                let mut all_args = vec![*receiver];
                 all_args.extend_from_slice(args);
-                let call_val = self.translate_call(method_name, &all_args, return_type)?;
+                if let Some(v) = self.try_int_intrinsic(method_name, &all_args, return_type)? {
+                    self.values.insert(result_id, v);
+                    return Ok(());
+                }
+                // With the receiver type known, call the method's function in
+                // this module directly instead of the bare method name
+                let targets = inst_data.inst.method_targets();
+                let callee = targets
+                    .iter()
+                    .find(|t| self.func_ids.contains_key(t.as_str()))
+                    .map_or(method_name.as_str(), String::as_str);
+                let call_val = self.translate_call(callee, &all_args, return_type)?;
                 if let Some(v) = call_val {
                     self.values.insert(result_id, v);
                 }
@@ -888,12 +2309,13 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
                 let tv_ty = self.builder.func.dfg.value_type(tv);
                 let fv_ty = self.builder.func.dfg.value_type(fv);
                 if tv_ty != fv_ty && tv_ty.is_int() && fv_ty.is_int() {
+                    let unsigned = self.is_unsigned_pair(true_val, false_val);
                     let target = if tv_ty.bytes() >= fv_ty.bytes() { tv_ty } else { fv_ty };
                     if tv_ty != target {
-                        tv = self.builder.ins().sextend(target, tv);
+                        tv = self.extend_int(tv, target, unsigned);
                     }
                     if fv_ty != target {
-                        fv = self.builder.ins().sextend(target, fv);
+                        fv = self.extend_int(fv, target, unsigned);
                     }
                 }
                 let val = self.builder.ins().select(cond, tv, fv);
@@ -930,6 +2352,15 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
                 self.values.insert(result_id, val);
             }
 
+            Instruction::ArrayFill {
+                element_type,
+                value,
+                count,
+            } => {
+                let val = self.translate_array_fill(element_type, value, *count)?;
+                self.values.insert(result_id, val);
+            }
+
             Instruction::Gep { base, indices } => {
                 let val = self.translate_gep(base, indices)?;
                 self.values.insert(result_id, val);
@@ -972,32 +2403,78 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
         Ok(())
     }
 
-    fn translate_constant(&mut self, constant: &Constant) -> BridgeResult<ClifValue> {
-        match constant {
-            Constant::Int {
-                value,
-                bit_width,
-                is_signed: _,
-            } => {
-                let ty = match bit_width {
-                    8 => types::I8,
-                    16 => types::I16,
-                    32 => types::I32,
-                    64 => types::I64,
-                    128 => types::I128,
-                    _ => types::I64,
-                };
-                Ok(self.builder.ins().iconst(ty, *value))
+    /// Emit the float pool and its address at the top of the entry block.
+    fn materialize_pool(&mut self) -> BridgeResult<()> {
+        if !self.const_plan.pool_bytes.is_empty() {
+            let name = format!(".const.{}", self.mir_func.name);
+            let data_id = self
+                .module
+                .declare_data(&name, Linkage::Local, false, false)
+                .map_err(|e| BridgeError::Codegen(format!("failed to declare constant pool: {}", e)))?;
+            let mut data_desc = cranelift_module::DataDescription::new();
+            data_desc.define(std::mem::take(&mut self.const_plan.pool_bytes).into_boxed_slice());
+            data_desc.set_align(8);
+            self.module
+                .define_data(data_id, &data_desc)
+                .map_err(|e| BridgeError::Codegen(format!("failed to define constant pool: {}", e)))?;
+            let gv = self.module.declare_data_in_func(data_id, self.builder.func);
+            self.pool_base = Some(self.builder.ins().symbol_value(POINTER_TYPE, gv));
+        }
+        Ok(())
+    }
+
+    /// Emit the shared constants planned for the current block at its top.
+    /// The plan puts each one in the nearest block dominating every block
+    /// that defines it, not the entry block, so it is not live across code
+    /// that never uses it.
+    fn materialize_shared_constants(&mut self) {
+        let block_id = self.block_id;
+        let here: Vec<ConstKey> =
+            self.const_plan.shared.iter().filter(|(_, home)| *home == block_id).map(|(key, _)| *key).collect();
+        for key in here {
+            let val = self.emit_const(key);
+            self.const_values.insert(key, (val, block_id));
+        }
+    }
+
+    /// Whether MIR block `a` dominates block `b`.
+    fn dominates(&self, a: u32, mut b: u32) -> bool {
+        loop {
+            if a == b {
+                return true;
             }
-            Constant::Float { value, is_f64 } => {
-                if *is_f64 {
-                    Ok(self.builder.ins().f64const(*value))
-                } else {
-                    Ok(self.builder.ins().f32const(*value as f32))
-                }
+            match self.idom.get(&b) {
+                Some(&up) if up != b => b = up,
+                _ => return false,
             }
-            Constant::Bool(b) => {
-                Ok(self.builder.ins().iconst(types::I8, if *b { 1 } else { 0 }))
+        }
+    }
+
+    /// A scalar constant: a load when pooled, an immediate otherwise.
+    fn emit_const(&mut self, key: ConstKey) -> ClifValue {
+        if let (Some(&offset), Some(base)) = (self.const_plan.pooled.get(&key), self.pool_base) {
+            let flags = MemFlags::trusted().with_readonly();
+            return self.builder.ins().load(key.ty, flags, base, offset as i32);
+        }
+        match key.ty {
+            types::F64 => self.builder.ins().f64const(f64::from_bits(key.bits)),
+            types::F32 => self.builder.ins().f32const(f32::from_bits(key.bits as u32)),
+            ty => self.builder.ins().iconst(ty, key.bits as i64),
+        }
+    }
+
+    fn translate_constant(&mut self, constant: &Constant) -> BridgeResult<ClifValue> {
+        if let Some(key) = ConstKey::of(constant) {
+            // Constants forwarded to an earlier block or produced by a folded
+            // call can be translated where the shared value is not available
+            return Ok(match self.const_values.get(&key) {
+                Some(&(val, home)) if self.dominates(home, self.block_id) => val,
+                _ => self.emit_const(key),
+            });
+        }
+        match constant {
+            Constant::Int { .. } | Constant::Float { .. } | Constant::Bool(_) => {
+                unreachable!("scalar constants have a key")
             }
             Constant::String(s) => {
                 self.translate_string_constant(s)
@@ -1005,16 +2482,22 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
             Constant::Unit => {
                 Ok(self.builder.ins().iconst(types::I64, 0))
             }
+            Constant::WideString(s) => {
+                self.translate_wide_string_constant(s)
+            }
         }
     }
 
+    /// Emit a string literal and return a pointer to its first byte.
+    ///
+    /// Layout: `[bytes][NUL]`, matching the `const char*` strings the runtime
+    /// and the LLVM backend use. The runtime would stop at an interior NUL,
+    /// so literals containing one are rejected rather than cut short.
     fn translate_string_constant(&mut self, s: &str) -> BridgeResult<ClifValue> {
         if let Some(&data_id) = self.string_data.get(s) {
-            let gv = self
-                .module
-                .declare_data_in_func(data_id, self.builder.func);
-            return Ok(self.builder.ins().symbol_value(POINTER_TYPE, gv));
+            return Ok(self.string_data_ptr(data_id));
         }
+        reject_interior_nul("string", s)?;
 
         let name = format!(".str.{}.{}", self.mir_func.name, self.string_data.len());
         let data_id = self
@@ -1023,7 +2506,8 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
             .map_err(|e| BridgeError::Codegen(format!("failed to declare string data: {}", e)))?;
 
         let mut data_desc = cranelift_module::DataDescription::new();
-        let mut bytes = s.as_bytes().to_vec();
+        let mut bytes = Vec::with_capacity(s.len() + 1);
+        bytes.extend_from_slice(s.as_bytes());
         bytes.push(0); // null terminator
         data_desc.define(bytes.into_boxed_slice());
         self.module
@@ -1031,6 +2515,47 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
             .map_err(|e| BridgeError::Codegen(format!("failed to define string data: {}", e)))?;
 
         self.string_data.insert(s.to_string(), data_id);
+        Ok(self.string_data_ptr(data_id))
+    }
+
+    /// Emit a wide string literal as UTF-16 in the target's byte order
+    /// (UTF-16LE on Windows) with a 16-bit NUL terminator.
+    ///
+    /// The pointer goes straight to `LPCWSTR` parameters of Windows APIs.
+    fn translate_wide_string_constant(&mut self, s: &str) -> BridgeResult<ClifValue> {
+        let data_id = match self.wide_string_data.get(s) {
+            Some(&data_id) => data_id,
+            None => {
+                reject_interior_nul("wide string", s)?;
+                let name = format!(
+                    ".wstr.{}.{}",
+                    self.mir_func.name,
+                    self.wide_string_data.len()
+                );
+                let data_id = self
+                    .module
+                    .declare_data(&name, Linkage::Local, false, false)
+                    .map_err(|e| {
+                        BridgeError::Codegen(format!("failed to declare wide string data: {}", e))
+                    })?;
+
+                let mut data_desc = cranelift_module::DataDescription::new();
+                let big_endian = self.module.isa().endianness() == Endianness::Big;
+                let bytes: Vec<u8> = s
+                    .encode_utf16()
+                    .chain(std::iter::once(0))
+                    .flat_map(|unit| if big_endian { unit.to_be_bytes() } else { unit.to_le_bytes() })
+                    .collect();
+                data_desc.define(bytes.into_boxed_slice());
+                data_desc.set_align(2);
+                self.module.define_data(data_id, &data_desc).map_err(|e| {
+                    BridgeError::Codegen(format!("failed to define wide string data: {}", e))
+                })?;
+
+                self.wide_string_data.insert(s.to_string(), data_id);
+                data_id
+            }
+        };
 
         let gv = self
             .module
@@ -1038,11 +2563,19 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
         Ok(self.builder.ins().symbol_value(POINTER_TYPE, gv))
     }
 
+    fn string_data_ptr(&mut self, data_id: cranelift_module::DataId) -> ClifValue {
+        let gv = self
+            .module
+            .declare_data_in_func(data_id, self.builder.func);
+        self.builder.ins().symbol_value(POINTER_TYPE, gv)
+    }
+
     fn translate_binary(
         &mut self,
         op: BinOp,
         lhs: ClifValue,
         rhs: ClifValue,
+        unsigned: bool,
     ) -> BridgeResult<ClifValue> {
         let lhs_ty = self.builder.func.dfg.value_type(lhs);
         let rhs_ty = self.builder.func.dfg.value_type(rhs);
@@ -1069,8 +2602,8 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
             } else if lhs_ty.is_int() && rhs_ty.is_int() {
                 // Both int but different widths
                 let target = if lhs_ty.bytes() >= rhs_ty.bytes() { lhs_ty } else { rhs_ty };
-                let l = if lhs_ty == target { lhs } else { self.builder.ins().sextend(target, lhs) };
-                let r = if rhs_ty == target { rhs } else { self.builder.ins().sextend(target, rhs) };
+                let l = if lhs_ty == target { lhs } else { self.extend_int(lhs, target, unsigned) };
+                let r = if rhs_ty == target { rhs } else { self.extend_int(rhs, target, unsigned) };
                 (l, r)
             } else {
                 (lhs, rhs)
@@ -1081,20 +2614,40 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
 
         let val = match op {
             BinOp::Add => {
-                if is_float { self.builder.ins().fadd(lhs, rhs) }
+                if is_float {
+                    match self.translate_fast_float(op, lhs, rhs) {
+                        Some(v) => v,
+                        None => self.builder.ins().fadd(lhs, rhs),
+                    }
+                }
                 else { self.builder.ins().iadd(lhs, rhs) }
             }
             BinOp::Sub => {
-                if is_float { self.builder.ins().fsub(lhs, rhs) }
+                if is_float {
+                    match self.translate_fast_float(op, lhs, rhs) {
+                        Some(v) => v,
+                        None => self.builder.ins().fsub(lhs, rhs),
+                    }
+                }
                 else { self.builder.ins().isub(lhs, rhs) }
             }
             BinOp::Mul => {
-                if is_float { self.builder.ins().fmul(lhs, rhs) }
+                if is_float {
+                    match self.translate_fast_float(op, lhs, rhs) {
+                        Some(v) => v,
+                        None => self.builder.ins().fmul(lhs, rhs),
+                    }
+                }
                 else { self.builder.ins().imul(lhs, rhs) }
             }
             BinOp::Div => {
                 if is_float { self.builder.ins().fdiv(lhs, rhs) }
-                else { self.builder.ins().sdiv(lhs, rhs) }
+                else {
+                    if self.trap_abort {
+                        self.guard_division(lhs, rhs, true)?;
+                    }
+                    self.builder.ins().sdiv(lhs, rhs)
+                }
             }
             BinOp::Mod => {
                 if is_float {
@@ -1102,6 +2655,9 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
                         "float modulo not directly supported".into(),
                     ));
                 } else {
+                    if self.trap_abort {
+                        self.guard_division(lhs, rhs, false)?;
+                    }
                     self.builder.ins().srem(lhs, rhs)
                 }
             }
@@ -1115,19 +2671,31 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
             }
             BinOp::Lt => {
                 if is_float { self.builder.ins().fcmp(FloatCC::LessThan, lhs, rhs) }
-                else { self.builder.ins().icmp(IntCC::SignedLessThan, lhs, rhs) }
+                else {
+                    let cc = if unsigned { IntCC::UnsignedLessThan } else { IntCC::SignedLessThan };
+                    self.builder.ins().icmp(cc, lhs, rhs)
+                }
             }
             BinOp::Le => {
                 if is_float { self.builder.ins().fcmp(FloatCC::LessThanOrEqual, lhs, rhs) }
-                else { self.builder.ins().icmp(IntCC::SignedLessThanOrEqual, lhs, rhs) }
+                else {
+                    let cc = if unsigned { IntCC::UnsignedLessThanOrEqual } else { IntCC::SignedLessThanOrEqual };
+                    self.builder.ins().icmp(cc, lhs, rhs)
+                }
             }
             BinOp::Gt => {
                 if is_float { self.builder.ins().fcmp(FloatCC::GreaterThan, lhs, rhs) }
-                else { self.builder.ins().icmp(IntCC::SignedGreaterThan, lhs, rhs) }
+                else {
+                    let cc = if unsigned { IntCC::UnsignedGreaterThan } else { IntCC::SignedGreaterThan };
+                    self.builder.ins().icmp(cc, lhs, rhs)
+                }
             }
             BinOp::Ge => {
                 if is_float { self.builder.ins().fcmp(FloatCC::GreaterThanOrEqual, lhs, rhs) }
-                else { self.builder.ins().icmp(IntCC::SignedGreaterThanOrEqual, lhs, rhs) }
+                else {
+                    let cc = if unsigned { IntCC::UnsignedGreaterThanOrEqual } else { IntCC::SignedGreaterThanOrEqual };
+                    self.builder.ins().icmp(cc, lhs, rhs)
+                }
             }
             BinOp::And => self.builder.ins().band(lhs, rhs),
             BinOp::Or => self.builder.ins().bor(lhs, rhs),
@@ -1136,11 +2704,110 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
             BinOp::BitXor => self.builder.ins().bxor(lhs, rhs),
             BinOp::Shl => self.builder.ins().ishl(lhs, rhs),
             BinOp::Shr => self.builder.ins().sshr(lhs, rhs),
+            BinOp::Ord
+            | BinOp::Uno
+            | BinOp::One
+            | BinOp::Ueq
+            | BinOp::Ult
+            | BinOp::Ule
+            | BinOp::Ugt
+            | BinOp::Uge => self.translate_nan_predicate(op, lhs, rhs, is_float, unsigned),
         };
 
         Ok(val)
     }
 
+    /// The explicitly ordered/unordered comparisons. Integers are never NaN,
+    /// so `Ord`/`Uno` fold to constants and the rest to the plain compare.
+    fn translate_nan_predicate(
+        &mut self,
+        op: BinOp,
+        lhs: ClifValue,
+        rhs: ClifValue,
+        is_float: bool,
+        unsigned: bool,
+    ) -> ClifValue {
+        if is_float {
+            let cc = match op {
+                BinOp::Ord => FloatCC::Ordered,
+                BinOp::Uno => FloatCC::Unordered,
+                BinOp::One => FloatCC::OrderedNotEqual,
+                BinOp::Ueq => FloatCC::UnorderedOrEqual,
+                BinOp::Ult => FloatCC::UnorderedOrLessThan,
+                BinOp::Ule => FloatCC::UnorderedOrLessThanOrEqual,
+                BinOp::Ugt => FloatCC::UnorderedOrGreaterThan,
+                _ => FloatCC::UnorderedOrGreaterThanOrEqual,
+            };
+            return self.builder.ins().fcmp(cc, lhs, rhs);
+        }
+        let cc = match (op, unsigned) {
+            (BinOp::Ord, _) => return self.builder.ins().iconst(types::I8, 1),
+            (BinOp::Uno, _) => return self.builder.ins().iconst(types::I8, 0),
+            (BinOp::One, _) => IntCC::NotEqual,
+            (BinOp::Ueq, _) => IntCC::Equal,
+            (BinOp::Ult, false) => IntCC::SignedLessThan,
+            (BinOp::Ule, false) => IntCC::SignedLessThanOrEqual,
+            (BinOp::Ugt, false) => IntCC::SignedGreaterThan,
+            (BinOp::Uge, false) => IntCC::SignedGreaterThanOrEqual,
+            (BinOp::Ult, true) => IntCC::UnsignedLessThan,
+            (BinOp::Ule, true) => IntCC::UnsignedLessThanOrEqual,
+            (BinOp::Ugt, true) => IntCC::UnsignedGreaterThan,
+            _ => IntCC::UnsignedGreaterThanOrEqual,
+        };
+        self.builder.ins().icmp(cc, lhs, rhs)
+    }
+
+    /// Result of a call to a constant function, if the call expects the
+    /// type the function was evaluated at.
+    fn folded_call(&self, func_name: &str, args: &[Value], return_type: &MirType) -> Option<&'a Constant> {
+        let constant = self.const_calls?.get(func_name).filter(|_| args.is_empty())?;
+        let ty = match constant {
+            Constant::Int { bit_width, .. } => types::Type::int(*bit_width as u16),
+            Constant::Float { is_f64: true, .. } => Some(types::F64),
+            Constant::Float { is_f64: false, .. } => Some(types::F32),
+            Constant::Bool(_) => Some(types::I8),
+            _ => None,
+        };
+        (ty.is_some() && ty == ty::mir_type_to_cranelift(return_type)).then_some(constant)
+    }
+
+    /// Lower `saturating_*` / `wrapping_*` calls on integers inline. Returns
+    /// None when `name` is not such an intrinsic or the types don't fit, in
+    /// which case the caller emits an ordinary call.
+    fn try_int_intrinsic(
+        &mut self,
+        name: &str,
+        args: &[Value],
+        return_type: &MirType,
+    ) -> BridgeResult<Option<ClifValue>> {
+        let Some(intrinsic) = IntIntrinsic::from_name(name) else {
+            return Ok(None);
+        };
+        let (Some(signed), Some(ty)) =
+            (int_signedness(return_type), ty::mir_type_to_cranelift(return_type))
+        else {
+            return Ok(None);
+        };
+        if args.len() != intrinsic.arity() || !intrinsic.supports(ty) {
+            return Ok(None);
+        }
+
+        let mut operands = Vec::with_capacity(args.len());
+        for arg in args {
+            let val = self.get_value(arg)?;
+            let val_ty = self.builder.func.dfg.value_type(val);
+            if !val_ty.is_int() {
+                return Ok(None);
+            }
+            operands.push(match val_ty.bits().cmp(&ty.bits()) {
+                std::cmp::Ordering::Less => self.extend_int(val, ty, !signed),
+                std::cmp::Ordering::Equal => val,
+                std::cmp::Ordering::Greater => self.builder.ins().ireduce(ty, val),
+            });
+        }
+        Ok(Some(intrinsic.emit(self.builder, ty, signed, &operands)))
+    }
+
     fn translate_unary(
         &mut self,
         op: UnaryOp,
@@ -1184,9 +2851,7 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
                 for _ in args {
                     sig.params.push(AbiParam::new(types::I64));
                 }
-                if let Some(ret_ty) = ty::mir_type_to_cranelift(return_type) {
-                    sig.returns.push(AbiParam::new(ret_ty));
-                }
+                sig.returns.extend(ty::abi_param(return_type));
                 match self.module.declare_function(&symbol_name, Linkage::Import, &sig) {
                     Ok(id) => {
                         // Store the new func_id for future lookups
@@ -1232,6 +2897,10 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
             .iter()
             .map(|p| p.value_type)
             .collect();
+        if self.strict_runtime_calls && self.symbols.runtime_names.contains(func_name) {
+            let expected_ret = self.builder.func.dfg.signatures[sig].returns.first().map(|r| r.value_type);
+            self.check_runtime_call(func_name, args, return_type, &expected_types, expected_ret)?;
+        }
 
         let mut arg_vals = Vec::with_capacity(args.len());
         for (i, arg) in args.iter().enumerate() {
@@ -1281,57 +2950,200 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
         }
     }
 
-    fn translate_cast(
+    /// Strict mode: a call to a runtime function must pass exactly the
+    /// manifest's parameter types and expect its return type.
+    fn check_runtime_call(
         &mut self,
-        kind: CastKind,
-        operand: ClifValue,
-        target_type: &MirType,
-    ) -> BridgeResult<ClifValue> {
-        let target_cl = ty::mir_type_to_cranelift(target_type).unwrap_or(types::I64);
-        let src_ty = self.builder.func.dfg.value_type(operand);
-
-        let val = match kind {
-            CastKind::Bitcast => {
-                if src_ty == target_cl {
+        func_name: &str,
+        args: &[Value],
+        return_type: &MirType,
+        expected: &[cranelift_codegen::ir::Type],
+        expected_ret: Option<cranelift_codegen::ir::Type>,
+    ) -> BridgeResult<()> {
+        let mismatch = |what: String| {
+            BridgeError::Translation(format!(
+                "call to runtime function '{}' {} (strict runtime calls)",
+                func_name, what
+            ))
+        };
+        let show = |ty: Option<cranelift_codegen::ir::Type>| ty.map_or("void".to_string(), |t| t.to_string());
+
+        if args.len() != expected.len() {
+            return Err(mismatch(format!(
+                "passes {} arguments, the runtime takes {}",
+                args.len(),
+                expected.len()
+            )));
+        }
+        for (i, (arg, &want)) in args.iter().zip(expected).enumerate() {
+            let val = self.get_value(arg)?;
+            let got = self.builder.func.dfg.value_type(val);
+            if got != want {
+                return Err(mismatch(format!("passes {} as argument {}, the runtime takes {}", got, i, want)));
+            }
+        }
+        let got_ret = ty::mir_type_to_cranelift(return_type);
+        if got_ret != expected_ret {
+            return Err(mismatch(format!(
+                "expects {}, the runtime returns {}",
+                show(got_ret),
+                show(expected_ret)
+            )));
+        }
+        Ok(())
+    }
+
+    fn translate_cast(
+        &mut self,
+        kind: CastKind,
+        operand: ClifValue,
+        target_type: &MirType,
+    ) -> BridgeResult<ClifValue> {
+        let target_cl = ty::mir_type_to_cranelift(target_type).unwrap_or(types::I64);
+        let src_ty = self.builder.func.dfg.value_type(operand);
+        let to_bool = matches!(target_type, MirType::Primitive(PrimitiveType::Bool));
+        let invalid = || {
+            BridgeError::Translation(format!(
+                "invalid cast {:?} from {} to {}",
+                kind, src_ty, target_cl
+            ))
+        };
+        // Cranelift has no lowering and the compiler-rt helpers LLVM calls
+        // are not guaranteed to be linked
+        let unsupported_i128_float = || {
+            BridgeError::UnsupportedInstruction(format!(
+                "{:?} between {} and {} (128-bit float conversion)",
+                kind, src_ty, target_cl
+            ))
+        };
+
+        let val = match kind {
+            CastKind::Bitcast => {
+                // Reinterpret the bits; differently sized pairs go through an
+                // integer of the source width and are zero-extended/truncated
+                if src_ty == target_cl {
                     operand
                 } else if src_ty.bytes() == target_cl.bytes() {
                     self.builder.ins().bitcast(target_cl, MemFlags::new(), operand)
-                } else if src_ty.is_int() && target_cl.is_int() {
-                    // Different-sized integers: use extend/reduce instead of bitcast
-                    if src_ty.bytes() < target_cl.bytes() {
-                        self.builder.ins().uextend(target_cl, operand)
+                } else {
+                    let bits = self.float_bits(operand);
+                    let int_ty = target_cl.as_int();
+                    let resized = self.resize_int(bits, int_ty, false);
+                    if target_cl.is_float() {
+                        self.builder.ins().bitcast(target_cl, MemFlags::new(), resized)
                     } else {
-                        self.builder.ins().ireduce(target_cl, operand)
+                        resized
                     }
+                }
+            }
+            CastKind::PtrToInt | CastKind::IntToPtr => {
+                if !src_ty.is_int() || !target_cl.is_int() {
+                    return Err(invalid());
+                }
+                if kind == CastKind::PtrToInt {
+                    FlatPointers.ptr_to_int(self.builder, operand, target_cl)
                 } else {
-                    // Fallback: try raw_bitcast for same-register-class types
-                    self.builder.ins().bitcast(target_cl, MemFlags::new(), operand)
+                    FlatPointers.int_to_ptr(self.builder, operand)
+                }
+            }
+            CastKind::Trunc | CastKind::ZExt | CastKind::SExt => {
+                if !src_ty.is_int() || !target_cl.is_int() {
+                    return Err(invalid());
+                }
+                if to_bool && (kind == CastKind::Trunc || src_ty.bits() > 8) {
+                    // Truncation to Bool keeps bit 0 only
+                    let narrow = self.resize_int(operand, types::I8, false);
+                    self.builder.ins().band_imm(narrow, 1)
+                } else {
+                    self.resize_int(operand, target_cl, kind == CastKind::SExt)
+                }
+            }
+            CastKind::FPTrunc | CastKind::FPExt => {
+                if !src_ty.is_float() || !target_cl.is_float() {
+                    return Err(invalid());
+                }
+                match src_ty.bits().cmp(&target_cl.bits()) {
+                    std::cmp::Ordering::Less => self.builder.ins().fpromote(target_cl, operand),
+                    std::cmp::Ordering::Equal => operand,
+                    std::cmp::Ordering::Greater => self.builder.ins().fdemote(target_cl, operand),
+                }
+            }
+            CastKind::FPToSI | CastKind::FPToUI => {
+                if !src_ty.is_float() || !target_cl.is_int() {
+                    return Err(invalid());
                 }
+                if target_cl == types::I128 {
+                    return Err(unsupported_i128_float());
+                }
+                // x64 converts to 32 and 64 bits only; narrower goes via I32
+                let conv_ty = if target_cl.bits() < 32 { types::I32 } else { target_cl };
+                let conv = if kind == CastKind::FPToSI {
+                    self.builder.ins().fcvt_to_sint(conv_ty, operand)
+                } else {
+                    self.builder.ins().fcvt_to_uint(conv_ty, operand)
+                };
+                self.resize_int(conv, target_cl, false)
             }
-            CastKind::Trunc => self.builder.ins().ireduce(target_cl, operand),
-            CastKind::ZExt => self.builder.ins().uextend(target_cl, operand),
-            CastKind::SExt => self.builder.ins().sextend(target_cl, operand),
-            CastKind::FPTrunc => self.builder.ins().fdemote(target_cl, operand),
-            CastKind::FPExt => self.builder.ins().fpromote(target_cl, operand),
-            CastKind::FPToSI => self.builder.ins().fcvt_to_sint(target_cl, operand),
-            CastKind::FPToUI => self.builder.ins().fcvt_to_uint(target_cl, operand),
-            CastKind::SIToFP => self.builder.ins().fcvt_from_sint(target_cl, operand),
-            CastKind::UIToFP => self.builder.ins().fcvt_from_uint(target_cl, operand),
-            CastKind::PtrToInt => {
-                if src_ty == target_cl { operand }
-                else if src_ty.bytes() > target_cl.bytes() { self.builder.ins().ireduce(target_cl, operand) }
-                else { self.builder.ins().uextend(target_cl, operand) }
+            CastKind::SIToFP | CastKind::UIToFP => {
+                if !src_ty.is_int() || !target_cl.is_float() {
+                    return Err(invalid());
+                }
+                if src_ty == types::I128 {
+                    return Err(unsupported_i128_float());
+                }
+                if kind == CastKind::SIToFP {
+                    self.builder.ins().fcvt_from_sint(target_cl, operand)
+                } else {
+                    self.builder.ins().fcvt_from_uint(target_cl, operand)
+                }
             }
-            CastKind::IntToPtr => {
-                if src_ty == POINTER_TYPE { operand }
-                else if src_ty.bytes() < POINTER_TYPE.bytes() { self.builder.ins().uextend(POINTER_TYPE, operand) }
-                else { self.builder.ins().ireduce(POINTER_TYPE, operand) }
+            CastKind::BoolCast => {
+                if !target_cl.is_int() {
+                    return Err(invalid());
+                }
+                let flag = if src_ty.is_float() {
+                    let zero = if src_ty == types::F32 {
+                        self.builder.ins().f32const(0.0)
+                    } else {
+                        self.builder.ins().f64const(0.0)
+                    };
+                    // NaN counts as true, as in C
+                    self.builder.ins().fcmp(FloatCC::NotEqual, operand, zero)
+                } else {
+                    self.builder.ins().icmp_imm(IntCC::NotEqual, operand, 0)
+                };
+                self.resize_int(flag, target_cl, false)
             }
         };
 
         Ok(val)
     }
 
+    /// Integer view of `v`: floats are reinterpreted as an int of equal width.
+    fn float_bits(&mut self, v: ClifValue) -> ClifValue {
+        let ty = self.builder.func.dfg.value_type(v);
+        if ty.is_float() {
+            self.builder.ins().bitcast(ty.as_int(), MemFlags::new(), v)
+        } else {
+            v
+        }
+    }
+
+    /// Extend or truncate integer `v` to `target`.
+    fn resize_int(
+        &mut self,
+        v: ClifValue,
+        target: cranelift_codegen::ir::Type,
+        signed: bool,
+    ) -> ClifValue {
+        let ty = self.builder.func.dfg.value_type(v);
+        match ty.bits().cmp(&target.bits()) {
+            std::cmp::Ordering::Less => self.extend_int(v, target, !signed),
+            std::cmp::Ordering::Equal => v,
+            std::cmp::Ordering::Greater => self.builder.ins().ireduce(target, v),
+        }
+    }
+
     fn translate_terminator(&mut self, term: &Terminator, current_block_id: u32) -> BridgeResult<()> {
         match term {
             Terminator::Return { value } => {
@@ -1371,18 +3183,27 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
                 }
             }
             Terminator::Branch { target } => {
-                let target_block = self.blocks[target];
+                let target_block = self.target_block(*target)?;
                 let args = self.collect_phi_args(*target, current_block_id)?;
                 self.builder.ins().jump(target_block, &args);
             }
+            Terminator::CondBranch { condition, .. } | Terminator::Switch { discriminant: condition, .. }
+                if self.const_ints.contains_key(&condition.id) =>
+            {
+                // Folded: only the taken edge exists, so only its phi args are passed
+                let target = self.live_successors(term)[0];
+                let args = self.collect_phi_args(target, current_block_id)?;
+                let target_block = self.target_block(target)?;
+                self.builder.ins().jump(target_block, &args);
+            }
             Terminator::CondBranch {
                 condition,
                 true_block,
                 false_block,
             } => {
                 let cond = self.get_value(condition)?;
-                let tb = self.blocks[true_block];
-                let fb = self.blocks[false_block];
+                let tb = self.target_block(*true_block)?;
+                let fb = self.target_block(*false_block)?;
                 let true_args = self.collect_phi_args(*true_block, current_block_id)?;
                 let false_args = self.collect_phi_args(*false_block, current_block_id)?;
                 self.builder.ins().brif(cond, tb, &true_args, fb, &false_args);
@@ -1392,24 +3213,116 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
                 cases,
                 default_block,
             } => {
-                let disc = self.get_value(discriminant)?;
-                let default_bl = self.blocks[default_block];
-
-                let mut switch = cranelift_frontend::Switch::new();
-                for (case_val, block_id) in cases {
-                    let target = self.blocks[block_id];
-                    switch.set_entry(*case_val as u128, target);
-                }
-                switch.emit(self.builder, disc, default_bl);
+                self.translate_switch(discriminant, cases, *default_block, current_block_id)?;
             }
             Terminator::Unreachable => {
-                self.builder.ins().trap(TrapCode::unwrap_user(0));
+                self.emit_trap(UNREACHABLE_TRAP)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lower a MIR switch.
+    ///
+    /// Case values are matched against the discriminant's bit pattern at its
+    /// own width, so `-1` matches an `i8` tag of `0xFF`; cases that cannot be
+    /// represented in that width can never match and are dropped. A switch
+    /// over a bool with only 0/1 cases becomes a `brif`. Targets with phis get
+    /// a trampoline block carrying their arguments, since jump tables cannot
+    /// pass block arguments.
+    fn translate_switch(
+        &mut self,
+        discriminant: &Value,
+        cases: &[(i64, u32)],
+        default_block: u32,
+        current_block_id: u32,
+    ) -> BridgeResult<()> {
+        let mut disc = self.get_value(discriminant)?;
+        let disc_ty = self.builder.func.dfg.value_type(disc);
+        if !disc_ty.is_int() {
+            if disc_ty.is_float() {
+                return Err(BridgeError::Translation(format!(
+                    "switch on floating-point discriminant ({}) in function '{}'",
+                    disc_ty, self.mir_func.name
+                )));
+            }
+            disc = self.coerce_value(disc, types::I64).ok_or_else(|| {
+                BridgeError::Translation(format!("unsupported switch discriminant type {}", disc_ty))
+            })?;
+        }
+        let disc_ty = self.builder.func.dfg.value_type(disc);
+        let bits = disc_ty.bits();
+        let signedness = self.signedness.get(&discriminant.id).copied();
+
+        // Normalize case values to the discriminant's width; first case wins
+        let mut entries: Vec<(u128, u32)> = Vec::with_capacity(cases.len());
+        for &(value, block_id) in cases {
+            let Some(pattern) = switch_case_pattern(value, bits, signedness) else {
+                continue;
+            };
+            if !entries.iter().any(|(p, _)| *p == pattern) {
+                entries.push((pattern, block_id));
             }
         }
 
+        let is_bool_value = self.value_types.get(&discriminant.id) == Some(&types::I8)
+            && bits == 8
+            && entries.iter().all(|(p, _)| *p <= 1);
+        if is_bool_value && !entries.is_empty() {
+            let target_for = |p: u128| {
+                entries
+                    .iter()
+                    .find(|(v, _)| *v == p)
+                    .map_or(default_block, |(_, b)| *b)
+            };
+            let (on_true, on_false) = (target_for(1), target_for(0));
+            let true_args = self.collect_phi_args(on_true, current_block_id)?;
+            let false_args = self.collect_phi_args(on_false, current_block_id)?;
+            let (tb, fb) = (self.target_block(on_true)?, self.target_block(on_false)?);
+            self.builder.ins().brif(disc, tb, &true_args, fb, &false_args);
+            return Ok(());
+        }
+
+        let mut trampolines: Vec<(Block, u32)> = Vec::new();
+        let mut edge_target = |this: &mut Self, block_id: u32| -> BridgeResult<Block> {
+            let target = this.target_block(block_id)?;
+            if !this.phi_info.block_params.contains_key(&block_id) {
+                return Ok(target);
+            }
+            if let Some(&(tramp, _)) = trampolines.iter().find(|(_, b)| *b == block_id) {
+                return Ok(tramp);
+            }
+            let tramp = this.builder.create_block();
+            trampolines.push((tramp, block_id));
+            Ok(tramp)
+        };
+
+        let mut switch = cranelift_frontend::Switch::new();
+        for &(pattern, block_id) in &entries {
+            let target = edge_target(self, block_id)?;
+            switch.set_entry(pattern, target);
+        }
+        let otherwise = edge_target(self, default_block)?;
+        switch.emit(self.builder, disc, otherwise);
+
+        for (tramp, block_id) in trampolines {
+            self.builder.switch_to_block(tramp);
+            let args = self.collect_phi_args(block_id, current_block_id)?;
+            let target = self.target_block(block_id)?;
+            self.builder.ins().jump(target, &args);
+        }
         Ok(())
     }
 
+    /// The Cranelift block of branch target `id`, which must be a live block
+    /// of this function.
+    fn target_block(&self, id: u32) -> BridgeResult<Block> {
+        self.blocks.get(&id).copied().ok_or_else(|| {
+            BridgeError::Translation(format!("branch to unknown block bb{} in function '{}'", id, self.mir_func.name))
+        })
+    }
+
     /// Collect the values to pass as block arguments for phi nodes in the target block.
     /// Handles type coercion when incoming value type doesn't match block parameter type.
     fn collect_phi_args(
@@ -1420,7 +3333,7 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
         let mut args = Vec::new();
 
         // Get the expected parameter types for the target block
-        let target_block = self.blocks[&target_block_id];
+        let target_block = self.target_block(target_block_id)?;
         let param_types: Vec<cranelift_codegen::ir::Type> = self
             .builder
             .block_params(target_block)
@@ -1428,13 +3341,19 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
             .map(|&p| self.builder.func.dfg.value_type(p))
             .collect();
 
-        if let Some(phis) = self.phi_info.block_params.get(&target_block_id) {
+        if let Some(phis) = self.phi_info.block_params.get(&target_block_id).cloned() {
             for (phi_idx, (_result_id, incoming)) in phis.iter().enumerate() {
                 let expected_ty = param_types.get(phi_idx).copied().unwrap_or(types::I64);
                 let mut found = false;
                 for (val_id, block_id) in incoming {
                     if *block_id == from_block_id {
-                        if let Some(&v) = self.values.get(val_id) {
+                        let val_id = *self.phi_aliases.get(val_id).unwrap_or(val_id);
+                        let known = self.values.get(&val_id).copied();
+                        let value = match known {
+                            Some(v) => Some(v),
+                            None => self.forward_constant(val_id)?,
+                        };
+                        if let Some(v) = value {
                             // Coerce type if needed
                             let actual_ty = self.builder.func.dfg.value_type(v);
                             let coerced = if actual_ty == expected_ty {
@@ -1483,6 +3402,13 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
     // Tier 2: Aggregate instructions
     // ========================================================================
 
+    /// Zero `size` bytes at `addr` (inline stores for small sizes, memset otherwise).
+    fn zero_fill(&mut self, addr: ClifValue, size: u32) {
+        let config = self.module.target_config();
+        self.builder
+            .emit_small_memset(config, addr, 0, size as u64, 1, MemFlags::new());
+    }
+
     fn translate_struct_init(
         &mut self,
         struct_name: &str,
@@ -1500,6 +3426,16 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
         let slot = self.builder.create_sized_stack_slot(make_stack_slot(total_size.max(8)));
         let base_addr = self.builder.ins().stack_addr(POINTER_TYPE, slot, 0);
 
+        // Padding and skipped fields must read as zero (the runtime compares
+        // aggregates with mem_eq), so clear the slot unless the fields cover it.
+        let dense = field_defs.as_ref().is_some_and(|fdefs| {
+            fields.len() >= fdefs.len()
+                && fdefs.iter().map(|f| ty::type_size(&f.ty)).sum::<u32>() == total_size
+        });
+        if !dense {
+            self.zero_fill(base_addr, total_size.max(8));
+        }
+
         if let Some(ref fdefs) = field_defs {
             let field_types: Vec<&MirType> = fdefs.iter().map(|f| &f.ty).collect();
             let (offsets, _) = ty::compute_struct_layout(&field_types);
@@ -1542,6 +3478,7 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
 
         let slot = self.builder.create_sized_stack_slot(make_stack_slot(total_size));
         let base_addr = self.builder.ins().stack_addr(POINTER_TYPE, slot, 0);
+        self.zero_fill(base_addr, total_size);
 
         let tag_val = self.builder.ins().iconst(types::I64, variant_idx as i64);
         self.builder
@@ -1562,6 +3499,8 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
         let total_size = ((elements.len() as u32) * 8).max(8);
         let slot = self.builder.create_sized_stack_slot(make_stack_slot(total_size));
         let base_addr = self.builder.ins().stack_addr(POINTER_TYPE, slot, 0);
+        // Elements narrower than their 8-byte cell leave garbage in the upper bytes
+        self.zero_fill(base_addr, total_size);
 
         for (i, elem) in elements.iter().enumerate() {
             let v = self.get_value(elem)?;
@@ -1595,36 +3534,180 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
         Ok(base_addr)
     }
 
-    fn translate_gep(
+    fn translate_array_fill(
         &mut self,
-        base: &Value,
-        indices: &[Value],
+        element_type: &MirType,
+        value: &Value,
+        count: u64,
     ) -> BridgeResult<ClifValue> {
-        let mut addr = self.get_value(base)?;
-        // Ensure base address is pointer-sized
-        let addr_ty = self.builder.func.dfg.value_type(addr);
-        if addr_ty != POINTER_TYPE && addr_ty.is_int() {
-            addr = if addr_ty.bytes() < POINTER_TYPE.bytes() {
-                self.builder.ins().uextend(POINTER_TYPE, addr)
+        let elem_size = ty::type_size(element_type);
+        let total_size = u32::try_from(elem_size as u64 * count)
+            .ok()
+            .filter(|&size| size <= MAX_FILL_ARRAY_SIZE)
+            .ok_or_else(|| {
+                BridgeError::Translation(format!(
+                    "array fill of {} x {} bytes is too large for the stack",
+                    count, elem_size
+                ))
+            })?;
+
+        let slot = self.builder.create_sized_stack_slot(make_stack_slot(total_size.max(8)));
+        let base_addr = self.builder.ins().stack_addr(POINTER_TYPE, slot, 0);
+        if count == 0 {
+            return Ok(base_addr);
+        }
+        let v = self.get_value(value)?;
+
+        // Zero (or any byte value in a byte array) lowers to a memset
+        match self.const_int_value(v) {
+            Some(0) => {
+                self.zero_fill(base_addr, total_size);
+                return Ok(base_addr);
+            }
+            Some(byte) if elem_size == 1 => {
+                let config = self.module.target_config();
+                self.builder
+                    .emit_small_memset(config, base_addr, byte as u8, count, 1, MemFlags::new());
+                return Ok(base_addr);
+            }
+            _ => {}
+        }
+
+        if count <= MAX_UNROLLED_FILL {
+            for i in 0..count as u32 {
+                self.builder
+                    .ins()
+                    .store(MemFlags::new(), v, base_addr, (i * elem_size) as i32);
+            }
+            return Ok(base_addr);
+        }
+
+        // Store loop: body(i) stores element i, then loops while i + 1 < count
+        let body = self.builder.create_block();
+        let exit = self.builder.create_block();
+        self.builder.append_block_param(body, POINTER_TYPE);
+        let zero = self.builder.ins().iconst(POINTER_TYPE, 0);
+        self.builder.ins().jump(body, &[BlockArg::Value(zero)]);
+
+        self.builder.switch_to_block(body);
+        let index = self.builder.block_params(body)[0];
+        let offset = self.builder.ins().imul_imm(index, elem_size as i64);
+        let addr = self.builder.ins().iadd(base_addr, offset);
+        self.builder.ins().store(MemFlags::new(), v, addr, 0);
+        let next = self.builder.ins().iadd_imm(index, 1);
+        let more = self
+            .builder
+            .ins()
+            .icmp_imm(IntCC::UnsignedLessThan, next, count as i64);
+        self.builder
+            .ins()
+            .brif(more, body, &[BlockArg::Value(next)], exit, &[]);
+
+        self.builder.switch_to_block(exit);
+        Ok(base_addr)
+    }
+
+    /// Float add/sub/mul under the fast-math option: fuse a multiply feeding
+    /// an add or sub into `fma`, and fold `(x op c1) op c2` into
+    /// `x op (c1 op c2)`. None means strict lowering applies.
+    fn translate_fast_float(
+        &mut self,
+        op: BinOp,
+        lhs: ClifValue,
+        rhs: ClifValue,
+    ) -> Option<ClifValue> {
+        let mode = self.float_mode;
+        if mode.contract && matches!(op, BinOp::Add | BinOp::Sub) {
+            let negate = matches!(op, BinOp::Sub);
+            if let Some((a, b)) = self.float_binary_args(lhs, Opcode::Fmul) {
+                // a*b + c, a*b - c
+                let c = if negate { self.builder.ins().fneg(rhs) } else { rhs };
+                return Some(self.builder.ins().fma(a, b, c));
+            }
+            if let Some((a, b)) = self.float_binary_args(rhs, Opcode::Fmul) {
+                // c + a*b, c - a*b = (-a)*b + c
+                let a = if negate { self.builder.ins().fneg(a) } else { a };
+                return Some(self.builder.ins().fma(a, b, lhs));
+            }
+        }
+        if mode.reassociate && matches!(op, BinOp::Add | BinOp::Mul) {
+            let opcode = if matches!(op, BinOp::Add) { Opcode::Fadd } else { Opcode::Fmul };
+            let c2 = self.const_float_value(rhs)?;
+            let (x, c1) = self.float_binary_args(lhs, opcode)?;
+            let c1 = self.const_float_value(c1)?;
+            let folded = if matches!(op, BinOp::Add) { c1 + c2 } else { c1 * c2 };
+            let c = if self.builder.func.dfg.value_type(rhs) == types::F32 {
+                self.builder.ins().f32const(folded as f32)
             } else {
-                self.builder.ins().ireduce(POINTER_TYPE, addr)
+                self.builder.ins().f64const(folded)
             };
+            return Some(if matches!(op, BinOp::Add) {
+                self.builder.ins().fadd(x, c)
+            } else {
+                self.builder.ins().fmul(x, c)
+            });
         }
+        None
+    }
 
-        for idx in indices {
-            let mut idx_val = self.get_value(idx)?;
-            // Coerce index to pointer-sized integer for arithmetic
-            let idx_ty = self.builder.func.dfg.value_type(idx_val);
-            if idx_ty != POINTER_TYPE && idx_ty.is_int() {
-                idx_val = if idx_ty.bytes() < POINTER_TYPE.bytes() {
-                    self.builder.ins().sextend(POINTER_TYPE, idx_val)
-                } else {
-                    self.builder.ins().ireduce(POINTER_TYPE, idx_val)
-                };
+    /// Operands of `v` if it is the result of a float binary `opcode`.
+    fn float_binary_args(&self, v: ClifValue, opcode: Opcode) -> Option<(ClifValue, ClifValue)> {
+        let dfg = &self.builder.func.dfg;
+        let ValueDef::Result(inst, _) = dfg.value_def(v) else {
+            return None;
+        };
+        match dfg.insts[inst] {
+            ClifInstData::Binary { opcode: op, args } if op == opcode => Some((args[0], args[1])),
+            _ => None,
+        }
+    }
+
+    /// Value of `v` if it is an `f32const`/`f64const`, computed in the
+    /// constant's own precision.
+    fn const_float_value(&self, v: ClifValue) -> Option<f64> {
+        let dfg = &self.builder.func.dfg;
+        let ValueDef::Result(inst, _) = dfg.value_def(v) else {
+            return None;
+        };
+        match dfg.insts[inst] {
+            ClifInstData::UnaryIeee32 { opcode: Opcode::F32const, imm } => {
+                Some(f32::from_bits(imm.bits()) as f64)
+            }
+            ClifInstData::UnaryIeee64 { opcode: Opcode::F64const, imm } => {
+                Some(f64::from_bits(imm.bits()))
             }
-            let eight = self.builder.ins().iconst(POINTER_TYPE, 8);
-            let offset = self.builder.ins().imul(idx_val, eight);
-            addr = self.builder.ins().iadd(addr, offset);
+            _ => None,
+        }
+    }
+
+    /// The integer constant behind `v`, if it was produced by an `iconst`.
+    fn const_int_value(&self, v: ClifValue) -> Option<i64> {
+        let dfg = &self.builder.func.dfg;
+        let ValueDef::Result(inst, _) = dfg.value_def(v) else {
+            return None;
+        };
+        match dfg.insts[inst] {
+            ClifInstData::UnaryImm {
+                opcode: Opcode::Iconst,
+                imm,
+            } => Some(imm.bits()),
+            _ => None,
+        }
+    }
+
+    fn translate_gep(
+        &mut self,
+        base: &Value,
+        indices: &[Value],
+    ) -> BridgeResult<ClifValue> {
+        let base_val = self.get_value(base)?;
+        let mut addr = FlatPointers.int_to_ptr(self.builder, base_val);
+
+        // Every GEP index steps over 8-byte slots
+        for idx in indices {
+            let idx_val = self.get_value(idx)?;
+            let offset = FlatPointers.scaled_offset(self.builder, idx_val, 8);
+            addr = FlatPointers.offset(self.builder, addr, offset);
         }
 
         Ok(addr)
@@ -1709,3 +3792,2545 @@ impl<'a, 'b> FunctionTranslator<'a, 'b> {
         Ok(base_addr)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::switch_case_pattern;
+    use crate::mir_types::*;
+    use crate::test_util::*;
+
+    fn switch_fn(disc_ty: PrimitiveType, cases: &[i64]) -> Function {
+        let mut blocks = vec![block(
+            0,
+            Vec::new(),
+            Terminator::Switch {
+                discriminant: v(0),
+                cases: cases.iter().enumerate().map(|(i, &c)| (c, i as u32 + 1)).collect(),
+                default_block: 99,
+            },
+        )];
+        for (i, _) in cases.iter().enumerate() {
+            let id = i as u32 + 1;
+            blocks.push(block(
+                id,
+                vec![const_int(10 + id, id as i64, 32)],
+                Terminator::Return { value: Some(v(10 + id)) },
+            ));
+        }
+        blocks.push(block(
+            99,
+            vec![const_int(9, -1, 32)],
+            Terminator::Return { value: Some(v(9)) },
+        ));
+        function("sw", vec![(0, prim(disc_ty))], prim(PrimitiveType::I32), blocks)
+    }
+
+    fn assert_compiles(func: Function) {
+        assert_compiles_module(&module(vec![func]));
+    }
+
+    fn assert_compiles_module(m: &Module) {
+        let outcome = compile(m, &default_options());
+        assert!(outcome.success, "{}", outcome.text);
+        assert!(!outcome.data.is_empty());
+    }
+
+    #[test]
+    fn case_patterns_wrap_to_discriminant_width() {
+        assert_eq!(switch_case_pattern(-1, 8, None), Some(0xFF));
+        assert_eq!(switch_case_pattern(255, 8, None), Some(0xFF));
+        assert_eq!(switch_case_pattern(-128, 8, None), Some(0x80));
+        assert_eq!(switch_case_pattern(-129, 8, None), None);
+        assert_eq!(switch_case_pattern(256, 8, None), None);
+        assert_eq!(switch_case_pattern(-2, 32, None), Some(0xFFFF_FFFE));
+        assert_eq!(switch_case_pattern(-1, 64, None), Some(u64::MAX as u128));
+    }
+
+    #[test]
+    fn case_patterns_respect_known_signedness() {
+        assert_eq!(switch_case_pattern(-1, 8, Some(false)), None);
+        assert_eq!(switch_case_pattern(255, 8, Some(false)), Some(0xFF));
+        assert_eq!(switch_case_pattern(255, 8, Some(true)), None);
+        assert_eq!(switch_case_pattern(-1, 8, Some(true)), Some(0xFF));
+        // u64 cases above i64::MAX arrive as negative i64 values
+        assert_eq!(switch_case_pattern(-1, 64, Some(false)), Some(u64::MAX as u128));
+    }
+
+    #[test]
+    fn unsigned_operands_use_unsigned_compares() {
+        let cmp = |op| {
+            function(
+                "cmp",
+                vec![(0, prim(PrimitiveType::U32)), (1, prim(PrimitiveType::U32))],
+                prim(PrimitiveType::Bool),
+                vec![block(
+                    0,
+                    vec![inst(2, Instruction::Binary { op, left: v(0), right: v(1) })],
+                    Terminator::Return { value: Some(v(2)) },
+                )],
+            )
+        };
+        let outcome = generate_ir(
+            &module(vec![cmp(BinOp::Lt), cmp(BinOp::Ge)]),
+            &default_options(),
+        );
+        assert!(outcome.success, "{}", outcome.text);
+        assert!(outcome.text.contains("icmp ult"), "{}", outcome.text);
+        assert!(outcome.text.contains("icmp uge"), "{}", outcome.text);
+        assert!(!outcome.text.contains("icmp slt"), "{}", outcome.text);
+    }
+
+    #[test]
+    fn negative_cases_on_narrow_discriminants() {
+        assert_compiles(switch_fn(PrimitiveType::I8, &[-1, 0, 127, -128]));
+        assert_compiles(switch_fn(PrimitiveType::I16, &[-32768, -1, 1]));
+        assert_compiles(switch_fn(PrimitiveType::I32, &[i32::MIN as i64, -1, 7]));
+        assert_compiles(switch_fn(PrimitiveType::I64, &[i64::MIN, -1, i64::MAX]));
+    }
+
+    #[test]
+    fn unrepresentable_and_aliased_cases_are_dropped() {
+        // 300 never matches an i8; 255 aliases -1 and the first case wins
+        assert_compiles(switch_fn(PrimitiveType::I8, &[-1, 300, 255, 2]));
+        assert_compiles(switch_fn(PrimitiveType::U8, &[255, -1, -200]));
+    }
+
+    #[test]
+    fn bool_discriminant() {
+        assert_compiles(switch_fn(PrimitiveType::Bool, &[0, 1]));
+        assert_compiles(switch_fn(PrimitiveType::Bool, &[1]));
+    }
+
+    #[test]
+    fn switch_targets_with_phis() {
+        let ret_ty = prim(PrimitiveType::I32);
+        let blocks = vec![
+            block(
+                0,
+                vec![const_int(1, 10, 32), const_int(2, 20, 32)],
+                Terminator::Switch {
+                    discriminant: v(0),
+                    cases: vec![(-5, 1), (3, 2)],
+                    default_block: 2,
+                },
+            ),
+            block(1, Vec::new(), Terminator::Branch { target: 2 }),
+            block(
+                2,
+                vec![inst(
+                    3,
+                    Instruction::Phi {
+                        incoming: vec![(v(1), 0), (v(2), 1)],
+                    },
+                )],
+                Terminator::Return { value: Some(v(3)) },
+            ),
+        ];
+        assert_compiles(function("sw_phi", vec![(0, prim(PrimitiveType::I8))], ret_ty, blocks));
+    }
+
+    #[test]
+    fn integer_intrinsics_lower_inline() {
+        use PrimitiveType::*;
+        let names = [
+            "saturating_add",
+            "saturating_sub",
+            "saturating_mul",
+            "wrapping_add",
+            "wrapping_sub",
+            "wrapping_mul",
+            "I32::wrapping_neg",
+        ];
+        let mut funcs = Vec::new();
+        for (ti, prim_ty) in [I8, I16, I32, I64, U8, U16, U32, U64].into_iter().enumerate() {
+            for (ni, name) in names.iter().enumerate() {
+                let args = if name.ends_with("neg") { vec![v(0)] } else { vec![v(0), v(1)] };
+                let call = Instruction::Call {
+                    func_name: name.to_string(),
+                    args,
+                    return_type: prim(prim_ty),
+                };
+                funcs.push(function(
+                    &format!("f{}_{}", ti, ni),
+                    vec![(0, prim(prim_ty)), (1, prim(prim_ty))],
+                    prim(prim_ty),
+                    vec![block(0, vec![inst(2, call)], Terminator::Return { value: Some(v(2)) })],
+                ));
+            }
+        }
+        let m = module(funcs);
+        let outcome = generate_ir(&m, &default_options());
+        assert!(outcome.success, "{}", outcome.text);
+        assert!(!outcome.text.contains("call "), "{}", outcome.text);
+        assert_compiles_module(&m);
+    }
+
+
+    fn float_chain() -> Module {
+        use PrimitiveType::F64;
+        let f64c = |result, value| {
+            inst(result, Instruction::Constant(Constant::Float { value, is_f64: true }))
+        };
+        let bin = |result, op, left, right| {
+            inst(result, Instruction::Binary { op, left: v(left), right: v(right) })
+        };
+        module(vec![function(
+            "chain",
+            vec![(0, prim(F64)), (1, prim(F64)), (2, prim(F64))],
+            prim(F64),
+            vec![block(
+                0,
+                vec![
+                    bin(3, BinOp::Mul, 0, 1),
+                    bin(4, BinOp::Add, 3, 2),
+                    f64c(5, 1.0),
+                    f64c(6, 2.0),
+                    bin(7, BinOp::Add, 4, 5),
+                    bin(8, BinOp::Add, 7, 6),
+                ],
+                Terminator::Return { value: Some(v(8)) },
+            )],
+        )])
+    }
+
+    #[test]
+    fn strict_float_lowering_by_default() {
+        let outcome = generate_ir(&float_chain(), &default_options());
+        assert!(outcome.success, "{}", outcome.text);
+        assert!(!outcome.text.contains("fma"), "{}", outcome.text);
+        assert_eq!(outcome.text.matches("fadd").count(), 3, "{}", outcome.text);
+    }
+
+    #[test]
+    fn fast_math_fuses_and_reassociates() {
+        let mut options = default_options();
+        options.fast_math =
+            crate::options::FAST_MATH_CONTRACT | crate::options::FAST_MATH_REASSOCIATE;
+        let outcome = generate_ir(&float_chain(), &options);
+        assert!(outcome.success, "{}", outcome.text);
+        // (x + 1.0) + 2.0 becomes x + 3.0
+        assert!(outcome.text.contains("f64const 0x1.8000000000000p1"), "{}", outcome.text);
+        #[cfg(target_arch = "x86_64")]
+        if std::is_x86_feature_detected!("fma") {
+            assert!(outcome.text.contains("fma"), "{}", outcome.text);
+        }
+        assert!(compile(&float_chain(), &options).success);
+    }
+
+
+    #[test]
+    fn ordered_and_unordered_float_predicates() {
+        let ops = [
+            (BinOp::Ord, "ord"),
+            (BinOp::Uno, "uno"),
+            (BinOp::One, "one"),
+            (BinOp::Ueq, "ueq"),
+            (BinOp::Ult, "ult"),
+            (BinOp::Ule, "ule"),
+            (BinOp::Ugt, "ugt"),
+            (BinOp::Uge, "uge"),
+        ];
+        for ty in [PrimitiveType::F64, PrimitiveType::I32] {
+            for (op, cc) in ops {
+                let m = module(vec![function(
+                    "pred",
+                    vec![(0, prim(ty)), (1, prim(ty))],
+                    prim(PrimitiveType::Bool),
+                    vec![block(
+                        0,
+                        vec![inst(2, Instruction::Binary { op, left: v(0), right: v(1) })],
+                        Terminator::Return { value: Some(v(2)) },
+                    )],
+                )]);
+                let outcome = generate_ir(&m, &default_options());
+                assert!(outcome.success, "{}", outcome.text);
+                if ty == PrimitiveType::F64 {
+                    assert!(outcome.text.contains(&format!("fcmp {}", cc)), "{}", outcome.text);
+                } else {
+                    assert!(!outcome.text.contains("fcmp"), "{}", outcome.text);
+                }
+                assert_compiles_module(&m);
+            }
+        }
+    }
+
+
+    /// Whether `kind` is meaningful from `src` to `dst`; everything else must
+    /// be rejected with an error rather than a verifier failure or panic.
+    fn cast_is_valid(kind: CastKind, src: PrimitiveType, dst: PrimitiveType) -> bool {
+        let int = |p: PrimitiveType| {
+            p.is_integer() || matches!(p, PrimitiveType::Bool | PrimitiveType::Ptr)
+        };
+        let wide = |p: PrimitiveType| matches!(p, PrimitiveType::I128 | PrimitiveType::U128);
+        match kind {
+            CastKind::Bitcast => true,
+            CastKind::Trunc
+            | CastKind::ZExt
+            | CastKind::SExt
+            | CastKind::PtrToInt
+            | CastKind::IntToPtr => int(src) && int(dst),
+            CastKind::FPTrunc | CastKind::FPExt => src.is_float() && dst.is_float(),
+            // 128-bit float conversions are rejected as unsupported
+            CastKind::FPToSI | CastKind::FPToUI => src.is_float() && int(dst) && !wide(dst),
+            CastKind::SIToFP | CastKind::UIToFP => int(src) && dst.is_float() && !wide(src),
+            CastKind::BoolCast => int(dst),
+        }
+    }
+
+    #[test]
+    fn cast_matrix() {
+        use PrimitiveType::*;
+        let prims = [Bool, I8, I16, I32, I64, I128, U8, U16, U32, U64, U128, F32, F64, Ptr];
+        let mut failures = Vec::new();
+        for kind in (0..=12).filter_map(CastKind::from_u8) {
+            for src in prims {
+                for dst in prims {
+                    let m = module(vec![function(
+                        "cast",
+                        vec![(0, prim(src))],
+                        prim(dst),
+                        vec![block(
+                            0,
+                            vec![inst(
+                                1,
+                                Instruction::Cast { kind, operand: v(0), target_type: prim(dst) },
+                            )],
+                            Terminator::Return { value: Some(v(1)) },
+                        )],
+                    )]);
+                    let outcome = compile(&m, &default_options());
+                    if outcome.success != cast_is_valid(kind, src, dst)
+                        || outcome.text.starts_with("PANIC")
+                    {
+                        failures.push(format!("{:?} {:?} -> {:?}: {}", kind, src, dst, outcome.text));
+                    }
+                }
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn bool_casts_test_nonzero_and_trunc_keeps_bit_zero() {
+        let cast = |kind| {
+            let m = module(vec![function(
+                "to_bool",
+                vec![(0, prim(PrimitiveType::I32))],
+                prim(PrimitiveType::Bool),
+                vec![block(
+                    0,
+                    vec![inst(
+                        1,
+                        Instruction::Cast {
+                            kind,
+                            operand: v(0),
+                            target_type: prim(PrimitiveType::Bool),
+                        },
+                    )],
+                    Terminator::Return { value: Some(v(1)) },
+                )],
+            )]);
+            generate_ir(&m, &default_options()).text
+        };
+        assert!(cast(CastKind::BoolCast).contains("icmp_imm ne"));
+        assert!(cast(CastKind::Trunc).contains("band_imm"));
+    }
+
+
+    /// `mem_zero(&local, 8)` for each local in turn; the call makes each
+    /// address escape, so none can be promoted to an SSA variable.
+    fn locals_used_in_sequence(loop_back: bool) -> Module {
+        let i64t = prim(PrimitiveType::I64);
+        let alloca = |id, name: &str| {
+            inst(id, Instruction::Alloca { name: name.into(), alloc_type: i64t.clone() })
+        };
+        let zero = |id, local| {
+            inst(
+                id,
+                Instruction::Call {
+                    func_name: "mem_zero".into(),
+                    args: vec![v(local), v(9)],
+                    return_type: prim(PrimitiveType::Unit),
+                },
+            )
+        };
+        let blocks = vec![
+            block(
+                0,
+                vec![alloca(1, "a"), alloca(2, "b"), const_int(9, 8, 64)],
+                Terminator::Branch { target: 1 },
+            ),
+            block(1, vec![zero(3, 1)], Terminator::Branch { target: 2 }),
+            block(
+                2,
+                vec![zero(4, 2)],
+                if loop_back {
+                    Terminator::CondBranch { condition: v(0), true_block: 1, false_block: 3 }
+                } else {
+                    Terminator::Branch { target: 3 }
+                },
+            ),
+            block(3, Vec::new(), Terminator::Return { value: None }),
+        ];
+        let params = vec![(0, prim(PrimitiveType::Bool))];
+        module(vec![function("locals", params, prim(PrimitiveType::Unit), blocks)])
+    }
+
+    fn slot_count(m: &Module, opt_level: i32) -> usize {
+        let mut options = default_options();
+        options.optimization_level = opt_level;
+        let outcome = generate_ir(m, &options);
+        assert!(outcome.success, "{}", outcome.text);
+        assert!(compile(m, &options).success);
+        outcome.text.matches("explicit_slot").count()
+    }
+
+    #[test]
+    fn disjoint_locals_share_a_slot_when_optimizing() {
+        let m = locals_used_in_sequence(false);
+        assert_eq!(slot_count(&m, 0), 2);
+        assert_eq!(slot_count(&m, 1), 1);
+    }
+
+    #[test]
+    fn locals_live_across_a_loop_keep_separate_slots() {
+        // `a` is used again on the next iteration, after `b`
+        let m = locals_used_in_sequence(true);
+        assert_eq!(slot_count(&m, 1), 2);
+    }
+
+    #[test]
+    fn addresses_kept_in_a_tuple_or_by_a_call_keep_their_slot() {
+        // `&a` escapes in block 1 and is read back through a pointer in
+        // block 3, after `b` has started using a slot in block 2
+        let i64t = prim(PrimitiveType::I64);
+        let ptr = prim(PrimitiveType::Ptr);
+        let call = |name: &str, args: Vec<Value>, return_type: &MirType| Instruction::Call {
+            func_name: name.into(),
+            args,
+            return_type: return_type.clone(),
+        };
+        let escaped = |keep: Instruction, read_back: Instruction| {
+            let alloca = |id, name: &str| inst(id, Instruction::Alloca { name: name.into(), alloc_type: i64t.clone() });
+            let unit = prim(PrimitiveType::Unit);
+            let blocks = vec![
+                block(0, vec![alloca(1, "a"), alloca(2, "b"), const_int(9, 8, 64)], Terminator::Branch { target: 1 }),
+                block(1, vec![inst(3, call("mem_zero", vec![v(1), v(9)], &unit)), inst(4, keep)], Terminator::Branch {
+                    target: 2,
+                }),
+                block(2, vec![inst(5, call("mem_zero", vec![v(2), v(9)], &unit))], Terminator::Branch { target: 3 }),
+                block(3, vec![inst(6, read_back), inst(7, Instruction::Load { ptr: v(6) })], Terminator::Return {
+                    value: Some(v(7)),
+                }),
+            ];
+            let retain = function("retain", vec![(0, ptr.clone())], unit.clone(), Vec::new());
+            let peek = function("peek", Vec::new(), ptr.clone(), Vec::new());
+            module(vec![function("escapes", Vec::new(), i64t.clone(), blocks), retain, peek])
+        };
+
+        let through_tuple = escaped(
+            Instruction::TupleInit { elements: vec![v(1)] },
+            Instruction::ExtractValue { aggregate: v(4), indices: vec![0] },
+        );
+        assert_eq!(slot_count(&through_tuple, 1), slot_count(&through_tuple, 0));
+        let retain = call("retain", vec![v(1)], &prim(PrimitiveType::Unit));
+        let through_call = escaped(retain, call("peek", Vec::new(), &ptr));
+        assert_eq!(slot_count(&through_call, 1), slot_count(&through_call, 0));
+    }
+
+
+    #[test]
+    fn large_frames_are_reported() {
+        let m = locals_used_in_sequence(false);
+        let mut options = default_options();
+        options.frame_warn_bytes = 8;
+        let outcome = generate_ir(&m, &options);
+        assert!(outcome.success, "{}", outcome.text);
+        assert!(
+            outcome.text.contains("warning: in function 'locals': stack frame of 16 bytes"),
+            "{}",
+            outcome.text
+        );
+
+        options.frame_warn_bytes = 16;
+        assert!(!generate_ir(&m, &options).text.contains("stack frame"));
+    }
+
+    #[test]
+    fn values_held_across_calls_raise_register_pressure() {
+        use crate::stats::{FunctionStats, MirStats};
+        use PrimitiveType::I64;
+
+        // Eight parameters stay live across the call, then get summed
+        let mut insts = vec![inst(
+            20,
+            Instruction::Call { func_name: "tick".into(), args: Vec::new(), return_type: prim(I64) },
+        )];
+        let mut acc = 20;
+        for p in 0..8 {
+            insts.push(inst(30 + p, Instruction::Binary { op: BinOp::Add, left: v(acc), right: v(p) }));
+            acc = 30 + p;
+        }
+        let f = function(
+            "held",
+            (0..8).map(|p| (p, prim(I64))).collect(),
+            prim(I64),
+            vec![block(0, insts, Terminator::Return { value: Some(v(acc)) })],
+        );
+
+        let fs = FunctionStats::of(&f);
+        assert_eq!(fs.live_across_calls, 8);
+        assert_eq!(fs.max_live, 9);
+        assert_eq!(fs.estimated_spills(), 2);
+
+        let json = MirStats::collect(&module(vec![f])).to_json();
+        assert!(json.contains("\"register_pressure\":[{\"name\":\"held\""), "{}", json);
+    }
+
+    #[test]
+    fn trap_abort_reports_trap_categories() {
+        use PrimitiveType::I32;
+
+        let m = module(vec![
+            function(
+                "div",
+                vec![(0, prim(I32)), (1, prim(I32))],
+                prim(I32),
+                vec![block(
+                    0,
+                    vec![inst(2, Instruction::Binary { op: BinOp::Div, left: v(0), right: v(1) })],
+                    Terminator::Return { value: Some(v(2)) },
+                )],
+            ),
+            function(
+                "never",
+                Vec::new(),
+                prim(PrimitiveType::Unit),
+                vec![block(0, Vec::new(), Terminator::Unreachable)],
+            ),
+        ]);
+
+        let plain = generate_ir(&m, &default_options());
+        assert!(plain.success, "{}", plain.text);
+        assert!(!plain.text.contains("tml_trap_abort"));
+
+        let mut options = default_options();
+        options.trap_abort = 1;
+        let outcome = generate_ir(&m, &options);
+        assert!(outcome.success, "{}", outcome.text);
+        // Zero divisor, MIN / -1, unreachable
+        for (category, code) in [(1, "int_divz"), (2, "int_ovf"), (0, "user1")] {
+            let call = format!("iconst.i32 {}\n", category);
+            assert!(outcome.text.contains(&call), "missing category {}:\n{}", category, outcome.text);
+            assert!(outcome.text.contains(&format!("trap {}", code)), "{}", outcome.text);
+        }
+        assert!(compile(&m, &options).success);
+    }
+
+    #[test]
+    fn test_registry_lists_defined_tests() {
+        use crate::CraneliftTestEntry;
+        use crate::test_registry::{self, MAGIC};
+
+        let unit = || prim(PrimitiveType::Unit);
+        let ret = || Terminator::Return { value: None };
+        let m = module(vec![
+            function("it_adds", Vec::new(), unit(), vec![block(0, Vec::new(), ret())]),
+            function("it_panics", Vec::new(), unit(), vec![block(0, Vec::new(), ret())]),
+        ]);
+        let entries = [
+            CraneliftTestEntry {
+                function: c"it_adds".as_ptr(),
+                name: c"math::adds".as_ptr(),
+                should_panic: 0,
+                expected_panic: std::ptr::null(),
+            },
+            CraneliftTestEntry {
+                function: c"it_panics".as_ptr(),
+                name: std::ptr::null(),
+                should_panic: 1,
+                expected_panic: c"overflow".as_ptr(),
+            },
+        ];
+        let mut options = default_options();
+        options.tests = entries.as_ptr();
+        options.num_tests = entries.len();
+
+        let outcome = compile(&m, &options);
+        assert!(outcome.success, "{}", outcome.text);
+        let start = outcome.data.windows(4).position(|w| w == MAGIC).expect("no .tml.tests data");
+        let tests = test_registry::decode(&outcome.data[start..]).unwrap();
+        assert_eq!(tests.len(), 2);
+        assert_eq!(tests[0].0, "tml_it_adds");
+        assert_eq!(tests[0].1.name, "math::adds");
+        assert!(!tests[0].1.should_panic && tests[0].1.expected_panic.is_none());
+        assert_eq!(tests[1].1.name, "it_panics");
+        assert!(tests[1].1.should_panic);
+        assert_eq!(tests[1].1.expected_panic.as_deref(), Some("overflow"));
+
+        let missing = [CraneliftTestEntry { function: c"gone".as_ptr(), ..entries[0] }];
+        options.tests = missing.as_ptr();
+        options.num_tests = 1;
+        let outcome = compile(&m, &options);
+        assert!(!outcome.success);
+        assert!(outcome.text.contains("test function 'gone' not found"), "{}", outcome.text);
+    }
+
+    #[test]
+    fn runtime_manifest_drives_imports_and_strict_calls() {
+        use crate::runtime_sigs;
+        use cranelift_codegen::ir::types;
+        use PrimitiveType::{I32, I64};
+
+        let should_panic = runtime_sigs::all().iter().find(|s| s.name == "tml_run_should_panic").unwrap();
+        assert_eq!(should_panic.params, vec![crate::types::POINTER_TYPE]);
+        assert_eq!(should_panic.ret, Some(types::I32));
+
+        let call = |arg_ty: PrimitiveType, nargs: usize| {
+            let mut insts: Vec<_> = (0..nargs as u32).map(|i| const_int(i, 7, arg_ty.bit_width() as u8)).collect();
+            insts.push(inst(
+                10,
+                Instruction::Call {
+                    func_name: "print_i32".into(),
+                    args: (0..nargs as u32).map(v).collect(),
+                    return_type: prim(PrimitiveType::Unit),
+                },
+            ));
+            module(vec![function(
+                "caller",
+                Vec::new(),
+                prim(PrimitiveType::Unit),
+                vec![block(0, insts, Terminator::Return { value: None })],
+            )])
+        };
+        let mut strict = default_options();
+        strict.strict_runtime_calls = 1;
+
+        assert!(compile(&call(I32, 1), &strict).success);
+        // Coerced by default, rejected in strict mode
+        assert!(compile(&call(I64, 1), &default_options()).success);
+        let wide = compile(&call(I64, 1), &strict);
+        assert!(!wide.success);
+        assert!(wide.text.contains("passes i64 as argument 0, the runtime takes i32"), "{}", wide.text);
+        let extra = compile(&call(I32, 2), &strict);
+        assert!(extra.text.contains("passes 2 arguments, the runtime takes 1"), "{}", extra.text);
+    }
+
+    #[test]
+    fn multiversioned_functions_get_variants_and_a_dispatcher() {
+        use PrimitiveType::I64;
+
+        let m = module(vec![function(
+            "hot",
+            vec![(0, prim(I64)), (1, prim(I64))],
+            prim(I64),
+            vec![block(
+                0,
+                vec![inst(2, Instruction::Binary { op: BinOp::Mul, left: v(0), right: v(1) })],
+                Terminator::Return { value: Some(v(2)) },
+            )],
+        )]);
+        let mut options = default_options();
+        options.multiversion = c"h*".as_ptr();
+        let outcome = compile(&m, &options);
+        assert!(outcome.success, "{}", outcome.text);
+
+        let has = |s: &str| outcome.data.windows(s.len()).any(|w| w == s.as_bytes());
+        if cfg!(target_arch = "x86_64") {
+            for symbol in ["tml_hot.base", "tml_hot.avx2", "tml_hot.impl", "tml_cpu_has_avx2"] {
+                assert!(has(symbol), "missing {}", symbol);
+            }
+        }
+    }
+
+    /// `answer()` sums 1..=n in a loop (a constant function), `caller()`
+    /// adds one to its result.
+    #[cfg(feature = "interpreter")]
+    fn constant_callee(n: i64) -> Module {
+        use PrimitiveType::I32;
+        let bin = |result, op, left, right| {
+            inst(result, Instruction::Binary { op, left: v(left), right: v(right) })
+        };
+        let phi = |result, entry, back| {
+            inst(result, Instruction::Phi { incoming: vec![(v(entry), 0), (v(back), 1)] })
+        };
+        let answer = function(
+            "answer",
+            Vec::new(),
+            prim(I32),
+            vec![
+                block(
+                    0,
+                    vec![const_int(0, 0, 32), const_int(1, 1, 32), const_int(2, n, 32)],
+                    Terminator::Branch { target: 1 },
+                ),
+                block(
+                    1,
+                    vec![
+                        phi(3, 0, 5),
+                        phi(4, 1, 6),
+                        bin(5, BinOp::Add, 3, 4),
+                        bin(6, BinOp::Add, 4, 1),
+                        bin(7, BinOp::Le, 6, 2),
+                    ],
+                    Terminator::CondBranch { condition: v(7), true_block: 1, false_block: 2 },
+                ),
+                block(2, Vec::new(), Terminator::Return { value: Some(v(5)) }),
+            ],
+        );
+        let call = Instruction::Call {
+            func_name: "answer".into(),
+            args: Vec::new(),
+            return_type: prim(I32),
+        };
+        let caller = function(
+            "caller",
+            Vec::new(),
+            prim(I32),
+            vec![block(
+                0,
+                vec![inst(0, call), const_int(1, 1, 32), bin(2, BinOp::Add, 0, 1)],
+                Terminator::Return { value: Some(v(2)) },
+            )],
+        );
+        module(vec![answer, caller])
+    }
+
+    #[cfg(feature = "interpreter")]
+    fn caller_ir(m: &Module, opt_level: i32) -> String {
+        let mut options = default_options();
+        options.optimization_level = opt_level;
+        options.ir_filter = c"caller".as_ptr();
+        let outcome = generate_ir(m, &options);
+        assert!(outcome.success, "{}", outcome.text);
+        assert!(compile(m, &options).success);
+        outcome.text
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn calls_to_constant_functions_fold_when_optimizing() {
+        let m = constant_callee(10);
+        let folded = caller_ir(&m, 2);
+        assert!(!folded.contains("call fn"), "{}", folded);
+        assert!(folded.contains("iconst.i32 55"), "{}", folded);
+        assert!(caller_ir(&m, 0).contains("call fn"));
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn constant_evaluation_follows_the_lowering() {
+        use crate::const_eval::evaluate;
+        use PrimitiveType::{I8, U8};
+
+        let eval = |ret, insts: Vec<InstructionData>, result| {
+            evaluate(&function("f", Vec::new(), ret, vec![block(
+                0,
+                insts,
+                Terminator::Return { value: Some(v(result)) },
+            )]))
+        };
+        let u8c = |result, value| {
+            inst(result, Instruction::Constant(Constant::Int { value, bit_width: 8, is_signed: false }))
+        };
+        let bin = |result, op, left, right| {
+            inst(result, Instruction::Binary { op, left: v(left), right: v(right) })
+        };
+        let int = |c: Option<Constant>| match c {
+            Some(Constant::Int { value, .. }) => Some(value),
+            _ => None,
+        };
+
+        let i8_pair = |a, b, op| vec![const_int(0, a, 8), const_int(1, b, 8), bin(2, op, 0, 1)];
+        let u8_pair = |a, b, op| vec![u8c(0, a), u8c(1, b), bin(2, op, 0, 1)];
+
+        // Wrapping arithmetic at the operand width
+        assert_eq!(int(eval(prim(I8), i8_pair(127, 1, BinOp::Add), 2)), Some(-128));
+        assert_eq!(int(eval(prim(U8), u8_pair(255, 1, BinOp::Add), 2)), Some(0));
+        // Unsigned operands compare unsigned
+        assert!(matches!(
+            eval(prim(PrimitiveType::Bool), u8_pair(200, 1, BinOp::Gt), 2),
+            Some(Constant::Bool(true))
+        ));
+        // Trapping divisions are left to run time
+        assert!(eval(prim(I8), i8_pair(1, 0, BinOp::Div), 2).is_none());
+        assert!(eval(prim(I8), i8_pair(-128, -1, BinOp::Div), 2).is_none());
+        // Non-terminating loops give up
+        let spin = function(
+            "spin",
+            Vec::new(),
+            prim(I8),
+            vec![block(0, Vec::new(), Terminator::Branch { target: 0 })],
+        );
+        assert!(evaluate(&spin).is_none());
+    }
+
+    #[test]
+    fn runtime_imports_use_prefixed_aliases_per_target() {
+        use crate::options::{RUNTIME_PREFIX_OFF, RUNTIME_PREFIX_ON};
+
+        let m = module(vec![function(
+            "caller",
+            Vec::new(),
+            prim(PrimitiveType::Unit),
+            vec![block(
+                0,
+                vec![
+                    const_int(0, 7, 32),
+                    inst(
+                        1,
+                        Instruction::Call {
+                            func_name: "print_i32".into(),
+                            args: vec![v(0)],
+                            return_type: prim(PrimitiveType::Unit),
+                        },
+                    ),
+                ],
+                Terminator::Return { value: None },
+            )],
+        )]);
+        let imports_alias = |runtime_prefix| {
+            let mut options = default_options();
+            options.runtime_prefix = runtime_prefix;
+            let outcome = compile(&m, &options);
+            assert!(outcome.success, "{}", outcome.text);
+            outcome.data.windows(16).any(|w| w == b"tml_rt_print_i32")
+        };
+        assert!(imports_alias(RUNTIME_PREFIX_ON));
+        assert!(!imports_alias(RUNTIME_PREFIX_OFF));
+        assert_eq!(imports_alias(0), !cfg!(windows));
+    }
+
+    #[test]
+    fn profiles_resolve_per_target_and_accept_overrides() {
+        let resolve = |profile: &std::ffi::CStr, target: &std::ffi::CStr, overrides: &std::ffi::CStr| {
+            let mut options = default_options();
+            options.profile = profile.as_ptr();
+            options.target_triple = target.as_ptr();
+            options.profile_overrides = overrides.as_ptr();
+            resolve_options(&options)
+        };
+
+        let debug = resolve(c"debug", c"x86_64-unknown-linux-gnu", c"");
+        assert!(debug.success, "{}", debug.text);
+        let fields = ["opt_level\":0", "debug_info\":true", "trap_abort\":true", "runtime_prefix\":true"];
+        for field in fields {
+            assert!(debug.text.contains(field), "{} not in {}", field, debug.text);
+        }
+        // Frame pointers are mandatory on Apple arm64 and overridable elsewhere
+        let apple = resolve(c"release", c"aarch64-apple-darwin", c"");
+        assert!(apple.text.contains("\"frame_pointers\":true"), "{}", apple.text);
+        let linux = resolve(c"release", c"aarch64-unknown-linux-gnu", c"opt_level=1,frame_pointers=1");
+        assert!(linux.text.contains("\"opt_level\":1"), "{}", linux.text);
+        assert!(linux.text.contains("\"frame_pointers\":true"), "{}", linux.text);
+        let windows = resolve(c"small", c"x86_64-pc-windows-msvc", c"");
+        assert!(windows.text.contains("\"runtime_prefix\":false"), "{}", windows.text);
+
+        let unknown = resolve(c"fast", c"", c"");
+        assert!(!unknown.success && unknown.text.contains("unknown profile 'fast'"), "{}", unknown.text);
+        let bad = resolve(c"jit", c"", c"opt_level=9");
+        assert!(!bad.success && bad.text.contains("expected 0-3"), "{}", bad.text);
+
+        // Profiles apply to compilation too
+        let mut options = default_options();
+        options.profile = c"release".as_ptr();
+        let m = module(vec![function(
+            "f",
+            Vec::new(),
+            prim(PrimitiveType::I32),
+            vec![block(0, vec![const_int(0, 1, 32)], Terminator::Return { value: Some(v(0)) })],
+        )]);
+        assert!(compile(&m, &options).success);
+        options.profile = c"bogus".as_ptr();
+        assert!(!compile(&m, &options).success);
+    }
+
+    #[test]
+    fn method_calls_bind_to_the_receiver_types_function() {
+        use PrimitiveType::I64;
+        let mut len = function(
+            "Point__len",
+            vec![(0, prim(I64))],
+            prim(I64),
+            vec![block(0, Vec::new(), Terminator::Return { value: Some(v(0)) })],
+        );
+        len.is_public = false;
+        let caller = |receiver_type: &str| {
+            let call = Instruction::MethodCall {
+                receiver: v(0),
+                receiver_type: receiver_type.into(),
+                method_name: "len".into(),
+                args: Vec::new(),
+                return_type: prim(I64),
+            };
+            function(
+                "caller",
+                vec![(0, prim(I64))],
+                prim(I64),
+                vec![block(0, vec![inst(1, call)], Terminator::Return { value: Some(v(1)) })],
+            )
+        };
+        let imports_bare_len = |m: &Module| {
+            let outcome = compile(m, &default_options());
+            assert!(outcome.success, "{}", outcome.text);
+            outcome.data.windows(8).any(|w| w == b"tml_len\0")
+        };
+
+        let known = module(vec![len.clone(), caller("Point")]);
+        assert!(!imports_bare_len(&known));
+        assert!(imports_bare_len(&module(vec![len, caller("")])));
+
+        // The receiver type survives serialization, and stats count the binding
+        let mir = crate::mir_writer::MirBinaryWriter::new().write_module(&known);
+        let mut result = unsafe { std::mem::zeroed() };
+        crate::cranelift_mir_stats_v2(mir.as_ptr(), mir.len(), &mut result);
+        let stats = take(&mut result);
+        assert!(stats.text.contains("\"method_calls\":{\"total\":1,\"devirtualized\":1}"), "{}", stats.text);
+
+        // LTO keeps the bound method reachable
+        let mut merged = known;
+        crate::lto::prune_unreachable(&mut merged);
+        assert_eq!(merged.functions.len(), 2);
+    }
+
+    #[test]
+    fn self_test_reports_every_check() {
+        let run = |overrides: &std::ffi::CStr| {
+            let mut options = default_options();
+            options.profile_overrides = overrides.as_ptr();
+            let mut result = unsafe { std::mem::zeroed() };
+            let ok = crate::cranelift_self_test_v2(&options, &mut result);
+            let error = if result.error_msg.is_null() {
+                String::new()
+            } else {
+                unsafe { std::ffi::CStr::from_ptr(result.error_msg) }.to_string_lossy().into_owned()
+            };
+            let report = take(&mut result).text;
+            (ok == 1, report, error)
+        };
+
+        let (ok, report, _) = run(c"");
+        assert!(ok, "{}", report);
+        assert!(report.starts_with("{\"passed\":true,"), "{}", report);
+        for name in ["isa", "mir", "object"] {
+            assert!(report.contains(&format!("{{\"name\":\"{}\",\"status\":\"pass\"", name)), "{}", report);
+        }
+        if cfg!(target_arch = "x86_64") {
+            assert!(report.contains("\"status\":\"pass\",\"detail\":\"self_test_sum(3, 10) = 135\""), "{}", report);
+            let (ok, report, _) = run(c"cpu=baseline,opt_level=2");
+            assert!(ok && report.contains("= 135\""), "{}", report);
+            let (ok, report, _) = run(c"cpu=haswell");
+            assert!(ok && report.contains("{\"name\":\"execute\",\"status\":\"skip\""), "{}", report);
+        }
+
+        let (ok, report, error) = run(c"cpu=pentium9");
+        assert!(!ok && report.contains("\"passed\":false"), "{}", report);
+        assert!(report.contains("{\"name\":\"object\",\"status\":\"skip\""), "{}", report);
+        assert!(error.starts_with("self test failed: isa: invalid options: unknown cpu 'pentium9'"), "{}", error);
+    }
+
+    #[test]
+    fn cpu_classes_and_cranelift_flags() {
+        let m = module(vec![function(
+            "f",
+            Vec::new(),
+            prim(PrimitiveType::I32),
+            vec![block(0, vec![const_int(0, 1, 32)], Terminator::Return { value: Some(v(0)) })],
+        )]);
+        let run = |overrides: &std::ffi::CStr| {
+            let mut options = default_options();
+            options.profile_overrides = overrides.as_ptr();
+            (compile(&m, &options), resolve_options(&options))
+        };
+
+        for overrides in [c"cpu=native", c"cpu=baseline", c"flag.enable_probestack=true,flag.probestack_strategy=inline"] {
+            let (compiled, resolved) = run(overrides);
+            assert!(compiled.success && resolved.success, "{:?}: {}", overrides, compiled.text);
+        }
+        let (_, resolved) = run(c"cpu=baseline,flag.regalloc_algorithm=single_pass");
+        assert!(resolved.text.contains("\"cpu\":\"baseline\",\"flags\":{\"regalloc_algorithm\":\"single_pass\"}"), "{}", resolved.text);
+        if cfg!(target_arch = "x86_64") {
+            // Presets and ISA flags of the host architecture
+            assert!(run(c"cpu=haswell").0.success);
+            assert!(run(c"cpu=baseline,flag.has_avx2=true").0.success);
+        }
+
+        let (compiled, resolved) = run(c"cpu=pentium9");
+        assert!(!compiled.success && compiled.text.contains("unknown cpu 'pentium9' (expected one of: native, baseline"), "{}", compiled.text);
+        assert!(!resolved.success);
+        let (compiled, _) = run(c"flag.enable_probestak=true");
+        assert!(compiled.text.contains("flag.enable_probestak=true: "), "{}", compiled.text);
+        let (compiled, _) = run(c"flag.opt_level=fastest");
+        assert!(!compiled.success, "{}", compiled.text);
+
+        // A profile resets both, and the snapshot round-trips them
+        let mut opts = crate::options::BridgeOptions::default();
+        crate::profiles::apply_overrides(&mut opts, "cpu=baseline,flag.enable_probestack=1").unwrap();
+        let restored = crate::options::BridgeOptions::from_snapshot(&opts.snapshot()).unwrap();
+        assert_eq!(restored.cpu, "baseline");
+        assert_eq!(restored.cranelift_flags, vec![("enable_probestack".to_string(), "1".to_string())]);
+        crate::profiles::apply(&mut opts, "release", &target_lexicon::Triple::host()).unwrap();
+        assert!(opts.cpu.is_empty() && opts.cranelift_flags.is_empty());
+    }
+
+    #[test]
+    fn target_feature_strings() {
+        let m = module(vec![function(
+            "f",
+            Vec::new(),
+            prim(PrimitiveType::I32),
+            vec![block(0, vec![const_int(0, 1, 32)], Terminator::Return { value: Some(v(0)) })],
+        )]);
+        let run = |overrides: &std::ffi::CStr| {
+            let mut options = default_options();
+            options.profile_overrides = overrides.as_ptr();
+            (compile(&m, &options), resolve_options(&options))
+        };
+
+        // Features start from the baseline unless a class is given, and keep
+        // their commas inside the overrides
+        let (overrides, features) = if cfg!(target_arch = "x86_64") {
+            (c"target_features=+avx2,+bmi2,-fma,opt_level=2", "+avx2,+bmi2,-fma")
+        } else {
+            (c"target_features=+lse,opt_level=2", "+lse")
+        };
+        let (compiled, resolved) = run(overrides);
+        assert!(compiled.success && resolved.success, "{}", compiled.text);
+        let expected = format!("\"cpu\":\"baseline\",\"flags\":{{}},\"target_features\":\"{}\"", features);
+        assert!(resolved.text.contains(&expected), "{}", resolved.text);
+        assert!(resolved.text.contains("\"opt_level\":2"), "{}", resolved.text);
+        let (_, resolved) = run(c"cpu=native");
+        assert!(resolved.text.contains("\"cpu\":\"native\",\"flags\":{},\"target_features\":\"\""), "{}", resolved.text);
+
+        let (compiled, _) = run(c"target_features=+warp_drive");
+        assert!(compiled.text.contains("target feature '+warp_drive': unknown on "), "{}", compiled.text);
+        let (compiled, _) = run(c"target_features=avx2");
+        assert!(compiled.text.contains("target feature 'avx2': expected +name or -name"), "{}", compiled.text);
+        if cfg!(target_arch = "x86_64") {
+            assert!(!run(c"target_features=+lse").0.success);
+            assert!(run(c"cpu=haswell,target_features=-avx2,-fma").0.success);
+            assert!(run(c"target_features=+sse4.2,+popcnt").0.success);
+        }
+
+        // The blob takes the string as one value; snapshots and profiles
+        // treat it like cpu
+        let blob = crate::options::encode_blob(&[("target_features", "+avx2,+bmi2")]);
+        let mut opts = crate::options::BridgeOptions::from_blob(&blob).unwrap();
+        assert_eq!(opts.target_features, "+avx2,+bmi2");
+        let restored = crate::options::BridgeOptions::from_snapshot(&opts.snapshot()).unwrap();
+        assert_eq!(restored.target_features, "+avx2,+bmi2");
+        crate::profiles::apply(&mut opts, "release", &target_lexicon::Triple::host()).unwrap();
+        assert!(opts.target_features.is_empty());
+    }
+
+    #[test]
+    fn supported_targets_compile_for_their_triple() {
+        let list = unsafe { std::ffi::CStr::from_ptr(crate::cranelift_get_supported_targets()) };
+        let targets: Vec<&str> = list.to_str().unwrap().split(',').collect();
+        assert_eq!(targets[0], target_lexicon::Triple::host().to_string());
+        for triple in ["x86_64-pc-windows-msvc", "aarch64-apple-darwin", "riscv64gc-unknown-linux-gnu"] {
+            assert!(targets.contains(&triple), "{} not in {:?}", triple, targets);
+        }
+
+        let m = module(vec![function(
+            "f",
+            Vec::new(),
+            prim(PrimitiveType::I32),
+            vec![block(0, vec![const_int(0, 1, 32)], Terminator::Return { value: Some(v(0)) })],
+        )]);
+        let run = |triple: &std::ffi::CStr, overrides: &std::ffi::CStr| {
+            let mut options = default_options();
+            options.target_triple = triple.as_ptr();
+            options.profile_overrides = overrides.as_ptr();
+            compile(&m, &options)
+        };
+        // The object is for the target, not the host
+        let windows = run(c"x86_64-pc-windows-msvc", c"");
+        assert!(windows.success && windows.data.starts_with(&[0x64, 0x86]), "{}", windows.text);
+        let apple = run(c"aarch64-apple-darwin", c"");
+        assert!(apple.success && apple.data.starts_with(&[0xCF, 0xFA, 0xED, 0xFE]), "{}", apple.text);
+        let riscv = run(c"riscv64gc-unknown-linux-gnu", c"");
+        assert!(riscv.success && riscv.data.starts_with(b"\x7fELF"), "{}", riscv.text);
+
+        // Host extensions cannot be detected for another target
+        let other = if cfg!(target_arch = "aarch64") { c"x86_64-unknown-linux-gnu" } else { c"aarch64-unknown-linux-gnu" };
+        let compiled = run(other, c"cpu=native");
+        assert!(compiled.text.contains("cpu 'native' needs the host as target"), "{}", compiled.text);
+    }
+
+    #[test]
+    fn repeated_constants_are_shared_and_floats_pooled() {
+        let float = |result, value| inst(result, Instruction::Constant(Constant::Float { value, is_f64: true }));
+        let binary = |result, op, left, right| {
+            inst(result, Instruction::Binary { op, left: v(left), right: v(right) })
+        };
+        let m = module(vec![function(
+            "poly",
+            vec![(0, prim(PrimitiveType::F64))],
+            prim(PrimitiveType::F64),
+            vec![
+                block(
+                    0,
+                    vec![float(1, 1.5), binary(2, BinOp::Mul, 0, 1), float(3, 1.5), binary(4, BinOp::Add, 2, 3)],
+                    Terminator::Branch { target: 1 },
+                ),
+                block(
+                    1,
+                    vec![float(5, 2.25), binary(6, BinOp::Mul, 4, 5), float(7, 1.5), binary(8, BinOp::Add, 6, 7)],
+                    Terminator::Return { value: Some(v(8)) },
+                ),
+            ],
+        )]);
+        let run = |overrides: &std::ffi::CStr| {
+            let mut options = default_options();
+            options.profile_overrides = overrides.as_ptr();
+            assert!(compile(&m, &options).success);
+            let ir = generate_ir(&m, &options);
+            assert!(ir.success, "{}", ir.text);
+            ir.text
+        };
+
+        // 1.5 is materialized once, in the entry block, and reused in bb1
+        let ir = run(c"");
+        assert_eq!(ir.matches("f64const").count(), 2, "{}", ir);
+        assert!(!ir.contains("load.f64"), "{}", ir);
+
+        let ir = run(c"const_pool_threshold=1");
+        assert!(!ir.contains("f64const"), "{}", ir);
+        assert_eq!(ir.matches("symbol_value").count(), 1, "{}", ir);
+        assert_eq!(ir.matches("load.f64 notrap aligned readonly").count(), 2, "{}", ir);
+        assert!(!run(c"const_pool_threshold=2").contains("load.f64"));
+    }
+
+    #[test]
+    fn shared_constants_go_to_the_nearest_dominating_block() {
+        use PrimitiveType::I64;
+        let add = |result, left, right| {
+            inst(result, Instruction::Binary { op: BinOp::Add, left: v(left), right: v(right) })
+        };
+        let ret = |value| Terminator::Return { value: Some(v(value)) };
+        // 1000 is repeated inside bb1 only, 2000 in both arms of the branch
+        let m = module(vec![function(
+            "f",
+            vec![(0, prim(PrimitiveType::Bool)), (1, prim(I64))],
+            prim(I64),
+            vec![
+                block(0, Vec::new(), Terminator::CondBranch { condition: v(0), true_block: 1, false_block: 2 }),
+                block(
+                    1,
+                    vec![const_int(2, 1000, 64), add(3, 1, 2), const_int(4, 1000, 64), add(5, 3, 4)],
+                    Terminator::Branch { target: 3 },
+                ),
+                block(2, vec![const_int(6, 2000, 64), add(7, 1, 6)], Terminator::Branch { target: 3 }),
+                block(
+                    3,
+                    vec![
+                        inst(8, Instruction::Phi { incoming: vec![(v(5), 1), (v(7), 2)] }),
+                        const_int(9, 2000, 64),
+                        add(10, 8, 9),
+                    ],
+                    ret(10),
+                ),
+            ],
+        )]);
+        let mut options = default_options();
+        options.optimization_level = 1;
+        assert!(compile(&m, &options).success);
+        let ir = generate_ir(&m, &options);
+        assert!(ir.success, "{}", ir.text);
+        let block_of = |needle: &str| {
+            let at = ir.text.find(needle).unwrap_or_else(|| panic!("{} not in {}", needle, ir.text));
+            ir.text[..at].rfind("\nblock").map(|b| &ir.text[b + 1..b + 7])
+        };
+        assert_eq!(ir.text.matches("iconst.i64 1000").count(), 1, "{}", ir.text);
+        assert_eq!(ir.text.matches("iconst.i64 2000").count(), 1, "{}", ir.text);
+        assert_eq!(block_of("iconst.i64 1000"), Some("block1"), "{}", ir.text);
+        assert_eq!(block_of("iconst.i64 2000"), Some("block0"), "{}", ir.text);
+    }
+
+    #[test]
+    fn redundant_phis_are_not_block_params() {
+        use PrimitiveType::I32;
+        let bin = |result, op, left, right| {
+            inst(result, Instruction::Binary { op, left: v(left), right: v(right) })
+        };
+        let phi = |result, entry, back| {
+            inst(result, Instruction::Phi { incoming: vec![(v(entry), 0), (v(back), 1)] })
+        };
+        // Only `i` (3) changes around the loop: the limit (4) passes itself
+        // back, and 8 is trivial once 4 is gone
+        let m = module(vec![function(
+            "count",
+            vec![(0, prim(I32))],
+            prim(I32),
+            vec![
+                block(0, vec![const_int(1, 0, 32), const_int(2, 1, 32)], Terminator::Branch { target: 1 }),
+                block(
+                    1,
+                    vec![
+                        phi(3, 1, 5),
+                        phi(4, 0, 4),
+                        phi(8, 0, 4),
+                        bin(5, BinOp::Add, 3, 2),
+                        bin(9, BinOp::Lt, 5, 8),
+                    ],
+                    Terminator::CondBranch { condition: v(9), true_block: 1, false_block: 2 },
+                ),
+                block(2, Vec::new(), Terminator::Return { value: Some(v(5)) }),
+            ],
+        )]);
+        let options = default_options();
+        assert!(compile(&m, &options).success);
+        let ir = generate_ir(&m, &options);
+        assert!(ir.success, "{}", ir.text);
+        let header = ir.text.lines().find(|l| l.starts_with("block1(")).unwrap();
+        assert_eq!(header.matches(": i32").count(), 1, "{}", ir.text);
+        assert!(ir.text.contains("block0(v1: i32)") && ir.text.contains("icmp slt v4, v1"), "{}", ir.text);
+    }
+
+    #[test]
+    fn failed_translations_write_a_replay_bundle() {
+        let m = module(vec![function(
+            "caller",
+            Vec::new(),
+            prim(PrimitiveType::Unit),
+            vec![block(
+                0,
+                vec![
+                    const_int(0, 7, 64),
+                    inst(
+                        1,
+                        Instruction::Call {
+                            func_name: "print_i32".into(),
+                            args: vec![v(0)],
+                            return_type: prim(PrimitiveType::Unit),
+                        },
+                    ),
+                ],
+                Terminator::Return { value: None },
+            )],
+        )]);
+        let dir = std::env::temp_dir().join(format!("tml-replay-test-{}", std::process::id()));
+        let dir_c = std::ffi::CString::new(dir.to_str().unwrap()).unwrap();
+        let mut options = default_options();
+        options.strict_runtime_calls = 1;
+
+        // Off unless a directory is given
+        let plain = compile(&m, &options);
+        assert!(!plain.success && !plain.text.contains("replay bundle"), "{}", plain.text);
+
+        options.replay_dir = dir_c.as_ptr();
+        let failed = compile(&m, &options);
+        assert!(!failed.success);
+        let path = failed.text.split("(replay bundle: ").nth(1).unwrap().trim_end_matches(')');
+        let bundle = crate::replay::decode(&std::fs::read(path).unwrap()).unwrap();
+        assert_eq!(bundle.entry, "compile");
+        assert_eq!(bundle.bridge_version, crate::replay::BRIDGE_VERSION);
+        assert_eq!(bundle.modules, vec![crate::mir_writer::MirBinaryWriter::new().write_module(&m)]);
+        assert!(failed.text.starts_with(&bundle.error), "{}", failed.text);
+        assert!(bundle.options.strict_runtime_calls && bundle.func_indices.is_none());
+        assert_eq!(bundle.options.snapshot(), crate::options::BridgeOptions::from_snapshot(&bundle.options.snapshot()).unwrap().snapshot());
+
+        // The bundle alone reproduces the failure, also through the dev API
+        let replayed = crate::compile_mir_impl(&bundle.modules[0], None, &bundle.options);
+        assert_eq!(replayed.unwrap_err().to_string(), bundle.error);
+        #[cfg(feature = "dev-tools")]
+        {
+            let mut input = crate::dev::Input::load(std::path::Path::new(path)).unwrap();
+            assert_eq!(crate::dev::compile(&input).unwrap_err(), bundle.error);
+            assert!(crate::dev::validate(&input).unwrap().is_empty());
+            assert!(crate::dev::stats(&input).unwrap().starts_with("module test: 1 functions"));
+            input.apply_overrides("strict_runtime_calls=0").unwrap();
+            assert!(crate::dev::compile(&input).is_ok());
+            assert!(crate::dev::ir_dump(&input).unwrap().contains("function"));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn json_output_keys_functions_by_symbol_and_reports_findings() {
+        let ret = |id| block(id, vec![const_int(0, 1, 32)], Terminator::Return { value: Some(v(0)) });
+        let m = module(vec![
+            function("good", Vec::new(), prim(PrimitiveType::I32), vec![ret(0)]),
+            function(
+                "broken",
+                Vec::new(),
+                prim(PrimitiveType::I32),
+                vec![block(0, Vec::new(), Terminator::Branch { target: 7 })],
+            ),
+        ]);
+        let mut options = default_options();
+        // Plain text fails on the invalid function
+        assert!(!generate_ir(&m, &options).success);
+
+        options.json_output = 1;
+        let out = generate_ir(&m, &options);
+        assert!(out.success, "{}", out.text);
+        assert!(out.text.starts_with("{\"module\":\"test\",\"functions\":{\"tml_good\":{\"name\":\"good\",\"index\":0,\"ir\":\"function"), "{}", out.text);
+        let broken = "\"tml_broken\":{\"name\":\"broken\",\"index\":1,\"ir\":null,\"findings\":[{\"severity\":\"error\",\
+                      \"message\":\"branch to unknown block bb7\",\"span\":{\"function\":\"broken\",\"block\":0,\"instruction\":null}}]}";
+        assert!(out.text.contains(broken), "{}", out.text);
+        assert!(out.text.ends_with("]}}"), "{}", out.text);
+        assert!(out.text.contains("},\"stats\":{\"module\":\"test\",\"functions\":2,"), "{}", out.text);
+    }
+
+    #[test]
+    fn cancelled_compiles_stop_with_a_cancelled_result() {
+        let ret = |id| block(id, vec![const_int(0, 1, 32)], Terminator::Return { value: Some(v(0)) });
+        let m = module(vec![
+            function("a", Vec::new(), prim(PrimitiveType::I32), vec![ret(0)]),
+            function("b", Vec::new(), prim(PrimitiveType::I32), vec![ret(0)]),
+        ]);
+        let mir = crate::mir_writer::MirBinaryWriter::new().write_module(&m);
+        let token = crate::cranelift_cancel_token_new();
+        let mut options = default_options();
+        options.cancel_token = token;
+        options.replay_dir = c"".as_ptr();
+        assert!(compile(&m, &options).success);
+
+        crate::cranelift_cancel(token);
+        let mut result = unsafe { std::mem::zeroed() };
+        assert_eq!(crate::cranelift_compile_mir_v2(mir.as_ptr(), mir.len(), &options, &mut result), 0);
+        assert_eq!(result.cancelled, 1);
+        // No replay bundle for a cancellation
+        assert_eq!(take(&mut result).text, "cancelled");
+        assert_eq!(generate_ir(&m, &options).text, "cancelled");
+
+        // The blob takes the handle as hex
+        let handle = format!("{:#x}", token as usize);
+        let blob = crate::options::encode_blob(&[("cancel_token", &handle)]);
+        let ok = crate::cranelift_compile_mir_kv(mir.as_ptr(), mir.len(), blob.as_ptr(), blob.len(), &mut result);
+        assert_eq!((ok, result.cancelled), (0, 1));
+        take(&mut result);
+
+        // Freed handles are rejected, not read
+        crate::cranelift_cancel_token_free(token);
+        crate::cranelift_cancel_token_free(token);
+        crate::cranelift_cancel(token);
+        let freed = compile(&m, &options);
+        assert!(freed.text.contains("options.cancel_token: ") && freed.text.ends_with("is not a live cancel token"), "{}", freed.text);
+        let ok = crate::cranelift_compile_mir_kv(mir.as_ptr(), mir.len(), blob.as_ptr(), blob.len(), &mut result);
+        assert_eq!((ok, result.cancelled), (0, 0));
+        assert!(take(&mut result).text.contains("option 'cancel_token': not a live cancel token"));
+    }
+
+    #[test]
+    fn parallel_compiles_match_for_every_thread_count() {
+        use PrimitiveType::I32;
+        // f0 returns 1, each fN returns f(N-1) + 1
+        let functions = (0..12)
+            .map(|n| {
+                let body = if n == 0 {
+                    vec![const_int(0, 1, 32)]
+                } else {
+                    let call = Instruction::Call {
+                        func_name: format!("f{}", n - 1),
+                        args: Vec::new(),
+                        return_type: prim(I32),
+                    };
+                    let add = Instruction::Binary { op: BinOp::Add, left: v(1), right: v(2) };
+                    vec![inst(1, call), const_int(2, 1, 32), inst(0, add)]
+                };
+                let body = block(0, body, Terminator::Return { value: Some(v(0)) });
+                function(&format!("f{}", n), Vec::new(), prim(I32), vec![body])
+            })
+            .collect();
+        let mir = crate::mir_writer::MirBinaryWriter::new().write_module(&module(functions));
+        let mut options = default_options();
+        options.optimization_level = 2;
+        // Variants compile with their own ISA on x86-64
+        options.multiversion = c"f3".as_ptr();
+        let run = |options: &crate::CraneliftOptions, threads| {
+            take(&mut crate::cranelift_compile_mir_parallel(mir.as_ptr(), mir.len(), options, threads))
+        };
+
+        let one = run(&options, 1);
+        assert!(one.success, "{}", one.text);
+        let has = |symbol: &[u8]| one.data.windows(symbol.len()).any(|w| w == symbol);
+        assert!(has(b"tml_f0") && has(b"tml_f11"));
+        assert_eq!(has(b"tml_f3.avx2"), cfg!(target_arch = "x86_64"));
+        for threads in [2, 5, 64, 0] {
+            assert!(run(&options, threads).data == one.data, "object differs with {} threads", threads);
+        }
+
+        let token = crate::cranelift_cancel_token_new();
+        crate::cranelift_cancel(token);
+        options.cancel_token = token;
+        let mut result = unsafe { std::mem::zeroed() };
+        crate::cranelift_compile_mir_parallel_v2(mir.as_ptr(), mir.len(), &options, 4, &mut result);
+        assert_eq!(result.cancelled, 1);
+        assert_eq!(take(&mut result).text, "cancelled");
+        crate::cranelift_cancel_token_free(token);
+    }
+
+    #[test]
+    fn emit_ir_returns_object_and_ir_text_together() {
+        use PrimitiveType::I32;
+        let call = Instruction::Call { func_name: "zeta".into(), args: Vec::new(), return_type: prim(I32) };
+        let ret = || Terminator::Return { value: Some(v(0)) };
+        let m = module(vec![
+            function("zeta", Vec::new(), prim(I32), vec![block(0, vec![const_int(0, 7, 32)], ret())]),
+            function("beta", Vec::new(), prim(I32), vec![block(0, vec![const_int(0, 2, 32)], ret())]),
+            function("alpha", Vec::new(), prim(I32), vec![block(0, vec![inst(0, call)], ret())]),
+        ]);
+        let mut options = default_options();
+        options.ir_filter = c"alpha,tml_zeta".as_ptr();
+        options.ir_annotate = 1;
+        options.ir_sort = 1;
+        let object = compile(&m, &options);
+        assert!(object.success && object.text.is_empty(), "{}", object.text);
+        let ir = generate_ir(&m, &options);
+        assert!(ir.text.find("alpha").unwrap() < ir.text.find("zeta").unwrap(), "{}", ir.text);
+        assert!(!ir.text.contains("beta"));
+
+        options.emit_ir = 1;
+        let both = compile(&m, &options);
+        assert!(both.success, "{}", both.text);
+        assert!(both.data == object.data);
+        assert_eq!(both.text, ir.text);
+        let mir = crate::mir_writer::MirBinaryWriter::new().write_module(&m);
+        let parallel = take(&mut crate::cranelift_compile_mir_parallel(mir.as_ptr(), mir.len(), &options, 2));
+        assert!(parallel.data == object.data);
+        assert_eq!(parallel.text, ir.text);
+    }
+
+    #[test]
+    fn failures_carry_an_error_code() {
+        use crate::error::*;
+        use PrimitiveType::F64;
+        let code = |m: &Module, options: &crate::CraneliftOptions| {
+            let mir = crate::mir_writer::MirBinaryWriter::new().write_module(m);
+            let mut result = crate::cranelift_compile_mir(mir.as_ptr(), mir.len(), options);
+            let code = result.error_code;
+            take(&mut result);
+            code
+        };
+        let ret = |value| Terminator::Return { value: Some(v(value)) };
+        let ok = module(vec![function("f", Vec::new(), prim(F64), vec![block(0, Vec::new(), ret(0))])]);
+        let options = default_options();
+        assert_eq!(code(&ok, &options), ERROR_NONE);
+
+        let modulo = Instruction::Binary { op: BinOp::Mod, left: v(0), right: v(1) };
+        let params = vec![(0, prim(F64)), (1, prim(F64))];
+        let fmod = module(vec![function("f", params, prim(F64), vec![block(0, vec![inst(2, modulo)], ret(2))])]);
+        assert_eq!(code(&fmod, &options), ERROR_UNSUPPORTED_INSTRUCTION);
+        let jump = block(0, Vec::new(), Terminator::Branch { target: 9 });
+        let dangling = module(vec![function("f", Vec::new(), prim(F64), vec![jump])]);
+        assert_eq!(code(&dangling, &options), ERROR_TRANSLATION);
+
+        let mut bad = default_options();
+        bad.target_triple = c"nonsense".as_ptr();
+        assert_eq!(code(&ok, &bad), ERROR_INVALID_TARGET);
+        let mut bad = default_options();
+        bad.profile = c"nonsense".as_ptr();
+        assert_eq!(code(&ok, &bad), ERROR_INVALID_OPTIONS);
+
+        let garbage = crate::cranelift_compile_mir(b"junk".as_ptr(), 4, &options);
+        assert_eq!(garbage.error_code, ERROR_DESERIALIZE);
+        let empty = crate::cranelift_compile_mir(std::ptr::null(), 0, &options);
+        assert_eq!(empty.error_code, ERROR_INVALID_ARGUMENT);
+        for mut result in [garbage, empty] {
+            crate::cranelift_free_result(&mut result);
+        }
+
+        let token = crate::cranelift_cancel_token_new();
+        crate::cranelift_cancel(token);
+        let mut cancelled = default_options();
+        cancelled.cancel_token = token;
+        assert_eq!(code(&ok, &cancelled), ERROR_CANCELLED);
+        crate::cranelift_cancel_token_free(token);
+    }
+
+    #[test]
+    fn validate_mir_checks_value_references() {
+        use PrimitiveType::{Bool, I32};
+        let validate = |functions: Vec<Function>| {
+            let mir = crate::mir_writer::MirBinaryWriter::new().write_module(&module(functions));
+            let mut result = crate::cranelift_validate_mir(mir.as_ptr(), mir.len());
+            let (success, code) = (result.success != 0, result.error_code);
+            let report = unsafe { std::slice::from_raw_parts(result.ir_text as *const u8, result.ir_text_len) };
+            let report = String::from_utf8(report.to_vec()).unwrap();
+            take(&mut result);
+            (success, code, report)
+        };
+        let add = |left, right| Instruction::Binary { op: BinOp::Add, left: v(left), right: v(right) };
+        let ret = |value| Terminator::Return { value: Some(v(value)) };
+
+        // A loop: the phi takes %3 from the back edge, defined after it in bb1
+        let phi = Instruction::Phi { incoming: vec![(v(1), 0), (v(3), 1)] };
+        let looping = function(
+            "count",
+            vec![(0, prim(Bool))],
+            prim(I32),
+            vec![
+                block(0, vec![const_int(1, 0, 32)], Terminator::Branch { target: 1 }),
+                block(
+                    1,
+                    vec![inst(2, phi), const_int(4, 1, 32), inst(3, add(2, 4))],
+                    Terminator::CondBranch { condition: v(0), true_block: 1, false_block: 2 },
+                ),
+                block(2, Vec::new(), ret(3)),
+            ],
+        );
+        let (success, code, report) = validate(vec![looping]);
+        assert!(success && code == crate::error::ERROR_NONE, "{}", report);
+        assert!(report.contains("\"errors\":0"), "{}", report);
+
+        let undefined = function("f", Vec::new(), prim(I32), vec![block(0, vec![inst(1, add(5, 5))], ret(1))]);
+        let body = vec![inst(1, add(2, 2)), const_int(2, 1, 32)];
+        let early = function("g", Vec::new(), prim(I32), vec![block(0, body, ret(1))]);
+        let body = vec![const_int(1, 1, 32), const_int(1, 2, 32)];
+        let twice = function("h", Vec::new(), prim(I32), vec![block(0, body, ret(1))]);
+        let branchy = function(
+            "k",
+            vec![(0, prim(Bool))],
+            prim(I32),
+            vec![
+                block(0, Vec::new(), Terminator::CondBranch { condition: v(0), true_block: 1, false_block: 2 }),
+                block(1, vec![const_int(1, 1, 32)], Terminator::Branch { target: 2 }),
+                block(2, Vec::new(), ret(1)),
+            ],
+        );
+        let (success, code, report) = validate(vec![undefined, early, twice, branchy]);
+        assert!(!success && code == crate::error::ERROR_TRANSLATION);
+        for expected in [
+            "%5 is not defined",
+            "%2 is used before its definition",
+            "%1 is defined more than once",
+            "terminator: %1 is defined in bb1, which does not dominate this use",
+        ] {
+            assert!(report.contains(expected), "missing '{}' in {}", expected, report);
+        }
+        assert!(report.contains("\"errors\":4"), "{}", report);
+
+        let mut garbage = crate::cranelift_validate_mir(b"junk".as_ptr(), 4);
+        assert_eq!(garbage.error_code, crate::error::ERROR_DESERIALIZE);
+        crate::cranelift_free_result(&mut garbage);
+    }
+
+    #[test]
+    fn function_stats_cover_each_defined_function() {
+        use PrimitiveType::I32;
+        let ret = Terminator::Return { value: Some(v(0)) };
+        let m = module(vec![
+            function("one", Vec::new(), prim(I32), vec![block(0, vec![const_int(0, 1, 32)], ret.clone())]),
+            function("two", Vec::new(), prim(I32), vec![block(0, vec![const_int(0, 2, 32)], ret)]),
+        ]);
+        let mir = crate::mir_writer::MirBinaryWriter::new().write_module(&m);
+        let mut options = default_options();
+        // The variants and stub of a multi-versioned function share its record
+        options.multiversion = c"two".as_ptr();
+        let mut plain = crate::cranelift_compile_mir(mir.as_ptr(), mir.len(), &options);
+        assert!(plain.function_stats.is_null() && plain.num_function_stats == 0);
+        crate::cranelift_free_result(&mut plain);
+
+        options.function_stats = 1;
+        for threads in [None, Some(2)] {
+            let mut result = match threads {
+                None => crate::cranelift_compile_mir(mir.as_ptr(), mir.len(), &options),
+                Some(n) => crate::cranelift_compile_mir_parallel(mir.as_ptr(), mir.len(), &options, n),
+            };
+            assert_eq!(result.success, 1);
+            let stats = unsafe { std::slice::from_raw_parts(result.function_stats, result.num_function_stats) };
+            let names: Vec<&str> = stats
+                .iter()
+                .map(|s| unsafe { std::ffi::CStr::from_ptr(s.name) }.to_str().unwrap())
+                .collect();
+            assert_eq!(names, ["one", "two"]);
+            assert!(stats.iter().all(|s| s.code_bytes > 0), "{:?}", threads);
+            crate::cranelift_free_result(&mut result);
+            assert!(result.function_stats.is_null() && result.num_function_stats == 0);
+        }
+    }
+
+    #[test]
+    fn list_functions_summarizes_signatures_without_compiling() {
+        let point = MirType::Struct { name: "Point".into(), type_args: vec![prim(PrimitiveType::F64)] };
+        let mut decl = function(
+            "ext",
+            vec![(0, MirType::Pointer { is_mut: true, pointee: Box::new(point) })],
+            MirType::Tuple { elements: vec![prim(PrimitiveType::I32), prim(PrimitiveType::Bool)] },
+            Vec::new(),
+        );
+        decl.is_public = false;
+        let m = module(vec![
+            function(
+                "f",
+                vec![(0, prim(PrimitiveType::I64))],
+                prim(PrimitiveType::I64),
+                vec![block(0, vec![const_int(1, 2, 64)], Terminator::Return { value: Some(v(1)) })],
+            ),
+            decl,
+        ]);
+        let mir = crate::mir_writer::MirBinaryWriter::new().write_module(&m);
+        let out = take(&mut crate::cranelift_list_functions(mir.as_ptr(), mir.len()));
+        assert!(out.success, "{}", out.text);
+        assert_eq!(
+            out.text,
+            "{\"module\":\"test\",\"functions\":[\
+             {\"index\":0,\"name\":\"f\",\"public\":true,\"defined\":true,\"params\":[{\"name\":\"p0\",\"type\":\"I64\"}],\
+             \"return\":\"I64\",\"blocks\":1,\"instructions\":1},\
+             {\"index\":1,\"name\":\"ext\",\"public\":false,\"defined\":false,\
+             \"params\":[{\"name\":\"p0\",\"type\":\"*mut Point[F64]\"}],\"return\":\"(I32, Bool)\",\"blocks\":0,\"instructions\":0}]}"
+        );
+        assert!(!take(&mut crate::cranelift_list_functions(mir.as_ptr(), 3)).success);
+    }
+
+    #[test]
+    fn named_cgu_compiles_match_index_cgus() {
+        let ret = |id| block(id, vec![const_int(0, 1, 32)], Terminator::Return { value: Some(v(0)) });
+        let m = module(["a", "b", "c"].iter().map(|n| function(n, Vec::new(), prim(PrimitiveType::I32), vec![ret(0)])).collect());
+        let mir = crate::mir_writer::MirBinaryWriter::new().write_module(&m);
+        let options = default_options();
+        let named = |names: &[&std::ffi::CStr]| {
+            let ptrs: Vec<*const i8> = names.iter().map(|n| n.as_ptr()).collect();
+            take(&mut crate::cranelift_compile_mir_named(mir.as_ptr(), mir.len(), ptrs.as_ptr(), ptrs.len(), &options))
+        };
+
+        let by_name = named(&[c"c", c"a"]);
+        assert!(by_name.success, "{}", by_name.text);
+        let indices = [2usize, 0];
+        let by_index = take(&mut crate::cranelift_compile_mir_cgu(mir.as_ptr(), mir.len(), indices.as_ptr(), 2, &options));
+        assert_eq!(by_name.data, by_index.data);
+
+        let missing = named(&[c"a", c"nope", c"d"]);
+        assert!(!missing.success);
+        assert_eq!(missing.text, "no function named 'nope', 'd' in module 'test'");
+        let ptrs = [c"a".as_ptr(), std::ptr::null()];
+        let null = take(&mut crate::cranelift_compile_mir_named(mir.as_ptr(), mir.len(), ptrs.as_ptr(), 2, &options));
+        assert_eq!(null.text, "null function name at index 1");
+    }
+
+    #[test]
+    fn disassemble_lists_functions_with_symbol_names() {
+        use PrimitiveType::I32;
+        let call = Instruction::Call { func_name: "zeta".into(), args: Vec::new(), return_type: prim(I32) };
+        let ret = || Terminator::Return { value: Some(v(0)) };
+        let m = module(vec![
+            function("zeta", Vec::new(), prim(I32), vec![block(0, vec![const_int(0, 7, 32)], ret())]),
+            function("alpha", Vec::new(), prim(I32), vec![block(0, vec![inst(0, call)], ret())]),
+        ]);
+        let mir = crate::mir_writer::MirBinaryWriter::new().write_module(&m);
+        let mut options = default_options();
+        options.ir_sort = 1;
+        let out = take(&mut crate::cranelift_disassemble(mir.as_ptr(), mir.len(), &options));
+        assert!(out.success, "{}", out.text);
+        assert!(out.data.is_empty());
+        let alpha = out.text.find("; Function: alpha (tml_alpha), ").expect(&out.text);
+        assert!(alpha < out.text.find("; Function: zeta (tml_zeta), ").expect(&out.text));
+        assert!(out.text[alpha..].lines().skip(1).any(|l| l.contains("tml_zeta")), "{}", out.text);
+        assert!(!out.text.contains("userextname"), "{}", out.text);
+
+        options.ir_filter = c"zeta".as_ptr();
+        let filtered = take(&mut crate::cranelift_disassemble(mir.as_ptr(), mir.len(), &options));
+        assert!(filtered.text.contains("(tml_zeta)") && !filtered.text.contains("alpha"), "{}", filtered.text);
+    }
+
+    #[test]
+    fn write_callback_streams_the_object() {
+        use std::ffi::c_void;
+        unsafe extern "C" fn collect(context: *mut c_void, data: *const u8, len: usize) -> i32 {
+            let out = unsafe { &mut *(context as *mut Vec<u8>) };
+            out.extend_from_slice(unsafe { std::slice::from_raw_parts(data, len) });
+            0
+        }
+        unsafe extern "C" fn refuse(_: *mut c_void, _: *const u8, _: usize) -> i32 {
+            28
+        }
+        let ret = Terminator::Return { value: Some(v(0)) };
+        let m = module(vec![function(
+            "f",
+            Vec::new(),
+            prim(PrimitiveType::I64),
+            vec![block(0, vec![const_int(0, 5, 64)], ret)],
+        )]);
+        let mut options = default_options();
+        let returned = compile(&m, &options);
+        assert!(returned.success && !returned.data.is_empty(), "{}", returned.text);
+
+        let mut streamed = Vec::new();
+        options.write = Some(collect);
+        options.write_context = &mut streamed as *mut Vec<u8> as *mut c_void;
+        let out = compile(&m, &options);
+        assert!(out.success && out.data.is_empty(), "{}", out.text);
+        assert!(streamed == returned.data);
+
+        options.write = Some(refuse);
+        let failed = compile(&m, &options);
+        assert!(!failed.success);
+        assert!(failed.text.contains("write callback returned 28"), "{}", failed.text);
+    }
+
+    #[test]
+    fn symbol_renames_apply_to_definitions_and_imports() {
+        use PrimitiveType::I32;
+        let call = |name: &str| Instruction::Call { func_name: name.into(), args: Vec::new(), return_type: prim(I32) };
+        let ret = |value| Terminator::Return { value: Some(v(value)) };
+        let mut foo = function("foo", Vec::new(), prim(I32), vec![block(0, vec![const_int(0, 7, 32)], ret(0))]);
+        foo.is_public = true;
+        let body = vec![inst(0, call("foo")), inst(1, call("ext"))];
+        let m = module(vec![foo, function("caller", Vec::new(), prim(I32), vec![block(0, body, ret(1))])]);
+        let renames = [
+            crate::CraneliftSymbolRename { from: c"tml_foo".as_ptr(), to: c"foo_v2".as_ptr() },
+            crate::CraneliftSymbolRename { from: c"tml_ext".as_ptr(), to: c"ext_v1".as_ptr() },
+        ];
+        let mut options = default_options();
+        options.symbol_renames = renames.as_ptr();
+        options.num_symbol_renames = renames.len();
+        let outcome = compile(&m, &options);
+        assert!(outcome.success, "{}", outcome.text);
+        let has = |s: &[u8]| outcome.data.windows(s.len()).any(|w| w == s);
+        assert!(has(b"foo_v2\0") && has(b"ext_v1\0"));
+        assert!(!has(b"tml_foo\0") && !has(b"tml_ext\0"));
+
+        let twice = [("symbol_rename", "tml_foo\ta"), ("symbol_rename", "tml_foo\tb")];
+        let twice = crate::options::encode_blob(&twice);
+        let error = crate::options::BridgeOptions::from_blob(&twice).unwrap_err();
+        assert!(error.to_string().contains("'tml_foo' is renamed more than once"), "{}", error);
+        let resolved = crate::options::BridgeOptions::from_raw(&options).unwrap();
+        let restored = crate::options::BridgeOptions::from_snapshot(&resolved.snapshot()).unwrap();
+        assert_eq!(restored.symbol_renames, resolved.symbol_renames);
+    }
+
+    #[test]
+    fn symbol_prefix_replaces_tml_or_is_left_out() {
+        use PrimitiveType::I32;
+        let call = |name: &str| Instruction::Call { func_name: name.into(), args: Vec::new(), return_type: prim(I32) };
+        let body = vec![block(0, vec![inst(0, call("ext"))], Terminator::Return { value: Some(v(0)) })];
+        let mut foo = function("foo", Vec::new(), prim(I32), body);
+        foo.is_public = true;
+        let m = module(vec![foo]);
+        let mut options = default_options();
+        for (prefix, symbols) in [(c"mylib_", ["mylib_foo", "mylib_ext"]), (c"", ["foo", "ext"])] {
+            options.symbol_prefix = prefix.as_ptr();
+            let outcome = compile(&m, &options);
+            assert!(outcome.success, "{}", outcome.text);
+            for symbol in symbols {
+                let name = format!("{}\0", symbol);
+                assert!(outcome.data.windows(name.len()).any(|w| w == name.as_bytes()), "missing {}", symbol);
+            }
+            // Runtime imports keep their tml_rt_ names; TML functions lose tml_
+            assert!(!outcome.data.windows(8).any(|w| w == b"tml_foo\0"));
+        }
+    }
+
+    #[test]
+    fn multi_compiles_modules_into_one_object() {
+        use PrimitiveType::I32;
+        let call = |name: &str| Instruction::Call { func_name: name.into(), args: Vec::new(), return_type: prim(I32) };
+        let ret = |value| Terminator::Return { value: Some(v(value)) };
+        let constant = |name: &str| {
+            function(name, Vec::new(), prim(I32), vec![block(0, vec![const_int(0, 1, 32)], ret(0))])
+        };
+        let mut shared = constant("shared");
+        shared.is_public = true;
+        let body = vec![inst(0, call("helper")), inst(1, call("shared"))];
+        let main = function("main", Vec::new(), prim(I32), vec![block(0, body, ret(1))]);
+        let helper_decl = function("helper", Vec::new(), prim(I32), Vec::new());
+        let mut unused = constant("unused");
+        unused.is_public = true;
+        let first = module(vec![main, helper_decl, shared.clone()]);
+        let second = module(vec![constant("helper"), shared, unused]);
+
+        let blobs: Vec<Vec<u8>> =
+            [first, second].iter().map(|m| crate::mir_writer::MirBinaryWriter::new().write_module(m)).collect();
+        let ptrs: Vec<*const u8> = blobs.iter().map(|b| b.as_ptr()).collect();
+        let lens: Vec<usize> = blobs.iter().map(Vec::len).collect();
+        let options = default_options();
+        let outcome = take(&mut crate::cranelift_compile_mir_multi(ptrs.as_ptr(), lens.as_ptr(), 2, &options));
+        assert!(outcome.success, "{}", outcome.text);
+        let count = |s: &[u8]| outcome.data.windows(s.len()).filter(|w| *w == s).count();
+        // One copy of the duplicate, and unlike LTO nothing unreachable is dropped
+        assert_eq!(count(b"tml_shared\0"), 1);
+        assert_eq!(count(b"tml_helper\0"), 1);
+        assert_eq!(count(b"tml_unused\0"), 1);
+
+        let outcome = take(&mut crate::cranelift_compile_mir_multi(ptrs.as_ptr(), lens.as_ptr(), 0, &options));
+        assert!(!outcome.success && outcome.text.contains("null or empty MIR module list"), "{}", outcome.text);
+    }
+
+    #[test]
+    fn position_independent_imports_go_through_the_got() {
+        use PrimitiveType::I32;
+        let call = Instruction::Call { func_name: "ext".into(), args: Vec::new(), return_type: prim(I32) };
+        let body = vec![block(0, vec![inst(0, call)], Terminator::Return { value: Some(v(0)) })];
+        let m = module(vec![function("foo", Vec::new(), prim(I32), body)]);
+        // An Elf64_Rela entry's r_info type and r_addend for R_X86_64_GOTPCREL
+        // with the usual -4, which absolute (non-PIC) addressing never uses
+        let got_relocation = |object: &[u8]| {
+            object.chunks_exact(8).collect::<Vec<_>>().windows(2).any(|w| {
+                w[0][..4] == 9u32.to_le_bytes() && w[1] == (-4i64).to_le_bytes()
+            })
+        };
+        let mut options = default_options();
+        options.target_triple = c"x86_64-unknown-linux-gnu".as_ptr();
+        let absolute = compile(&m, &options);
+        assert!(absolute.success, "{}", absolute.text);
+        assert!(!got_relocation(&absolute.data));
+        options.position_independent = 1;
+        let pic = compile(&m, &options);
+        assert!(pic.success, "{}", pic.text);
+        assert!(got_relocation(&pic.data));
+    }
+
+    #[test]
+    fn source_locations_reach_traps_and_line_info() {
+        use PrimitiveType::I32;
+        let mut div = inst(2, Instruction::Binary { op: BinOp::Div, left: v(0), right: v(1) });
+        div.loc = SourceLoc { line: 12, column: 9 };
+        let body = vec![block(0, vec![div], Terminator::Return { value: Some(v(2)) })];
+        let m = module(vec![function("div", vec![(0, prim(I32)), (1, prim(I32))], prim(I32), body)]);
+        let has = |object: &[u8], name: &[u8]| object.windows(name.len()).any(|w| w == name);
+
+        let mut options = default_options();
+        options.target_triple = c"x86_64-unknown-linux-gnu".as_ptr();
+        options.trap_abort = 1;
+        options.debug_info = 1;
+        let unlocated = compile(&m, &options);
+        assert!(unlocated.success, "{}", unlocated.text);
+        assert!(!has(&unlocated.data, b"tml_trap_abort_at"));
+        assert!(!has(&unlocated.data, b".debug_line"));
+
+        options.source_file = c"src/main.tml".as_ptr();
+        let located = compile(&m, &options);
+        assert!(located.success, "{}", located.text);
+        assert!(has(&located.data, b"tml_trap_abort_at"));
+        assert!(has(&located.data, b"src/main.tml"));
+        // Builds without the debug-info feature still compile, without DWARF
+        let dwarf = cfg!(feature = "debug-info");
+        assert_eq!(has(&located.data, b".debug_line"), dwarf);
+        assert_eq!(has(&located.data, b".debug_info"), dwarf);
+        assert_eq!(crate::cranelift_features() & crate::FEATURE_DEBUG_INFO != 0, dwarf);
+
+        let ir = generate_ir(&m, &options);
+        assert!(ir.success, "{}", ir.text);
+        assert!(ir.text.contains("iconst.i32 12\n"), "{}", ir.text);
+    }
+
+    #[test]
+    fn build_info_names_the_producer_and_settings() {
+        let body = vec![block(0, vec![const_int(0, 1, 32)], Terminator::Return { value: Some(v(0)) })];
+        let m = module(vec![function("f", Vec::new(), prim(PrimitiveType::I32), body)]);
+        let mut options = default_options();
+        options.target_triple = c"x86_64-pc-windows-msvc".as_ptr();
+        options.optimization_level = 2;
+        let plain = compile(&m, &options);
+        assert!(plain.success, "{}", plain.text);
+        assert!(!plain.data.windows(9).any(|w| w == b"producer="));
+
+        options.emit_build_info = 1;
+        let outcome = compile(&m, &options);
+        assert!(outcome.success, "{}", outcome.text);
+        let start = outcome.data.windows(9).position(|w| w == b"producer=").expect("no .tml.build data");
+        let end = start + outcome.data[start..].windows(9).position(|w| w == b"features=").unwrap();
+        let info = std::str::from_utf8(&outcome.data[start..end]).unwrap();
+        let version = format!("producer=tml-cranelift-bridge {}\n", env!("CARGO_PKG_VERSION"));
+        for line in [&version, "target=x86_64-pc-windows-msvc\n", "opt_level=2\n", "cpu=baseline\n", "mir=1."] {
+            assert!(info.contains(line), "{:?} not in:\n{}", line, info);
+        }
+    }
+
+    #[cfg(feature = "memory-limit")]
+    #[test]
+    fn memory_limit_fails_with_a_resource_error() {
+        let ret = || block(0, vec![const_int(0, 1, 32)], Terminator::Return { value: Some(v(0)) });
+        let funcs = (0..64).map(|i| function(&format!("f{}", i), Vec::new(), prim(PrimitiveType::I32), vec![ret()]));
+        let mir = crate::mir_writer::MirBinaryWriter::new().write_module(&module(funcs.collect()));
+        let mut options = default_options();
+        options.max_memory_bytes = 1 << 30;
+        let mut result = unsafe { std::mem::zeroed() };
+        assert_eq!(crate::cranelift_compile_mir_v2(mir.as_ptr(), mir.len(), &options, &mut result), 1);
+        take(&mut result);
+
+        options.max_memory_bytes = 64 * 1024;
+        for threads in [None, Some(4)] {
+            match threads {
+                None => crate::cranelift_compile_mir_v2(mir.as_ptr(), mir.len(), &options, &mut result),
+                Some(n) => crate::cranelift_compile_mir_parallel_v2(mir.as_ptr(), mir.len(), &options, n, &mut result),
+            };
+            assert_eq!(result.error_code, crate::error::ERROR_RESOURCE_LIMIT);
+            let text = take(&mut result).text;
+            assert!(text.starts_with("resource limit exceeded: memory limit of 65536 bytes"), "{}", text);
+        }
+
+        // A corrupt function count fails at EOF instead of reserving for it
+        let mut corrupt = crate::mir_writer::MirBinaryWriter::new().write_module(&module(Vec::new()));
+        let count_at = corrupt.len() - 8;
+        corrupt[count_at..count_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        crate::cranelift_compile_mir_v2(corrupt.as_ptr(), corrupt.len(), &options, &mut result);
+        assert_eq!(result.error_code, crate::error::ERROR_DESERIALIZE);
+        take(&mut result);
+    }
+
+    #[test]
+    fn estimates_match_compiled_sizes_without_an_object() {
+        use PrimitiveType::I32;
+        let ret = Terminator::Return { value: Some(v(0)) };
+        let m = module(vec![
+            function("one", Vec::new(), prim(I32), vec![block(0, vec![const_int(0, 1, 32)], ret.clone())]),
+            function("two", Vec::new(), prim(I32), vec![block(0, vec![const_int(0, 2, 32)], ret)]),
+        ]);
+        let mir = crate::mir_writer::MirBinaryWriter::new().write_module(&m);
+        let sizes = |result: &crate::CraneliftResult| -> Vec<(String, u64)> {
+            let stats = unsafe { std::slice::from_raw_parts(result.function_stats, result.num_function_stats) };
+            stats
+                .iter()
+                .map(|s| (unsafe { std::ffi::CStr::from_ptr(s.name) }.to_string_lossy().into_owned(), s.code_bytes))
+                .collect()
+        };
+        let mut options = default_options();
+        options.multiversion = c"two".as_ptr();
+        let mut estimate = crate::cranelift_estimate(mir.as_ptr(), mir.len(), &options);
+        assert_eq!(estimate.success, 1);
+        assert!(estimate.data.is_null() && estimate.data_len == 0);
+        options.function_stats = 1;
+        let mut compiled = crate::cranelift_compile_mir(mir.as_ptr(), mir.len(), &options);
+        assert_eq!(compiled.success, 1);
+        let estimated = sizes(&estimate);
+        assert_eq!(estimated.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), ["one", "two"]);
+        assert_eq!(estimated, sizes(&compiled));
+        crate::cranelift_free_result(&mut estimate);
+        crate::cranelift_free_result(&mut compiled);
+
+        let bad = take(&mut crate::cranelift_estimate(mir.as_ptr(), mir.len() - 4, &options));
+        assert!(!bad.success && bad.text.contains("MIR"), "{}", bad.text);
+    }
+
+    #[test]
+    fn dumps_go_to_the_callback_or_file_in_the_options() {
+        unsafe extern "C" fn collect(context: *mut std::ffi::c_void, kind: i32, text: *const i8, len: usize) {
+            let records = unsafe { &mut *(context as *mut Vec<(i32, String)>) };
+            let bytes = unsafe { std::slice::from_raw_parts(text as *const u8, len + 1) };
+            assert_eq!(bytes[len], 0);
+            records.push((kind, String::from_utf8(bytes[..len].to_vec()).unwrap()));
+        }
+        let m = locals_used_in_sequence(false);
+        let mut records: Vec<(i32, String)> = Vec::new();
+        let mut options = default_options();
+        options.frame_warn_bytes = 8;
+        options.dump = Some(collect);
+        options.dump_context = &mut records as *mut Vec<(i32, String)> as *mut std::ffi::c_void;
+        let outcome = compile(&m, &options);
+        assert!(outcome.success, "{}", outcome.text);
+        // The CFG validation findings, the frame size warning, then the IR
+        let (ir, diagnostics) = records.split_last().unwrap();
+        assert!(diagnostics.iter().all(|(kind, _)| *kind == crate::dump::DUMP_DIAGNOSTIC), "{:?}", records);
+        assert!(diagnostics.iter().any(|(_, text)| text.contains("stack frame of 16 bytes")), "{:?}", records);
+        assert_eq!(ir.0, crate::dump::DUMP_IR);
+        assert!(ir.1.starts_with("; Function: locals\n") && !ir.1.contains("warning"), "{}", ir.1);
+        assert!(records.iter().all(|(_, text)| text.ends_with('\n')));
+
+        let path = std::env::temp_dir().join(format!("tml-dump-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let path_c = std::ffi::CString::new(path.to_str().unwrap()).unwrap();
+        options.dump = None;
+        options.dump_path = path_c.as_ptr();
+        assert!(compile(&m, &options).success);
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text, records.iter().map(|(_, text)| text.as_str()).collect::<String>());
+        let _ = std::fs::remove_file(&path);
+
+        let missing = std::ffi::CString::new(path.join("no/such/dir").to_str().unwrap()).unwrap();
+        options.dump_path = missing.as_ptr();
+        let outcome = compile(&m, &options);
+        assert!(!outcome.success && outcome.text.contains("dump_path"), "{}", outcome.text);
+    }
+
+    #[test]
+    fn panic_errors_carry_a_backtrace_when_asked() {
+        let panicking_call = |panic_backtrace: i32| {
+            let mut options = default_options();
+            options.panic_backtrace = panic_backtrace;
+            let options = &options as *const crate::CraneliftOptions as usize;
+            take(&mut crate::by_value(|result| {
+                crate::write_result(result, move || {
+                    crate::RawOptions::Struct(options as *const crate::CraneliftOptions).resolve().unwrap();
+                    panic!("boom")
+                })
+            }))
+        };
+        let traced = panicking_call(1);
+        assert!(!traced.success && traced.text.starts_with("PANIC: boom\nbacktrace:\n"), "{}", traced.text);
+        assert!(traced.text.contains("panic_errors_carry_a_backtrace_when_asked"), "{}", traced.text);
+        assert_eq!(panicking_call(0).text, "PANIC: boom");
+    }
+
+    #[test]
+    fn narrow_integers_are_extended_in_aarch64_signatures() {
+        let m = module(vec![function(
+            "narrow",
+            vec![(0, prim(PrimitiveType::U8)), (1, prim(PrimitiveType::I16)), (2, prim(PrimitiveType::I32))],
+            prim(PrimitiveType::Bool),
+            vec![block(0, vec![const_int(3, 1, 8)], Terminator::Return { value: Some(v(3)) })],
+        )]);
+        let targets = [(c"aarch64-apple-darwin", "apple_aarch64"), (c"aarch64-unknown-linux-gnu", "system_v")];
+        for (triple, call_conv) in targets {
+            let mut options = default_options();
+            options.target_triple = triple.as_ptr();
+            let ir = generate_ir(&m, &options);
+            let expected = format!("(i8 uext, i16 sext, i32) -> i8 uext {}", call_conv);
+            assert!(ir.success && ir.text.contains(&expected), "{:?}: {}", triple, ir.text);
+            let object = compile(&m, &options);
+            assert!(object.success, "{:?}: {}", triple, object.text);
+        }
+    }
+
+    #[test]
+    fn string_data_follows_the_target_byte_order() {
+        let string = |result, c: Constant| inst(result, Instruction::Constant(c));
+        let m = module(vec![function(
+            "strings",
+            Vec::new(),
+            prim(PrimitiveType::Ptr),
+            vec![block(
+                0,
+                vec![string(0, Constant::String("hello".into())), string(1, Constant::WideString("hi".into()))],
+                Terminator::Return { value: Some(v(1)) },
+            )],
+        )]);
+        let has = |object: &[u8], bytes: &[u8]| object.windows(bytes.len()).any(|w| w == bytes);
+        for (triple, big_endian) in [(c"s390x-unknown-linux-gnu", true), (c"x86_64-unknown-linux-gnu", false)] {
+            let mut options = default_options();
+            options.target_triple = triple.as_ptr();
+            let compiled = compile(&m, &options);
+            assert!(compiled.success, "{:?}: {}", triple, compiled.text);
+            let wide = if big_endian { [0, b'h', 0, b'i', 0, 0] } else { [b'h', 0, b'i', 0, 0, 0] };
+            assert!(has(&compiled.data, b"hello\0"), "{:?}: no string", triple);
+            assert!(has(&compiled.data, &wide), "{:?}: no wide string", triple);
+        }
+    }
+
+    #[test]
+    fn dll_export_fills_the_coff_export_directives() {
+        let body = || vec![block(0, vec![const_int(0, 1, 32)], Terminator::Return { value: Some(v(0)) })];
+        let mut private = function("private", Vec::new(), prim(PrimitiveType::I32), body());
+        private.is_public = false;
+        let m = module(vec![function("exported", Vec::new(), prim(PrimitiveType::I32), body()), private]);
+        let run = |triple: &std::ffi::CStr, dll_export| {
+            let mut options = default_options();
+            options.target_triple = triple.as_ptr();
+            options.dll_export = dll_export;
+            let compiled = compile(&m, &options);
+            assert!(compiled.success, "{:?}: {}", triple, compiled.text);
+            compiled.data
+        };
+        let has = |object: &[u8], text: &[u8]| object.windows(text.len()).any(|w| w == text);
+
+        let msvc = run(c"x86_64-pc-windows-msvc", 1);
+        assert!(has(&msvc, b".drectve") && has(&msvc, b" /EXPORT:\"tml_exported\""));
+        assert!(!has(&msvc, b"EXPORT:\"tml_private"));
+        let mingw = run(c"x86_64-pc-windows-gnu", 1);
+        assert!(has(&mingw, b" -export:\"tml_exported\""));
+        // Only asked for, and only on COFF
+        assert!(!has(&run(c"x86_64-pc-windows-msvc", 0), b".drectve"));
+        assert!(!has(&run(c"x86_64-unknown-linux-gnu", 1), b".drectve"));
+    }
+
+    #[cfg(not(feature = "memory-limit"))]
+    #[test]
+    fn memory_limit_needs_the_counting_allocator() {
+        let ret = block(0, vec![const_int(0, 1, 32)], Terminator::Return { value: Some(v(0)) });
+        let m = module(vec![function("f", Vec::new(), prim(PrimitiveType::I32), vec![ret])]);
+        let mut options = default_options();
+        options.max_memory_bytes = 1 << 30;
+        let out = compile(&m, &options);
+        assert!(!out.success);
+        assert!(out.text.contains("memory-limit feature"), "{}", out.text);
+        assert_eq!(crate::cranelift_features() & crate::FEATURE_MEMORY_LIMIT, 0);
+    }
+
+    #[test]
+    fn narrow_local_in_a_shared_slot_is_stored_at_its_own_width() {
+        // `a` (i64) and then `b` (i32) share one 8-byte slot; `b` must be
+        // stored as the i32 it is loaded as, not widened to the slot size
+        let alloca = |id, name: &str, ty| inst(id, Instruction::Alloca { name: name.into(), alloc_type: prim(ty) });
+        let zero = |id, local, size| {
+            inst(
+                id,
+                Instruction::Call {
+                    func_name: "mem_zero".into(),
+                    args: vec![v(local), v(size)],
+                    return_type: prim(PrimitiveType::Unit),
+                },
+            )
+        };
+        let blocks = vec![
+            block(
+                0,
+                vec![
+                    alloca(1, "a", PrimitiveType::I64),
+                    alloca(2, "b", PrimitiveType::I32),
+                    const_int(8, 8, 64),
+                    const_int(9, 4, 64),
+                ],
+                Terminator::Branch { target: 1 },
+            ),
+            block(1, vec![zero(3, 1, 8)], Terminator::Branch { target: 2 }),
+            block(
+                2,
+                vec![
+                    zero(4, 2, 9),
+                    const_int(5, -2, 32),
+                    inst(6, Instruction::Store { ptr: v(2), value: v(5) }),
+                    inst(7, Instruction::Load { ptr: v(2) }),
+                ],
+                Terminator::Return { value: Some(v(7)) },
+            ),
+        ];
+        let m = module(vec![function("shared", Vec::new(), prim(PrimitiveType::I32), blocks)]);
+        assert_eq!(slot_count(&m, 1), 1);
+
+        let mut options = default_options();
+        options.optimization_level = 1;
+        let ir = generate_ir(&m, &options).text;
+        assert!(ir.contains("explicit_slot 8"), "{}", ir);
+        assert!(!ir.contains("sextend"), "{}", ir);
+        assert!(ir.contains("stack_load.i32 ss0"), "{}", ir);
+    }
+
+    /// CLIF of a one-function module, checked to compile as well.
+    fn checked_ir(params: Vec<(u32, MirType)>, ret: MirType, blocks: Vec<BasicBlock>) -> String {
+        let m = module(vec![function("f", params, ret, blocks)]);
+        let outcome = generate_ir(&m, &default_options());
+        assert!(outcome.success, "{}", outcome.text);
+        assert!(compile(&m, &default_options()).success);
+        outcome.text
+    }
+
+    fn alloca(id: u32, ty: PrimitiveType) -> InstructionData {
+        inst(id, Instruction::Alloca { name: format!("l{}", id), alloc_type: prim(ty) })
+    }
+
+    fn store(id: u32, ptr: u32, value: u32) -> InstructionData {
+        inst(id, Instruction::Store { ptr: v(ptr), value: v(value) })
+    }
+
+    fn load(id: u32, ptr: u32) -> InstructionData {
+        inst(id, Instruction::Load { ptr: v(ptr) })
+    }
+
+    /// Blocks other than the entry that take parameters, i.e. the phis
+    /// mem2reg inserted.
+    fn blocks_with_params(ir: &str) -> usize {
+        ir.lines().filter(|l| l.starts_with("block") && !l.starts_with("block0") && l.contains('(')).count()
+    }
+
+    #[test]
+    fn locals_assigned_on_both_branches_become_a_phi() {
+        let blocks = vec![
+            block(
+                0,
+                vec![alloca(1, PrimitiveType::I32)],
+                Terminator::CondBranch { condition: v(0), true_block: 1, false_block: 2 },
+            ),
+            block(1, vec![const_int(2, 1, 32), store(3, 1, 2)], Terminator::Branch { target: 3 }),
+            block(2, vec![const_int(4, 2, 32), store(5, 1, 4)], Terminator::Branch { target: 3 }),
+            block(3, vec![load(6, 1)], Terminator::Return { value: Some(v(6)) }),
+        ];
+        let ir = checked_ir(vec![(0, prim(PrimitiveType::Bool))], prim(PrimitiveType::I32), blocks);
+        assert!(!ir.contains("explicit_slot") && !ir.contains("stack_"), "{}", ir);
+        assert_eq!(blocks_with_params(&ir), 1, "{}", ir);
+    }
+
+    #[test]
+    fn loop_counters_are_promoted_with_a_phi_at_the_header() {
+        // i = 0; while i < 10 { i = i + 1 }; return i
+        let blocks = vec![
+            block(
+                0,
+                vec![alloca(1, PrimitiveType::I32), const_int(2, 0, 32), store(3, 1, 2)],
+                Terminator::Branch { target: 1 },
+            ),
+            block(
+                1,
+                vec![
+                    load(4, 1),
+                    const_int(5, 10, 32),
+                    inst(6, Instruction::Binary { op: BinOp::Lt, left: v(4), right: v(5) }),
+                ],
+                Terminator::CondBranch { condition: v(6), true_block: 2, false_block: 3 },
+            ),
+            block(
+                2,
+                vec![
+                    load(7, 1),
+                    const_int(8, 1, 32),
+                    inst(9, Instruction::Binary { op: BinOp::Add, left: v(7), right: v(8) }),
+                    store(10, 1, 9),
+                ],
+                Terminator::Branch { target: 1 },
+            ),
+            block(3, vec![load(11, 1)], Terminator::Return { value: Some(v(11)) }),
+        ];
+        let ir = checked_ir(Vec::new(), prim(PrimitiveType::I32), blocks);
+        assert!(!ir.contains("explicit_slot") && !ir.contains("stack_"), "{}", ir);
+        assert!(blocks_with_params(&ir) >= 1, "{}", ir);
+    }
+
+    #[test]
+    fn stores_of_another_type_are_converted_or_keep_the_slot() {
+        // An i32 stored into an i64 local is widened into the variable
+        let widened = vec![block(
+            0,
+            vec![alloca(1, PrimitiveType::I64), const_int(2, -1, 32), store(3, 1, 2), load(4, 1)],
+            Terminator::Return { value: Some(v(4)) },
+        )];
+        let ir = checked_ir(Vec::new(), prim(PrimitiveType::I64), widened);
+        assert!(!ir.contains("explicit_slot") && ir.contains("sextend.i64"), "{}", ir);
+
+        // A float stored into an i64 local has no conversion: it stays in memory
+        let float = inst(2, Instruction::Constant(Constant::Float { value: 1.5, is_f64: true }));
+        let mismatched = vec![block(
+            0,
+            vec![alloca(1, PrimitiveType::I64), float, store(3, 1, 2), load(4, 1)],
+            Terminator::Return { value: Some(v(4)) },
+        )];
+        let ir = checked_ir(Vec::new(), prim(PrimitiveType::I64), mismatched);
+        assert!(ir.contains("explicit_slot 8") && ir.contains("stack_store"), "{}", ir);
+    }
+
+    #[test]
+    fn locals_whose_address_escapes_are_not_promoted() {
+        let zero = |id, local| {
+            inst(
+                id,
+                Instruction::Call {
+                    func_name: "mem_zero".into(),
+                    args: vec![v(local), v(9)],
+                    return_type: prim(PrimitiveType::Unit),
+                },
+            )
+        };
+        // Passed to a call, and stored as a value into another local
+        let blocks = vec![block(
+            0,
+            vec![
+                alloca(1, PrimitiveType::I64),
+                alloca(2, PrimitiveType::I64),
+                alloca(3, PrimitiveType::I64),
+                const_int(9, 8, 64),
+                zero(4, 1),
+                store(5, 3, 2),
+                load(6, 1),
+            ],
+            Terminator::Return { value: Some(v(6)) },
+        )];
+        let ir = checked_ir(Vec::new(), prim(PrimitiveType::I64), blocks);
+        // l1 and l2 keep slots; l3 only holds a pointer and is promoted
+        assert_eq!(ir.matches("explicit_slot").count(), 2, "{}", ir);
+        assert!(ir.contains("stack_load.i64"), "{}", ir);
+    }
+
+    #[test]
+    fn wide_locals_are_stored_at_their_full_width() {
+        // An i128 local keeps its slot (too wide to promote); storing it
+        // must not narrow the value to the i64 of the old size table
+        let blocks = vec![block(
+            0,
+            vec![
+                inst(1, Instruction::Alloca { name: "x".into(), alloc_type: prim(PrimitiveType::I128) }),
+                const_int(2, -3, 64),
+                inst(
+                    3,
+                    Instruction::Cast { kind: CastKind::SExt, operand: v(2), target_type: prim(PrimitiveType::I128) },
+                ),
+                inst(4, Instruction::Store { ptr: v(1), value: v(3) }),
+                inst(5, Instruction::Load { ptr: v(1) }),
+            ],
+            Terminator::Return { value: Some(v(5)) },
+        )];
+        let m = module(vec![function("wide", Vec::new(), prim(PrimitiveType::I128), blocks)]);
+        let outcome = generate_ir(&m, &default_options());
+        assert!(outcome.success, "{}", outcome.text);
+        assert!(outcome.text.contains("explicit_slot 16"), "{}", outcome.text);
+        assert!(!outcome.text.contains("ireduce"), "{}", outcome.text);
+        assert!(compile(&m, &default_options()).success);
+    }
+
+    /// `stack_load`s in the CLIF of an i128 local's function: i128 is too
+    /// wide to promote, so the local keeps its slot and store-to-load
+    /// forwarding decides which loads read it back.
+    fn slot_loads(blocks: Vec<BasicBlock>) -> usize {
+        checked_ir(Vec::new(), prim(PrimitiveType::I128), blocks).matches("stack_load").count()
+    }
+
+    /// An i128 from the i64 constant `%(result + 100)`.
+    fn widen(result: u32) -> InstructionData {
+        let target_type = prim(PrimitiveType::I128);
+        inst(result, Instruction::Cast { kind: CastKind::SExt, operand: v(result + 100), target_type })
+    }
+
+    #[test]
+    fn stored_values_are_forwarded_within_a_block() {
+        let blocks = vec![block(
+            0,
+            vec![
+                alloca(1, PrimitiveType::I128),
+                const_int(102, 5, 64),
+                widen(2),
+                store(3, 1, 2),
+                load(4, 1),
+                load(5, 1),
+                inst(6, Instruction::Binary { op: BinOp::Add, left: v(4), right: v(5) }),
+            ],
+            Terminator::Return { value: Some(v(6)) },
+        )];
+        assert_eq!(slot_loads(blocks), 0);
+    }
+
+    #[test]
+    fn stored_values_are_not_forwarded_across_blocks() {
+        let blocks = vec![
+            block(
+                0,
+                vec![alloca(1, PrimitiveType::I128), const_int(102, 5, 64), widen(2), store(3, 1, 2), load(4, 1)],
+                Terminator::Branch { target: 1 },
+            ),
+            block(1, vec![load(5, 1)], Terminator::Return { value: Some(v(5)) }),
+        ];
+        // The load in the first block is forwarded, the one in the second is not
+        assert_eq!(slot_loads(blocks), 1);
+    }
+
+    #[test]
+    fn escaping_locals_are_reloaded_after_calls_and_stores_through_pointers() {
+        // The address goes to a call, which may write the local
+        let call = inst(
+            4,
+            Instruction::Call {
+                func_name: "mem_zero".into(),
+                args: vec![v(1), v(9)],
+                return_type: prim(PrimitiveType::Unit),
+            },
+        );
+        let blocks = vec![block(
+            0,
+            vec![
+                alloca(1, PrimitiveType::I128),
+                const_int(102, 5, 64),
+                widen(2),
+                const_int(9, 16, 64),
+                store(3, 1, 2),
+                call,
+                load(5, 1),
+            ],
+            Terminator::Return { value: Some(v(5)) },
+        )];
+        assert_eq!(slot_loads(blocks), 1);
+
+        // The address is kept in another local and written through
+        let blocks = vec![block(
+            0,
+            vec![
+                alloca(1, PrimitiveType::I128),
+                alloca(2, PrimitiveType::Ptr),
+                store(3, 2, 1),
+                const_int(104, 5, 64),
+                widen(4),
+                store(5, 1, 4),
+                load(6, 2),
+                const_int(107, 6, 64),
+                widen(7),
+                store(8, 6, 7),
+                load(9, 1),
+            ],
+            Terminator::Return { value: Some(v(9)) },
+        )];
+        assert_eq!(slot_loads(blocks), 1);
+    }
+
+    fn phi(result: u32, incoming: Vec<(u32, u32)>) -> InstructionData {
+        inst(result, Instruction::Phi { incoming: incoming.into_iter().map(|(val, from)| (v(val), from)).collect() })
+    }
+
+    #[test]
+    fn branches_to_unknown_blocks_fail_the_translation() {
+        let blocks = vec![block(0, Vec::new(), Terminator::Branch { target: 7 })];
+        let m = module(vec![function("f", Vec::new(), prim(PrimitiveType::Unit), blocks)]);
+        let outcome = compile(&m, &default_options());
+        assert!(!outcome.success);
+        assert!(outcome.text.contains("branch to unknown block bb7"), "{}", outcome.text);
+    }
+
+    #[test]
+    fn folded_branches_pass_the_taken_edges_phi_arguments() {
+        // bb1 joins the folded edge from bb0 and an edge from the dead bb2
+        let join = |term| {
+            vec![
+                block(0, vec![const_int(1, 1, 8), const_int(2, 10, 32)], term),
+                block(1, vec![phi(3, vec![(2, 0), (4, 2)])], Terminator::Return { value: Some(v(3)) }),
+                block(2, vec![const_int(4, 20, 32)], Terminator::Branch { target: 1 }),
+            ]
+        };
+        let cond = Terminator::CondBranch { condition: v(1), true_block: 1, false_block: 2 };
+        let switch = Terminator::Switch { discriminant: v(1), cases: vec![(0, 2), (1, 1)], default_block: 2 };
+        for term in [cond, switch] {
+            let ir = checked_ir(Vec::new(), prim(PrimitiveType::I32), join(term));
+            assert!(!ir.contains("brif") && !ir.contains("br_table") && !ir.contains("; bb2"), "{}", ir);
+            assert!(ir.contains("iconst.i32 10") && !ir.contains("iconst.i32 20"), "{}", ir);
+        }
+    }
+
+    #[test]
+    fn phis_may_name_unreachable_predecessors() {
+        // bb1 has no predecessors but is still listed by bb2's phi
+        let blocks = vec![
+            block(0, vec![const_int(1, 10, 32)], Terminator::Branch { target: 2 }),
+            block(1, vec![const_int(2, 20, 32)], Terminator::Branch { target: 2 }),
+            block(2, vec![phi(3, vec![(1, 0), (2, 1)])], Terminator::Return { value: Some(v(3)) }),
+        ];
+        let ir = checked_ir(Vec::new(), prim(PrimitiveType::I32), blocks);
+        assert!(!ir.contains("; bb1"), "{}", ir);
+        assert!(ir.contains("iconst.i32 10") && !ir.contains("iconst.i32 20"), "{}", ir);
+    }
+
+    #[test]
+    fn string_literals_with_interior_nuls_are_rejected() {
+        let literal = |c: Constant| {
+            let body = vec![inst(0, Instruction::Constant(c))];
+            let blocks = vec![block(0, body, Terminator::Return { value: Some(v(0)) })];
+            module(vec![function("nul", Vec::new(), prim(PrimitiveType::Ptr), blocks)])
+        };
+        for m in [literal(Constant::String("a\0b".into())), literal(Constant::WideString("a\0b".into()))] {
+            for outcome in [compile(&m, &default_options()), generate_ir(&m, &default_options())] {
+                assert!(!outcome.success);
+                assert!(outcome.text.contains("has a NUL at byte 1"), "{}", outcome.text);
+            }
+        }
+        let plain = compile(&literal(Constant::String("ab".into())), &default_options());
+        assert!(plain.success && plain.data.windows(3).any(|w| w == b"ab\0"), "{}", plain.text);
+    }
+
+    #[test]
+    fn inline_section_round_trips_candidates_without_private_callees() {
+        use cranelift_object::object::{self, Object, ObjectSection};
+        use PrimitiveType::I32;
+
+        let ret = |value| Terminator::Return { value: Some(v(value)) };
+        let call = |name: &str| Instruction::Call { func_name: name.into(), args: Vec::new(), return_type: prim(I32) };
+        let mut helper = function("helper", Vec::new(), prim(I32), vec![block(0, vec![const_int(0, 1, 32)], ret(0))]);
+        helper.is_public = false;
+        let m = module(vec![
+            function("seven", Vec::new(), prim(I32), vec![block(0, vec![const_int(0, 7, 32)], ret(0))]),
+            function("uses_helper", Vec::new(), prim(I32), vec![block(0, vec![inst(0, call("helper"))], ret(0))]),
+            function("uses_seven", Vec::new(), prim(I32), vec![block(0, vec![inst(0, call("seven"))], ret(0))]),
+            helper,
+        ]);
+        let mut options = default_options();
+        options.emit_inline_metadata = 1;
+        let outcome = compile(&m, &options);
+        assert!(outcome.success, "{}", outcome.text);
+
+        let file = object::File::parse(&*outcome.data).unwrap();
+        let section = file.section_by_name(crate::inline_meta::INLINE_SECTION).expect("no .tml.inline section");
+        let inline = crate::mir_reader::MirBinaryReader::new(section.data().unwrap()).read_module().unwrap();
+        let names: Vec<&str> = inline.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["seven", "uses_seven"]);
+        assert_eq!(inline.functions[0], m.functions[0]);
+        assert_eq!(inline.functions[1], m.functions[2]);
+    }
+
+    #[test]
+    fn array_fills_unroll_short_arrays_and_loop_over_long_ones() {
+        let fill = |value: i64, count: u64| {
+            let fill = Instruction::ArrayFill { element_type: prim(PrimitiveType::I32), value: v(1), count };
+            let blocks = vec![block(
+                0,
+                vec![const_int(1, value, 32), inst(2, fill)],
+                Terminator::Return { value: Some(v(2)) },
+            )];
+            checked_ir(Vec::new(), prim(PrimitiveType::Ptr), blocks)
+        };
+        let short = fill(7, 4);
+        assert_eq!(short.matches("store").count(), 4, "{}", short);
+        assert!(!short.contains("brif"), "{}", short);
+        let long = fill(7, 10_000);
+        assert_eq!(long.matches("store").count(), 1, "{}", long);
+        assert!(long.contains("brif") && long.contains("icmp_imm ult"), "{}", long);
+        let zeroed = fill(0, 10_000);
+        assert!(!zeroed.contains("brif") && zeroed.contains("%Memset"), "{}", zeroed);
+
+        let huge = Instruction::ArrayFill { element_type: prim(PrimitiveType::I64), value: v(1), count: 1 << 20 };
+        let blocks = vec![block(0, vec![const_int(1, 1, 64), inst(2, huge)], Terminator::Return { value: Some(v(2)) })];
+        let m = module(vec![function("f", Vec::new(), prim(PrimitiveType::Ptr), blocks)]);
+        let outcome = compile(&m, &default_options());
+        assert!(!outcome.success && outcome.text.contains("too large for the stack"), "{}", outcome.text);
+    }
+
+    #[test]
+    fn phis_take_constants_defined_in_the_merge_block() {
+        // The builders' `a or b`: the left edge carries a `true` that bb2 defines
+        use PrimitiveType::{Bool, I32};
+        let cmp = |result, op| inst(result, Instruction::Binary { op, left: v(0), right: v(1) });
+        let blocks = vec![
+            block(
+                0,
+                vec![const_int(1, 0, 32), cmp(2, BinOp::Eq)],
+                Terminator::CondBranch { condition: v(2), true_block: 2, false_block: 1 },
+            ),
+            block(1, vec![cmp(3, BinOp::Lt)], Terminator::Branch { target: 2 }),
+            block(
+                2,
+                vec![inst(4, Instruction::Constant(Constant::Bool(true))), phi(5, vec![(4, 0), (3, 1)])],
+                Terminator::Return { value: Some(v(5)) },
+            ),
+        ];
+        let ir = checked_ir(vec![(0, prim(I32))], prim(Bool), blocks);
+        assert!(ir.contains("iconst.i8 1") && !ir.contains("iconst.i8 0"), "{}", ir);
+    }
+
+    #[test]
+    fn body_less_functions_are_imported() {
+        use cranelift_object::object::{self, Object, ObjectSymbol};
+        use PrimitiveType::{I32, Unit};
+
+        let mut c_abs = function("c_abs", vec![(0, prim(I32))], prim(I32), Vec::new());
+        let mut print_i32 = function("print_i32", vec![(0, prim(I32))], prim(Unit), Vec::new());
+        c_abs.is_public = false;
+        print_i32.is_public = false;
+        let call = |result, name: &str, arg, ret| {
+            inst(result, Instruction::Call { func_name: name.into(), args: vec![v(arg)], return_type: prim(ret) })
+        };
+        let main = function(
+            "main",
+            Vec::new(),
+            prim(I32),
+            vec![block(
+                0,
+                vec![const_int(0, -3, 32), call(1, "c_abs", 0, I32), call(2, "print_i32", 1, Unit)],
+                Terminator::Return { value: Some(v(1)) },
+            )],
+        );
+        let outcome = compile(&module(vec![c_abs, print_i32, main]), &default_options());
+        assert!(outcome.success, "{}", outcome.text);
+
+        // `@extern` C functions keep their name; runtime ones get the manifest symbol
+        let file = object::File::parse(&*outcome.data).unwrap();
+        let undefined: Vec<&str> =
+            file.symbols().filter(|s| s.is_undefined()).filter_map(|s| s.name().ok()).collect();
+        assert!(undefined.contains(&"c_abs") && undefined.contains(&"tml_rt_print_i32"), "{:?}", undefined);
+        assert!(!undefined.contains(&"tml_c_abs"), "{:?}", undefined);
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn folded_branches_compare_constants_as_the_unfolded_ones_do() {
+        use PrimitiveType::I32;
+        let ret = |id, value| {
+            block(id, vec![const_int(10 + id, value, 32)], Terminator::Return { value: Some(v(10 + id)) })
+        };
+        // An i8 constant stored as 255 against `case -1`, and 256 wrapped to
+        // i8 0 as a condition. The interpreter follows the unfolded lowering.
+        let switch = Terminator::Switch { discriminant: v(0), cases: vec![(-1, 1)], default_block: 2 };
+        let branch = Terminator::CondBranch { condition: v(0), true_block: 1, false_block: 2 };
+        for (value, term, returned) in [(255, switch, 10), (256, branch, 20)] {
+            let i8_const = Instruction::Constant(Constant::Int { value, bit_width: 8, is_signed: true });
+            let blocks = vec![block(0, vec![inst(0, i8_const)], term), ret(1, 10), ret(2, 20)];
+            let func = function("f", Vec::new(), prim(I32), blocks.clone());
+            let unfolded = crate::const_eval::evaluate(&func);
+            assert_eq!(unfolded, Some(Constant::Int { value: returned, bit_width: 32, is_signed: true }));
+            let folded = checked_ir(Vec::new(), prim(I32), blocks);
+            assert!(folded.contains(&format!("return v1  ; v1 = {}", returned)), "{}", folded);
+        }
+    }
+}
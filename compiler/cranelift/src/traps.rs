@@ -0,0 +1,55 @@
+//! Trap Categories
+//!
+//! Groups the trap codes the translator can produce into the categories
+//! WebAssembly distinguishes, so a wasm target (or a host embedding native
+//! code) reports the same failure for the same TML program. With the
+//! `trap_abort` option, explicit traps and division checks call
+//! `TRAP_ABORT_IMPORT(category)` instead of executing a hardware trap; the
+//! runtime turns that into a regular panic, which `should_panic` tests and
+//! hosts without signal handlers can observe. Traps raised inside Cranelift
+//! instructions (float-to-int conversion, stack overflow) stay hardware traps.
+
+use cranelift_codegen::ir::TrapCode;
+
+/// Host function called by `trap_abort` code: `void (i32 category)`.
+/// Never returns; the call is followed by a trap in case it does.
+pub const TRAP_ABORT_IMPORT: &str = "tml_trap_abort";
+
+/// `TRAP_ABORT_IMPORT` with the trap's source location, used when the
+/// `source_file` option is set and the trap follows a located instruction:
+/// `void (i32 category, ptr file, i32 line, i32 column)`.
+pub const TRAP_ABORT_AT_IMPORT: &str = "tml_trap_abort_at";
+
+/// Trap code for MIR `Unreachable` (user codes start at 1).
+pub const UNREACHABLE_TRAP: TrapCode = TrapCode::unwrap_user(1);
+
+/// Wasm trap categories. The discriminants are the ABI passed to
+/// `TRAP_ABORT_IMPORT` and must match `TmlTrapCategory` in the runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum TrapCategory {
+    /// `unreachable` executed (MIR `Unreachable` terminator)
+    Unreachable = 0,
+    IntegerDivideByZero = 1,
+    /// Signed division of MIN by -1, or a float conversion out of range
+    IntegerOverflow = 2,
+    /// Float-to-int conversion of NaN
+    InvalidConversion = 3,
+    /// Memory access outside an allocation
+    OutOfBounds = 4,
+    StackOverflow = 5,
+}
+
+impl TrapCategory {
+    /// Category of a trap code emitted by the translator or by Cranelift.
+    pub fn of(code: TrapCode) -> Self {
+        match code {
+            TrapCode::INTEGER_DIVISION_BY_ZERO => TrapCategory::IntegerDivideByZero,
+            TrapCode::INTEGER_OVERFLOW => TrapCategory::IntegerOverflow,
+            TrapCode::BAD_CONVERSION_TO_INTEGER => TrapCategory::InvalidConversion,
+            TrapCode::HEAP_OUT_OF_BOUNDS => TrapCategory::OutOfBounds,
+            TrapCode::STACK_OVERFLOW => TrapCategory::StackOverflow,
+            _ => TrapCategory::Unreachable,
+        }
+    }
+}
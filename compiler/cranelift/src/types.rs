@@ -1,15 +1,20 @@
-/// Type mapping: MIR types → Cranelift types
-///
-/// Cranelift types are sign-agnostic integers (I8, I16, I32, I64, I128)
-/// and floats (F32, F64). Aggregate types are lowered to memory with
-/// explicit load/store at computed offsets.
+//! Type mapping: MIR types → Cranelift types
+//!
+//! Cranelift types are sign-agnostic integers (I8, I16, I32, I64, I128)
+//! and floats (F32, F64). Aggregate types are lowered to memory with
+//! explicit load/store at computed offsets.
 
 use cranelift_codegen::ir::types;
+use cranelift_codegen::ir::AbiParam;
 use cranelift_codegen::ir::Type as CraneliftType;
 
 use crate::mir_types::{MirType, PrimitiveType};
 
-/// Pointer type for the target (always 64-bit for now).
+/// Pointer type for the target.
+///
+/// Always 64-bit: every backend Cranelift 0.128 builds natively (x86_64,
+/// aarch64, riscv64, s390x) has 64-bit pointers, and there is none for
+/// i686, armv7 or wasm32. `cpu::isa` rejects an ISA with other pointers.
 pub const POINTER_TYPE: CraneliftType = types::I64;
 
 /// Map a MIR primitive type to a Cranelift type.
@@ -44,6 +49,22 @@ pub fn mir_type_to_cranelift(ty: &MirType) -> Option<CraneliftType> {
     }
 }
 
+/// Map a MIR type to a signature parameter or return, None where
+/// `mir_type_to_cranelift` has no type.
+///
+/// Integers narrower than 32 bits are extended to 32 by their signedness
+/// (bool unsigned). AAPCS64 leaves the upper bits undefined, but Apple's
+/// arm64 ABI and the x86-64 compilers C is linked with expect the caller
+/// to extend them, so C code reading a TML `u8` gets the value it was given.
+pub fn abi_param(ty: &MirType) -> Option<AbiParam> {
+    let param = AbiParam::new(mir_type_to_cranelift(ty)?);
+    Some(match ty {
+        MirType::Primitive(PrimitiveType::I8 | PrimitiveType::I16) => param.sext(),
+        MirType::Primitive(PrimitiveType::Bool | PrimitiveType::U8 | PrimitiveType::U16) => param.uext(),
+        _ => param,
+    })
+}
+
 /// Compute the size in bytes of a MIR type.
 pub fn type_size(ty: &MirType) -> u32 {
     match ty {
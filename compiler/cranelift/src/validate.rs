@@ -0,0 +1,363 @@
+//! MIR CFG Validation
+//!
+//! Checks a function's control-flow graph before translation. Structural
+//! problems that would otherwise surface as Cranelift verifier failures or
+//! panics (branches to missing blocks, reachable blocks without a terminator)
+//! are errors. Stale bookkeeping — `predecessors` lists that disagree with the
+//! actual edges, phis naming non-predecessor blocks — only produces warnings,
+//! since the translator derives edges from terminators anyway.
+//!
+//! `check_references` adds the SSA checks `cranelift_validate_mir` runs for
+//! MIR writers: operands that are undefined, defined twice, or not
+//! available where they are used. The translator does not run them, as it
+//! substitutes a zero for a missing value.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+use crate::json;
+use crate::mir_types::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub function: String,
+    pub block: Option<u32>,
+    /// Position of the offending instruction within `block`
+    pub instruction: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: in function '{}'", severity, self.function)?;
+        if let Some(block) = self.block {
+            write!(f, ", bb{}", block)?;
+        }
+        if let Some(index) = self.instruction {
+            write!(f, ", instruction {}", index)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl Diagnostic {
+    /// The finding as JSON, located by a `span` of function, block and
+    /// instruction index (`null` where the finding covers the whole unit).
+    pub fn to_json(&self) -> String {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        let or_null = |v: Option<String>| v.unwrap_or_else(|| "null".to_string());
+        format!(
+            "{{\"severity\":\"{}\",\"message\":{},\"span\":{{\"function\":{},\"block\":{},\"instruction\":{}}}}}",
+            severity,
+            json::string(&self.message),
+            json::string(&self.function),
+            or_null(self.block.map(|b| b.to_string())),
+            or_null(self.instruction.map(|i| i.to_string()))
+        )
+    }
+}
+
+/// All successor block ids named by `term`, without constant folding.
+pub fn successors(term: &Terminator) -> Vec<u32> {
+    match term {
+        Terminator::Branch { target } => vec![*target],
+        Terminator::CondBranch {
+            true_block,
+            false_block,
+            ..
+        } => vec![*true_block, *false_block],
+        Terminator::Switch {
+            cases,
+            default_block,
+            ..
+        } => cases
+            .iter()
+            .map(|(_, b)| *b)
+            .chain(std::iter::once(*default_block))
+            .collect(),
+        Terminator::Return { .. } | Terminator::Unreachable => Vec::new(),
+    }
+}
+
+/// Validate the CFG of `func`. Functions without a body are trivially valid.
+pub fn check_function(func: &Function) -> Vec<Diagnostic> {
+    let mut diags = Vec::new();
+    let diag = |severity, block: Option<u32>, message: String| Diagnostic {
+        severity,
+        function: func.name.clone(),
+        block,
+        instruction: None,
+        message,
+    };
+
+    let mut by_id: HashMap<u32, &BasicBlock> = HashMap::new();
+    for block in &func.blocks {
+        if by_id.insert(block.id, block).is_some() {
+            diags.push(diag(Severity::Error, Some(block.id), "duplicate block id".into()));
+        }
+    }
+
+    // Actual predecessors, derived from the terminators
+    let mut preds: HashMap<u32, BTreeSet<u32>> = HashMap::new();
+    for block in &func.blocks {
+        for succ in block.terminator.iter().flat_map(successors) {
+            if by_id.contains_key(&succ) {
+                preds.entry(succ).or_default().insert(block.id);
+            } else {
+                diags.push(diag(
+                    Severity::Error,
+                    Some(block.id),
+                    format!("branch to unknown block bb{}", succ),
+                ));
+            }
+        }
+    }
+
+    // Reachability from the entry block
+    let mut reachable = HashSet::new();
+    let mut worklist: Vec<u32> = func.blocks.first().map(|b| b.id).into_iter().collect();
+    while let Some(id) = worklist.pop() {
+        if !reachable.insert(id) {
+            continue;
+        }
+        if let Some(term) = by_id.get(&id).and_then(|b| b.terminator.as_ref()) {
+            worklist.extend(successors(term).into_iter().filter(|s| by_id.contains_key(s)));
+        }
+    }
+
+    for block in func.blocks.iter().filter(|b| reachable.contains(&b.id)) {
+        if block.terminator.is_none() {
+            diags.push(diag(
+                Severity::Error,
+                Some(block.id),
+                "reachable block has no terminator".into(),
+            ));
+        }
+
+        let actual = preds.get(&block.id).cloned().unwrap_or_default();
+        let declared: BTreeSet<u32> = block.predecessors.iter().copied().collect();
+        if declared != actual {
+            diags.push(diag(
+                Severity::Warning,
+                Some(block.id),
+                format!(
+                    "predecessor list {:?} does not match CFG edges {:?}",
+                    declared, actual
+                ),
+            ));
+        }
+
+        for (index, inst) in block.instructions.iter().enumerate() {
+            if let Instruction::Phi { incoming } = &inst.inst {
+                for (_, from) in incoming.iter().filter(|(_, from)| !actual.contains(from)) {
+                    diags.push(Diagnostic {
+                        instruction: Some(index),
+                        ..diag(
+                            Severity::Warning,
+                            Some(block.id),
+                            format!(
+                                "phi %{} has an incoming value from bb{}, which is not a predecessor",
+                                inst.result, from
+                            ),
+                        )
+                    });
+                }
+            }
+        }
+    }
+
+    diags
+}
+
+/// Check that every operand is defined before it is used: a parameter, or
+/// the result of an earlier instruction of the same block or of one in a
+/// dominating block. A phi operand is used at the end of its incoming
+/// block. Unreachable blocks are not checked, and `u32::MAX` (the "no
+/// value" sentinel) is accepted anywhere.
+pub fn check_references(func: &Function) -> Vec<Diagnostic> {
+    let mut diags = Vec::new();
+    let diag = |block: u32, instruction: Option<usize>, message: String| Diagnostic {
+        severity: Severity::Error,
+        function: func.name.clone(),
+        block: Some(block),
+        instruction,
+        message,
+    };
+
+    let params: HashSet<ValueId> = func.params.iter().map(|p| p.value_id).collect();
+    // Defining block and position of each instruction result
+    let mut defs: HashMap<ValueId, (u32, usize)> = HashMap::new();
+    for block in &func.blocks {
+        // Void instructions (stores) have the `u32::MAX` result
+        for (index, inst) in block.instructions.iter().enumerate().filter(|(_, i)| i.result != u32::MAX) {
+            if params.contains(&inst.result) || defs.insert(inst.result, (block.id, index)).is_some() {
+                diags.push(diag(block.id, Some(index), format!("%{} is defined more than once", inst.result)));
+            }
+        }
+    }
+
+    let idom = immediate_dominators(func);
+    let dominates = |a: u32, mut b: u32| loop {
+        if a == b {
+            return true;
+        }
+        match idom.get(&b) {
+            Some(&up) if up != b => b = up,
+            _ => return false,
+        }
+    };
+    // Whether `value` is available at position `index` of `block`
+    let available = |value: ValueId, block: u32, index: usize| -> Result<(), String> {
+        if value == u32::MAX || params.contains(&value) {
+            return Ok(());
+        }
+        match defs.get(&value) {
+            None => Err(format!("%{} is not defined", value)),
+            Some(&(def_block, def_index)) if def_block == block && def_index >= index => {
+                Err(format!("%{} is used before its definition", value))
+            }
+            Some(&(def_block, _)) if !dominates(def_block, block) => {
+                Err(format!("%{} is defined in bb{}, which does not dominate this use", value, def_block))
+            }
+            Some(_) => Ok(()),
+        }
+    };
+
+    // Each problem once per instruction, even for a repeated operand
+    let note = |problems: &mut Vec<String>, found: Result<(), String>| {
+        if let Err(p) = found
+            && !problems.contains(&p)
+        {
+            problems.push(p);
+        }
+    };
+    for block in func.blocks.iter().filter(|b| idom.contains_key(&b.id)) {
+        for (index, inst) in block.instructions.iter().enumerate() {
+            let mut problems = Vec::new();
+            if let Instruction::Phi { incoming } = &inst.inst {
+                for (value, from) in incoming.iter().filter(|(_, from)| idom.contains_key(from)) {
+                    let end = func.blocks.iter().find(|b| b.id == *from).map_or(0, |b| b.instructions.len());
+                    note(&mut problems, available(value.id, *from, end));
+                }
+            } else {
+                inst.inst.for_each_operand(|value| note(&mut problems, available(value.id, block.id, index)));
+            }
+            diags.extend(problems.into_iter().map(|p| diag(block.id, Some(index), p)));
+        }
+        if let Some(term) = &block.terminator {
+            let end = block.instructions.len();
+            let mut problems = Vec::new();
+            term.for_each_operand(|value| note(&mut problems, available(value.id, block.id, end)));
+            diags.extend(problems.into_iter().map(|p| diag(block.id, None, format!("terminator: {}", p))));
+        }
+    }
+    diags
+}
+
+/// Immediate dominator of every block reachable from the entry, the entry
+/// mapping to itself (Cooper, Harvey and Kennedy's iterative algorithm).
+pub(crate) fn immediate_dominators(func: &Function) -> HashMap<u32, u32> {
+    let by_id: HashMap<u32, &BasicBlock> = func.blocks.iter().map(|b| (b.id, b)).collect();
+    let succs = |id: u32| -> Vec<u32> {
+        by_id[&id].terminator.iter().flat_map(successors).filter(|s| by_id.contains_key(s)).collect()
+    };
+    let Some(entry) = func.blocks.first().map(|b| b.id) else {
+        return HashMap::new();
+    };
+
+    // Reverse postorder by an iterative depth-first search
+    let mut postorder = Vec::new();
+    let mut visited = HashSet::from([entry]);
+    let mut stack = vec![(entry, succs(entry))];
+    while let Some((id, pending)) = stack.last_mut() {
+        match pending.pop() {
+            Some(next) if visited.insert(next) => {
+                let next_succs = succs(next);
+                stack.push((next, next_succs));
+            }
+            Some(_) => {}
+            None => {
+                postorder.push(*id);
+                stack.pop();
+            }
+        }
+    }
+    let order: HashMap<u32, usize> = postorder.iter().enumerate().map(|(i, &b)| (b, i)).collect();
+    let mut preds: HashMap<u32, Vec<u32>> = HashMap::new();
+    for &id in &postorder {
+        for succ in succs(id) {
+            preds.entry(succ).or_default().push(id);
+        }
+    }
+
+    let mut idom = HashMap::from([(entry, entry)]);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &id in postorder.iter().rev().filter(|&&b| b != entry) {
+            let mut new_idom: Option<u32> = None;
+            for &pred in preds.get(&id).into_iter().flatten().filter(|p| idom.contains_key(p)) {
+                new_idom = Some(match new_idom {
+                    None => pred,
+                    Some(mut a) => {
+                        // Walk both up to their common dominator
+                        let mut b = pred;
+                        while a != b {
+                            while order[&a] < order[&b] {
+                                a = idom[&a];
+                            }
+                            while order[&b] < order[&a] {
+                                b = idom[&b];
+                            }
+                        }
+                        a
+                    }
+                });
+            }
+            if let Some(new_idom) = new_idom
+                && idom.insert(id, new_idom) != Some(new_idom)
+            {
+                changed = true;
+            }
+        }
+    }
+    idom
+}
+
+/// CFG and reference findings for every function of `module`.
+pub fn check_module(module: &Module) -> Vec<Diagnostic> {
+    module
+        .functions
+        .iter()
+        .flat_map(|f| check_function(f).into_iter().chain(check_references(f)))
+        .collect()
+}
+
+/// The findings of `check_module` as the JSON document
+/// `cranelift_validate_mir` returns: counts by severity and each finding.
+pub fn report_json(module: &Module, diags: &[Diagnostic]) -> String {
+    let count = |severity| diags.iter().filter(|d| d.severity == severity).count();
+    let findings: Vec<String> = diags.iter().map(Diagnostic::to_json).collect();
+    format!(
+        "{{\"module\":{},\"functions\":{},\"errors\":{},\"warnings\":{},\"findings\":[{}]}}",
+        json::string(&module.name),
+        module.functions.len(),
+        count(Severity::Error),
+        count(Severity::Warning),
+        findings.join(",")
+    )
+}
@@ -0,0 +1,301 @@
+//! End-to-end test: benchmark programs compiled through the bridge
+//!
+//! Each `tests/fixtures/programs/<name>.mir` is a TML program in the MIR
+//! binary format the C++ compiler hands to the bridge, next to
+//! `<name>.expected`, the stdout the program must print. The test compiles
+//! every fixture with `cranelift_compile_mir` at O0 and O2 and with
+//! `cranelift_compile_mir_parallel` at O2, links the object
+//! against `tests/fixtures/runtime_stub.c` with the system C compiler, runs
+//! the binary and compares its output with the expected file. Every fixture
+//! is also compiled, but not run, for arm64 Linux and macOS and for s390x. See
+//! `tests/fixtures/README.md` for regenerating fixtures.
+//!
+//! Needs the `e2e` feature and a C compiler (`CC`, default `cc`):
+//!
+//! ```text
+//! cargo test --features e2e --test benchmark_programs
+//! ```
+//!
+//! `TML_E2E_FILTER=<substring>` runs only matching fixtures;
+//! `TML_E2E_KEEP=1` keeps the build directory for inspection.
+
+#![cfg(unix)]
+
+use std::ffi::CStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use tml_cranelift_bridge::{
+    CraneliftOptions, cranelift_compile_mir, cranelift_compile_mir_parallel, cranelift_free_result,
+};
+
+/// Stands for any text within one line of an expected file (timings,
+/// addresses).
+const WILDCARD: &str = "{...}";
+/// Optimization level and parallel worker count (None = the serial entry
+/// point, 0 = one per CPU) of each compile of every fixture.
+const BUILDS: [(i32, Option<usize>); 3] = [(0, None), (2, None), (2, Some(0))];
+/// Other targets every fixture is also compiled for, with the architecture
+/// of their objects. The objects are not linked or run (that needs their
+/// toolchains), but the compiles put the whole corpus through the Cranelift
+/// verifier, the AAPCS64 lowering and big-endian data emission.
+const CROSS_TARGETS: [(&CStr, &str); 3] = [
+    (c"aarch64-unknown-linux-gnu", "aarch64"),
+    (c"aarch64-apple-darwin", "aarch64"),
+    (c"s390x-unknown-linux-gnu", "s390x"),
+];
+/// Longest a fixture binary may run.
+const RUN_TIMEOUT: Duration = Duration::from_secs(120);
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+struct Fixture {
+    name: String,
+    mir: PathBuf,
+    expected: PathBuf,
+}
+
+fn fixtures() -> Vec<Fixture> {
+    let dir = fixtures_dir().join("programs");
+    let filter = std::env::var("TML_E2E_FILTER").unwrap_or_default();
+    let mut found: Vec<Fixture> = std::fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", dir.display(), e))
+        .filter_map(|entry| {
+            let mir = entry.ok()?.path();
+            if mir.extension()? != "mir" {
+                return None;
+            }
+            let name = mir.file_stem()?.to_str()?.to_string();
+            Some(Fixture { expected: mir.with_extension("expected"), name, mir })
+        })
+        .filter(|f| f.name.contains(&filter))
+        .collect();
+    found.sort_by(|a, b| a.name.cmp(&b.name));
+    found
+}
+
+/// Compile MIR to an object for `triple` (None = the host) through the C API.
+fn compile(mir: &[u8], opt_level: i32, threads: Option<usize>, triple: Option<&CStr>) -> Result<Vec<u8>, String> {
+    // All-zero is the C++ side's default: host target, no extras
+    let mut options: CraneliftOptions = unsafe { std::mem::zeroed() };
+    options.struct_size = std::mem::size_of::<CraneliftOptions>();
+    options.optimization_level = opt_level;
+    if let Some(triple) = triple {
+        options.target_triple = triple.as_ptr();
+    }
+    let mut result = match threads {
+        None => cranelift_compile_mir(mir.as_ptr(), mir.len(), &options),
+        Some(threads) => cranelift_compile_mir_parallel(mir.as_ptr(), mir.len(), &options, threads),
+    };
+    let outcome = if result.success != 0 {
+        Ok(unsafe { std::slice::from_raw_parts(result.data, result.data_len) }.to_vec())
+    } else {
+        let msg = unsafe { std::slice::from_raw_parts(result.error_msg as *const u8, result.error_msg_len) };
+        Err(String::from_utf8_lossy(msg).into_owned())
+    };
+    cranelift_free_result(&mut result);
+    outcome
+}
+
+/// Run `cmd` to completion, failing with its stderr.
+fn run_tool(cmd: &mut Command) -> Result<(), String> {
+    let output = cmd.output().map_err(|e| format!("cannot run {:?}: {}", cmd.get_program(), e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(format!("{:?} failed ({}):\n{}", cmd, output.status, String::from_utf8_lossy(&output.stderr)))
+}
+
+/// Run `exe` with a timeout and return its stdout. Output goes to files
+/// rather than pipes, which a chatty program would fill while the test
+/// polls for exit.
+fn run_program(exe: &Path) -> Result<String, String> {
+    let (out_path, err_path) = (exe.with_extension("stdout"), exe.with_extension("stderr"));
+    let file = |path: &Path| std::fs::File::create(path).map_err(|e| format!("cannot create {}: {}", path.display(), e));
+    let mut child = Command::new(exe)
+        .stdout(file(&out_path)?)
+        .stderr(file(&err_path)?)
+        .spawn()
+        .map_err(|e| format!("cannot run {}: {}", exe.display(), e))?;
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            break status;
+        }
+        if start.elapsed() > RUN_TIMEOUT {
+            let _ = child.kill();
+            return Err(format!("timed out after {:?}", RUN_TIMEOUT));
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    let read = |path: &Path| String::from_utf8_lossy(&std::fs::read(path).unwrap_or_default()).into_owned();
+    let stdout = read(&out_path);
+    if !status.success() {
+        return Err(format!("exited with {}\n--- stdout\n{}--- stderr\n{}", status, stdout, read(&err_path)));
+    }
+    Ok(stdout)
+}
+
+/// Whether `actual` matches `expected`, where WILDCARD matches any text.
+fn line_matches(expected: &str, actual: &str) -> bool {
+    let mut parts = expected.split(WILDCARD);
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = actual.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// The first line where `actual` differs from `expected`, if any. Trailing
+/// whitespace and line endings are ignored.
+fn first_difference(expected: &str, actual: &str) -> Option<String> {
+    let expected: Vec<&str> = expected.lines().map(str::trim_end).collect();
+    let actual: Vec<&str> = actual.lines().map(str::trim_end).collect();
+    for i in 0..expected.len().max(actual.len()) {
+        let (e, a) = (expected.get(i).copied(), actual.get(i).copied());
+        let same = match (e, a) {
+            (Some(e), Some(a)) => line_matches(e, a),
+            _ => false,
+        };
+        if !same {
+            return Some(format!("line {}:\n  expected: {:?}\n  actual:   {:?}", i + 1, e, a));
+        }
+    }
+    None
+}
+
+/// Name of a build in `BUILDS`.
+fn build_name(opt_level: i32, threads: Option<usize>) -> String {
+    match threads {
+        None => format!("O{}", opt_level),
+        Some(_) => format!("O{}-parallel", opt_level),
+    }
+}
+
+/// Compile, link and run one fixture in one build.
+fn check(
+    fixture: &Fixture,
+    opt_level: i32,
+    threads: Option<usize>,
+    work: &Path,
+    cc: &str,
+    stub: &Path,
+) -> Result<(), String> {
+    let mir = std::fs::read(&fixture.mir).map_err(|e| format!("cannot read {}: {}", fixture.mir.display(), e))?;
+    let expected = std::fs::read_to_string(&fixture.expected)
+        .map_err(|e| format!("cannot read {}: {}", fixture.expected.display(), e))?;
+    let object = compile(&mir, opt_level, threads, None).map_err(|e| format!("compile failed: {}", e))?;
+
+    let base = work.join(format!("{}-{}", fixture.name, build_name(opt_level, threads)));
+    let obj_path = base.with_extension("o");
+    std::fs::write(&obj_path, object).map_err(|e| e.to_string())?;
+    run_tool(Command::new(cc).arg("-o").arg(&base).arg(&obj_path).arg(stub).arg("-lm"))?;
+
+    let actual = run_program(&base)?;
+    match first_difference(&expected, &actual) {
+        Some(diff) => Err(format!("output differs at {}\n--- full output\n{}", diff, actual)),
+        None => Ok(()),
+    }
+}
+
+#[test]
+fn benchmark_programs_match_expected_output() {
+    let fixtures = fixtures();
+    assert!(!fixtures.is_empty(), "no fixtures in {}/programs", fixtures_dir().display());
+
+    let work = std::env::temp_dir().join(format!("tml-cranelift-e2e-{}", std::process::id()));
+    std::fs::create_dir_all(&work).unwrap();
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let stub = work.join("runtime_stub.o");
+    run_tool(
+        Command::new(&cc)
+            .args(["-std=c11", "-O1", "-c", "-o"])
+            .arg(&stub)
+            .arg(fixtures_dir().join("runtime_stub.c")),
+    )
+    .unwrap_or_else(|e| panic!("cannot build the runtime stub: {}", e));
+
+    let mut failures = Vec::new();
+    for fixture in &fixtures {
+        for (opt_level, threads) in BUILDS {
+            let build = build_name(opt_level, threads);
+            let result = check(fixture, opt_level, threads, &work, &cc, &stub);
+            println!("{} {}: {}", fixture.name, build, if result.is_ok() { "ok" } else { "FAILED" });
+            if let Err(e) = result {
+                failures.push(format!("{} at {}: {}", fixture.name, build, e));
+            }
+        }
+    }
+
+    if std::env::var("TML_E2E_KEEP").is_ok_and(|v| v == "1") {
+        println!("build directory kept: {}", work.display());
+    } else {
+        let _ = std::fs::remove_dir_all(&work);
+    }
+    assert!(failures.is_empty(), "{} of {} runs failed:\n\n{}", failures.len(), fixtures.len() * BUILDS.len(), failures.join("\n\n"));
+}
+
+/// The architecture of an ELF or Mach-O `object` from `CROSS_TARGETS`: the
+/// ELF machine is at offset 18 in the object's byte order, the Mach-O CPU
+/// type follows the magic.
+fn object_arch(object: &[u8]) -> Option<&'static str> {
+    if object.starts_with(b"\x7fELF") {
+        return match object.get(18..20)? {
+            [0xB7, 0x00] => Some("aarch64"),
+            [0x00, 0x16] => Some("s390x"),
+            _ => None,
+        };
+    }
+    match object.get(..8)? {
+        [0xCF, 0xFA, 0xED, 0xFE, 0x0C, 0x00, 0x00, 0x01] => Some("aarch64"),
+        _ => None,
+    }
+}
+
+#[test]
+fn benchmark_programs_compile_for_other_targets() {
+    let fixtures = fixtures();
+    assert!(!fixtures.is_empty(), "no fixtures in {}/programs", fixtures_dir().display());
+
+    let mut failures = Vec::new();
+    for fixture in &fixtures {
+        let mir = std::fs::read(&fixture.mir)
+            .unwrap_or_else(|e| panic!("cannot read {}: {}", fixture.mir.display(), e));
+        for (triple, arch) in CROSS_TARGETS {
+            for (opt_level, threads) in BUILDS {
+                let run = format!("{} {:?} {}", fixture.name, triple, build_name(opt_level, threads));
+                match compile(&mir, opt_level, threads, Some(triple)) {
+                    Ok(object) if object_arch(&object) == Some(arch) => {}
+                    Ok(object) => failures.push(format!("{}: not an {} object ({:02x?})", run, arch, &object[..8])),
+                    Err(e) => failures.push(format!("{}: {}", run, e)),
+                }
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{} compiles failed:\n\n{}", failures.len(), failures.join("\n\n"));
+}
+
+#[test]
+fn wildcards_match_within_a_line() {
+    assert!(line_matches("fib(20) = 6765", "fib(20) = 6765"));
+    assert!(!line_matches("fib(20) = 6765", "fib(20) = 6766"));
+    assert!(line_matches("now: {...} ns", "now: 123456 ns"));
+    assert!(line_matches("{...}", ""));
+    assert!(line_matches("a{...}b{...}c", "a1b2c"));
+    assert!(!line_matches("ab{...}ba", "aba"));
+    assert!(first_difference("a\nb {...}\n", "a\r\nb 12  \n").is_none());
+    assert!(first_difference("a\n", "a\nextra\n").is_some());
+}
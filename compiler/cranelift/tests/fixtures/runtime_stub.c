@@ -0,0 +1,221 @@
+// Runtime Stub for the end-to-end tests
+//
+// The TML_RT_SHIM functions of compiler/runtime/core/runtime_signatures.def
+// that benchmark programs call, under both their bare and tml_rt_ names,
+// plus the C entry point. Output matches essential.c; the panic-catching,
+// backtrace and logging machinery of the real runtime is left out, so a
+// panic always prints and exits.
+
+// nanosleep
+#define _POSIX_C_SOURCE 200809L
+
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+
+// ============================================================================
+// I/O
+// ============================================================================
+
+void print(const char* message) {
+    if (message)
+        printf("%s", message);
+}
+
+void println(const char* message) {
+    if (message)
+        printf("%s\n", message);
+    else
+        printf("\n");
+}
+
+void panic(const char* message) {
+    fflush(stdout);
+    fprintf(stderr, "panic: %s\n", message ? message : "(null)");
+    exit(1);
+}
+
+void assert_tml_loc(int32_t condition, const char* message, const char* file, int32_t line) {
+    if (condition)
+        return;
+    fflush(stdout);
+    fprintf(stderr, "assertion failed: %s at %s:%d\n", message ? message : "", file ? file : "?", line);
+    exit(1);
+}
+
+void print_i32(int32_t n) {
+    printf("%d", n);
+}
+
+void print_i64(int64_t n) {
+    printf("%lld", (long long)n);
+}
+
+void print_f64(double n) {
+    printf("%g", n);
+}
+
+void print_bool(int32_t b) {
+    printf("%s", b ? "true" : "false");
+}
+
+// ============================================================================
+// Time
+// ============================================================================
+
+int64_t time_ns(void) {
+    struct timespec ts;
+    timespec_get(&ts, TIME_UTC);
+    return (int64_t)ts.tv_sec * 1000000000 + ts.tv_nsec;
+}
+
+void sleep_ms(int32_t ms) {
+    struct timespec ts;
+    ts.tv_sec = ms / 1000;
+    ts.tv_nsec = (long)(ms % 1000) * 1000000;
+    nanosleep(&ts, NULL);
+}
+
+// ============================================================================
+// Memory
+// ============================================================================
+
+void* mem_alloc(int64_t size) {
+    return malloc((size_t)size);
+}
+
+void* mem_alloc_zeroed(int64_t size) {
+    return calloc(1, (size_t)size);
+}
+
+void* mem_realloc(void* ptr, int64_t new_size) {
+    return realloc(ptr, (size_t)new_size);
+}
+
+void mem_free(void* ptr) {
+    free(ptr);
+}
+
+void mem_copy(void* dest, const void* src, int64_t size) {
+    memcpy(dest, src, (size_t)size);
+}
+
+void mem_move(void* dest, const void* src, int64_t size) {
+    memmove(dest, src, (size_t)size);
+}
+
+void mem_set(void* ptr, int32_t value, int64_t size) {
+    memset(ptr, value, (size_t)size);
+}
+
+void mem_zero(void* ptr, int64_t size) {
+    memset(ptr, 0, (size_t)size);
+}
+
+int32_t mem_compare(const void* a, const void* b, int64_t size) {
+    return memcmp(a, b, (size_t)size);
+}
+
+int32_t mem_eq(const void* a, const void* b, int64_t size) {
+    return memcmp(a, b, (size_t)size) == 0;
+}
+
+// ============================================================================
+// Prefixed Runtime Aliases
+// ============================================================================
+// For code compiled with runtime prefixing, as in essential.c.
+
+void tml_rt_print(const char* message) {
+    print(message);
+}
+
+void tml_rt_println(const char* message) {
+    println(message);
+}
+
+void tml_rt_panic(const char* message) {
+    panic(message);
+}
+
+void tml_rt_assert_tml_loc(int32_t condition, const char* message, const char* file, int32_t line) {
+    assert_tml_loc(condition, message, file, line);
+}
+
+void tml_rt_print_i32(int32_t n) {
+    print_i32(n);
+}
+
+void tml_rt_print_i64(int64_t n) {
+    print_i64(n);
+}
+
+void tml_rt_print_f64(double n) {
+    print_f64(n);
+}
+
+void tml_rt_print_bool(int32_t b) {
+    print_bool(b);
+}
+
+int64_t tml_rt_time_ns(void) {
+    return time_ns();
+}
+
+void tml_rt_sleep_ms(int32_t ms) {
+    sleep_ms(ms);
+}
+
+void* tml_rt_mem_alloc(int64_t size) {
+    return mem_alloc(size);
+}
+
+void* tml_rt_mem_alloc_zeroed(int64_t size) {
+    return mem_alloc_zeroed(size);
+}
+
+void* tml_rt_mem_realloc(void* ptr, int64_t new_size) {
+    return mem_realloc(ptr, new_size);
+}
+
+void tml_rt_mem_free(void* ptr) {
+    mem_free(ptr);
+}
+
+void tml_rt_mem_copy(void* dest, const void* src, int64_t size) {
+    mem_copy(dest, src, size);
+}
+
+void tml_rt_mem_move(void* dest, const void* src, int64_t size) {
+    mem_move(dest, src, size);
+}
+
+void tml_rt_mem_set(void* ptr, int32_t value, int64_t size) {
+    mem_set(ptr, value, size);
+}
+
+void tml_rt_mem_zero(void* ptr, int64_t size) {
+    mem_zero(ptr, size);
+}
+
+int32_t tml_rt_mem_compare(const void* a, const void* b, int64_t size) {
+    return mem_compare(a, b, size);
+}
+
+int32_t tml_rt_mem_eq(const void* a, const void* b, int64_t size) {
+    return mem_eq(a, b, size);
+}
+
+// ============================================================================
+// Entry Point
+// ============================================================================
+
+// The user's `main` is compiled as tml_main; a return value, if it has one,
+// is ignored
+extern void tml_main(void);
+
+int main(void) {
+    tml_main();
+    return 0;
+}
@@ -13,42 +13,511 @@
 extern "C" {
 #endif
 
+// Consumes the next len bytes of a streamed object (CraneliftOptions::write).
+// Return 0, or anything else to fail the compile with CRANELIFT_ERROR_CODEGEN.
+typedef int (*CraneliftWriteFn)(void* context, const uint8_t* data, size_t len);
+
+// Receives one debug dump record (CraneliftOptions::dump): len bytes of UTF-8 text ending
+// in a newline, followed by a NUL the length does not count. kind is a CRANELIFT_DUMP_*
+// value. Called on the thread that made the bridge call, in translation order.
+typedef void (*CraneliftDumpFn)(void* context, int kind, const char* text, size_t len);
+
+#define CRANELIFT_DUMP_IR 1         // CLIF of a function a compile translates (ir_filter applies)
+#define CRANELIFT_DUMP_DIAGNOSTIC 2 // CFG validation finding or frame size warning
+#define CRANELIFT_DUMP_VERIFIER 3   // function rejected by the Cranelift verifier, annotated
+
+// Compile time and code size of one function (CraneliftOptions::function_stats).
+// A multi-versioned function's variants and dispatch stub count toward its record.
+typedef struct CraneliftFunctionStats {
+    const char* name;      // MIR function name, NUL-terminated (owned by the result)
+    uint64_t translate_us; // MIR to Cranelift IR, including validation
+    uint64_t codegen_us;   // Cranelift compile and object definition (worker time when parallel)
+    uint64_t code_bytes;   // machine code size
+} CraneliftFunctionStats;
+
 // Result of a Cranelift compilation operation.
+// String fields (ir_text, error_msg) are valid UTF-8 of *_len bytes, always followed
+// by a NUL that the length does not count. ir_text may contain interior NULs (quoted
+// string data), so read it by length; error_msg never does. Invalid UTF-8 in string
+// options is replaced with U+FFFD.
 typedef struct CraneliftResult {
     int success;           // 0 = failure, 1 = success
     const uint8_t* data;   // Object file bytes (owned by bridge)
     size_t data_len;       // Length of object data
-    const char* ir_text;   // Cranelift IR text (for generate_ir, null otherwise)
-    size_t ir_text_len;    // Length of IR text
+    const char* ir_text;   // IR text (generate_ir, compiles with emit_ir), listing (disassemble)
+    size_t ir_text_len;    // Length of IR text, excluding the NUL
     const char* error_msg; // Error message (null if success)
+    size_t error_msg_len;  // Length of error message, excluding the NUL
+    uint32_t magic;        // CRANELIFT_RESULT_LIVE until freed, then CRANELIFT_RESULT_FREED
+    int cancelled;         // 1 = failed because the options' cancel token was set
+    int error_code;        // CRANELIFT_ERROR_* (CRANELIFT_ERROR_NONE on success)
+    const CraneliftFunctionStats* function_stats; // per defined function, in definition
+                                                  // order, with function_stats set; else NULL
+    size_t num_function_stats;
+    uint64_t generation; // unique per result; buffers are freed only by their own result
 } CraneliftResult;
 
-// Options for Cranelift compilation.
+// CraneliftResult::magic values. cranelift_free_result only frees buffers the bridge
+// still owns for the result's generation, at their original size: freeing a result
+// twice, a copy of it (even after its addresses were reused), or a zero-initialized
+// struct is a no-op.
+#define CRANELIFT_RESULT_LIVE 0x544D4C52u  // "TMLR"
+#define CRANELIFT_RESULT_FREED 0x46524545u // "FREE"
+
+// CraneliftResult::error_code values. A driver can fall back to the LLVM backend on
+// UNSUPPORTED_INSTRUCTION, INVALID_TARGET, CODEGEN and PANIC without matching error_msg;
+// the other codes point at the caller's input and would fail there too.
+#define CRANELIFT_ERROR_NONE 0
+#define CRANELIFT_ERROR_DESERIALIZE 1             // MIR data could not be read
+#define CRANELIFT_ERROR_TRANSLATION 2             // invalid MIR (e.g. a branch to a missing block)
+#define CRANELIFT_ERROR_CODEGEN 3                 // Cranelift rejected a function or the object
+#define CRANELIFT_ERROR_UNSUPPORTED_INSTRUCTION 4 // valid MIR this backend cannot compile yet
+#define CRANELIFT_ERROR_INVALID_TARGET 5          // no ISA for the target, or a failed self test
+#define CRANELIFT_ERROR_PANIC 6                   // panic caught in the bridge or Cranelift
+#define CRANELIFT_ERROR_INVALID_OPTIONS 7         // unknown profile, bad override or flag
+#define CRANELIFT_ERROR_INVALID_ARGUMENT 8        // null/empty input, unknown function name, FFI misuse
+#define CRANELIFT_ERROR_CANCELLED 9               // the cancel token was set (cancelled = 1)
+#define CRANELIFT_ERROR_RESOURCE_LIMIT 10         // went over max_memory_bytes
+
+// A @test function to record in the .tml.tests section (Mach-O: __TML,__tml_tests).
+// Section layout, little-endian: "TMLT" | u32 version (1) | u32 count | entries, where an
+// entry is u8 flags (1 = should_panic, 2 = has expected) | str symbol | str name
+// [| str expected] and a str is a u32 byte length followed by UTF-8 bytes.
+typedef struct CraneliftTestEntry {
+    const char* function;       // MIR function name
+    const char* name;           // display name (NULL = function)
+    int should_panic;           // 0 or 1
+    const char* expected_panic; // expected panic message substring (NULL = any)
+} CraneliftTestEntry;
+
+// A symbol declared under another name, for aliases and versioned exports without
+// post-processing the object (e.g. { "tml_foo", "foo_v2" }). from is the symbol the
+// bridge would use; the rename applies to definitions, imports and calls alike.
+typedef struct CraneliftSymbolRename {
+    const char* from;
+    const char* to;
+} CraneliftSymbolRename;
+
+// FFI checks: builds reporting CRANELIFT_FEATURE_FFI_CHECKS can validate every call's
+// pointers (not in the first page, aligned), lengths (no overflow, no NULL array with a
+// count) and CraneliftOptions fields (booleans 0/1, levels and enums in range) before
+// use, failing with error_msg "FFI misuse: <parameter>: <problem>" instead of undefined
+// behavior. Debug builds check unless TML_CRANELIFT_FFI_CHECKS=0; release builds with the
+// cargo feature ffi-checks check only with TML_CRANELIFT_FFI_CHECKS=1. The variable is
+// read once per process.
+
+// Cancel token shared by the compiles of one build (see cranelift_cancel).
+typedef struct CraneliftCancelToken CraneliftCancelToken;
+
+// Result context owning the results of one compile session (see cranelift_context_new).
+typedef struct CraneliftContext CraneliftContext;
+
+// Layout version of the structs in this header. Compare it with cranelift_abi_version()
+// once before the first call: a bridge library built from another header reads every
+// struct at the wrong offsets.
+#define CRANELIFT_ABI_VERSION 10
+
+uint32_t cranelift_abi_version(void);
+
+// Options for Cranelift compilation. struct_size is required (also in
+// CraneliftOptionsV2::base): a call whose struct_size is not the bridge's
+// sizeof(CraneliftOptions) fails with CRANELIFT_ERROR_INVALID_OPTIONS before any other
+// field is read. A NULL options pointer still means defaults.
 typedef struct CraneliftOptions {
+    size_t struct_size;        // sizeof(CraneliftOptions)
     int optimization_level;    // 0 = none, 1-3 = speed_and_size
-    const char* target_triple; // e.g. "x86_64-pc-windows-msvc"
-    int debug_info;            // 0 or 1
-    int dll_export;            // 0 or 1 (export public functions as dllexport)
+    const char* target_triple; // e.g. "x86_64-pc-windows-msvc" (NULL = host); see
+                               // cranelift_get_supported_targets
+    int debug_info;            // 0 or 1 (ELF: DWARF line info for source_file, from the MIR's
+                               // source locations)
+    int dll_export;            // 0 or 1 (export public functions as dllexport; COFF only)
+    int emit_inline_metadata;  // 0 or 1 (serialize small public functions into .tml.inline)
+    int trace_hooks;           // 0 or 1 (call __tml_trace(func_index, block_id) at block heads)
+    const char* ir_filter;     // IR text: comma-separated globs over function names (NULL = all)
+    int ir_annotate;           // IR text: 0 or 1 (";; @function"/";; @end" separators, symbols, signatures)
+    int ir_sort;               // IR text: 0 or 1 (sort functions by name)
+    int fast_math;             // CRANELIFT_FAST_MATH_* bits; 0 = strict IEEE
+    int nan_canonicalization;  // 0 or 1 (canonical NaN bits after float ops, ~3 extra insts each)
+    size_t frame_warn_bytes;   // warn when a function's stack frame exceeds this (0 = 256 KiB)
+    int frame_pointers;        // 0 = omit frames in leaf functions where the target allows
+                               // (aarch64), 1 = always keep (profilers); no red zone is used
+    int trap_abort;            // 0 or 1 (call tml_trap_abort(TmlTrapCategory) before traps and
+                               // check divisions, so traps surface as panics)
+    const CraneliftTestEntry* tests; // tests defined here go into .tml.tests (NULL = no section)
+    size_t num_tests;
+    int strict_runtime_calls;  // 0 or 1 (error when a runtime call disagrees with
+                               // runtime/core/runtime_signatures.def instead of coercing)
+    const char* multiversion;  // comma-separated globs: compile matches as <sym>.base and <sym>.avx2
+                               // behind a <sym> stub dispatching on tml_cpu_has_avx2() (x86_64)
+    int runtime_prefix;        // CRANELIFT_RUNTIME_PREFIX_*: import TML_RT_SHIM runtime functions
+                               // as tml_rt_<name> so they cannot collide with C symbols
+    const char* profile;       // "debug", "release", "small" or "jit": replaces the codegen fields
+                               // (optimization_level, debug_info, emit_inline_metadata, fast_math,
+                               // nan_canonicalization, frame_warn_bytes, frame_pointers, trap_abort,
+                               // strict_runtime_calls, runtime_prefix) for target_triple (NULL = as given)
+    const char* profile_overrides; // comma-separated field=value pairs applied after the profile,
+                                   // e.g. "opt_level=1,frame_pointers=0" (fast_math bits are
+                                   // fp_contract and fp_reassociate; NULL = none). Also takes
+                                   // cpu=<class> ("native", "baseline" or an ISA preset such as
+                                   // "haswell"), target_features=<+name,-name,...> to add or remove
+                                   // single extensions (e.g. target_features=+avx2,+bmi2; without
+                                   // a cpu they start from baseline, not the host) and
+                                   // flag.<name>=<value> for any Cranelift setting (e.g.
+                                   // flag.enable_probestack=true); profiles reset all three.
+                                   // const_pool_threshold=<n> loads a function's floats from a
+                                   // read-only pool once it has more than n distinct ones
+                                   // (0 = never, the default); repeated constants are always
+                                   // emitted once per function
+    const char* replay_dir; // on a failed translation or panic, write a tml-<entry>-*.tmlreplay bundle
+                            // (MIR, resolved options, bridge version) here and name it in
+                            // error_msg; "" = the system temp directory, NULL = off
+    int json_output;        // IR calls return JSON in ir_text instead of CLIF text:
+                            // {"module", "functions": {symbol: {"name", "index", "ir",
+                            // "findings"}}, "stats"}; validation errors become findings
+                            // (with "ir": null) instead of failing the call
+    const CraneliftCancelToken* cancel_token; // checked before each function; NULL = none
+    int emit_ir;            // compiles: 0 or 1 (also return the CLIF text of the functions they
+                            // define in ir_text, as generate_ir prints it with the same
+                            // ir_filter/ir_annotate/ir_sort, from the same translation)
+    int function_stats;     // compiles: 0 or 1 (also return a CraneliftFunctionStats record
+                            // per defined function in the result)
+    CraneliftWriteFn write; // compiles: stream the object through write(write_context, ...) in
+                            // chunks of up to 64 KiB instead of returning it in data, which
+                            // stays NULL (NULL = return it); not available as a _kv key
+    void* write_context;
+    const CraneliftSymbolRename* symbol_renames; // each symbol at most once; empty names fail
+    size_t num_symbol_renames;
+    const char* symbol_prefix; // prefix of TML function symbols, e.g. "mylib_" (NULL = "tml_",
+                               // "" = none); runtime functions and names already carrying the
+                               // prefix are left alone, and symbol_renames apply afterwards
+    int position_independent;  // 0 or 1 (PIC: reach imports through the GOT, so the object can go
+                               // into a shared library without text relocations; always on for
+                               // aarch64)
+    const char* source_file;   // path of the module's source (NULL = unknown); with trap_abort,
+                               // traps call tml_trap_abort_at with it and the trap's line
+    int emit_build_info;       // 0 or 1 (write a .tml.build section with the bridge, Cranelift and
+                               // MIR versions, target, opt level, cpu and features as
+                               // key=value lines; __TML,__tml_build on Mach-O)
+    const CraneliftContext* result_context; // owns the call's result; NULL = the caller
+    size_t max_memory_bytes; // heap the bridge may hold while reading and translating MIR,
+                             // checked between functions (0 = no limit); over it the call
+                             // fails with CRANELIFT_ERROR_RESOURCE_LIMIT. Needs a bridge
+                             // with CRANELIFT_FEATURE_MEMORY_LIMIT; others reject a limit
+                             // with CRANELIFT_ERROR_INVALID_OPTIONS
+    CraneliftDumpFn dump;    // debug dumps: pass each record to dump(dump_context, ...) instead
+                             // of dropping it; the bridge never writes to stdout or stderr
+    void* dump_context;
+    const char* dump_path;   // without dump: append the records to this file (NULL = none);
+                             // a file that cannot be opened fails the call
+    int panic_backtrace;     // 0 or 1 (append the Rust backtrace of a caught panic to the
+                             // "PANIC: ..." error_msg, as "\nbacktrace:\n" and its frames)
 } CraneliftOptions;
 
+// CraneliftOptions::fast_math bits.
+#define CRANELIFT_FAST_MATH_CONTRACT 1   // fuse float a * b + c into fma (native FMA only)
+#define CRANELIFT_FAST_MATH_REASSOCIATE 2 // regroup constants in float add/mul chains
+
+// CraneliftOptions::runtime_prefix values.
+#define CRANELIFT_RUNTIME_PREFIX_TARGET 0 // target default: prefixed on ELF/Mach-O, bare on COFF
+#define CRANELIFT_RUNTIME_PREFIX_ON 1     // always import the tml_rt_ aliases
+#define CRANELIFT_RUNTIME_PREFIX_OFF 2    // always import the bare names
+
 // Compile a full MIR module to an object file.
 CraneliftResult cranelift_compile_mir(const uint8_t* mir_data, size_t mir_len,
                                       const CraneliftOptions* options);
 
+// Compile a full MIR module to one object file, compiling the function bodies on
+// num_threads threads inside the bridge (0 = one per CPU). Translation to Cranelift IR
+// stays on the calling thread; the object is the same for every thread count, so a
+// driver can hand the bridge a whole module instead of splitting it into CGU processes.
+CraneliftResult cranelift_compile_mir_parallel(const uint8_t* mir_data, size_t mir_len,
+                                               const CraneliftOptions* options,
+                                               size_t num_threads);
+
 // Compile a subset of functions from a MIR module (CGU mode).
 CraneliftResult cranelift_compile_mir_cgu(const uint8_t* mir_data, size_t mir_len,
                                           const size_t* func_indices, size_t num_indices,
                                           const CraneliftOptions* options);
 
+// Compile the functions named in func_names (MIR names, as cranelift_list_functions
+// reports them) from a MIR module: CGU mode that survives the MIR writer reordering
+// functions. Fails with "no function named 'a', 'b' in module 'm'" listing every
+// name the module lacks. In both CGU modes the functions left out are imported.
+CraneliftResult cranelift_compile_mir_named(const uint8_t* mir_data, size_t mir_len,
+                                            const char* const* func_names, size_t num_names,
+                                            const CraneliftOptions* options);
+
+// Whole-module LTO: merge several MIR modules into one namespace, drop unreachable
+// functions, inline small leaf functions and emit a single object. Private functions
+// that modules define differently are renamed apart; different public definitions of
+// one name fail with CRANELIFT_ERROR_TRANSLATION.
+CraneliftResult cranelift_compile_mir_lto(const uint8_t* const* mir_blobs, const size_t* mir_lens,
+                                          size_t count, const CraneliftOptions* options);
+
+// Compile several MIR modules into a single object. All functions are declared
+// before any is translated, so calls between the modules resolve inside the object;
+// a function defined identically by more than one module is emitted once, and
+// duplicates are otherwise handled as for LTO.
+// Unlike LTO, nothing is pruned, internalized or inlined.
+CraneliftResult cranelift_compile_mir_multi(const uint8_t* const* mir_blobs, const size_t* mir_lens,
+                                            size_t count, const CraneliftOptions* options);
+
 // Generate Cranelift IR text from a MIR module (no compilation).
 CraneliftResult cranelift_generate_ir(const uint8_t* mir_data, size_t mir_len,
                                       const CraneliftOptions* options);
 
-// Free a CraneliftResult. Must be called for every result returned.
+// Generate Cranelift IR text for a subset of functions (CGU mode).
+CraneliftResult cranelift_generate_ir_cgu(const uint8_t* mir_data, size_t mir_len,
+                                          const size_t* func_indices, size_t num_indices,
+                                          const CraneliftOptions* options);
+
+// Compile a MIR module and return an assembly listing of its functions in ir_text
+// (no object). Each function starts with "; Function: <name> (<symbol>), <n> bytes"
+// and lists Cranelift's final machine code, after register allocation, with calls and
+// data references by symbol. ir_filter and ir_sort select and order the functions.
+CraneliftResult cranelift_disassemble(const uint8_t* mir_data, size_t mir_len,
+                                      const CraneliftOptions* options);
+
+// Summarize a MIR module as JSON (returned in ir_text): instruction counts per
+// kind, average blocks per function, phi density, method calls bound directly to
+// their receiver type's function, the largest functions and functions whose
+// register pressure likely forces spills or callee-saved saves.
+CraneliftResult cranelift_mir_stats(const uint8_t* mir_data, size_t mir_len);
+
+// List the functions of a MIR module without compiling (JSON in ir_text), for CGU
+// planning: {"module", "functions": [{"index", "name", "public", "defined", "params":
+// [{"name", "type"}], "return", "blocks", "instructions"}]} in module order. "defined"
+// is false for declarations; types use TML notation ("I32", "*mut Point", "List[I64]",
+// "(I32, Bool)", "[U8; 16]", "func(I32) -> Bool").
+CraneliftResult cranelift_list_functions(const uint8_t* mir_data, size_t mir_len);
+
+// Check a MIR module without translating it, for MIR writer conformance tests: the
+// header and encoding (by reading it), each function's CFG (branch targets exist,
+// reachable blocks have terminators) and its value references (defined once, before
+// use, in a block dominating the use). Findings come back as JSON in ir_text:
+// {"module", "functions", "errors", "warnings", "findings": [{"severity", "message",
+// "span": {"function", "block", "instruction"}}]}. Any error fails the call with the
+// first one in error_msg (CRANELIFT_ERROR_TRANSLATION), still returning the findings;
+// unreadable data fails with CRANELIFT_ERROR_DESERIALIZE and no findings.
+CraneliftResult cranelift_validate_mir(const uint8_t* mir_data, size_t mir_len);
+
+// Resolve the profile, target defaults and overrides of options and return the
+// resulting codegen fields as a JSON object in ir_text. Fails on an unknown
+// profile, override field, target triple, cpu class, target feature or Cranelift flag.
+CraneliftResult cranelift_resolve_options(const CraneliftOptions* options);
+
+// Out-parameter variants of the functions above, with the same arguments plus the
+// result to fill. They avoid returning a struct by value, whose ABI differs between
+// toolchains (MSVC vs MinGW). Each returns result->success, or does nothing and
+// returns 0 if result is NULL. The filled result must be freed as usual.
+int cranelift_compile_mir_v2(const uint8_t* mir_data, size_t mir_len,
+                             const CraneliftOptions* options, CraneliftResult* result);
+int cranelift_compile_mir_parallel_v2(const uint8_t* mir_data, size_t mir_len,
+                                      const CraneliftOptions* options, size_t num_threads,
+                                      CraneliftResult* result);
+int cranelift_compile_mir_cgu_v2(const uint8_t* mir_data, size_t mir_len,
+                                 const size_t* func_indices, size_t num_indices,
+                                 const CraneliftOptions* options, CraneliftResult* result);
+int cranelift_compile_mir_named_v2(const uint8_t* mir_data, size_t mir_len,
+                                   const char* const* func_names, size_t num_names,
+                                   const CraneliftOptions* options, CraneliftResult* result);
+int cranelift_compile_mir_lto_v2(const uint8_t* const* mir_blobs, const size_t* mir_lens,
+                                 size_t count, const CraneliftOptions* options,
+                                 CraneliftResult* result);
+int cranelift_compile_mir_multi_v2(const uint8_t* const* mir_blobs, const size_t* mir_lens,
+                                   size_t count, const CraneliftOptions* options,
+                                   CraneliftResult* result);
+int cranelift_generate_ir_v2(const uint8_t* mir_data, size_t mir_len,
+                             const CraneliftOptions* options, CraneliftResult* result);
+int cranelift_generate_ir_cgu_v2(const uint8_t* mir_data, size_t mir_len,
+                                 const size_t* func_indices, size_t num_indices,
+                                 const CraneliftOptions* options, CraneliftResult* result);
+int cranelift_disassemble_v2(const uint8_t* mir_data, size_t mir_len,
+                             const CraneliftOptions* options, CraneliftResult* result);
+int cranelift_mir_stats_v2(const uint8_t* mir_data, size_t mir_len, CraneliftResult* result);
+int cranelift_list_functions_v2(const uint8_t* mir_data, size_t mir_len, CraneliftResult* result);
+int cranelift_validate_mir_v2(const uint8_t* mir_data, size_t mir_len, CraneliftResult* result);
+int cranelift_resolve_options_v2(const CraneliftOptions* options, CraneliftResult* result);
+
+// Key-value options variants of the _v2 functions: options come as a versioned blob
+// instead of a CraneliftOptions struct, so new options need no struct field.
+// Layout, little-endian: "TMLO" | u32 version (1) | u32 count | (key, value)*, where
+// key and value are u32 byte length | UTF-8 bytes. Keys are the CraneliftOptions field
+// names with booleans as "0"/"1": profile, target_triple, opt_level, debug_info,
+// dll_export, emit_inline_metadata, trace_hooks, ir_filter, ir_annotate, ir_sort,
+// json_output, emit_ir, function_stats, position_independent, source_file,
+// emit_build_info, max_memory_bytes, panic_backtrace, fp_contract, fp_reassociate,
+// nan_canonicalization, frame_warn_bytes, frame_pointers, trap_abort, strict_runtime_calls,
+// multiversion, runtime_prefix ("target", "0", "1"), cpu, target_features, flag.<name>
+// (repeatable), const_pool_threshold, replay_dir, dump_path, cancel_token and
+// result_context (handles as "0x" hex), test ("function\tname\tshould_panic[\texpected]",
+// repeatable), symbol_prefix and symbol_rename ("from\tto", repeatable). A profile is
+// applied before the other keys.
+// Unknown keys, bad values and newer blob versions fail the call; a NULL or empty blob
+// means defaults.
+int cranelift_compile_mir_kv(const uint8_t* mir_data, size_t mir_len, const uint8_t* options,
+                             size_t options_len, CraneliftResult* result);
+int cranelift_compile_mir_cgu_kv(const uint8_t* mir_data, size_t mir_len,
+                                 const size_t* func_indices, size_t num_indices,
+                                 const uint8_t* options, size_t options_len,
+                                 CraneliftResult* result);
+int cranelift_compile_mir_named_kv(const uint8_t* mir_data, size_t mir_len,
+                                   const char* const* func_names, size_t num_names,
+                                   const uint8_t* options, size_t options_len,
+                                   CraneliftResult* result);
+int cranelift_compile_mir_lto_kv(const uint8_t* const* mir_blobs, const size_t* mir_lens,
+                                 size_t count, const uint8_t* options, size_t options_len,
+                                 CraneliftResult* result);
+int cranelift_compile_mir_multi_kv(const uint8_t* const* mir_blobs, const size_t* mir_lens,
+                                   size_t count, const uint8_t* options, size_t options_len,
+                                   CraneliftResult* result);
+int cranelift_generate_ir_kv(const uint8_t* mir_data, size_t mir_len, const uint8_t* options,
+                             size_t options_len, CraneliftResult* result);
+int cranelift_generate_ir_cgu_kv(const uint8_t* mir_data, size_t mir_len,
+                                 const size_t* func_indices, size_t num_indices,
+                                 const uint8_t* options, size_t options_len,
+                                 CraneliftResult* result);
+int cranelift_disassemble_kv(const uint8_t* mir_data, size_t mir_len, const uint8_t* options,
+                             size_t options_len, CraneliftResult* result);
+int cranelift_resolve_options_kv(const uint8_t* options, size_t options_len,
+                                 CraneliftResult* result);
+
+// Versioned options: CraneliftOptions behind a size/version header, plus raw Cranelift
+// settings. Set size = sizeof(CraneliftOptionsV2) and version =
+// CRANELIFT_OPTIONS_VERSION; a newer version or a smaller size fails the call. Each
+// flags entry is "name=value" for a Cranelift setting (e.g. "preserve_frame_pointers=true",
+// "enable_alias_analysis=false"), applied after the profile and profile_overrides like a
+// flag.<name> override; unknown settings and bad values fail when the ISA is built.
+#define CRANELIFT_OPTIONS_VERSION 2
+
+typedef struct CraneliftOptionsV2 {
+    uint32_t size;
+    uint32_t version;
+    CraneliftOptions base;
+    const char* const* flags; // NULL = none
+    size_t num_flags;
+} CraneliftOptionsV2;
+
+// _v2 functions taking versioned options; NULL means defaults.
+int cranelift_compile_mir_ex(const uint8_t* mir_data, size_t mir_len,
+                             const CraneliftOptionsV2* options, CraneliftResult* result);
+int cranelift_compile_mir_cgu_ex(const uint8_t* mir_data, size_t mir_len,
+                                 const size_t* func_indices, size_t num_indices,
+                                 const CraneliftOptionsV2* options, CraneliftResult* result);
+int cranelift_compile_mir_named_ex(const uint8_t* mir_data, size_t mir_len,
+                                   const char* const* func_names, size_t num_names,
+                                   const CraneliftOptionsV2* options, CraneliftResult* result);
+int cranelift_compile_mir_lto_ex(const uint8_t* const* mir_blobs, const size_t* mir_lens,
+                                 size_t count, const CraneliftOptionsV2* options,
+                                 CraneliftResult* result);
+int cranelift_compile_mir_multi_ex(const uint8_t* const* mir_blobs, const size_t* mir_lens,
+                                   size_t count, const CraneliftOptionsV2* options,
+                                   CraneliftResult* result);
+int cranelift_generate_ir_ex(const uint8_t* mir_data, size_t mir_len,
+                             const CraneliftOptionsV2* options, CraneliftResult* result);
+int cranelift_generate_ir_cgu_ex(const uint8_t* mir_data, size_t mir_len,
+                                 const size_t* func_indices, size_t num_indices,
+                                 const CraneliftOptionsV2* options, CraneliftResult* result);
+int cranelift_disassemble_ex(const uint8_t* mir_data, size_t mir_len,
+                             const CraneliftOptionsV2* options, CraneliftResult* result);
+int cranelift_resolve_options_ex(const CraneliftOptionsV2* options, CraneliftResult* result);
+
+// Run a built-in self test under options (NULL = defaults): build the ISA, round-trip
+// an embedded MIR module, compile it and check the object magic, then run a compiled
+// function in process (x86-64 hosts, native or baseline cpu only). ir_text holds a JSON
+// report {"passed", "target", "cpu", "checks": [{"name", "status", "detail"}]} with
+// status "pass", "fail" or "skip", also on failure, when error_msg names the first
+// failed check. Meant for driver or installer startup.
+CraneliftResult cranelift_self_test(const CraneliftOptions* options);
+int cranelift_self_test_v2(const CraneliftOptions* options, CraneliftResult* result);
+int cranelift_self_test_kv(const uint8_t* options, size_t options_len, CraneliftResult* result);
+int cranelift_self_test_ex(const CraneliftOptionsV2* options, CraneliftResult* result);
+
+// Estimate the machine code size of each function of a MIR module without building the
+// object, for balancing CGUs before compiling them: every function is translated and
+// compiled, but nothing is defined, relocated or emitted. The result has no data and one
+// function_stats record per function in definition order, whatever the function_stats
+// option; code_bytes is what a compile with the same options produces (a multi-versioned
+// function counts its variants and dispatch stub).
+CraneliftResult cranelift_estimate(const uint8_t* mir_data, size_t mir_len,
+                                   const CraneliftOptions* options);
+int cranelift_estimate_v2(const uint8_t* mir_data, size_t mir_len,
+                          const CraneliftOptions* options, CraneliftResult* result);
+int cranelift_estimate_kv(const uint8_t* mir_data, size_t mir_len, const uint8_t* options,
+                          size_t options_len, CraneliftResult* result);
+int cranelift_estimate_ex(const uint8_t* mir_data, size_t mir_len,
+                          const CraneliftOptionsV2* options, CraneliftResult* result);
+
+// Cancellation: create a token, set it as cancel_token in the options of every compile
+// of a build, and call cranelift_cancel when the sources change again. Each compile
+// (including IR generation) stops before its next function with error_msg "cancelled"
+// and result->cancelled = 1; compiles started with a cancelled token fail at once. No
+// replay bundle is written. Tokens are not reset: create a new one per build. Freeing a
+// token does not affect compiles already running with it; cancelling or freeing an
+// unknown or freed token is a no-op, and passing one in options fails the call.
+CraneliftCancelToken* cranelift_cancel_token_new(void);
+void cranelift_cancel(const CraneliftCancelToken* token);
+void cranelift_cancel_token_free(CraneliftCancelToken* token);
+
+// Free a CraneliftResult. Must be called for every result returned, unless a result
+// context owns it.
 void cranelift_free_result(CraneliftResult* result);
 
-// Get the Cranelift version string (statically allocated, do not free).
-const char* cranelift_version(void);
+// Result contexts: create one per compile session and set it as result_context in the
+// options of every call; each result is then owned by the context as well as by the
+// caller. cranelift_context_free_results frees every result the context owns, whether
+// or not early-exit paths reached cranelift_free_result; the context stays usable.
+// Results freed one by one first are skipped, but results the context has freed must not
+// be used or freed again. A call that fails before reading its options (null MIR, bad
+// options) returns a result the caller still owns. cranelift_context_free also frees the
+// context's results; freeing an unknown or freed context is a no-op, and passing one in
+// options fails the call.
+CraneliftContext* cranelift_context_new(void);
+void cranelift_context_free_results(const CraneliftContext* context);
+void cranelift_context_free(CraneliftContext* context);
+
+// Versions of this build (statically allocated, do not free). Check the MIR range before
+// serializing: a module whose major version differs from mir_major, or whose minor version
+// is outside mir_minor_min..mir_minor_max, fails with CRANELIFT_ERROR_DESERIALIZE.
+typedef struct CraneliftVersionInfo {
+    const char* cranelift_version; // Cranelift crates built in, e.g. "0.128.3"
+    uint32_t bridge_major;         // the bridge's semantic version
+    uint32_t bridge_minor;
+    uint32_t bridge_patch;
+    uint16_t mir_major;            // MIR binary format read
+    uint16_t mir_minor_min;
+    uint16_t mir_minor_max;
+} CraneliftVersionInfo;
+
+const CraneliftVersionInfo* cranelift_version_info(void);
+
+// Target triples this build compiles for, comma-separated with the host first
+// (statically allocated, do not free), e.g.
+// "x86_64-unknown-linux-gnu,x86_64-pc-windows-msvc,aarch64-apple-darwin,...". A triple is
+// listed when the self test's isa, mir and object checks pass for it; the first call runs
+// them, which takes a few milliseconds per candidate. Route other targets to LLVM.
+const char* cranelift_get_supported_targets(void);
+
+// Triple the bridge compiles for when target_triple is NULL or empty (statically
+// allocated, do not free), e.g. "x86_64-pc-windows-gnu": the target the bridge library
+// was built for, which is not necessarily the driver's own guess at the platform. Link
+// objects compiled without a target_triple for this triple.
+const char* cranelift_native_triple(void);
+
+// cranelift_features() bits.
+#define CRANELIFT_FEATURE_OBJECT 1      // object file emission (every build)
+#define CRANELIFT_FEATURE_JIT 2         // in-process JIT (out of scope: never reported)
+#define CRANELIFT_FEATURE_DEBUG_INFO 4  // DWARF line info on ELF targets (see debug_info;
+                                        // debug-info builds)
+#define CRANELIFT_FEATURE_INTERPRETER 8 // constant-function folding through the MIR interpreter
+#define CRANELIFT_FEATURE_FFI_CHECKS 16 // argument checks at this API (debug or ffi-checks builds)
+#define CRANELIFT_FEATURE_MEMORY_LIMIT 32 // max_memory_bytes honored (memory-limit builds, whose
+                                          // counting allocator is the process's allocator)
+
+// Capabilities compiled into this build of the bridge (CRANELIFT_FEATURE_* bits).
+// Minimal builds (cargo --no-default-features --features object) only report
+// CRANELIFT_FEATURE_OBJECT.
+uint32_t cranelift_features(void);
 
 #ifdef __cplusplus
 }
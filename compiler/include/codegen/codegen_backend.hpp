@@ -63,6 +63,7 @@ struct CodegenOptions {
     bool dll_export = false;
     bool generate_exe_main = false; ///< Emit @main(argc,argv) C entry wrapper for executables.
     std::string target_triple;
+    std::string source_file; ///< Source path for trap messages and debug info (empty = unknown).
 };
 
 /// Abstract behavior for code generation backends.
@@ -1713,6 +1713,8 @@ private:
     std::vector<std::pair<std::string, std::string>> string_literals_;
     std::unordered_map<std::string, std::string> string_literal_dedup_;
     auto add_string_literal(const std::string& value) -> std::string;
+    std::unordered_map<std::string, std::string> wide_string_literal_dedup_;
+    auto add_wide_string_literal(const std::string& value) -> std::string;
 
 public:
     /// Inferred type for print format specifier selection.
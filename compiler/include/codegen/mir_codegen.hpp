@@ -114,6 +114,9 @@ private:
     // String constants (value -> global name)
     std::unordered_map<std::string, std::string> string_constants_;
 
+    // Wide string constants (UTF-8 value -> global name), emitted as UTF-16
+    std::unordered_map<std::string, std::string> wide_string_constants_;
+
     // ValueId -> string content (for compile-time constant string length optimization)
     std::unordered_map<mir::ValueId, std::string> value_string_contents_;
 
@@ -131,6 +134,7 @@ private:
 
     // Generate helpers
     void emit_preamble();
+    void emit_wide_string_constants();
     void emit_type_defs(const mir::Module& module);
     void emit_struct_def(const mir::StructDef& s);
     void emit_enum_def(const mir::EnumDef& e);
@@ -198,6 +202,7 @@ private:
                                const mir::InstructionData& inst);
     void emit_tuple_init_inst(const mir::TupleInitInst& i, const std::string& result_reg);
     void emit_array_init_inst(const mir::ArrayInitInst& i, const std::string& result_reg);
+    void emit_array_fill_inst(const mir::ArrayFillInst& i, const std::string& result_reg);
     void emit_closure_init_inst(const mir::ClosureInitInst& i, const std::string& result_reg,
                                 const mir::InstructionData& inst);
     void emit_atomic_load_inst(const mir::AtomicLoadInst& i, const std::string& result_reg,
@@ -0,0 +1,82 @@
+//! # UTF-16 Conversion Utility
+//!
+//! This module converts UTF-8 text to UTF-16 code units, as needed for wide
+//! string literals passed to Windows `W` APIs (`LPCWSTR`).
+//!
+//! ## Usage
+//!
+//! ```cpp
+//! #include "common/utf16.hpp"
+//!
+//! std::vector<uint16_t> units = tml::utf8_to_utf16("h\xC3\xA9");
+//! // units == {0x0068, 0x00E9}
+//! ```
+
+#ifndef TML_COMMON_UTF16_HPP
+#define TML_COMMON_UTF16_HPP
+
+#include <cstdint>
+#include <string_view>
+#include <vector>
+
+namespace tml {
+
+/// Converts UTF-8 text to UTF-16 code units, without a terminator.
+///
+/// Code points above U+FFFF become surrogate pairs. Malformed sequences
+/// are replaced with U+FFFD, one per offending byte.
+///
+/// @param text UTF-8 encoded text
+/// @return UTF-16 code units
+[[nodiscard]] inline std::vector<uint16_t> utf8_to_utf16(std::string_view text) {
+    std::vector<uint16_t> units;
+    units.reserve(text.size());
+    size_t i = 0;
+    while (i < text.size()) {
+        auto lead = static_cast<uint8_t>(text[i]);
+        size_t len = 0;
+        uint32_t cp = 0;
+        uint32_t min_cp = 0;
+        if (lead < 0x80) {
+            len = 1;
+            cp = lead;
+        } else if ((lead & 0xE0) == 0xC0) {
+            len = 2;
+            cp = lead & 0x1F;
+            min_cp = 0x80;
+        } else if ((lead & 0xF0) == 0xE0) {
+            len = 3;
+            cp = lead & 0x0F;
+            min_cp = 0x800;
+        } else if ((lead & 0xF8) == 0xF0) {
+            len = 4;
+            cp = lead & 0x07;
+            min_cp = 0x10000;
+        }
+        bool valid = len != 0 && i + len <= text.size();
+        for (size_t k = 1; valid && k < len; ++k) {
+            auto cont = static_cast<uint8_t>(text[i + k]);
+            valid = (cont & 0xC0) == 0x80;
+            cp = (cp << 6) | (cont & 0x3F);
+        }
+        // Reject overlong forms, surrogates and code points past U+10FFFF
+        if (!valid || cp < min_cp || cp > 0x10FFFF || (cp >= 0xD800 && cp <= 0xDFFF)) {
+            units.push_back(0xFFFD);
+            ++i;
+            continue;
+        }
+        if (cp >= 0x10000) {
+            cp -= 0x10000;
+            units.push_back(static_cast<uint16_t>(0xD800 | (cp >> 10)));
+            units.push_back(static_cast<uint16_t>(0xDC00 | (cp & 0x3FF)));
+        } else {
+            units.push_back(static_cast<uint16_t>(cp));
+        }
+        i += len;
+    }
+    return units;
+}
+
+} // namespace tml
+
+#endif // TML_COMMON_UTF16_HPP
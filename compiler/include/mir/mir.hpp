@@ -253,6 +253,11 @@ struct ConstString {
     std::string value;
 };
 
+// Wide string literal: UTF-8 here, emitted as NUL-terminated UTF-16
+struct ConstWideString {
+    std::string value;
+};
+
 struct ConstUnit {};
 
 struct ConstFuncRef {
@@ -260,8 +265,8 @@ struct ConstFuncRef {
     MirTypePtr func_type;  // Type of the function (for call signature)
 };
 
-using Constant =
-    std::variant<ConstInt, ConstFloat, ConstBool, ConstString, ConstUnit, ConstFuncRef>;
+using Constant = std::variant<ConstInt, ConstFloat, ConstBool, ConstString, ConstUnit,
+                              ConstFuncRef, ConstWideString>;
 
 // ============================================================================
 // MIR Instructions (SSA Form)
@@ -489,6 +494,15 @@ struct ArrayInitInst {
     MirTypePtr result_type; // Full array type
 };
 
+// Array fill: result = [value; count]
+// One operand however long the array is, unlike ArrayInitInst.
+struct ArrayFillInst {
+    Value value;
+    uint64_t count;
+    MirTypePtr element_type;
+    MirTypePtr result_type; // Full array type
+};
+
 // Await instruction: result = await poll_value (suspension point)
 // This instruction marks a potential suspension point in async functions.
 // The poll_value is a Poll[T] and result is T (extracted from Ready).
@@ -664,7 +678,7 @@ using Instruction =
     std::variant<BinaryInst, UnaryInst, LoadInst, StoreInst, AllocaInst, GetElementPtrInst,
                  ExtractValueInst, InsertValueInst, CallInst, MethodCallInst, CastInst, PhiInst,
                  ConstantInst, SelectInst, StructInitInst, EnumInitInst, TupleInitInst,
-                 ArrayInitInst, AwaitInst, ClosureInitInst,
+                 ArrayInitInst, AwaitInst, ClosureInitInst, ArrayFillInst,
                  // Atomic instructions
                  AtomicLoadInst, AtomicStoreInst, AtomicRMWInst, AtomicCmpXchgInst, FenceInst,
                  // SIMD vector instructions
@@ -676,7 +690,7 @@ struct InstructionData {
     ValueId result;  // INVALID_VALUE for void instructions (store)
     MirTypePtr type; // Result type
     Instruction inst;
-    SourceSpan span; // Source location for debugging
+    SourceSpan span{}; // Source location for debugging (zero = unknown)
 };
 
 // ============================================================================
@@ -54,7 +54,9 @@ constexpr uint32_t MIR_MAGIC = 0x544D4952; // "TMIR" in little-endian
 /// MIR binary format major version.
 constexpr uint16_t MIR_VERSION_MAJOR = 1;
 /// MIR binary format minor version.
-constexpr uint16_t MIR_VERSION_MINOR = 0;
+/// 1: MethodCall carries the receiver type name after the receiver.
+/// 2: Every instruction ends with its source line and column (0 = unknown).
+constexpr uint16_t MIR_VERSION_MINOR = 2;
 
 /// Writes MIR modules to binary format.
 class MirBinaryWriter {
@@ -103,6 +105,7 @@ private:
     std::istream& in_;
     bool has_error_ = false;
     std::string error_;
+    uint16_t minor_ = 0; ///< Minor version of the data being read
 
     void set_error(const std::string& msg);
     auto read_u8() -> uint8_t;
@@ -22,7 +22,7 @@
 //! ## How It Works
 //!
 //! 1. Scan for allocas with array types (known size from MirArrayType)
-//! 2. Scan for ArrayInitInst (known size from element count)
+//! 2. Scan for ArrayInitInst/ArrayFillInst (known size from element count)
 //! 3. Find method calls to "len" on those values
 //! 4. Replace the method call with a constant integer
 //!
@@ -44,6 +44,15 @@
 #define TML_EXPORT __attribute__((visibility("default")))
 #endif
 
+// CPU feature detection for multi-versioned functions
+#if defined(__x86_64__) || defined(_M_X64)
+#if defined(_MSC_VER)
+#include <intrin.h>
+#else
+#include <cpuid.h>
+#endif
+#endif
+
 // Backtrace support for panic handlers
 #include "../diagnostics/backtrace.h"
 
@@ -108,6 +117,34 @@ TML_EXPORT int32_t tml_get_output_suppressed(void) {
     return tml_suppress_output;
 }
 
+// ============================================================================
+// Trace Hooks (Cranelift trace mode)
+// ============================================================================
+
+/** @brief Callback installed by the debugger; NULL means tracing is a no-op. */
+static void (*tml_trace_callback)(int32_t func_index, int32_t block_id) = NULL;
+
+/**
+ * @brief Installs the callback invoked by `__tml_trace`.
+ *
+ * @param callback Function receiving (func_index, block_id), or NULL to disable.
+ */
+TML_EXPORT void tml_set_trace_hook(void (*callback)(int32_t func_index, int32_t block_id)) {
+    tml_trace_callback = callback;
+}
+
+/**
+ * @brief Trace hook called at every block head of code compiled in trace mode.
+ *
+ * `func_index` is the function's index in its MIR module and `block_id` the
+ * MIR block id, so the debugger can map events back to source.
+ */
+TML_EXPORT void __tml_trace(int32_t func_index, int32_t block_id) {
+    if (tml_trace_callback) {
+        tml_trace_callback(func_index, block_id);
+    }
+}
+
 // ============================================================================
 // Panic Catching State (for @should_panic tests)
 // ============================================================================
@@ -566,6 +603,121 @@ TML_EXPORT const char* tml_get_panic_backtrace_json(void) {
     return tml_panic_backtrace_json;
 }
 
+/**
+ * @brief Reports a trap raised by code compiled with the `trap_abort` option.
+ *
+ * Uses the messages WebAssembly engines report, so native and wasm builds
+ * of the same program fail the same way.
+ *
+ * @param category A `TmlTrapCategory` value.
+ */
+static const char* tml_trap_message(int32_t category) {
+    static const char* const messages[] = {
+        "unreachable",
+        "integer divide by zero",
+        "integer overflow",
+        "invalid conversion to integer",
+        "out of bounds memory access",
+        "call stack exhausted",
+    };
+    if (category < 0 || category >= (int32_t)(sizeof(messages) / sizeof(messages[0]))) {
+        category = 0; // TML_TRAP_UNREACHABLE
+    }
+    return messages[category];
+}
+
+TML_EXPORT void tml_trap_abort(int32_t category) {
+    panic(tml_trap_message(category));
+}
+
+/**
+ * @brief Reports a `trap_abort` trap whose source location is known.
+ *
+ * @param category A `TmlTrapCategory` value.
+ * @param file Source file the module was compiled from.
+ * @param line 1-based line of the trapping instruction.
+ * @param column 1-based column, or 0 when unknown.
+ */
+TML_EXPORT void tml_trap_abort_at(int32_t category, const char* file, int32_t line,
+                                  int32_t column) {
+    static char message[1024];
+    const char* what = tml_trap_message(category);
+    file = file ? file : "<unknown>";
+    if (column > 0) {
+        snprintf(message, sizeof(message), "%s at %s:%d:%d", what, file, line, column);
+    } else {
+        snprintf(message, sizeof(message), "%s at %s:%d", what, file, line);
+    }
+    panic(message);
+}
+
+#if defined(__x86_64__) || defined(_M_X64)
+#if defined(_MSC_VER)
+static void tml_cpuid(int leaf, int subleaf, int regs[4]) {
+    __cpuidex(regs, leaf, subleaf);
+}
+static uint64_t tml_xgetbv0(void) {
+    return _xgetbv(0);
+}
+#else
+static void tml_cpuid(int leaf, int subleaf, int regs[4]) {
+    unsigned a, b, c, d;
+    __cpuid_count(leaf, subleaf, a, b, c, d);
+    regs[0] = (int)a;
+    regs[1] = (int)b;
+    regs[2] = (int)c;
+    regs[3] = (int)d;
+}
+static uint64_t tml_xgetbv0(void) {
+    uint32_t lo, hi;
+    __asm__ volatile("xgetbv" : "=a"(lo), "=d"(hi) : "c"(0));
+    return ((uint64_t)hi << 32) | lo;
+}
+#endif
+#endif
+
+/**
+ * @brief Checks for the extensions the Cranelift bridge enables in `.avx2`
+ * variants of multi-versioned functions.
+ *
+ * SSE3/SSSE3/SSE4.1/SSE4.2, POPCNT, AVX, AVX2, FMA, BMI1, BMI2 and LZCNT,
+ * plus OS support for saving YMM state. Must stay in sync with
+ * `AVX2_FLAGS` in compiler/cranelift/src/multiversion.rs.
+ *
+ * @return 1 if all are available, 0 otherwise (always 0 off x86-64).
+ */
+TML_EXPORT int32_t tml_cpu_has_avx2(void) {
+#if defined(__x86_64__) || defined(_M_X64)
+    int r[4];
+    tml_cpuid(0, 0, r);
+    if (r[0] < 7) {
+        return 0;
+    }
+    tml_cpuid(1, 0, r);
+    const uint32_t ecx1 = (uint32_t)r[2];
+    const uint32_t leaf1_bits = (1u << 0)     // SSE3
+                                | (1u << 9)   // SSSE3
+                                | (1u << 12)  // FMA
+                                | (1u << 19)  // SSE4.1
+                                | (1u << 20)  // SSE4.2
+                                | (1u << 23)  // POPCNT
+                                | (1u << 27)  // OSXSAVE
+                                | (1u << 28); // AVX
+    if ((ecx1 & leaf1_bits) != leaf1_bits || (tml_xgetbv0() & 0x6) != 0x6) {
+        return 0;
+    }
+    tml_cpuid(7, 0, r);
+    const uint32_t leaf7_bits = (1u << 3) | (1u << 5) | (1u << 8); // BMI1, AVX2, BMI2
+    if (((uint32_t)r[1] & leaf7_bits) != leaf7_bits) {
+        return 0;
+    }
+    tml_cpuid((int)0x80000001, 0, r);
+    return ((uint32_t)r[2] & (1u << 5)) != 0; // LZCNT
+#else
+    return 0;
+#endif
+}
+
 /** @brief Callback type for test functions that return int (int -> void args). */
 typedef int32_t (*tml_test_entry_fn)(void);
 
@@ -1407,6 +1559,106 @@ TML_EXPORT void tml_str_free(void* ptr) {
 #endif
 }
 
+// ============================================================================
+// Prefixed Runtime Aliases
+// ============================================================================
+// tml_rt_<name> forwarders for the TML_RT_SHIM entries of
+// runtime_signatures.def, imported by code compiled with runtime prefixing.
+// The C++ runtime signature test checks both names against the manifest.
+
+extern int64_t time_ns(void);
+extern void sleep_ms(int32_t ms);
+extern void* mem_alloc_zeroed(int64_t size);
+extern void* mem_realloc(void* ptr, int64_t new_size);
+extern void mem_free(void* ptr);
+extern void mem_copy(void* dest, const void* src, int64_t size);
+extern void mem_move(void* dest, const void* src, int64_t size);
+extern void mem_set(void* ptr, int32_t value, int64_t size);
+extern void mem_zero(void* ptr, int64_t size);
+extern int32_t mem_compare(const void* a, const void* b, int64_t size);
+extern int32_t mem_eq(const void* a, const void* b, int64_t size);
+
+TML_EXPORT void tml_rt_print(const char* message) {
+    print(message);
+}
+
+TML_EXPORT void tml_rt_println(const char* message) {
+    println(message);
+}
+
+TML_EXPORT void tml_rt_panic(const char* message) {
+    panic(message);
+}
+
+TML_EXPORT void tml_rt_assert_tml_loc(int32_t condition, const char* message, const char* file,
+                                      int32_t line) {
+    assert_tml_loc(condition, message, file, line);
+}
+
+TML_EXPORT void tml_rt_print_i32(int32_t n) {
+    print_i32(n);
+}
+
+TML_EXPORT void tml_rt_print_i64(int64_t n) {
+    print_i64(n);
+}
+
+TML_EXPORT void tml_rt_print_f64(double n) {
+    print_f64(n);
+}
+
+TML_EXPORT void tml_rt_print_bool(int32_t b) {
+    print_bool(b);
+}
+
+TML_EXPORT int64_t tml_rt_time_ns(void) {
+    return time_ns();
+}
+
+TML_EXPORT void tml_rt_sleep_ms(int32_t ms) {
+    sleep_ms(ms);
+}
+
+TML_EXPORT void* tml_rt_mem_alloc(int64_t size) {
+    return mem_alloc(size);
+}
+
+TML_EXPORT void* tml_rt_mem_alloc_zeroed(int64_t size) {
+    return mem_alloc_zeroed(size);
+}
+
+TML_EXPORT void* tml_rt_mem_realloc(void* ptr, int64_t new_size) {
+    return mem_realloc(ptr, new_size);
+}
+
+TML_EXPORT void tml_rt_mem_free(void* ptr) {
+    mem_free(ptr);
+}
+
+TML_EXPORT void tml_rt_mem_copy(void* dest, const void* src, int64_t size) {
+    mem_copy(dest, src, size);
+}
+
+TML_EXPORT void tml_rt_mem_move(void* dest, const void* src, int64_t size) {
+    mem_move(dest, src, size);
+}
+
+TML_EXPORT void tml_rt_mem_set(void* ptr, int32_t value, int64_t size) {
+    mem_set(ptr, value, size);
+}
+
+TML_EXPORT void tml_rt_mem_zero(void* ptr, int64_t size) {
+    mem_zero(ptr, size);
+}
+
+TML_EXPORT int32_t tml_rt_mem_compare(const void* a, const void* b, int64_t size) {
+    return mem_compare(a, b, size);
+}
+
+TML_EXPORT int32_t tml_rt_mem_eq(const void* a, const void* b, int64_t size) {
+    return mem_eq(a, b, size);
+}
+
 // ============================================================================
 // Windows DLL Entry Point
 // ============================================================================
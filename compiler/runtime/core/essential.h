@@ -54,6 +54,19 @@ void tml_set_output_suppressed(int32_t suppress);
  */
 int32_t tml_get_output_suppressed(void);
 
+/**
+ * @brief Installs the callback invoked by `__tml_trace` (NULL disables it).
+ * @param callback Receives (func_index, block_id) for each traced block.
+ */
+void tml_set_trace_hook(void (*callback)(int32_t func_index, int32_t block_id));
+
+/**
+ * @brief Block-entry hook emitted by the Cranelift backend in trace mode.
+ * @param func_index Index of the function in its MIR module.
+ * @param block_id MIR block id.
+ */
+void __tml_trace(int32_t func_index, int32_t block_id);
+
 // ============================================================================
 // IO Functions
 // ============================================================================
@@ -277,6 +290,85 @@ const char* tml_get_panic_backtrace_json(void);
  */
 int32_t tml_panic_message_contains(const char* expected);
 
+/**
+ * @brief Trap categories passed to `tml_trap_abort`.
+ *
+ * Mirrors `TrapCategory` in the Cranelift bridge (compiler/cranelift/src/traps.rs);
+ * the values are part of the generated code's ABI.
+ */
+typedef enum TmlTrapCategory {
+    TML_TRAP_UNREACHABLE = 0,
+    TML_TRAP_INTEGER_DIVIDE_BY_ZERO = 1,
+    TML_TRAP_INTEGER_OVERFLOW = 2,
+    TML_TRAP_INVALID_CONVERSION = 3,
+    TML_TRAP_OUT_OF_BOUNDS = 4,
+    TML_TRAP_STACK_OVERFLOW = 5,
+} TmlTrapCategory;
+
+/**
+ * @brief Reports a trap raised by code compiled with the `trap_abort` option.
+ *
+ * Panics with the WebAssembly trap message for `category` (e.g. "integer
+ * divide by zero"), so traps behave like `panic()` under `@should_panic`.
+ *
+ * @param category A `TmlTrapCategory` value.
+ * @note This function never returns.
+ */
+void tml_trap_abort(int32_t category);
+
+/**
+ * @brief Like `tml_trap_abort`, with the trap's source location.
+ *
+ * Called instead of `tml_trap_abort` when the module was compiled with a
+ * `source_file` and the trapping instruction has a line. The panic message
+ * ends with " at file:line:column" (e.g. "integer divide by zero at
+ * main.tml:12:9"); the column is left out when it is 0.
+ *
+ * @param category A `TmlTrapCategory` value.
+ * @param file Source file the module was compiled from.
+ * @param line 1-based source line.
+ * @param column 1-based source column, or 0 when unknown.
+ * @note This function never returns.
+ */
+void tml_trap_abort_at(int32_t category, const char* file, int32_t line, int32_t column);
+
+/**
+ * @brief Reports whether the CPU runs the `.avx2` variants of functions
+ * multi-versioned by the Cranelift backend (AVX2, FMA, BMI1/2, LZCNT, ...).
+ *
+ * @return 1 if supported, 0 otherwise.
+ */
+int32_t tml_cpu_has_avx2(void);
+
+// ============================================================================
+// Prefixed Runtime Aliases
+// ============================================================================
+
+// Every TML_RT_SHIM entry of runtime_signatures.def is also exported as
+// tml_rt_<name>. Code compiled with runtime prefixing imports these instead of
+// the bare names, which can collide with libc or user C code at link time.
+void tml_rt_print(const char* message);
+void tml_rt_println(const char* message);
+void tml_rt_panic(const char* message);
+void tml_rt_assert_tml_loc(int32_t condition, const char* message, const char* file,
+                           int32_t line);
+void tml_rt_print_i32(int32_t n);
+void tml_rt_print_i64(int64_t n);
+void tml_rt_print_f64(double n);
+void tml_rt_print_bool(int32_t b);
+int64_t tml_rt_time_ns(void);
+void tml_rt_sleep_ms(int32_t ms);
+void* tml_rt_mem_alloc(int64_t size);
+void* tml_rt_mem_alloc_zeroed(int64_t size);
+void* tml_rt_mem_realloc(void* ptr, int64_t new_size);
+void tml_rt_mem_free(void* ptr);
+void tml_rt_mem_copy(void* dest, const void* src, int64_t size);
+void tml_rt_mem_move(void* dest, const void* src, int64_t size);
+void tml_rt_mem_set(void* ptr, int32_t value, int64_t size);
+void tml_rt_mem_zero(void* ptr, int64_t size);
+int32_t tml_rt_mem_compare(const void* a, const void* b, int64_t size);
+int32_t tml_rt_mem_eq(const void* a, const void* b, int64_t size);
+
 // ============================================================================
 // Async Runtime (see async.h for full API)
 // ============================================================================
@@ -864,11 +864,8 @@ SuiteCompileResult compile_test_suite(const TestSuite& suite, bool verbose, bool
                             codegen::CodegenOptions cg_opts;
                             cg_opts.optimization_level = CompilerOptions::optimization_level;
                             cg_opts.dll_export = true;
-#ifdef _WIN32
-                            cg_opts.target_triple = "x86_64-pc-windows-msvc";
-#else
-                            cg_opts.target_triple = "x86_64-unknown-linux-gnu";
-#endif
+                            // No target_triple: the bridge builds for its
+                            // cranelift_native_triple()
                             auto cl_backend =
                                 codegen::create_backend(codegen::BackendType::Cranelift);
                             auto cg_result = cl_backend->compile_mir(mir_module, cg_opts);
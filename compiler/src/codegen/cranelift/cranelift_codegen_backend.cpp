@@ -38,10 +38,12 @@ auto CraneliftCodegenBackend::capabilities() const -> BackendCapabilities {
 
 auto CraneliftCodegenBackend::to_cranelift_opts(const CodegenOptions& opts) -> ::CraneliftOptions {
     ::CraneliftOptions c_opts{};
+    c_opts.struct_size = sizeof(c_opts);
     c_opts.optimization_level = opts.optimization_level;
     c_opts.target_triple = opts.target_triple.empty() ? nullptr : opts.target_triple.c_str();
     c_opts.debug_info = opts.debug_info ? 1 : 0;
     c_opts.dll_export = opts.dll_export ? 1 : 0;
+    c_opts.source_file = opts.source_file.empty() ? nullptr : opts.source_file.c_str();
     return c_opts;
 }
 
@@ -84,11 +86,13 @@ auto CraneliftCodegenBackend::compile_mir(const mir::Module& module, const Codeg
 
     // Call Cranelift bridge
     auto c_opts = to_cranelift_opts(opts);
-    auto c_result = cranelift_compile_mir(mir_bytes.data(), mir_bytes.size(), &c_opts);
+    ::CraneliftResult c_result{};
+    cranelift_compile_mir_v2(mir_bytes.data(), mir_bytes.size(), &c_opts, &c_result);
 
     if (!c_result.success) {
-        result.error_message =
-            c_result.error_msg ? std::string(c_result.error_msg) : "Cranelift compilation failed";
+        result.error_message = c_result.error_msg
+                                   ? std::string(c_result.error_msg, c_result.error_msg_len)
+                                   : "Cranelift compilation failed";
         cranelift_free_result(&c_result);
         return result;
     }
@@ -117,12 +121,14 @@ auto CraneliftCodegenBackend::compile_mir_cgu(const mir::Module& module,
     auto mir_bytes = mir::serialize_binary(module);
 
     auto c_opts = to_cranelift_opts(opts);
-    auto c_result = cranelift_compile_mir_cgu(mir_bytes.data(), mir_bytes.size(),
-                                              func_indices.data(), func_indices.size(), &c_opts);
+    ::CraneliftResult c_result{};
+    cranelift_compile_mir_cgu_v2(mir_bytes.data(), mir_bytes.size(), func_indices.data(),
+                                 func_indices.size(), &c_opts, &c_result);
 
     if (!c_result.success) {
-        result.error_message = c_result.error_msg ? std::string(c_result.error_msg)
-                                                  : "Cranelift CGU compilation failed";
+        result.error_message = c_result.error_msg
+                                   ? std::string(c_result.error_msg, c_result.error_msg_len)
+                                   : "Cranelift CGU compilation failed";
         cranelift_free_result(&c_result);
         return result;
     }
@@ -159,13 +165,14 @@ auto CraneliftCodegenBackend::generate_ir(const mir::Module& module, const Codeg
     auto mir_bytes = mir::serialize_binary(module);
 
     auto c_opts = to_cranelift_opts(opts);
-    auto c_result = cranelift_generate_ir(mir_bytes.data(), mir_bytes.size(), &c_opts);
+    ::CraneliftResult c_result{};
+    cranelift_generate_ir_v2(mir_bytes.data(), mir_bytes.size(), &c_opts, &c_result);
 
     std::string ir_text;
     if (c_result.success && c_result.ir_text) {
         ir_text.assign(c_result.ir_text, c_result.ir_text_len);
     } else if (c_result.error_msg) {
-        ir_text = std::string("; ERROR: ") + c_result.error_msg;
+        ir_text = "; ERROR: " + std::string(c_result.error_msg, c_result.error_msg_len);
     } else {
         ir_text = "; ERROR: Failed to generate Cranelift IR";
     }
@@ -20,6 +20,7 @@ TML_MODULE("codegen_x86")
 //! |-------------------------|----------------------------------|
 //! | `f*_is_nan/is_infinite` | Pure LLVM IR (`fcmp`)            |
 //! | `f64_round`             | `@llvm.round.f64` intrinsic     |
+//! | `wide_str`              | Private UTF-16 constant          |
 
 #include "codegen/llvm/llvm_ir_gen.hpp"
 
@@ -33,6 +34,24 @@ auto LLVMIRGen::try_gen_builtin_string(const std::string& fn_name, const parser:
     // All `lowlevel { f64_to_string_precision(...) }` calls resolve via functions_[] map.
     // The bare builtin interceptor here was unreachable.
 
+    // ========================================================================
+    // Wide Strings (UTF-16 data for Windows W APIs)
+    // ========================================================================
+
+    // wide_str("...") -> *U16 — a private UTF-16 constant, no runtime transcoding.
+    // The type checker only accepts a string literal argument.
+    if (fn_name == "wide_str") {
+        if (call.args.size() == 1 && call.args[0]->is<parser::LiteralExpr>()) {
+            const auto& token = call.args[0]->as<parser::LiteralExpr>().token;
+            if (token.kind == lexer::TokenKind::StringLiteral) {
+                last_expr_type_ = "ptr";
+                return add_wide_string_literal(std::string(token.string_value().value));
+            }
+        }
+        last_expr_type_ = "ptr";
+        return "null";
+    }
+
     // ========================================================================
     // Float Intrinsics (pure LLVM IR, no C runtime)
     // ========================================================================
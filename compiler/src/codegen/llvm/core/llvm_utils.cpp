@@ -29,6 +29,7 @@ TML_MODULE("codegen_x86")
 //! `report_error()` collects codegen errors for later reporting.
 
 #include "codegen/llvm/llvm_ir_gen.hpp"
+#include "common/utf16.hpp"
 
 namespace tml::codegen {
 
@@ -174,6 +175,25 @@ auto LLVMIRGen::add_string_literal(const std::string& value) -> std::string {
     return name;
 }
 
+auto LLVMIRGen::add_wide_string_literal(const std::string& value) -> std::string {
+    auto it = wide_string_literal_dedup_.find(value);
+    if (it != wide_string_literal_dedup_.end()) {
+        return it->second;
+    }
+    // Kept with the narrow literals as UTF-16LE bytes plus the low byte of the
+    // terminator; emit_string_constants() appends the high byte
+    std::string bytes;
+    for (uint16_t unit : utf8_to_utf16(value)) {
+        bytes += static_cast<char>(unit & 0xFF);
+        bytes += static_cast<char>(unit >> 8);
+    }
+    bytes += '\0';
+    std::string name = "@.wstr." + std::to_string(string_literals_.size());
+    string_literals_.emplace_back(name, bytes);
+    wide_string_literal_dedup_.emplace(value, name);
+    return name;
+}
+
 auto LLVMIRGen::get_suite_prefix() const -> std::string {
     // Suite prefix is only used for test-local functions (current_module_prefix_ empty)
     // Library functions should NOT have suite prefix - they're shared across tests
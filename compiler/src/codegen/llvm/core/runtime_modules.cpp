@@ -1051,13 +1051,17 @@ void LLVMIRGen::emit_string_constants() {
                 escaped += "\\5C";
             else if (c == '"')
                 escaped += "\\22";
+            else if (c == '\0')
+                escaped += "\\00";
             else
                 escaped += c;
         }
         escaped += "\\00";
 
+        // Wide literals are UTF-16 and need 16-bit alignment
+        std::string align = name.rfind("@.wstr.", 0) == 0 ? ", align 2" : "";
         emit_line(name + " = private constant [" + std::to_string(value.size() + 1) + " x i8] c\"" +
-                  escaped + "\"");
+                  escaped + "\"" + align);
     }
     emit_line("");
 }
@@ -16,7 +16,8 @@ TML_MODULE("codegen_x86")
 //! | Control      | CallInst, MethodCallInst, SelectInst, PhiInst         |
 //! | Type         | CastInst                                              |
 //! | Constants    | ConstantInst                                          |
-//! | Collections  | TupleInitInst, ArrayInitInst, EnumInitInst            |
+//! | Collections  | TupleInitInst, ArrayInitInst, ArrayFillInst,          |
+//! |              | EnumInitInst                                          |
 //! | Atomic       | AtomicLoadInst, AtomicStoreInst, AtomicRMWInst, etc.  |
 
 #include "codegen/mir_codegen.hpp"
@@ -208,6 +209,9 @@ void MirCodegen::emit_instruction(const mir::InstructionData& inst) {
             } else if constexpr (std::is_same_v<T, mir::ArrayInitInst>) {
                 emit_array_init_inst(i, result_reg);
 
+            } else if constexpr (std::is_same_v<T, mir::ArrayFillInst>) {
+                emit_array_fill_inst(i, result_reg);
+
             } else if constexpr (std::is_same_v<T, mir::AtomicLoadInst>) {
                 emit_atomic_load_inst(i, result_reg, inst);
 
@@ -9,6 +9,7 @@ TML_MODULE("codegen_x86")
 
 #include "codegen/mir_codegen.hpp"
 
+#include <algorithm>
 #include <iomanip>
 #include <sstream>
 #include <unordered_set>
@@ -180,6 +181,16 @@ void MirCodegen::emit_constant_inst(const mir::ConstantInst& i, const std::strin
                     // Store string content for compile-time length optimization
                     value_string_contents_[inst.result] = c.value;
                 }
+            } else if constexpr (std::is_same_v<C, mir::ConstWideString>) {
+                auto it = wide_string_constants_.find(c.value);
+                if (it != wide_string_constants_.end()) {
+                    emitln("    " + result_reg + " = bitcast ptr " + it->second + " to ptr");
+                } else {
+                    emitln("    " + result_reg + " = bitcast ptr null to ptr");
+                }
+                if (inst.result != mir::INVALID_VALUE) {
+                    value_types_[inst.result] = "ptr";
+                }
             } else if constexpr (std::is_same_v<C, mir::ConstUnit>) {
                 // Unit type - no value needed
             } else if constexpr (std::is_same_v<C, mir::ConstFuncRef>) {
@@ -475,6 +486,76 @@ void MirCodegen::emit_array_init_inst(const mir::ArrayInitInst& i, const std::st
     }
 }
 
+// ============================================================================
+// Array Fill Instruction
+// ============================================================================
+
+void MirCodegen::emit_array_fill_inst(const mir::ArrayFillInst& i, const std::string& result_reg) {
+    mir::MirTypePtr elem_ptr = i.element_type ? i.element_type : mir::make_i32_type();
+    mir::MirTypePtr array_ptr =
+        i.result_type ? i.result_type : mir::make_array_type(elem_ptr, i.count);
+    std::string array_type = mir_type_to_llvm(array_ptr);
+    std::string elem_type = mir_type_to_llvm(elem_ptr);
+    std::string value = get_value_reg(i.value);
+
+    // Narrow a wider integer value to the element type, as ArrayInit does
+    if (i.value.type && (elem_type == "i8" || elem_type == "i16")) {
+        std::string val_type = mir_type_to_llvm(i.value.type);
+        if (val_type != elem_type) {
+            std::string trunc_reg = "%trunc" + std::to_string(temp_counter_++);
+            emitln("    " + trunc_reg + " = trunc " + val_type + " " + value + " to " + elem_type);
+            value = trunc_reg;
+        }
+    }
+
+    bool is_zero = value == "0";
+    auto zero_it = value_int_constants_.find(i.value.id);
+    if (zero_it != value_int_constants_.end() && zero_it->second == 0) {
+        is_zero = true;
+    }
+
+    std::string alloc_reg = "%arr_alloc" + std::to_string(temp_counter_++);
+    if (is_zero || i.count == 0) {
+        emitln("    " + alloc_reg + " = alloca " + array_type + ", align 16");
+        emitln("    store " + array_type + " zeroinitializer, ptr " + alloc_reg + ", align 16");
+        emitln("    " + result_reg + " = load " + array_type + ", ptr " + alloc_reg +
+               ", align 16");
+        return;
+    }
+
+    // Short arrays: an insertvalue chain the optimizer folds easily
+    if (i.count <= 16) {
+        std::string current = "undef";
+        for (uint64_t j = 0; j < i.count; ++j) {
+            std::string next =
+                (j == i.count - 1) ? result_reg : "%tmp" + std::to_string(temp_counter_++);
+            emitln("    " + next + " = insertvalue " + array_type + " " + current + ", " +
+                   elem_type + " " + value + ", " + std::to_string(j));
+            current = next;
+        }
+        return;
+    }
+
+    // Long arrays: store the first element, then double the filled prefix
+    // with memcpy, so [x; n] costs O(log n) instructions
+    emitln("    " + alloc_reg + " = alloca " + array_type + ", align 16");
+    emitln("    store " + elem_type + " " + value + ", ptr " + alloc_reg);
+    for (uint64_t filled = 1; filled < i.count; filled *= 2) {
+        uint64_t n = std::min(filled, i.count - filled);
+        std::string dst = "%fill_dst" + std::to_string(temp_counter_++);
+        std::string size_ptr = "%fill_size_ptr" + std::to_string(temp_counter_++);
+        std::string size = "%fill_size" + std::to_string(temp_counter_++);
+        emitln("    " + dst + " = getelementptr inbounds " + array_type + ", ptr " + alloc_reg +
+               ", i64 0, i64 " + std::to_string(filled));
+        emitln("    " + size_ptr + " = getelementptr " + elem_type + ", ptr null, i64 " +
+               std::to_string(n));
+        emitln("    " + size + " = ptrtoint ptr " + size_ptr + " to i64");
+        emitln("    call void @llvm.memcpy.p0.p0.i64(ptr " + dst + ", ptr " + alloc_reg + ", i64 " +
+               size + ", i1 false)");
+    }
+    emitln("    " + result_reg + " = load " + array_type + ", ptr " + alloc_reg + ", align 16");
+}
+
 // ============================================================================
 // Atomic Instructions
 // ============================================================================
@@ -37,6 +37,7 @@ TML_MODULE("compiler")
 #include "codegen/mir_codegen.hpp"
 
 #include "codegen/target.hpp"
+#include "common/utf16.hpp"
 #include "version_generated.hpp"
 
 #include <sstream>
@@ -74,6 +75,7 @@ auto MirCodegen::generate(const mir::Module& module) -> std::string {
     block_labels_.clear();
     emitted_types_.clear();
     string_constants_.clear();
+    wide_string_constants_.clear();
     value_string_contents_.clear();
     used_enum_types_.clear();
 
@@ -95,6 +97,13 @@ auto MirCodegen::generate(const mir::Module& module) -> std::string {
                                 "@.str." + std::to_string(string_constants_.size());
                             string_constants_[str_const->value] = global_name;
                         }
+                    } else if (auto* wide_const =
+                                   std::get_if<mir::ConstWideString>(&const_inst->value)) {
+                        if (wide_string_constants_.count(wide_const->value) == 0) {
+                            std::string global_name =
+                                "@.wstr." + std::to_string(wide_string_constants_.size());
+                            wide_string_constants_[wide_const->value] = global_name;
+                        }
                     }
                 }
                 // Collect enum types from EnumInitInst (for imported enums)
@@ -136,6 +145,7 @@ auto MirCodegen::generate(const mir::Module& module) -> std::string {
     if (!string_constants_.empty()) {
         emitln();
     }
+    emit_wide_string_constants();
 
     emit_type_defs(module);
 
@@ -189,6 +199,7 @@ auto MirCodegen::generate_cgu(const mir::Module& module,
     block_labels_.clear();
     emitted_types_.clear();
     string_constants_.clear();
+    wide_string_constants_.clear();
     value_string_contents_.clear();
     used_enum_types_.clear();
 
@@ -214,6 +225,13 @@ auto MirCodegen::generate_cgu(const mir::Module& module,
                                 "@.str." + std::to_string(string_constants_.size());
                             string_constants_[str_const->value] = global_name;
                         }
+                    } else if (auto* wide_const =
+                                   std::get_if<mir::ConstWideString>(&const_inst->value)) {
+                        if (wide_string_constants_.count(wide_const->value) == 0) {
+                            std::string global_name =
+                                "@.wstr." + std::to_string(wide_string_constants_.size());
+                            wide_string_constants_[wide_const->value] = global_name;
+                        }
                     }
                 }
                 if (auto* enum_inst = std::get_if<mir::EnumInitInst>(&inst.inst)) {
@@ -253,6 +271,7 @@ auto MirCodegen::generate_cgu(const mir::Module& module,
     if (!string_constants_.empty()) {
         emitln();
     }
+    emit_wide_string_constants();
 
     emit_type_defs(module);
 
@@ -360,6 +379,24 @@ void MirCodegen::emit_main_wrapper(const mir::Module& module) {
     emitln();
 }
 
+void MirCodegen::emit_wide_string_constants() {
+    // UTF-16 in the target's byte order: LLVM lays out the i16 elements
+    for (const auto& [value, name] : wide_string_constants_) {
+        std::vector<uint16_t> units = utf8_to_utf16(value);
+        std::string elems;
+        for (uint16_t unit : units) {
+            elems += "i16 " + std::to_string(unit) + ", ";
+        }
+        elems += "i16 0";
+        size_t len = units.size() + 1; // +1 for the 16-bit terminator
+        emitln(name + " = private constant [" + std::to_string(len) + " x i16] [" + elems +
+               "], align 2");
+    }
+    if (!wide_string_constants_.empty()) {
+        emitln();
+    }
+}
+
 void MirCodegen::emit_preamble() {
     emit_comment("Generated by TML MIR Codegen");
 
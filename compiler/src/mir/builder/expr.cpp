@@ -276,6 +276,17 @@ auto MirBuilder::build_call(const parser::CallExpr& call) -> Value {
         return emit(std::move(inst), return_type);
     }
 
+    // wide_str("...") is UTF-16 data emitted at compile time, not a call
+    if (func_name == "wide_str" && call.args.size() == 1 &&
+        call.args[0]->is<parser::LiteralExpr>()) {
+        const auto& token = call.args[0]->as<parser::LiteralExpr>().token;
+        if (token.kind == lexer::TokenKind::StringLiteral) {
+            ConstantInst inst;
+            inst.value = ConstWideString{std::string(token.string_value().value)};
+            return emit(std::move(inst), make_pointer_type(make_u16_type()));
+        }
+    }
+
     // Build arguments
     std::vector<Value> args;
     for (const auto& arg : call.args) {
@@ -533,15 +544,9 @@ auto MirBuilder::build_array(const parser::ArrayExpr& arr) -> Value {
                 // Use hint element type if available; otherwise use the value's type.
                 MirTypePtr elem_type = hint_elem_type ? hint_elem_type : val.type;
 
-                // Create array with repeated values
-                std::vector<Value> elements;
-                elements.reserve(count);
-                for (size_t i = 0; i < count; ++i) {
-                    elements.push_back(val);
-                }
-
-                ArrayInitInst inst;
-                inst.elements = std::move(elements);
+                ArrayFillInst inst;
+                inst.value = val;
+                inst.count = count;
                 inst.element_type = elem_type;
                 inst.result_type = make_array_type(elem_type, count);
 
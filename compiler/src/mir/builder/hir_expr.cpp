@@ -401,6 +401,17 @@ auto HirMirBuilder::build_unary(const hir::HirUnaryExpr& unary) -> Value {
 // ============================================================================
 
 auto HirMirBuilder::build_call(const hir::HirCallExpr& call) -> Value {
+    // wide_str("...") is UTF-16 data emitted at compile time, not a call
+    if (call.func_name == "wide_str" && call.args.size() == 1) {
+        if (auto* lit = std::get_if<hir::HirLiteralExpr>(&call.args[0]->kind)) {
+            if (auto* text = std::get_if<std::string>(&lit->value)) {
+                ConstantInst inst;
+                inst.value = ConstWideString{*text};
+                return emit(inst, convert_type(call.type), call.span);
+            }
+        }
+    }
+
     // Build arguments
     std::vector<Value> args;
     std::vector<MirTypePtr> arg_types;
@@ -265,11 +265,10 @@ auto HirMirBuilder::build_array_repeat(const hir::HirArrayRepeatExpr& arr) -> Va
     }
     MirTypePtr result_type = make_array_type(element_type, arr.count);
 
-    // Build array by repeating element
-    std::vector<Value> elements(arr.count, element);
-
-    ArrayInitInst inst;
-    inst.elements = std::move(elements);
+    // One fill instruction instead of arr.count copies of the element
+    ArrayFillInst inst;
+    inst.value = element;
+    inst.count = arr.count;
     inst.element_type = element_type;
     inst.result_type = result_type;
 
@@ -800,6 +800,8 @@ auto is_value_used(const Function& func, ValueId value) -> bool {
                                 return true;
                         }
                         return false;
+                    } else if constexpr (std::is_same_v<T, ArrayFillInst>) {
+                        return i.value.id == value;
                     } else if constexpr (std::is_same_v<T, AwaitInst>) {
                         return i.poll_value.id == value;
                     } else if constexpr (std::is_same_v<T, ClosureInitInst>) {
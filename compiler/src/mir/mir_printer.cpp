@@ -247,6 +247,8 @@ auto MirPrinter::print_instruction(const InstructionData& inst) -> std::string {
                             out << "const unit";
                         } else if constexpr (std::is_same_v<C, ConstFuncRef>) {
                             out << "const func_ref @" << c.func_name;
+                        } else if constexpr (std::is_same_v<C, ConstWideString>) {
+                            out << "const wstr \"" << c.value << "\"";
                         }
                     },
                     i.value);
@@ -288,6 +290,8 @@ auto MirPrinter::print_instruction(const InstructionData& inst) -> std::string {
                     out << print_value(i.elements[j]);
                 }
                 out << "]";
+            } else if constexpr (std::is_same_v<T, ArrayFillInst>) {
+                out << "array [" << print_value(i.value) << "; " << i.count << "]";
             } else if constexpr (std::is_same_v<T, AwaitInst>) {
                 out << "await " << print_value(i.poll_value);
                 out << " (suspension " << i.suspension_id << ")";
@@ -180,6 +180,8 @@ auto ADCEPass::get_operands(const InstructionData& inst) -> std::vector<ValueId>
                 for (const auto& elem : i.elements) {
                     operands.push_back(elem.id);
                 }
+            } else if constexpr (std::is_same_v<T, ArrayFillInst>) {
+                operands.push_back(i.value.id);
             } else if constexpr (std::is_same_v<T, AwaitInst>) {
                 operands.push_back(i.poll_value.id);
             } else if constexpr (std::is_same_v<T, ClosureInitInst>) {
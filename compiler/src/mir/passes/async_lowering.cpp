@@ -232,6 +232,8 @@ void AsyncAnalysis::analyze_saved_locals() {
                         for (const auto& elem : inst.elements) {
                             record_use(elem.id, block.id);
                         }
+                    } else if constexpr (std::is_same_v<T, ArrayFillInst>) {
+                        record_use(inst.value.id, block.id);
                     } else if constexpr (std::is_same_v<T, AwaitInst>) {
                         record_use(inst.poll_value.id, block.id);
                     } else if constexpr (std::is_same_v<T, ClosureInitInst>) {
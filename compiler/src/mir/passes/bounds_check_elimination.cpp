@@ -64,6 +64,9 @@ void BoundsCheckEliminationPass::collect_constants(const Function& func) {
             else if (auto* arr_init = std::get_if<ArrayInitInst>(&inst.inst)) {
                 array_sizes_[inst.result] = static_cast<int64_t>(arr_init->elements.size());
             }
+            else if (auto* arr_fill = std::get_if<ArrayFillInst>(&inst.inst)) {
+                array_sizes_[inst.result] = static_cast<int64_t>(arr_fill->count);
+            }
             // Handle alloca for arrays
             else if (auto* alloca_inst = std::get_if<AllocaInst>(&inst.inst)) {
                 if (auto* arr_type = std::get_if<MirArrayType>(&alloca_inst->alloc_type->kind)) {
@@ -243,6 +243,9 @@ auto CommonSubexpressionEliminationPass::replace_uses(Function& func, ValueId ol
                             if (elem.id == old_value)
                                 elem.id = new_value;
                         }
+                    } else if constexpr (std::is_same_v<T, ArrayFillInst>) {
+                        if (i.value.id == old_value)
+                            i.value.id = new_value;
                     } else if constexpr (std::is_same_v<T, AwaitInst>) {
                         if (i.poll_value.id == old_value)
                             i.poll_value.id = new_value;
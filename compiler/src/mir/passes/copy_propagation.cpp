@@ -237,6 +237,8 @@ auto CopyPropagationPass::propagate_copies(
                         for (auto& elem : i.elements) {
                             replace(elem);
                         }
+                    } else if constexpr (std::is_same_v<T, ArrayFillInst>) {
+                        replace(i.value);
                     } else if constexpr (std::is_same_v<T, AwaitInst>) {
                         replace(i.poll_value);
                     } else if constexpr (std::is_same_v<T, ClosureInitInst>) {
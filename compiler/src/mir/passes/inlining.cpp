@@ -1024,6 +1024,10 @@ auto InliningPass::clone_function_body(const Function& callee, ValueId first_new
                             if (it != value_map.end())
                                 elem.id = it->second;
                         }
+                    } else if constexpr (std::is_same_v<T, ArrayFillInst>) {
+                        auto it = value_map.find(i.value.id);
+                        if (it != value_map.end())
+                            i.value.id = it->second;
                     } else if constexpr (std::is_same_v<T, AwaitInst>) {
                         auto it = value_map.find(i.poll_value.id);
                         if (it != value_map.end())
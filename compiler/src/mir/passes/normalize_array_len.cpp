@@ -36,6 +36,8 @@ auto NormalizeArrayLenPass::run_on_function(Function& func) -> bool {
                         array_sizes[inst.result] = static_cast<int64_t>(arr_type->size);
                     }
                 }
+            } else if (auto* arr_fill = std::get_if<ArrayFillInst>(&inst.inst)) {
+                array_sizes[inst.result] = static_cast<int64_t>(arr_fill->count);
             }
         }
     }
@@ -693,6 +693,8 @@ auto SimplifyCfgPass::cleanup_phi_nodes(Function& func) -> bool {
                             for (auto& elem : i.elements) {
                                 replace_value(elem);
                             }
+                        } else if constexpr (std::is_same_v<T, ArrayFillInst>) {
+                            replace_value(i.value);
                         }
                     },
                     inst.inst);
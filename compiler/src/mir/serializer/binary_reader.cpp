@@ -92,8 +92,8 @@ auto MirBinaryReader::verify_header() -> bool {
     }
 
     uint16_t major = read_u16();
-    uint16_t minor = read_u16();
-    (void)minor; // Minor version differences are OK
+    // Minor version differences are OK; fields added since are defaulted
+    minor_ = read_u16();
 
     if (major != MIR_VERSION_MAJOR) {
         set_error("Unsupported MIR version");
@@ -262,6 +262,9 @@ auto MirBinaryReader::read_instruction() -> InstructionData {
     case InstTag::MethodCall: {
         MethodCallInst inst;
         inst.receiver = read_value();
+        if (minor_ >= 1) {
+            inst.receiver_type = read_string();
+        }
         inst.method_name = read_string();
         uint32_t count = read_u32();
         for (uint32_t i = 0; i < count; ++i) {
@@ -324,6 +327,12 @@ auto MirBinaryReader::read_instruction() -> InstructionData {
         case ConstTag::Unit:
             inst.value = ConstUnit{};
             break;
+        case ConstTag::WideString: {
+            ConstWideString c;
+            c.value = read_string();
+            inst.value = c;
+            break;
+        }
         }
         data.inst = inst;
         break;
@@ -376,6 +385,15 @@ auto MirBinaryReader::read_instruction() -> InstructionData {
         data.inst = inst;
         break;
     }
+    case InstTag::ArrayFill: {
+        ArrayFillInst inst;
+        inst.element_type = read_type();
+        inst.value = read_value();
+        inst.count = read_u64();
+        inst.result_type = make_array_type(inst.element_type, inst.count);
+        data.inst = inst;
+        break;
+    }
     case InstTag::Await: {
         AwaitInst inst;
         inst.poll_value = read_value();
@@ -406,6 +424,11 @@ auto MirBinaryReader::read_instruction() -> InstructionData {
     }
     }
 
+    if (minor_ >= 2) {
+        data.span.start.line = read_u32();
+        data.span.start.column = read_u32();
+        data.span.end = data.span.start;
+    }
     return data;
 }
 
@@ -604,6 +627,12 @@ auto MirBinaryReader::read_module() -> Module {
         case ConstTag::Unit:
             value = ConstUnit{};
             break;
+        case ConstTag::WideString: {
+            ConstWideString c;
+            c.value = read_string();
+            value = c;
+            break;
+        }
         }
 
         module.constants[name] = value;
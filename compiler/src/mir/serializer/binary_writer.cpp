@@ -200,6 +200,7 @@ void MirBinaryWriter::write_instruction(const InstructionData& inst) {
             } else if constexpr (std::is_same_v<T, MethodCallInst>) {
                 write_u8(static_cast<uint8_t>(InstTag::MethodCall));
                 write_value(i.receiver);
+                write_string(i.receiver_type);
                 write_string(i.method_name);
                 write_u32(static_cast<uint32_t>(i.args.size()));
                 for (const auto& arg : i.args) {
@@ -240,6 +241,9 @@ void MirBinaryWriter::write_instruction(const InstructionData& inst) {
                             write_string(c.value);
                         } else if constexpr (std::is_same_v<C, ConstUnit>) {
                             write_u8(static_cast<uint8_t>(ConstTag::Unit));
+                        } else if constexpr (std::is_same_v<C, ConstWideString>) {
+                            write_u8(static_cast<uint8_t>(ConstTag::WideString));
+                            write_string(c.value);
                         }
                     },
                     i.value);
@@ -276,6 +280,11 @@ void MirBinaryWriter::write_instruction(const InstructionData& inst) {
                 for (const auto& elem : i.elements) {
                     write_value(elem);
                 }
+            } else if constexpr (std::is_same_v<T, ArrayFillInst>) {
+                write_u8(static_cast<uint8_t>(InstTag::ArrayFill));
+                write_type(i.element_type);
+                write_value(i.value);
+                write_u64(i.count);
             } else if constexpr (std::is_same_v<T, AwaitInst>) {
                 write_u8(static_cast<uint8_t>(InstTag::Await));
                 write_value(i.poll_value);
@@ -299,6 +308,10 @@ void MirBinaryWriter::write_instruction(const InstructionData& inst) {
             }
         },
         inst.inst);
+
+    // Source position (minor 2); the file is the module's, passed to backends separately
+    write_u32(inst.span.start.line);
+    write_u32(inst.span.start.column);
 }
 
 void MirBinaryWriter::write_terminator(const Terminator& term) {
@@ -464,6 +477,9 @@ void MirBinaryWriter::write_module(const Module& module) {
                     write_string(c.value);
                 } else if constexpr (std::is_same_v<C, ConstUnit>) {
                     write_u8(static_cast<uint8_t>(ConstTag::Unit));
+                } else if constexpr (std::is_same_v<C, ConstWideString>) {
+                    write_u8(static_cast<uint8_t>(ConstTag::WideString));
+                    write_string(c.value);
                 }
             },
             value);
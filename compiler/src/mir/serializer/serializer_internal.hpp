@@ -48,6 +48,7 @@ enum class InstTag : uint8_t {
     ArrayInit = 17,
     Await = 18,
     ClosureInit = 19,
+    ArrayFill = 20,
 };
 
 enum class TermTag : uint8_t {
@@ -64,6 +65,7 @@ enum class ConstTag : uint8_t {
     Bool = 2,
     String = 3,
     Unit = 4,
+    WideString = 5,
 };
 
 } // namespace tml::mir
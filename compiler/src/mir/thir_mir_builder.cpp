@@ -617,6 +617,17 @@ auto ThirMirBuilder::build_unary(const thir::ThirUnaryExpr& unary) -> Value {
 }
 
 auto ThirMirBuilder::build_call(const thir::ThirCallExpr& call) -> Value {
+    // wide_str("...") is UTF-16 data emitted at compile time, not a call
+    if (call.func_name == "wide_str" && call.args.size() == 1) {
+        if (auto* lit = std::get_if<thir::ThirLiteralExpr>(&call.args[0]->kind)) {
+            if (auto* text = std::get_if<std::string>(&lit->value)) {
+                ConstantInst inst;
+                inst.value = ConstWideString{*text};
+                return emit(std::move(inst), convert_type(call.type), call.span);
+            }
+        }
+    }
+
     std::vector<Value> args;
     std::vector<MirTypePtr> arg_types;
     for (const auto& arg : call.args) {
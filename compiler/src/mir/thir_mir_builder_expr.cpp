@@ -102,11 +102,9 @@ auto ThirMirBuilder::build_array_repeat(const thir::ThirArrayRepeatExpr& arr) ->
     MirTypePtr element_type = val.type;
     MirTypePtr result_type = make_array_type(element_type, arr.count);
 
-    // Replicate the value into an array
-    std::vector<Value> elements(arr.count, val);
-
-    ArrayInitInst inst;
-    inst.elements = std::move(elements);
+    ArrayFillInst inst;
+    inst.value = val;
+    inst.count = arr.count;
     inst.element_type = element_type;
     inst.result_type = result_type;
     return emit(std::move(inst), result_type, arr.span);
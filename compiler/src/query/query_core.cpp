@@ -626,6 +626,7 @@ std::any provide_codegen_unit(QueryContext& ctx, const QueryKey& key) {
         // can be linked into a standalone executable by `tml build`.
         // tml run also benefits (it links an EXE too, not a DLL).
         codegen_opts.generate_exe_main = true;
+        codegen_opts.source_file = ck.file_path;
 #ifdef _WIN32
         codegen_opts.target_triple = "x86_64-pc-windows-msvc";
 #else
@@ -639,6 +640,11 @@ std::any provide_codegen_unit(QueryContext& ctx, const QueryKey& key) {
         auto backend_type = codegen::default_backend_type();
         if (ctx.options().backend == "cranelift") {
             backend_type = codegen::BackendType::Cranelift;
+            if (ctx.options().target_triple.empty()) {
+                // Let the bridge build for cranelift_native_triple(): the platform guess
+                // above can name another environment (gnu vs msvc on Windows)
+                codegen_opts.target_triple.clear();
+            }
         }
         auto backend = codegen::create_backend(backend_type);
 
@@ -23,6 +23,15 @@ TML_MODULE("compiler")
 //! | `mem_cmp`   | `(*Unit, *Unit, I64) -> I32`    | Compare memory       |
 //!
 //! These are `lowlevel` functions used by the allocator and collections.
+//!
+//! ## FFI Strings
+//!
+//! | Function    | Signature                       | Description          |
+//! |-------------|---------------------------------|----------------------|
+//! | `wide_str`  | `(Str) -> *U16`                 | UTF-16 literal       |
+//!
+//! `wide_str` takes a string literal and yields a static, NUL-terminated
+//! UTF-16 copy of it for Windows `W` APIs (`LPCWSTR` parameters).
 
 #include "types/env.hpp"
 
@@ -189,6 +198,16 @@ void TypeEnv::init_builtin_mem() {
                 {},
                 false,
                 builtin_span});
+
+    // ============ FFI Strings ============
+
+    // wide_str(s: Str) -> *U16 - UTF-16 copy of a string literal (checked in check_call)
+    functions_["wide_str"].push_back(FuncSig{"wide_str",
+                                             {make_primitive(PrimitiveKind::Str)},
+                                             make_ptr(make_primitive(PrimitiveKind::U16)),
+                                             {},
+                                             false,
+                                             builtin_span});
 }
 
 } // namespace tml::types
@@ -137,6 +137,19 @@ auto TypeChecker::check_call(const parser::CallExpr& call) -> TypePtr {
             }
             return make_unit();
         }
+        // wide_str's UTF-16 data is emitted at compile time, so only literals work
+        if (name == "wide_str") {
+            bool is_literal = call.args.size() == 1 && call.args[0]->is<parser::LiteralExpr>() &&
+                              call.args[0]->as<parser::LiteralExpr>().token.kind ==
+                                  lexer::TokenKind::StringLiteral;
+            if (!is_literal) {
+                error("wide_str expects a single string literal", call.callee->span, "T001");
+            }
+            for (const auto& arg : call.args) {
+                check_expr(*arg, make_primitive(PrimitiveKind::Str));
+            }
+            return make_ptr(make_primitive(PrimitiveKind::U16));
+        }
     }
 
     // Check for compiler intrinsics called with generics (e.g., type_id[I32](), size_of[T]())
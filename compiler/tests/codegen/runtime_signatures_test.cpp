@@ -0,0 +1,117 @@
+// TML Compiler - Runtime Signature Conformance Tests
+// Checks every TML_RT_FN and TML_RT_SHIM entry of
+// runtime/core/runtime_signatures.def against its prototypes in essential.h.
+// The Cranelift bridge declares runtime imports from the same manifest, so a
+// prototype change that isn't mirrored there fails this file at compile time
+// instead of miscompiling calls.
+
+#include "../runtime/core/essential.h"
+
+#include <array>
+#include <cstddef>
+#include <gtest/gtest.h>
+#include <type_traits>
+
+namespace {
+
+enum class Abi { Void, I8, I32, I64, F32, F64, Ptr, Unknown };
+
+template <typename T> constexpr Abi abi_of() {
+    if constexpr (std::is_void_v<T>) {
+        return Abi::Void;
+    } else if constexpr (std::is_pointer_v<T>) {
+        return Abi::Ptr;
+    } else if constexpr (std::is_same_v<T, float>) {
+        return Abi::F32;
+    } else if constexpr (std::is_same_v<T, double>) {
+        return Abi::F64;
+    } else if constexpr (std::is_integral_v<T> && sizeof(T) == 1) {
+        return Abi::I8;
+    } else if constexpr (std::is_integral_v<T> && sizeof(T) == 4) {
+        return Abi::I32;
+    } else if constexpr (std::is_integral_v<T> && sizeof(T) == 8) {
+        return Abi::I64;
+    } else {
+        return Abi::Unknown;
+    }
+}
+
+template <typename F> struct Signature;
+
+template <typename R, typename... Args> struct Signature<R (*)(Args...)> {
+    static constexpr Abi result = abi_of<R>();
+    static constexpr std::array<Abi, sizeof...(Args)> args{abi_of<Args>()...};
+};
+
+// Manifest parameter tokens
+namespace manifest {
+constexpr Abi i8 = Abi::I8;
+constexpr Abi i32 = Abi::I32;
+constexpr Abi i64 = Abi::I64;
+constexpr Abi f32 = Abi::F32;
+constexpr Abi f64 = Abi::F64;
+constexpr Abi ptr = Abi::Ptr;
+
+template <typename... A> constexpr std::array<Abi, sizeof...(A)> list(A... a) {
+    return {a...};
+}
+} // namespace manifest
+
+// Manifest return tokens (`void` is a keyword, so returns are pasted)
+#define TML_RT_RET_void Abi::Void
+#define TML_RT_RET_i8 Abi::I8
+#define TML_RT_RET_i32 Abi::I32
+#define TML_RT_RET_i64 Abi::I64
+#define TML_RT_RET_f32 Abi::F32
+#define TML_RT_RET_f64 Abi::F64
+#define TML_RT_RET_ptr Abi::Ptr
+
+template <std::size_t N, std::size_t M>
+constexpr bool same_args(const std::array<Abi, N>& a, const std::array<Abi, M>& b) {
+    if constexpr (N != M) {
+        return false;
+    } else {
+        for (std::size_t i = 0; i < N; ++i) {
+            if (a[i] != b[i]) {
+                return false;
+            }
+        }
+        return true;
+    }
+}
+
+#define TML_RT_FN(name, ret, params)                                                               \
+    static_assert(Signature<decltype(&::name)>::result == TML_RT_RET_##ret,                       \
+                  "return type of " #name " differs from runtime_signatures.def");                 \
+    static_assert(same_args(Signature<decltype(&::name)>::args, manifest::list params),           \
+                  "parameters of " #name " differ from runtime_signatures.def");
+// Shims are checked under both names
+#define TML_RT_SHIM(name, ret, params)                                                             \
+    TML_RT_FN(name, ret, params)                                                                   \
+    TML_RT_FN(tml_rt_##name, ret, params)
+#define TML_RT_LEGACY(name, ret, params)
+namespace manifest { // parameter tokens resolve here
+#include "../runtime/core/runtime_signatures.def"
+}
+#undef TML_RT_FN
+#undef TML_RT_SHIM
+#undef TML_RT_LEGACY
+
+// Runtime-visible count, so the test shows up in the suite
+#define TML_RT_FN(name, ret, params) +1
+#define TML_RT_SHIM(name, ret, params) +2
+#define TML_RT_LEGACY(name, ret, params)
+constexpr int kCheckedFunctions = 0
+#include "../runtime/core/runtime_signatures.def"
+    ;
+#undef TML_RT_FN
+#undef TML_RT_SHIM
+#undef TML_RT_LEGACY
+
+} // namespace
+
+TEST(RuntimeSignaturesTest, ManifestMatchesEssentialHeader) {
+    // The checks themselves are static_asserts above; reaching this point
+    // means every TML_RT_FN and TML_RT_SHIM entry matched its prototypes.
+    EXPECT_GT(kCheckedFunctions, 0);
+}
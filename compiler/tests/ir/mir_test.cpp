@@ -507,6 +507,59 @@ TEST_F(MirTest, SerializeConvenienceFunctions) {
     EXPECT_EQ(restored.functions.size(), 1u);
 }
 
+TEST_F(MirTest, SerializeRoundTripArrayFill) {
+    auto mir = build_mir(R"(
+        func main() {
+            let a: [I32; 100000] = [0; 100000]
+        }
+    )");
+
+    std::vector<uint8_t> binary = tml::mir::serialize_binary(mir);
+    auto restored = tml::mir::deserialize_binary(binary);
+
+    // The repeat is one instruction, not one operand per element
+    ASSERT_EQ(restored.functions.size(), 1u);
+    size_t fills = 0;
+    for (const auto& block : restored.functions[0].blocks) {
+        for (const auto& inst : block.instructions) {
+            if (auto* fill = std::get_if<tml::mir::ArrayFillInst>(&inst.inst)) {
+                ++fills;
+                EXPECT_EQ(fill->count, 100000u);
+                EXPECT_TRUE(fill->element_type->is_integer());
+            }
+            EXPECT_FALSE(std::holds_alternative<tml::mir::ArrayInitInst>(inst.inst));
+        }
+    }
+    EXPECT_EQ(fills, 1u);
+    EXPECT_LT(binary.size(), 1024u);
+}
+
+TEST_F(MirTest, SerializeRoundTripWideString) {
+    auto mir = build_mir(R"(
+        func main() {
+            let title: *U16 = wide_str("h\u{e9}")
+        }
+    )");
+
+    std::vector<uint8_t> binary = tml::mir::serialize_binary(mir);
+    auto restored = tml::mir::deserialize_binary(binary);
+
+    ASSERT_EQ(restored.functions.size(), 1u);
+    bool found_wide = false;
+    for (const auto& block : restored.functions[0].blocks) {
+        for (const auto& inst : block.instructions) {
+            if (auto* ci = std::get_if<tml::mir::ConstantInst>(&inst.inst)) {
+                if (auto* wide = std::get_if<tml::mir::ConstWideString>(&ci->value)) {
+                    found_wide = true;
+                    EXPECT_EQ(wide->value, "h\xC3\xA9");
+                }
+            }
+            EXPECT_FALSE(std::holds_alternative<tml::mir::CallInst>(inst.inst));
+        }
+    }
+    EXPECT_TRUE(found_wide) << "wide_str() should lower to a wide string constant";
+}
+
 // ============================================================================
 // MIR Codegen Tests
 // ============================================================================
@@ -551,6 +604,34 @@ TEST_F(MirTest, MirCodegenWithReturn) {
     EXPECT_NE(llvm_ir.find("ret"), std::string::npos);
 }
 
+TEST_F(MirTest, MirCodegenArrayFill) {
+    auto mir = build_mir(R"(
+        func main() {
+            let a: [I32; 100000] = [7; 100000]
+        }
+    )");
+
+    tml::codegen::MirCodegen codegen;
+    std::string llvm_ir = codegen.generate(mir);
+
+    // Long fills double with memcpy instead of 100000 insertvalues
+    EXPECT_NE(llvm_ir.find("llvm.memcpy"), std::string::npos);
+    EXPECT_EQ(llvm_ir.find("insertvalue"), std::string::npos);
+}
+
+TEST_F(MirTest, MirCodegenWideString) {
+    auto mir = build_mir(R"(
+        func main() {
+            let title: *U16 = wide_str("hi")
+        }
+    )");
+
+    tml::codegen::MirCodegen codegen;
+    std::string llvm_ir = codegen.generate(mir);
+
+    EXPECT_NE(llvm_ir.find("[3 x i16] [i16 104, i16 105, i16 0], align 2"), std::string::npos);
+}
+
 // ============================================================================
 // Optimization Pass Tests
 // ============================================================================
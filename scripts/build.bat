@@ -178,7 +178,7 @@ if "%ENABLE_CRANELIFT_BACKEND%"=="ON" (
     if not exist "!CRANELIFT_LIB!" (
         echo Building Cranelift bridge...
         cd /d "%ROOT_DIR%\compiler\cranelift"
-        cargo build --release
+        cargo build --release --features memory-limit
         if errorlevel 1 (
             echo Cranelift bridge build failed!
             exit /b 1